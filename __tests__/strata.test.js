@@ -13,7 +13,16 @@ const {
   ConflictError,
   StateError,
   ConstraintError,
+  AccessDeniedError,
+  ResourceExhaustedError,
+  TimeoutError,
+  StrataClosedError,
+  FilterOp,
 } = require('../stratadb');
+const fs = require('fs');
+const os = require('os');
+const path = require('path');
+const { PassThrough } = require('stream');
 
 describe('Strata', () => {
   let db;
@@ -39,10 +48,35 @@ describe('Strata', () => {
       expect(result.count).toBe(42);
     });
 
+    test('set and get nested values', async () => {
+      await db.kv.set('nested', {
+        list: [1, 2.5, 'three', undefined, { deep: [true, null] }],
+        skipped: undefined,
+        fn: () => 1,
+        nan: NaN,
+      });
+      expect(await db.kv.get('nested')).toEqual({
+        list: [1, 2.5, 'three', null, { deep: [true, null] }],
+        nan: null,
+      });
+    });
+
     test('get missing returns null', async () => {
       expect(await db.kv.get('nonexistent')).toBeNull();
     });
 
+    test('putRaw and getRaw', async () => {
+      // MessagePack for { a: 1, b: [true, 'x'] }
+      const packed = Buffer.from([0x82, 0xa1, 0x61, 0x01, 0xa1, 0x62, 0x92, 0xc3, 0xa1, 0x78]);
+      await db.kv.putRaw('raw', packed);
+      expect(await db.kv.get('raw')).toEqual({ a: 1, b: [true, 'x'] });
+
+      await db.kv.set('raw2', 'hello');
+      expect(await db.kv.getRaw('raw2')).toEqual(Buffer.from([0xa5, ...Buffer.from('hello')]));
+      expect(await db.kv.getRaw('nonexistent')).toBeNull();
+      await expect(db.kv.putRaw('bad', Buffer.from([0xc1]))).rejects.toThrow(ValidationError);
+    });
+
     test('delete', async () => {
       await db.kv.set('to_delete', 'value');
       expect(await db.kv.delete('to_delete')).toBe(true);
@@ -89,6 +123,75 @@ describe('Strata', () => {
       expect(history[0]).toHaveProperty('timestamp');
     });
 
+    test('history pages', async () => {
+      for (const v of ['v1', 'v2', 'v3']) {
+        await db.kv.set('pkey', v);
+      }
+      const first = await db.kv.history('pkey', { limit: 2 });
+      expect(first.versions.map((v) => v.value)).toEqual(['v3', 'v2']);
+      const rest = await db.kv.history('pkey', { limit: 2, cursor: first.cursor });
+      expect(rest.versions.map((v) => v.value)).toEqual(['v1']);
+      expect(rest.cursor).toBeNull();
+      const asc = await db.kv.history('pkey', { order: 'asc', limit: 1 });
+      expect(asc.versions[0].value).toBe('v1');
+    });
+
+    test('timeline', async () => {
+      await db.kv.set('tkey', { name: 'a', tags: ['x'] });
+      await db.kv.set('tkey', { name: 'b', tags: ['x'], done: true });
+      const timeline = await db.kv.timeline('tkey');
+      expect(timeline).toHaveLength(2);
+      expect(timeline[0].diff).toEqual([
+        { op: 'add', path: '', value: { name: 'a', tags: ['x'] } },
+      ]);
+      expect(timeline[1].diff).toEqual([
+        { op: 'replace', path: '/name', value: 'b' },
+        { op: 'add', path: '/done', value: true },
+      ]);
+    });
+
+    test('annotations are returned by history, timeline, and diffs', async () => {
+      const note = { author: 'ada', message: 'initial price', requestId: 'req-1' };
+      await db.kv.set('price', 10, note);
+      await db.kv.set('price', 12);
+      const history = await db.kv.history('price');
+      expect(history.find((v) => v.value === 10).annotation).toEqual(note);
+      expect(history.find((v) => v.value === 12).annotation).toBeUndefined();
+      const [first] = await db.kv.timeline('price');
+      expect(first.annotation).toEqual(note);
+
+      await db.branch.fork('annotated_b');
+      await db.branch.switch('annotated_b');
+      await db.kv.set('price', 15, { author: 'bob', message: 'raise' });
+      await db.branch.switch('default');
+      const diff = await db.branch.diff('default', 'annotated_b', { detailed: true });
+      const entry = diff.spaces[0].modified.find((e) => e.key === 'price');
+      expect(entry.annotationA).toBeUndefined();
+      expect(entry.annotationB).toEqual({ author: 'bob', message: 'raise' });
+      expect(await db.currentBranch()).toBe('default');
+    });
+
+    test('annotation keys are hidden from listings and reserved', async () => {
+      const version = await db.kvPut('price', 10, undefined, { author: 'ada' });
+      const [latest] = await db.kv.history('price');
+      expect(latest.version).toBe(version);
+      expect(latest.annotation).toEqual({ author: 'ada' });
+      expect(await db.kvList()).toEqual(['price']);
+      const page = await db.kvListPaginated(undefined, 1);
+      expect(page.keys).toEqual(['price']);
+      expect(page.hasMore).toBe(false);
+      await expect(db.kvPut('_annotation/price', { author: 'mallory' })).rejects.toThrow(
+        /reserved for write annotations/,
+      );
+      await expect(db.kvDelete('_annotation/price')).rejects.toThrow(/reserved/);
+      await expect(
+        db.kvBatchPut([{ key: '_annotation/price', value: {} }]),
+      ).rejects.toThrow(/reserved/);
+      await expect(
+        db.execute('KvPut', { key: '_annotation/price', value: {} }),
+      ).rejects.toThrow(/reserved/);
+    });
+
     test('getVersioned', async () => {
       await db.kv.set('vk', 'val');
       const vv = await db.kv.getVersioned('vk');
@@ -114,6 +217,19 @@ describe('Strata', () => {
       expect(await db.kv.get('tt', { asOf: ts })).toBe('v1');
     });
 
+    test('get with a tag as asOf', async () => {
+      const sleep = (ms) => new Promise((r) => setTimeout(r, ms));
+      await db.kv.set('tagged', 'v1');
+      await sleep(50);
+      await db.tagCreate('release-1');
+      await sleep(50);
+      await db.kv.set('tagged', 'v2');
+
+      expect(await db.kv.get('tagged', { asOf: 'release-1' })).toBe('v1');
+      expect(await db.at('release-1').kv.get('tagged')).toBe('v1');
+      await expect(db.kv.get('tagged', { asOf: 'missing' })).rejects.toThrow(NotFoundError);
+    });
+
     test('getVersioned timestamp roundtrip with asOf', async () => {
       await db.kv.set('kv_rt', 'v1');
       const vv = await db.kv.getVersioned('kv_rt');
@@ -189,6 +305,246 @@ describe('Strata', () => {
     });
   });
 
+  // =========================================================================
+  // Counters
+  // =========================================================================
+
+  describe('Counters', () => {
+    test('concurrent increments, reset, and history', async () => {
+      expect(await db.counterGet('hits')).toBe(0);
+      const totals = await Promise.all(Array.from({ length: 20 }, () => db.counterIncr('hits')));
+      expect(Math.max(...totals)).toBe(20);
+      expect(await db.counterIncr('hits', -5)).toBe(15);
+      const before = await db.counterHistory('hits');
+      expect(before[0].value).toBe(15);
+
+      expect(await db.counterReset('hits')).toBe(15);
+      expect(await db.counterGet('hits')).toBe(0);
+      expect(await db.counterGet('hits', before[0].timestamp)).toBe(15);
+
+      await db.state.set('_counter/bad', 'x');
+      await expect(db.counterIncr('bad')).rejects.toThrow(StateError);
+    });
+
+    test('increments through several handles on one database', async () => {
+      const dir = fs.mkdtempSync(path.join(os.tmpdir(), 'strata-counter-'));
+      try {
+        const a = Strata.open(dir);
+        const b = Strata.open(dir);
+        await Promise.all(Array.from({ length: 20 }, (_, i) => (i % 2 ? a : b).counterIncr('hits')));
+        expect(await a.counterGet('hits')).toBe(20);
+        expect((await b.counterHistory('hits'))[0].value).toBe(20);
+        await a.close();
+        await b.close();
+      } finally {
+        fs.rmSync(dir, { recursive: true, force: true });
+      }
+    });
+  });
+
+  describe('Queues', () => {
+    test('pop, ack, and redelivery after the visibility timeout', async () => {
+      await db.queuePush('jobs', { n: 1 });
+      await db.queuePush('jobs', { n: 2 });
+      expect(await db.queueStats('jobs')).toEqual({ ready: 2, inFlight: 0, pushed: 2 });
+
+      const first = await db.queuePop('jobs', { visibilityTimeout: 20 });
+      expect(first.item).toEqual({ n: 1 });
+      expect(first.attempts).toBe(1);
+      const second = await db.queuePop('jobs');
+      expect(second.item).toEqual({ n: 2 });
+      expect(await db.queuePop('jobs')).toBeNull();
+      await db.queueAck(second.id);
+      await expect(db.queueAck(second.id)).rejects.toThrow(NotFoundError);
+
+      await new Promise((resolve) => setTimeout(resolve, 30));
+      const again = await db.queuePop('jobs');
+      expect(again.item).toEqual({ n: 1 });
+      expect(again.attempts).toBe(2);
+      await expect(db.queueAck(first.id)).rejects.toThrow(ConflictError);
+      await db.queueAck(again.id);
+      expect(await db.queueStats('jobs')).toEqual({ ready: 0, inFlight: 0, pushed: 2 });
+    });
+
+    test('skips acknowledged messages and keeps its cells from state writes', async () => {
+      for (let n = 1; n <= 3; n++) {
+        await db.queuePush('jobs', { n });
+      }
+      await db.queueAck((await db.queuePop('jobs')).id);
+      await db.queueAck((await db.queuePop('jobs')).id);
+      expect(await db.queuePop('jobs')).toEqual(expect.objectContaining({ item: { n: 3 } }));
+      expect(await db.state.get('_queue_head/jobs')).toBe(3);
+      await expect(db.state.set('_queue/jobs', 0)).rejects.toThrow(ValidationError);
+      await expect(db.state.delete('_queue_head/jobs')).rejects.toThrow(ValidationError);
+      await expect(db.execute('state_set', { cell: '_queue/jobs', value: 0 }))
+        .rejects.toThrow(ValidationError);
+    });
+
+    test('refuses queue names with a slash and writes to reserved cells', async () => {
+      await db.queuePush('jobs', 1);
+      await expect(db.queuePush('jobs/00000000000000000001', 2)).rejects.toThrow(ValidationError);
+      await expect(db.queuePop('a/b')).rejects.toThrow(ValidationError);
+      for (const cell of ['_counter/hits', '_ts/temp', '_collection/users', '_trace/run']) {
+        await expect(db.state.set(cell, 0)).rejects.toThrow(/reserved for/);
+      }
+      await expect(db.kv.set('_ts/temp/00000000000000000001', 0)).rejects.toThrow(/reserved for/);
+      await expect(
+        db.execute('state_batch_set', { entries: [{ cell: '_counter/hits', value: 0 }] }),
+      ).rejects.toThrow(ValidationError);
+    });
+  });
+
+  describe('Locks', () => {
+    test('leases exclude other holders until released or expired', async () => {
+      const token = await db.lockAcquire('cron', { ttlMs: 50 });
+      expect(typeof token).toBe('string');
+      expect(await db.lockAcquire('cron')).toBeNull();
+      expect(await db.lockRenew('cron', token, 50)).toBe(true);
+
+      const waited = await db.lockAcquire('cron', { waitMs: 500 });
+      expect(waited).not.toBeNull();
+      expect(await db.lockRenew('cron', token)).toBe(false);
+      expect(await db.lockRelease('cron', token)).toBe(false);
+      expect(await db.lockRelease('cron', waited)).toBe(true);
+      expect(await db.lockAcquire('cron')).not.toBeNull();
+    });
+
+    test('locks span branches and spaces', async () => {
+      const token = await db.lockAcquire('cron');
+      expect(token).toMatch(/^[0-9a-f]{32}$/);
+      expect(await db.lockRenew('cron', token)).toBe(true);
+      await db.branch.create('feature');
+      await db.branch.switch('feature');
+      await db.space.switch('other');
+      expect(await db.lockAcquire('cron')).toBeNull();
+      expect(await db.lockRelease('cron', token)).toBe(true);
+      await expect(db.stateSet('_lock/cron', null)).rejects.toThrow(/reserved for locks/);
+    });
+
+    test('leases are stored in the database', async () => {
+      const dir = fs.mkdtempSync(path.join(os.tmpdir(), 'strata-locks-'));
+      try {
+        const first = Strata.open(dir);
+        const token = await first.lockAcquire('cron', { ttlMs: 60_000 });
+        expect(token).not.toBeNull();
+        await first.close();
+
+        const second = Strata.open(dir);
+        expect(await second.lockAcquire('cron')).toBeNull();
+        expect(await second.lockRelease('cron', token)).toBe(true);
+        await second.close();
+        await expect(second.lockAcquire('cron')).rejects.toThrow(StrataClosedError);
+      } finally {
+        fs.rmSync(dir, { recursive: true, force: true });
+      }
+    });
+  });
+
+  describe('Time series', () => {
+    test('queries ranges and aggregates buckets', async () => {
+      await db.tsAppend('temp', 1_000_000, 20, { room: 'a' });
+      await db.tsAppend('temp', 1_500_000, 22, { room: 'b' });
+      await db.tsAppend('temp', 2_000_000, 30, { room: 'a' });
+      await db.tsAppend('temp', 2_000_000, 24, { room: 'a' });
+
+      const points = await db.tsQuery('temp', { from: 1_200_000 });
+      expect(points.map((p) => p.value)).toEqual([22, 24]);
+      expect(points[0].tags).toEqual({ room: 'b' });
+      expect((await db.tsQuery('temp', { tags: { room: 'a' } })).length).toBe(2);
+
+      const buckets = await db.tsQuery('temp', { aggregate: 'avg', bucket: 1_000_000 });
+      expect(buckets).toEqual([
+        { timestamp: 1_000_000, value: 21, count: 2 },
+        { timestamp: 2_000_000, value: 24, count: 1 },
+      ]);
+      await expect(db.tsQuery('temp', { bucket: 10 })).rejects.toThrow(ValidationError);
+    });
+  });
+
+  describe('Edges', () => {
+    test('lists and traverses edges in either direction', async () => {
+      await db.edgeAdd('ada', 'works_at', 'acme', { since: 2021 });
+      await db.edgeAdd('acme', 'located_in', 'london');
+      await db.edgeAdd('bob', 'works_at', 'acme');
+
+      expect(await db.edgeList('ada')).toEqual([
+        { from: 'ada', relation: 'works_at', to: 'acme', properties: { since: 2021 } },
+      ]);
+      const incoming = await db.edgeList('acme', { direction: 'incoming', relation: 'works_at' });
+      expect(incoming.map((e) => e.from).sort()).toEqual(['ada', 'bob']);
+
+      const walk = await db.traverse('ada', { depth: 2 });
+      expect(walk.visited).toEqual(['ada', 'acme', 'london']);
+      expect(walk.depths).toEqual({ ada: 0, acme: 1, london: 2 });
+      expect((await db.traverse('ada', { depth: 1 })).visited).toEqual(['ada', 'acme']);
+
+      const before = Date.now() * 1000;
+      expect(await db.edgeRemove('ada', 'works_at', 'acme')).toBe(true);
+      expect(await db.edgeList('ada')).toEqual([]);
+      expect(await db.edgeList('ada', { asOf: before })).toHaveLength(1);
+      await expect(db.edgeList('ada', { direction: 'sideways' })).rejects.toThrow(ValidationError);
+    });
+  });
+
+  describe('Blobs', () => {
+    test('stores buffers and streams in chunks', async () => {
+      const { Readable } = require('stream');
+      const stat = await db.blobPut('doc', Readable.from(['hello ', 'chunked ', 'world']), {
+        chunkSize: 4,
+      });
+      expect(stat).toMatchObject({ size: 19, chunks: 5 });
+      expect((await db.blobGet('doc')).toString()).toBe('hello chunked world');
+
+      let streamed = '';
+      for await (const chunk of await db.blobGet('doc', { stream: true })) {
+        streamed += chunk;
+      }
+      expect(streamed).toBe('hello chunked world');
+
+      await db.blobPut('doc', Buffer.from('replaced'));
+      expect(await db.blobStat('doc')).toMatchObject({ size: 8, chunks: 1 });
+      expect(await db.kvList('_blob/')).toHaveLength(1);
+      expect(await db.blobDelete('doc')).toBe(true);
+      expect(await db.blobGet('doc')).toBeNull();
+      expect(await db.blobDelete('doc')).toBe(false);
+    });
+  });
+
+  describe('Pub/Sub', () => {
+    test('delivers a copy of each message to current subscribers', async () => {
+      const received = [];
+      const unsubscribe = await db.subscribe('news', (message, channel) => {
+        received.push([channel, message]);
+      });
+      const message = { headline: 'hello' };
+      expect(await db.publish('news', message)).toBe(1);
+      message.headline = 'changed';
+      await new Promise((resolve) => setImmediate(resolve));
+      expect(received).toEqual([['news', { headline: 'hello' }]]);
+
+      unsubscribe();
+      expect(await db.publish('news', 'again')).toBe(0);
+      await expect(db.publish('', 1)).rejects.toThrow(ValidationError);
+    });
+
+    test('reaches server clients', async () => {
+      const server = await db.listen({ port: 0, auth: { token: 'rw', readOnlyToken: 'ro' } });
+      const url = `strata://127.0.0.1:${server.address.port}`;
+      try {
+        const reader = await Strata.connect(url, { token: 'ro' });
+        let deliver;
+        const delivered = new Promise((resolve) => { deliver = resolve; });
+        await reader.subscribe('jobs', (message) => deliver(message));
+        expect(await db.publish('jobs', { id: 7 })).toBe(1);
+        expect(await delivered).toEqual({ id: 7 });
+        await expect(reader.publish('jobs', 1)).rejects.toThrow(AccessDeniedError);
+        await reader.close();
+      } finally {
+        await server.close();
+      }
+    });
+  });
+
   // =========================================================================
   // Event Log — db.events
   // =========================================================================
@@ -292,6 +648,54 @@ describe('Strata', () => {
       const past = await db.json.get('jv_rt', '$', { asOf: vv.timestamp });
       expect(past.v).toBe(1);
     });
+
+    test('query with comparison and geo filters', async () => {
+      await db.json.set('shop:1', '$', { open: true, loc: { lat: 51.51, lon: -0.12 } });
+      await db.json.set('shop:2', '$', { open: false, loc: { lat: 51.52, lon: -0.1 } });
+      await db.json.set('shop:3', '$', { open: true, loc: { lat: 48.86, lon: 2.35 } });
+      const near = await db.json.query([
+        { field: 'loc', op: FilterOp.WithinRadius, value: { lat: 51.5, lon: -0.1, radius: 5000 } },
+        { field: 'open', op: 'eq', value: true },
+      ]);
+      expect(near).toEqual([{ key: 'shop:1', value: await db.json.get('shop:1', '$') }]);
+      const boxed = await db.json.query([
+        { field: 'loc.lon', op: 'gt', value: -1 },
+        { field: 'loc', op: 'boundingBox', value: { minLat: 48, minLon: 2, maxLat: 49, maxLon: 3 } },
+      ]);
+      expect(boxed.map((m) => m.key)).toEqual(['shop:3']);
+      await expect(db.json.query([
+        { field: 'loc', op: 'withinRadius', value: { lat: 95, lon: 0, radius: 1 } },
+      ])).rejects.toThrow(/latitude/);
+    });
+  });
+
+  describe('Collections', () => {
+    const schema = {
+      type: 'object',
+      required: ['id', 'email'],
+      properties: { id: { type: 'string' }, age: { type: 'integer', minimum: 0 } },
+    };
+
+    test('put, get, and query documents by id', async () => {
+      const users = db.collection('users', { schema });
+      await users.put({ id: 'u1', email: 'ada@example.com', age: 36 });
+      await users.put({ id: 'u2', email: 'bob@example.com', age: 12 });
+      expect(await users.get('u1')).toEqual({ id: 'u1', email: 'ada@example.com', age: 36 });
+      expect(await db.json.get('users/u2', '$.age')).toBe(12);
+      const adults = await users.query([{ field: 'age', op: 'gte', value: 18 }]);
+      expect(adults.map((m) => m.id)).toEqual(['u1']);
+      expect(await users.schema()).toEqual(schema);
+    });
+
+    test('rejects documents and schemas that do not validate', async () => {
+      const users = db.collection('users', { schema });
+      await expect(users.put({ id: 'u1', age: 3 })).rejects.toThrow(ValidationError);
+      await expect(db.collection('users').put({ id: 'u1', email: 'a', age: -1 }))
+        .rejects.toThrow(/\$\.age/);
+      expect(await users.get('u1')).toBeNull();
+      const typo = db.collection('typo', { schema: { type: 'object', requried: ['id'] } });
+      await expect(typo.put({ id: 'x' })).rejects.toThrow(/unsupported keyword 'requried'/);
+    });
   });
 
   // =========================================================================
@@ -319,6 +723,21 @@ describe('Strata', () => {
       expect(results[0].key).toBe('v1');
     });
 
+    test('enum constants for metric and filter op', async () => {
+      expect(Strata.DistanceMetric.Cosine).toBe('cosine');
+      expect(Strata.MergeStrategy.LastWriterWins).toBe('last_writer_wins');
+      expect(Strata.FilterOp).toBe(FilterOp);
+      await db.vector.createCollection('enums', {
+        dimension: 4, metric: Strata.DistanceMetric.Euclidean,
+      });
+      await db.vector.upsert('enums', 'a', [1, 0, 0, 0], { metadata: { year: 2019 } });
+      await db.vector.upsert('enums', 'b', [0, 1, 0, 0], { metadata: { year: 2024 } });
+      const results = await db.vector.search('enums', [1, 0, 0, 0], {
+        limit: 2, filter: [{ field: 'year', op: FilterOp.Gte, value: 2020 }],
+      });
+      expect(results.map((r) => r.key)).toEqual(['b']);
+    });
+
     test('upsert with metadata option', async () => {
       await db.vector.createCollection('docs', { dimension: 4 });
       const vec = [1.0, 0.0, 0.0, 0.0];
@@ -386,6 +805,26 @@ describe('Strata', () => {
       expect(results[0].key).toBe('k1');
     });
 
+    test('search with geo filter', async () => {
+      await db.vector.createCollection('places', { dimension: 4 });
+      for (let i = 0; i < 20; i++) {
+        const loc = i === 19 ? { lat: 35.68, lon: 139.76 } : { lat: 51.5, lon: -0.1 + i / 100 };
+        await db.vector.upsert('places', `p${i}`, [1, i / 20, 0, 0], { metadata: { loc } });
+      }
+      const results = await db.vector.search('places', [1, 0, 0, 0], {
+        limit: 1,
+        filter: [{ field: 'loc', op: 'withinRadius', value: { lat: 35.7, lon: 139.7, radius: 10000 } }],
+      });
+      expect(results.map((r) => r.key)).toEqual(['p19']);
+      const wrapped = await db.vector.search('places', [1, 0, 0, 0], {
+        limit: 5,
+        filter: [{
+          field: 'loc', op: 'boundingBox', value: { minLat: 30, minLon: 130, maxLat: 40, maxLon: -170 },
+        }],
+      });
+      expect(wrapped.map((r) => r.key)).toEqual(['p19']);
+    });
+
     test('rejects non-finite vector values', async () => {
       await db.vector.createCollection('finite_test', { dimension: 4 });
       await expect(
@@ -435,6 +874,26 @@ describe('Strata', () => {
       expect(await db.kv.get('shared')).toBe('original');
     });
 
+    test('fork as of a past timestamp', async () => {
+      const sleep = (ms) => new Promise((r) => setTimeout(r, ms));
+      await db.kv.set('k', 'old');
+      await db.state.set('cell', 1);
+      await sleep(50);
+      const ts = (await db.timeRange()).latestTs;
+      await sleep(50);
+      await db.kv.set('k', 'new');
+      await db.kv.set('later', true);
+
+      const result = await db.branch.fork('tuesday', { asOf: ts });
+      expect(result.keysCopied).toBe(2);
+      expect(await db.branch.current()).toBe('default');
+
+      await db.branch.switch('tuesday');
+      expect(await db.kv.get('k')).toBe('old');
+      expect(await db.kv.get('later')).toBeNull();
+      expect(await db.state.get('cell')).toBe(1);
+    });
+
     test('delete', async () => {
       await db.branch.create('to_del');
       await db.branch.delete('to_del');
@@ -442,6 +901,88 @@ describe('Strata', () => {
       expect(branches).not.toContain('to_del');
     });
 
+    test('rename keeps data and follows the current branch', async () => {
+      await db.branch.create('old_name');
+      await db.branch.switch('old_name');
+      await db.kv.set('k', 'v');
+      await db.branch.setMetadata('old_name', { description: 'scratch' });
+
+      await db.branch.rename('old_name', 'new_name');
+      expect(await db.branch.current()).toBe('new_name');
+      expect(await db.branch.exists('old_name')).toBe(false);
+      expect(await db.kv.get('k')).toBe('v');
+      expect((await db.branch.get('new_name')).description).toBe('scratch');
+    });
+
+    test('protected branch rejects deletes and direct writes', async () => {
+      await db.branch.create('prod');
+      await db.branch.protect('prod');
+      await expect(db.branch.delete('prod')).rejects.toThrow(AccessDeniedError);
+
+      await db.branch.switch('prod');
+      await expect(db.kv.set('k', 1)).rejects.toThrow(AccessDeniedError);
+      await db.branch.switch('default');
+
+      await db.branch.protect('prod', false);
+      await db.branch.delete('prod');
+      expect(await db.branch.exists('prod')).toBe(false);
+    });
+
+    test('archived branch is read-only and hidden from list', async () => {
+      await db.branch.create('old');
+      await db.branch.archive('old');
+      expect(await db.branch.list()).not.toContain('old');
+      expect(await db.branch.list({ includeArchived: true })).toContain('old');
+      expect((await db.branch.get('old')).archived).toBe(true);
+
+      await db.branch.switch('old');
+      await expect(db.kv.set('k', 1)).rejects.toThrow(AccessDeniedError);
+    });
+
+    test('stats counts entries per primitive', async () => {
+      await db.branch.create('st');
+      await db.branch.switch('st');
+      await db.kv.set('a', 1);
+      await db.kv.set('b', 'two');
+      await db.state.set('c', true);
+      await db.events.append('e', { x: 1 });
+      await db.branch.switch('default');
+
+      const stats = await db.branch.stats('st');
+      expect(stats.branch).toBe('st');
+      expect(stats.kv).toBe(2);
+      expect(stats.state).toBe(1);
+      expect(stats.events).toBe(1);
+      expect(stats.estimatedBytes).toBeGreaterThan(0);
+      expect(stats.lastWriteAt).toEqual(expect.any(Number));
+      expect(await db.branch.current()).toBe('default');
+    });
+
+    test('lineage reports parents and divergence', async () => {
+      await db.kv.set('a', 1);
+      await db.branch.fork('child');
+      await db.branch.switch('child');
+      await db.kv.set('b', 2);
+
+      const { branches } = await db.branch.lineage();
+      const child = branches.find((b) => b.id === 'child');
+      expect(child.parentId).toBe('default');
+      expect(child.forkedAt).toEqual(expect.any(Number));
+      expect(child.divergence.added).toBe(1);
+
+      const root = branches.find((b) => b.id === 'default');
+      expect(root.divergence).toBeNull();
+    });
+
+    test('setMetadata surfaces via get', async () => {
+      await db.branch.create('meta');
+      await db.branch.setMetadata('meta', { description: 'release', labels: ['prod'] });
+      await db.branch.setMetadata('meta', { labels: ['prod', 'v2'] });
+      const info = await db.branch.get('meta');
+      expect(info.description).toBe('release');
+      expect(info.labels).toEqual(['prod', 'v2']);
+    });
+
     test('exists returns false for missing', async () => {
       expect(await db.branch.exists('nope')).toBe(false);
     });
@@ -466,6 +1007,69 @@ describe('Strata', () => {
       expect(diff.summary).toHaveProperty('totalAdded');
     });
 
+    test('detailed diff lists keys per space', async () => {
+      await db.kv.set('shared', 'a');
+      await db.branch.fork('detail_b');
+      await db.branch.switch('detail_b');
+      await db.kv.set('shared', 'b');
+      await db.kv.set('only_b', 1);
+      await db.branch.switch('default');
+
+      const diff = await db.branch.diff('default', 'detail_b', { detailed: true, values: 'values' });
+      const space = diff.spaces.find((s) => s.space === 'default');
+      expect(space.added.map((e) => e.key)).toContain('only_b');
+      const modified = space.modified.find((e) => e.key === 'shared');
+      expect(modified.valueA).toBe('a');
+      expect(modified.valueB).toBe('b');
+      expect(diff.hasMore).toBe(false);
+    });
+
+    test('detailed diff paginates', async () => {
+      await db.branch.fork('page_b');
+      await db.branch.switch('page_b');
+      await db.kv.set('p1', 1);
+      await db.kv.set('p2', 2);
+      await db.kv.set('p3', 3);
+      await db.branch.switch('default');
+
+      const first = await db.branch.diff('default', 'page_b', { detailed: true, limit: 2 });
+      expect(first.hasMore).toBe(true);
+      const second = await db.branch.diff('default', 'page_b', {
+        detailed: true,
+        limit: 2,
+        cursor: first.cursor,
+      });
+      const count = (d) => d.spaces.reduce((n, s) => n + s.added.length, 0);
+      expect(count(first) + count(second)).toBe(3);
+      await expect(
+        db.branch.diff('default', 'page_b', { detailed: true, limit: 0 }),
+      ).rejects.toThrow(ValidationError);
+      await expect(
+        db.branch.diff('default', 'page_b', { detailed: true, cursor: '2' }),
+      ).rejects.toThrow(ValidationError);
+    });
+
+    test('detailed diff cursor resumes after its key when the diff changes', async () => {
+      await db.branch.fork('cursor_b');
+      await db.branch.switch('cursor_b');
+      for (const key of ['c1', 'c2', 'c3', 'c4']) {
+        await db.kv.set(key, key);
+      }
+      const first = await db.branch.diff('default', 'cursor_b', { detailed: true, limit: 2 });
+      const keys = (d) => d.spaces.flatMap((s) => s.added.map((e) => e.key));
+      expect(keys(first)).toEqual(['c1', 'c2']);
+      await db.kv.delete('c1');
+      const second = await db.branch.diff('default', 'cursor_b', {
+        detailed: true,
+        limit: 2,
+        cursor: first.cursor,
+      });
+      await db.branch.switch('default');
+      expect(keys(second)).toEqual(['c3', 'c4']);
+      expect(second.hasMore).toBe(false);
+      expect(second.cursor).toBeNull();
+    });
+
     test('merge with and without strategy option', async () => {
       await db.kv.set('base', 'val');
       await db.branch.fork('merge_src');
@@ -482,6 +1086,359 @@ describe('Strata', () => {
       const r2 = await db.branch.merge('merge_src2', { strategy: 'last_writer_wins' });
       expect(r2).toHaveProperty('keysApplied');
     });
+
+    test('merge dryRun previews without writing', async () => {
+      await db.kv.set('base', 'val');
+      await db.kv.set('ours', 'val');
+      await db.kv.set('both', 'val');
+      await db.branch.fork('dry_src');
+      await db.branch.switch('dry_src');
+      await db.kv.set('new_key', 'from_src');
+      await db.kv.set('base', 'changed');
+      await db.kv.set('both', 'theirs');
+      await db.branch.switch('default');
+      await db.kv.set('ours', 'mine');
+      await db.kv.set('both', 'mine');
+
+      const preview = await db.branch.merge('dry_src', { dryRun: true });
+      expect(preview.dryRun).toBe(true);
+      expect(preview.keysApplied).toBe(3);
+      expect(preview.conflicts.map((c) => c.key)).toEqual(['both']);
+      expect(preview.spaces[0].space).toBe('default');
+      expect(await db.kv.get('new_key')).toBeNull();
+      expect(await db.kv.get('base')).toBe('val');
+
+      const strict = await db.branch.merge('dry_src', { dryRun: true, strategy: 'strict' });
+      expect(strict.wouldFail).toBe(true);
+      const merged = await db.branch.merge('dry_src');
+      expect(merged.conflicts.map((c) => c.key)).toEqual(preview.conflicts.map((c) => c.key));
+    });
+
+    test('merge conflicts carry values with includeValues', async () => {
+      await db.kv.set('c', 'base');
+      await db.branch.fork('cv_src');
+      await db.branch.switch('cv_src');
+      await db.kv.set('c', 'theirs');
+      await db.branch.switch('default');
+      await db.kv.set('c', 'ours');
+
+      const preview = await db.branch.merge('cv_src', { dryRun: true, includeValues: true });
+      const conflict = preview.conflicts.find((c) => c.key === 'c');
+      expect(conflict.targetValue).toBe('ours');
+      expect(conflict.sourceValue).toBe('theirs');
+      expect(conflict.primitive).toBe('kv');
+    });
+
+    test('merge resolve callback picks the kept value', async () => {
+      await db.kv.set('n', 1);
+      await db.branch.fork('res_src');
+      await db.branch.switch('res_src');
+      await db.kv.set('n', 5);
+      await db.branch.switch('default');
+      await db.kv.set('n', 3);
+
+      const seen = [];
+      const result = await db.branch.merge('res_src', {
+        resolve: (c) => {
+          seen.push(c.key);
+          return c.targetValue + c.sourceValue;
+        },
+      });
+      expect(result.conflicts.length).toBeGreaterThan(0);
+      expect(seen).toEqual(['n']);
+      expect(result.conflicts[0].resolvedValue).toBe(8);
+      expect(await db.kv.get('n')).toBe(8);
+    });
+
+    test('merge resolve that throws writes nothing', async () => {
+      await db.kv.set('n', 1);
+      await db.branch.fork('throw_src');
+      await db.branch.switch('throw_src');
+      await db.kv.set('n', 5);
+      await db.kv.set('extra', true);
+      await db.branch.switch('default');
+      await db.kv.set('n', 3);
+
+      await expect(db.branch.merge('throw_src', {
+        resolve: () => {
+          throw new Error('undecided');
+        },
+      })).rejects.toThrow('undecided');
+      expect(await db.kv.get('n')).toBe(3);
+      expect(await db.kv.get('extra')).toBeNull();
+    });
+
+    test('manual merge stages conflicts until resolved', async () => {
+      await db.kv.set('n', 1);
+      await db.branch.fork('manual_src');
+      await db.branch.switch('manual_src');
+      await db.kv.set('n', 5);
+      await db.kv.set('extra', true);
+      await db.branch.switch('default');
+      await db.kv.set('n', 3);
+
+      const result = await db.branch.merge('manual_src', { strategy: 'manual' });
+      expect(result.conflicts.map((c) => c.key)).toEqual(['n']);
+      expect(await db.kv.get('n')).toBe(3);
+      expect(await db.kv.get('extra')).toBe(true);
+
+      const staged = await db.branch.conflicts();
+      expect(staged).toHaveLength(1);
+      expect(staged[0]).toMatchObject({
+        key: 'n',
+        branch: 'default',
+        source: 'manual_src',
+        targetValue: 3,
+        sourceValue: 5,
+      });
+      await db.branch.resolveConflict(staged[0], 4);
+      expect(await db.kv.get('n')).toBe(4);
+      expect(await db.branch.conflicts()).toEqual([]);
+      await expect(db.branch.resolveConflict(staged[0], 4)).rejects.toThrow(NotFoundError);
+      await expect(
+        db.branch.merge('manual_src', { strategy: 'manual', resolve: () => 1 }),
+      ).rejects.toThrow(ValidationError);
+    });
+
+    test('merge resolve rejects strict strategy', async () => {
+      await db.branch.create('res_strict');
+      await expect(
+        db.branch.merge('res_strict', { strategy: 'strict', resolve: () => null }),
+      ).rejects.toThrow(ValidationError);
+    });
+  });
+
+  // =========================================================================
+  // Bundles
+  // =========================================================================
+
+  describe('Bundles', () => {
+    let dir;
+    let other;
+
+    beforeEach(() => {
+      dir = fs.mkdtempSync(path.join(os.tmpdir(), 'strata-bundle-'));
+      other = Strata.open(path.join(dir, 'other'));
+    });
+
+    afterEach(async () => {
+      await other.close().catch(() => {});
+      fs.rmSync(dir, { recursive: true, force: true });
+    });
+
+    test('streams a branch into another database', async () => {
+      await db.branch.create('streamed');
+      await db.branch.switch('streamed');
+      await db.kv.set('a', 1);
+      await db.json.set('doc', '$', { n: 2 });
+
+      const stream = db.branch.exportStream('streamed', { chunkSize: 16 });
+      const result = await other.branch.importStream(stream);
+      expect(result.branchId).toBe('streamed');
+
+      await other.branch.switch('streamed');
+      expect(await other.kv.get('a')).toBe(1);
+      expect(await other.json.get('doc', '$')).toEqual({ n: 2 });
+    });
+
+    test('exports only the filtered entries', async () => {
+      await db.branch.create('filtered');
+      await db.branch.switch('filtered');
+      await db.kv.set('keep/a', 1);
+      await db.kv.set('keep/a', 2);
+      await db.kv.set('drop/b', 3);
+      await db.state.set('keep/s', 'on');
+
+      const file = path.join(dir, 'filtered.bundle');
+      await db.branch.export('filtered', file, {
+        prefixes: ['keep/'],
+        primitives: ['kv'],
+        includeHistory: false,
+      });
+      await other.branch.import(file);
+
+      await other.branch.switch('filtered');
+      expect(await other.kv.get('keep/a')).toBe(2);
+      expect(await other.kv.history('keep/a')).toHaveLength(1);
+      expect(await other.kv.get('drop/b')).toBeNull();
+      expect(await other.state.get('keep/s')).toBeNull();
+      await expect(
+        db.branch.export('filtered', file, { primitives: ['blob'] }),
+      ).rejects.toThrow(ValidationError);
+    });
+
+    test('applies a delta bundle onto its base', async () => {
+      await db.branch.create('delta');
+      await db.branch.switch('delta');
+      await db.kv.set('a', 1);
+      const base = await db.kv.set('b', 1);
+      await db.branch.export('delta', path.join(dir, 'base.bundle'));
+      await other.branch.import(path.join(dir, 'base.bundle'));
+
+      await db.kv.set('b', 2);
+      await db.kv.set('c', 3);
+      const file = path.join(dir, 'delta.bundle');
+      await db.branch.exportIncremental('delta', base, file);
+      expect((await db.branch.validateBundle(file)).sinceVersion).toBe(base);
+      await other.branch.import(file);
+
+      await other.branch.switch('delta');
+      expect(await other.kv.get('a')).toBe(1);
+      expect(await other.kv.get('b')).toBe(2);
+      expect(await other.kv.get('c')).toBe(3);
+
+      await other.branch.switch('default');
+      await other.branch.delete('delta');
+      await expect(other.branch.import(file)).rejects.toThrow(NotFoundError);
+    });
+
+    test('encrypts a bundle and needs the key to read it', async () => {
+      await db.branch.create('secret');
+      await db.branch.switch('secret');
+      await db.kv.set('token', 'abc');
+
+      const key = Buffer.alloc(32, 7);
+      const file = path.join(dir, 'secret.bundle');
+      await db.branch.export('secret', file, { encryption: { key, keyId: 'k1' } });
+      expect(fs.readFileSync(file).includes('abc')).toBe(false);
+
+      const info = await db.branch.validateBundle(file, { encryption: { key } });
+      expect(info).toMatchObject({ encrypted: true, keyId: 'k1', checksumsValid: true });
+      await expect(other.branch.import(file)).rejects.toThrow();
+      await expect(
+        other.branch.import(file, { encryption: { key: Buffer.alloc(32, 8) } }),
+      ).rejects.toThrow();
+      expect(await other.branch.exists('secret')).toBe(false);
+      await expect(
+        other.branch.import(file, { encryption: { key: Buffer.alloc(16) } }),
+      ).rejects.toThrow(ValidationError);
+
+      await other.branch.import(file, { encryption: { key } });
+      await other.branch.switch('secret');
+      expect(await other.kv.get('token')).toBe('abc');
+    });
+
+    test('derives the bundle key from a passphrase', async () => {
+      await db.branch.create('phrase');
+      await db.branch.switch('phrase');
+      await db.kv.set('a', 1);
+      const file = path.join(dir, 'phrase.bundle');
+      await db.branch.export('phrase', file, { encryption: { passphrase: 'hunter2' } });
+      await expect(
+        other.branch.import(file, { encryption: { passphrase: 'wrong' } }),
+      ).rejects.toThrow();
+
+      await other.branch.import(file, { encryption: { passphrase: 'hunter2' } });
+      await other.branch.switch('phrase');
+      expect(await other.kv.get('a')).toBe(1);
+    });
+
+    test('imports under another name and handles an existing branch', async () => {
+      await db.branch.create('release');
+      await db.branch.switch('release');
+      await db.kv.set('a', 1);
+      const file = path.join(dir, 'release.bundle');
+      await db.branch.export('release', file);
+
+      const result = await db.branch.import(file, { destinationBranch: 'review' });
+      expect(result.branchId).toBe('review');
+      await expect(
+        db.branch.import(file, { destinationBranch: 'review' }),
+      ).rejects.toThrow(StateError);
+
+      await db.branch.switch('review');
+      await db.kv.set('a', 9);
+      await db.kv.set('local', true);
+      await db.branch.switch('default');
+      await db.branch.import(file, { destinationBranch: 'review', onConflict: 'replace' });
+      await db.branch.switch('review');
+      expect(await db.kv.get('a')).toBe(1);
+      expect(await db.kv.get('local')).toBeNull();
+
+      await db.kv.set('local', true);
+      await db.branch.switch('default');
+      await db.branch.import(file, { destinationBranch: 'review', onConflict: 'merge' });
+      await db.branch.switch('review');
+      expect(await db.kv.get('a')).toBe(1);
+      expect(await db.kv.get('local')).toBe(true);
+      expect((await db.branch.list()).filter((b) => b.startsWith('_import-'))).toEqual([]);
+
+      await expect(
+        db.branch.import(file, { onConflict: 'skip' }),
+      ).rejects.toThrow(ValidationError);
+      await expect(
+        db.branch.import(file, { destinationBranch: '' }),
+      ).rejects.toThrow(ValidationError);
+    });
+
+    test('reports progress and stops when aborted', async () => {
+      await db.branch.create('big');
+      await db.branch.switch('big');
+      await db.kv.batchPut(Array.from({ length: 200 }, (_, i) => ({ key: `k${i}`, value: i })));
+      const file = path.join(dir, 'big.bundle');
+
+      const progress = [];
+      await db.branch.export('big', file, { onProgress: (p) => progress.push(p) });
+      await new Promise((resolve) => setImmediate(resolve));
+      expect(progress.length).toBeGreaterThan(0);
+      const last = progress[progress.length - 1];
+      expect(last.entriesDone).toBeGreaterThan(0);
+      expect(last.bytesDone).toBeGreaterThan(0);
+
+      const ac = new AbortController();
+      ac.abort(new Error('stop'));
+      await expect(
+        other.branch.import(file, { signal: ac.signal }),
+      ).rejects.toThrow('stop');
+      await expect(
+        other.branch.validateBundle(file, { signal: ac.signal }),
+      ).rejects.toThrow('stop');
+      expect(await other.branch.exists('big')).toBe(false);
+
+      await other.branch.import(file, { signal: new AbortController().signal });
+      await other.branch.switch('big');
+      expect(await other.kv.get('k199')).toBe(199);
+    });
+
+    test('diffs bundles against each other and a live branch', async () => {
+      await db.branch.create('snap');
+      await db.branch.switch('snap');
+      await db.kv.set('shared', 'a');
+      const first = path.join(dir, 'first.bundle');
+      await db.branch.export('snap', first);
+      await db.kv.set('shared', 'b');
+      await db.kv.set('added', 1);
+      const second = path.join(dir, 'second.bundle');
+      await db.branch.export('snap', second);
+
+      const diff = await db.branch.diffBundle(first, second, { detailed: true, values: 'values' });
+      expect(diff.branchA).toBe(first);
+      expect(diff.branchB).toBe(second);
+      const space = diff.spaces.find((s) => s.space === 'default');
+      expect(space.added.map((e) => e.key)).toEqual(['added']);
+      expect(space.modified.find((e) => e.key === 'shared')).toMatchObject({
+        valueA: 'a',
+        valueB: 'b',
+      });
+
+      await db.kv.set('live', true);
+      const live = await db.branch.diffBundle(second, { branch: 'snap' }, { detailed: true });
+      expect(live.branchB).toBe('snap');
+      expect(live.spaces.find((s) => s.space === 'default').added.map((e) => e.key))
+        .toEqual(['live']);
+      expect((await db.branch.list()).filter((b) => b.startsWith('_import-'))).toEqual([]);
+
+      const key = Buffer.alloc(32, 3);
+      const sealed = path.join(dir, 'sealed.bundle');
+      await db.branch.export('snap', sealed, { encryption: { key } });
+      await expect(db.branch.diffBundle(sealed, { branch: 'snap' })).rejects.toThrow();
+      const same = await db.branch.diffBundle(sealed, { branch: 'snap' }, { encryption: { key } });
+      expect(same.summary.totalAdded).toBe(0);
+
+      const delta = path.join(dir, 'delta.bundle');
+      await db.branch.exportIncremental('snap', 1, delta);
+      await expect(db.branch.diffBundle(delta, first)).rejects.toThrow(ValidationError);
+    });
   });
 
   // =========================================================================
@@ -520,6 +1477,66 @@ describe('Strata', () => {
       await db.space.delete('to_del_space', { force: true });
       expect(await db.space.exists('to_del_space')).toBe(false);
     });
+
+    test('stats reports counts for one space', async () => {
+      await db.space.switch('tenant_b');
+      await db.kv.set('a', 1);
+      await db.events.append('signup', { user: 'x' });
+      await db.space.switch('default');
+      await db.kv.set('unrelated', 1);
+
+      const stats = await db.space.stats('tenant_b');
+      expect(stats.space).toBe('tenant_b');
+      expect(stats.kv).toBe(1);
+      expect(stats.events).toBe(1);
+      expect(stats.estimatedBytes).toBeGreaterThan(0);
+      expect(await db.space.current()).toBe('default');
+      await expect(db.space.stats('missing')).rejects.toThrow(NotFoundError);
+    });
+
+    test('copy duplicates a template space', async () => {
+      await db.space.switch('template');
+      await db.kv.set('plan', 'free');
+      await db.json.set('settings', '$', { theme: 'dark' });
+      await db.space.switch('default');
+
+      const result = await db.space.copy('template', 'tenant_a');
+      expect(result.keysCopied).toBe(2);
+      expect(await db.space.current()).toBe('default');
+
+      await db.space.switch('tenant_a');
+      expect(await db.kv.get('plan')).toBe('free');
+      expect(await db.json.get('settings', '$.theme')).toBe('dark');
+      await expect(db.space.copy('template', 'tenant_a')).rejects.toThrow(StateError);
+    });
+
+    test('rename moves data and follows the current space', async () => {
+      await db.space.switch('draft');
+      await db.kv.set('k', 1);
+      await db.space.rename('draft', 'final');
+
+      expect(await db.space.current()).toBe('final');
+      expect(await db.kv.get('k')).toBe(1);
+      expect(await db.space.exists('draft')).toBe(false);
+    });
+
+    test('keys across spaces', async () => {
+      await db.kv.set('user:1', 'a');
+      await db.space.switch('tenant_a');
+      await db.kv.set('user:7', 'b');
+      await db.space.switch('default');
+
+      const all = await db.kv.keys({ prefix: 'user:', spaces: '*' });
+      expect(all).toEqual(
+        expect.arrayContaining([
+          { space: 'default', key: 'user:1' },
+          { space: 'tenant_a', key: 'user:7' },
+        ]),
+      );
+      const some = await db.kv.keys({ spaces: ['tenant_a', 'missing'] });
+      expect(some).toEqual([{ space: 'tenant_a', key: 'user:7' }]);
+      expect(await db.space.current()).toBe('default');
+    });
   });
 
   // =========================================================================
@@ -532,10 +1549,71 @@ describe('Strata', () => {
       expect(version).toBeTruthy();
     });
 
+    test('poolStats', async () => {
+      const before = (await db.poolStats()).completed;
+      await Promise.all([db.kv.set('p1', 1), db.kv.set('p2', 2), db.ping()]);
+      const stats = await db.poolStats();
+      expect(stats.dedicated).toBe(false);
+      expect(stats.size).toBeGreaterThanOrEqual(4);
+      expect(stats.size).toBeLessThanOrEqual(Math.max(os.cpus().length * 2, 4));
+      expect(stats.completed).toBeGreaterThanOrEqual(before + 3);
+      expect(stats.queued).toBe(0);
+      expect(stats.saturation).toBeGreaterThanOrEqual(0);
+    });
+
     test('info', async () => {
       const info = await db.info();
       expect(info.version).toBeTruthy();
       expect(info.branchCount).toBeGreaterThanOrEqual(1);
+      expect(info.diskBytes).toBeNull();
+    });
+
+    test('info reports disk usage only when asked', async () => {
+      expect((await db.info({ diskBytes: true })).diskBytes).toBeNull();
+      const dir = fs.mkdtempSync(path.join(os.tmpdir(), 'strata-info-'));
+      try {
+        const handle = Strata.open(dir);
+        await handle.kv.set('a', 1);
+        await handle.flush();
+        expect((await handle.info()).diskBytes).toBeNull();
+        fs.symlinkSync('/', path.join(dir, 'root-link'));
+        const { diskBytes } = await handle.info({ diskBytes: true });
+        expect(diskBytes).toBeGreaterThan(0);
+        expect(diskBytes).toBeLessThan(1024 * 1024 * 1024);
+        await handle.close();
+      } finally {
+        fs.rmSync(dir, { recursive: true, force: true });
+      }
+    });
+
+    test('compression is not an open option', () => {
+      expect(() => Strata.open('/tmp/unused', { compression: 'zstd' })).toThrow(ValidationError);
+    });
+
+    test('cacheMB caches latest KV reads until the next write', async () => {
+      expect(await db.cacheStats()).toEqual(expect.objectContaining({ capacityBytes: 0, hits: 0 }));
+      const dir = fs.mkdtempSync(path.join(os.tmpdir(), 'strata-cache-'));
+      try {
+        const cached = Strata.open(dir, { cacheMB: 1 });
+        await cached.kv.set('k', { n: 1 });
+        expect(await cached.kv.get('k')).toEqual({ n: 1 });
+        expect(await cached.kv.get('k')).toEqual({ n: 1 });
+        let stats = await cached.cacheStats();
+        expect(stats).toEqual(expect.objectContaining({ capacityBytes: 1024 * 1024, hits: 1, misses: 1, entries: 1 }));
+        expect(stats.residentBytes).toBeGreaterThan(0);
+        expect(stats.hitRate).toBe(0.5);
+
+        await cached.kv.set('k', { n: 2 });
+        expect(await cached.kv.get('k')).toEqual({ n: 2 });
+        await cached.branch.create('other');
+        await cached.branch.switch('other');
+        expect(await cached.kv.get('k')).toBeNull();
+        stats = await cached.cacheStats();
+        expect(stats.hits).toBe(1);
+        await cached.close();
+      } finally {
+        fs.rmSync(dir, { recursive: true, force: true });
+      }
     });
 
     test('flush', async () => {
@@ -545,6 +1623,26 @@ describe('Strata', () => {
     test('compact', async () => {
       await db.compact();
     });
+
+    test('verify', async () => {
+      await db.kv.set('verify:1', { ok: true });
+      await db.graph.create('verify-graph');
+      await db.graph.addNode('verify-graph', 'a');
+      await db.graph.addNode('verify-graph', 'b');
+      await db.graph.addEdge('verify-graph', 'a', 'b', 'links');
+
+      const quick = await db.verify();
+      expect(quick).toMatchObject({ ok: true, deep: false, entriesChecked: 0, problems: [] });
+      expect(quick.branches).toBeGreaterThanOrEqual(1);
+      expect(quick.spaces).toBeGreaterThanOrEqual(1);
+
+      const deep = await db.verify({ deep: true });
+      expect(deep.ok).toBe(true);
+      expect(deep.entriesChecked).toBeGreaterThanOrEqual(2);
+      expect((await db.status()).branch).toBe('default');
+      await db.graph.delete('verify-graph');
+      await db.kv.delete('verify:1');
+    });
   });
 
   // =========================================================================
@@ -565,15 +1663,95 @@ describe('Strata', () => {
       await expect(db.txnIsActive()).resolves.toBe(false);
     });
 
-    test('txnIsActive before begin', async () => {
-      expect(await db.txnIsActive()).toBe(false);
+    test('rollback to a savepoint', async () => {
+      await db.begin();
+      await db.execute('kv_put', { key: 'sp_a', value: 1 });
+      await db.txSavepoint('after_a');
+      await db.execute('kv_put', { key: 'sp_b', value: 2 });
+      await db.txRollbackTo('after_a');
+      await expect(db.txnIsActive()).resolves.toBe(true);
+      await db.commit();
+
+      expect(await db.kv.get('sp_a')).toBe(1);
+      expect(await db.kv.get('sp_b')).toBeNull();
     });
 
-    test('txnInfo', async () => {
-      expect(await db.txnInfo()).toBeNull();
+    test('detailed commit lists each write', async () => {
       await db.begin();
-      const info = await db.txnInfo();
-      expect(info).not.toBeNull();
+      await db.execute('kv_put', { key: 'op_a', value: 1 });
+      await db.execute('state_set', { cell: 'op_b', value: 2 });
+      const { version, operations } = await db.commit({ detailed: true });
+      expect(operations.map((o) => [o.command, o.key])).toEqual([
+        ['kv_put', 'op_a'],
+        ['state_set', 'op_b'],
+      ]);
+      expect(operations.every((o) => o.version === version)).toBe(true);
+    });
+
+    test('savepoints need a transaction', async () => {
+      await expect(db.txSavepoint('nope')).rejects.toThrow(StateError);
+    });
+
+    test('transactionWithRetry retries conflicting commits', async () => {
+      const commit = db.commit;
+      let failures = 1;
+      db.commit = async function () {
+        if (failures-- > 0) {
+          throw new ConflictError('conflict');
+        }
+        return commit.call(this);
+      };
+      try {
+        const attempts = [];
+        const result = await db.transactionWithRetry(async (tx, attempt) => {
+          attempts.push(attempt);
+          return 'done';
+        }, { backoff: () => 0 });
+        expect(result).toBe('done');
+        expect(attempts).toEqual([0, 1]);
+      } finally {
+        delete db.commit;
+      }
+      await expect(db.txnIsActive()).resolves.toBe(false);
+    });
+
+    test('snapshot transaction reads at asOf', async () => {
+      const sleep = (ms) => new Promise((r) => setTimeout(r, ms));
+      await db.kv.set('snap_txn', 'old');
+      await sleep(50);
+      const ts = (await db.timeRange()).latestTs;
+      await sleep(50);
+      await db.kv.set('snap_txn', 'new');
+
+      const read = await db.transaction(
+        (tx) => tx.execute('kv_get', { key: 'snap_txn' }),
+        { readOnly: true, asOf: ts },
+      );
+      expect(read.value).toBe('old');
+      await expect(db.begin({ asOf: ts })).rejects.toThrow(ValidationError);
+    });
+
+    test('transaction on another branch and space', async () => {
+      await db.branch.create('txn_target');
+      await db.transaction(async (tx) => {
+        await tx.execute('kv_put', { key: 'scoped', value: 1 });
+      }, { branch: 'txn_target', space: 'txn_space' });
+
+      expect(await db.branch.current()).toBe('default');
+      expect(await db.kv.get('scoped')).toBeNull();
+      const view = db.readBranch('txn_target', { space: 'txn_space' });
+      expect(await view.kv.get('scoped')).toBe(1);
+    });
+
+    test('txnIsActive before begin', async () => {
+      expect(await db.txnIsActive()).toBe(false);
+    });
+
+    test('txnInfo', async () => {
+      expect(await db.txnInfo()).toBeNull();
+      await db.begin();
+      const info = await db.txnInfo();
+      expect(info).not.toBeNull();
       expect(info).toHaveProperty('id');
       expect(info).toHaveProperty('status');
       await db.rollback();
@@ -589,6 +1767,57 @@ describe('Strata', () => {
       await db.kv.set('r_key', 'val');
       await db.retentionApply();
     });
+
+    test('configure, get, and reset rules', async () => {
+      await db.retentionConfigure({ primitive: 'kv', maxVersions: 3 });
+      await db.retentionConfigure({ space: 'audit', primitive: 'event', keepTags: true });
+
+      const { rules } = await db.retentionGetConfig();
+      expect(rules).toHaveLength(2);
+      const kv = rules.find((r) => r.primitive === 'kv');
+      expect(kv.maxVersions).toBe(3);
+      expect(kv.space).toBeNull();
+
+      await db.retentionApply();
+      expect(await db.retentionReset(null, 'kv')).toBe(true);
+      expect((await db.retentionGetConfig()).rules).toHaveLength(1);
+    });
+
+    test('pinned keys survive retentionApply', async () => {
+      for (const v of ['v1', 'v2', 'v3']) {
+        await db.kv.set('unpinned', v);
+      }
+      for (const v of ['v1', 'v2', 'v3']) {
+        await db.kv.set('pinned', v);
+      }
+      await db.retentionConfigure({ primitive: 'kv', maxVersions: 1 });
+      await db.retentionPin({ key: 'pinned' });
+      expect(await db.retentionListPins()).toEqual([
+        { key: 'pinned', space: 'default', primitive: 'kv' },
+      ]);
+
+      const report = await db.retentionApply();
+      expect(report.versionsRemoved.kv).toBe(2);
+      expect(report.oldestTimestamps).toHaveProperty('default');
+      expect(await db.kv.history('pinned')).toHaveLength(3);
+      expect(await db.kv.history('unpinned')).toHaveLength(1);
+      expect(await db.retentionUnpin({ key: 'pinned' })).toBe(true);
+    });
+
+    test('historyTrim keeps the newest versions', async () => {
+      for (const v of ['v1', 'v2', 'v3']) {
+        await db.kv.set('runaway', v);
+      }
+      const report = await db.historyTrim({ keepLast: 1, primitive: 'kv' });
+      expect(report.versionsRemoved.kv).toBe(2);
+      expect(await db.kv.history('runaway')).toHaveLength(1);
+      await expect(db.historyTrim({ space: 'default' })).rejects.toThrow(ValidationError);
+    });
+
+    test('configure rejects unknown primitives', async () => {
+      await expect(db.retentionConfigure({ primitive: 'blob', maxVersions: 1 }))
+        .rejects.toThrow(ValidationError);
+    });
   });
 
   // =========================================================================
@@ -650,6 +1879,16 @@ describe('Strata', () => {
       expect(Array.isArray(results)).toBe(true);
     });
 
+    test('search with an AbortSignal', async () => {
+      const ac = new AbortController();
+      const results = await db.search('data', { signal: ac.signal });
+      expect(Array.isArray(results)).toBe(true);
+
+      ac.abort(new Error('client went away'));
+      await expect(db.search('data', { signal: ac.signal })).rejects.toThrow('client went away');
+      await expect(db.compact({ signal: ac.signal })).rejects.toThrow('client went away');
+    });
+
     test('search with mode', async () => {
       const results = await db.search('test', { mode: 'keyword' });
       expect(Array.isArray(results)).toBe(true);
@@ -679,6 +1918,26 @@ describe('Strata', () => {
     });
   });
 
+  // =========================================================================
+  // Tags
+  // =========================================================================
+
+  describe('Tags', () => {
+    test('create, list, delete', async () => {
+      const tag = await db.tagCreate('v1', { timestamp: 1000 });
+      expect(tag.timestamp).toBe(1000);
+      expect(tag.branch).toBe('default');
+      await db.tagCreate('v2');
+
+      const names = (await db.tagList()).map((t) => t.name);
+      expect(names).toEqual(['v1', 'v2']);
+      await expect(db.tagCreate('v1')).rejects.toThrow(StateError);
+
+      expect(await db.tagDelete('v1')).toBe(true);
+      expect(await db.tagDelete('v1')).toBe(false);
+    });
+  });
+
   // =========================================================================
   // Snapshot API — db.at(timestamp)
   // =========================================================================
@@ -735,6 +1994,54 @@ describe('Strata', () => {
       expect(() => snapshot.vector.batchUpsert('c', [])).toThrow(StateError);
     });
 
+    test('history and getVersioned are pinned to snapshot time', async () => {
+      await db.kv.set('snap_hist', 'v1');
+      await sleep(50);
+      await db.tagCreate('snap-hist');
+      await sleep(50);
+      await db.kv.set('snap_hist', 'v2');
+
+      const snapshot = db.at('snap-hist');
+      const history = await snapshot.kv.history('snap_hist');
+      expect(history.map((v) => v.value)).toEqual(['v1']);
+      expect((await snapshot.kv.getVersioned('snap_hist')).value).toBe('v1');
+      expect(await snapshot.timestamp()).toBeGreaterThan(0);
+    });
+
+    test('get at a write version', async () => {
+      const v1 = await db.kv.set('at_version', 'v1');
+      await sleep(50);
+      await db.kv.set('at_version', 'v2');
+
+      expect(await db.kv.get('at_version', { atVersion: v1 })).toBe('v1');
+      expect(await db.kv.get('at_version')).toBe('v2');
+      await expect(
+        db.kv.get('at_version', { atVersion: v1, asOf: 1 }),
+      ).rejects.toThrow(ValidationError);
+    });
+
+    test('diffTime reports changes between timestamps', async () => {
+      await db.kv.set('dt_kept', 1);
+      await db.kv.set('dt_changed', 1);
+      await db.kv.set('dt_removed', 1);
+      await sleep(50);
+      const before = (await db.timeRange()).latestTs;
+      await sleep(50);
+      await db.kv.set('dt_changed', 2);
+      await db.kv.delete('dt_removed');
+      await db.kv.set('dt_added', 1);
+      await db.state.set('other', 1);
+      await sleep(50);
+      const after = (await db.timeRange()).latestTs;
+
+      const diff = await db.diffTime(before, after, { prefix: 'dt_' });
+      expect(diff.summary).toEqual({ totalAdded: 1, totalRemoved: 1, totalModified: 1 });
+      const [space] = diff.spaces;
+      expect(space.added).toEqual([{ key: 'dt_added', primitive: 'kv' }]);
+      expect(space.removed).toEqual([{ key: 'dt_removed', primitive: 'kv' }]);
+      expect(space.modified).toEqual([{ key: 'dt_changed', primitive: 'kv' }]);
+    });
+
     test('state.get reads at snapshot time', async () => {
       await db.state.set('snap_state', 'old');
       await sleep(50);
@@ -774,184 +2081,1770 @@ describe('Strata', () => {
       expect(before).toBeNull();
     });
 
-    test('events.list reads at snapshot time', async () => {
-      await db.events.append('tt_etype', { n: 1 });
-      await sleep(50);
-      const range = await db.timeRange();
-      const ts = range.latestTs;
-      await sleep(50);
-      await db.events.append('tt_etype', { n: 2 });
+    test('events.list reads at snapshot time', async () => {
+      await db.events.append('tt_etype', { n: 1 });
+      await sleep(50);
+      const range = await db.timeRange();
+      const ts = range.latestTs;
+      await sleep(50);
+      await db.events.append('tt_etype', { n: 2 });
+
+      const current = await db.events.list('tt_etype');
+      expect(current.length).toBe(2);
+      const snapshot = db.at(ts);
+      const past = await snapshot.events.list('tt_etype');
+      expect(past.length).toBe(1);
+    });
+
+    test('json.get reads at snapshot time', async () => {
+      await db.json.set('snap_json', '$', { v: 1 });
+      await sleep(50);
+      const range = await db.timeRange();
+      const ts = range.latestTs;
+      await sleep(50);
+      await db.json.set('snap_json', '$', { v: 2 });
+
+      const snapshot = db.at(ts);
+      const val = await snapshot.json.get('snap_json', '$');
+      expect(val.v).toBe(1);
+    });
+
+    test('json.keys reads at snapshot time', async () => {
+      await db.json.set('ttj_a', '$', { x: 1 });
+      await sleep(50);
+      const range = await db.timeRange();
+      const ts = range.latestTs;
+      await sleep(50);
+      await db.json.set('ttj_b', '$', { x: 2 });
+
+      const snapshot = db.at(ts);
+      const past = await snapshot.json.keys({ prefix: 'ttj_' });
+      expect(past.keys.length).toBe(1);
+    });
+
+    test('vector.search reads at snapshot time', async () => {
+      await db.vector.createCollection('snap_vec', { dimension: 4 });
+      await db.vector.upsert('snap_vec', 'a', [1, 0, 0, 0]);
+      await sleep(50);
+      const range = await db.timeRange();
+      const ts = range.latestTs;
+      await sleep(50);
+      await db.vector.upsert('snap_vec', 'b', [0, 1, 0, 0]);
+
+      const snapshot = db.at(ts);
+      const results = await snapshot.vector.search('snap_vec', [1, 0, 0, 0], { limit: 10 });
+      expect(results.length).toBe(1);
+    });
+
+    test('vector.get reads at snapshot time', async () => {
+      await db.vector.createCollection('tt_vget', { dimension: 4 });
+      await db.vector.upsert('tt_vget', 'k', [1, 0, 0, 0], { metadata: { tag: 'v1' } });
+      await sleep(50);
+      const range = await db.timeRange();
+      const ts = range.latestTs;
+      await sleep(50);
+      await db.vector.upsert('tt_vget', 'k', [0, 1, 0, 0], { metadata: { tag: 'v2' } });
+
+      const current = await db.vector.get('tt_vget', 'k');
+      expect(current.metadata.tag).toBe('v2');
+      const snapshot = db.at(ts);
+      const past = await snapshot.vector.get('tt_vget', 'k');
+      expect(past).not.toBeNull();
+      expect(past.metadata.tag).toBe('v1');
+    });
+
+    test('timeRange returns oldest and latest', async () => {
+      await db.kv.set('tr_key', 'val');
+      const range = await db.timeRange();
+      expect(range).toHaveProperty('oldestTs');
+      expect(range).toHaveProperty('latestTs');
+      expect(typeof range.oldestTs).toBe('number');
+      expect(typeof range.latestTs).toBe('number');
+      expect(range.latestTs).toBeGreaterThanOrEqual(range.oldestTs);
+    });
+
+    test('StrataSnapshot is exported', () => {
+      expect(StrataSnapshot).toBeDefined();
+      const snapshot = db.at(12345);
+      expect(snapshot).toBeInstanceOf(StrataSnapshot);
+    });
+  });
+
+  // =========================================================================
+  // Transaction callback — db.transaction()
+  // =========================================================================
+
+  describe('db.transaction()', () => {
+    test('auto-commits on success', async () => {
+      await db.kv.set('pre', 'before');
+      await db.transaction(async (tx) => {
+        await tx.kv.set('tx_key', 'tx_value');
+      });
+      expect(await db.kv.get('tx_key')).toBe('tx_value');
+    });
+
+    test('auto-rollback on error', async () => {
+      await expect(
+        db.transaction(async (tx) => {
+          throw new Error('intentional');
+        }),
+      ).rejects.toThrow('intentional');
+      // After rollback, no transaction should be active
+      expect(await db.txnIsActive()).toBe(false);
+    });
+
+    test('returns result from callback', async () => {
+      const result = await db.transaction(async (tx) => {
+        await tx.kv.set('r_key', 'r_val');
+        return 42;
+      });
+      expect(result).toBe(42);
+    });
+
+    test('read-only transaction', async () => {
+      await db.kv.set('ro_key', 'ro_val');
+      const result = await db.transaction(
+        async (tx) => {
+          const val = await tx.kv.get('ro_key');
+          return val;
+        },
+        { readOnly: true },
+      );
+      expect(result).toBe('ro_val');
+    });
+  });
+
+  // =========================================================================
+  // Migrations
+  // =========================================================================
+
+  describe('Migrations', () => {
+    const migrations = [
+      {
+        id: '001',
+        up: (tx) => tx.kv.set('schema', 1),
+        down: (tx) => tx.kv.delete('schema'),
+      },
+      {
+        id: '002',
+        up: (tx) => tx.kv.set('schema', 2),
+        down: (tx) => tx.kv.set('schema', 1),
+      },
+    ];
+
+    test('applies pending migrations once and reverts with down', async () => {
+      expect(await db.migrate(migrations, { dryRun: true }))
+        .toEqual({ applied: ['001', '002'], reverted: [] });
+      expect(await db.kv.get('schema')).toBeNull();
+      expect(await db.migrate(migrations)).toEqual({ applied: ['001', '002'], reverted: [] });
+      expect(await db.migrate(migrations)).toEqual({ applied: [], reverted: [] });
+      expect(await db.kv.get('schema')).toBe(2);
+      expect(await db.migrate(migrations, { to: '001' })).toEqual({ applied: [], reverted: ['002'] });
+      expect(await db.kv.get('schema')).toBe(1);
+      expect(await db.migrate(migrations, { to: null })).toEqual({ applied: [], reverted: ['001'] });
+      expect(await db.kv.get('schema')).toBeNull();
+    });
+
+    test('rolls back a failing migration and stops', async () => {
+      const failing = [
+        { id: '001', up: migrations[0].up },
+        { id: '002', up: async (tx) => { await tx.kv.set('half', true); throw new Error('boom'); } },
+        { id: '003', up: (tx) => tx.kv.set('late', true) },
+      ];
+      await expect(db.migrate(failing)).rejects.toThrow('boom');
+      expect(await db.kv.get('half')).toBeNull();
+      expect(await db.kv.get('late')).toBeNull();
+      expect(await db.migrate(failing, { dryRun: true }))
+        .toEqual({ applied: ['002', '003'], reverted: [] });
+      await expect(db.migrate(failing, { to: null })).rejects.toThrow(ValidationError);
+    });
+  });
+
+  // =========================================================================
+  // Errors
+  // =========================================================================
+
+  describe('Errors', () => {
+    test('NotFoundError with correct hierarchy and code', async () => {
+      try {
+        await db.vector.search('no_such_collection', [1, 0, 0, 0], { limit: 1 });
+        fail('Expected error');
+      } catch (err) {
+        expect(err).toBeInstanceOf(NotFoundError);
+        expect(err).toBeInstanceOf(StrataError);
+        expect(err).toBeInstanceOf(Error);
+        expect(err.code).toBe('NOT_FOUND');
+        // negative checks — not other subclasses
+        expect(err instanceof ValidationError).toBe(false);
+        expect(err instanceof ConflictError).toBe(false);
+      }
+    });
+
+    test('ValidationError on invalid metric', async () => {
+      try {
+        await db.vector.createCollection('x', { dimension: 4, metric: 'invalid_metric' });
+        fail('Expected error');
+      } catch (err) {
+        expect(err).toBeInstanceOf(ValidationError);
+        expect(err.code).toBe('VALIDATION');
+      }
+    });
+
+    test('ConstraintError on dimension mismatch', async () => {
+      await db.vector.createCollection('dim_test', { dimension: 4 });
+      try {
+        await db.vector.upsert('dim_test', 'k', [1, 0]); // wrong dimension
+        fail('Expected error');
+      } catch (err) {
+        expect(err).toBeInstanceOf(ConstraintError);
+        expect(err.code).toBe('CONSTRAINT');
+      }
+    });
+  });
+
+  // =========================================================================
+  // Batch KV — db.kv.batchPut
+  // =========================================================================
+
+  describe('Timeouts', () => {
+    let dir;
+    let timed;
+
+    beforeEach(() => {
+      dir = fs.mkdtempSync(path.join(os.tmpdir(), 'strata-timeout-'));
+      timed = Strata.open(dir, { timeoutMs: 0 });
+    });
+
+    afterEach(async () => {
+      await timed.close().catch(() => {});
+      fs.rmSync(dir, { recursive: true, force: true });
+    });
+
+    test('default timeoutMs rejects with TimeoutError', async () => {
+      const entries = Array.from({ length: 20000 }, (_, i) => ({ key: `k${i}`, value: i }));
+      const err = await timed.kv.batchPut(entries).catch((e) => e);
+      expect(err).toBeInstanceOf(TimeoutError);
+      expect(err).toBeInstanceOf(StrataError);
+      expect(err.code).toBe('TIMEOUT');
+    });
+
+    test('per-call timeoutMs overrides the default', async () => {
+      const results = await timed.search('anything', { timeoutMs: 60000 });
+      expect(Array.isArray(results)).toBe(true);
+    });
+  });
+
+  describe('Backpressure', () => {
+    let dir;
+
+    beforeEach(() => {
+      dir = fs.mkdtempSync(path.join(os.tmpdir(), 'strata-backpressure-'));
+    });
+
+    afterEach(() => {
+      fs.rmSync(dir, { recursive: true, force: true });
+    });
+
+    test('calls past maxQueuedOps fail with ResourceExhaustedError', async () => {
+      const limited = Strata.open(dir, { maxConcurrentOps: 1, maxQueuedOps: 0 });
+      const entries = Array.from({ length: 2000 }, (_, i) => ({ key: `k${i}`, value: i }));
+      const results = await Promise.allSettled(
+        Array.from({ length: 20 }, () => limited.kv.batchPut(entries)),
+      );
+      const rejected = results.filter((r) => r.status === 'rejected');
+      expect(rejected.length).toBeGreaterThan(0);
+      for (const r of rejected) {
+        expect(r.reason).toBeInstanceOf(ResourceExhaustedError);
+      }
+      const stats = await limited.poolStats();
+      expect(stats.size).toBe(1);
+      expect(stats.rejected).toBe(rejected.length);
+      await limited.close();
+    });
+
+    test('close is not turned away by maxQueuedOps', async () => {
+      const limited = Strata.open(dir, { maxConcurrentOps: 1, maxQueuedOps: 0 });
+      const entries = Array.from({ length: 2000 }, (_, i) => ({ key: `k${i}`, value: i }));
+      const busy = limited.kv.batchPut(entries);
+      await limited.close();
+      const wrote = await busy.then(() => true, () => false);
+      const reopened = Strata.open(dir);
+      expect(await reopened.kv.get('k1999')).toBe(wrote ? 1999 : null);
+      await reopened.close();
+    });
+
+    test('maxQueuedOps requires maxConcurrentOps', () => {
+      expect(() => Strata.open(dir, { maxQueuedOps: 1 })).toThrow(ValidationError);
+    });
+  });
+
+  describe('Durability', () => {
+    test('syncMode always syncs every write', async () => {
+      const dir = fs.mkdtempSync(path.join(os.tmpdir(), 'strata-sync-'));
+      try {
+        const synced = Strata.open(dir, { syncMode: 'always' });
+        const before = (await synced.durabilityCounters()).syncCalls;
+        await synced.kv.set('a', 1);
+        await synced.kv.set('b', 2);
+        expect((await synced.durabilityCounters()).syncCalls).toBeGreaterThanOrEqual(before + 2);
+        await synced.close();
+      } finally {
+        fs.rmSync(dir, { recursive: true, force: true });
+      }
+    });
+
+    test('durable writes resolve after a sync', async () => {
+      const dir = fs.mkdtempSync(path.join(os.tmpdir(), 'strata-durable-'));
+      try {
+        const handle = Strata.open(dir);
+        const before = (await handle.durabilityCounters()).syncCalls;
+        await handle.kv.set('a', 1, { durable: true });
+        expect((await handle.durabilityCounters()).syncCalls).toBeGreaterThan(before);
+        await handle.close();
+      } finally {
+        fs.rmSync(dir, { recursive: true, force: true });
+      }
+    });
+
+    test('walStats has no WAL files for an in-memory database', async () => {
+      const stats = await db.walStats();
+      expect(stats.sizeBytes).toBeNull();
+      expect(stats.segments).toBeNull();
+    });
+
+    test('walStats records the last flush', async () => {
+      const dir = fs.mkdtempSync(path.join(os.tmpdir(), 'strata-wal-'));
+      try {
+        const handle = Strata.open(dir);
+        expect((await handle.walStats()).lastFlushAt).toBeNull();
+        await handle.kv.set('a', 1);
+        await handle.flush();
+        const stats = await handle.walStats();
+        expect(stats.appends).toBeGreaterThan(0);
+        expect(stats.lastFlushAt).toBeGreaterThan(0);
+        expect(stats.sizeBytes).toBeGreaterThanOrEqual(0);
+        expect(stats.segments).toBeGreaterThanOrEqual(0);
+        await handle.close();
+      } finally {
+        fs.rmSync(dir, { recursive: true, force: true });
+      }
+    });
+
+    test('rejects invalid sync options', () => {
+      expect(() => Strata.open('/tmp/unused', { syncMode: 'sometimes' })).toThrow(ValidationError);
+      expect(() => Strata.open('/tmp/unused', { syncIntervalMs: 10 })).toThrow(ValidationError);
+    });
+  });
+
+  describe('Compaction', () => {
+    test('reports duration and size, and cannot be scoped', async () => {
+      const dir = fs.mkdtempSync(path.join(os.tmpdir(), 'strata-compact-'));
+      try {
+        const handle = Strata.open(dir);
+        await handle.kv.set('a', 1);
+        const result = await handle.compact();
+        expect(result.durationMs).toBeGreaterThanOrEqual(0);
+        expect(result.diskBytesBefore).toBeGreaterThan(0);
+        expect(typeof result.diskBytesAfter).toBe('number');
+        await expect(handle.compact({ space: 'default' })).rejects.toThrow(ValidationError);
+        await handle.close();
+      } finally {
+        fs.rmSync(dir, { recursive: true, force: true });
+      }
+    });
+
+    test('compacts in the background', async () => {
+      const dir = fs.mkdtempSync(path.join(os.tmpdir(), 'strata-compact-'));
+      try {
+        const handle = Strata.open(dir, { compactionMode: 'interval', compactionIntervalMs: 20 });
+        const compacted = new Promise((resolve) => handle.once('compactionEnd', resolve));
+        await handle.kv.set('a', 1);
+        expect((await compacted).error).toBeUndefined();
+        await handle.close();
+
+        const amplified = Strata.open(dir, {
+          compactionMode: 'writeAmplification',
+          compactionIntervalMs: 20,
+          compactionTarget: 0.001,
+        });
+        const again = new Promise((resolve) => amplified.once('compactionEnd', resolve));
+        await amplified.kv.set('b', 'x'.repeat(10_000));
+        await again;
+        await amplified.close();
+      } finally {
+        fs.rmSync(dir, { recursive: true, force: true });
+      }
+    });
+
+    test('rejects invalid compaction options', () => {
+      const open = (options) => () => Strata.open('/tmp/unused', options);
+      expect(open({ compactionMode: 'nightly' })).toThrow(ValidationError);
+      expect(open({ compactionIntervalMs: 10 })).toThrow(ValidationError);
+      expect(open({ compactionMode: 'interval', compactionTarget: 2 })).toThrow(ValidationError);
+      expect(open({ compactionMode: 'writeAmplification', compactionTarget: 0 })).toThrow(ValidationError);
+    });
+  });
+
+  describe('JSON limits', () => {
+    const nested = (depth) => {
+      let value = 'leaf';
+      for (let i = 0; i < depth; i++) {
+        value = { child: value };
+      }
+      return value;
+    };
+
+    test('default depth limit is 64', async () => {
+      const handle = Strata.cache();
+      await handle.kv.set('ok', nested(64));
+      await expect(handle.kv.set('deep', nested(100))).rejects.toThrow(ValidationError);
+      await handle.close();
+    });
+
+    test('open options and per-call options raise the limits', async () => {
+      const dir = fs.mkdtempSync(path.join(os.tmpdir(), 'strata-limits-'));
+      try {
+        const handle = Strata.open(dir, { jsonLimits: { maxDepth: 200 } });
+        await handle.json.set('doc', '$', nested(150));
+        expect(await handle.json.get('doc', '$')).toEqual(nested(150));
+        await expect(handle.kv.set('deeper', nested(300))).rejects.toThrow(ValidationError);
+        await handle.kv.set('deeper', nested(300), { jsonLimits: { maxDepth: 400 } });
+        await handle.close();
+      } finally {
+        fs.rmSync(dir, { recursive: true, force: true });
+      }
+    });
+
+    test('size and element limits', async () => {
+      const handle = Strata.cache();
+      const small = { jsonLimits: { maxValueBytes: 16 } };
+      await expect(handle.kv.set('big', 'x'.repeat(17), small)).rejects.toThrow(ValidationError);
+      await expect(
+        handle.state.set('list', [1, 2, 3], { jsonLimits: { maxElements: 2 } }),
+      ).rejects.toThrow(ValidationError);
+      await expect(
+        handle.kv.batchPut([{ key: 'b', value: 'x'.repeat(17) }], small),
+      ).rejects.toThrow(ValidationError);
+      expect(() => Strata.open('/tmp/unused', { jsonLimits: { maxDepth: 5000 } })).toThrow(
+        ValidationError,
+      );
+      await handle.close();
+    });
+  });
+
+  describe('Metrics', () => {
+    test('counts calls, errors, and latency per family', async () => {
+      const handle = Strata.cache();
+      await handle.kv.set('a', 1);
+      await handle.kv.get('a');
+      await handle.json.set('doc', '$', { n: 1 });
+      await expect(handle.kv.set('deep', JSON.parse('['.repeat(100) + ']'.repeat(100)))).rejects.toThrow(
+        ValidationError,
+      );
+      await handle.compact();
+
+      const metrics = await handle.metrics();
+      expect(metrics.calls).toBe(5);
+      expect(metrics.errors).toBe(1);
+      expect(metrics.errorsByCode).toEqual({ VALIDATION: 1 });
+      expect(metrics.compactions).toBe(1);
+      expect(metrics.families.kv.calls).toBe(3);
+      expect(metrics.families.kv.errors).toBe(1);
+      expect(metrics.families.json.calls).toBe(1);
+      expect(metrics.families.kv.p50Ms).toBeLessThanOrEqual(metrics.families.kv.p99Ms);
+      expect(metrics.families.kv.p99Ms).toBeLessThanOrEqual(metrics.families.kv.maxMs);
+
+      const text = await handle.metricsPrometheus({ labels: { db: 'test' } });
+      expect(text).toContain('# TYPE strata_call_duration_seconds histogram');
+      expect(text).toContain('strata_calls_total{db="test",family="kv"} 3');
+      expect(text).toContain('strata_errors_total{db="test",code="VALIDATION"} 1');
+      expect(text).toContain('strata_call_duration_seconds_bucket{db="test",family="json",le="+Inf"} 1');
+      expect(text).toContain('strata_compactions_total{db="test"} 1');
+
+      await handle.metricsReset();
+      const reset = await handle.metrics();
+      expect(reset.calls).toBe(0);
+      expect(reset.families).toEqual({});
+      await handle.close();
+    });
+  });
+
+  describe('Tracing', () => {
+    test('tracer sees each call with its attributes and outcome', async () => {
+      const handle = Strata.cache();
+      const spans = [];
+      handle.setTracer((operation, attributes) => {
+        const span = { operation, attributes };
+        spans.push(span);
+        return (outcome) => {
+          span.outcome = outcome;
+        };
+      });
+      await handle.kv.batchPut([{ key: 'a', value: 1 }, { key: 'b', value: 2 }]);
+      await handle.kv.get('missing');
+      await expect(handle.kv.set('deep', JSON.parse('['.repeat(100) + ']'.repeat(100)))).rejects.toThrow(
+        ValidationError,
+      );
+      handle.setTracer(null);
+      await handle.ping();
+
+      expect(spans.map((s) => s.operation)).toEqual(['kvBatchPut', 'kvGet', 'kvPut']);
+      expect(spans[0].attributes).toEqual({
+        'strata.operation': 'kvBatchPut',
+        'strata.branch': 'default',
+        'strata.space': 'default',
+        'strata.key_count': 2,
+      });
+      expect(spans[1].outcome.durationMs).toBeGreaterThanOrEqual(0);
+      expect(spans[1].outcome.error).toBeUndefined();
+      expect(spans[2].outcome.error).toBeInstanceOf(ValidationError);
+      await handle.close();
+    });
+
+    test('a throwing tracer does not fail the call', async () => {
+      const handle = Strata.cache();
+      const warned = new Promise((resolve) => process.once('warning', resolve));
+      handle.setTracer(() => {
+        throw new Error('tracer bug');
+      });
+      await handle.kv.set('a', 1);
+      expect((await warned).message).toBe('tracer bug');
+      await handle.close();
+    });
+  });
+
+  describe('Slow operation log', () => {
+    test('reports calls over the threshold until unsubscribed', async () => {
+      const handle = Strata.cache();
+      const slow = [];
+      const unsubscribe = handle.onSlowOp(0, (op) => slow.push(op));
+      const ignored = handle.onSlowOp(60 * 60 * 1000, () => {
+        throw new Error('not slow enough');
+      });
+      await handle.kv.set('user:1', { name: 'Ada', email: 'ada@example.com' });
+      await handle.kv.batchPut([{ key: 'a', value: 1 }]);
+      unsubscribe();
+      ignored();
+      await handle.kv.get('user:1');
+
+      expect(slow.map((s) => s.op)).toEqual(['kvPut', 'kvBatchPut']);
+      expect(slow[0]).toMatchObject({
+        args: ['user:1', '{name, email}', undefined],
+        branch: 'default',
+        space: 'default',
+      });
+      expect(slow[0].durationMs).toBeGreaterThanOrEqual(0);
+      expect(slow[1].args[0]).toBe('[1 items]');
+      expect(() => handle.onSlowOp(-1, () => {})).toThrow(ValidationError);
+      await handle.close();
+    });
+  });
+
+  describe('Lifecycle events', () => {
+    test('emits events for calls on the handle', async () => {
+      const handle = Strata.cache();
+      const events = [];
+      for (const event of ['flush', 'compactionStart', 'compactionEnd', 'branchCreated', 'branchDeleted']) {
+        handle.on(event, (payload) => events.push([event, payload]));
+      }
+      const onFlush = () => {
+        throw new Error('listener bug');
+      };
+      handle.once('flush', onFlush);
+      await handle.flush();
+      await handle.compact();
+      await handle.createBranch('feature');
+      await handle.deleteBranch('feature');
+
+      expect(events.map(([event]) => event)).toEqual([
+        'flush', 'compactionStart', 'compactionEnd', 'branchCreated', 'branchDeleted',
+      ]);
+      expect(events[2][1].durationMs).toBeGreaterThanOrEqual(0);
+      expect(events[3][1]).toEqual({ branch: 'feature' });
+      await handle.close();
+    });
+  });
+
+  describe('Logger', () => {
+    afterEach(() => Strata.setLogger(null));
+
+    test('routes native records by level', async () => {
+      const dir = fs.mkdtempSync(path.join(os.tmpdir(), 'strata-log-'));
+      const records = [];
+      Strata.setLogger({ level: 'info', sink: (...record) => records.push(record) });
+      try {
+        const handle = Strata.open(dir);
+        await handle.close();
+        // Native records arrive on the next turns of the event loop.
+        await new Promise((resolve) => setTimeout(resolve, 50));
+        expect(records.map(([level, message]) => [level, message])).toEqual([
+          ['info', 'opened database'],
+          ['info', 'closed database'],
+        ]);
+        expect(records[0][2]).toEqual({ path: fs.realpathSync(dir), accessMode: 'readWrite' });
+
+        records.length = 0;
+        Strata.setLogger({ level: 'warn', sink: (...record) => records.push(record) });
+        const quiet = Strata.open(dir);
+        await quiet.close();
+        await new Promise((resolve) => setTimeout(resolve, 50));
+        expect(records).toEqual([]);
+      } finally {
+        fs.rmSync(dir, { recursive: true, force: true });
+      }
+    });
+
+    test('rejects invalid options', () => {
+      expect(() => Strata.setLogger({ level: 'verbose', sink: () => {} })).toThrow(ValidationError);
+      expect(() => Strata.setLogger({ level: 'info' })).toThrow(ValidationError);
+    });
+  });
+
+  describe('Trace mode', () => {
+    afterEach(() => Strata.setLogger(null));
+
+    test('reports every call with its timing', async () => {
+      const dir = fs.mkdtempSync(path.join(os.tmpdir(), 'strata-trace-'));
+      const traced = [];
+      try {
+        const handle = Strata.open(dir, { trace: (record) => traced.push(record) });
+        await handle.kv.set('user:1', { name: 'Ada' });
+        await handle.execute('kv_get', { key: 'user:1' });
+        await expect(handle.execute('no_such_command', {})).rejects.toThrow();
+        await handle.close();
+
+        expect(traced.map((t) => t.op)).toEqual([
+          'kvPut', 'execute:kv_get', 'execute:no_such_command', 'close',
+        ]);
+        expect(traced[0]).toMatchObject({ args: ['user:1', '{name}', undefined], branch: 'default' });
+        expect(traced[0].durationMs).toBeGreaterThanOrEqual(0);
+        expect(traced[1].args).toEqual(['{key}']);
+        expect(traced[1].error).toBeUndefined();
+        expect(traced[2].error).toBeInstanceOf(Error);
+      } finally {
+        fs.rmSync(dir, { recursive: true, force: true });
+      }
+    });
+
+    test('trace: true logs at debug level', async () => {
+      const dir = fs.mkdtempSync(path.join(os.tmpdir(), 'strata-trace-'));
+      const records = [];
+      Strata.setLogger({ level: 'debug', sink: (...record) => records.push(record) });
+      try {
+        const handle = Strata.open(dir, { trace: true });
+        await handle.kv.get('missing');
+        await handle.close();
+        const traces = records.filter(([, , fields]) => fields?.op != null);
+        expect(traces[0][0]).toBe('debug');
+        expect(traces[0][1]).toMatch(/^kvGet [\d.]+ ms$/);
+        expect(() => Strata.open(dir, { trace: 'yes' })).toThrow(ValidationError);
+      } finally {
+        fs.rmSync(dir, { recursive: true, force: true });
+      }
+    });
+  });
+
+  describe('Backup', () => {
+    test('full and incremental backups restore into a new database', async () => {
+      const dir = fs.mkdtempSync(path.join(os.tmpdir(), 'strata-backup-'));
+      try {
+        const source = Strata.open(path.join(dir, 'db'));
+        await source.kv.set('a', 1);
+        await source.branch.create('feature');
+        await source.branch.setMetadata('feature', { description: 'wip' });
+        await source.branch.switch('feature');
+        await source.kv.set('f', 'one');
+
+        const full = await source.backup(path.join(dir, 'full'));
+        expect(full).toMatchObject({ incremental: false, branches: 2 });
+        await expect(source.backup(path.join(dir, 'full'))).rejects.toThrow(StateError);
+
+        const system = await source.systemBranch();
+        expect(await system.kvList('backup/')).toEqual([]);
+
+        await source.kv.set('f', 'two');
+        await source.branch.switch('default');
+        await source.kv.set('b', 2);
+        await source.close();
+
+        // A read-only handle can take the incremental backup.
+        const reader = Strata.open(path.join(dir, 'db'), { readOnly: true });
+        const inc = await reader.backup(path.join(dir, 'inc'), {
+          incrementalFrom: path.join(dir, 'full'),
+        });
+        expect(inc.incremental).toBe(true);
+        expect(inc.version).toBeGreaterThan(full.version);
+        await reader.close();
+
+        const restored = await Strata.restore(path.join(dir, 'inc'), path.join(dir, 'restored'));
+        expect(restored).toMatchObject({ backups: 2, branches: 2 });
+        const db2 = Strata.open(path.join(dir, 'restored'));
+        expect(await db2.kv.get('a')).toBe(1);
+        expect(await db2.kv.get('b')).toBe(2);
+        expect((await db2.branch.get('feature')).description).toBe('wip');
+        await db2.branch.switch('feature');
+        expect(await db2.kv.get('f')).toBe('two');
+        await db2.close();
+
+        await expect(
+          Strata.restore(path.join(dir, 'full'), path.join(dir, 'restored')),
+        ).rejects.toThrow();
+      } finally {
+        fs.rmSync(dir, { recursive: true, force: true });
+      }
+    });
+
+    test('rewinds a restore inside the backup', async () => {
+      const dir = fs.mkdtempSync(path.join(os.tmpdir(), 'strata-pitr-'));
+      try {
+        const source = Strata.open(path.join(dir, 'db'));
+        await source.kv.set('a', 1);
+        await source.json.set('doc', '$', { n: 1 });
+        const { timestamp } = await source.kv.getVersioned('a');
+        await new Promise((r) => setTimeout(r, 50));
+        await source.kv.set('a', 2);
+        await source.kv.set('late', true);
+        await source.json.set('doc', '$', { n: 2 });
+        await source.branch.create('later');
+        await source.backup(path.join(dir, 'full'));
+        await source.close();
+
+        const result = await Strata.restore(path.join(dir, 'full'), path.join(dir, 'pitr'), {
+          rewindTo: timestamp,
+        });
+        expect(result).toMatchObject({ rewoundTo: timestamp, branches: 1 });
+        const db2 = Strata.open(path.join(dir, 'pitr'));
+        expect(await db2.kv.get('a')).toBe(1);
+        expect(await db2.kv.get('late')).toBeNull();
+        expect(await db2.json.get('doc', '$')).toEqual({ n: 1 });
+        expect(await db2.branch.exists('later')).toBe(false);
+        await db2.close();
+
+        await expect(
+          Strata.restore(path.join(dir, 'full'), path.join(dir, 'future'), {
+            rewindTo: (Date.now() + 60_000) * 1000,
+          }),
+        ).rejects.toThrow(ValidationError);
+      } finally {
+        fs.rmSync(dir, { recursive: true, force: true });
+      }
+    });
+  });
+
+  describe('Dump and load', () => {
+    test('round-trips KV, state, and JSON entries through NDJSON', async () => {
+      const dir = fs.mkdtempSync(path.join(os.tmpdir(), 'strata-dump-'));
+      try {
+        const source = Strata.open(path.join(dir, 'db'));
+        await source.kv.set('user:1', { name: 'ada' });
+        await source.kv.set('user:2', Buffer.from([1, 2, 3]));
+        await source.kv.set('other', 1);
+        await source.state.set('cell', 'on');
+        await source.space.create('archive');
+        await source.space.switch('archive');
+        await source.json.set('doc', '$', { n: 1 });
+        await source.space.switch('default');
+
+        const file = path.join(dir, 'all.ndjson');
+        const progress = [];
+        const dumped = await source.dump(file, { onProgress: (p) => progress.push(p) });
+        expect(dumped).toEqual({ entries: 5, spaces: 2 });
+        expect(progress.length).toBeGreaterThan(0);
+        const lines = fs.readFileSync(file, 'utf8').trim().split('\n').map((l) => JSON.parse(l));
+        expect(lines).toContainEqual({
+          space: 'default', primitive: 'kv', key: 'user:1', value: { name: 'ada' },
+        });
+        expect(lines).toContainEqual({
+          space: 'archive', primitive: 'json', key: 'doc', value: { n: 1 },
+        });
+
+        const users = path.join(dir, 'users.ndjson');
+        expect((await source.dump(users, { prefix: 'user:', primitive: 'kv' })).entries).toBe(2);
+        await source.close();
+
+        const target = Strata.open(path.join(dir, 'target'));
+        const loaded = await target.load(file, { batchSize: 2 });
+        expect(loaded).toEqual({ entries: 5, lines: 5 });
+        expect(await target.kv.get('user:1')).toEqual({ name: 'ada' });
+        expect(Buffer.from(await target.kv.get('user:2'))).toEqual(Buffer.from([1, 2, 3]));
+        expect(await target.state.get('cell')).toBe('on');
+        await target.space.switch('archive');
+        expect(await target.json.get('doc', '$')).toEqual({ n: 1 });
+
+        const bad = path.join(dir, 'bad.ndjson');
+        fs.writeFileSync(bad, '{"primitive":"kv","key":"k","value":1}\nnot json\n');
+        await expect(target.load(bad)).rejects.toThrow(ValidationError);
+        await expect(target.dump(file, { primitive: 'vector' })).rejects.toThrow(ValidationError);
+        await target.close();
+      } finally {
+        fs.rmSync(dir, { recursive: true, force: true });
+      }
+    });
+  });
+
+  describe('Fixtures', () => {
+    test('loads a fixture and captures it back', async () => {
+      const fixture = {
+        kv: { 'user:1': { name: 'ada' }, blob: { $base64: 'AQID' } },
+        state: { status: 'ready' },
+        json: { 'doc:1': { title: 'Hello' } },
+        events: [
+          { type: 'signup', payload: { user: 1 } },
+          { type: 'login', payload: { user: 1 } },
+          { type: 'signup', payload: { user: 2 } },
+        ],
+        vectors: {
+          docs: {
+            dimension: 3,
+            metric: 'euclidean',
+            entries: [
+              { key: 'd1', vector: [1, 0, 0], metadata: { tag: 'a' } },
+              { key: 'd2', vector: [0, 1, 0] },
+            ],
+          },
+        },
+      };
+      const loaded = await Strata.fromFixture(JSON.stringify(fixture));
+      try {
+        expect(await loaded.kv.get('user:1')).toEqual({ name: 'ada' });
+        expect(Buffer.from(await loaded.kv.get('blob'))).toEqual(Buffer.from([1, 2, 3]));
+        expect(await loaded.events.count()).toBe(3);
+        expect((await loaded.vector.get('docs', 'd1')).metadata).toEqual({ tag: 'a' });
+        const captured = await loaded.toFixture({ eventTypes: ['signup', 'login'] });
+        expect(captured).toEqual(fixture);
+      } finally {
+        await loaded.close();
+      }
+      await expect(Strata.fromFixture({ kvs: {} })).rejects.toThrow(ValidationError);
+      await expect(Strata.fromFixture('./no-such-fixture.json')).rejects.toThrow(NotFoundError);
+    });
+  });
+
+  describe('Changelog', () => {
+    test('replicates writes and deletes to another database', async () => {
+      const dir = fs.mkdtempSync(path.join(os.tmpdir(), 'strata-changelog-'));
+      try {
+        const primary = Strata.open(path.join(dir, 'primary'));
+        const replica = Strata.open(path.join(dir, 'replica'));
+        await primary.kv.set('a', 1);
+        await primary.kv.set('gone', true);
+        await primary.branch.create('feature');
+        await primary.branch.switch('feature');
+        await primary.json.set('doc', '$', { n: 1 });
+        await primary.branch.switch('default');
+
+        const records = [];
+        const first = await primary.changelogExport(0, (r) => records.push(r));
+        expect(first.records).toBe(3);
+        const versions = records.map((r) => r.version);
+        expect(versions).toEqual([...versions].sort((x, y) => x - y));
+        expect(records).toContainEqual(expect.objectContaining({
+          branch: 'feature', primitive: 'json', key: 'doc', op: 'put', value: { n: 1 },
+        }));
+        expect(await replica.changelogApply(records)).toEqual({ applied: 3, version: first.version });
+
+        await primary.kv.set('a', 2);
+        await primary.kv.delete('gone');
+        const lines = new PassThrough();
+        const second = await primary.changelogExport(first.version, lines);
+        lines.end();
+        expect(second.records).toBe(2);
+        expect((await replica.changelogApply(lines)).applied).toBe(2);
+
+        expect(await replica.kv.get('a')).toBe(2);
+        expect(await replica.kv.get('gone')).toBeNull();
+        await replica.branch.switch('feature');
+        expect(await replica.json.get('doc', '$')).toEqual({ n: 1 });
+        await primary.close();
+        await replica.close();
+      } finally {
+        fs.rmSync(dir, { recursive: true, force: true });
+      }
+    });
+
+    test('reads in pages and refuses events', async () => {
+      await db.kv.set('a', 1);
+      await db.kv.set('b', 2);
+      await db.kv.set('c', 3);
+      const first = await db.changelogRead(0, 0, 2);
+      expect(first.records.map((r) => r.key)).toEqual(['a', 'b']);
+      expect(first.more).toBe(true);
+      const rest = await db.changelogRead(0, first.version, 2);
+      expect(rest.records.map((r) => r.key)).toEqual(['c']);
+      expect(rest.more).toBe(false);
+      await expect(db.changelogRead(1, 0)).rejects.toThrow(ValidationError);
+
+      await db.events.append('signup', { user: 1 });
+      await expect(db.changelogExport(0, () => {})).rejects.toThrow(ConstraintError);
+    });
+  });
+
+  describe('Sync', () => {
+    test('exchanges changes and reports conflicts', async () => {
+      const dir = fs.mkdtempSync(path.join(os.tmpdir(), 'strata-sync-'));
+      try {
+        const local = Strata.open(path.join(dir, 'local'));
+        const central = Strata.open(path.join(dir, 'central'));
+        await local.kv.set('mine', 1);
+        await central.kv.set('theirs', 2);
+        await local.kv.set('both', 'a');
+        await central.kv.set('both', 'b');
+
+        const first = await local.syncTo(central);
+        expect(first).toMatchObject({ pushed: 1, pulled: 1 });
+        expect(first.conflicts).toEqual([
+          { branch: 'default', space: 'default', primitive: 'kv', key: 'both' },
+        ]);
+        expect(await central.kv.get('mine')).toBe(1);
+        expect(await local.kv.get('theirs')).toBe(2);
+
+        await central.kv.set('both', 'a');
+        await local.kv.set('mine', 10);
+        await central.kv.delete('theirs');
+        const second = await local.syncTo(central);
+        expect(second).toMatchObject({ pushed: 1, pulled: 1, conflicts: [] });
+        expect(await central.kv.get('mine')).toBe(10);
+        expect(await local.kv.get('theirs')).toBeNull();
+
+        await local.kv.set('mine', 11);
+        expect(await local.syncTo(central, { mode: 'pull' })).toMatchObject({ pushed: 0, pulled: 0 });
+        expect(await central.kv.get('mine')).toBe(10);
+        await expect(local.syncTo(central, { mode: 'sideways' })).rejects.toThrow(ValidationError);
+        await local.close();
+        await central.close();
+      } finally {
+        fs.rmSync(dir, { recursive: true, force: true });
+      }
+    });
+
+    test('keys peers on the database id and refuses events', async () => {
+      const dir = fs.mkdtempSync(path.join(os.tmpdir(), 'strata-sync-'));
+      try {
+        const local = Strata.open(path.join(dir, 'local'));
+        let central = Strata.open(path.join(dir, 'central'));
+        await central.kv.set('both', 'a');
+        await local.kv.set('both', 'a');
+        await local.syncTo(central);
+        const id = await central.databaseId();
+        expect(typeof id).toBe('string');
+        await central.close();
+
+        fs.renameSync(path.join(dir, 'central'), path.join(dir, 'moved'));
+        central = Strata.open(path.join(dir, 'moved'));
+        expect(await central.databaseId()).toBe(id);
+        await local.kv.set('both', 'b');
+        expect(await local.syncTo(central)).toMatchObject({ pushed: 1, conflicts: [] });
+        expect(await central.kv.get('both')).toBe('b');
+
+        await central.events.append('signup', { user: 1 });
+        await expect(local.syncTo(central)).rejects.toThrow(ConstraintError);
+        await local.close();
+        await central.close();
+      } finally {
+        fs.rmSync(dir, { recursive: true, force: true });
+      }
+    });
+  });
+
+  describe('Bulk load', () => {
+    test('writes in batches and flushes once', async () => {
+      const dir = fs.mkdtempSync(path.join(os.tmpdir(), 'strata-bulk-'));
+      try {
+        const db = Strata.open(dir, { syncMode: 'always' });
+        await db.vector.createCollection('docs', { dimension: 2 });
+        let flushes = 0;
+        db.on('flush', () => { flushes += 1; });
+        const progress = [];
+        const result = await db.bulkLoad(async (loader) => {
+          for (let i = 0; i < 10; i++) {
+            await loader.kvPut(`k${i}`, i);
+          }
+          await loader.stateSet('cell', 'on');
+          await loader.jsonSet('doc', { n: 1 });
+          await loader.eventAppend('loaded', { n: 10 });
+          await loader.vectorUpsert('docs', 'v1', [1, 0], { title: 'one' });
+        }, { batchSize: 4, onProgress: (p) => progress.push(p) });
+        expect(result).toMatchObject({ entries: 14, failed: 0, batches: 7 });
+        expect(progress[progress.length - 1]).toEqual({ entries: 14, batches: 7 });
+        expect(flushes).toBe(1);
+        expect(await db.kv.get('k9')).toBe(9);
+        expect(await db.state.get('cell')).toBe('on');
+        expect(await db.json.get('doc', '$')).toEqual({ n: 1 });
+        expect((await db.vector.get('docs', 'v1')).metadata.title).toBe('one');
+
+        let kept;
+        await expect(db.bulkLoad(async (loader) => {
+          kept = loader;
+          await loader.kvPut('dropped', 1);
+          throw new Error('stop');
+        })).rejects.toThrow('stop');
+        expect(await db.kv.get('dropped')).toBeNull();
+        await expect(kept.kvPut('late', 1)).rejects.toThrow(StateError);
+        await db.close();
+      } finally {
+        fs.rmSync(dir, { recursive: true, force: true });
+      }
+    });
+  });
+
+  describe('Write hooks', () => {
+    test('enrich, veto, and observe writes', async () => {
+      const db = Strata.cache();
+      const seen = [];
+      const stopBefore = db.onBeforeWrite((write) => {
+        if (write.key === 'forbidden') {
+          throw new Error('no');
+        }
+        if (write.primitive === 'json' && write.op === 'put') {
+          write.value = { ...write.value, stamped: true };
+        }
+      });
+      const stopAfter = db.onAfterWrite(async (write) => {
+        seen.push(`${write.primitive}:${write.op}:${write.key}`);
+        await db.kv.set(`audit:${write.key}`, write.op);
+      });
+
+      await db.json.set('doc', '$', { n: 1 });
+      expect(await db.json.get('doc', '$')).toEqual({ n: 1, stamped: true });
+      await db.kv.set('a', 1);
+      await expect(db.kv.set('forbidden', 1)).rejects.toThrow(AccessDeniedError);
+      expect(await db.kv.get('forbidden')).toBeNull();
+      expect(seen).toEqual(['json:put:doc', 'kv:put:a']);
+      expect(await db.kv.get('audit:a')).toBe('put');
+
+      stopBefore();
+      stopAfter();
+      await db.kv.set('forbidden', 1);
+      expect(await db.kv.get('forbidden')).toBe(1);
+      expect(seen).toHaveLength(2);
+      await db.close();
+    });
+
+    test('see execute() commands under every spelling, batches, and vector writes', async () => {
+      const db = Strata.cache();
+      const seen = [];
+      db.onBeforeWrite((write) => {
+        seen.push(`${write.primitive}:${write.op}:${write.key}`);
+        if (String(write.key).startsWith('secret')) {
+          throw new Error('tenant boundary');
+        }
+      });
+      const vetoed = [
+        ['kv.put', { key: 'secret1', value: 1 }],
+        ['KvPut', { key: 'secret2', value: 1 }],
+        ['kv_batch_put', { entries: [{ key: 'ok', value: 1 }, { key: 'secret3', value: 1 }] }],
+        ['state_init', { cell: 'secret4', value: 1 }],
+        ['state_cas', { cell: 'secret5', value: 1 }],
+        ['state_batch_set', { entries: [{ cell: 'secret6', value: 1 }] }],
+        ['event_batch_append', { entries: [{ event_type: 'secret7', payload: {} }] }],
+        ['json_batch_set', { entries: [{ key: 'secret8', path: '$', value: {} }] }],
+        ['json_batch_delete', { entries: [{ key: 'secret9', path: '$' }] }],
+      ];
+      for (const [command, args] of vetoed) {
+        await expect(db.execute(command, args)).rejects.toThrow(AccessDeniedError);
+      }
+      expect(await db.kv.get('ok')).toBeNull();
+      expect(await db.kv.get('secret1')).toBeNull();
+      expect(await db.state.get('secret4')).toBeNull();
+
+      await db.vector.createCollection('vecs', { dimension: 2 });
+      const vectorWrites = [
+        ['vector_upsert', { collection: 'vecs', key: 'secret10', vector: [1, 0] }],
+        ['vector_batch_upsert', { collection: 'vecs', entries: [{ key: 'secret11', vector: [0, 1] }] }],
+        ['vector_delete', { collection: 'vecs', key: 'secret12' }],
+      ];
+      for (const [command, args] of vectorWrites) {
+        await expect(db.execute(command, args)).rejects.toThrow(AccessDeniedError);
+      }
+      expect(await db.vector.get('vecs', 'secret10')).toBeNull();
+      expect(seen).toContain('kv:put:ok');
+      expect(seen).toContain('vector:put:secret11');
+      await db.close();
+    });
+  });
+
+  describe('Triggers', () => {
+    test('run after commit with old and new values', async () => {
+      const db = Strata.cache();
+      const changes = [];
+      let done;
+      const seen = new Promise((resolve) => { done = resolve; });
+      const drop = db.createTrigger('order:', async (change) => {
+        changes.push(change);
+        await db.kv.set(`order:copy:${changes.length}`, change.newValue);
+        if (changes.length === 3) {
+          done();
+        }
+      });
+
+      await db.kv.set('order:1', { amount: 5 });
+      await db.kv.set('other', 1);
+      await db.kv.set('order:1', { amount: 7 });
+      await db.transaction(async () => {
+        await db.kv.set('order:2', { amount: 1 });
+        throw new Error('abort');
+      }).catch(() => {});
+      await db.transaction(async () => {
+        await db.kv.delete('order:1');
+      });
+      await seen;
+      const summary = changes.map((c) => [c.op, c.key, c.oldValue, c.newValue]);
+      expect(summary).toEqual([
+        ['put', 'order:1', null, { amount: 5 }],
+        ['put', 'order:1', { amount: 5 }, { amount: 7 }],
+        ['delete', 'order:1', { amount: 7 }, null],
+      ]);
+      expect(changes[0]).toMatchObject({ primitive: 'kv', branch: 'default', space: 'default' });
+
+      drop();
+      expect(() => db.createTrigger({ primitive: 'kv' }, () => {})).toThrow(ValidationError);
+      await db.close();
+    });
+
+    test('run for writes through other handles on the database', async () => {
+      const db = Strata.cache();
+      const other = Strata.fromShared(await db.share());
+      const changes = [];
+      let done;
+      const seen = new Promise((resolve) => { done = resolve; });
+      const drop = db.createTrigger('order:', (change) => {
+        changes.push([change.key, change.oldValue, change.newValue]);
+        if (changes.length === 2) {
+          done();
+        }
+      });
+      await other.kv.set('order:1', 1);
+      await other.kv.set('order:1', 2);
+      await seen;
+      expect(changes).toEqual([['order:1', null, 1], ['order:1', 1, 2]]);
+      drop();
+      await other.close();
+      await db.close();
+    });
+  });
+
+  describe('Views', () => {
+    test('build, update, and refresh a map/reduce view', async () => {
+      const db = Strata.cache();
+      await db.kv.set('order:1', { customer: 'ann', amount: 5 });
+      await db.kv.set('order:2', { customer: 'bob', amount: 2 });
+      const status = await db.viewCreate('totals', {
+        source: { prefix: 'order:' },
+        map: ({ value }) => [[value.customer, value.amount]],
+        reduce: (amounts) => amounts.reduce((a, b) => a + b, 0),
+        space: 'reports',
+      });
+      expect(status).toMatchObject({ sources: 2, keys: 2, lastError: null });
+      const reports = db.readBranch('default', { space: 'reports' });
+      expect(await reports.kv.get('ann')).toBe(5);
+
+      // Updates land after the write commits.
+      const settled = async (key, expected) => {
+        for (let i = 0; i < 50 && (await reports.kv.get(key)) !== expected; i++) {
+          await new Promise((resolve) => setTimeout(resolve, 10));
+        }
+        expect(await reports.kv.get(key)).toBe(expected);
+      };
+      await db.kv.set('order:3', { customer: 'ann', amount: 4 });
+      await settled('ann', 9);
+      await db.kv.delete('order:2');
+      await settled('bob', null);
+
+      await db.viewDrop('totals');
+      await db.kv.set('order:4', { customer: 'ann', amount: 1 });
+      expect(await db.viewList()).toEqual([]);
+      await expect(db.viewRefresh('totals')).rejects.toThrow(NotFoundError);
+      await expect(db.viewCreate('bad', {
+        source: { prefix: 'x' }, map: () => [], space: 'default',
+      })).rejects.toThrow(ValidationError);
+      await db.close();
+    });
+  });
+
+  describe('Async context', () => {
+    test('calls target the context branch and space', async () => {
+      const db = Strata.cache();
+      await db.spaceCreate('tenant-a');
+      await db.spaceCreate('tenant-b');
+      await db.createBranch('preview');
+      const handle = async (tenant) => {
+        await new Promise((resolve) => setTimeout(resolve, 1));
+        await db.kv.set('name', tenant);
+        return db.kv.get('name');
+      };
+      const names = await Promise.all(['a', 'b', 'a', 'b'].map(
+        (tenant) => db.runWithContext({ space: `tenant-${tenant}` }, () => handle(tenant)),
+      ));
+      expect(names).toEqual(['a', 'b', 'a', 'b']);
+      expect(await db.kv.get('name')).toBeNull();
+      expect(await db.currentSpace()).toBe('default');
+
+      await db.runWithContext({ branch: 'preview' }, async () => {
+        expect(db.currentContext()).toEqual({ branch: 'preview', space: 'default' });
+        await db.transaction(async () => {
+          await db.kv.set('draft', 1);
+        });
+        await expect(db.setSpace('tenant-a')).rejects.toThrow(StateError);
+      });
+      expect(db.currentContext()).toBeNull();
+      expect(await db.kv.get('draft')).toBeNull();
+      await expect(db.runWithContext({ branch: 'nope' }, () => {})).rejects.toThrow(NotFoundError);
+      await db.close();
+    });
+
+    test('a context on one handle leaves other handles on the database alone', async () => {
+      const dir = fs.mkdtempSync(path.join(os.tmpdir(), 'strata-context-'));
+      try {
+        const a = Strata.open(dir);
+        const b = Strata.open(dir);
+        await a.createBranch('preview');
+        const seen = [];
+        b.onBeforeWrite((write) => {
+          seen.push(write.branch);
+        });
+        await Promise.all([
+          a.runWithContext({ branch: 'preview' }, async () => {
+            await a.kv.set('k', 'preview');
+            await a.kv.set('k2', 'preview');
+          }),
+          b.kv.set('k', 'default'),
+        ]);
+        expect(seen).toEqual(['default']);
+        expect(await b.kv.get('k')).toBe('default');
+        await b.runWithContext({ branch: 'preview' }, async () => {
+          expect(await b.kv.get('k')).toBe('preview');
+        });
+        await a.close();
+        await b.close();
+      } finally {
+        fs.rmSync(dir, { recursive: true, force: true });
+      }
+    });
+  });
+
+  describe('BigInt values', () => {
+    test('writes BigInt and reads it back with bigints', async () => {
+      const dir = fs.mkdtempSync(path.join(os.tmpdir(), 'strata-bigint-'));
+      try {
+        const db = Strata.open(dir, { bigints: true });
+        await db.kv.set('big', 9007199254740993n);
+        await db.state.set('small', 42n);
+        await db.json.set('doc', '$', { id: -9007199254740995n, n: 1 });
+        expect(await db.kv.get('big')).toBe(9007199254740993n);
+        expect(await db.state.get('small')).toBe(42);
+        expect(await db.json.get('doc', '$')).toEqual({ id: -9007199254740995n, n: 1 });
+        await expect(db.kv.set('huge', 2n ** 64n)).rejects.toThrow(ValidationError);
+
+        const plain = Strata.open(dir);
+        expect(typeof (await plain.kv.get('big'))).toBe('number');
+        await plain.close();
+        await db.close();
+      } finally {
+        fs.rmSync(dir, { recursive: true, force: true });
+      }
+    });
+  });
+
+  describe('Float values', () => {
+    test('boxed numbers keep whole-number floats as floats', async () => {
+      const dir = fs.mkdtempSync(path.join(os.tmpdir(), 'strata-floats-'));
+      try {
+        const db = Strata.open(dir, { boxedFloats: true });
+        await db.kv.set('price', new Number(2));
+        await db.kv.set('count', 2);
+        await db.json.set('doc', '$', { ratio: new Number(1), half: 0.5 });
+        const price = await db.kv.get('price');
+        expect(price).toBeInstanceOf(Number);
+        expect(+price).toBe(2);
+        expect(await db.kv.get('count')).toBe(2);
+        const doc = await db.json.get('doc', '$');
+        expect(doc.ratio).toBeInstanceOf(Number);
+        expect(doc.half).toBe(0.5);
+
+        await db.kv.set('copy', price);
+        expect(await db.kv.get('copy')).toBeInstanceOf(Number);
+        const plain = Strata.open(dir);
+        expect(await plain.kv.get('copy')).toBe(2);
+        await plain.close();
+        await db.close();
+      } finally {
+        fs.rmSync(dir, { recursive: true, force: true });
+      }
+    });
+  });
 
-      const current = await db.events.list('tt_etype');
-      expect(current.length).toBe(2);
-      const snapshot = db.at(ts);
-      const past = await snapshot.events.list('tt_etype');
-      expect(past.length).toBe(1);
+  describe('Dates', () => {
+    test('Dates as timestamps and in values', async () => {
+      const dir = fs.mkdtempSync(path.join(os.tmpdir(), 'strata-dates-'));
+      try {
+        const db = Strata.open(dir, { dateTimestamps: true });
+        await db.kv.set('config', { mode: 'a' });
+        const first = await db.kv.getVersioned('config');
+        expect(first.timestamp).toBeInstanceOf(Date);
+        await new Promise((resolve) => setTimeout(resolve, 5));
+        const between = new Date();
+        await new Promise((resolve) => setTimeout(resolve, 5));
+        await db.kv.set('config', { mode: 'b' });
+        expect(await db.kv.get('config', { asOf: between })).toEqual({ mode: 'a' });
+        expect(await db.at(between).kv.get('config')).toEqual({ mode: 'a' });
+
+        const at = new Date('2026-01-02T03:04:05.000Z');
+        await db.kv.set('stamped', { at, timestamp: 5 });
+        expect(await db.kv.get('stamped')).toEqual({ at: '2026-01-02T03:04:05.000Z', timestamp: 5 });
+        await expect(db.kv.get('config', { asOf: new Date('nope') })).rejects.toThrow(ValidationError);
+        await db.close();
+      } finally {
+        fs.rmSync(dir, { recursive: true, force: true });
+      }
     });
+  });
 
-    test('json.get reads at snapshot time', async () => {
-      await db.json.set('snap_json', '$', { v: 1 });
-      await sleep(50);
-      const range = await db.timeRange();
-      const ts = range.latestTs;
-      await sleep(50);
-      await db.json.set('snap_json', '$', { v: 2 });
+  describe('Strict values', () => {
+    test('strictValues rejects lossy conversions', async () => {
+      const dir = fs.mkdtempSync(path.join(os.tmpdir(), 'strata-strict-'));
+      try {
+        const db = Strata.open(dir, { strictValues: true });
+        await expect(db.kv.set('user', { name: 'Ada', age: undefined }))
+          .rejects.toThrow(ValidationError);
+        await expect(db.json.set('doc', '$', { n: [1, NaN] })).rejects.toThrow(ValidationError);
+        await expect(db.state.set('cell', 2 ** 60)).rejects.toThrow(ValidationError);
+        await expect(db.kv.batchPut([{ key: 'f', value: { run: () => {} } }]))
+          .rejects.toThrow(ValidationError);
+        expect(await db.kv.get('user')).toBeNull();
+
+        await db.kv.set('big', 2n ** 60n);
+        await db.kv.set('ratio', NaN, { strictValues: false });
+        expect(await db.kv.get('ratio')).toBeNull();
+        await db.close();
+
+        const lax = Strata.open(dir);
+        await lax.kv.set('user', { name: 'Ada', age: undefined });
+        expect(await lax.kv.get('user')).toEqual({ name: 'Ada' });
+        await expect(lax.kv.set('n', Infinity, { strictValues: true }))
+          .rejects.toThrow(ValidationError);
+        await lax.close();
+      } finally {
+        fs.rmSync(dir, { recursive: true, force: true });
+      }
+    });
+  });
 
-      const snapshot = db.at(ts);
-      const val = await snapshot.json.get('snap_json', '$');
-      expect(val.v).toBe(1);
+  describe('Open URL', () => {
+    test('options in the query string', async () => {
+      const dir = fs.mkdtempSync(path.join(os.tmpdir(), 'strata-url-'));
+      try {
+        const writer = Strata.open(`strata://${dir}?createIfMissing=true`);
+        await writer.kv.set('k', 1);
+        await writer.close();
+        const reader = Strata.open(`strata://${dir}?readOnly=true&workerThreads=2&cacheMB=256`);
+        expect((await reader.status()).accessMode).toBe('readOnly');
+        expect((await reader.cacheStats()).capacityBytes).toBe(256 * 1024 * 1024);
+        expect(await reader.kv.get('k')).toBe(1);
+        await reader.close();
+        const overridden = await Strata.openAsync(`strata://${dir}?readOnly=true`, { readOnly: false });
+        expect((await overridden.status()).accessMode).toBe('readWrite');
+        await overridden.close();
+      } finally {
+        fs.rmSync(dir, { recursive: true, force: true });
+      }
     });
 
-    test('json.keys reads at snapshot time', async () => {
-      await db.json.set('ttj_a', '$', { x: 1 });
-      await sleep(50);
-      const range = await db.timeRange();
-      const ts = range.latestTs;
-      await sleep(50);
-      await db.json.set('ttj_b', '$', { x: 2 });
+    test('rejects malformed URLs and unknown options', () => {
+      expect(() => Strata.open('strata://host:7474/data')).toThrow(ValidationError);
+      expect(() => Strata.open('strata:///tmp/unused?cacheMB=lots')).toThrow(ValidationError);
+      expect(() => Strata.open('strata:///tmp/bad%zzpath')).toThrow(ValidationError);
+      expect(() => Strata.open('strata:///tmp/bad%zzpath')).toThrow(/strata:\/\/\/tmp\/bad%zzpath/);
+      expect(() => Strata.open('strata:///tmp/unused?compression=zstd')).toThrow(/compression is not supported/);
+      expect(() => Strata.open('strata:///tmp/unused?readonly=true')).toThrow(ValidationError);
+      expect(() => Strata.open('strata:///tmp/unused?readOnly=1')).toThrow(ValidationError);
+    });
+  });
 
-      const snapshot = db.at(ts);
-      const past = await snapshot.json.keys({ prefix: 'ttj_' });
-      expect(past.keys.length).toBe(1);
+  describe('Open mode', () => {
+    test('createIfMissing and errorIfExists', async () => {
+      const dir = fs.mkdtempSync(path.join(os.tmpdir(), 'strata-mode-'));
+      const dbPath = path.join(dir, 'db');
+      try {
+        expect(() => Strata.open(dbPath, { createIfMissing: false })).toThrow(NotFoundError);
+        const created = Strata.open(dbPath, { errorIfExists: true });
+        await created.kv.set('k', 'v');
+        await created.close();
+        expect(() => Strata.open(dbPath, { errorIfExists: true })).toThrow(StateError);
+        const attached = Strata.open(dbPath, { createIfMissing: false });
+        expect(await attached.kv.get('k')).toBe('v');
+        await attached.close();
+      } finally {
+        fs.rmSync(dir, { recursive: true, force: true });
+      }
     });
+  });
 
-    test('vector.search reads at snapshot time', async () => {
-      await db.vector.createCollection('snap_vec', { dimension: 4 });
-      await db.vector.upsert('snap_vec', 'a', [1, 0, 0, 0]);
-      await sleep(50);
-      const range = await db.timeRange();
-      const ts = range.latestTs;
-      await sleep(50);
-      await db.vector.upsert('snap_vec', 'b', [0, 1, 0, 0]);
+  describe('Strata.temp', () => {
+    test('removes the directory on close unless kept', async () => {
+      const a = Strata.temp();
+      const b = Strata.temp();
+      const dirA = (await a.status()).path;
+      expect(dirA).not.toBe((await b.status()).path);
+      await a.kv.set('k', 'v');
+      expect(fs.existsSync(dirA)).toBe(true);
+      await a.close();
+      await b.close();
+      expect(fs.existsSync(dirA)).toBe(false);
+
+      const warnings = [];
+      const onWarning = (w) => warnings.push(w);
+      process.on('warning', onWarning);
+      const kept = Strata.temp({ persistToDiskForDebug: true });
+      const keptDir = (await kept.status()).path;
+      await kept.close();
+      await new Promise((resolve) => setImmediate(resolve));
+      process.off('warning', onWarning);
+      try {
+        expect(fs.existsSync(keptDir)).toBe(true);
+        expect(warnings.some((w) => w.code === 'STRATA_TEMP_KEPT')).toBe(true);
+      } finally {
+        fs.rmSync(keptDir, { recursive: true, force: true });
+      }
+    });
 
-      const snapshot = db.at(ts);
-      const results = await snapshot.vector.search('snap_vec', [1, 0, 0, 0], { limit: 10 });
-      expect(results.length).toBe(1);
+    test('removes the directory if the open fails', () => {
+      const tempDirs = () => fs.readdirSync(os.tmpdir()).filter((name) => name.startsWith('strata-'));
+      const before = new Set(tempDirs());
+      expect(() => Strata.temp({ maxQueuedOps: 1 })).toThrow(ValidationError);
+      expect(tempDirs().filter((name) => !before.has(name))).toEqual([]);
     });
+  });
 
-    test('vector.get reads at snapshot time', async () => {
-      await db.vector.createCollection('tt_vget', { dimension: 4 });
-      await db.vector.upsert('tt_vget', 'k', [1, 0, 0, 0], { metadata: { tag: 'v1' } });
-      await sleep(50);
-      const range = await db.timeRange();
-      const ts = range.latestTs;
-      await sleep(50);
-      await db.vector.upsert('tt_vget', 'k', [0, 1, 0, 0], { metadata: { tag: 'v2' } });
+  describe('Handle registry', () => {
+    test('opening a path twice shares the database', async () => {
+      const dir = fs.mkdtempSync(path.join(os.tmpdir(), 'strata-shared-'));
+      try {
+        const first = Strata.open(dir);
+        const second = Strata.open(path.join(dir, '.'));
+        await first.kv.set('k', 'v');
+        expect(await second.kv.get('k')).toBe('v');
+        expect(() => Strata.open(dir, { readOnly: true })).toThrow(StateError);
+        await first.close();
+        expect(await second.kv.get('k')).toBe('v');
+        await second.close();
+        const reopened = Strata.open(dir);
+        expect(await reopened.kv.get('k')).toBe('v');
+        await reopened.close();
+      } finally {
+        fs.rmSync(dir, { recursive: true, force: true });
+      }
+    });
 
-      const current = await db.vector.get('tt_vget', 'k');
-      expect(current.metadata.tag).toBe('v2');
-      const snapshot = db.at(ts);
-      const past = await snapshot.vector.get('tt_vget', 'k');
-      expect(past).not.toBeNull();
-      expect(past.metadata.tag).toBe('v1');
+    test('each handle keeps its own branch and space', async () => {
+      const dir = fs.mkdtempSync(path.join(os.tmpdir(), 'strata-shared-'));
+      try {
+        const first = Strata.open(dir);
+        const second = Strata.open(dir);
+        await first.branch.create('feature');
+        await first.branch.switch('feature');
+        await first.space.switch('scratch');
+        await first.kv.set('k', 'feature');
+        expect(await second.branch.current()).toBe('default');
+        expect(await second.space.current()).toBe('default');
+        expect(await second.kv.get('k')).toBeNull();
+        await second.branch.switch('feature');
+        await second.space.switch('scratch');
+        expect(await second.kv.get('k')).toBe('feature');
+        await first.close();
+        await second.close();
+      } finally {
+        fs.rmSync(dir, { recursive: true, force: true });
+      }
     });
+  });
 
-    test('timeRange returns oldest and latest', async () => {
-      await db.kv.set('tr_key', 'val');
-      const range = await db.timeRange();
-      expect(range).toHaveProperty('oldestTs');
-      expect(range).toHaveProperty('latestTs');
-      expect(typeof range.oldestTs).toBe('number');
-      expect(typeof range.latestTs).toBe('number');
-      expect(range.latestTs).toBeGreaterThanOrEqual(range.oldestTs);
+  describe('Multi-process access', () => {
+    test('a second process cannot open the path for writing', async () => {
+      const { execFileSync } = require('child_process');
+      const dir = fs.mkdtempSync(path.join(os.tmpdir(), 'strata-procs-'));
+      const open = (options) =>
+        execFileSync(process.execPath, [
+          '-e',
+          `
+          const { Strata } = require(${JSON.stringify(path.join(__dirname, '..', 'stratadb'))});
+          try {
+            Strata.open(${JSON.stringify(dir)}, ${JSON.stringify(options)});
+            process.stdout.write('ok');
+          } catch (err) {
+            process.stdout.write(err.code);
+          }
+          `,
+        ]).toString();
+      try {
+        const writer = Strata.open(dir);
+        expect(open({})).toBe('STATE');
+        expect(open({ follower: true })).toBe('ok');
+        await writer.close();
+        expect(open({})).toBe('ok');
+      } finally {
+        fs.rmSync(dir, { recursive: true, force: true });
+      }
     });
 
-    test('StrataSnapshot is exported', () => {
-      expect(StrataSnapshot).toBeDefined();
-      const snapshot = db.at(12345);
-      expect(snapshot).toBeInstanceOf(StrataSnapshot);
+    test('lockWaitMs waits for the writer and reports the holder', async () => {
+      const { spawn } = require('child_process');
+      const dir = fs.mkdtempSync(path.join(os.tmpdir(), 'strata-lockwait-'));
+      const holder = spawn(process.execPath, [
+        '-e',
+        `
+        const { Strata } = require(${JSON.stringify(path.join(__dirname, '..', 'stratadb'))});
+        const db = Strata.open(${JSON.stringify(dir)});
+        process.stdout.write('locked');
+        setTimeout(() => db.close().then(() => process.exit(0)), 500);
+        `,
+      ]);
+      try {
+        await new Promise((resolve) => holder.stdout.once('data', resolve));
+        let refused;
+        try {
+          Strata.open(dir);
+        } catch (err) {
+          refused = err;
+        }
+        expect(refused).toBeInstanceOf(StateError);
+        expect(refused.lockHolder.pid).toBe(holder.pid);
+        expect(refused.lockHolder.since).toBeGreaterThan(0);
+
+        const db = await Strata.openAsync(dir, { lockWaitMs: 10000 });
+        await db.kv.set('k', 1);
+        await db.close();
+      } finally {
+        holder.kill();
+        fs.rmSync(dir, { recursive: true, force: true });
+      }
     });
   });
 
-  // =========================================================================
-  // Transaction callback — db.transaction()
-  // =========================================================================
-
-  describe('db.transaction()', () => {
-    test('auto-commits on success', async () => {
-      await db.kv.set('pre', 'before');
-      await db.transaction(async (tx) => {
-        await tx.kv.set('tx_key', 'tx_value');
+  describe('Strata.connect', () => {
+    const net = require('net');
+    const { encodeFrame, FrameDecoder } = require('../lib/wire');
+
+    // Answers hello with the token check and kvGet with a canned value.
+    function stubServer() {
+      const server = net.createServer((socket) => {
+        const decoder = new FrameDecoder();
+        socket.on('data', (chunk) => {
+          for (const { id, method, args } of decoder.push(chunk)) {
+            if (method === 'hello' && args[0].token !== 'secret') {
+              socket.write(encodeFrame({ id, error: { code: 'ACCESS_DENIED', message: 'Bad token' } }));
+            } else {
+              socket.write(encodeFrame({ id, result: method === 'kvGet' ? `remote ${args[0]}` : null }));
+            }
+          }
+        });
       });
-      expect(await db.kv.get('tx_key')).toBe('tx_value');
+      return new Promise((resolve) => server.listen(0, '127.0.0.1', () => resolve(server)));
+    }
+
+    test('sends calls to the server', async () => {
+      const server = await stubServer();
+      try {
+        const url = `strata://127.0.0.1:${server.address().port}`;
+        await expect(Strata.connect(url, { token: 'wrong' })).rejects.toThrow(AccessDeniedError);
+        const remote = await Strata.connect(url, { token: 'secret' });
+        expect(await remote.kv.get('k')).toBe('remote k');
+        await expect(remote.share()).rejects.toThrow(StateError);
+        await remote.close();
+      } finally {
+        server.close();
+      }
     });
 
-    test('auto-rollback on error', async () => {
-      await expect(
-        db.transaction(async (tx) => {
-          throw new Error('intentional');
-        }),
-      ).rejects.toThrow('intentional');
-      // After rollback, no transaction should be active
-      expect(await db.txnIsActive()).toBe(false);
+    test('rejects unsupported URLs', async () => {
+      await expect(Strata.connect('http://localhost')).rejects.toThrow(ValidationError);
     });
+  });
 
-    test('returns result from callback', async () => {
-      const result = await db.transaction(async (tx) => {
-        await tx.kv.set('r_key', 'r_val');
-        return 42;
+  describe('db.listen', () => {
+    test('serves clients with read-only enforcement', async () => {
+      const server = await db.listen({
+        port: 0,
+        auth: { token: 'rw', readOnlyToken: 'ro' },
+        maxConnections: 2,
       });
-      expect(result).toBe(42);
+      const url = `strata://127.0.0.1:${server.address.port}`;
+      try {
+        const writer = await Strata.connect(url, { token: 'rw' });
+        await writer.kv.set('served', { n: 1 });
+        expect(await db.kv.get('served')).toEqual({ n: 1 });
+
+        const reader = await Strata.connect(url, { token: 'ro' });
+        expect(await reader.kv.get('served')).toEqual({ n: 1 });
+        await expect(reader.kv.set('served', 2)).rejects.toThrow(AccessDeniedError);
+
+        await expect(Strata.connect(url, { token: 'rw' })).rejects.toThrow(ResourceExhaustedError);
+        await reader.close();
+        while (server.connections > 1) {
+          await new Promise((resolve) => setTimeout(resolve, 10));
+        }
+        await expect(Strata.connect(url, { token: 'nope' })).rejects.toThrow(AccessDeniedError);
+        await writer.close();
+      } finally {
+        await server.close();
+      }
     });
 
-    test('read-only transaction', async () => {
-      await db.kv.set('ro_key', 'ro_val');
-      const result = await db.transaction(
-        async (tx) => {
-          const val = await tx.kv.get('ro_key');
-          return val;
-        },
-        { readOnly: true },
-      );
-      expect(result).toBe('ro_val');
+    test('refuses a second hello and read-only resets', async () => {
+      const net = require('net');
+      const { encodeFrame, FrameDecoder } = require('../lib/wire');
+      const server = await db.listen({ port: 0, auth: { token: 'rw', readOnlyToken: 'ro' } });
+      try {
+        const socket = net.connect(server.address.port, '127.0.0.1');
+        const decoder = new FrameDecoder();
+        const replies = [];
+        socket.on('data', (chunk) => replies.push(...decoder.push(chunk)));
+        socket.write(encodeFrame({ id: 1, method: 'hello', args: [{ token: 'ro' }] }));
+        socket.write(encodeFrame({ id: 2, method: 'hello', args: [{ token: 'rw' }] }));
+        socket.write(encodeFrame({ id: 3, method: 'metricsReset', args: [] }));
+        while (replies.length < 3) {
+          await new Promise((resolve) => setTimeout(resolve, 10));
+        }
+        const byId = Object.fromEntries(replies.map((reply) => [reply.id, reply]));
+        expect(byId[1].error).toBeUndefined();
+        expect(byId[2].error.code).toBe('STATE');
+        expect(byId[3].error.code).toBe('ACCESS_DENIED');
+        expect(server.connections).toBe(1);
+        socket.destroy();
+      } finally {
+        await server.close();
+      }
     });
-  });
-
-  // =========================================================================
-  // Errors
-  // =========================================================================
 
-  describe('Errors', () => {
-    test('NotFoundError with correct hierarchy and code', async () => {
+    test('an auth function grants only readWrite or readOnly', async () => {
+      const net = require('net');
+      const { encodeFrame, FrameDecoder } = require('../lib/wire');
+      const grants = { rw: 'readWrite', ro: 'readOnly', yes: true, short: 'ro' };
+      const server = await db.listen({ port: 0, auth: (token) => grants[token] ?? null });
+      const url = `strata://127.0.0.1:${server.address.port}`;
       try {
-        await db.vector.search('no_such_collection', [1, 0, 0, 0], { limit: 1 });
-        fail('Expected error');
-      } catch (err) {
-        expect(err).toBeInstanceOf(NotFoundError);
-        expect(err).toBeInstanceOf(StrataError);
-        expect(err).toBeInstanceOf(Error);
-        expect(err.code).toBe('NOT_FOUND');
-        // negative checks — not other subclasses
-        expect(err instanceof ValidationError).toBe(false);
-        expect(err instanceof ConflictError).toBe(false);
+        await expect(Strata.connect(url, { token: 'yes' })).rejects.toThrow(AccessDeniedError);
+        await expect(Strata.connect(url, { token: 'short' })).rejects.toThrow(AccessDeniedError);
+        const writer = await Strata.connect(url, { token: 'rw' });
+        await writer.kv.set('k', 1);
+        await writer.close();
+
+        const socket = net.connect(server.address.port, '127.0.0.1');
+        const decoder = new FrameDecoder();
+        const replies = [];
+        socket.on('data', (chunk) => replies.push(...decoder.push(chunk)));
+        socket.write(encodeFrame({ id: 1, method: 'hello', args: [{ token: 'ro' }] }));
+        socket.write(encodeFrame({ id: 2, method: 'cancelOperation', args: [1] }));
+        while (replies.length < 2) {
+          await new Promise((resolve) => setTimeout(resolve, 10));
+        }
+        const byId = Object.fromEntries(replies.map((reply) => [reply.id, reply]));
+        expect(byId[1].error).toBeUndefined();
+        expect(byId[2].error.code).toBe('ACCESS_DENIED');
+        socket.destroy();
+      } finally {
+        await server.close();
       }
     });
 
-    test('ValidationError on invalid metric', async () => {
+    test('limits unauthenticated sockets', async () => {
+      const net = require('net');
+      const server = await db.listen({ port: 0, maxConnections: 1, handshakeTimeoutMs: 50 });
+      const url = `strata://127.0.0.1:${server.address.port}`;
+      const closed = (socket) => new Promise((resolve) => socket.once('close', resolve));
       try {
-        await db.vector.createCollection('x', { dimension: 4, metric: 'invalid_metric' });
-        fail('Expected error');
-      } catch (err) {
-        expect(err).toBeInstanceOf(ValidationError);
-        expect(err.code).toBe('VALIDATION');
+        // An idle socket holds the only slot until the handshake times out.
+        const idle = net.connect(server.address.port, '127.0.0.1');
+        await new Promise((resolve) => idle.once('connect', resolve));
+        await expect(Strata.connect(url)).rejects.toThrow(ResourceExhaustedError);
+        await closed(idle);
+        await new Promise((resolve) => setTimeout(resolve, 20));
+        const remote = await Strata.connect(url);
+        await remote.close();
+        await new Promise((resolve) => setTimeout(resolve, 20));
+
+        // A frame too large to be a hello is refused before authentication.
+        const big = net.connect(server.address.port, '127.0.0.1');
+        const header = Buffer.alloc(4);
+        header.writeUInt32BE(1024 * 1024);
+        big.write(header);
+        await closed(big);
+      } finally {
+        await server.close();
       }
     });
 
-    test('ConstraintError on dimension mismatch', async () => {
-      await db.vector.createCollection('dim_test', { dimension: 4 });
+    test('confines bundle paths to bundleDir', async () => {
+      const dir = fs.mkdtempSync(path.join(os.tmpdir(), 'strata-bundles-'));
+      const url = (server) => `strata://127.0.0.1:${server.address.port}`;
+      await db.kv.set('k', 1);
+      let server = await db.listen({ port: 0 });
       try {
-        await db.vector.upsert('dim_test', 'k', [1, 0]); // wrong dimension
-        fail('Expected error');
-      } catch (err) {
-        expect(err).toBeInstanceOf(ConstraintError);
-        expect(err.code).toBe('CONSTRAINT');
+        const remote = await Strata.connect(url(server));
+        await expect(remote.branch.export('default', path.join(dir, 'a.bundle')))
+          .rejects.toThrow(AccessDeniedError);
+        await remote.close();
+      } finally {
+        await server.close();
+      }
+      server = await db.listen({ port: 0, bundleDir: dir });
+      try {
+        const remote = await Strata.connect(url(server));
+        await remote.branch.export('default', 'a.bundle');
+        expect(fs.existsSync(path.join(dir, 'a.bundle'))).toBe(true);
+        await expect(remote.branch.export('default', '../escape.bundle'))
+          .rejects.toThrow(AccessDeniedError);
+        await expect(remote.branch.validateBundle('/etc/passwd'))
+          .rejects.toThrow(AccessDeniedError);
+        await remote.close();
+      } finally {
+        await server.close();
+        fs.rmSync(dir, { recursive: true, force: true });
       }
     });
   });
 
-  // =========================================================================
-  // Batch KV — db.kv.batchPut
-  // =========================================================================
+  describe('Shared handles', () => {
+    test('a worker thread attaches with a share token', async () => {
+      const { Worker } = require('worker_threads');
+      const token = await db.share();
+      const worker = new Worker(
+        `
+        const { parentPort, workerData } = require('worker_threads');
+        const { Strata } = require(workerData.module);
+        const db = Strata.fromShared(workerData.token);
+        db.kv.set('from-worker', 'hi')
+          .then(() => db.close())
+          .then(() => parentPort.postMessage('done'));
+        `,
+        { eval: true, workerData: { token, module: path.join(__dirname, '..', 'stratadb') } },
+      );
+      await new Promise((resolve, reject) => {
+        worker.once('message', resolve);
+        worker.once('error', reject);
+      });
+      expect(await db.kv.get('from-worker')).toBe('hi');
+    });
+
+    test('fromShared rejects unknown tokens', () => {
+      expect(() => Strata.fromShared('nonsense')).toThrow(ValidationError);
+      expect(() => Strata.fromShared('strata-share:0')).toThrow(NotFoundError);
+    });
+  });
+
+  describe('Strata.openAsync', () => {
+    test('opens a database off the event loop', async () => {
+      const dir = fs.mkdtempSync(path.join(os.tmpdir(), 'strata-open-'));
+      try {
+        const opened = await Strata.openAsync(dir, { maxConcurrentOps: 2 });
+        await opened.kv.set('k', 'v');
+        expect(await opened.kv.get('k')).toBe('v');
+        expect((await opened.poolStats()).size).toBe(2);
+        await opened.close();
+      } finally {
+        fs.rmSync(dir, { recursive: true, force: true });
+      }
+    });
+  });
 
   describe('db.kv.batchPut', () => {
     test('batchPut stores all entries and returns versions', async () => {
@@ -986,6 +3879,17 @@ describe('Strata', () => {
       expect(await db.kv.get('bk_ow')).toBe('updated');
     });
 
+    test('batchPut writes null for an undefined value', async () => {
+      await db.kv.set('bk_undef', 'before');
+      const results = await db.kv.batchPut([
+        { key: 'bk_undef', value: undefined },
+        { key: 'bk_nested', value: { a: 1, b: undefined } },
+      ]);
+      expect(results.map((r) => r.error)).toEqual([null, null]);
+      expect(await db.kv.get('bk_undef')).toBeNull();
+      expect(await db.kv.get('bk_nested')).toEqual({ a: 1 });
+    });
+
     test('batchPut rejects entries missing key', async () => {
       await expect(db.kv.batchPut([{ value: 'no_key' }])).rejects.toThrow(/key/i);
     });
@@ -1225,6 +4129,141 @@ describe('Strata', () => {
       expect(typeof status.pending).toBe('number');
       expect(typeof status.totalQueued).toBe('number');
     });
+
+    test.skip('ingestDocument chunks and upserts (requires embed feature)', async () => {
+      await db.vectorCreateCollection('docs', 384);
+      const chunks = await db.ingestDocument('docs', 'readme', 'a'.repeat(250), {
+        chunkSize: 100,
+        overlap: 10,
+        metadata: { lang: 'en' },
+      });
+      expect(chunks.map((c) => c.key)).toEqual(['readme#0', 'readme#1', 'readme#2']);
+      const stored = await db.vectorGet('docs', 'readme#1');
+      expect(stored.metadata.source).toBe('readme');
+      expect(stored.metadata.lang).toBe('en');
+    });
+
+    test.skip('re-ingesting a shorter document removes its stale chunks (requires embed feature)', async () => {
+      await db.vectorCreateCollection('docs', 384);
+      const opts = { chunkSize: 100, overlap: 10 };
+      await db.ingestDocument('docs', 'readme', 'a'.repeat(250), opts);
+      const chunks = await db.ingestDocument('docs', 'readme', 'b'.repeat(50), opts);
+      expect(chunks.map((c) => c.key)).toEqual(['readme#0']);
+      expect((await db.vectorGet('docs', 'readme#0')).metadata.text).toBe('b'.repeat(50));
+      expect(await db.vectorGet('docs', 'readme#1')).toBeNull();
+      expect(await db.vectorGet('docs', 'readme#2')).toBeNull();
+    });
+
+    test('re-ingesting replaces the chunk keys all at once', async () => {
+      await db.vectorCreateCollection('chunks', 384);
+      const vector = Array.from({ length: 384 }, (_, i) => (i === 0 ? 1 : 0));
+      const earlier = ['readme#0', 'readme#1', 'readme#2'];
+      for (const key of earlier) {
+        await db.vectorUpsert('chunks', key, vector);
+      }
+      // Without the embed feature the ingest fails, and must leave the
+      // earlier chunks as they were rather than half replaced.
+      const ingested = await db
+        .ingestDocument('chunks', 'readme', 'b'.repeat(50), { chunkSize: 100, overlap: 10 })
+        .then((chunks) => chunks.map((c) => c.key), () => null);
+      const keys = [];
+      for (const key of earlier) {
+        if (await db.vectorGet('chunks', key)) keys.push(key);
+      }
+      expect(keys).toEqual(ingested ?? earlier);
+    });
+
+    test('ingestDocument rejects overlap >= chunkSize', async () => {
+      await expect(
+        db.ingestDocument('docs', 'k', 'text', { chunkSize: 10, overlap: 10 }),
+      ).rejects.toThrow(ValidationError);
+    });
+
+    test.skip('rerank orders candidates by relevance (requires embed feature)', async () => {
+      const results = await db.rerank('capital of France', [
+        'Bananas are yellow',
+        'Paris is the capital of France',
+      ]);
+      expect(results.length).toBe(2);
+      expect(results[0].index).toBe(1);
+      expect(results[0].score).toBeGreaterThanOrEqual(results[1].score);
+    });
+
+    test('rerank orders candidates with a scorer', async () => {
+      const overlap = (query, text) => {
+        const words = new Set(query.toLowerCase().split(' '));
+        return text.toLowerCase().split(' ').filter((w) => words.has(w)).length;
+      };
+      const results = await db.rerank('capital of France', [
+        'Bananas are yellow',
+        'Paris is the capital of France',
+        'Lyon is in France',
+        'Grapes are green',
+      ], { scorer: async (query, text) => overlap(query, text) });
+      expect(results.map((r) => r.index)).toEqual([1, 2, 0, 3]);
+      expect(results[0]).toEqual({ index: 1, text: 'Paris is the capital of France', score: 3 });
+      await expect(db.rerank('q', ['a'], { scorer: 1 })).rejects.toThrow(ValidationError);
+      await expect(db.rerank('q', ['a'], { scorer: () => 0, model: 'm' }))
+        .rejects.toThrow(ValidationError);
+      for (const score of [NaN, Infinity, undefined, '1']) {
+        await expect(db.rerank('q', ['a', 'b'], { scorer: () => score }))
+          .rejects.toThrow(ValidationError);
+      }
+    });
+  });
+
+  describe('Conversation memory', () => {
+    test.skip('recalls the turns of a session by meaning (requires embed feature)', async () => {
+      await db.memoryAppend('s1', { role: 'user', content: 'My flight to Lisbon is on May 3rd' });
+      await db.memoryAppend('s1', { role: 'user', content: 'I like green tea' });
+      await db.memoryAppend('s2', { role: 'user', content: 'My train to Porto leaves at noon' });
+      const [best, ...rest] = await db.memoryRecall('s1', 'when do I travel?', 5);
+      expect(best.turn.content).toBe('My flight to Lisbon is on May 3rd');
+      expect(rest.map((m) => m.turn.content)).toEqual(['I like green tea']);
+      const turns = await db.eventList('_memory/s1');
+      expect(turns.map((e) => e.value.content)[0]).toBe(best.turn.content);
+    });
+
+    test('rejects turns without content and recalls nothing before the first', async () => {
+      await expect(db.memoryAppend('s1', { role: 'user' })).rejects.toThrow(ValidationError);
+      await expect(db.memoryAppend('', { content: 'hi' })).rejects.toThrow(ValidationError);
+      expect(await db.memoryRecall('s1', 'anything', 3)).toEqual([]);
+    });
+  });
+
+  describe('Traces', () => {
+    test('nests spans under their parents', async () => {
+      await db.traceStart('run-1', { agent: 'planner' });
+      await db.traceRecord('run-1', { id: 'root', name: 'plan', startTime: 1 });
+      await db.traceRecord('run-1', { id: 'b', parentId: 'root', name: 'tool:b', startTime: 3 });
+      await db.traceRecord('run-1', { id: 'a', parentId: 'root', name: 'tool:a', startTime: 2 });
+      await db.traceRecord('run-1', { id: 'a', parentId: 'root', name: 'tool:a', startTime: 2, endTime: 4 });
+      const trace = await db.traceGet('run-1');
+      expect(trace.runId).toBe('run-1');
+      expect(trace.metadata).toEqual({ agent: 'planner' });
+      const [root] = trace.spans;
+      expect(root.children.map((s) => s.id)).toEqual(['a', 'b']);
+      expect(root.children[0].endTime).toBe(4);
+      expect(await db.traceGet('never')).toBeNull();
+    });
+
+    test('rejects unknown runs and parents, and keeps forks apart', async () => {
+      await expect(db.traceRecord('run-2', { id: 'x', name: 'x' })).rejects.toThrow(NotFoundError);
+      await db.traceStart('run-2');
+      await expect(db.traceStart('run-2')).rejects.toThrow(ConflictError);
+      await expect(db.traceRecord('run-2', { id: 'x', parentId: 'nope', name: 'x' }))
+        .rejects.toThrow(NotFoundError);
+      await db.traceRecord('run-2', { id: 'x', name: 'x' });
+      await expect(db.traceRecord('run-2', { id: 'x', parentId: 'x', name: 'x' }))
+        .rejects.toThrow(ValidationError);
+
+      await db.branch.fork('replay');
+      await db.branch.switch('replay');
+      await db.traceRecord('run-2', { id: 'y', parentId: 'x', name: 'replayed' });
+      await db.branch.switch('default');
+      const [x] = (await db.traceGet('run-2')).spans;
+      expect(x.children).toEqual([]);
+    });
   });
 
   describe('Inference', () => {
@@ -2011,6 +5050,22 @@ describe('Strata', () => {
       await db.branch.delete('lb3');
     });
 
+    test('readBranch reads without switching', async () => {
+      await db.kv.set('peek', 'main');
+      await db.branch.fork('other');
+      await db.branch.switch('other');
+      await db.kv.set('peek', 'other');
+      await db.state.set('cell', 7);
+      await db.branch.switch('default');
+
+      const view = db.readBranch('other');
+      expect(await view.kv.get('peek')).toBe('other');
+      expect(await view.state.get('cell')).toBe(7);
+      expect(await db.kv.get('peek', { branch: 'other' })).toBe('other');
+      expect(await db.branch.current()).toBe('default');
+      expect(() => view.kv.set('peek', 1)).toThrow(StateError);
+    });
+
     test('branch.create without metadata still works', async () => {
       await db.branch.create('no-meta');
       const exists = await db.branch.exists('no-meta');
@@ -2635,5 +5690,113 @@ describe('Strata', () => {
       await tempDb.kv.set('k', 'v');
       await tempDb.close();
     });
+
+    test('a collected handle is released with a warning', () => {
+      const { execFileSync } = require('child_process');
+      const dir = fs.mkdtempSync(path.join(os.tmpdir(), 'strata-gc-'));
+      try {
+        const output = execFileSync(process.execPath, [
+          '--expose-gc',
+          '-e',
+          `
+          const { Strata } = require(${JSON.stringify(path.join(__dirname, '..', 'stratadb'))});
+          const tick = () => new Promise((resolve) => setTimeout(resolve, 50));
+          process.on('warning', (w) => console.log(w.code));
+          (async () => {
+            await Strata.open(${JSON.stringify(dir)}).kv.set('k', 'v');
+            for (let i = 0; i < 3; i++) {
+              global.gc();
+              await tick();
+            }
+            const again = Strata.open(${JSON.stringify(dir)});
+            console.log(await again.kv.get('k'));
+            await again.close();
+          })();
+          `,
+        ]).toString();
+        expect(output).toContain('STRATA_UNCLOSED_HANDLE');
+        expect(output).toContain('v');
+      } finally {
+        fs.rmSync(dir, { recursive: true, force: true });
+      }
+    });
+
+    test('flushOnExit closes the handle on exit and SIGTERM', async () => {
+      const { spawnSync } = require('child_process');
+      const dir = fs.mkdtempSync(path.join(os.tmpdir(), 'strata-exit-'));
+      const run = (body) =>
+        spawnSync(process.execPath, [
+          '-e',
+          `
+          const { Strata } = require(${JSON.stringify(path.join(__dirname, '..', 'stratadb'))});
+          const db = Strata.open(${JSON.stringify(dir)}, { flushOnExit: true });
+          ${body}
+          `,
+        ]);
+      try {
+        expect(run(`db.kv.set('drained', 1);`).status).toBe(0);
+        const killed = run(`
+          setInterval(() => {}, 1000);
+          db.kv.set('killed', 2).then(() => process.kill(process.pid, 'SIGTERM'));
+        `);
+        expect(killed.signal).toBe('SIGTERM');
+        const reopened = Strata.open(dir);
+        expect(await reopened.kv.get('drained')).toBe(1);
+        expect(await reopened.kv.get('killed')).toBe(2);
+        await reopened.close();
+      } finally {
+        fs.rmSync(dir, { recursive: true, force: true });
+      }
+    });
+
+    test('status reports the handle state', async () => {
+      const tempDb = Strata.cache();
+      await tempDb.begin();
+      expect(await tempDb.status()).toEqual({
+        open: true,
+        path: null,
+        accessMode: 'readWrite',
+        branch: 'default',
+        space: 'default',
+        inTransaction: true,
+        inFlight: 0,
+      });
+      await tempDb.rollback();
+      await tempDb.close();
+      expect(await tempDb.isOpen()).toBe(false);
+      const closed = await tempDb.status();
+      expect(closed.open).toBe(false);
+      expect(closed.branch).toBeNull();
+    });
+
+    test('calls after close reject with StrataClosedError', async () => {
+      const tempDb = Strata.cache();
+      await tempDb.kv.set('k', 'v');
+      await tempDb.close();
+      await expect(tempDb.kv.get('k')).rejects.toThrow(StrataClosedError);
+      await expect(tempDb.kv.set('k', 'w')).rejects.toThrow(StrataClosedError);
+      await tempDb.close();
+    });
+
+    test('every entry point rejects after close', async () => {
+      const tempDb = Strata.cache();
+      const system = await tempDb.systemBranch();
+      await tempDb.close();
+      await expect(tempDb.poolStats()).rejects.toThrow(StrataClosedError);
+      await expect(tempDb.share()).rejects.toThrow(StrataClosedError);
+      await expect(tempDb.systemBranch()).rejects.toThrow(StrataClosedError);
+      await expect(tempDb.branchExportOpen('default')).rejects.toThrow(StrataClosedError);
+      await expect(tempDb.branchImportOpen()).rejects.toThrow(StrataClosedError);
+      await expect(system.kvGet('k')).rejects.toThrow(/CLOSED/);
+    });
+
+    test('concurrent closes release the handle once', async () => {
+      const tempDb = Strata.cache();
+      const writer = await tempDb.branchImportOpen();
+      await Promise.all([tempDb.close(), tempDb.close()]);
+      expect(await tempDb.isOpen()).toBe(false);
+      await expect(writer.write(Buffer.from('x'))).rejects.toThrow(/CLOSED/);
+      writer.abort();
+    });
   });
 });
@@ -18,6 +18,79 @@ export interface JsOpenOptions {
    * from the primary.
    */
   follower?: boolean
+  /**
+   * Run this handle's operations on a dedicated pool of this many
+   * blocking threads. Absent = share tokio's default blocking pool with
+   * the rest of the process.
+   */
+  workerThreads?: number
+  /**
+   * Run at most this many operations at once; further calls wait for a
+   * free slot. Gives the handle a dedicated pool of this size (or caps
+   * `workerThreads`).
+   */
+  maxConcurrentOps?: number
+  /**
+   * With `maxConcurrentOps`, how many calls may wait for a slot before
+   * new calls fail with `RESOURCE_EXHAUSTED`. Absent = unbounded; 0 =
+   * fail as soon as every slot is busy. `close()` is never turned away.
+   */
+  maxQueuedOps?: number
+  /**
+   * Create the database if there is none at the path (default: true).
+   * Pass `false` to only attach to an existing database.
+   */
+  createIfMissing?: boolean
+  /**
+   * Fail if a database already exists at the path (default: false), so
+   * initialization never reuses an existing one.
+   */
+  errorIfExists?: boolean
+  /**
+   * Limits on values written through this handle. Calls can override
+   * them with their own `jsonLimits`.
+   */
+  jsonLimits?: JsJsonLimits
+  /**
+   * Return stored integers outside the safe-integer range from
+   * `kvGet`, `stateGet`, and `jsonGet` as BigInt (default: false, a
+   * rounded number).
+   */
+  bigints?: boolean
+  /**
+   * Return whole-number floats from `kvGet`, `stateGet`, and `jsonGet`
+   * as boxed `Number` objects, which are written back as floats
+   * (default: false, a plain number, written back as an integer).
+   */
+  boxedFloats?: boolean
+  /**
+   * If another process has the path open for writing, retry for up to
+   * this many milliseconds before failing (default: 0, fail at once).
+   */
+  lockWaitMs?: number
+  /**
+   * Megabytes of latest KV values `kvGet` keeps in a read cache shared
+   * by every handle on the database (default: 0, no cache). Only the
+   * first open of a path sizes it.
+   */
+  cacheMB?: number
+}
+/** Limits on the values a write accepts. Unset fields keep their default. */
+export interface JsJsonLimits {
+  /** Deepest nesting of arrays and objects (default 64, at most 1024). */
+  maxDepth?: number
+  /**
+   * Largest value, counting string and byte lengths, object keys, and 8
+   * bytes per other scalar (default 64 MiB).
+   */
+  maxValueBytes?: number
+  /** Most elements in one array or object (default 1,000,000). */
+  maxElements?: number
+  /**
+   * Reject a value that loses data in conversion instead of storing
+   * what is left (default: false); see `JsValue`.
+   */
+  strictValues?: boolean
 }
 /** Time range filter for search (ISO 8601 datetime strings). */
 export interface JsTimeRange {
@@ -40,7 +113,338 @@ export interface JsSearchOptions {
   expand?: boolean
   /** Enable/disable reranking. Absent = auto. */
   rerank?: boolean
+  /** Search these spaces (or `"*"` for all) instead of the current one. */
+  spaces?: Array<string> | string
+}
+/** Options for document ingestion. */
+export interface JsIngestOptions {
+  /** Maximum chunk length in characters (default: 1000). */
+  chunkSize?: number
+  /** Characters shared between consecutive chunks (default: 100). */
+  overlap?: number
+  /** Metadata merged into every chunk's vector metadata. */
+  metadata?: any
+}
+/** Editable branch annotations. */
+export interface JsBranchMetadata {
+  /** Free-form description. An empty string clears it. */
+  description?: string
+  /** Labels, replacing any existing ones. */
+  labels?: Array<string>
+}
+/** Options for creating a tag. */
+export interface JsTagOptions {
+  /** Timestamp to tag (microseconds since epoch). Defaults to now. */
+  timestamp?: number
+}
+/** Options for copying a space. */
+export interface JsSpaceCopyOptions {
+  /** Branch to copy within (default: the current branch). */
+  branch?: string
+}
+/** A retention rule for a space and/or primitive. */
+export interface JsRetentionOptions {
+  /** Space the rule applies to (default: every space). */
+  space?: string
+  /**
+   * Primitive the rule applies to: "kv", "state", "json", "event", or
+   * "vector" (default: every primitive).
+   */
+  primitive?: string
+  /** Keep at most this many versions per key. */
+  maxVersions?: number
+  /** Drop versions older than this many seconds. */
+  maxAge?: number
+  /** Keep versions visible at any tagged timestamp (default: false). */
+  keepTags?: boolean
+}
+/** Options for committing a transaction. */
+export interface JsCommitOptions {
+  /** Return `{ version, operations }` rather than just the version. */
+  detailed?: boolean
+}
+/** Options for beginning a transaction. */
+export interface JsBeginOptions {
+  /** Reject writes in this transaction. */
+  readOnly?: boolean
+  /** Pin every read to this timestamp (or tag). Requires `readOnly`. */
+  asOf?: number | string
+  /** Run the transaction on this branch (default: the current branch). */
+  branch?: string
+  /**
+   * Default space for commands in the transaction (default: the
+   * current space).
+   */
+  space?: string
+}
+/**
+ * A history pin that `retentionApply` must not trim. Set exactly one of
+ * `key`, `tag`, or `timestamp`.
+ */
+export interface JsRetentionPin {
+  /** Keep every version of this key. */
+  key?: string
+  /** Space of `key` (default: the current space). */
+  space?: string
+  /** Primitive of `key`: "kv" (default), "state", or "json". */
+  primitive?: string
+  /** Keep the versions visible at this tag's timestamp. */
+  tag?: string
+  /** Keep the versions visible at this timestamp. */
+  timestamp?: number
+}
+/** Options for `info()`. */
+export interface JsInfoOptions {
+  /**
+   * Also report `diskBytes`, the size of the database directory. Off by
+   * default because it walks the whole directory.
+   */
+  diskBytes?: boolean
+}
+/** Options for exporting part of a branch to a bundle. */
+export interface JsBundleExportOptions {
+  /** Only export these spaces (default: every space). */
+  spaces?: Array<string>
+  /** Only export keys starting with one of these prefixes. */
+  prefixes?: Array<string>
+  /**
+   * Only export these primitives: "kv", "state", "json", "event",
+   * "vector", or "graph" (default: all).
+   */
+  primitives?: Array<string>
+  /**
+   * Include version history (default: true). When false, only the
+   * latest version of each key is exported.
+   */
+  includeHistory?: boolean
+  /** Encrypt the bundle (AES-256-GCM) with a key or passphrase. */
+  encryption?: JsBundleEncryption
+}
+/**
+ * Key material for encrypted bundles. Give exactly one of `key` or
+ * `passphrase`.
+ */
+export interface JsBundleEncryption {
+  /** Raw 32-byte key. */
+  key?: Buffer
+  /** Passphrase; the key is derived from it with a per-bundle salt. */
+  passphrase?: string
+  /**
+   * Identifier recorded in the bundle header so the right key can be
+   * found when importing. Ignored on import.
+   */
+  keyId?: string
+}
+/** A branch for `backupBranches` to export. */
+export interface JsBackupBranch {
+  branch: string
+  path: string
+  /** Export only the changes after this version, as a delta bundle. */
+  sinceVersion?: number
+}
+/** Options for importing or validating a bundle. */
+export interface JsBundleImportOptions {
+  /** Key material for an encrypted bundle. */
+  encryption?: JsBundleEncryption
+  /**
+   * Import under this branch name instead of the one recorded in the
+   * bundle. For delta bundles, the branch the delta is applied to.
+   */
+  destinationBranch?: string
+  /**
+   * What to do if the destination branch already exists: "fail"
+   * (default), "replace", or "merge" (last-writer-wins). Ignored when
+   * validating.
+   */
+  onConflict?: string
+}
+/** Options for branch diffs. */
+export interface JsDiffOptions {
+  /** Include per-space key lists, not just summary counts. */
+  detailed?: boolean
+  /** For modified keys: "none" (default), "values", or "hashes". */
+  values?: string
+  /** Maximum number of key entries to return per page; at least 1. */
+  limit?: number
+  /**
+   * Cursor returned by a previous page. It names the last key of that
+   * page, so the next page resumes after it.
+   */
+  cursor?: string
+}
+/** Options for `queuePop`. */
+export interface JsQueuePopOptions {
+  /**
+   * Milliseconds the message stays hidden from other `queuePop` calls
+   * before it is delivered again unless acknowledged (default 30,000).
+   */
+  visibilityTimeout?: number
+}
+/** Options for `lockAcquire`. */
+export interface JsLockOptions {
+  /** Milliseconds the lease lasts unless renewed (default 30,000). */
+  ttlMs?: number
+  /**
+   * Milliseconds to keep retrying while another holder has the lock
+   * (default 0, try once).
+   */
+  waitMs?: number
+}
+/** Options for `edgeList`. */
+export interface JsEdgeListOptions {
+  /** `"outgoing"` (default), `"incoming"`, or `"both"`. */
+  direction?: string
+  /** Only edges with this relation. */
+  relation?: string
+  /** Read the edges as of this timestamp (microseconds) or tag. */
+  asOf?: number | string
+}
+/** Range, filter, and downsampling for `tsQuery`. */
+export interface JsTsQueryOptions {
+  /** Earliest timestamp to include (microseconds, inclusive). */
+  from?: number
+  /** Latest timestamp to include (microseconds, inclusive). */
+  to?: number
+  /** Only points carrying all of these tags. */
+  tags?: Record<string, string>
+  /**
+   * Combine the points in each bucket: "avg", "sum", "min", "max",
+   * "count", "first", or "last".
+   */
+  aggregate?: string
+  /**
+   * Bucket width in microseconds, aligned to the epoch. Without it,
+   * `aggregate` combines the whole range.
+   */
+  bucket?: number
+}
+/** Paging for `jsonQuery`. */
+export interface JsJsonQueryOptions {
+  /** Only documents whose key starts with this. */
+  prefix?: string
+  /** Most documents to return (default: all that match). */
+  limit?: number
+  /** Read the documents as of this timestamp (microseconds) or tag. */
+  asOf?: number | string
+}
+/** Options for `traverse`. */
+export interface JsTraverseOptions {
+  /** Hops to follow from the start node (default 1). */
+  depth?: number
+  /** `"outgoing"` (default), `"incoming"`, or `"both"`. */
+  direction?: string
+  /** Only follow edges with this relation. */
+  relation?: string
+  /** Most nodes to visit, counting the start node (default 1,000). */
+  limit?: number
+  /** Read the edges as of this timestamp (microseconds) or tag. */
+  asOf?: number | string
+}
+/** Filters and paging for `kvHistory`, `stateHistory`, and `jsonHistory`. */
+export interface JsHistoryOptions {
+  /** Maximum number of versions to return. */
+  limit?: number
+  /** Only versions older than this version. */
+  beforeVersion?: number
+  /** Only versions written at or after this timestamp (or tag). */
+  fromTs?: number | string
+  /** Only versions written at or before this timestamp (or tag). */
+  toTs?: number | string
+  /** "desc" (newest first, default) or "asc". */
+  order?: string
+  /** Cursor returned by a previous page. */
+  cursor?: string
+}
+/** Who made a `kvPut` and why, kept with the version it writes. */
+export interface JsWriteAnnotation {
+  author?: string
+  message?: string
+  /** Id of the request the write served, for tracing it across systems. */
+  requestId?: string
+}
+/** A targeted history trim. Set `before`, `keepLast`, or both. */
+export interface JsHistoryTrimOptions {
+  /** Drop versions written before this timestamp (or tag). */
+  before?: number | string
+  /** Keep at most this many versions per key. */
+  keepLast?: number
+  /** Only trim this space (default: every space on the branch). */
+  space?: string
+  /**
+   * Only trim this primitive: "kv", "state", "json", "event", or
+   * "vector" (default: all).
+   */
+  primitive?: string
+}
+/**
+ * Time bounds for a key timeline. Either bound may be a timestamp or a
+ * tag name.
+ */
+export interface JsTimelineOptions {
+  /** Earliest write to include (inclusive). */
+  from?: number | string
+  /** Latest write to include (inclusive). */
+  to?: number | string
 }
+/** Options for diffing the current branch between two timestamps. */
+export interface JsDiffTimeOptions {
+  /** Only diff this space (default: every space on the branch). */
+  space?: string
+  /** Only report keys starting with this prefix. */
+  prefix?: string
+}
+/** A live branch to compare a bundle against. */
+export interface JsBundleDiffTarget {
+  branch: string
+}
+/** Options for merging branches. */
+export interface JsMergeOptions {
+  /** "last_writer_wins" (default), "strict", or "manual"; see `MergeStrategy`. */
+  strategy?: MergeStrategy | `${MergeStrategy}`
+  /** Compute the merge result without writing anything. */
+  dryRun?: boolean
+  /** Include both sides' values and versions in each reported conflict. */
+  includeValues?: boolean
+}
+/**
+ * Distance metrics for vector collections, exported as
+ * `DistanceMetric`. Methods also take the value strings.
+ */
+export const enum DistanceMetric {
+  Cosine = 'cosine',
+  Euclidean = 'euclidean',
+  DotProduct = 'dot_product'
+}
+/** Branch merge strategies, exported as `MergeStrategy`. */
+export const enum MergeStrategy {
+  LastWriterWins = 'last_writer_wins',
+  Strict = 'strict',
+  /** Handled by the JS wrapper, which stages conflicts for later. */
+  Manual = 'manual'
+}
+/** Vector metadata and JSON query filter operators, exported as `FilterOp`. */
+export const enum FilterOp {
+  Eq = 'eq',
+  Ne = 'ne',
+  Gt = 'gt',
+  Gte = 'gte',
+  Lt = 'lt',
+  Lte = 'lte',
+  In = 'in',
+  Contains = 'contains',
+  WithinRadius = 'withinRadius',
+  BoundingBox = 'boundingBox'
+}
+/** Options for cross-encoder reranking. */
+export interface JsRerankOptions {
+  /** Reranker model name. Absent = the default cross-encoder. */
+  model?: string
+}
+/**
+ * Route the binding's log records to `sink` as `{ level, message, fields }`
+ * instead of stderr. `null` restores stderr.
+ */
+export declare function setLogSink(sink?: (...args: any[]) => any | undefined | null): void
 /** Download model files for auto-embedding. */
 export declare function setup(): string
 /**
@@ -51,53 +455,334 @@ export declare function setup(): string
  * Node.js event loop is never blocked.
  */
 export declare class Strata {
-  /** Open a database at the given path. */
+  /**
+   * Open a database at the given path.
+   *
+   * Runs on the calling thread, including WAL recovery and any model
+   * download; use `openAsync` to keep the event loop free.
+   */
   static open(path: string, options?: JsOpenOptions | undefined | null): Strata
+  /**
+   * Open a database at the given path on the blocking pool, resolving
+   * to the handle once recovery (and any model download) is done.
+   */
+  static openAsync(path: string, options?: JsOpenOptions | undefined | null): Promise<Strata>
   /** Create an in-memory database (no persistence). */
   static cache(): Strata
-  /** Store a key-value pair. */
-  kvPut(key: string, value: any): Promise<number>
   /**
-   * Get a value by key. Optionally pass `asOf` (microseconds since epoch)
-   * to read as of a past timestamp.
+   * Attach to a database shared by `share()`, typically from another
+   * worker thread. `options` configures this handle's worker pool.
+   */
+  static fromShared(token: string, options?: JsOpenOptions | undefined | null): Strata
+  /**
+   * A token that `Strata.fromShared()` turns into another handle on this
+   * database. It is a plain string, so it can be sent to worker threads
+   * with `postMessage`.
+   */
+  share(): string
+  /**
+   * Whether this handle is open (not closed, and its database not
+   * dropped).
+   */
+  isOpen(): boolean
+  /**
+   * Id of the database this handle shares with the other handles opened
+   * on it or made from its `share()` token, unique in the process.
    */
-  kvGet(key: string, asOf?: number | undefined | null): Promise<any>
+  sharedId(): number
+  /**
+   * Handle health: `{ open, path, accessMode, branch, space,
+   * inTransaction, inFlight }`. `branch` and `space` are `null` once
+   * closed, and while an operation holds the database exclusively.
+   */
+  status(): any
+  /**
+   * Worker pool usage: `{ dedicated, size, active, queued, completed,
+   * saturation }`, where `saturation` is `active / size`.
+   *
+   * Without `workerThreads` or `maxConcurrentOps` the handle uses the
+   * pool shared by every such handle in the process, whose `size` is
+   * twice the number of cores (at least 4); `active` and `queued` count
+   * only this handle's calls.
+   */
+  poolStats(): any
+  /**
+   * Read cache usage: `{ capacityBytes, residentBytes, entries, hits,
+   * misses, hitRate }`, shared by every handle on the database. The
+   * cache is sized by the `cacheMB` open option; without it,
+   * `capacityBytes` is 0 and nothing is counted.
+   */
+  cacheStats(): any
+  /**
+   * Store a key-value pair.
+   *
+   * With `annotation`, the write's author, message, and request id are
+   * recorded for its version, and returned with it by `kvHistory`,
+   * `kvTimeline`, and `diffBranches`.
+   */
+  kvPut(key: string, value: any, jsonLimits?: JsJsonLimits | undefined | null, annotation?: JsWriteAnnotation | undefined | null): Promise<number>
+  /**
+   * Get a value by key. Optionally pass `asOf` (microseconds since epoch,
+   * or a tag name) to read as of a past timestamp, or `atVersion` to read
+   * the key as it was at that write version.
+   */
+  kvGet(key: string, asOf?: number | string | undefined | null, atVersion?: number | undefined | null): Promise<any>
+  /**
+   * Store a key from a pre-serialized MessagePack buffer, skipping
+   * per-field JS object conversion.
+   */
+  kvPutRaw(key: string, value: Buffer, jsonLimits?: JsJsonLimits | undefined | null): Promise<number>
+  /**
+   * Get a key as a MessagePack buffer, or `null` if it doesn't exist.
+   * Optionally pass `asOf` to read as of a past timestamp or tag.
+   */
+  kvGetRaw(key: string, asOf?: number | string | undefined | null): Promise<Buffer | null>
   /** Delete a key. */
   kvDelete(key: string): Promise<boolean>
-  /** List keys with optional prefix filter. Optionally pass `asOf` for time-travel. */
-  kvList(prefix?: string | undefined | null, asOf?: number | undefined | null): Promise<Array<string>>
-  /** Get version history for a key. */
-  kvHistory(key: string): Promise<any>
+  /**
+   * List keys with optional prefix filter. Optionally pass `asOf` for time-travel.
+   *
+   * With `spaces` (a list of names, or `"*"` for all), keys from every
+   * selected space are returned as `{ space, key }` objects.
+   */
+  kvList(prefix?: string | undefined | null, asOf?: number | string | undefined | null, spaces?: Array<string> | string | undefined | null, operationId?: number | undefined | null): Promise<any>
+  /**
+   * Get version history for a key.
+   *
+   * With `options`, returns one page as `{ versions, cursor }`; pass
+   * `cursor` back to continue.
+   */
+  kvHistory(key: string, options?: JsHistoryOptions | undefined | null): Promise<any>
+  /**
+   * Get the versions of a key written between `from` and `to`, oldest
+   * first, each with a `diff` against the version before it.
+   *
+   * Diffs are JSON Patch style operations (`add`, `remove`, `replace`)
+   * addressed by JSON Pointer, so object values only report the fields
+   * that changed.
+   */
+  kvTimeline(key: string, options?: JsTimelineOptions | undefined | null): Promise<any>
   /** Set a state cell value. */
-  stateSet(cell: string, value: any): Promise<number>
-  /** Get a state cell value. Optionally pass `asOf` for time-travel. */
-  stateGet(cell: string, asOf?: number | undefined | null): Promise<any>
+  stateSet(cell: string, value: any, jsonLimits?: JsJsonLimits | undefined | null): Promise<number>
+  /**
+   * Get a state cell value. Optionally pass `asOf` or `atVersion` for
+   * time-travel.
+   */
+  stateGet(cell: string, asOf?: number | string | undefined | null, atVersion?: number | undefined | null): Promise<any>
   /** Initialize a state cell if it doesn't exist. */
-  stateInit(cell: string, value: any): Promise<number>
+  stateInit(cell: string, value: any, jsonLimits?: JsJsonLimits | undefined | null): Promise<number>
   /** Compare-and-swap update based on version. */
-  stateCas(cell: string, newValue: any, expectedVersion?: number | undefined | null): Promise<number | null>
-  /** Get version history for a state cell. */
-  stateHistory(cell: string): Promise<any>
+  stateCas(cell: string, newValue: any, expectedVersion?: number | undefined | null, jsonLimits?: JsJsonLimits | undefined | null): Promise<number | null>
+  /**
+   * Get version history for a state cell.
+   *
+   * With `options`, returns one page as `{ versions, cursor }`; pass
+   * `cursor` back to continue.
+   */
+  stateHistory(cell: string, options?: JsHistoryOptions | undefined | null): Promise<any>
+  /**
+   * Add `delta` (default 1, may be negative) to counter `name` and
+   * return the new total. A counter starts at 0.
+   */
+  counterIncr(name: string, delta?: number | undefined | null): Promise<number>
+  /**
+   * Counter `name`'s total, 0 if it was never incremented. Optionally
+   * pass `asOf` to read it as of a past timestamp or tag.
+   */
+  counterGet(name: string, asOf?: number | string | undefined | null): Promise<number>
+  /**
+   * Set counter `name` back to `value` (default 0) and return the total
+   * it had, e.g. to read and restart a metering window in one call.
+   */
+  counterReset(name: string, value?: number | undefined | null): Promise<number>
+  /**
+   * Every total counter `name` has had, newest first, as
+   * `{ value, version, timestamp }`. With `options`, returns one page as
+   * `{ versions, cursor }`, as `stateHistory` does.
+   */
+  counterHistory(name: string, options?: JsHistoryOptions | undefined | null): Promise<any>
+  /** Add `item` to the end of `queue`. Returns its sequence number. */
+  queuePush(queue: string, item: any, jsonLimits?: JsJsonLimits | undefined | null): Promise<number>
+  /**
+   * Take the oldest visible message of `queue` as `{ id, item, attempts }`,
+   * or `null` if there is none. The message is hidden for the visibility
+   * timeout, then delivered again unless `queueAck(id)` was called.
+   */
+  queuePop(queue: string, options?: JsQueuePopOptions | undefined | null): Promise<any>
+  /**
+   * Acknowledge a message from `queuePop`, removing it from its queue.
+   * Fails with a conflict if its visibility timeout ran out and it was
+   * delivered again, since the later delivery now owns it.
+   */
+  queueAck(id: string): Promise<void>
+  /**
+   * Counts for `queue`: `{ ready, inFlight, pushed }`, where `inFlight`
+   * messages are popped but not yet acknowledged or timed out, and
+   * `pushed` counts every message ever pushed.
+   */
+  queueStats(queue: string): Promise<any>
+  /**
+   * Take lock `name` for `ttlMs`, retrying for up to `waitMs` while
+   * another holder has it. Returns a new random lease token, or `null`
+   * if the lock is still held when the wait runs out. An expired lease
+   * is free for the taking.
+   */
+  lockAcquire(name: string, options?: JsLockOptions | undefined | null): Promise<string | null>
+  /**
+   * Extend the lease `token` on lock `name` to `ttlMs` (default 30,000)
+   * from now. Returns `false` if the lease is no longer held. A lease
+   * that expired but was not taken by anyone else is still `token`'s.
+   */
+  lockRenew(name: string, token: string, ttlMs?: number | undefined | null): Promise<boolean>
+  /**
+   * Give up the lease `token` on lock `name`. Returns `false` if it was
+   * no longer held.
+   */
+  lockRelease(name: string, token: string): Promise<boolean>
+  /**
+   * Store chunk `index` of upload `upload`, for `blobPut`. Chunks are
+   * kept as bytes, so they skip value conversion and JSON limits.
+   */
+  blobWriteChunk(upload: string, index: number, data: Buffer): Promise<void>
+  /**
+   * Point blob `key` at the first `chunks` chunks of `upload`, `size`
+   * bytes in all, and delete the chunks of the content it replaces.
+   * Returns the blob's `blobStat`.
+   */
+  blobCommit(key: string, upload: string, size: number, chunks: number): Promise<any>
+  /**
+   * Delete the first `chunks` chunks of an upload that `blobPut` gave up
+   * on.
+   */
+  blobDiscard(upload: string, chunks: number): Promise<void>
+  /** Chunk `index` of upload `upload`, or `null` if it was deleted. */
+  blobReadChunk(upload: string, index: number): Promise<Buffer | null>
+  /**
+   * `{ size, chunks, upload, timestamp }` of blob `key`, or `null` if it
+   * doesn't exist. `timestamp` is when it was last written.
+   */
+  blobStat(key: string): Promise<any>
+  /** Delete blob `key` and its chunks. Returns `false` if it didn't exist. */
+  blobDelete(key: string): Promise<boolean>
+  /**
+   * Add the edge `from -[relation]-> to` with optional `properties`,
+   * replacing the properties if it exists.
+   */
+  edgeAdd(from: string, relation: string, to: string, properties?: any | undefined | null, jsonLimits?: JsJsonLimits | undefined | null): Promise<void>
+  /**
+   * Remove the edge `from -[relation]-> to`. Returns `false` if it
+   * didn't exist.
+   */
+  edgeRemove(from: string, relation: string, to: string): Promise<boolean>
+  /**
+   * The edges at `node` as `{ from, relation, to, properties }`, outgoing
+   * unless `direction` says otherwise.
+   */
+  edgeList(node: string, options?: JsEdgeListOptions | undefined | null): Promise<any[]>
+  /**
+   * Walk the edges breadth-first from `start`, up to `depth` hops and
+   * `limit` nodes. Returns `{ visited, depths, edges }`: the nodes in the
+   * order reached, each node's hop count, and the edges followed.
+   */
+  traverse(start: string, options?: JsTraverseOptions | undefined | null): Promise<any>
+  /**
+   * Record `value` in `series` at `timestamp` (microseconds, default
+   * now) with optional string `tags`. A point at the same timestamp is
+   * replaced. Returns the timestamp.
+   */
+  tsAppend(series: string, timestamp: number | undefined | null, value: number, tags?: Record<string, string> | undefined | null): Promise<number>
+  /**
+   * The points of `series` in `[from, to]`, oldest first, as
+   * `{ timestamp, value, tags }`. With `aggregate`, returns one
+   * `{ timestamp, value, count }` per `bucket` instead, stamped with the
+   * bucket's start.
+   */
+  tsQuery(series: string, options?: JsTsQueryOptions | undefined | null): Promise<any>
+  /**
+   * Register a JSON Schema for the documents of collection `name` on the
+   * current branch, replacing any earlier one. `collectionPut` checks
+   * each document against it.
+   */
+  collectionDefine(name: string, schema: any): Promise<void>
+  /** The schema registered for collection `name`, or null. */
+  collectionSchema(name: string): Promise<any>
+  /**
+   * Write document `id` of collection `name`, the JSON document
+   * `<name>/<id>`, once it passes the collection's schema, if it has one.
+   * Returns the version.
+   */
+  collectionPut(name: string, id: string, value: any, jsonLimits?: JsJsonLimits | undefined | null): Promise<number>
+  /**
+   * Append `turn`, an object with a string `content`, to the memory of
+   * session `sessionId`: an event of type `_memory/<sessionId>`, with
+   * `content` embedded into the `_memory` vector collection so
+   * `memoryRecall` can find it. Returns the event's sequence number.
+   */
+  memoryAppend(sessionId: string, turn: any, jsonLimits?: JsJsonLimits | undefined | null): Promise<number>
+  /**
+   * The `k` turns of session `sessionId` closest in meaning to `query`,
+   * best first, as `{ sequence, timestamp, score, turn }`. The query is
+   * embedded, searched among the session's turns, and the matches read
+   * back from the event log in one call.
+   */
+  memoryRecall(sessionId: string, query: string, k: number): Promise<any>
+  /**
+   * Start the trace of run `runId` on the current branch, with optional
+   * `metadata`. Fails if the run already has one.
+   */
+  traceStart(runId: string, metadata?: any | undefined | null): Promise<number>
+  /**
+   * Record `span` in the trace of run `runId`: an object with string
+   * `id` and `name`, and a `parentId` naming a span already recorded.
+   * Recording an id again replaces that span. Returns the version.
+   */
+  traceRecord(runId: string, span: any, jsonLimits?: JsJsonLimits | undefined | null): Promise<number>
+  /**
+   * The trace of run `runId` as `{ runId, startedAt, metadata, spans }`,
+   * or null if it was never started. `spans` holds the root spans, each
+   * with its `children`, ordered by `startTime` and then id.
+   */
+  traceGet(runId: string): Promise<any>
   /** Append an event to the log. */
-  eventAppend(eventType: string, payload: any): Promise<number>
+  eventAppend(eventType: string, payload: any, jsonLimits?: JsJsonLimits | undefined | null): Promise<number>
   /** Get an event by sequence number. Optionally pass `asOf` for time-travel. */
-  eventGet(sequence: number, asOf?: number | undefined | null): Promise<any>
+  eventGet(sequence: number, asOf?: number | string | undefined | null): Promise<any>
   /** List events by type. Optionally pass `asOf` for time-travel. */
-  eventList(eventType: string, asOf?: number | undefined | null): Promise<any>
+  eventList(eventType: string, asOf?: number | string | undefined | null): Promise<any>
   /** Get total event count. */
   eventLen(): Promise<number>
   /** Set a value at a JSONPath. */
-  jsonSet(key: string, path: string, value: any): Promise<number>
-  /** Get a value at a JSONPath. Optionally pass `asOf` for time-travel. */
-  jsonGet(key: string, path: string, asOf?: number | undefined | null): Promise<any>
+  jsonSet(key: string, path: string, value: any, jsonLimits?: JsJsonLimits | undefined | null): Promise<number>
+  /**
+   * Get a value at a JSONPath. Optionally pass `asOf` or `atVersion` for
+   * time-travel.
+   */
+  jsonGet(key: string, path: string, asOf?: number | string | undefined | null, atVersion?: number | undefined | null): Promise<any>
   /** Delete a JSON document. */
   jsonDelete(key: string, path: string): Promise<number>
-  /** Get version history for a JSON document. */
-  jsonHistory(key: string): Promise<any>
-  /** List JSON document keys. Optionally pass `asOf` for time-travel. */
-  jsonList(limit: number, prefix?: string | undefined | null, cursor?: string | undefined | null, asOf?: number | undefined | null): Promise<any>
+  /**
+   * Get version history for a JSON document.
+   *
+   * With `options`, returns one page as `{ versions, cursor }`; pass
+   * `cursor` back to continue.
+   */
+  jsonHistory(key: string, options?: JsHistoryOptions | undefined | null): Promise<any>
+  /**
+   * List JSON document keys. Optionally pass `asOf` for time-travel.
+   *
+   * With `spaces`, up to `limit` keys are listed from each selected space
+   * and returned as `{ space, key }` objects; `cursor` is not supported.
+   */
+  jsonList(limit: number, prefix?: string | undefined | null, cursor?: string | undefined | null, asOf?: number | string | undefined | null, spaces?: Array<string> | string | undefined | null, operationId?: number | undefined | null): Promise<any>
+  /**
+   * The JSON documents matching every filter, as `{ key, value }` in key
+   * order. Filters take the `{ field, op, value }` form of
+   * `vectorSearchFiltered`, with a dotted path into the document as
+   * `field`; a document without the field doesn't match.
+   */
+  jsonQuery(filter: Array<any>, options?: JsJsonQueryOptions | undefined | null): Promise<any[]>
   /** Create a vector collection. */
-  vectorCreateCollection(collection: string, dimension: number, metric?: string | undefined | null): Promise<number>
+  vectorCreateCollection(collection: string, dimension: number, metric?: DistanceMetric | `${DistanceMetric}` | undefined | null): Promise<number>
   /** Delete a vector collection. */
   vectorDeleteCollection(collection: string): Promise<boolean>
   /** List vector collections. */
@@ -105,11 +790,11 @@ export declare class Strata {
   /** Insert or update a vector. */
   vectorUpsert(collection: string, key: string, vector: Array<number>, metadata?: any | undefined | null): Promise<number>
   /** Get a vector by key. Optionally pass `asOf` for time-travel. */
-  vectorGet(collection: string, key: string, asOf?: number | undefined | null): Promise<any>
+  vectorGet(collection: string, key: string, asOf?: number | string | undefined | null): Promise<any>
   /** Delete a vector. */
   vectorDelete(collection: string, key: string): Promise<boolean>
   /** Search for similar vectors. Optionally pass `asOf` for time-travel. */
-  vectorSearch(collection: string, query: Array<number>, k: number, asOf?: number | undefined | null): Promise<any>
+  vectorSearch(collection: string, query: Array<number>, k: number, asOf?: number | string | undefined | null, operationId?: number | undefined | null): Promise<any>
   /** Get statistics for a single collection. */
   vectorCollectionStats(collection: string): Promise<any>
   /** Batch insert/update multiple vectors. */
@@ -122,18 +807,114 @@ export declare class Strata {
   createBranch(branch: string, metadata?: any | undefined | null): Promise<void>
   /** Fork the current branch to a new branch, copying all data. */
   forkBranch(destination: string): Promise<any>
-  /** List all branches. */
-  listBranches(limit?: number | undefined | null, offset?: number | undefined | null): Promise<any>
+  /**
+   * Create a new branch from the current branch's state at a past
+   * timestamp (microseconds since epoch, or a tag name).
+   *
+   * KV, state, and JSON data visible at `asOf` is copied into the new
+   * branch in every space. Events, vectors, and graphs are not copied.
+   */
+  forkBranchAt(destination: string, asOf: number | string): Promise<any>
+  /**
+   * Rewind a branch's KV, state, and JSON data, in every space, to what
+   * it held at `asOf` (microseconds since epoch), writing the
+   * differences as new versions. Events, vectors, and graphs are left
+   * as they are. Used by `Strata.restore()` for `rewindTo`.
+   */
+  branchRewind(branch: string, asOf: number): Promise<any>
+  /**
+   * List all branches.
+   *
+   * Archived branches are left out unless `includeArchived` is `true`.
+   */
+  listBranches(limit?: number | undefined | null, offset?: number | undefined | null, includeArchived?: boolean | undefined | null): Promise<any>
   /** Delete a branch. */
   deleteBranch(branch: string): Promise<void>
   /** Check if a branch exists. */
   branchExists(name: string): Promise<boolean>
   /** Get branch metadata with version info. */
   branchGet(name: string): Promise<any>
-  /** Compare two branches. */
-  diffBranches(branchA: string, branchB: string): Promise<any>
-  /** Merge a branch into the current branch. */
-  mergeBranches(source: string, strategy?: string | undefined | null): Promise<any>
+  /**
+   * Rename a branch.
+   *
+   * The branch is forked under the new name and the old name is deleted,
+   * carrying its description and labels along. If the renamed branch is
+   * the current branch, the handle follows it to the new name.
+   */
+  renameBranch(oldName: string, newName: string): Promise<void>
+  /**
+   * Set a branch's description and/or labels.
+   *
+   * Fields left out are unchanged. The values are returned by `branchGet`.
+   */
+  setBranchMetadata(name: string, metadata: JsBranchMetadata): Promise<void>
+  /**
+   * Protect a branch from deletion and direct writes.
+   *
+   * Data can still reach a protected branch by merging into it. Pass
+   * `false` to lift protection.
+   */
+  protectBranch(name: string, enabled?: boolean | undefined | null): Promise<void>
+  /**
+   * Archive a branch, making it read-only and hiding it from
+   * `listBranches` by default. Pass `false` to unarchive.
+   */
+  archiveBranch(name: string, enabled?: boolean | undefined | null): Promise<void>
+  /**
+   * Get statistics for one branch.
+   *
+   * Reports entry counts per primitive summed over all spaces, the space
+   * count, an estimate of the stored bytes, and the last write timestamp.
+   * Every value on the branch is read, so this is O(branch size).
+   */
+  branchStats(name: string): Promise<any>
+  /**
+   * Get the branch fork graph.
+   *
+   * Returns one node per branch with its parent, fork timestamp, and
+   * divergence counts relative to the parent (keys added, removed, and
+   * modified on the child). Pass `false` to skip the divergence diffs,
+   * which cost one branch diff per forked branch.
+   */
+  branchLineage(includeDivergence?: boolean | undefined | null): Promise<any>
+  /**
+   * Compare two branches.
+   *
+   * By default only summary counts are returned. Pass `{ detailed: true }`
+   * to also get per-space lists of added, removed, and modified keys,
+   * paginated with `limit`/`cursor`.
+   */
+  diffBranches(branchA: string, branchB: string, options?: JsDiffOptions | undefined | null, operationId?: number | undefined | null): Promise<any>
+  /**
+   * Compare a bundle with another bundle, or with a live branch when the
+   * second argument is `{ branch }`.
+   *
+   * Neither bundle is imported: each is staged in a hidden branch that
+   * is removed once the diff is taken. Bundle A is reported as `branchA`.
+   * `options` shape the result as for `diffBranches`; `encryption`
+   * decrypts both bundles.
+   */
+  bundleDiff(pathA: string, target: string | JsBundleDiffTarget, options?: JsDiffOptions | undefined | null, encryption?: JsBundleEncryption | undefined | null): Promise<any>
+  /**
+   * Merge a branch into the current branch.
+   *
+   * The second argument is either a strategy name or an options object.
+   * With `{ dryRun: true }` nothing is written: the result reports the
+   * keys that would be applied, the keys changed on both branches since
+   * they forked as conflicts, and per-space effects.
+   */
+  mergeBranches(source: string, options?: MergeStrategy | `${MergeStrategy}` | JsMergeOptions | undefined | null): Promise<any>
+  /**
+   * Create a named tag for a point in time.
+   *
+   * Tags default to the current time. Any `asOf` parameter accepts a tag
+   * name in place of a timestamp.
+   */
+  tagCreate(name: string, options?: JsTagOptions | undefined | null): Promise<any>
+  /** List all tags, oldest timestamp first. */
+  tagList(): Promise<any>
+  /** Delete a tag. Returns `true` if it existed. */
+  tagDelete(name: string): Promise<boolean>
   /** Get the current space name. */
   currentSpace(): Promise<string>
   /** Switch to a different space. */
@@ -144,10 +925,86 @@ export declare class Strata {
   deleteSpace(space: string): Promise<void>
   /** Force delete a space even if non-empty. */
   deleteSpaceForce(space: string): Promise<void>
+  /**
+   * Get statistics for one space on the current branch.
+   *
+   * Reports KV, state, JSON, event, vector, and graph counts plus an
+   * estimate of the stored bytes. Every value in the space is read.
+   */
+  spaceStats(space: string): Promise<any>
+  /**
+   * Copy a space's KV, state, and JSON data into a new space.
+   *
+   * Runs on the current branch unless `branch` is given. Events, vectors,
+   * and graphs are not copied. Returns the number of entries copied.
+   */
+  spaceCopy(source: string, destination: string, options?: JsSpaceCopyOptions | undefined | null): Promise<any>
+  /**
+   * Rename a space on the current branch.
+   *
+   * The space's KV, state, and JSON data is copied to the new name and
+   * the old space is deleted. Spaces holding events, vectors, or graphs
+   * are rejected, since those would be lost.
+   */
+  spaceRename(oldName: string, newName: string): Promise<void>
+  /**
+   * Read a page of the KV, state, and JSON writes made after
+   * `sinceVersion`, on every branch, for `changelogExport()`.
+   *
+   * Returns `{ version, records, more }`: records are `{ version, branch,
+   * space, primitive, key, op, value }`, oldest first, starting after
+   * version `after` (default `sinceVersion`) and ending on a version
+   * boundary once at least `limit` (default 1000) are read. `version` is
+   * the newest version read, to pass as `after` for the next page while
+   * `more` is true and as `sinceVersion` next time. Deletes carry no
+   * version and come on the last page. Databases holding events,
+   * vectors, or graphs are refused, since those can't be replayed.
+   */
+  changelogRead(sinceVersion: number, after?: number | undefined | null, limit?: number | undefined | null): Promise<any>
+  /**
+   * Apply a batch of changelog records in order, for `changelogApply()`.
+   * Branches and spaces that don't exist are created. Returns the number
+   * of records applied.
+   */
+  changelogApplyBatch(records: Array<any>, jsonLimits?: JsJsonLimits | undefined | null): Promise<number>
+  /**
+   * Read every KV, state, and JSON entry of `branch`, in every space,
+   * for `syncTo()`. Returns `[{ space, primitive, key, value }, ...]`.
+   * Refuses a branch with a space holding events, vectors, or graphs.
+   */
+  syncRead(branch: string): Promise<any>
+  /**
+   * This database's id, kept on the `_system_` branch, which stays the
+   * same wherever the database is opened from. `null` if it has none;
+   * given `candidate`, a database without one takes it, which needs a
+   * writable handle.
+   */
+  databaseId(candidate?: string | undefined | null): Promise<string | null>
+  /**
+   * Read one page of a space's KV, state, or JSON entries on the current
+   * branch, for `dump()`. With `primitive` `"event"`, `prefix` is an
+   * event type and the entries are its events, keyed by sequence.
+   *
+   * Returns `{ entries: [[key, value], ...], cursor }`. Pass `cursor`
+   * back for the next page; it is `null` after the last one.
+   */
+  dumpPage(space: string, primitive: string, prefix: string | undefined | null, cursor: string | undefined | null, limit: number): Promise<any>
+  /**
+   * Write a batch of `{ space, primitive, key, value }` entries on the
+   * current branch, for `load()`. Spaces that don't exist are created.
+   * Returns the number of entries written.
+   */
+  loadBatch(entries: Array<any>, jsonLimits?: JsJsonLimits | undefined | null): Promise<number>
   /** Check database connectivity. */
   ping(): Promise<string>
-  /** Get database info. */
-  info(): Promise<any>
+  /**
+   * Get database info.
+   *
+   * `diskBytes` is the size of the database directory when requested
+   * with `{ diskBytes: true }`, and `null` otherwise or for an in-memory
+   * database.
+   */
+  info(options?: JsInfoOptions | undefined | null): Promise<any>
   /**
    * Get a structured snapshot of the database for agent introspection.
    *
@@ -158,20 +1015,123 @@ export declare class Strata {
   describe(): Promise<any>
   /** Flush writes to disk. */
   flush(): Promise<void>
-  /** Trigger compaction. */
-  compact(): Promise<void>
-  /** Export a branch to a bundle file. */
-  branchExport(branch: string, path: string): Promise<any>
-  /** Import a branch from a bundle file. */
-  branchImport(path: string): Promise<any>
-  /** Validate a bundle file without importing. */
-  branchValidateBundle(path: string): Promise<any>
-  /** Begin a new transaction. */
-  begin(readOnly?: boolean | undefined | null): Promise<void>
-  /** Commit the current transaction. */
-  commit(): Promise<number>
+  /**
+   * Trigger compaction.
+   *
+   * Returns `durationMs` and, for a database on disk, the size of its
+   * directory before and after (`diskBytesBefore`, `diskBytesAfter`).
+   * The engine compacts the whole database and cannot stop once started.
+   */
+  compact(operationId?: number | undefined | null): Promise<any>
+  /**
+   * Check the database for damage without changing it.
+   *
+   * Walks every branch and space, checking branch metadata and that each
+   * primitive's listing can be read. With `deep`, also reads every
+   * latest value, event, and graph edge, so the engine verifies their
+   * checksums and listed keys are checked against stored values.
+   * Returns `{ ok, deep, branches, spaces, entriesChecked, problems }`;
+   * failures are reported as problems rather than thrown.
+   */
+  verify(deep?: boolean | undefined | null, operationId?: number | undefined | null): Promise<any>
+  /**
+   * Export a branch to a bundle file.
+   *
+   * Pass `options` to export only some spaces, key prefixes, or
+   * primitives, or to leave out version history. `onProgress` receives
+   * `{ entriesDone, bytesDone }`; `operationId` makes the export
+   * cancellable through `bundleCancel()`.
+   */
+  branchExport(branch: string, path: string, options?: JsBundleExportOptions | undefined | null, onProgress?: (...args: any[]) => any | undefined | null, operationId?: number | undefined | null): Promise<any>
+  /**
+   * Export only the changes made to a branch after `sinceVersion`.
+   *
+   * The delta bundle is applied by `branchImport` on top of a copy of the
+   * branch that already holds everything up to `sinceVersion`.
+   */
+  branchExportIncremental(branch: string, sinceVersion: number, path: string, options?: JsBundleExportOptions | undefined | null): Promise<any>
+  /**
+   * Export several branches as of one moment, for `backup()`.
+   *
+   * Writes through every handle on the database wait until the last
+   * export finishes, so the bundles and the `_system_` records under
+   * `systemPrefixes` form one consistent cut. Returns the cut's
+   * `version`, where the next incremental backup starts, each export's
+   * result, and the records as `[key, value]` pairs. Nothing is written,
+   * so read-only and follower handles can back up too.
+   */
+  backupBranches(branches: Array<JsBackupBranch>, systemPrefixes: Array<string>): Promise<any>
+  /**
+   * Import a branch from a bundle file.
+   *
+   * Delta bundles from `branchExportIncremental` are applied on top of the
+   * existing branch instead of creating a new one. `onProgress` and
+   * `operationId` work as for `branchExport`.
+   */
+  branchImport(path: string, options?: JsBundleImportOptions | undefined | null, onProgress?: (...args: any[]) => any | undefined | null, operationId?: number | undefined | null): Promise<any>
+  /**
+   * Validate a bundle file without importing.
+   *
+   * Checksums of an encrypted bundle are only verified when its key is
+   * given; the header (including `keyId`) is always readable. `onProgress`
+   * and `operationId` work as for `branchExport`.
+   */
+  branchValidateBundle(path: string, options?: JsBundleImportOptions | undefined | null, onProgress?: (...args: any[]) => any | undefined | null, operationId?: number | undefined | null): Promise<any>
+  /**
+   * Export a branch for streaming. The bundle is staged in a temporary
+   * file and handed out in chunks by the returned reader.
+   */
+  branchExportOpen(branch: string, options?: JsBundleExportOptions | undefined | null): Promise<BundleReader>
+  /**
+   * Start a streamed import. Chunks written to the returned writer are
+   * staged in a temporary file and imported on `finish()`.
+   */
+  branchImportOpen(options?: JsBundleImportOptions | undefined | null): BundleWriter
+  /**
+   * Cancel the bundle export, import, or validation started with this
+   * `operationId`. Same as `cancelOperation`.
+   */
+  bundleCancel(operationId: number): void
+  /**
+   * Cancel the operation started with this `operationId`. Bundle
+   * operations stop at their next progress check; search, vector search,
+   * diff, and compaction stop before their next step, or never start if
+   * still queued for a worker thread. Either way the call rejects.
+   */
+  cancelOperation(operationId: number): void
+  /**
+   * Begin a new transaction.
+   *
+   * Pass `true` or `{ readOnly: true }` for a read-only transaction. A
+   * read-only transaction with `asOf` is a snapshot: every read in it
+   * sees the database as of that timestamp or tag.
+   *
+   * `branch` and `space` target the transaction without switching the
+   * handle: `execute()` commands that don't name a branch or space use
+   * them until the transaction ends.
+   */
+  begin(options?: boolean | JsBeginOptions | undefined | null): Promise<void>
+  /**
+   * Commit the current transaction. Returns the commit version.
+   *
+   * With `{ detailed: true }`, returns `{ version, operations }` instead,
+   * listing each write made through `execute()` in the transaction with
+   * the version it was stamped with. Every write in a transaction gets
+   * the commit version.
+   */
+  commit(options?: JsCommitOptions | undefined | null): Promise<number | any>
   /** Rollback the current transaction. */
   rollback(): Promise<void>
+  /**
+   * Mark a savepoint in the active transaction. Reusing a name moves
+   * the savepoint.
+   */
+  txSavepoint(name: string): Promise<void>
+  /**
+   * Undo the writes made since a savepoint, keeping the transaction (and
+   * the savepoint) active. Later savepoints are discarded.
+   */
+  txRollbackTo(name: string): Promise<void>
   /** Get current transaction info. */
   txnInfo(): Promise<any>
   /** Check if a transaction is currently active. */
@@ -179,7 +1139,7 @@ export declare class Strata {
   /** Delete a state cell. */
   stateDelete(cell: string): Promise<boolean>
   /** List state cell names with optional prefix filter. Optionally pass `asOf` for time-travel. */
-  stateList(prefix?: string | undefined | null, asOf?: number | undefined | null): Promise<Array<string>>
+  stateList(prefix?: string | undefined | null, asOf?: number | string | undefined | null): Promise<Array<string>>
   /** Get a value by key with version info. */
   kvGetVersioned(key: string): Promise<any>
   /** Get a state cell value with version info. */
@@ -187,14 +1147,20 @@ export declare class Strata {
   /** Get a JSON document value with version info. */
   jsonGetVersioned(key: string): Promise<any>
   /** List keys with pagination support. Optionally pass `asOf` for time-travel. */
-  kvListPaginated(prefix?: string | undefined | null, limit?: number | undefined | null, asOf?: number | undefined | null): Promise<any>
+  kvListPaginated(prefix?: string | undefined | null, limit?: number | undefined | null, asOf?: number | string | undefined | null): Promise<any>
   /** List events by type with pagination support. Optionally pass `asOf` for time-travel. */
-  eventListPaginated(eventType: string, limit?: number | undefined | null, after?: number | undefined | null, asOf?: number | undefined | null): Promise<any>
+  eventListPaginated(eventType: string, limit?: number | undefined | null, after?: number | undefined | null, asOf?: number | string | undefined | null): Promise<any>
   /**
    * Search for similar vectors with optional filter and metric override.
    * Optionally pass `asOf` for time-travel.
+   *
+   * With `spaces`, the collection is searched in each selected space and
+   * the best `k` matches overall are returned, each tagged with `space`.
+   *
+   * `withinRadius` and `boundingBox` filters match metadata fields holding
+   * a `{ lat, lon }` point.
    */
-  vectorSearchFiltered(collection: string, query: Array<number>, k: number, metric?: string | undefined | null, filter?: Array<any> | undefined | null, asOf?: number | undefined | null): Promise<any>
+  vectorSearchFiltered(collection: string, query: Array<number>, k: number, metric?: DistanceMetric | `${DistanceMetric}` | undefined | null, filter?: Array<any> | undefined | null, asOf?: number | string | undefined | null, spaces?: Array<string> | string | undefined | null, operationId?: number | undefined | null): Promise<any>
   /** Create a new space explicitly. */
   spaceCreate(space: string): Promise<void>
   /** Check if a space exists in the current branch. */
@@ -221,10 +1187,61 @@ export declare class Strata {
    * Persisted to strata.toml.
    */
   configureModel(endpoint: string, model: string, apiKey?: string | undefined | null, timeoutMs?: number | undefined | null): Promise<void>
-  /** Search across multiple primitives for matching content. */
-  search(query: string, options?: JsSearchOptions | undefined | null): Promise<any>
-  /** Apply retention policy to trigger garbage collection. */
-  retentionApply(): Promise<void>
+  /**
+   * Search across multiple primitives for matching content.
+   *
+   * With `spaces`, each selected space is searched and the best `k`
+   * hits overall are returned, re-ranked and tagged with `space`.
+   */
+  search(query: string, options?: JsSearchOptions | undefined | null, operationId?: number | undefined | null): Promise<any>
+  /**
+   * Apply retention policy to trigger garbage collection.
+   *
+   * Rules set with `retentionConfigure` are applied first, on every
+   * branch, then the database-wide retention pass runs. Returns what the
+   * rules removed: `versionsRemoved` per primitive, `bytesReclaimed`,
+   * the oldest surviving timestamp of each trimmed space, and
+   * `durationMs` for the whole call.
+   */
+  retentionApply(): Promise<any>
+  /**
+   * Set the retention rule for a space and/or primitive.
+   *
+   * The most specific rule wins: space + primitive, then space, then
+   * primitive, then the database-wide rule. A rule with neither
+   * `maxVersions` nor `maxAge` keeps all history for its scope.
+   */
+  retentionConfigure(options: JsRetentionOptions): Promise<void>
+  /**
+   * Remove the retention rule for a space and/or primitive. Returns
+   * `true` if a rule existed.
+   */
+  retentionReset(space?: string | undefined | null, primitive?: string | undefined | null): Promise<boolean>
+  /** Get all configured retention rules. */
+  retentionGetConfig(): Promise<any>
+  /**
+   * Trim history on the current branch now, independent of the
+   * retention rules.
+   *
+   * Versions older than `before`, or beyond the newest `keepLast` per
+   * key, are removed; retention pins are still honoured. Returns a report
+   * shaped like `retentionApply`'s.
+   */
+  historyTrim(options: JsHistoryTrimOptions): Promise<any>
+  /**
+   * Pin history so `retentionApply` keeps it.
+   *
+   * A key pin keeps every version of that key on every branch (pins act
+   * on write timestamps, so versions of other keys in the same space
+   * that were current at those writes are kept too). A tag or timestamp
+   * pin keeps the versions visible at that moment, in every space. A tag pin records the tag's timestamp, so deleting the tag
+   * later does not release it.
+   */
+  retentionPin(pin: JsRetentionPin): Promise<any>
+  /** Remove a pin set with `retentionPin`. Returns `true` if it existed. */
+  retentionUnpin(pin: JsRetentionPin): Promise<boolean>
+  /** List retention pins. */
+  retentionListPins(): Promise<any>
   /**
    * Execute any command by name with JSON arguments.
    *
@@ -255,21 +1272,33 @@ export declare class Strata {
   /**
    * Close the database, releasing all resources.
    *
-   * After calling `close()`, any further method call on this instance will
-   * fail with a "Lock poisoned" or similar error.  This mirrors the
-   * `client.close()` pattern used by every major Node.js database driver.
+   * After calling `close()`, any further method call on this instance
+   * fails with a `[CLOSED]` error; calls already running finish first.
+   * This mirrors the `client.close()` pattern used by every major Node.js
+   * database driver. When other handles share the database, only this
+   * handle's share is given up; the last one to close drops the database.
+   * Closing twice is a no-op.
    */
   close(): Promise<void>
   /** Get the time range (oldest and latest timestamps) for the current branch. */
   timeRange(): Promise<any>
+  /**
+   * Diff the current branch against itself between two timestamps (or
+   * tag names).
+   *
+   * Reports the KV, state, and JSON keys added, removed, and modified
+   * between `tsA` and `tsB`, per space. Events, vectors, and graphs are
+   * not compared.
+   */
+  diffTime(tsA: number | string, tsB: number | string, options?: JsDiffTimeOptions | undefined | null): Promise<any>
   /** Batch put multiple KV entries. */
-  kvBatchPut(entries: Array<any>): Promise<any>
+  kvBatchPut(entries: Array<any>, jsonLimits?: JsJsonLimits | undefined | null): Promise<any>
   /** Batch set multiple state cells. */
-  stateBatchSet(entries: Array<any>): Promise<any>
+  stateBatchSet(entries: Array<any>, jsonLimits?: JsJsonLimits | undefined | null): Promise<any>
   /** Batch append multiple events. */
-  eventBatchAppend(entries: Array<any>): Promise<any>
+  eventBatchAppend(entries: Array<any>, jsonLimits?: JsJsonLimits | undefined | null): Promise<any>
   /** Batch set multiple JSON documents. */
-  jsonBatchSet(entries: Array<any>): Promise<any>
+  jsonBatchSet(entries: Array<any>, jsonLimits?: JsJsonLimits | undefined | null): Promise<any>
   /** Batch get multiple JSON documents. */
   jsonBatchGet(entries: Array<any>): Promise<any>
   /** Batch delete multiple JSON documents. */
@@ -284,6 +1313,23 @@ export declare class Strata {
   embedBatch(texts: Array<string>): Promise<Array<Array<number>>>
   /** Get the embedding pipeline status. */
   embedStatus(): Promise<any>
+  /**
+   * Chunk a document, embed every chunk, and upsert the chunks into a
+   * vector collection in a single call.
+   *
+   * Chunks are stored under `{key}#{index}` with the caller's metadata
+   * plus `source`, `chunk`, and `text` fields. Re-ingesting a key
+   * replaces its chunks: those past the new chunk count are deleted.
+   * Returns the chunk keys and their versions in order.
+   */
+  ingestDocument(collection: string, key: string, text: string, options?: JsIngestOptions | undefined | null): Promise<any>
+  /**
+   * Score candidate texts against a query with a cross-encoder.
+   *
+   * Returns one entry per candidate, sorted by descending score, with the
+   * candidate's original `index` so callers can map back to their hits.
+   */
+  rerank(query: string, candidates: Array<string>, options?: JsRerankOptions | undefined | null): Promise<any>
   /** Generate text from a model. */
   generate(model: string, prompt: string, options?: any | undefined | null): Promise<any>
   /** Tokenize text using a model's tokenizer. */
@@ -300,6 +1346,16 @@ export declare class Strata {
   modelsLocal(): Promise<any>
   /** Get WAL durability counters. */
   durabilityCounters(): Promise<any>
+  /**
+   * WAL activity since open, the size and number of the WAL segments on
+   * disk (`null` for an in-memory database), and when the WAL was last
+   * synced.
+   *
+   * `lastFlushAt` covers `flush()` on any handle and the engine's own
+   * syncs. The engine doesn't report when it synced, so one of its syncs
+   * is dated when `walStats()` or `durabilityCounters()` first sees it.
+   */
+  walStats(): Promise<any>
   /** Create a new graph. */
   graphCreate(graph: string, cascadePolicy?: string | undefined | null): Promise<void>
   /** Delete a graph. */
@@ -365,3 +1421,22 @@ export declare class Strata {
   /** Single-Source Shortest Path (Dijkstra). */
   graphSssp(graph: string, source: string, direction?: string | undefined | null): Promise<any>
 }
+/** Reader over an exported bundle. Obtained via `db.branchExportOpen()`. */
+export declare class BundleReader {
+  /** Read the next chunk of at most `size` bytes, or `null` at the end. */
+  read(size: number): Promise<Buffer | null>
+  /** Release the staged bundle. Further reads return `null`. */
+  close(): void
+}
+/**
+ * Writer that stages a streamed bundle and imports it on `finish()`.
+ * Obtained via `db.branchImportOpen()`.
+ */
+export declare class BundleWriter {
+  /** Append a chunk of bundle bytes. */
+  write(chunk: Buffer): Promise<void>
+  /** Import the staged bundle and release it. */
+  finish(): Promise<any>
+  /** Discard the staged bundle without importing it. */
+  abort(): void
+}
@@ -310,7 +310,10 @@ if (!nativeBinding) {
   throw new Error(`Failed to load native binding`)
 }
 
-const { Strata, setup } = nativeBinding
+const { Strata, DistanceMetric, MergeStrategy, FilterOp, setup } = nativeBinding
 
 module.exports.Strata = Strata
+module.exports.DistanceMetric = DistanceMetric
+module.exports.MergeStrategy = MergeStrategy
+module.exports.FilterOp = FilterOp
 module.exports.setup = setup
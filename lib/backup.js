@@ -0,0 +1,205 @@
+'use strict';
+
+/**
+ * Whole-database backups: one bundle per branch, plus the records the
+ * binding keeps on the `_system_` branch (branch annotations, tags, and
+ * retention rules), described by a manifest. An incremental backup holds
+ * delta bundles of the changes made since the backup it builds on.
+ */
+
+const fs = require('fs');
+const path = require('path');
+const { NotFoundError, StateError, ValidationError } = require('./errors.js');
+
+const MANIFEST = 'manifest.json';
+const FORMAT_VERSION = 1;
+
+// `_system_` KV prefixes of the binding's own records.
+const SYSTEM_PREFIXES = ['branch-meta/', 'tag/', 'retention/', 'retention-pin/'];
+
+/**
+ * Read and check the manifest of the backup in `dir`.
+ * @param {string} dir
+ */
+function readManifest(dir) {
+  let text;
+  try {
+    text = fs.readFileSync(path.join(dir, MANIFEST), 'utf8');
+  } catch (err) {
+    throw new NotFoundError(`No backup at ${dir}: ${err.message}`);
+  }
+  let manifest;
+  try {
+    manifest = JSON.parse(text);
+  } catch (err) {
+    throw new ValidationError(`Invalid backup manifest in ${dir}: ${err.message}`);
+  }
+  if (manifest.format !== FORMAT_VERSION) {
+    throw new ValidationError(`Unsupported backup format ${manifest.format} in ${dir}`);
+  }
+  return manifest;
+}
+
+/**
+ * Back up every branch of `db` into `destDir`, which must be empty or
+ * missing.
+ * @param {object} db
+ * @param {string} destDir
+ * @param {{ incrementalFrom?: string }} [opts] - See `BackupOptions` in stratadb.d.ts.
+ */
+async function backup(db, destDir, opts) {
+  const dest = path.resolve(destDir);
+  if (fs.existsSync(dest) && fs.readdirSync(dest).length > 0) {
+    throw new StateError(`Backup directory ${dest} is not empty`);
+  }
+  const base = opts?.incrementalFrom != null ? path.resolve(opts.incrementalFrom) : null;
+  const baseManifest = base != null ? readManifest(base) : null;
+  fs.mkdirSync(dest, { recursive: true });
+
+  // Branches the base backup has get a delta; new ones a full bundle.
+  const inBase = new Set(baseManifest?.branches.map((b) => b.name));
+  const branches = [];
+  const names = await db.listBranches(null, null, true);
+  for (const [i, name] of names.entries()) {
+    const { createdAt } = await db.branchGet(name);
+    branches.push({ name, file: `branch-${i}.bundle`, delta: inBase.has(name), createdAt });
+  }
+
+  // Every branch is exported, and the system records read, as of one version.
+  const createdAt = Date.now();
+  const { version, exports, system: records } = await db.backupBranches(
+    branches.map(({ name, file, delta }) => ({
+      branch: name,
+      path: path.join(dest, file),
+      sinceVersion: delta ? baseManifest.version : undefined,
+    })),
+    SYSTEM_PREFIXES,
+  );
+  for (const [i, { entryCount }] of exports.entries()) {
+    branches[i].entryCount = entryCount;
+  }
+
+  const manifest = {
+    format: FORMAT_VERSION,
+    createdAt,
+    version,
+    // Relative, so a chain of backups can be moved together.
+    incrementalFrom: base != null ? path.relative(dest, base) : null,
+    sinceVersion: baseManifest?.version ?? null,
+    branches,
+    system: records,
+  };
+  fs.writeFileSync(path.join(dest, MANIFEST), JSON.stringify(manifest, null, 2));
+  return {
+    path: dest,
+    version,
+    incremental: base != null,
+    branches: branches.length,
+    entryCount: branches.reduce((sum, b) => sum + b.entryCount, 0),
+  };
+}
+
+/**
+ * The backup in `dir` and the ones it builds on, oldest first.
+ * @param {string} dir
+ */
+function backupChain(dir) {
+  const chain = [];
+  const seen = new Set();
+  for (let current = path.resolve(dir); current != null;) {
+    if (seen.has(current)) {
+      throw new ValidationError(`Backup ${dir} builds on itself through ${current}`);
+    }
+    seen.add(current);
+    const manifest = readManifest(current);
+    chain.unshift({ dir: current, manifest });
+    current = manifest.incrementalFrom != null
+      ? path.resolve(current, manifest.incrementalFrom)
+      : null;
+  }
+  return chain;
+}
+
+// A rewind replays nothing past the backup: it can only go back to a
+// moment whose versions the newest backup in the chain still holds.
+function checkRewindPoint(rewindTo, { dir, manifest }) {
+  if (typeof rewindTo !== 'number' || !Number.isInteger(rewindTo) || rewindTo < 0) {
+    throw new ValidationError('rewindTo must be a timestamp in microseconds since the epoch');
+  }
+  if (rewindTo > manifest.createdAt * 1000) {
+    throw new ValidationError(
+      `rewindTo is after backup ${dir} was taken; back up again to rewind to a later time`,
+    );
+  }
+}
+
+/**
+ * Restore the backup in `backupDir`, and the backups it builds on, into a
+ * new database at `targetPath`.
+ * @param {string} backupDir
+ * @param {string} targetPath
+ * @param {(path: string, options: object) => Promise<object>} open
+ * @param {{ rewindTo?: number }} [opts] - See `RestoreOptions` in stratadb.d.ts.
+ */
+async function restore(backupDir, targetPath, open, opts) {
+  const chain = backupChain(backupDir);
+  const rewindTo = opts?.rewindTo;
+  if (rewindTo != null) {
+    checkRewindPoint(rewindTo, chain[chain.length - 1]);
+  }
+  const db = await open(targetPath, { errorIfExists: true });
+  try {
+    for (const { dir, manifest } of chain) {
+      for (const { name, file, delta } of manifest.branches) {
+        const bundle = path.join(dir, file);
+        // A new database already has its default branch, empty.
+        const onConflict = !delta && (await db.branchExists(name)) ? 'merge' : undefined;
+        await db.branchImport(bundle, onConflict ? { onConflict } : undefined);
+      }
+    }
+    const last = chain[chain.length - 1].manifest;
+    const kept = new Set(last.branches.map((b) => b.name));
+    for (const name of await db.listBranches(null, null, true)) {
+      if (!kept.has(name)) {
+        await db.deleteBranch(name);
+      }
+    }
+    // The system records come from the first backup taken after the
+    // rewind point, the nearest snapshot of them there is.
+    let { system: records } = last;
+    if (rewindTo != null) {
+      // Imported branches are created anew, so when each branch was
+      // created comes from the manifest.
+      for (const { name, createdAt } of last.branches) {
+        if (createdAt > rewindTo && name !== 'default') {
+          await db.deleteBranch(name);
+          kept.delete(name);
+        } else {
+          await db.branchRewind(name, rewindTo);
+        }
+      }
+      const after = chain.find(({ manifest }) => manifest.createdAt * 1000 >= rewindTo);
+      records = after.manifest.system;
+    }
+    const system = await db.systemBranch();
+    for (const [key, value] of records) {
+      await system.kvPut(key, value);
+    }
+    return {
+      path: path.resolve(targetPath),
+      version: last.version,
+      rewoundTo: rewindTo ?? null,
+      backups: chain.length,
+      branches: kept.size,
+    };
+  } finally {
+    await db.close();
+  }
+}
+
+module.exports = {
+  backup,
+  backupChain,
+  readManifest,
+  restore,
+};
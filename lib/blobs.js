@@ -0,0 +1,122 @@
+'use strict';
+
+/**
+ * Blobs: byte content stored in chunks, so files of hundreds of megabytes
+ * are written and read a chunk at a time instead of being held in memory
+ * or converted as values. `blobPut` writes the chunks of an upload under a
+ * fresh id and then points the blob at them, so readers see the old
+ * content or the new, never a mix.
+ */
+
+const crypto = require('crypto');
+const { Readable } = require('stream');
+const { ConflictError, ValidationError } = require('./errors.js');
+
+/** Default bytes per chunk (1 MiB). */
+const DEFAULT_CHUNK_SIZE = 1024 * 1024;
+
+function toBuffer(piece) {
+  if (typeof piece === 'string') {
+    return Buffer.from(piece);
+  }
+  if (ArrayBuffer.isView(piece)) {
+    return Buffer.from(piece.buffer, piece.byteOffset, piece.byteLength);
+  }
+  throw new ValidationError('blobPut() takes a Buffer, a typed array, or a stream of them');
+}
+
+// The bytes of `source` in pieces of `chunkSize`, the last one shorter.
+async function* chunksOf(source, chunkSize) {
+  let pending = [];
+  let pendingBytes = 0;
+  for await (const piece of ArrayBuffer.isView(source) ? [source] : source) {
+    let buf = toBuffer(piece);
+    while (pendingBytes + buf.length >= chunkSize) {
+      const take = chunkSize - pendingBytes;
+      pending.push(buf.subarray(0, take));
+      yield Buffer.concat(pending, chunkSize);
+      buf = buf.subarray(take);
+      pending = [];
+      pendingBytes = 0;
+    }
+    if (buf.length > 0) {
+      pending.push(buf);
+      pendingBytes += buf.length;
+    }
+  }
+  if (pendingBytes > 0) {
+    yield Buffer.concat(pending, pendingBytes);
+  }
+}
+
+/**
+ * Store the bytes of `source` as blob `key`, replacing its content.
+ * @param {object} db
+ * @param {string} key
+ * @param {Buffer | Uint8Array | AsyncIterable<Buffer | Uint8Array | string>} source
+ * @param {{ chunkSize?: number }} [opts]
+ */
+async function blobPut(db, key, source, opts) {
+  const chunkSize = opts?.chunkSize ?? DEFAULT_CHUNK_SIZE;
+  if (!Number.isInteger(chunkSize) || chunkSize <= 0) {
+    throw new ValidationError('chunkSize must be a positive integer');
+  }
+  if (!ArrayBuffer.isView(source) && typeof source?.[Symbol.asyncIterator] !== 'function') {
+    throw new ValidationError('blobPut() takes a Buffer, a typed array, or a readable stream');
+  }
+  const upload = crypto.randomUUID();
+  let chunks = 0;
+  let size = 0;
+  try {
+    for await (const chunk of chunksOf(source, chunkSize)) {
+      await db.blobWriteChunk(upload, chunks, chunk);
+      chunks += 1;
+      size += chunk.length;
+    }
+  } catch (err) {
+    await db.blobDiscard(upload, chunks).catch(() => {});
+    throw err;
+  }
+  return db.blobCommit(key, upload, size, chunks);
+}
+
+async function readChunk(db, key, stat, index) {
+  const chunk = await db.blobReadChunk(stat.upload, index);
+  if (chunk === null) {
+    throw new ConflictError(`Blob '${key}' was replaced or deleted while it was being read`);
+  }
+  return chunk;
+}
+
+/**
+ * The content of blob `key` as a Buffer, or as a Readable with
+ * `{ stream: true }`; null if it doesn't exist.
+ */
+async function blobGet(db, key, opts) {
+  const stat = await db.blobStat(key);
+  if (stat === null) {
+    return null;
+  }
+  if (opts?.stream) {
+    let index = 0;
+    return new Readable({
+      async read() {
+        try {
+          this.push(index < stat.chunks ? await readChunk(db, key, stat, index++) : null);
+        } catch (err) {
+          this.destroy(err);
+        }
+      },
+    });
+  }
+  const chunks = [];
+  for (let index = 0; index < stat.chunks; index++) {
+    chunks.push(await readChunk(db, key, stat, index));
+  }
+  return Buffer.concat(chunks, stat.size);
+}
+
+module.exports = {
+  blobGet,
+  blobPut,
+};
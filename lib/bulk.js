@@ -0,0 +1,129 @@
+'use strict';
+
+/**
+ * Batched writes for `bulkLoad()`: writes made through the loader are
+ * buffered per primitive (and per collection, for vectors) and written with
+ * the batch methods, one engine transaction per batch. The handle skips the
+ * flushes its `syncMode` would make while the load runs and flushes once
+ * when it finishes. Index and embedding maintenance is not deferred; the
+ * engine does it as each batch is written.
+ */
+
+const { StateError, ValidationError } = require('./errors.js');
+
+/** Default entries per batch. */
+const DEFAULT_BATCH_SIZE = 5000;
+
+// How each buffer is written.
+const WRITERS = {
+  kv: (db, entries) => db.kvBatchPut(entries),
+  state: (db, entries) => db.stateBatchSet(entries),
+  json: (db, entries) => db.jsonBatchSet(entries),
+  event: (db, entries) => db.eventBatchAppend(entries),
+  vector: (db, entries, collection) => db.vectorBatchUpsert(collection, entries),
+};
+
+class BulkLoader {
+  constructor(db, batchSize, onProgress) {
+    this._db = db;
+    this._batchSize = batchSize;
+    this._onProgress = onProgress;
+    this._buffers = new Map();
+    this._finished = false;
+    this.entries = 0;
+    this.failed = 0;
+    this.batches = 0;
+  }
+
+  kvPut(key, value) {
+    return this._add('kv', null, { key, value });
+  }
+
+  stateSet(cell, value) {
+    return this._add('state', null, { cell, value });
+  }
+
+  jsonSet(key, value) {
+    return this._add('json', null, { key, path: '$', value });
+  }
+
+  eventAppend(eventType, payload) {
+    return this._add('event', null, { eventType, payload });
+  }
+
+  vectorUpsert(collection, key, vector, metadata) {
+    return this._add('vector', collection, { key, vector, metadata });
+  }
+
+  async _add(kind, collection, entry) {
+    if (this._finished) {
+      throw new StateError('The loader cannot be used after bulkLoad() returns');
+    }
+    const id = collection == null ? kind : `${kind}/${collection}`;
+    let buffer = this._buffers.get(id);
+    if (!buffer) {
+      buffer = { kind, collection, entries: [] };
+      this._buffers.set(id, buffer);
+    }
+    buffer.entries.push(entry);
+    if (buffer.entries.length >= this._batchSize) {
+      await this._write(buffer);
+    }
+  }
+
+  async _write(buffer) {
+    const { kind, collection, entries } = buffer;
+    buffer.entries = [];
+    const results = await WRITERS[kind](this._db, entries, collection);
+    this.entries += entries.length;
+    this.batches += 1;
+    if (kind !== 'vector') {
+      this.failed += results.filter((r) => r.error != null).length;
+    }
+    this._onProgress?.({ entries: this.entries, batches: this.batches });
+  }
+
+  async _finish() {
+    for (const buffer of this._buffers.values()) {
+      if (buffer.entries.length > 0) {
+        await this._write(buffer);
+      }
+    }
+    this._finished = true;
+  }
+}
+
+/**
+ * Run `callback` with a loader, then write what it buffered and flush.
+ * @param {object} db
+ * @param {(loader: BulkLoader) => Promise<void>} callback
+ * @param {object} [opts] - See `BulkLoadOptions` in stratadb.d.ts.
+ */
+async function bulkLoad(db, callback, opts) {
+  const { batchSize = DEFAULT_BATCH_SIZE, onProgress } = opts ?? {};
+  if (typeof callback !== 'function') {
+    throw new ValidationError('bulkLoad() needs a callback');
+  }
+  if (!Number.isInteger(batchSize) || batchSize < 1) {
+    throw new ValidationError('batchSize must be a positive integer');
+  }
+  const started = performance.now();
+  const loader = new BulkLoader(db, batchSize, onProgress);
+  try {
+    await callback(loader);
+    await loader._finish();
+  } finally {
+    loader._finished = true;
+  }
+  await db.flush();
+  return {
+    entries: loader.entries,
+    failed: loader.failed,
+    batches: loader.batches,
+    durationMs: performance.now() - started,
+  };
+}
+
+module.exports = {
+  bulkLoad,
+};
@@ -0,0 +1,118 @@
+'use strict';
+
+/**
+ * Changelogs for primary→replica replication: the KV, state, and JSON
+ * writes a database committed after a version, as records
+ *
+ *   { version, branch, space, primitive, key, op: 'put' | 'delete', value }
+ *
+ * oldest first, which a replica applies in order. Written to a stream,
+ * each record is one NDJSON line, with Buffers as `{"$base64":"..."}`.
+ */
+
+const readline = require('readline');
+const { once } = require('events');
+const { ValidationError } = require('./errors.js');
+const { decodeValue, encodeValue } = require('./dump.js');
+
+/** Records per `changelogRead` page. */
+const PAGE_SIZE = 1000;
+
+/** Default records per `changelogApplyBatch` call. */
+const DEFAULT_BATCH_SIZE = 1000;
+
+/**
+ * Send the writes made after `sinceVersion` to `sink`.
+ * @param {object} db
+ * @param {number} sinceVersion
+ * @param {((record: object) => unknown) | NodeJS.WritableStream} sink
+ */
+async function changelogExport(db, sinceVersion, sink) {
+  if (!Number.isInteger(sinceVersion) || sinceVersion < 0) {
+    throw new ValidationError('sinceVersion must be a non-negative integer');
+  }
+  if (typeof sink !== 'function' && typeof sink?.write !== 'function') {
+    throw new ValidationError('changelogExport() needs a function or a writable stream');
+  }
+  let after = sinceVersion;
+  let count = 0;
+  let page;
+  do {
+    page = await db.changelogRead(sinceVersion, after, PAGE_SIZE);
+    for (const record of page.records) {
+      if (typeof sink === 'function') {
+        await sink(record);
+      } else {
+        const line = `${JSON.stringify({ ...record, value: encodeValue(record.value) })}\n`;
+        if (!sink.write(line)) {
+          await once(sink, 'drain');
+        }
+      }
+    }
+    count += page.records.length;
+    after = page.version;
+  } while (page.more);
+  return { version: page.version, records: count };
+}
+
+// Records from an iterable of records, or from the NDJSON lines of a byte
+// stream.
+async function* readRecords(source) {
+  if (source?.readableObjectMode === false) {
+    let lineNo = 0;
+    for await (const text of readline.createInterface({ input: source, crlfDelay: Infinity })) {
+      lineNo += 1;
+      if (text.trim() === '') {
+        continue;
+      }
+      let record;
+      try {
+        record = JSON.parse(text);
+      } catch (err) {
+        throw new ValidationError(`Line ${lineNo} is not valid JSON: ${err.message}`);
+      }
+      yield { ...record, value: decodeValue(record.value ?? null) };
+    }
+    return;
+  }
+  if (typeof source?.[Symbol.asyncIterator] !== 'function'
+    && typeof source?.[Symbol.iterator] !== 'function') {
+    throw new ValidationError('changelogApply() needs an iterable of records or a readable stream');
+  }
+  yield* source;
+}
+
+/**
+ * Apply the records of a changelog, in order.
+ * @param {object} db
+ * @param {Iterable<object> | AsyncIterable<object> | NodeJS.ReadableStream} source
+ * @param {{ batchSize?: number }} [opts]
+ */
+async function changelogApply(db, source, opts) {
+  const { batchSize = DEFAULT_BATCH_SIZE } = opts ?? {};
+  if (!Number.isInteger(batchSize) || batchSize < 1) {
+    throw new ValidationError('batchSize must be a positive integer');
+  }
+  let batch = [];
+  let applied = 0;
+  let version = null;
+  for await (const record of readRecords(source)) {
+    batch.push(record);
+    if (record.version != null) {
+      version = Math.max(version ?? 0, record.version);
+    }
+    if (batch.length >= batchSize) {
+      applied += await db.changelogApplyBatch(batch);
+      batch = [];
+    }
+  }
+  if (batch.length > 0) {
+    applied += await db.changelogApplyBatch(batch);
+  }
+  return { applied, version };
+}
+
+module.exports = {
+  changelogApply,
+  changelogExport,
+};
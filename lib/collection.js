@@ -0,0 +1,93 @@
+'use strict';
+
+/**
+ * Collections: JSON documents of one kind, stored as `<name>/<id>`, with
+ * the id taken from each document by `keyFn` and an optional JSON Schema
+ * the binding checks on every `put()`. The schema is kept in the branch,
+ * so every handle writing through `collection(name)` enforces it; plain
+ * `jsonSet()` calls on a collection's keys are not checked.
+ */
+
+const { ValidationError } = require('./errors.js');
+
+function defaultKey(doc) {
+  return doc?.id;
+}
+
+class Collection {
+  constructor(db, name, opts) {
+    if (typeof name !== 'string' || name === '' || name.includes('/')) {
+      throw new ValidationError('A collection name must be a non-empty string without "/"');
+    }
+    if (opts?.keyFn != null && typeof opts.keyFn !== 'function') {
+      throw new ValidationError('keyFn must be a function');
+    }
+    this._db = db;
+    this._schema = opts?.schema;
+    this._keyFn = opts?.keyFn ?? defaultKey;
+    this._defined = null;
+    this.name = name;
+  }
+
+  _key(id) {
+    return `${this.name}/${id}`;
+  }
+
+  _idOf(doc) {
+    const id = this._keyFn(doc);
+    if ((typeof id !== 'string' || id === '') && !Number.isFinite(id)) {
+      throw new ValidationError(
+        `keyFn must return a non-empty string or a number for documents of '${this.name}'`,
+      );
+    }
+    return String(id);
+  }
+
+  // Register the schema, once, before the first write.
+  async _define() {
+    if (this._schema == null) {
+      return;
+    }
+    this._defined ??= this._db.collectionDefine(this.name, this._schema).catch((err) => {
+      this._defined = null;
+      throw err;
+    });
+    await this._defined;
+  }
+
+  /** The registered schema, or null. */
+  schema() {
+    return this._db.collectionSchema(this.name);
+  }
+
+  async put(doc, opts) {
+    const id = this._idOf(doc);
+    await this._define();
+    return this._db.collectionPut(this.name, id, doc, opts?.jsonLimits);
+  }
+
+  get(id, opts) {
+    return this._db.jsonGet(this._key(id), '$', opts?.asOf);
+  }
+
+  delete(id) {
+    return this._db.jsonDelete(this._key(id), '$');
+  }
+
+  history(id, opts) {
+    return this._db.jsonHistory(this._key(id), opts);
+  }
+
+  async query(filter, opts) {
+    const prefix = `${this.name}/`;
+    const matches = await this._db.jsonQuery(filter ?? [], {
+      ...opts,
+      prefix: prefix + (opts?.prefix ?? ''),
+    });
+    return matches.map(({ key, value }) => ({ id: key.slice(prefix.length), value }));
+  }
+}
+
+module.exports = {
+  Collection,
+};
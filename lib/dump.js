@@ -0,0 +1,192 @@
+'use strict';
+
+/**
+ * NDJSON dumps: one line per KV entry, state cell, or JSON document,
+ *
+ *   {"space":"default","primitive":"kv","key":"user:1","value":{...}}
+ *
+ * Buffers are written as `{"$base64":"..."}` and read back as Buffers.
+ * Events, vectors, and graphs are not dumped; branch bundles carry those.
+ */
+
+const fs = require('fs');
+const readline = require('readline');
+const { once } = require('events');
+const { NotFoundError, ValidationError } = require('./errors.js');
+
+const PRIMITIVES = ['kv', 'state', 'json'];
+
+/** Entries read per `dumpPage` call. */
+const PAGE_SIZE = 1000;
+
+/** Default entries written per `loadBatch` call. */
+const DEFAULT_BATCH_SIZE = 1000;
+
+function encodeValue(value) {
+  if (Buffer.isBuffer(value)) {
+    return { $base64: value.toString('base64') };
+  }
+  if (Array.isArray(value)) {
+    return value.map(encodeValue);
+  }
+  if (value !== null && typeof value === 'object') {
+    const out = {};
+    for (const [k, v] of Object.entries(value)) {
+      out[k] = encodeValue(v);
+    }
+    return out;
+  }
+  return value;
+}
+
+function decodeValue(value) {
+  if (Array.isArray(value)) {
+    return value.map(decodeValue);
+  }
+  if (value !== null && typeof value === 'object') {
+    const keys = Object.keys(value);
+    if (keys.length === 1 && keys[0] === '$base64' && typeof value.$base64 === 'string') {
+      return Buffer.from(value.$base64, 'base64');
+    }
+    const out = {};
+    for (const [k, v] of Object.entries(value)) {
+      out[k] = decodeValue(v);
+    }
+    return out;
+  }
+  return value;
+}
+
+// Resolve a path or stream argument. Streams opened here are closed here.
+function openTarget(target, open, kind) {
+  if (typeof target === 'string') {
+    return { stream: open(target), owned: true };
+  }
+  if (target == null || typeof target !== 'object') {
+    throw new ValidationError(`${kind} needs a path or a stream`);
+  }
+  return { stream: target, owned: false };
+}
+
+/**
+ * Write the current branch's KV, state, and JSON entries to `target` as
+ * NDJSON.
+ * @param {object} db
+ * @param {string | NodeJS.WritableStream} target
+ * @param {object} [opts] - See `DumpOptions` in stratadb.d.ts.
+ */
+async function dump(db, target, opts) {
+  const { space, prefix, primitive, onProgress } = opts ?? {};
+  if (primitive != null && !PRIMITIVES.includes(primitive)) {
+    throw new ValidationError(`Unknown primitive '${primitive}'; expected kv, state, or json`);
+  }
+  const spaces = space != null ? [space] : await db.listSpaces();
+  const primitives = primitive != null ? [primitive] : PRIMITIVES;
+  const { stream, owned } = openTarget(target, (p) => fs.createWriteStream(p), 'dump()');
+  let entries = 0;
+  try {
+    for (const s of spaces) {
+      for (const p of primitives) {
+        let cursor = null;
+        do {
+          const page = await db.dumpPage(s, p, prefix ?? null, cursor, PAGE_SIZE);
+          let text = '';
+          for (const [key, value] of page.entries) {
+            const line = { space: s, primitive: p, key, value: encodeValue(value) };
+            text += `${JSON.stringify(line)}\n`;
+          }
+          if (text !== '' && !stream.write(text)) {
+            await once(stream, 'drain');
+          }
+          entries += page.entries.length;
+          cursor = page.cursor;
+          onProgress?.({ entries, space: s, primitive: p });
+        } while (cursor != null);
+      }
+    }
+  } finally {
+    if (owned) {
+      stream.end();
+      await once(stream, 'finish');
+    }
+  }
+  return { entries, spaces: spaces.length };
+}
+
+/**
+ * Write the NDJSON entries read from `source` into the current branch.
+ * @param {object} db
+ * @param {string | NodeJS.ReadableStream} source
+ * @param {object} [opts] - See `LoadOptions` in stratadb.d.ts.
+ */
+async function load(db, source, opts) {
+  const { batchSize = DEFAULT_BATCH_SIZE, onProgress } = opts ?? {};
+  if (!Number.isInteger(batchSize) || batchSize < 1) {
+    throw new ValidationError('batchSize must be a positive integer');
+  }
+  const { stream, owned } = openTarget(source, (p) => fs.createReadStream(p), 'load()');
+  if (owned) {
+    try {
+      await once(stream, 'open');
+    } catch (err) {
+      throw new NotFoundError(`Cannot read ${source}: ${err.message}`);
+    }
+  }
+  const defaultSpace = await db.currentSpace();
+  const lines = readline.createInterface({ input: stream, crlfDelay: Infinity });
+  let batch = [];
+  let entries = 0;
+  let lineNo = 0;
+  const flush = async () => {
+    entries += await db.loadBatch(batch);
+    batch = [];
+    onProgress?.({ entries, line: lineNo });
+  };
+  try {
+    for await (const text of lines) {
+      lineNo += 1;
+      if (text.trim() === '') {
+        continue;
+      }
+      let line;
+      try {
+        line = JSON.parse(text);
+      } catch (err) {
+        throw new ValidationError(`Line ${lineNo} is not valid JSON: ${err.message}`);
+      }
+      if (line === null || typeof line !== 'object' || typeof line.key !== 'string') {
+        throw new ValidationError(`Line ${lineNo} needs a string key`);
+      }
+      if (!PRIMITIVES.includes(line.primitive)) {
+        throw new ValidationError(
+          `Line ${lineNo} has unknown primitive '${line.primitive}'; expected kv, state, or json`,
+        );
+      }
+      batch.push({
+        space: line.space ?? defaultSpace,
+        primitive: line.primitive,
+        key: line.key,
+        value: decodeValue(line.value ?? null),
+      });
+      if (batch.length >= batchSize) {
+        await flush();
+      }
+    }
+    if (batch.length > 0) {
+      await flush();
+    }
+  } finally {
+    lines.close();
+    if (owned) {
+      stream.destroy();
+    }
+  }
+  return { entries, lines: lineNo };
+}
+
+module.exports = {
+  decodeValue,
+  dump,
+  encodeValue,
+  load,
+};
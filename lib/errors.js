@@ -67,6 +67,27 @@ class IoError extends StrataError {
   }
 }
 
+class ResourceExhaustedError extends StrataError {
+  constructor(message) {
+    super(message, 'RESOURCE_EXHAUSTED');
+    this.name = 'ResourceExhaustedError';
+  }
+}
+
+class TimeoutError extends StrataError {
+  constructor(message) {
+    super(message, 'TIMEOUT');
+    this.name = 'TimeoutError';
+  }
+}
+
+class StrataClosedError extends StrataError {
+  constructor(message) {
+    super(message, 'CLOSED');
+    this.name = 'StrataClosedError';
+  }
+}
+
 /**
  * Map from error code prefix to typed error class.
  * @type {Record<string, typeof StrataError>}
@@ -79,6 +100,9 @@ const ERROR_MAP = {
   CONSTRAINT: ConstraintError,
   ACCESS_DENIED: AccessDeniedError,
   IO: IoError,
+  RESOURCE_EXHAUSTED: ResourceExhaustedError,
+  TIMEOUT: TimeoutError,
+  CLOSED: StrataClosedError,
 };
 
 /**
@@ -92,6 +116,9 @@ const ERROR_MAP = {
  * @returns {StrataError}
  */
 function toTypedError(err) {
+  if (err instanceof StrataError) {
+    return err;
+  }
   const msg = err.message || String(err);
   const match = msg.match(/^\[([A-Z_]+)\]\s*(.*)/s);
   if (match) {
@@ -115,5 +142,8 @@ module.exports = {
   ConstraintError,
   AccessDeniedError,
   IoError,
+  ResourceExhaustedError,
+  TimeoutError,
+  StrataClosedError,
   toTypedError,
 };
@@ -0,0 +1,206 @@
+'use strict';
+
+/**
+ * Fixtures: a declarative document of the data a test starts from,
+ *
+ *   {
+ *     "kv": { "user:1": { "name": "Ada" } },
+ *     "state": { "status": "ready" },
+ *     "json": { "doc:1": { "title": "Hello" } },
+ *     "events": [{ "type": "signup", "payload": { "user": 1 } }],
+ *     "vectors": {
+ *       "docs": {
+ *         "dimension": 3, "metric": "cosine",
+ *         "entries": [{ "key": "doc:1", "vector": [1, 0, 0], "metadata": {} }]
+ *       }
+ *     }
+ *   }
+ *
+ * loaded into the current branch and space with the batch writers of
+ * `bulkLoad()`. Buffers are written as `{"$base64":"..."}`, as in dumps.
+ */
+
+const fs = require('fs');
+const { NotFoundError, StateError, ValidationError } = require('./errors.js');
+const { decodeValue, encodeValue } = require('./dump.js');
+
+const SECTIONS = ['kv', 'state', 'json', 'events', 'vectors'];
+
+/** Entries read per `dumpPage` call. */
+const PAGE_SIZE = 1000;
+
+function isObject(value) {
+  return value !== null && typeof value === 'object' && !Array.isArray(value);
+}
+
+/**
+ * The fixture `source` names: a fixture object, its JSON text, or the path
+ * of a JSON file holding it.
+ */
+async function readFixture(source) {
+  if (isObject(source)) {
+    return source;
+  }
+  if (typeof source !== 'string') {
+    throw new ValidationError('A fixture is an object, JSON text, or the path of a JSON file');
+  }
+  let text = source;
+  if (!source.trimStart().startsWith('{')) {
+    try {
+      text = await fs.promises.readFile(source, 'utf8');
+    } catch (err) {
+      throw new NotFoundError(`Cannot read fixture ${source}: ${err.message}`);
+    }
+  }
+  let fixture;
+  try {
+    fixture = JSON.parse(text);
+  } catch (err) {
+    throw new ValidationError(`Fixture is not valid JSON: ${err.message}`);
+  }
+  if (!isObject(fixture)) {
+    throw new ValidationError('A fixture must be a JSON object');
+  }
+  return fixture;
+}
+
+function checkFixture(fixture) {
+  for (const [name, section] of Object.entries(fixture)) {
+    if (!SECTIONS.includes(name)) {
+      throw new ValidationError(
+        `Unknown fixture section '${name}'; expected ${SECTIONS.join(', ')}`,
+      );
+    }
+    if (name === 'events' ? !Array.isArray(section) : !isObject(section)) {
+      const kind = name === 'events' ? 'an array' : 'an object';
+      throw new ValidationError(`Fixture section '${name}' must be ${kind}`);
+    }
+  }
+  for (const [i, event] of (fixture.events ?? []).entries()) {
+    if (typeof event?.type !== 'string') {
+      throw new ValidationError(`Fixture event ${i} needs a string type`);
+    }
+  }
+  for (const [name, collection] of Object.entries(fixture.vectors ?? {})) {
+    if (!Number.isInteger(collection?.dimension) || !Array.isArray(collection.entries ?? [])) {
+      throw new ValidationError(
+        `Fixture collection '${name}' needs an integer dimension and an entries array`,
+      );
+    }
+  }
+}
+
+/**
+ * Write the data of `fixture` into `db`'s current branch and space.
+ * @param {object} db
+ * @param {object} fixture - See `Fixture` in stratadb.d.ts.
+ */
+async function loadFixture(db, fixture) {
+  checkFixture(fixture);
+  const vectors = Object.entries(fixture.vectors ?? {});
+  for (const [name, { dimension, metric }] of vectors) {
+    await db.vectorCreateCollection(name, dimension, metric);
+  }
+  return db.bulkLoad(async (loader) => {
+    for (const [key, value] of Object.entries(fixture.kv ?? {})) {
+      await loader.kvPut(key, decodeValue(value));
+    }
+    for (const [cell, value] of Object.entries(fixture.state ?? {})) {
+      await loader.stateSet(cell, decodeValue(value));
+    }
+    for (const [key, value] of Object.entries(fixture.json ?? {})) {
+      await loader.jsonSet(key, decodeValue(value));
+    }
+    for (const { type, payload } of fixture.events ?? []) {
+      await loader.eventAppend(type, decodeValue(payload ?? null));
+    }
+    for (const [name, { entries = [] }] of vectors) {
+      for (const { key, vector, metadata } of entries) {
+        await loader.vectorUpsert(name, key, vector, decodeValue(metadata ?? null));
+      }
+    }
+  });
+}
+
+async function readSection(db, space, primitive) {
+  const out = {};
+  let cursor = null;
+  do {
+    const page = await db.dumpPage(space, primitive, null, cursor, PAGE_SIZE);
+    for (const [key, value] of page.entries) {
+      out[key] = encodeValue(value);
+    }
+    cursor = page.cursor;
+  } while (cursor != null);
+  return out;
+}
+
+// Every entry of a collection. The engine can't list a collection's keys,
+// so they come from a search as wide as the collection.
+async function readCollection(db, { name, dimension, metric, count }) {
+  const entries = [];
+  if (count > 0) {
+    const query = new Array(dimension).fill(0);
+    query[0] = 1;
+    const matches = await db.vectorSearch(name, query, count);
+    if (matches.length < count) {
+      throw new StateError(`Cannot read every vector of collection '${name}'`);
+    }
+    const keys = matches.map((m) => m.key).sort();
+    for (const key of keys) {
+      const v = await db.vectorGet(name, key);
+      const entry = { key, vector: v.embedding };
+      if (v.metadata != null) {
+        entry.metadata = encodeValue(v.metadata);
+      }
+      entries.push(entry);
+    }
+  }
+  return { dimension, metric, entries };
+}
+
+/**
+ * The current branch and space of `db` as a fixture. The engine keeps no
+ * list of event types, so events are captured for `opts.eventTypes` only,
+ * in the order they were appended.
+ * @param {object} db
+ * @param {{ eventTypes?: string[] }} [opts]
+ */
+async function toFixture(db, opts) {
+  const eventTypes = opts?.eventTypes ?? [];
+  if (!Array.isArray(eventTypes) || eventTypes.some((t) => typeof t !== 'string')) {
+    throw new ValidationError('eventTypes must be an array of strings');
+  }
+  const space = await db.currentSpace();
+  const fixture = {};
+  for (const primitive of ['kv', 'state', 'json']) {
+    const section = await readSection(db, space, primitive);
+    if (Object.keys(section).length > 0) {
+      fixture[primitive] = section;
+    }
+  }
+  const events = [];
+  for (const type of eventTypes) {
+    for (const e of await db.eventList(type)) {
+      events.push({ sequence: e.version, type, payload: encodeValue(e.value) });
+    }
+  }
+  if (events.length > 0) {
+    events.sort((a, b) => a.sequence - b.sequence);
+    fixture.events = events.map(({ type, payload }) => ({ type, payload }));
+  }
+  const collections = await db.vectorListCollections();
+  if (collections.length > 0) {
+    fixture.vectors = {};
+    for (const collection of collections) {
+      fixture.vectors[collection.name] = await readCollection(db, collection);
+    }
+  }
+  return fixture;
+}
+
+module.exports = {
+  loadFixture,
+  readFixture,
+  toFixture,
+};
@@ -0,0 +1,255 @@
+'use strict';
+
+/**
+ * Write hooks. `onBeforeWrite` callbacks see each entry a write call is
+ * about to change, and may replace its value or throw to veto the call;
+ * `onAfterWrite` callbacks see the entries once the call succeeds. Writes
+ * made from inside a hook don't run hooks, so a hook can write an audit
+ * record without triggering itself.
+ *
+ * Triggers are callbacks for the KV, state, JSON, and event writes under a
+ * key prefix or in a space, run after the write commits with the entry's
+ * old and new values. Writes they make skip hooks and triggers too.
+ */
+
+const { AsyncLocalStorage } = require('async_hooks');
+const { AccessDeniedError, StrataError, ValidationError } = require('./errors.js');
+
+const inHook = new AsyncLocalStorage();
+
+// Where each hooked method keeps what it writes. Numbers index the call's
+// arguments; strings name fields of a batch entry. `collection` is always
+// an argument.
+const HOOKED_METHODS = {
+  kvPut: { primitive: 'kv', op: 'put', key: 0, value: 1 },
+  kvPutRaw: { primitive: 'kv', op: 'put', key: 0, value: 1 },
+  kvDelete: { primitive: 'kv', op: 'delete', key: 0 },
+  kvBatchPut: { primitive: 'kv', op: 'put', batch: 0, key: 'key', value: 'value' },
+  stateSet: { primitive: 'state', op: 'put', key: 0, value: 1 },
+  stateInit: { primitive: 'state', op: 'put', key: 0, value: 1 },
+  stateCas: { primitive: 'state', op: 'put', key: 0, value: 1 },
+  stateDelete: { primitive: 'state', op: 'delete', key: 0 },
+  stateBatchSet: { primitive: 'state', op: 'put', batch: 0, key: 'cell', value: 'value' },
+  eventAppend: { primitive: 'event', op: 'append', key: 0, value: 1 },
+  eventBatchAppend: {
+    primitive: 'event', op: 'append', batch: 0, key: ['eventType', 'event_type'], value: 'payload',
+  },
+  jsonSet: { primitive: 'json', op: 'put', key: 0, path: 1, value: 2 },
+  jsonDelete: { primitive: 'json', op: 'delete', key: 0, path: 1 },
+  jsonBatchSet: { primitive: 'json', op: 'put', batch: 0, key: 'key', path: 'path', value: 'value' },
+  jsonBatchDelete: { primitive: 'json', op: 'delete', batch: 0, key: 'key', path: 'path' },
+  vectorUpsert: { primitive: 'vector', op: 'put', collection: 0, key: 1, value: 3 },
+  vectorDelete: { primitive: 'vector', op: 'delete', collection: 0, key: 1 },
+  vectorBatchUpsert: {
+    primitive: 'vector', op: 'put', collection: 0, batch: 1, key: 'key', value: 'metadata',
+  },
+};
+
+// The same for `execute()` commands, by the command's PascalCase name
+// (see `commandName`). Their fields are in the args object; `batch` and
+// `collection` name fields of it too.
+const HOOKED_COMMANDS = {
+  KvPut: { primitive: 'kv', op: 'put', key: 'key', value: 'value' },
+  KvDelete: { primitive: 'kv', op: 'delete', key: 'key' },
+  KvBatchPut: { primitive: 'kv', op: 'put', batch: 'entries', key: 'key', value: 'value' },
+  StateSet: { primitive: 'state', op: 'put', key: 'cell', value: 'value' },
+  StateInit: { primitive: 'state', op: 'put', key: 'cell', value: 'value' },
+  StateCas: { primitive: 'state', op: 'put', key: 'cell', value: 'value' },
+  StateDelete: { primitive: 'state', op: 'delete', key: 'cell' },
+  StateBatchSet: { primitive: 'state', op: 'put', batch: 'entries', key: 'cell', value: 'value' },
+  EventAppend: { primitive: 'event', op: 'append', key: 'event_type', value: 'payload' },
+  EventBatchAppend: {
+    primitive: 'event', op: 'append', batch: 'entries', key: 'event_type', value: 'payload',
+  },
+  JsonSet: { primitive: 'json', op: 'put', key: 'key', path: 'path', value: 'value' },
+  JsonDelete: { primitive: 'json', op: 'delete', key: 'key', path: 'path' },
+  JsonBatchSet: {
+    primitive: 'json', op: 'put', batch: 'entries', key: 'key', path: 'path', value: 'value',
+  },
+  JsonBatchDelete: { primitive: 'json', op: 'delete', batch: 'entries', key: 'key', path: 'path' },
+  VectorUpsert: {
+    primitive: 'vector', op: 'put', collection: 'collection', key: 'key', value: 'metadata',
+  },
+  VectorDelete: { primitive: 'vector', op: 'delete', collection: 'collection', key: 'key' },
+  VectorBatchUpsert: {
+    primitive: 'vector', op: 'put', collection: 'collection', batch: 'entries', key: 'key',
+    value: 'metadata',
+  },
+};
+
+/**
+ * The executor command an `execute()` name maps to, as the binding maps
+ * it: `kv_put`, `kv.put`, and `KvPut` are all `KvPut`.
+ */
+function commandName(command) {
+  return String(command)
+    .replace(/\./g, '_')
+    .split('_')
+    .map((part) => part.charAt(0).toUpperCase() + part.slice(1))
+    .join('');
+}
+
+function read(container, field) {
+  if (Array.isArray(field)) {
+    return field.map((f) => container[f]).find((v) => v !== undefined);
+  }
+  return container[field];
+}
+
+/** Whether the current call was made from inside a write hook. */
+function inWriteHook() {
+  return inHook.getStore() === true;
+}
+
+/**
+ * The entries a call to `method` writes, as hook arguments, or null if it
+ * is not a hooked write. Copies the arguments a before hook could change,
+ * so the caller's objects are left alone.
+ * @param {string} method
+ * @param {unknown[]} args - Updated in place with the copies.
+ * @param {{ branch: string, space: string }} current - The handle's branch and space.
+ */
+function describeWrites(method, args, current) {
+  const command = method === 'execute';
+  const spec = command ? HOOKED_COMMANDS[commandName(args[0])] : HOOKED_METHODS[method];
+  if (!spec) {
+    return null;
+  }
+  let source = args;
+  if (command) {
+    args[1] = { ...args[1] };
+    source = args[1];
+  }
+  let containers;
+  if (spec.batch != null) {
+    source[spec.batch] = (source[spec.batch] ?? []).map((entry) => ({ ...entry }));
+    containers = source[spec.batch];
+  } else {
+    containers = [source];
+  }
+  return containers.map((container) => {
+    const write = {
+      method,
+      primitive: spec.primitive,
+      op: spec.op,
+      key: read(container, spec.key),
+      value: spec.value != null ? read(container, spec.value) : undefined,
+      branch: command ? source.branch ?? current.branch : current.branch,
+      space: command ? source.space ?? current.space : current.space,
+    };
+    if (spec.path != null) {
+      write.path = read(container, spec.path) ?? '$';
+    }
+    if (spec.collection != null) {
+      write.collection = source[spec.collection];
+    }
+    return { write, container, field: spec.value };
+  });
+}
+
+/**
+ * Run the before hooks over `writes`, in registration order, then copy
+ * the values they set back into the call's arguments. A hook that throws
+ * vetoes the call; errors that aren't StrataErrors become
+ * AccessDeniedError.
+ */
+async function runBeforeHooks(hooks, writes) {
+  for (const hook of hooks) {
+    for (const { write } of writes) {
+      try {
+        await inHook.run(true, () => hook(write));
+      } catch (err) {
+        if (err instanceof StrataError) {
+          throw err;
+        }
+        const vetoed = new AccessDeniedError(`Write vetoed by hook: ${err?.message ?? err}`);
+        vetoed.cause = err;
+        throw vetoed;
+      }
+    }
+  }
+  for (const { write, container, field } of writes) {
+    if (field != null) {
+      container[field] = write.value;
+    }
+  }
+}
+
+/**
+ * Run the after hooks over `writes` with the call's result. The write has
+ * already happened, so a hook that throws is reported to `onError` rather
+ * than failing the call.
+ */
+async function runAfterHooks(hooks, writes, result, onError) {
+  for (const hook of hooks) {
+    for (const { write } of writes) {
+      try {
+        await inHook.run(true, () => hook({ ...write, result }));
+      } catch (err) {
+        onError(err);
+      }
+    }
+  }
+}
+
+const TRIGGERED = new Set(['kv', 'state', 'json', 'event']);
+
+/**
+ * The `{ prefix, space, primitive }` a trigger watches, from the target
+ * passed to `createTrigger()`: a key prefix, or an object naming any of
+ * the three.
+ */
+function triggerTarget(target) {
+  if (typeof target === 'string') {
+    return { prefix: target };
+  }
+  if (target === null || typeof target !== 'object'
+    || (target.prefix == null && target.space == null)) {
+    throw new ValidationError('createTrigger() takes a key prefix or { prefix, space, primitive }');
+  }
+  const { prefix, space, primitive } = target;
+  if (primitive != null && !TRIGGERED.has(primitive)) {
+    throw new ValidationError(
+      `Triggers watch 'kv', 'state', 'json', or 'event' writes, not '${primitive}'`,
+    );
+  }
+  return { prefix, space, primitive };
+}
+
+/** Whether a trigger watching `target` runs for `write`. */
+function triggerMatches(target, write) {
+  return TRIGGERED.has(write.primitive)
+    && (target.primitive == null || target.primitive === write.primitive)
+    && (target.space == null || target.space === write.space)
+    && (target.prefix == null || String(write.key).startsWith(target.prefix));
+}
+
+/**
+ * Run each trigger over the changes it matches, in order. A trigger that
+ * throws is reported to `onError`.
+ */
+async function runTriggers(triggers, changes, onError) {
+  for (const change of changes) {
+    for (const { target, callback } of triggers) {
+      if (!triggerMatches(target, change)) {
+        continue;
+      }
+      try {
+        await inHook.run(true, () => callback(change));
+      } catch (err) {
+        onError(err);
+      }
+    }
+  }
+}
+
+module.exports = {
+  commandName,
+  describeWrites,
+  inWriteHook,
+  runAfterHooks,
+  runBeforeHooks,
+  runTriggers,
+  triggerMatches,
+  triggerTarget,
+};
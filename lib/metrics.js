@@ -0,0 +1,193 @@
+'use strict';
+
+/**
+ * Per-handle call metrics, recorded by the wrapper stratadb.js puts around
+ * every native method: call and error counts, errors by code, and latency
+ * histograms per method family.
+ */
+
+/** Upper bounds of the latency histogram buckets, in milliseconds. */
+const LATENCY_BUCKETS_MS = [
+  0.1, 0.25, 0.5, 1, 2.5, 5, 10, 25, 50, 100, 250, 500, 1000, 2500, 5000, 10000,
+];
+
+const TXN_METHODS = new Set(['begin', 'commit', 'abort', 'rollback', 'txnInfo', 'txnIsActive']);
+
+const FAMILY_PREFIXES = ['kv', 'state', 'event', 'json', 'vector', 'graph'];
+
+/**
+ * The family a method's latency is reported under.
+ * @param {string} method
+ * @returns {string}
+ */
+function familyOf(method) {
+  if (TXN_METHODS.has(method)) {
+    return 'txn';
+  }
+  return FAMILY_PREFIXES.find((prefix) => method.startsWith(prefix)) ?? 'other';
+}
+
+class Histogram {
+  constructor() {
+    // One count per bucket, plus the overflow bucket.
+    this.counts = new Array(LATENCY_BUCKETS_MS.length + 1).fill(0);
+    this.count = 0;
+    this.sum = 0;
+    this.max = 0;
+  }
+
+  observe(ms) {
+    let i = LATENCY_BUCKETS_MS.findIndex((bound) => ms <= bound);
+    if (i === -1) {
+      i = LATENCY_BUCKETS_MS.length;
+    }
+    this.counts[i] += 1;
+    this.count += 1;
+    this.sum += ms;
+    this.max = Math.max(this.max, ms);
+  }
+
+  // The bucket bound at or below which `q` of the observations fall,
+  // capped at the largest observation.
+  quantile(q) {
+    const rank = Math.ceil(q * this.count);
+    let seen = 0;
+    for (let i = 0; i < LATENCY_BUCKETS_MS.length; i++) {
+      seen += this.counts[i];
+      if (seen >= rank) {
+        return Math.min(LATENCY_BUCKETS_MS[i], this.max);
+      }
+    }
+    return this.max;
+  }
+}
+
+class Metrics {
+  constructor() {
+    this.reset();
+  }
+
+  /** Forget everything recorded so far. */
+  reset() {
+    this.since = Date.now();
+    this.calls = 0;
+    this.errors = 0;
+    this.errorsByCode = {};
+    this.families = new Map();
+    this.compactions = 0;
+  }
+
+  /**
+   * Record one finished call.
+   * @param {string} method
+   * @param {number} ms - How long it took.
+   * @param {Error} [err] - The typed error it failed with.
+   */
+  record(method, ms, err) {
+    const name = familyOf(method);
+    let family = this.families.get(name);
+    if (!family) {
+      family = { calls: 0, errors: 0, latency: new Histogram() };
+      this.families.set(name, family);
+    }
+    this.calls += 1;
+    family.calls += 1;
+    family.latency.observe(ms);
+    if (err) {
+      const code = err.code ?? 'UNKNOWN';
+      this.errors += 1;
+      family.errors += 1;
+      this.errorsByCode[code] = (this.errorsByCode[code] ?? 0) + 1;
+    } else if (method === 'compact') {
+      this.compactions += 1;
+    }
+  }
+
+  /** A plain-object snapshot; see `Metrics` in stratadb.d.ts. */
+  snapshot() {
+    const families = {};
+    for (const [name, { calls, errors, latency }] of this.families) {
+      families[name] = {
+        calls,
+        errors,
+        meanMs: latency.sum / latency.count,
+        p50Ms: latency.quantile(0.5),
+        p90Ms: latency.quantile(0.9),
+        p99Ms: latency.quantile(0.99),
+        maxMs: latency.max,
+      };
+    }
+    return {
+      since: this.since,
+      calls: this.calls,
+      errors: this.errors,
+      errorsByCode: { ...this.errorsByCode },
+      families,
+      compactions: this.compactions,
+    };
+  }
+
+  /**
+   * The metrics in the Prometheus text exposition format.
+   * @param {Record<string, string>} [labels] - Added to every sample.
+   * @returns {string}
+   */
+  prometheus(labels) {
+    const base = Object.entries(labels ?? {});
+    const sample = (name, extra, value) => {
+      const all = [...base, ...extra];
+      const set = all.length === 0
+        ? ''
+        : `{${all.map(([k, v]) => `${k}="${escapeLabel(v)}"`).join(',')}}`;
+      return `${name}${set} ${value}`;
+    };
+    const lines = [
+      '# HELP strata_calls_total Calls made through the handle.',
+      '# TYPE strata_calls_total counter',
+    ];
+    for (const [name, family] of this.families) {
+      lines.push(sample('strata_calls_total', [['family', name]], family.calls));
+    }
+    lines.push(
+      '# HELP strata_errors_total Failed calls, by error code.',
+      '# TYPE strata_errors_total counter',
+    );
+    for (const [code, count] of Object.entries(this.errorsByCode)) {
+      lines.push(sample('strata_errors_total', [['code', code]], count));
+    }
+    lines.push(
+      '# HELP strata_call_duration_seconds Call latency.',
+      '# TYPE strata_call_duration_seconds histogram',
+    );
+    for (const [name, { latency }] of this.families) {
+      let cumulative = 0;
+      LATENCY_BUCKETS_MS.forEach((bound, i) => {
+        cumulative += latency.counts[i];
+        const le = [['family', name], ['le', String(bound / 1000)]];
+        lines.push(sample('strata_call_duration_seconds_bucket', le, cumulative));
+      });
+      const inf = [['family', name], ['le', '+Inf']];
+      lines.push(
+        sample('strata_call_duration_seconds_bucket', inf, latency.count),
+        sample('strata_call_duration_seconds_sum', [['family', name]], latency.sum / 1000),
+        sample('strata_call_duration_seconds_count', [['family', name]], latency.count),
+      );
+    }
+    lines.push(
+      '# HELP strata_compactions_total Completed compactions.',
+      '# TYPE strata_compactions_total counter',
+      sample('strata_compactions_total', [], this.compactions),
+    );
+    return `${lines.join('\n')}\n`;
+  }
+}
+
+function escapeLabel(value) {
+  return String(value).replace(/\\/g, '\\\\').replace(/"/g, '\\"').replace(/\n/g, '\\n');
+}
+
+module.exports = {
+  Metrics,
+  familyOf,
+  LATENCY_BUCKETS_MS,
+};
@@ -0,0 +1,120 @@
+'use strict';
+
+/**
+ * Schema and data migrations: a list of `{ id, up, down? }` steps, each run
+ * in its own transaction along with the write that records it, so a step
+ * that throws leaves no trace and a step is never recorded without its
+ * changes. Applied ids are KV keys in the `_migrations` space of the
+ * branch being migrated, so a fork carries its migration state with it.
+ */
+
+const { ValidationError } = require('./errors.js');
+
+/** Space holding a KV entry for each applied migration. */
+const MIGRATIONS_SPACE = '_migrations';
+
+function checkMigrations(migrations) {
+  if (!Array.isArray(migrations)) {
+    throw new ValidationError('migrate() takes an array of { id, up, down? } migrations');
+  }
+  const ids = new Set();
+  for (const m of migrations) {
+    if (typeof m?.id !== 'string' || m.id === '') {
+      throw new ValidationError('Each migration needs a non-empty string id');
+    }
+    if (ids.has(m.id)) {
+      throw new ValidationError(`Duplicate migration id '${m.id}'`);
+    }
+    if (typeof m.up !== 'function') {
+      throw new ValidationError(`Migration '${m.id}' needs an up() function`);
+    }
+    if (m.down != null && typeof m.down !== 'function') {
+      throw new ValidationError(`Migration '${m.id}': down must be a function`);
+    }
+    ids.add(m.id);
+  }
+}
+
+async function appliedIds(db) {
+  if (!(await db.spaceExists(MIGRATIONS_SPACE))) {
+    return new Set();
+  }
+  const r = await db.execute('kv_list', { space: MIGRATIONS_SPACE });
+  return new Set(Array.isArray(r) ? r : r.keys);
+}
+
+// The migrations to revert and to apply, in the order they run: applied
+// ones after `to`, last first, then pending ones up to `to` in list order.
+function planOf(migrations, applied, opts) {
+  let target = migrations.length;
+  if (opts?.to === null) {
+    target = 0;
+  } else if (opts?.to !== undefined) {
+    target = migrations.findIndex((m) => m.id === opts.to) + 1;
+    if (target === 0) {
+      throw new ValidationError(`Unknown migration id '${opts.to}'`);
+    }
+  }
+  const up = migrations.slice(0, target).filter((m) => !applied.has(m.id));
+  const down = migrations.slice(target).filter((m) => applied.has(m.id)).reverse();
+  const irreversible = down.find((m) => m.down == null);
+  if (irreversible) {
+    throw new ValidationError(
+      `Migration '${irreversible.id}' has no down() and cannot be reverted`,
+    );
+  }
+  return { up, down };
+}
+
+// Run `step` and record the migration as applied (or no longer applied)
+// in one transaction, unless another handle got there first.
+function runStep(db, id, step, apply) {
+  return db.transaction(async (tx) => {
+    const record = await tx.execute('kv_get', { space: MIGRATIONS_SPACE, key: id });
+    if ((record != null) === apply) {
+      return false;
+    }
+    await step(tx);
+    if (apply) {
+      const value = { appliedAt: Date.now() * 1000 };
+      await tx.execute('kv_put', { space: MIGRATIONS_SPACE, key: id, value });
+    } else {
+      await tx.execute('kv_delete', { space: MIGRATIONS_SPACE, key: id });
+    }
+    return true;
+  });
+}
+
+/**
+ * Bring `db`'s current branch to the state after migration `opts.to`
+ * (default: the last one), applying pending migrations and reverting
+ * applied ones after `to` with their `down()`. `to: null` reverts them
+ * all. With `dryRun`, nothing runs and the result says what would.
+ * @param {object} db
+ * @param {{ id: string, up: Function, down?: Function }[]} migrations
+ * @param {{ to?: string | null, dryRun?: boolean }} [opts]
+ * @returns {Promise<{ applied: string[], reverted: string[] }>}
+ */
+async function migrate(db, migrations, opts) {
+  checkMigrations(migrations);
+  const plan = planOf(migrations, await appliedIds(db), opts);
+  if (opts?.dryRun) {
+    return { applied: plan.up.map((m) => m.id), reverted: plan.down.map((m) => m.id) };
+  }
+  const result = { applied: [], reverted: [] };
+  for (const m of plan.down) {
+    if (await runStep(db, m.id, m.down, false)) {
+      result.reverted.push(m.id);
+    }
+  }
+  for (const m of plan.up) {
+    if (await runStep(db, m.id, m.up, true)) {
+      result.applied.push(m.id);
+    }
+  }
+  return result;
+}
+
+module.exports = {
+  migrate,
+};
@@ -0,0 +1,115 @@
+'use strict';
+
+/**
+ * Pub/sub channels: notifications between the handles on a database in
+ * this process and, through `db.listen()`, its remote clients. Nothing is
+ * written to the database, so a message published while no one is
+ * subscribed is dropped, and subscriptions end when their handle closes.
+ * Use events when a message has to survive a restart.
+ */
+
+const v8 = require('v8');
+const { ValidationError } = require('./errors.js');
+
+// Subscribers of each database, by channel: file databases by path, since
+// the handles on a path share one, and in-memory databases by handle.
+const byPath = new Map();
+const byHandle = new WeakMap();
+
+// The function that ends each of a handle's subscriptions.
+const subscriptionsOf = new WeakMap();
+
+function checkChannel(channel) {
+  if (typeof channel !== 'string' || channel === '') {
+    throw new ValidationError('A channel name must be a non-empty string');
+  }
+}
+
+function registryOf(key) {
+  return typeof key === 'string' ? byPath : byHandle;
+}
+
+function channelsOf(key) {
+  const registry = registryOf(key);
+  let channels = registry.get(key);
+  if (!channels) {
+    channels = new Map();
+    registry.set(key, channels);
+  }
+  return channels;
+}
+
+/**
+ * Call `callback(message, channel)` for each message published to
+ * `channel` on the database `key` names, until the returned function is
+ * called or `db` closes.
+ * @param {object} db - The subscribing handle.
+ * @param {string | object} key - The database's path, or its handle if in memory.
+ * @param {string} channel
+ * @param {(message: unknown, channel: string) => unknown} callback
+ * @param {(err: Error) => void} onError - Gets what callbacks throw.
+ * @returns {() => void}
+ */
+function subscribe(db, key, channel, callback, onError) {
+  checkChannel(channel);
+  if (typeof callback !== 'function') {
+    throw new ValidationError('subscribe() takes a channel and a callback');
+  }
+  const channels = channelsOf(key);
+  if (!channels.has(channel)) {
+    channels.set(channel, new Set());
+  }
+  const subscriber = { callback, onError };
+  channels.get(channel).add(subscriber);
+  if (!subscriptionsOf.has(db)) {
+    subscriptionsOf.set(db, new Set());
+  }
+  const owned = subscriptionsOf.get(db);
+  const unsubscribe = () => {
+    owned.delete(unsubscribe);
+    const subscribers = channels.get(channel);
+    if (subscribers?.delete(subscriber) && subscribers.size === 0) {
+      channels.delete(channel);
+      if (channels.size === 0 && typeof key === 'string') {
+        byPath.delete(key);
+      }
+    }
+  };
+  owned.add(unsubscribe);
+  return unsubscribe;
+}
+
+/**
+ * Send `message` to the subscribers of `channel`. Each gets its own copy,
+ * made as `postMessage` would, after the call returns.
+ * @returns {number} How many subscribers the message went to.
+ */
+function publish(key, channel, message) {
+  checkChannel(channel);
+  let encoded;
+  try {
+    encoded = v8.serialize(message);
+  } catch (err) {
+    throw new ValidationError(`Cannot publish this message: ${err.message}`);
+  }
+  const subscribers = [...(registryOf(key).get(key)?.get(channel) ?? [])];
+  for (const { callback, onError } of subscribers) {
+    Promise.resolve()
+      .then(() => callback(v8.deserialize(encoded), channel))
+      .catch(onError);
+  }
+  return subscribers.length;
+}
+
+/** End every subscription `db` made, as it closes. */
+function unsubscribeAll(db) {
+  for (const unsubscribe of subscriptionsOf.get(db) ?? []) {
+    unsubscribe();
+  }
+}
+
+module.exports = {
+  publish,
+  subscribe,
+  unsubscribeAll,
+};
@@ -0,0 +1,234 @@
+'use strict';
+
+/**
+ * Client side of the wire protocol (see lib/wire.js). A `RemoteStrata`
+ * exposes the same methods as an embedded handle; stratadb.js fills in its
+ * prototype, sending each method to the server as a request.
+ */
+
+const net = require('net');
+const tls = require('tls');
+const { encodeFrame, FrameDecoder } = require('./wire.js');
+const { IoError, StrataClosedError, ValidationError, toTypedError } = require('./errors.js');
+
+const DEFAULT_PORT = 7474;
+
+/**
+ * Parse `strata://host[:port]` or `strata+unix:///path/to.sock` into
+ * `net.connect()` options.
+ * @param {string} url
+ */
+function parseConnectUrl(url) {
+  let parsed;
+  try {
+    parsed = new URL(url);
+  } catch (err) {
+    throw new ValidationError(`Invalid connection URL '${url}'`);
+  }
+  if (parsed.protocol === 'strata+unix:') {
+    return { path: decodeURIComponent(parsed.pathname) };
+  }
+  if (parsed.protocol === 'strata:') {
+    if (!parsed.hostname) {
+      throw new ValidationError(`Connection URL '${url}' has no host`);
+    }
+    return {
+      host: parsed.hostname.replace(/^\[(.*)\]$/, '$1'),
+      port: parsed.port ? Number(parsed.port) : DEFAULT_PORT,
+    };
+  }
+  throw new ValidationError(
+    `Unsupported connection URL '${url}'; use strata://host:port or strata+unix:///path`,
+  );
+}
+
+// Split an `AbortSignal` and any callbacks off a trailing options object;
+// neither can cross the wire, and the signal is honoured on this side.
+function takeLocalControl(args) {
+  const last = args[args.length - 1];
+  if (last == null || typeof last !== 'object' || Array.isArray(last) || ArrayBuffer.isView(last)) {
+    return [args, undefined];
+  }
+  const { signal, ...rest } = last;
+  for (const [key, value] of Object.entries(rest)) {
+    if (typeof value === 'function') {
+      delete rest[key];
+    }
+  }
+  return [[...args.slice(0, -1), rest], signal];
+}
+
+class RemoteStrata {
+  constructor(socket, label) {
+    this._socket = socket;
+    this._label = label;
+    this._nextId = 1;
+    this._pending = new Map();
+    this._closed = null;
+    // Subscribed channels: their callbacks, and the server's answer to the
+    // subscribe request.
+    this._channels = new Map();
+
+    const decoder = new FrameDecoder();
+    socket.on('data', (chunk) => {
+      let messages;
+      try {
+        messages = decoder.push(chunk);
+      } catch (err) {
+        socket.destroy(err);
+        return;
+      }
+      for (const { id, result, error, channel, message } of messages) {
+        if (id === undefined) {
+          this._deliver(channel, message);
+          continue;
+        }
+        const pending = this._pending.get(id);
+        if (!pending) {
+          continue;
+        }
+        this._pending.delete(id);
+        if (error) {
+          pending.reject(toTypedError(new Error(`[${error.code}] ${error.message}`)));
+        } else {
+          pending.resolve(result);
+        }
+      }
+    });
+    socket.on('error', (err) => {
+      this._closed ??= new IoError(`Connection to ${label} failed: ${err.message}`);
+    });
+    socket.on('close', () => {
+      this._closed ??= new IoError(`Connection to ${label} closed`);
+      for (const { reject } of this._pending.values()) {
+        reject(this._closed);
+      }
+      this._pending.clear();
+    });
+  }
+
+  /** Send `method(...args)` to the server and resolve with its result. */
+  _call(method, args) {
+    if (this._closed) {
+      return Promise.reject(this._closed);
+    }
+    const [wireArgs, signal] = takeLocalControl(args);
+    if (signal?.aborted) {
+      return Promise.reject(signal.reason);
+    }
+    const id = this._nextId++;
+    return new Promise((resolve, reject) => {
+      const onAbort = () => {
+        this._pending.delete(id);
+        reject(signal.reason);
+      };
+      signal?.addEventListener('abort', onAbort, { once: true });
+      this._pending.set(id, {
+        resolve: (value) => {
+          signal?.removeEventListener('abort', onAbort);
+          resolve(value);
+        },
+        reject: (err) => {
+          signal?.removeEventListener('abort', onAbort);
+          reject(err);
+        },
+      });
+      let frame;
+      try {
+        frame = encodeFrame({ id, method, args: wireArgs });
+      } catch (err) {
+        this._pending.get(id).reject(new ValidationError(`Cannot send ${method} arguments: ${err.message}`));
+        this._pending.delete(id);
+        return;
+      }
+      this._socket.write(frame);
+    });
+  }
+
+  _deliver(channel, message) {
+    for (const callback of this._channels.get(channel)?.callbacks ?? []) {
+      Promise.resolve()
+        .then(() => callback(message, channel))
+        .catch((err) => process.emitWarning(err));
+    }
+  }
+
+  /**
+   * Call `callback(message, channel)` for each message published to
+   * `channel` on the server. Resolves, once the server has the
+   * subscription, with a function that ends it.
+   */
+  async subscribe(channel, callback) {
+    if (typeof callback !== 'function') {
+      throw new ValidationError('subscribe() takes a channel and a callback');
+    }
+    let subscription = this._channels.get(channel);
+    if (!subscription) {
+      subscription = { callbacks: new Set(), ready: this._call('subscribe', [channel]) };
+      this._channels.set(channel, subscription);
+      subscription.ready.catch(() => {
+        if (this._channels.get(channel) === subscription) {
+          this._channels.delete(channel);
+        }
+      });
+    }
+    await subscription.ready;
+    if (this._channels.get(channel) !== subscription) {
+      // Its last callback went while this one waited; subscribe afresh.
+      return this.subscribe(channel, callback);
+    }
+    const entry = (message, name) => callback(message, name);
+    subscription.callbacks.add(entry);
+    return () => {
+      if (subscription.callbacks.delete(entry) && subscription.callbacks.size === 0
+        && this._channels.get(channel) === subscription) {
+        this._channels.delete(channel);
+        this._call('unsubscribe', [channel]).catch(() => {});
+      }
+    };
+  }
+
+  /** Close the connection. The server's database stays open. */
+  async close() {
+    if (this._closed) {
+      return;
+    }
+    this._closed = new StrataClosedError(`Connection to ${this._label} is closed`);
+    await new Promise((resolve) => {
+      this._socket.once('close', resolve);
+      this._socket.end();
+    });
+  }
+}
+
+/**
+ * Connect to a server and authenticate.
+ * @param {string} url - `strata://host:port` or `strata+unix:///path`.
+ * @param {{ tls?: boolean | import('tls').ConnectionOptions, token?: string }} [options]
+ * @returns {Promise<RemoteStrata>}
+ */
+async function connect(url, options) {
+  const target = parseConnectUrl(url);
+  const useTls = options?.tls != null && options.tls !== false;
+  const socket = await new Promise((resolve, reject) => {
+    const tlsOptions = typeof options?.tls === 'object' ? options.tls : {};
+    const s = useTls
+      ? tls.connect({ ...target, servername: target.host, ...tlsOptions }, () => resolve(s))
+      : net.connect(target, () => resolve(s));
+    s.once('error', (err) => reject(new IoError(`Cannot connect to ${url}: ${err.message}`)));
+  });
+  const db = new RemoteStrata(socket, url);
+  try {
+    await db._call('hello', [{ token: options?.token ?? null }]);
+  } catch (err) {
+    socket.destroy();
+    throw err;
+  }
+  return db;
+}
+
+module.exports = {
+  RemoteStrata,
+  connect,
+  parseConnectUrl,
+};
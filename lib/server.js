@@ -0,0 +1,369 @@
+'use strict';
+
+/**
+ * Server side of the wire protocol (see lib/wire.js). Each connection gets
+ * its own handle on the served database, so transactions don't mix across
+ * clients; closing the connection rolls back whatever it left open. Channel
+ * messages go out on the connections subscribed to them.
+ */
+
+const crypto = require('crypto');
+const fs = require('fs');
+const net = require('net');
+const path = require('path');
+const tls = require('tls');
+const {
+  encodeFrame,
+  FrameDecoder,
+  errorPayload,
+  HELLO_FRAME_BYTES,
+  MAX_FRAME_BYTES,
+} = require('./wire.js');
+const {
+  AccessDeniedError,
+  ResourceExhaustedError,
+  StateError,
+  ValidationError,
+} = require('./errors.js');
+
+// Methods a read-only connection may call. begin() is allowed only for
+// read-only transactions, and databaseId() only to read the id.
+// cancelOperation() is left out: it can cancel other connections' calls.
+const READ_METHODS = new Set([
+  'autoEmbedEnabled', 'blobReadChunk', 'blobStat', 'branchConflicts', 'branchExists', 'branchGet',
+  'branchLineage', 'branchStats', 'cacheStats', 'changelogRead', 'collectionSchema', 'commit',
+  'abort', 'rollback', 'config', 'configureGet', 'counterGet', 'counterHistory', 'currentBranch',
+  'currentSpace', 'describe', 'detokenize', 'diffBranches', 'diffTime', 'dumpPage',
+  'durabilityCounters', 'edgeList', 'embed', 'embedBatch', 'embedStatus', 'eventGet', 'eventLen',
+  'eventList', 'eventListPaginated', 'graphBfs', 'graphCdlp', 'graphGetLinkType', 'graphGetMeta',
+  'graphGetNode', 'graphGetObjectType', 'graphLcc', 'graphList', 'graphListLinkTypes',
+  'graphListNodes', 'graphListNodesPaginated', 'graphListObjectTypes', 'graphListOntologyTypes',
+  'graphNeighbors', 'graphNodesByType', 'graphOntologyStatus', 'graphOntologySummary',
+  'graphPagerank', 'graphSssp', 'graphWcc', 'info', 'isFollower', 'isOpen', 'jsonBatchGet',
+  'jsonGet', 'jsonGetVersioned', 'jsonHistory', 'jsonList', 'jsonQuery', 'kvGet', 'kvGetRaw',
+  'kvGetVersioned', 'kvHistory', 'kvList', 'kvListPaginated', 'kvTimeline', 'listBranches',
+  'listSpaces', 'memoryRecall', 'metrics', 'metricsPrometheus', 'ping', 'poolStats', 'queueStats',
+  'read', 'rerank', 'retentionGetConfig', 'retentionListPins', 'search', 'spaceExists',
+  'spaceStats', 'stateGet', 'stateGetVersioned', 'stateHistory', 'stateList', 'status', 'syncRead',
+  'tagList', 'timeRange', 'tokenize', 'traceGet', 'traverse', 'tsQuery', 'txnInfo', 'txnIsActive',
+  'vectorCollectionStats', 'vectorGet', 'vectorListCollections', 'vectorSearch',
+  'vectorSearchFiltered', 'verify', 'walStats',
+]);
+
+function isReadOnlyCall(method, args) {
+  if (method === 'begin') {
+    return args[0] === true || args[0]?.readOnly === true;
+  }
+  if (method === 'databaseId') {
+    return args[0] == null;
+  }
+  return READ_METHODS.has(method);
+}
+
+// How long a connection has to authenticate before it is dropped.
+const DEFAULT_HANDSHAKE_TIMEOUT_MS = 10_000;
+
+// Argument positions that are file paths, which only reach the served
+// handle once they resolve inside `options.bundleDir`.
+const PATH_ARGS = {
+  branchExport: () => [1],
+  branchExportIncremental: () => [2],
+  branchImport: () => [0],
+  branchValidateBundle: () => [0],
+  bundleDiff: (args) => (typeof args[1] === 'string' ? [0, 1] : [0]),
+};
+
+// Resolve `given` against `dir`, refusing paths that lead outside it,
+// including through symlinks in the part of the path that exists.
+function confinePath(dir, given) {
+  if (typeof given !== 'string') {
+    throw new ValidationError('Bundle path must be a string');
+  }
+  const root = fs.realpathSync(dir);
+  const resolved = path.resolve(root, given);
+  let existing = path.dirname(resolved);
+  while (!fs.existsSync(existing)) {
+    existing = path.dirname(existing);
+  }
+  const real = path.join(fs.realpathSync(existing), path.relative(existing, resolved));
+  const relative = path.relative(root, real);
+  if (relative.startsWith('..') || path.isAbsolute(relative)) {
+    throw new AccessDeniedError(`Path '${given}' is outside the server's bundle directory`);
+  }
+  return real;
+}
+
+// Rewrite a path-taking call's paths to server paths inside `bundleDir`.
+function confinePathArgs(bundleDir, method, args) {
+  if (bundleDir == null) {
+    throw new AccessDeniedError(`${method}() needs the server to be started with bundleDir`);
+  }
+  const confined = [...args];
+  for (const index of PATH_ARGS[method](args)) {
+    confined[index] = confinePath(bundleDir, args[index]);
+  }
+  return confined;
+}
+
+// Compare tokens in constant time; tokens of different lengths never match.
+function tokenMatches(expected, given) {
+  if (expected == null || typeof given !== 'string') {
+    return false;
+  }
+  const a = Buffer.from(String(expected));
+  const b = Buffer.from(given);
+  return a.length === b.length && crypto.timingSafeEqual(a, b);
+}
+
+// Resolve a client's token to 'readWrite', 'readOnly', or null (refused).
+// An auth function granting anything but those two is taken as a refusal.
+async function authorize(auth, token) {
+  if (auth == null) {
+    return 'readWrite';
+  }
+  if (typeof auth === 'function') {
+    const granted = await auth(token);
+    return granted === 'readWrite' || granted === 'readOnly' ? granted : null;
+  }
+  if (tokenMatches(auth.token, token)) {
+    return 'readWrite';
+  }
+  if (tokenMatches(auth.readOnlyToken, token)) {
+    return 'readOnly';
+  }
+  return null;
+}
+
+class StrataServer {
+  constructor(server, options) {
+    this._server = server;
+    this._connections = new Set();
+    // Sockets let into the handshake, which count against maxConnections
+    // whether or not they go on to authenticate.
+    this._accepted = 0;
+    this._admitted = 0;
+    this._options = options;
+  }
+
+  /** The bound `{ port, host }`, or `{ socketPath }`. */
+  get address() {
+    const address = this._server.address();
+    return typeof address === 'string'
+      ? { socketPath: address }
+      : { port: address.port, host: address.address };
+  }
+
+  /** Number of authenticated client connections. */
+  get connections() {
+    return this._admitted;
+  }
+
+  /** Stop accepting connections and drop the open ones. */
+  async close() {
+    const closed = new Promise((resolve) => this._server.close(() => resolve()));
+    for (const socket of this._connections) {
+      socket.destroy();
+    }
+    await closed;
+    if (this._options.socketPath != null) {
+      fs.rmSync(this._options.socketPath, { force: true });
+    }
+  }
+}
+
+/**
+ * Serve `db` until `close()` is called on the returned server.
+ * @param {object} db - The handle to serve.
+ * @param {object} options - See `ListenOptions` in stratadb.d.ts.
+ * @param {Set<string>} methods - Methods clients may call.
+ * @param {(db: object) => Promise<object>} openConnectionHandle - A new handle on `db`.
+ * @returns {Promise<StrataServer>}
+ */
+async function serve(db, options, methods, openConnectionHandle) {
+  if ((options?.port == null) === (options?.socketPath == null)) {
+    throw new ValidationError('listen() needs exactly one of port or socketPath');
+  }
+  const maxConnections = options.maxConnections ?? Infinity;
+  const handshakeTimeoutMs = options.handshakeTimeoutMs ?? DEFAULT_HANDSHAKE_TIMEOUT_MS;
+  const server = options.tls != null
+    ? tls.createServer(options.tls, serveConnection)
+    : net.createServer(serveConnection);
+  const strataServer = new StrataServer(server, options);
+
+  // Past the limit, a socket only gets its hello answered with
+  // ResourceExhaustedError before it is closed.
+  function refuseConnection(socket) {
+    strataServer._connections.add(socket);
+    const decoder = new FrameDecoder(HELLO_FRAME_BYTES);
+    const timer = setTimeout(() => socket.destroy(), handshakeTimeoutMs);
+    socket.on('data', (chunk) => {
+      let requests;
+      try {
+        requests = decoder.push(chunk);
+      } catch (err) {
+        socket.destroy();
+        return;
+      }
+      if (requests.length > 0 && !socket.writableEnded) {
+        const full = new ResourceExhaustedError(
+          `Server is at its limit of ${maxConnections} connections`,
+        );
+        socket.end(encodeFrame({ id: requests[0].id, error: errorPayload(full) }));
+      }
+    });
+    socket.on('error', () => {});
+    socket.on('close', () => {
+      clearTimeout(timer);
+      strataServer._connections.delete(socket);
+    });
+  }
+
+  function serveConnection(socket) {
+    if (strataServer._accepted >= maxConnections) {
+      refuseConnection(socket);
+      return;
+    }
+    strataServer._accepted += 1;
+    strataServer._connections.add(socket);
+    let connectionDb;
+    let access;
+    let greeted = false;
+    // Unauthenticated sockets get small frames and a deadline.
+    const decoder = new FrameDecoder(HELLO_FRAME_BYTES);
+    const handshakeTimer = setTimeout(() => {
+      if (access == null) {
+        socket.destroy();
+      }
+    }, handshakeTimeoutMs);
+    let authenticated = Promise.resolve();
+    // The function that ends each of this connection's channel subscriptions.
+    const subscriptions = new Map();
+    const send = (message) => {
+      if (!socket.destroyed) {
+        socket.write(encodeFrame(message));
+      }
+    };
+
+    // Subscriptions are made on the served handle, so that they and
+    // publish() reach the same channels as its in-process subscribers.
+    async function updateSubscription(method, channel) {
+      if (method === 'unsubscribe') {
+        subscriptions.get(channel)?.();
+        subscriptions.delete(channel);
+      } else if (!subscriptions.has(channel)) {
+        const unsubscribe = await db.subscribe(channel, (message) => send({ channel, message }));
+        if (socket.destroyed) {
+          unsubscribe();
+        } else {
+          subscriptions.set(channel, unsubscribe);
+        }
+      }
+      return null;
+    }
+
+    async function dispatch(request) {
+      const { method, args } = request;
+      if (method === 'hello') {
+        // Only the first hello counts; another would admit the connection twice.
+        if (greeted) {
+          throw new StateError('Connection has already sent hello');
+        }
+        greeted = true;
+        const granted = await authorize(options.auth, args[0]?.token);
+        if (granted == null) {
+          throw new AccessDeniedError('Invalid token');
+        }
+        strataServer._admitted += 1;
+        access = options.readOnly ? 'readOnly' : granted;
+        clearTimeout(handshakeTimer);
+        decoder.maxFrameBytes = MAX_FRAME_BYTES;
+        connectionDb = openConnectionHandle(db);
+        await connectionDb;
+        return null;
+      }
+      if (access == null) {
+        throw new AccessDeniedError('Connection has not authenticated');
+      }
+      if (method === 'subscribe' || method === 'unsubscribe') {
+        return updateSubscription(method, args?.[0]);
+      }
+      if (!methods.has(method)) {
+        throw new ValidationError(`Unknown method '${method}'`);
+      }
+      if (access === 'readOnly' && !isReadOnlyCall(method, args)) {
+        throw new AccessDeniedError(`${method}() is not allowed on a read-only connection`);
+      }
+      let callArgs = Array.isArray(args) ? args : [];
+      if (PATH_ARGS[method] != null) {
+        callArgs = confinePathArgs(options.bundleDir, method, callArgs);
+      }
+      if (method === 'publish') {
+        return db.publish(...callArgs);
+      }
+      return (await connectionDb)[method](...callArgs);
+    }
+
+    socket.on('data', (chunk) => {
+      let requests;
+      try {
+        requests = decoder.push(chunk);
+      } catch (err) {
+        socket.destroy();
+        return;
+      }
+      for (const request of requests) {
+        // Requests sent after hello wait for it to finish.
+        const run = request.method === 'hello'
+          ? dispatch(request)
+          : authenticated.then(() => dispatch(request));
+        if (request.method === 'hello') {
+          authenticated = run.catch(() => {});
+        }
+        run.then(
+          (result) => {
+            try {
+              send({ id: request.id, result });
+            } catch (err) {
+              const unsent = new StateError(`Cannot send ${request.method} result: ${err.message}`);
+              send({ id: request.id, error: errorPayload(unsent) });
+            }
+          },
+          (err) => {
+            send({ id: request.id, error: errorPayload(err) });
+            if (request.method === 'hello' && access == null) {
+              socket.end();
+            }
+          },
+        );
+      }
+    });
+    socket.on('error', () => {});
+    socket.on('close', () => {
+      clearTimeout(handshakeTimer);
+      strataServer._accepted -= 1;
+      strataServer._connections.delete(socket);
+      for (const unsubscribe of subscriptions.values()) {
+        unsubscribe();
+      }
+      if (access != null) {
+        strataServer._admitted -= 1;
+      }
+      connectionDb?.then((h) => h.close()).catch(() => {});
+    });
+  }
+
+  await new Promise((resolve, reject) => {
+    server.once('error', reject);
+    if (options.socketPath != null) {
+      server.listen(options.socketPath, resolve);
+    } else {
+      server.listen(options.port, options.host ?? '127.0.0.1', resolve);
+    }
+  });
+  return strataServer;
+}
+
+module.exports = {
+  serve,
+  StrataServer,
+};
@@ -0,0 +1,147 @@
+'use strict';
+
+/**
+ * One-shot sync of KV, state, and JSON data between two databases. Each
+ * branch is compared entry by entry against what the two held after the
+ * last sync, which the local database keeps on its `_system_` branch as
+ * value hashes, so an entry changed on only one side since then is copied
+ * to the other, and one changed on both is reported as a conflict and left
+ * alone. Before the first sync, an entry only one side has is copied and
+ * one both have with different values is a conflict. Peers are told apart
+ * by `databaseId()`, so moving a database or reaching it another way keeps
+ * its sync state, and branches holding events, vectors, or graphs are
+ * refused, since those aren't copied.
+ */
+
+const crypto = require('crypto');
+const { ValidationError } = require('./errors.js');
+const { encodeValue } = require('./dump.js');
+
+const MODES = ['push', 'pull', 'two-way'];
+
+/** Records per `changelogApplyBatch` call. */
+const BATCH_SIZE = 1000;
+
+const SEP = '\u0000';
+
+function hashValue(value) {
+  return crypto.createHash('sha256').update(JSON.stringify(encodeValue(value))).digest('base64');
+}
+
+// The `_system_` key holding a space's hashes as of the last sync with a
+// peer.
+function baseKey(peer, branch, space) {
+  return ['sync', peer, branch, space].map(encodeURIComponent).join('/');
+}
+
+// `space\0primitive\0key` → entry, for one side of a branch.
+async function readSide(db, branch) {
+  const entries = new Map();
+  if (await db.branchExists(branch)) {
+    for (const entry of await db.syncRead(branch)) {
+      entries.set([entry.space, entry.primitive, entry.key].join(SEP), entry);
+    }
+  }
+  return entries;
+}
+
+// The peer's id, which keys its sync state however it is reached; one is
+// assigned on first sync.
+async function databaseId(db) {
+  return (await db.databaseId()) ?? db.databaseId(crypto.randomUUID());
+}
+
+async function writeBatches(db, records) {
+  for (let i = 0; i < records.length; i += BATCH_SIZE) {
+    await db.changelogApplyBatch(records.slice(i, i + BATCH_SIZE));
+  }
+}
+
+/**
+ * Sync `local` with `remote` in the given direction.
+ * @param {object} local
+ * @param {object} remote
+ * @param {object} [opts] - See `SyncOptions` in stratadb.d.ts.
+ */
+async function sync(local, remote, opts) {
+  const { branches, mode = 'two-way' } = opts ?? {};
+  if (!MODES.includes(mode)) {
+    throw new ValidationError(`Invalid sync mode '${mode}'; use 'push', 'pull', or 'two-way'`);
+  }
+  const push = mode !== 'pull';
+  const pull = mode !== 'push';
+  let names = branches;
+  if (names == null) {
+    const set = new Set();
+    if (push) {
+      (await local.listBranches()).forEach((b) => set.add(b));
+    }
+    if (pull) {
+      (await remote.listBranches()).forEach((b) => set.add(b));
+    }
+    names = [...set];
+  }
+  const peer = await databaseId(remote);
+  const system = await local.systemBranch();
+  const result = { branches: names.length, pushed: 0, pulled: 0, conflicts: [] };
+
+  for (const branch of names) {
+    if (push && (await local.branchExists(branch)) && !(await remote.branchExists(branch))) {
+      await remote.createBranch(branch);
+    }
+    if (pull && (await remote.branchExists(branch)) && !(await local.branchExists(branch))) {
+      await local.createBranch(branch);
+    }
+    const ours = await readSide(local, branch);
+    const theirs = await readSide(remote, branch);
+    const spaces = new Set([...ours.values(), ...theirs.values()].map((e) => e.space));
+    const bases = new Map();
+    for (const space of spaces) {
+      bases.set(space, (await system.kvGet(baseKey(peer, branch, space))) ?? {});
+    }
+    const record = (space, field, hash) => {
+      if (hash == null) {
+        delete bases.get(space)[field];
+      } else {
+        bases.get(space)[field] = hash;
+      }
+    };
+    const toRemote = [];
+    const toLocal = [];
+    for (const id of new Set([...ours.keys(), ...theirs.keys()])) {
+      const [space, primitive, key] = id.split(SEP);
+      const field = `${primitive}${SEP}${key}`;
+      const here = ours.get(id);
+      const there = theirs.get(id);
+      const hashHere = here ? hashValue(here.value) : null;
+      const hashThere = there ? hashValue(there.value) : null;
+      if (hashHere === hashThere) {
+        record(space, field, hashHere);
+        continue;
+      }
+      const synced = bases.get(space)[field] ?? null;
+      const changedHere = hashHere !== synced;
+      const changedThere = hashThere !== synced;
+      if (changedHere && changedThere) {
+        result.conflicts.push({ branch, space, primitive, key });
+      } else if (changedHere ? push : pull) {
+        const from = changedHere ? here : there;
+        (changedHere ? toRemote : toLocal).push(from
+          ? { branch, space, primitive, key, op: 'put', value: from.value }
+          : { branch, space, primitive, key, op: 'delete', value: null });
+        record(space, field, changedHere ? hashHere : hashThere);
+        result[changedHere ? 'pushed' : 'pulled'] += 1;
+      }
+    }
+    await writeBatches(remote, toRemote);
+    await writeBatches(local, toLocal);
+    for (const [space, base] of bases) {
+      await system.kvPut(baseKey(peer, branch, space), base);
+    }
+  }
+  return result;
+}
+
+module.exports = {
+  sync,
+};
@@ -0,0 +1,71 @@
+'use strict';
+
+/**
+ * Temporary databases for tests: each in a fresh directory under the OS
+ * temp dir, removed when its handle closes, is garbage collected without
+ * closing, or is still open as the process exits.
+ */
+
+const fs = require('fs');
+const os = require('os');
+const path = require('path');
+
+// Directory of each temporary database's handle, and the directories not
+// yet removed.
+const dirsByHandle = new WeakMap();
+const liveDirs = new Set();
+
+/** Remove `dir`, a temporary database's directory, now. */
+function remove(dir) {
+  fs.rmSync(dir, { recursive: true, force: true });
+  liveDirs.delete(dir);
+}
+
+const collected = new FinalizationRegistry((dir) => {
+  try {
+    remove(dir);
+  } catch (_) { /* no caller to report to; the exit hook tries again */ }
+});
+
+let exitHookInstalled = false;
+
+function removeAllOnExit() {
+  for (const dir of liveDirs) {
+    try {
+      fs.rmSync(dir, { recursive: true, force: true });
+    } catch (_) { /* nothing to report to as the process exits */ }
+  }
+}
+
+/** A new, empty directory for a temporary database. */
+function makeTempDir() {
+  return fs.mkdtempSync(path.join(os.tmpdir(), 'strata-'));
+}
+
+/** Remove `dir` once `db` is closed or collected. */
+function removeWith(db, dir) {
+  dirsByHandle.set(db, dir);
+  liveDirs.add(dir);
+  collected.register(db, dir, db);
+  if (!exitHookInstalled) {
+    process.on('exit', removeAllOnExit);
+    exitHookInstalled = true;
+  }
+}
+
+/** Remove the directory of `db`, which has closed, if it is temporary. */
+function release(db) {
+  const dir = dirsByHandle.get(db);
+  if (dir !== undefined) {
+    dirsByHandle.delete(db);
+    collected.unregister(db);
+    remove(dir);
+  }
+}
+
+module.exports = {
+  makeTempDir,
+  release,
+  remove,
+  removeWith,
+};
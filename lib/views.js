@@ -0,0 +1,285 @@
+'use strict';
+
+/**
+ * Materialized views: a JS `map` over the KV, state, or JSON entries under
+ * a key prefix, or the events of one type, optionally grouped by `reduce`,
+ * written as KV entries into a target space. A view is built in full when
+ * created or refreshed and then kept up to date by a trigger on its
+ * source, which re-reduces only the output keys a write touches. The
+ * binding, not the engine, runs the functions, so views live on the handle
+ * that created them and are recreated after each open.
+ */
+
+const { NotFoundError, StateError, ValidationError } = require('./errors.js');
+
+const SOURCE_PRIMITIVES = ['kv', 'state', 'json'];
+
+/** Entries read per `dumpPage` call. */
+const PAGE_SIZE = 1000;
+
+/** Output records per `changelogApplyBatch` call. */
+const BATCH_SIZE = 1000;
+
+// Views of each handle, by name.
+const viewsByHandle = new WeakMap();
+
+function viewsOf(db) {
+  let views = viewsByHandle.get(db);
+  if (!views) {
+    views = new Map();
+    viewsByHandle.set(db, views);
+  }
+  return views;
+}
+
+function viewOf(db, name) {
+  const view = viewsOf(db).get(name);
+  if (!view) {
+    throw new NotFoundError(`View not found: ${name}`);
+  }
+  return view;
+}
+
+// The `{ primitive, prefix, eventType, space }` a view reads, with the
+// source space defaulting to the handle's current one.
+function sourceOf(source, currentSpace) {
+  if (source === null || typeof source !== 'object') {
+    throw new ValidationError('A view needs a source: { prefix } or { eventType }');
+  }
+  const { prefix, eventType, primitive = 'kv', space = currentSpace } = source;
+  if (eventType != null) {
+    if (typeof eventType !== 'string' || prefix != null) {
+      throw new ValidationError('A view source takes an eventType or a prefix, not both');
+    }
+    return { primitive: 'event', eventType, space };
+  }
+  if (typeof prefix !== 'string') {
+    throw new ValidationError('A view source needs a key prefix or an eventType');
+  }
+  if (!SOURCE_PRIMITIVES.includes(primitive)) {
+    throw new ValidationError(`Unknown primitive '${primitive}'; expected kv, state, or json`);
+  }
+  return { primitive, prefix, space };
+}
+
+// The `[key, value]` pairs `map` returns for an entry.
+function mapEntry(view, entry) {
+  const pairs = view.map(entry) ?? [];
+  const valid = Array.isArray(pairs)
+    && pairs.every((pair) => Array.isArray(pair) && typeof pair[0] === 'string');
+  if (!valid) {
+    throw new ValidationError(
+      `View '${view.name}': map() must return an array of [key, value] pairs with string keys`,
+    );
+  }
+  return pairs;
+}
+
+// Replace what source entry `id` maps to, and return the output keys that
+// changed.
+function contribute(view, id, pairs) {
+  const touched = new Set();
+  for (const [key] of view.bySource.get(id) ?? []) {
+    view.byOutput.get(key)?.delete(id);
+    touched.add(key);
+  }
+  if (pairs.length === 0) {
+    view.bySource.delete(id);
+  } else {
+    view.bySource.set(id, pairs);
+  }
+  for (const [key, value] of pairs) {
+    if (!view.byOutput.has(key)) {
+      view.byOutput.set(key, new Map());
+    }
+    const values = view.byOutput.get(key);
+    values.set(id, [...(values.get(id) ?? []), value]);
+    touched.add(key);
+  }
+  return touched;
+}
+
+// The output record for `key`: its reduced values, or a delete once
+// nothing maps to it.
+function outputRecord(view, key) {
+  const record = { branch: view.branch, space: view.space, primitive: 'kv', key };
+  const values = view.byOutput.get(key);
+  if (!values || values.size === 0) {
+    view.byOutput.delete(key);
+    return { ...record, op: 'delete', value: null };
+  }
+  const all = [...values.values()].flat();
+  return { ...record, op: 'put', value: view.reduce ? view.reduce(all, key) : all };
+}
+
+async function writeOutputs(db, records) {
+  for (let i = 0; i < records.length; i += BATCH_SIZE) {
+    await db.changelogApplyBatch(records.slice(i, i + BATCH_SIZE));
+  }
+}
+
+async function readPages(db, space, primitive, prefix, onPage) {
+  let cursor = null;
+  do {
+    const page = await db.dumpPage(space, primitive, prefix, cursor, PAGE_SIZE);
+    onPage(page.entries);
+    cursor = page.cursor;
+  } while (cursor != null);
+}
+
+// Rebuild a view from its whole source, then replace the target space's
+// KV entries with the result.
+async function rebuild(db, view) {
+  if ((await db.currentBranch()) !== view.branch) {
+    throw new StateError(
+      `View '${view.name}' is on branch '${view.branch}'; switch back to refresh it`,
+    );
+  }
+  view.bySource = new Map();
+  view.byOutput = new Map();
+  const { primitive, prefix, eventType, space } = view.source;
+  const spaces = await db.listSpaces();
+  if (spaces.includes(space)) {
+    await readPages(db, space, primitive, eventType ?? prefix, (entries) => {
+      for (const [key, value] of entries) {
+        const id = primitive === 'event' ? `#${key}` : key;
+        const entry = primitive === 'event' ? { key: eventType, value } : { key, value };
+        contribute(view, id, mapEntry(view, entry));
+      }
+    });
+  }
+  const records = [...view.byOutput.keys()].map((key) => outputRecord(view, key));
+  if (spaces.includes(view.space)) {
+    await readPages(db, view.space, 'kv', null, (entries) => {
+      for (const [key] of entries) {
+        if (!view.byOutput.has(key)) {
+          records.push(outputRecord(view, key));
+        }
+      }
+    });
+  }
+  await writeOutputs(db, records);
+  view.refreshedAt = Date.now();
+  view.updatedAt = view.refreshedAt;
+  view.lastError = null;
+}
+
+// Fold one committed source write into the view.
+async function update(db, view, change) {
+  const { primitive, eventType } = view.source;
+  if (change.branch !== view.branch || (primitive === 'event' && change.key !== eventType)) {
+    return;
+  }
+  let id = change.key;
+  let value = change.newValue;
+  if (primitive === 'event') {
+    view.appended += 1;
+    id = `+${view.appended}`;
+  } else if (primitive === 'json' && change.path !== '$') {
+    const r = await db.execute('json_get', {
+      branch: change.branch, space: change.space, key: change.key, path: '$',
+    });
+    value = r == null ? null : r.value;
+  }
+  try {
+    const pairs = value == null ? [] : mapEntry(view, { key: change.key, value });
+    const touched = contribute(view, id, pairs);
+    await writeOutputs(db, [...touched].map((key) => outputRecord(view, key)));
+    view.updatedAt = Date.now();
+  } catch (err) {
+    view.lastError = err;
+    throw err;
+  }
+}
+
+function statusOf(view) {
+  return {
+    name: view.name,
+    branch: view.branch,
+    source: { ...view.source },
+    space: view.space,
+    sources: view.bySource.size,
+    keys: view.byOutput.size,
+    refreshedAt: view.refreshedAt,
+    updatedAt: view.updatedAt,
+    lastError: view.lastError,
+  };
+}
+
+/**
+ * Define view `name` on the handle's current branch and build it.
+ * @param {object} db
+ * @param {string} name
+ * @param {object} def - See `ViewDefinition` in stratadb.d.ts.
+ */
+async function viewCreate(db, name, def) {
+  if (typeof name !== 'string' || name === '') {
+    throw new ValidationError('viewCreate() needs a view name');
+  }
+  if (viewsOf(db).has(name)) {
+    throw new ValidationError(`View already exists: ${name}`);
+  }
+  const { source, map, reduce, space } = def ?? {};
+  if (typeof map !== 'function' || (reduce != null && typeof reduce !== 'function')) {
+    throw new ValidationError('A view needs a map() function, and reduce() must be a function');
+  }
+  if (typeof space !== 'string' || space === '') {
+    throw new ValidationError('A view needs a target space');
+  }
+  const view = {
+    name,
+    branch: await db.currentBranch(),
+    source: sourceOf(source, await db.currentSpace()),
+    map,
+    reduce,
+    space,
+    appended: 0,
+    bySource: new Map(),
+    byOutput: new Map(),
+    refreshedAt: null,
+    updatedAt: null,
+    lastError: null,
+  };
+  if (view.space === view.source.space) {
+    throw new ValidationError('A view cannot write into the space it reads');
+  }
+  await rebuild(db, view);
+  const { primitive, prefix, eventType } = view.source;
+  view.drop = db.createTrigger(
+    { primitive, prefix: eventType ?? prefix, space: view.source.space },
+    (change) => update(db, view, change),
+  );
+  viewsOf(db).set(name, view);
+  return statusOf(view);
+}
+
+/** Rebuild view `name` from its whole source. */
+async function viewRefresh(db, name) {
+  const view = viewOf(db, name);
+  await rebuild(db, view);
+  return statusOf(view);
+}
+
+/** Where view `name` stands. */
+function viewStatus(db, name) {
+  return statusOf(viewOf(db, name));
+}
+
+/** Stop maintaining view `name`. Its output is left in place. */
+function viewDrop(db, name) {
+  viewOf(db, name).drop();
+  viewsOf(db).delete(name);
+}
+
+/** Names of the handle's views. */
+function viewList(db) {
+  return [...viewsOf(db).keys()];
+}
+
+module.exports = {
+  viewCreate,
+  viewDrop,
+  viewList,
+  viewRefresh,
+  viewStatus,
+};
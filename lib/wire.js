@@ -0,0 +1,92 @@
+'use strict';
+
+/**
+ * Wire protocol between `Strata.connect()` clients and `db.listen()` servers.
+ *
+ * Each frame is a 4-byte big-endian length followed by a message encoded
+ * with `v8.serialize`, so Buffers, typed arrays, and Dates survive the trip.
+ *
+ *   request:  { id, method, args }
+ *   response: { id, result } | { id, error: { code, message } }
+ *   message:  { channel, message }
+ *
+ * The first request on a connection is `hello` with `[{ token }]`; the
+ * server answers it before serving anything else, and until it succeeds
+ * accepts frames of at most `HELLO_FRAME_BYTES`. After a `subscribe`
+ * request with `[channel]`, the server sends each message published to the
+ * channel, without an id, until an `unsubscribe` request with `[channel]`.
+ */
+
+const v8 = require('v8');
+
+const HEADER_BYTES = 4;
+
+/** Largest frame either side accepts (256 MiB). */
+const MAX_FRAME_BYTES = 256 * 1024 * 1024;
+
+/** Largest frame a server accepts before the connection's hello succeeds (64 KiB). */
+const HELLO_FRAME_BYTES = 64 * 1024;
+
+/**
+ * Encode one message as a frame.
+ * @param {object} message
+ * @returns {Buffer}
+ */
+function encodeFrame(message) {
+  const body = v8.serialize(message);
+  const header = Buffer.alloc(HEADER_BYTES);
+  header.writeUInt32BE(body.length);
+  return Buffer.concat([header, body]);
+}
+
+/**
+ * Reassembles messages from the chunks of a stream.
+ */
+class FrameDecoder {
+  /**
+   * @param {number} [maxFrameBytes] - Largest frame to accept; may be raised later.
+   */
+  constructor(maxFrameBytes = MAX_FRAME_BYTES) {
+    this._buffered = Buffer.alloc(0);
+    this.maxFrameBytes = maxFrameBytes;
+  }
+
+  /**
+   * Add a chunk and return the messages it completes.
+   * @param {Buffer} chunk
+   * @returns {object[]}
+   */
+  push(chunk) {
+    this._buffered = Buffer.concat([this._buffered, chunk]);
+    const messages = [];
+    while (this._buffered.length >= HEADER_BYTES) {
+      const length = this._buffered.readUInt32BE(0);
+      if (length > this.maxFrameBytes) {
+        throw new Error(`[VALIDATION] Frame of ${length} bytes exceeds the ${this.maxFrameBytes} byte limit`);
+      }
+      if (this._buffered.length < HEADER_BYTES + length) {
+        break;
+      }
+      const body = this._buffered.subarray(HEADER_BYTES, HEADER_BYTES + length);
+      messages.push(v8.deserialize(body));
+      this._buffered = this._buffered.subarray(HEADER_BYTES + length);
+    }
+    return messages;
+  }
+}
+
+/**
+ * The `{ code, message }` sent for a failed request.
+ * @param {Error} err - A typed StrataError or any other error.
+ */
+function errorPayload(err) {
+  return { code: err.code ?? 'UNKNOWN', message: err.message ?? String(err) };
+}
+
+module.exports = {
+  encodeFrame,
+  FrameDecoder,
+  errorPayload,
+  HELLO_FRAME_BYTES,
+  MAX_FRAME_BYTES,
+};
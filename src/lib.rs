@@ -6,23 +6,45 @@
 
 #![deny(clippy::all)]
 
+use napi::bindgen_prelude::{Buffer, Either, FromNapiValue, ToNapiValue, TypeName};
+use napi::threadsafe_function::{ErrorStrategy, ThreadsafeFunction, ThreadsafeFunctionCallMode};
+use napi::{
+    sys, Env, JsBigInt, JsFunction, JsObject, JsString, JsUnknown, NapiRaw, NapiValue, ValueType,
+};
 use napi_derive::napi;
-use std::collections::HashMap;
-use std::sync::{Arc, Mutex};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::future::Future;
+use std::io::{Read, Write};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock, RwLock, Weak};
 
 use stratadb::{
     AccessMode, BatchEventEntry, BatchGetItemResult, BatchItemResult, BatchJsonDeleteEntry,
     BatchJsonEntry, BatchJsonGetEntry, BatchKvEntry, BatchStateEntry, BatchVectorEntry,
-    BranchExportResult, BranchImportResult, BulkGraphEdge, BulkGraphNode,
-    BundleValidateResult, CollectionInfo, Command, DescribeResult, DistanceMetric,
+    BranchDiffEntry, BranchExportResult, BranchImportResult, BulkGraphEdge, BulkGraphNode,
+    BundleEncryption, BundleExportOptions, BundleImportOptions, BundleProgress, BundleProgressFn,
+    BundleSecret, BundleValidateResult, CollectionInfo, Command, DescribeResult, DistanceMetric,
     Error as StrataError, FilterOp, GraphBfsResult, GraphGroupSummary, GraphScoreSummary,
-    MergeStrategy, MetadataFilter, OpenOptions, Output, SearchQuery, Session,
-    Strata as RustStrata, TimeRangeInput, TxnOptions, Value, VersionedBranchInfo, VersionedValue,
+    HistoryTrimOptions, HistoryTrimResult, MergeStrategy, MetadataFilter, OpenOptions, Output,
+    SearchQuery, Session, SpaceDiff, Strata as RustStrata, TimeRangeInput, TxnOptions, Value,
+    VersionedBranchInfo, VersionedValue,
 };
 
-/// Maximum nesting depth for JSON → Value conversion.
+/// Maximum nesting depth for JSON → Value conversion, and the default
+/// `jsonLimits.maxDepth` for stored values.
 const MAX_JSON_DEPTH: usize = 64;
 
+/// Deepest nesting accepted whatever `jsonLimits` says; bounds the
+/// recursion in conversion.
+const JSON_DEPTH_CEILING: usize = 1024;
+
+/// Default `jsonLimits.maxValueBytes` (64 MiB).
+const DEFAULT_MAX_VALUE_BYTES: usize = 64 * 1024 * 1024;
+
+/// Default `jsonLimits.maxElements`.
+const DEFAULT_MAX_ELEMENTS: usize = 1_000_000;
+
 /// Options for opening a database.
 #[napi(object)]
 pub struct JsOpenOptions {
@@ -37,6 +59,58 @@ pub struct JsOpenOptions {
     /// operations are rejected. Call `refresh()` to see new commits
     /// from the primary.
     pub follower: Option<bool>,
+    /// Run this handle's operations on a dedicated pool of this many
+    /// blocking threads. Absent = share tokio's default blocking pool with
+    /// the rest of the process.
+    pub worker_threads: Option<u32>,
+    /// Run at most this many operations at once; further calls wait for a
+    /// free slot. Gives the handle a dedicated pool of this size (or caps
+    /// `workerThreads`).
+    pub max_concurrent_ops: Option<u32>,
+    /// With `maxConcurrentOps`, how many calls may wait for a slot before
+    /// new calls fail with `RESOURCE_EXHAUSTED`. Absent = unbounded; 0 =
+    /// fail as soon as every slot is busy. `close()` is never turned away.
+    pub max_queued_ops: Option<u32>,
+    /// Create the database if there is none at the path (default: true).
+    /// Pass `false` to only attach to an existing database.
+    pub create_if_missing: Option<bool>,
+    /// Fail if a database already exists at the path (default: false), so
+    /// initialization never reuses an existing one.
+    pub error_if_exists: Option<bool>,
+    /// If another process has the path open for writing, retry for up to
+    /// this many milliseconds before failing (default: 0, fail at once).
+    pub lock_wait_ms: Option<u32>,
+    /// Megabytes of latest KV values `kvGet` keeps in a read cache shared
+    /// by every handle on the database (default: 0, no cache). Only the
+    /// first open of a path sizes it.
+    #[napi(js_name = "cacheMB")]
+    pub cache_mb: Option<u32>,
+    /// Limits on values written through this handle. Calls can override
+    /// them with their own `jsonLimits`.
+    pub json_limits: Option<JsJsonLimits>,
+    /// Return stored integers outside the safe-integer range from
+    /// `kvGet`, `stateGet`, and `jsonGet` as BigInt (default: false, a
+    /// rounded number).
+    pub bigints: Option<bool>,
+    /// Return whole-number floats from `kvGet`, `stateGet`, and `jsonGet`
+    /// as boxed `Number` objects, which are written back as floats
+    /// (default: false, a plain number, written back as an integer).
+    pub boxed_floats: Option<bool>,
+}
+
+/// Limits on the values a write accepts. Unset fields keep their default.
+#[napi(object)]
+pub struct JsJsonLimits {
+    /// Deepest nesting of arrays and objects (default 64, at most 1024).
+    pub max_depth: Option<u32>,
+    /// Largest value, counting string and byte lengths, object keys, and 8
+    /// bytes per other scalar (default 64 MiB).
+    pub max_value_bytes: Option<u32>,
+    /// Most elements in one array or object (default 1,000,000).
+    pub max_elements: Option<u32>,
+    /// Reject a value that loses data in conversion instead of storing
+    /// what is left (default: false); see `JsValue`.
+    pub strict_values: Option<bool>,
 }
 
 /// Time range filter for search (ISO 8601 datetime strings).
@@ -63,6 +137,377 @@ pub struct JsSearchOptions {
     pub expand: Option<bool>,
     /// Enable/disable reranking. Absent = auto.
     pub rerank: Option<bool>,
+    /// Search these spaces (or `"*"` for all) instead of the current one.
+    pub spaces: Option<Either<Vec<String>, String>>,
+}
+
+/// Options for document ingestion.
+#[napi(object)]
+pub struct JsIngestOptions {
+    /// Maximum chunk length in characters (default: 1000).
+    pub chunk_size: Option<u32>,
+    /// Characters shared between consecutive chunks (default: 100).
+    pub overlap: Option<u32>,
+    /// Metadata merged into every chunk's vector metadata.
+    pub metadata: Option<serde_json::Value>,
+}
+
+/// Editable branch annotations.
+#[napi(object)]
+pub struct JsBranchMetadata {
+    /// Free-form description. An empty string clears it.
+    pub description: Option<String>,
+    /// Labels, replacing any existing ones.
+    pub labels: Option<Vec<String>>,
+}
+
+/// Options for creating a tag.
+#[napi(object)]
+pub struct JsTagOptions {
+    /// Timestamp to tag (microseconds since epoch). Defaults to now.
+    pub timestamp: Option<i64>,
+}
+
+/// Options for copying a space.
+#[napi(object)]
+pub struct JsSpaceCopyOptions {
+    /// Branch to copy within (default: the current branch).
+    pub branch: Option<String>,
+}
+
+/// A retention rule for a space and/or primitive.
+#[napi(object)]
+pub struct JsRetentionOptions {
+    /// Space the rule applies to (default: every space).
+    pub space: Option<String>,
+    /// Primitive the rule applies to: "kv", "state", "json", "event", or
+    /// "vector" (default: every primitive).
+    pub primitive: Option<String>,
+    /// Keep at most this many versions per key.
+    pub max_versions: Option<u32>,
+    /// Drop versions older than this many seconds.
+    pub max_age: Option<u32>,
+    /// Keep versions visible at any tagged timestamp (default: false).
+    pub keep_tags: Option<bool>,
+}
+
+/// Options for committing a transaction.
+#[napi(object)]
+pub struct JsCommitOptions {
+    /// Return `{ version, operations }` rather than just the version.
+    pub detailed: Option<bool>,
+}
+
+/// Options for beginning a transaction.
+#[napi(object)]
+pub struct JsBeginOptions {
+    /// Reject writes in this transaction.
+    pub read_only: Option<bool>,
+    /// Pin every read to this timestamp (or tag). Requires `readOnly`.
+    pub as_of: Option<Either<i64, String>>,
+    /// Run the transaction on this branch (default: the current branch).
+    pub branch: Option<String>,
+    /// Default space for commands in the transaction (default: the
+    /// current space).
+    pub space: Option<String>,
+}
+
+/// A history pin that `retentionApply` must not trim. Set exactly one of
+/// `key`, `tag`, or `timestamp`.
+#[napi(object)]
+pub struct JsRetentionPin {
+    /// Keep every version of this key.
+    pub key: Option<String>,
+    /// Space of `key` (default: the current space).
+    pub space: Option<String>,
+    /// Primitive of `key`: "kv" (default), "state", or "json".
+    pub primitive: Option<String>,
+    /// Keep the versions visible at this tag's timestamp.
+    pub tag: Option<String>,
+    /// Keep the versions visible at this timestamp.
+    pub timestamp: Option<i64>,
+}
+
+/// Options for `info()`.
+#[napi(object)]
+#[derive(Default)]
+pub struct JsInfoOptions {
+    /// Also report `diskBytes`, the size of the database directory. Off by
+    /// default because it walks the whole directory.
+    pub disk_bytes: Option<bool>,
+}
+
+/// Options for exporting part of a branch to a bundle.
+#[napi(object)]
+#[derive(Default)]
+pub struct JsBundleExportOptions {
+    /// Only export these spaces (default: every space).
+    pub spaces: Option<Vec<String>>,
+    /// Only export keys starting with one of these prefixes.
+    pub prefixes: Option<Vec<String>>,
+    /// Only export these primitives: "kv", "state", "json", "event",
+    /// "vector", or "graph" (default: all).
+    pub primitives: Option<Vec<String>>,
+    /// Include version history (default: true). When false, only the
+    /// latest version of each key is exported.
+    pub include_history: Option<bool>,
+    /// Encrypt the bundle (AES-256-GCM) with a key or passphrase.
+    pub encryption: Option<JsBundleEncryption>,
+}
+
+/// Key material for encrypted bundles. Give exactly one of `key` or
+/// `passphrase`.
+#[napi(object)]
+pub struct JsBundleEncryption {
+    /// Raw 32-byte key.
+    pub key: Option<Buffer>,
+    /// Passphrase; the key is derived from it with a per-bundle salt.
+    pub passphrase: Option<String>,
+    /// Identifier recorded in the bundle header so the right key can be
+    /// found when importing. Ignored on import.
+    pub key_id: Option<String>,
+}
+
+/// A branch for `backupBranches` to export.
+#[napi(object)]
+pub struct JsBackupBranch {
+    pub branch: String,
+    pub path: String,
+    /// Export only the changes after this version, as a delta bundle.
+    pub since_version: Option<i64>,
+}
+
+/// Options for importing or validating a bundle.
+#[napi(object)]
+pub struct JsBundleImportOptions {
+    /// Key material for an encrypted bundle.
+    pub encryption: Option<JsBundleEncryption>,
+    /// Import under this branch name instead of the one recorded in the
+    /// bundle. For delta bundles, the branch the delta is applied to.
+    pub destination_branch: Option<String>,
+    /// What to do if the destination branch already exists: "fail"
+    /// (default), "replace", or "merge" (last-writer-wins). Ignored when
+    /// validating.
+    pub on_conflict: Option<String>,
+}
+
+/// Options for branch diffs.
+#[napi(object)]
+pub struct JsDiffOptions {
+    /// Include per-space key lists, not just summary counts.
+    pub detailed: Option<bool>,
+    /// For modified keys: "none" (default), "values", or "hashes".
+    pub values: Option<String>,
+    /// Maximum number of key entries to return per page; at least 1.
+    pub limit: Option<u32>,
+    /// Cursor returned by a previous page. It names the last key of that
+    /// page, so the next page resumes after it.
+    pub cursor: Option<String>,
+}
+
+/// Options for `queuePop`.
+#[napi(object)]
+pub struct JsQueuePopOptions {
+    /// Milliseconds the message stays hidden from other `queuePop` calls
+    /// before it is delivered again unless acknowledged (default 30,000).
+    pub visibility_timeout: Option<u32>,
+}
+
+/// Options for `lockAcquire`.
+#[napi(object)]
+pub struct JsLockOptions {
+    /// Milliseconds the lease lasts unless renewed (default 30,000).
+    pub ttl_ms: Option<u32>,
+    /// Milliseconds to keep retrying while another holder has the lock
+    /// (default 0, try once).
+    pub wait_ms: Option<u32>,
+}
+
+/// Options for `edgeList`.
+#[napi(object)]
+pub struct JsEdgeListOptions {
+    /// `"outgoing"` (default), `"incoming"`, or `"both"`.
+    pub direction: Option<String>,
+    /// Only edges with this relation.
+    pub relation: Option<String>,
+    /// Read the edges as of this timestamp (microseconds) or tag.
+    pub as_of: Option<Either<i64, String>>,
+}
+
+/// Options for `traverse`.
+#[napi(object)]
+pub struct JsTraverseOptions {
+    /// Hops to follow from the start node (default 1).
+    pub depth: Option<u32>,
+    /// `"outgoing"` (default), `"incoming"`, or `"both"`.
+    pub direction: Option<String>,
+    /// Only follow edges with this relation.
+    pub relation: Option<String>,
+    /// Most nodes to visit, counting the start node (default 1,000).
+    pub limit: Option<u32>,
+    /// Read the edges as of this timestamp (microseconds) or tag.
+    pub as_of: Option<Either<i64, String>>,
+}
+
+/// Range, filter, and downsampling for `tsQuery`.
+#[napi(object)]
+pub struct JsTsQueryOptions {
+    /// Earliest timestamp to include (microseconds, inclusive).
+    pub from: Option<i64>,
+    /// Latest timestamp to include (microseconds, inclusive).
+    pub to: Option<i64>,
+    /// Only points carrying all of these tags.
+    pub tags: Option<HashMap<String, String>>,
+    /// Combine the points in each bucket: "avg", "sum", "min", "max",
+    /// "count", "first", or "last".
+    pub aggregate: Option<String>,
+    /// Bucket width in microseconds, aligned to the epoch. Without it,
+    /// `aggregate` combines the whole range.
+    pub bucket: Option<i64>,
+}
+
+/// Paging for `jsonQuery`.
+#[napi(object)]
+pub struct JsJsonQueryOptions {
+    /// Only documents whose key starts with this.
+    pub prefix: Option<String>,
+    /// Most documents to return (default: all that match).
+    pub limit: Option<u32>,
+    /// Read the documents as of this timestamp (microseconds) or tag.
+    pub as_of: Option<Either<i64, String>>,
+}
+
+/// Filters and paging for `kvHistory`, `stateHistory`, and `jsonHistory`.
+#[napi(object)]
+pub struct JsHistoryOptions {
+    /// Maximum number of versions to return.
+    pub limit: Option<u32>,
+    /// Only versions older than this version.
+    pub before_version: Option<i64>,
+    /// Only versions written at or after this timestamp (or tag).
+    pub from_ts: Option<Either<i64, String>>,
+    /// Only versions written at or before this timestamp (or tag).
+    pub to_ts: Option<Either<i64, String>>,
+    /// "desc" (newest first, default) or "asc".
+    pub order: Option<String>,
+    /// Cursor returned by a previous page.
+    pub cursor: Option<String>,
+}
+
+/// Who made a `kvPut` and why, kept with the version it writes.
+#[napi(object)]
+pub struct JsWriteAnnotation {
+    pub author: Option<String>,
+    pub message: Option<String>,
+    /// Id of the request the write served, for tracing it across systems.
+    pub request_id: Option<String>,
+}
+
+/// A targeted history trim. Set `before`, `keepLast`, or both.
+#[napi(object)]
+pub struct JsHistoryTrimOptions {
+    /// Drop versions written before this timestamp (or tag).
+    pub before: Option<Either<i64, String>>,
+    /// Keep at most this many versions per key.
+    pub keep_last: Option<u32>,
+    /// Only trim this space (default: every space on the branch).
+    pub space: Option<String>,
+    /// Only trim this primitive: "kv", "state", "json", "event", or
+    /// "vector" (default: all).
+    pub primitive: Option<String>,
+}
+
+/// Time bounds for a key timeline. Either bound may be a timestamp or a
+/// tag name.
+#[napi(object)]
+pub struct JsTimelineOptions {
+    /// Earliest write to include (inclusive).
+    pub from: Option<Either<i64, String>>,
+    /// Latest write to include (inclusive).
+    pub to: Option<Either<i64, String>>,
+}
+
+/// Options for diffing the current branch between two timestamps.
+#[napi(object)]
+pub struct JsDiffTimeOptions {
+    /// Only diff this space (default: every space on the branch).
+    pub space: Option<String>,
+    /// Only report keys starting with this prefix.
+    pub prefix: Option<String>,
+}
+
+/// A live branch to compare a bundle against.
+#[napi(object)]
+pub struct JsBundleDiffTarget {
+    pub branch: String,
+}
+
+/// Options for merging branches.
+#[napi(object)]
+pub struct JsMergeOptions {
+    /// "last_writer_wins" (default), "strict", or "manual"; see `MergeStrategy`.
+    #[napi(ts_type = "MergeStrategy | `${MergeStrategy}`")]
+    pub strategy: Option<String>,
+    /// Compute the merge result without writing anything.
+    pub dry_run: Option<bool>,
+    /// Include both sides' values and versions in each reported conflict.
+    pub include_values: Option<bool>,
+}
+
+/// Distance metrics for vector collections, exported as
+/// `DistanceMetric`. Methods also take the value strings.
+#[napi(string_enum, js_name = "DistanceMetric")]
+pub enum JsDistanceMetric {
+    #[napi(value = "cosine")]
+    Cosine,
+    #[napi(value = "euclidean")]
+    Euclidean,
+    #[napi(value = "dot_product")]
+    DotProduct,
+}
+
+/// Branch merge strategies, exported as `MergeStrategy`.
+#[napi(string_enum, js_name = "MergeStrategy")]
+pub enum JsMergeStrategy {
+    #[napi(value = "last_writer_wins")]
+    LastWriterWins,
+    #[napi(value = "strict")]
+    Strict,
+    /// Handled by the JS wrapper, which stages conflicts for later.
+    #[napi(value = "manual")]
+    Manual,
+}
+
+/// Vector metadata and JSON query filter operators, exported as `FilterOp`.
+#[napi(string_enum, js_name = "FilterOp")]
+pub enum JsFilterOp {
+    #[napi(value = "eq")]
+    Eq,
+    #[napi(value = "ne")]
+    Ne,
+    #[napi(value = "gt")]
+    Gt,
+    #[napi(value = "gte")]
+    Gte,
+    #[napi(value = "lt")]
+    Lt,
+    #[napi(value = "lte")]
+    Lte,
+    #[napi(value = "in")]
+    In,
+    #[napi(value = "contains")]
+    Contains,
+    #[napi(value = "withinRadius")]
+    WithinRadius,
+    #[napi(value = "boundingBox")]
+    BoundingBox,
+}
+
+/// Options for cross-encoder reranking.
+#[napi(object)]
+pub struct JsRerankOptions {
+    /// Reranker model name. Absent = the default cross-encoder.
+    pub model: Option<String>,
 }
 
 // ---------------------------------------------------------------------------
@@ -106,6 +551,110 @@ fn js_to_value_checked(val: serde_json::Value, depth: usize) -> napi::Result<Val
     }
 }
 
+/// Limits a handle applies to the values it writes; see `JsJsonLimits`.
+#[derive(Clone, Copy)]
+struct JsonLimits {
+    max_depth: usize,
+    max_value_bytes: usize,
+    max_elements: usize,
+    strict: bool,
+}
+
+impl Default for JsonLimits {
+    fn default() -> Self {
+        JsonLimits {
+            max_depth: MAX_JSON_DEPTH,
+            max_value_bytes: DEFAULT_MAX_VALUE_BYTES,
+            max_elements: DEFAULT_MAX_ELEMENTS,
+            strict: false,
+        }
+    }
+}
+
+impl JsonLimits {
+    /// These limits with the fields set in `overrides` replaced.
+    fn with(self, overrides: Option<&JsJsonLimits>) -> napi::Result<Self> {
+        let Some(o) = overrides else {
+            return Ok(self);
+        };
+        if let Some(depth) = o.max_depth {
+            if depth as usize > JSON_DEPTH_CEILING {
+                return Err(napi::Error::from_reason(format!(
+                    "[VALIDATION] maxDepth must be at most {}",
+                    JSON_DEPTH_CEILING
+                )));
+            }
+        }
+        Ok(JsonLimits {
+            max_depth: o.max_depth.map_or(self.max_depth, |n| n as usize),
+            max_value_bytes: o
+                .max_value_bytes
+                .map_or(self.max_value_bytes, |n| n as usize),
+            max_elements: o.max_elements.map_or(self.max_elements, |n| n as usize),
+            strict: o.strict_values.unwrap_or(self.strict),
+        })
+    }
+
+    /// With `strictValues`, reject `value` if converting it lost data.
+    fn check_exact(&self, value: &JsValue) -> napi::Result<()> {
+        match &value.1 {
+            Some(loss) if self.strict => Err(napi::Error::from_reason(format!(
+                "[VALIDATION] {} (strictValues)",
+                loss
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    /// Reject `value` if it exceeds these limits.
+    fn check(&self, value: &Value) -> napi::Result<()> {
+        let mut bytes = 0;
+        self.check_at(value, 0, &mut bytes)
+    }
+
+    fn check_at(&self, value: &Value, depth: usize, bytes: &mut usize) -> napi::Result<()> {
+        if depth > self.max_depth {
+            return Err(napi::Error::from_reason(format!(
+                "[VALIDATION] JSON nesting depth exceeds maximum of {}",
+                self.max_depth
+            )));
+        }
+        let len = match value {
+            Value::Array(arr) => arr.len(),
+            Value::Object(map) => map.len(),
+            _ => 0,
+        };
+        if len > self.max_elements {
+            return Err(napi::Error::from_reason(format!(
+                "[VALIDATION] Array or object has {} elements, more than the maximum of {}",
+                len, self.max_elements
+            )));
+        }
+        *bytes += match value {
+            Value::String(s) => s.len(),
+            Value::Bytes(b) => b.len(),
+            Value::Object(map) => map.keys().map(String::len).sum(),
+            Value::Array(_) => 0,
+            _ => 8,
+        };
+        if *bytes > self.max_value_bytes {
+            return Err(napi::Error::from_reason(format!(
+                "[VALIDATION] Value exceeds the maximum size of {} bytes",
+                self.max_value_bytes
+            )));
+        }
+        match value {
+            Value::Array(arr) => arr
+                .iter()
+                .try_for_each(|item| self.check_at(item, depth + 1, bytes)),
+            Value::Object(map) => map
+                .values()
+                .try_for_each(|item| self.check_at(item, depth + 1, bytes)),
+            _ => Ok(()),
+        }
+    }
+}
+
 /// Validate a vector, rejecting NaN/Infinity, and convert f64 → f32.
 fn validate_vector(vec: &[f64]) -> napi::Result<Vec<f32>> {
     let mut out = Vec::with_capacity(vec.len());
@@ -121,6 +670,34 @@ fn validate_vector(vec: &[f64]) -> napi::Result<Vec<f32>> {
     Ok(out)
 }
 
+/// Split text into chunks of at most `chunk_size` characters, where each
+/// chunk starts `chunk_size - overlap` characters after the previous one.
+fn chunk_text(text: &str, chunk_size: usize, overlap: usize) -> napi::Result<Vec<String>> {
+    if chunk_size == 0 {
+        return Err(napi::Error::from_reason(
+            "[VALIDATION] chunkSize must be greater than 0",
+        ));
+    }
+    if overlap >= chunk_size {
+        return Err(napi::Error::from_reason(
+            "[VALIDATION] overlap must be smaller than chunkSize",
+        ));
+    }
+    let chars: Vec<char> = text.chars().collect();
+    let step = chunk_size - overlap;
+    let mut chunks = Vec::new();
+    let mut start = 0;
+    while start < chars.len() {
+        let end = (start + chunk_size).min(chars.len());
+        chunks.push(chars[start..end].iter().collect());
+        if end == chars.len() {
+            break;
+        }
+        start += step;
+    }
+    Ok(chunks)
+}
+
 /// Convert a stratadb Value to a serde_json Value.
 fn value_to_js(val: Value) -> serde_json::Value {
     match val {
@@ -145,6 +722,308 @@ fn value_to_js(val: Value) -> serde_json::Value {
     }
 }
 
+/// A stratadb Value passed to or from JavaScript without a serde_json
+/// intermediary: inputs are read straight off the napi object and array
+/// APIs, and outputs are built the same way.
+///
+/// Conversion follows `JSON.stringify`: `undefined`, functions, and symbols
+/// are dropped from objects and become `null` in arrays, non-finite numbers
+/// become `null`, and integral numbers within the safe-integer range are
+/// stored as `Int`. BigInts are stored as `Int` if they fit in 64 bits,
+/// boxed numbers (`new Number(2)`) as `Float`, and Dates as ISO 8601
+/// strings. The second field describes the first conversion that lost
+/// data, which writes reject under `strictValues`.
+struct JsValue(Value, Option<String>);
+
+impl TypeName for JsValue {
+    fn type_name() -> &'static str {
+        "any"
+    }
+
+    fn value_type() -> ValueType {
+        ValueType::Unknown
+    }
+}
+
+impl FromNapiValue for JsValue {
+    unsafe fn from_napi_value(env: sys::napi_env, napi_val: sys::napi_value) -> napi::Result<Self> {
+        let val = JsUnknown::from_raw_unchecked(env, napi_val);
+        let env = Env::from_raw(env);
+        let mut loss = None;
+        let value = napi_to_value(&env, val, 0, &mut loss)?.unwrap_or_else(|| {
+            lose(&mut loss, || "Value became null".into());
+            Value::Null
+        });
+        Ok(JsValue(value, loss))
+    }
+}
+
+/// An entry of a batch write: converted like `JsValue`, except that a
+/// top-level field set to `undefined` is kept as `null` instead of dropped,
+/// so `{ key, value: undefined }` writes null.
+struct JsBatchEntry(JsValue);
+
+impl TypeName for JsBatchEntry {
+    fn type_name() -> &'static str {
+        "any"
+    }
+
+    fn value_type() -> ValueType {
+        ValueType::Unknown
+    }
+}
+
+impl FromNapiValue for JsBatchEntry {
+    unsafe fn from_napi_value(env: sys::napi_env, napi_val: sys::napi_value) -> napi::Result<Self> {
+        let val = JsUnknown::from_raw_unchecked(env, napi_val);
+        if val.get_type()? != ValueType::Object || val.is_array()? {
+            return JsValue::from_napi_value(env, napi_val).map(JsBatchEntry);
+        }
+        let obj: JsObject = val.cast();
+        let env = Env::from_raw(env);
+        let names = obj.get_property_names()?;
+        let len = names.get_array_length()?;
+        let mut loss = None;
+        let mut out = HashMap::with_capacity(len as usize);
+        for i in 0..len {
+            let name = names.get_element::<JsString>(i)?;
+            let item = obj.get_property::<_, JsUnknown>(name)?;
+            let name = name.into_utf8()?.into_owned()?;
+            let value = if item.get_type()? == ValueType::Undefined {
+                Some(Value::Null)
+            } else {
+                napi_to_value(&env, item, 1, &mut loss)?
+            };
+            match value {
+                Some(v) => {
+                    out.insert(name, v);
+                }
+                None => lose(&mut loss, || format!("Property '{}' was dropped", name)),
+            }
+        }
+        Ok(JsBatchEntry(JsValue(Value::Object(Box::new(out)), loss)))
+    }
+}
+
+impl ToNapiValue for JsValue {
+    unsafe fn to_napi_value(env: sys::napi_env, val: Self) -> napi::Result<sys::napi_value> {
+        let env = Env::from_raw(env);
+        Ok(value_to_napi(&env, val.0, ValueMode::default())?.raw())
+    }
+}
+
+/// How a handle returns the values it reads; see the `bigints` and
+/// `boxedFloats` open options.
+#[derive(Clone, Copy, Default)]
+struct ValueMode {
+    big_ints: bool,
+    boxed_floats: bool,
+}
+
+impl ValueMode {
+    fn from_options(options: Option<&JsOpenOptions>) -> Self {
+        ValueMode {
+            big_ints: options.and_then(|o| o.bigints).unwrap_or(false),
+            boxed_floats: options.and_then(|o| o.boxed_floats).unwrap_or(false),
+        }
+    }
+}
+
+/// A value returned to JavaScript in a handle's `ValueMode`.
+struct JsOutput(Value, ValueMode);
+
+impl TypeName for JsOutput {
+    fn type_name() -> &'static str {
+        "any"
+    }
+
+    fn value_type() -> ValueType {
+        ValueType::Unknown
+    }
+}
+
+impl ToNapiValue for JsOutput {
+    unsafe fn to_napi_value(env: sys::napi_env, val: Self) -> napi::Result<sys::napi_value> {
+        let env = Env::from_raw(env);
+        Ok(value_to_napi(&env, val.0, val.1)?.raw())
+    }
+}
+
+/// Largest integer a JS number represents exactly (2^53).
+const MAX_SAFE_INTEGER: f64 = 9_007_199_254_740_992.0;
+
+/// Record what was lost in conversion, unless something already was.
+fn lose(loss: &mut Option<String>, what: impl FnOnce() -> String) {
+    if loss.is_none() {
+        *loss = Some(what());
+    }
+}
+
+/// Convert a JS value to a stratadb Value with depth checking. Returns
+/// `None` for values JSON has no representation for (`undefined`,
+/// functions, symbols), and describes in `loss` the first thing dropped
+/// or changed.
+fn napi_to_value(
+    env: &Env,
+    val: JsUnknown,
+    depth: usize,
+    loss: &mut Option<String>,
+) -> napi::Result<Option<Value>> {
+    if depth > JSON_DEPTH_CEILING {
+        return Err(napi::Error::from_reason(
+            "[VALIDATION] JSON nesting depth exceeds maximum of 1024",
+        ));
+    }
+    let value = match val.get_type()? {
+        ValueType::Null => Value::Null,
+        ValueType::Boolean => Value::Bool(val.coerce_to_bool()?.get_value()?),
+        ValueType::Number => {
+            let f = val.coerce_to_number()?.get_double()?;
+            if !f.is_finite() {
+                lose(loss, || format!("{} became null", f));
+                Value::Null
+            } else if f.fract() == 0.0 && f.abs() <= MAX_SAFE_INTEGER {
+                Value::Int(f as i64)
+            } else {
+                if f.fract() == 0.0 {
+                    lose(loss, || format!("{} is beyond 2^53; pass a BigInt", f));
+                }
+                Value::Float(f)
+            }
+        }
+        ValueType::String => Value::String(val.coerce_to_string()?.into_utf8()?.into_owned()?),
+        ValueType::Object if val.is_array()? => {
+            let arr: JsObject = unsafe { val.cast() };
+            let len = arr.get_array_length()?;
+            let mut out = Vec::with_capacity(len as usize);
+            for i in 0..len {
+                let item = arr.get_element::<JsUnknown>(i)?;
+                match napi_to_value(env, item, depth + 1, loss)? {
+                    Some(v) => out.push(v),
+                    None => {
+                        lose(loss, || format!("Array element {} became null", i));
+                        out.push(Value::Null);
+                    }
+                }
+            }
+            Value::Array(Box::new(out))
+        }
+        ValueType::Object => {
+            let obj: JsObject = unsafe { val.cast() };
+            let names = obj.get_property_names()?;
+            let len = names.get_array_length()?;
+            // Dates, like JSON.stringify, become their `toJSON()` string.
+            if len == 0 && val.is_date()? {
+                let to_json: JsFunction = obj.get_named_property("toJSON")?;
+                return napi_to_value(env, to_json.call_without_args(Some(&obj))?, depth, loss);
+            }
+            // Boxed numbers have no enumerable properties.
+            if len == 0 && is_boxed_number(env, &obj)? {
+                let f = val.coerce_to_number()?.get_double()?;
+                return Ok(Some(if f.is_finite() {
+                    Value::Float(f)
+                } else {
+                    lose(loss, || format!("{} became null", f));
+                    Value::Null
+                }));
+            }
+            let mut out = HashMap::with_capacity(len as usize);
+            for i in 0..len {
+                let name = names.get_element::<JsString>(i)?;
+                let item = obj.get_property::<_, JsUnknown>(name)?;
+                let name = name.into_utf8()?.into_owned()?;
+                match napi_to_value(env, item, depth + 1, loss)? {
+                    Some(v) => {
+                        out.insert(name, v);
+                    }
+                    None => lose(loss, || format!("Property '{}' was dropped", name)),
+                }
+            }
+            Value::Object(Box::new(out))
+        }
+        ValueType::BigInt => {
+            let big: JsBigInt = unsafe { val.cast() };
+            let (i, lossless) = big.get_i64()?;
+            if !lossless {
+                return Err(napi::Error::from_reason(
+                    "[VALIDATION] BigInt value does not fit in a 64-bit integer",
+                ));
+            }
+            Value::Int(i)
+        }
+        _ => return Ok(None),
+    };
+    Ok(Some(value))
+}
+
+/// Whether `obj` is a boxed number, e.g. `new Number(2)`.
+fn is_boxed_number(env: &Env, obj: &JsObject) -> napi::Result<bool> {
+    let number: JsFunction = env.get_global()?.get_named_property("Number")?;
+    obj.instanceof(number)
+}
+
+/// Build a JS value from a stratadb Value, with the same mapping as
+/// `value_to_js`, except as `mode` says.
+fn value_to_napi(env: &Env, val: Value, mode: ValueMode) -> napi::Result<JsUnknown> {
+    Ok(match val {
+        Value::Null => env.get_null()?.into_unknown(),
+        Value::Bool(b) => env.get_boolean(b)?.into_unknown(),
+        Value::Int(i) if mode.big_ints && (i as f64).abs() > MAX_SAFE_INTEGER => {
+            env.create_bigint_from_i64(i)?.into_unknown()?
+        }
+        Value::Int(i) => env.create_int64(i)?.into_unknown(),
+        Value::Float(f) if mode.boxed_floats && f.is_finite() && f.fract() == 0.0 => {
+            env.create_double(f)?.coerce_to_object()?.into_unknown()
+        }
+        Value::Float(f) if f.is_finite() => env.create_double(f)?.into_unknown(),
+        Value::Float(_) => env.get_null()?.into_unknown(),
+        Value::String(s) => env.create_string_from_std(s)?.into_unknown(),
+        Value::Bytes(b) => env
+            .create_string_from_std(base64_encode(&b))?
+            .into_unknown(),
+        Value::Array(arr) => {
+            let mut out = env.create_array_with_length(arr.len())?;
+            for (i, item) in (*arr).into_iter().enumerate() {
+                out.set_element(i as u32, value_to_napi(env, item, mode)?)?;
+            }
+            out.into_unknown()
+        }
+        Value::Object(map) => {
+            let mut out = env.create_object()?;
+            for (k, v) in *map {
+                out.set_property(env.create_string_from_std(k)?, value_to_napi(env, v, mode)?)?;
+            }
+            out.into_unknown()
+        }
+    })
+}
+
+/// Take the named field out of a converted batch entry.
+fn take_field(entry: &mut HashMap<String, Value>, field: &str) -> napi::Result<Value> {
+    entry
+        .remove(field)
+        .ok_or_else(|| napi::Error::from_reason(format!("[VALIDATION] Missing '{}'", field)))
+}
+
+/// Take the named string field out of a converted batch entry.
+fn take_string(entry: &mut HashMap<String, Value>, field: &str) -> napi::Result<String> {
+    match entry.remove(field) {
+        Some(Value::String(s)) => Ok(s),
+        _ => Err(napi::Error::from_reason(format!(
+            "[VALIDATION] Missing '{}'",
+            field
+        ))),
+    }
+}
+
+/// Unwrap a batch entry, which must be an object.
+fn batch_entry(entry: JsBatchEntry) -> napi::Result<HashMap<String, Value>> {
+    match entry.0 .0 {
+        Value::Object(map) => Ok(*map),
+        _ => Err(napi::Error::from_reason("[VALIDATION] Expected object")),
+    }
+}
+
 /// Simple base64 encoding for bytes.
 fn base64_encode(data: &[u8]) -> String {
     use std::io::Write;
@@ -187,13 +1066,309 @@ fn base64_encoder(writer: &mut Vec<u8>) -> impl std::io::Write + '_ {
     Base64Writer(writer)
 }
 
-/// Convert a VersionedValue to a JSON object.
-fn versioned_to_js(vv: VersionedValue) -> serde_json::Value {
-    serde_json::json!({
-        "value": value_to_js(vv.value),
-        "version": vv.version,
-        "timestamp": vv.timestamp,
-    })
+/// Encode a stratadb Value as MessagePack. Ints use compact integer
+/// formats, floats are always float 64, and bytes are `bin`.
+fn msgpack_encode(val: &Value, out: &mut Vec<u8>) {
+    /// Write a length prefix: the fix format (`(base, max_len)`) if the
+    /// length fits, else the 8-bit (where the type has one), 16- or 32-bit form.
+    fn len_header(
+        out: &mut Vec<u8>,
+        len: usize,
+        fix: Option<(u8, usize)>,
+        len8: Option<u8>,
+        len16: u8,
+        len32: u8,
+    ) {
+        match (fix, len8) {
+            (Some((base, max)), _) if len <= max => out.push(base | len as u8),
+            (_, Some(code)) if len <= 0xff => out.extend_from_slice(&[code, len as u8]),
+            _ if len <= 0xffff => {
+                out.push(len16);
+                out.extend_from_slice(&(len as u16).to_be_bytes());
+            }
+            _ => {
+                out.push(len32);
+                out.extend_from_slice(&(len as u32).to_be_bytes());
+            }
+        }
+    }
+    match val {
+        Value::Null => out.push(0xc0),
+        Value::Bool(b) => out.push(if *b { 0xc3 } else { 0xc2 }),
+        Value::Int(i) => match *i {
+            -32..=127 => out.push(*i as u8),
+            -128..=-33 => out.extend_from_slice(&[0xd0, *i as u8]),
+            128..=0xff => out.extend_from_slice(&[0xcc, *i as u8]),
+            -32768..=32767 => {
+                out.push(0xd1);
+                out.extend_from_slice(&(*i as i16).to_be_bytes());
+            }
+            -2_147_483_648..=2_147_483_647 => {
+                out.push(0xd2);
+                out.extend_from_slice(&(*i as i32).to_be_bytes());
+            }
+            _ => {
+                out.push(0xd3);
+                out.extend_from_slice(&i.to_be_bytes());
+            }
+        },
+        Value::Float(f) => {
+            out.push(0xcb);
+            out.extend_from_slice(&f.to_be_bytes());
+        }
+        Value::String(s) => {
+            len_header(out, s.len(), Some((0xa0, 31)), Some(0xd9), 0xda, 0xdb);
+            out.extend_from_slice(s.as_bytes());
+        }
+        Value::Bytes(b) => {
+            len_header(out, b.len(), None, Some(0xc4), 0xc5, 0xc6);
+            out.extend_from_slice(b);
+        }
+        Value::Array(arr) => {
+            len_header(out, arr.len(), Some((0x90, 15)), None, 0xdc, 0xdd);
+            for item in arr.iter() {
+                msgpack_encode(item, out);
+            }
+        }
+        Value::Object(map) => {
+            len_header(out, map.len(), Some((0x80, 15)), None, 0xde, 0xdf);
+            for (k, v) in map.iter() {
+                msgpack_encode(&Value::String(k.clone()), out);
+                msgpack_encode(v, out);
+            }
+        }
+    }
+}
+
+/// Decode a complete MessagePack buffer into a stratadb Value. Map keys
+/// must be strings; extension types and trailing bytes are rejected.
+fn msgpack_decode(buf: &[u8]) -> napi::Result<Value> {
+    let mut pos = 0;
+    let val = msgpack_decode_at(buf, &mut pos, 0)?;
+    if pos != buf.len() {
+        return Err(napi::Error::from_reason(
+            "[VALIDATION] Trailing bytes after MessagePack value",
+        ));
+    }
+    Ok(val)
+}
+
+fn msgpack_decode_at(buf: &[u8], pos: &mut usize, depth: usize) -> napi::Result<Value> {
+    fn take<'a>(buf: &'a [u8], pos: &mut usize, n: usize) -> napi::Result<&'a [u8]> {
+        let end = pos
+            .checked_add(n)
+            .filter(|&end| end <= buf.len())
+            .ok_or_else(|| napi::Error::from_reason("[VALIDATION] Truncated MessagePack value"))?;
+        let bytes = &buf[*pos..end];
+        *pos = end;
+        Ok(bytes)
+    }
+    fn uint(buf: &[u8], pos: &mut usize, n: usize) -> napi::Result<u64> {
+        Ok(take(buf, pos, n)?
+            .iter()
+            .fold(0u64, |acc, &b| (acc << 8) | b as u64))
+    }
+    fn string(buf: &[u8], pos: &mut usize, len: usize) -> napi::Result<String> {
+        String::from_utf8(take(buf, pos, len)?.to_vec()).map_err(|_| {
+            napi::Error::from_reason("[VALIDATION] MessagePack string is not valid UTF-8")
+        })
+    }
+
+    if depth > JSON_DEPTH_CEILING {
+        return Err(napi::Error::from_reason(
+            "[VALIDATION] JSON nesting depth exceeds maximum of 1024",
+        ));
+    }
+    let marker = take(buf, pos, 1)?[0];
+    let (len, container) = match marker {
+        0x00..=0x7f => return Ok(Value::Int(marker as i64)),
+        0xe0..=0xff => return Ok(Value::Int(marker as i8 as i64)),
+        0xc0 => return Ok(Value::Null),
+        0xc2 => return Ok(Value::Bool(false)),
+        0xc3 => return Ok(Value::Bool(true)),
+        0xcc..=0xce => return Ok(Value::Int(uint(buf, pos, 1 << (marker - 0xcc))? as i64)),
+        0xcf => {
+            let n = uint(buf, pos, 8)?;
+            return i64::try_from(n).map(Value::Int).map_err(|_| {
+                napi::Error::from_reason("[VALIDATION] MessagePack integer exceeds i64 range")
+            });
+        }
+        0xd0 => return Ok(Value::Int(uint(buf, pos, 1)? as i8 as i64)),
+        0xd1 => return Ok(Value::Int(uint(buf, pos, 2)? as i16 as i64)),
+        0xd2 => return Ok(Value::Int(uint(buf, pos, 4)? as i32 as i64)),
+        0xd3 => return Ok(Value::Int(uint(buf, pos, 8)? as i64)),
+        0xca => {
+            let bits = uint(buf, pos, 4)? as u32;
+            return Ok(Value::Float(f32::from_bits(bits) as f64));
+        }
+        0xcb => return Ok(Value::Float(f64::from_bits(uint(buf, pos, 8)?))),
+        0xa0..=0xbf => return Ok(Value::String(string(buf, pos, (marker & 0x1f) as usize)?)),
+        0xd9..=0xdb => {
+            let len = uint(buf, pos, 1 << (marker - 0xd9))? as usize;
+            return Ok(Value::String(string(buf, pos, len)?));
+        }
+        0xc4..=0xc6 => {
+            let len = uint(buf, pos, 1 << (marker - 0xc4))? as usize;
+            return Ok(Value::Bytes(take(buf, pos, len)?.to_vec()));
+        }
+        0x90..=0x9f => ((marker & 0x0f) as usize, 0x90),
+        0xdc | 0xdd => (uint(buf, pos, 2 << (marker - 0xdc))? as usize, 0x90),
+        0x80..=0x8f => ((marker & 0x0f) as usize, 0x80),
+        0xde | 0xdf => (uint(buf, pos, 2 << (marker - 0xde))? as usize, 0x80),
+        _ => {
+            return Err(napi::Error::from_reason(format!(
+                "[VALIDATION] Unsupported MessagePack type 0x{:02x}",
+                marker
+            )))
+        }
+    };
+    // Every element takes at least one byte, so cap preallocation by what's left.
+    let cap = len.min(buf.len() - *pos);
+    if container == 0x90 {
+        let mut out = Vec::with_capacity(cap);
+        for _ in 0..len {
+            out.push(msgpack_decode_at(buf, pos, depth + 1)?);
+        }
+        return Ok(Value::Array(Box::new(out)));
+    }
+    let mut out = HashMap::with_capacity(cap);
+    for _ in 0..len {
+        let key = match msgpack_decode_at(buf, pos, depth + 1)? {
+            Value::String(k) => k,
+            _ => {
+                return Err(napi::Error::from_reason(
+                    "[VALIDATION] MessagePack map keys must be strings",
+                ))
+            }
+        };
+        out.insert(key, msgpack_decode_at(buf, pos, depth + 1)?);
+    }
+    Ok(Value::Object(Box::new(out)))
+}
+
+/// Convert a VersionedValue to a JSON object.
+fn versioned_to_js(vv: VersionedValue) -> serde_json::Value {
+    serde_json::json!({
+        "value": value_to_js(vv.value),
+        "version": vv.version,
+        "timestamp": vv.timestamp,
+    })
+}
+
+/// Filter, order, and page a version history per `options`, returning
+/// `{ versions, cursor }`. The cursor is the last version returned.
+fn history_page(
+    db: &RustStrata,
+    mut versions: Vec<VersionedValue>,
+    options: JsHistoryOptions,
+) -> napi::Result<serde_json::Value> {
+    let descending = match options.order.as_deref() {
+        None | Some("desc") => true,
+        Some("asc") => false,
+        Some(other) => {
+            return Err(napi::Error::from_reason(format!(
+                "[VALIDATION] order must be \"asc\" or \"desc\", got \"{}\"",
+                other
+            )))
+        }
+    };
+    let after = match options.cursor {
+        Some(c) => Some(c.parse::<u64>().map_err(|_| {
+            napi::Error::from_reason(format!("[VALIDATION] Invalid cursor: {}", c))
+        })?),
+        None => None,
+    };
+    let before = options.before_version.map(|v| v.max(0) as u64);
+    let from = resolve_as_of(db, options.from_ts)?;
+    let to = resolve_as_of(db, options.to_ts)?;
+    versions.retain(|vv| {
+        before.is_none_or(|b| vv.version < b)
+            && from.is_none_or(|f| vv.timestamp >= f)
+            && to.is_none_or(|t| vv.timestamp <= t)
+            && after.is_none_or(|a| {
+                if descending {
+                    vv.version < a
+                } else {
+                    vv.version > a
+                }
+            })
+    });
+    versions.sort_by_key(|vv| vv.version);
+    if descending {
+        versions.reverse();
+    }
+    let limit = match options.limit {
+        Some(0) => {
+            return Err(napi::Error::from_reason(
+                "[VALIDATION] limit must be greater than 0",
+            ))
+        }
+        Some(l) => l as usize,
+        None => usize::MAX,
+    };
+    let cursor = (versions.len() > limit).then(|| versions[limit - 1].version.to_string());
+    let page: Vec<serde_json::Value> = versions
+        .into_iter()
+        .take(limit)
+        .map(versioned_to_js)
+        .collect();
+    Ok(serde_json::json!({ "versions": page, "cursor": cursor }))
+}
+
+/// Append the JSON Patch operations that turn `a` into `b` to `ops`.
+///
+/// Objects and arrays are compared member by member; anything else that
+/// differs is replaced whole. `path` is the JSON Pointer of `a` and `b`.
+fn json_diff(
+    a: &serde_json::Value,
+    b: &serde_json::Value,
+    path: &mut String,
+    ops: &mut Vec<serde_json::Value>,
+) {
+    use serde_json::Value as J;
+    fn child(path: &mut String, token: &str) -> usize {
+        let len = path.len();
+        path.push('/');
+        path.push_str(&token.replace('~', "~0").replace('/', "~1"));
+        len
+    }
+    match (a, b) {
+        (J::Object(ma), J::Object(mb)) => {
+            for (k, va) in ma {
+                let len = child(path, k);
+                match mb.get(k) {
+                    Some(vb) => json_diff(va, vb, path, ops),
+                    None => ops.push(serde_json::json!({ "op": "remove", "path": path })),
+                }
+                path.truncate(len);
+            }
+            for (k, vb) in mb.iter().filter(|(k, _)| !ma.contains_key(*k)) {
+                let len = child(path, k);
+                ops.push(serde_json::json!({ "op": "add", "path": path, "value": vb }));
+                path.truncate(len);
+            }
+        }
+        (J::Array(xa), J::Array(xb)) => {
+            for (i, (va, vb)) in xa.iter().zip(xb).enumerate() {
+                let len = child(path, &i.to_string());
+                json_diff(va, vb, path, ops);
+                path.truncate(len);
+            }
+            for (i, vb) in xb.iter().enumerate().skip(xa.len()) {
+                let len = child(path, &i.to_string());
+                ops.push(serde_json::json!({ "op": "add", "path": path, "value": vb }));
+                path.truncate(len);
+            }
+            // Remove from the end so earlier indices stay valid.
+            for i in (xb.len()..xa.len()).rev() {
+                let len = child(path, &i.to_string());
+                ops.push(serde_json::json!({ "op": "remove", "path": path }));
+                path.truncate(len);
+            }
+        }
+        _ if a != b => ops.push(serde_json::json!({ "op": "replace", "path": path, "value": b })),
+        _ => {}
+    }
 }
 
 /// Convert a DescribeResult to camelCase JSON for JS consumers.
@@ -252,6 +1427,10 @@ fn describe_to_js(desc: DescribeResult) -> serde_json::Value {
 }
 
 /// Convert stratadb error to napi Error with category prefix.
+fn io_err(e: std::io::Error) -> napi::Error {
+    napi::Error::from_reason(format!("[IO] {}", e))
+}
+
 fn to_napi_err(e: StrataError) -> napi::Error {
     let code = match &e {
         StrataError::KeyNotFound { .. }
@@ -294,11 +1473,135 @@ fn to_napi_err(e: StrataError) -> napi::Error {
     napi::Error::from_reason(format!("{} {}", code, e))
 }
 
-/// Helper to acquire the mutex lock, mapping poison errors.
-fn lock_inner(
-    inner: &Mutex<RustStrata>,
-) -> napi::Result<std::sync::MutexGuard<'_, RustStrata>> {
-    inner
+/// A handle's own view of its database: its current branch and space over
+/// the engine it shares with every other handle on the database.
+struct HandleDb {
+    db: RwLock<Option<RustStrata>>,
+    /// Shared with `Registered::cut`. Every call holds it shared; an
+    /// operation that must see no write from any handle takes it exclusively.
+    cut: Arc<RwLock<()>>,
+}
+
+/// Helper to acquire shared access to the database, mapping poison errors
+/// and failing with `[CLOSED]` once the database has been closed.
+///
+/// Reads and writes through `&RustStrata` run in parallel; the engine does
+/// its own concurrency control. Only operations that change the handle's
+/// current branch or space take the lock exclusively (`lock_inner_mut`).
+fn lock_inner(inner: &HandleDb) -> napi::Result<DbRead<'_>> {
+    let cut = inner
+        .cut
+        .read()
+        .map_err(|_| napi::Error::from_reason("Lock poisoned"))?;
+    let guard = inner
+        .db
+        .read()
+        .map_err(|_| napi::Error::from_reason("Lock poisoned"))?;
+    if guard.is_none() {
+        return Err(closed_err());
+    }
+    Ok(DbRead(guard, cut))
+}
+
+/// Exclusive counterpart of `lock_inner`. Other handles on the database
+/// keep running.
+fn lock_inner_mut(inner: &HandleDb) -> napi::Result<DbWrite<'_>> {
+    let cut = inner
+        .cut
+        .read()
+        .map_err(|_| napi::Error::from_reason("Lock poisoned"))?;
+    let guard = inner
+        .db
+        .write()
+        .map_err(|_| napi::Error::from_reason("Lock poisoned"))?;
+    if guard.is_none() {
+        return Err(closed_err());
+    }
+    Ok(DbWrite(guard, cut))
+}
+
+/// Like `lock_inner`, but waits out every call on every handle of the
+/// database and holds them off until the guard is dropped.
+fn lock_database(inner: &HandleDb) -> napi::Result<DbCut<'_>> {
+    let cut = inner
+        .cut
+        .write()
+        .map_err(|_| napi::Error::from_reason("Lock poisoned"))?;
+    let guard = inner
+        .db
+        .read()
+        .map_err(|_| napi::Error::from_reason("Lock poisoned"))?;
+    if guard.is_none() {
+        return Err(closed_err());
+    }
+    Ok(DbCut(guard, cut))
+}
+
+/// Fail with `[CLOSED]` once the handle has been closed, for entry points
+/// that don't otherwise touch the database. Never blocks: while the view is
+/// locked exclusively the handle counts as open, and the call fails later
+/// in `lock_inner` if a `close()` took it.
+fn ensure_open(inner: &HandleDb) -> napi::Result<()> {
+    match inner.db.try_read() {
+        Ok(guard) if guard.is_none() => Err(closed_err()),
+        Err(std::sync::TryLockError::Poisoned(_)) => Err(napi::Error::from_reason("Lock poisoned")),
+        _ => Ok(()),
+    }
+}
+
+fn closed_err() -> napi::Error {
+    napi::Error::from_reason("[CLOSED] Database is closed")
+}
+
+/// Shared access to an open database, from `lock_inner`.
+struct DbRead<'a>(
+    std::sync::RwLockReadGuard<'a, Option<RustStrata>>,
+    std::sync::RwLockReadGuard<'a, ()>,
+);
+
+/// Exclusive access to an open database, from `lock_inner_mut`.
+struct DbWrite<'a>(
+    std::sync::RwLockWriteGuard<'a, Option<RustStrata>>,
+    std::sync::RwLockReadGuard<'a, ()>,
+);
+
+/// Access to an open database with every other call held off, from
+/// `lock_database`.
+struct DbCut<'a>(
+    std::sync::RwLockReadGuard<'a, Option<RustStrata>>,
+    std::sync::RwLockWriteGuard<'a, ()>,
+);
+
+impl std::ops::Deref for DbRead<'_> {
+    type Target = RustStrata;
+    fn deref(&self) -> &RustStrata {
+        // lock_inner only hands out guards on an open database.
+        self.0.as_ref().expect("database is open")
+    }
+}
+
+impl std::ops::Deref for DbWrite<'_> {
+    type Target = RustStrata;
+    fn deref(&self) -> &RustStrata {
+        self.0.as_ref().expect("database is open")
+    }
+}
+
+impl std::ops::Deref for DbCut<'_> {
+    type Target = RustStrata;
+    fn deref(&self) -> &RustStrata {
+        self.0.as_ref().expect("database is open")
+    }
+}
+
+impl std::ops::DerefMut for DbWrite<'_> {
+    fn deref_mut(&mut self) -> &mut RustStrata {
+        self.0.as_mut().expect("database is open")
+    }
+}
+
+fn lock_scope(scope: &Mutex<TxnScope>) -> napi::Result<std::sync::MutexGuard<'_, TxnScope>> {
+    scope
         .lock()
         .map_err(|_| napi::Error::from_reason("Lock poisoned"))
 }
@@ -567,338 +1870,1325 @@ fn output_to_json(output: Output) -> serde_json::Value {
 /// Node.js event loop is never blocked.
 #[napi]
 pub struct Strata {
-    inner: Arc<Mutex<RustStrata>>,
+    /// This handle's view of the database; taken by `close()`.
+    inner: Arc<HandleDb>,
     session: Arc<Mutex<Option<Session>>>,
+    /// Branch and space set by `begin()`, applied to `execute()` commands
+    /// that don't name their own while the transaction is active, and the
+    /// writes made so far.
+    txn_scope: Arc<Mutex<TxnScope>>,
+    pool: Arc<WorkerPool>,
+    /// This handle's share of the database; taken by `close()`.
+    registration: Arc<Mutex<Option<Arc<Registered>>>>,
+    /// Canonical path of the database, `None` for in-memory databases.
+    path: Option<String>,
+    /// `"readWrite"`, `"readOnly"`, or `"follower"`.
+    access_mode: &'static str,
+    /// Shared with `Registered::last_flush`.
+    last_flush: Arc<AtomicU64>,
+    /// Shared with `Registered::syncs_seen`.
+    syncs_seen: Arc<AtomicU64>,
+    /// Shared with `Registered::counter_gate`.
+    counter_gate: Arc<Mutex<()>>,
+    /// Shared with `Registered::cache`.
+    cache: Arc<ReadCache>,
+    /// `Registered::id` of the database this handle shares.
+    shared_id: u64,
+    /// `jsonLimits` from the open options.
+    json_limits: JsonLimits,
+    /// How values read through this handle are returned.
+    value_mode: ValueMode,
 }
 
-#[napi]
-impl Strata {
-    // =========================================================================
-    // Factory methods (sync — lightweight, no I/O worth spawning for)
-    // =========================================================================
+/// A database shared by every handle opened on it. Handles to the same path
+/// hold the same `Registered`, and the last one to close releases it.
+struct Registered {
+    /// Unique in the process, from `next_registered_id`.
+    id: u64,
+    /// The engine; each handle works through its own view of it, so
+    /// handles keep their own current branch and space.
+    engine: RustStrata,
+    /// Held shared by every call on every handle; see `lock_database`.
+    cut: Arc<RwLock<()>>,
+    /// Canonical path in the registry, `None` for in-memory databases.
+    key: Option<PathBuf>,
+    read_only: bool,
+    follower: bool,
+    /// The writer lock on the data directory, held by read-write opens.
+    _writer_lock: Option<std::fs::File>,
+    /// When the WAL was last synced, by `flush()` on any handle or by the
+    /// engine, in microseconds since the epoch; 0 if it hasn't been.
+    last_flush: Arc<AtomicU64>,
+    /// The engine's sync count when `last_flush` was last brought up to
+    /// date; see `note_engine_syncs`.
+    syncs_seen: Arc<AtomicU64>,
+    /// Held while a counter is updated, so updates through different
+    /// handles don't race each other's compare-and-swap.
+    counter_gate: Arc<Mutex<()>>,
+    /// Sized by the `cacheMB` of the first open.
+    cache: Arc<ReadCache>,
+}
 
-    /// Open a database at the given path.
-    #[napi(factory)]
-    pub fn open(path: String, options: Option<JsOpenOptions>) -> napi::Result<Self> {
-        let auto_embed = options.as_ref().and_then(|o| o.auto_embed).unwrap_or(false);
-        let read_only = options.as_ref().and_then(|o| o.read_only).unwrap_or(false);
-        let follower = options.as_ref().and_then(|o| o.follower).unwrap_or(false);
+fn next_registered_id() -> u64 {
+    static NEXT: AtomicU64 = AtomicU64::new(1);
+    NEXT.fetch_add(1, Ordering::Relaxed)
+}
 
-        #[cfg(feature = "embed")]
-        if auto_embed {
-            if let Err(e) = strata_intelligence::embed::download::ensure_model() {
-                eprintln!("Warning: failed to download model files: {}", e);
-            }
-        }
+/// A branch, space, and key a `ReadCache` holds the value of.
+type CacheKey = (String, String, String);
+
+/// Latest KV values read through `kvGet`, kept by the binding and shared by
+/// every handle on a database, up to `capacity` bytes (keys plus JSON-
+/// encoded values). Entries are only good for the database version they
+/// were read at, so the first lookup after any write empties the cache.
+struct ReadCache {
+    capacity: u64,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    state: Mutex<CacheState>,
+}
 
-        let mut opts = OpenOptions::new();
-        if read_only || follower {
-            opts = opts.access_mode(AccessMode::ReadOnly);
-        }
-        if follower {
-            opts = opts.follower(true);
-        }
+#[derive(Default)]
+struct CacheState {
+    /// Database version the entries were read at.
+    version: u64,
+    /// Bumped on every use, so `order` runs from least to most recently
+    /// used.
+    tick: u64,
+    bytes: u64,
+    entries: HashMap<CacheKey, (u64, Option<Value>, u64)>,
+    order: BTreeMap<u64, CacheKey>,
+}
 
-        let raw = RustStrata::open_with(&path, opts).map_err(to_napi_err)?;
-        if auto_embed {
-            raw.set_auto_embed(true).map_err(to_napi_err)?;
+impl CacheState {
+    /// Drop every entry if they were read at another version than
+    /// `version`.
+    fn at_version(&mut self, version: u64) {
+        if self.version != version {
+            *self = CacheState {
+                version,
+                ..CacheState::default()
+            };
         }
-        Ok(Self {
-            inner: Arc::new(Mutex::new(raw)),
-            session: Arc::new(Mutex::new(None)),
-        })
     }
+}
 
-    /// Create an in-memory database (no persistence).
-    #[napi(factory)]
-    pub fn cache() -> napi::Result<Self> {
-        let raw = RustStrata::cache().map_err(to_napi_err)?;
-        Ok(Self {
-            inner: Arc::new(Mutex::new(raw)),
-            session: Arc::new(Mutex::new(None)),
-        })
+impl ReadCache {
+    fn new(cache_mb: Option<u32>) -> Self {
+        ReadCache {
+            capacity: u64::from(cache_mb.unwrap_or(0)) * 1024 * 1024,
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            state: Mutex::new(CacheState::default()),
+        }
     }
 
-    // =========================================================================
-    // KV Store
-    // =========================================================================
+    fn lock_state(&self) -> napi::Result<std::sync::MutexGuard<'_, CacheState>> {
+        self.state
+            .lock()
+            .map_err(|_| napi::Error::from_reason("Lock poisoned"))
+    }
 
-    /// Store a key-value pair.
-    #[napi(js_name = "kvPut")]
-    pub async fn kv_put(&self, key: String, value: serde_json::Value) -> napi::Result<i64> {
-        let inner = self.inner.clone();
-        let v = js_to_value_checked(value, 0)?;
-        tokio::task::spawn_blocking(move || {
-            let guard = lock_inner(&inner)?;
-            guard.kv_put(&key, v).map(|n| n as i64).map_err(to_napi_err)
-        })
-        .await
-        .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
+    fn key(db: &RustStrata, key: &str) -> CacheKey {
+        (
+            db.current_branch().to_string(),
+            db.current_space().to_string(),
+            key.to_string(),
+        )
     }
 
-    /// Get a value by key. Optionally pass `asOf` (microseconds since epoch)
-    /// to read as of a past timestamp.
-    #[napi(js_name = "kvGet")]
-    pub async fn kv_get(&self, key: String, as_of: Option<i64>) -> napi::Result<serde_json::Value> {
-        let inner = self.inner.clone();
-        let as_of_u64 = as_of.map(|t| t as u64);
-        tokio::task::spawn_blocking(move || {
-            let guard = lock_inner(&inner)?;
-            match guard.kv_get_as_of(&key, as_of_u64).map_err(to_napi_err)? {
-                Some(v) => Ok(value_to_js(v)),
-                None => Ok(serde_json::Value::Null),
+    /// The latest value of `key` in `db`'s current branch and space, read
+    /// through the cache.
+    fn kv_get(&self, db: &RustStrata, key: &str) -> napi::Result<Option<Value>> {
+        if self.capacity == 0 {
+            return db.kv_get_as_of(key, None).map_err(to_napi_err);
+        }
+        let version = db.database().current_version();
+        let cache_key = Self::key(db, key);
+        {
+            let mut guard = self.lock_state()?;
+            let state = &mut *guard;
+            state.at_version(version);
+            state.tick += 1;
+            let tick = state.tick;
+            if let Some((used, value, _)) = state.entries.get_mut(&cache_key) {
+                let (last, value) = (std::mem::replace(used, tick), value.clone());
+                state.order.remove(&last);
+                state.order.insert(tick, cache_key);
+                self.hits.fetch_add(1, Ordering::Relaxed);
+                return Ok(value);
             }
-        })
-        .await
-        .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
+        }
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        let value = db.kv_get_as_of(key, None).map_err(to_napi_err)?;
+        let size = key.len() as u64 + value.clone().map_or(0, value_size);
+        if size > self.capacity {
+            return Ok(value);
+        }
+        let mut state = self.lock_state()?;
+        // A write since the read makes the value stale, and it empties
+        // the cache on the next lookup; don't put it back.
+        if state.version != version || version != db.database().current_version() {
+            return Ok(value);
+        }
+        state.tick += 1;
+        let tick = state.tick;
+        if let Some((used, _, old)) = state.entries.remove(&cache_key) {
+            state.order.remove(&used);
+            state.bytes -= old;
+        }
+        while state.bytes + size > self.capacity {
+            let Some((_, evicted)) = state.order.pop_first() else {
+                break;
+            };
+            if let Some((_, _, old)) = state.entries.remove(&evicted) {
+                state.bytes -= old;
+            }
+        }
+        state.bytes += size;
+        state.order.insert(tick, cache_key.clone());
+        state.entries.insert(cache_key, (tick, value.clone(), size));
+        Ok(value)
+    }
+
+    fn stats(&self) -> napi::Result<serde_json::Value> {
+        let state = self.lock_state()?;
+        let hits = self.hits.load(Ordering::Relaxed);
+        let misses = self.misses.load(Ordering::Relaxed);
+        let lookups = hits + misses;
+        Ok(serde_json::json!({
+            "capacityBytes": self.capacity,
+            "residentBytes": state.bytes,
+            "entries": state.entries.len(),
+            "hits": hits,
+            "misses": misses,
+            "hitRate": if lookups == 0 { 0.0 } else { hits as f64 / lookups as f64 },
+        }))
     }
+}
 
-    /// Delete a key.
-    #[napi(js_name = "kvDelete")]
-    pub async fn kv_delete(&self, key: String) -> napi::Result<bool> {
-        let inner = self.inner.clone();
-        tokio::task::spawn_blocking(move || {
-            let guard = lock_inner(&inner)?;
-            guard.kv_delete(&key).map_err(to_napi_err)
-        })
-        .await
-        .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
+/// Directory under the database path holding the engine's WAL segments.
+const WAL_DIR: &str = "wal";
+
+/// Total size and number of the WAL segment files of the database at
+/// `path`. A segment removed during the walk is not counted.
+fn wal_segments(path: &str) -> std::io::Result<(u64, u64)> {
+    let gone = |e: &std::io::Error| e.kind() == std::io::ErrorKind::NotFound;
+    let entries = match std::fs::read_dir(std::path::Path::new(path).join(WAL_DIR)) {
+        Err(e) if gone(&e) => return Ok((0, 0)),
+        entries => entries?,
+    };
+    let (mut bytes, mut segments) = (0, 0);
+    for entry in entries {
+        let meta = match entry.and_then(|entry| std::fs::symlink_metadata(entry.path())) {
+            Err(e) if gone(&e) => continue,
+            meta => meta?,
+        };
+        if meta.is_file() {
+            bytes += meta.len();
+            segments += 1;
+        }
     }
+    Ok((bytes, segments))
+}
 
-    /// List keys with optional prefix filter. Optionally pass `asOf` for time-travel.
-    #[napi(js_name = "kvList")]
-    pub async fn kv_list(
-        &self,
-        prefix: Option<String>,
-        as_of: Option<i64>,
-    ) -> napi::Result<Vec<String>> {
-        let inner = self.inner.clone();
-        let as_of_u64 = as_of.map(|t| t as u64);
-        tokio::task::spawn_blocking(move || {
-            let guard = lock_inner(&inner)?;
-            guard
-                .kv_list_as_of(prefix.as_deref(), None, None, as_of_u64)
-                .map_err(to_napi_err)
-        })
-        .await
-        .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
+/// Bring `last_flush` up to date with syncs the engine made on its own.
+/// The engine counts its syncs but doesn't say when they happened, so one
+/// counts from when it is first noticed here.
+fn note_engine_syncs(last_flush: &AtomicU64, syncs_seen: &AtomicU64, sync_calls: u64) {
+    if syncs_seen.fetch_max(sync_calls, Ordering::Relaxed) < sync_calls {
+        last_flush.fetch_max(now_micros(), Ordering::Relaxed);
     }
+}
 
-    /// Get version history for a key.
-    #[napi(js_name = "kvHistory")]
-    pub async fn kv_history(&self, key: String) -> napi::Result<serde_json::Value> {
-        let inner = self.inner.clone();
-        tokio::task::spawn_blocking(move || {
-            let guard = lock_inner(&inner)?;
-            match guard.kv_getv(&key).map_err(to_napi_err)? {
-                Some(versions) => {
-                    let arr: Vec<serde_json::Value> =
-                        versions.into_iter().map(versioned_to_js).collect();
-                    Ok(serde_json::Value::Array(arr))
+/// Advisory lock file taken by the process that opens a path for writing.
+const WRITER_LOCK_FILE: &str = "writer.lock";
+
+/// How often a blocked open retries the writer lock.
+const LOCK_RETRY_INTERVAL: std::time::Duration = std::time::Duration::from_millis(25);
+
+/// Take the writer lock on the data directory at `path`, creating the
+/// directory if needed, and record this process as its holder. While
+/// another process holds it, retries for up to `wait`, then returns `None`;
+/// `writer_lock_busy` is the error to report.
+fn acquire_writer_lock(
+    path: &str,
+    wait: std::time::Duration,
+) -> napi::Result<Option<std::fs::File>> {
+    std::fs::create_dir_all(path).map_err(io_err)?;
+    let lock_path = std::path::Path::new(path).join(WRITER_LOCK_FILE);
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .read(true)
+        .write(true)
+        .open(&lock_path)
+        .map_err(io_err)?;
+    let deadline = std::time::Instant::now() + wait;
+    let mut waiting = false;
+    loop {
+        match file.try_lock() {
+            Ok(()) => break,
+            Err(std::fs::TryLockError::WouldBlock) => {
+                let now = std::time::Instant::now();
+                if now >= deadline {
+                    return Ok(None);
                 }
-                None => Ok(serde_json::Value::Null),
+                if !waiting {
+                    waiting = true;
+                    log_lock_wait(path, wait);
+                }
+                std::thread::sleep(LOCK_RETRY_INTERVAL.min(deadline - now));
             }
-        })
-        .await
-        .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
+            Err(std::fs::TryLockError::Error(e)) => return Err(io_err(e)),
+        }
     }
+    let holder = serde_json::json!({ "pid": std::process::id(), "since": now_micros() });
+    file.set_len(0).map_err(io_err)?;
+    file.write_all(holder.to_string().as_bytes())
+        .map_err(io_err)?;
+    Ok(Some(file))
+}
 
-    // =========================================================================
-    // State Cell
-    // =========================================================================
+fn log_lock_wait(path: &str, wait: std::time::Duration) {
+    log(
+        "info",
+        "waiting for the writer lock",
+        serde_json::json!({
+            "path": path,
+            "lockWaitMs": wait.as_millis() as u64,
+        }),
+    );
+}
 
-    /// Set a state cell value.
-    #[napi(js_name = "stateSet")]
-    pub async fn state_set(&self, cell: String, value: serde_json::Value) -> napi::Result<i64> {
-        let inner = self.inner.clone();
-        let v = js_to_value_checked(value, 0)?;
-        tokio::task::spawn_blocking(move || {
-            let guard = lock_inner(&inner)?;
-            guard
-                .state_set(&cell, v)
-                .map(|n| n as i64)
-                .map_err(to_napi_err)
-        })
-        .await
-        .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
+/// The `[STATE]` error for a writer lock another process still holds,
+/// naming the holder and the way out.
+fn writer_lock_busy(path: &str) -> napi::Error {
+    let lock_path = std::path::Path::new(path).join(WRITER_LOCK_FILE);
+    // Unreadable where the lock covers reads too (Windows).
+    let holder = std::fs::read_to_string(&lock_path)
+        .ok()
+        .and_then(|s| serde_json::from_str::<serde_json::Value>(&s).ok())
+        .and_then(|h| Some((h["pid"].as_u64()?, h["since"].as_u64()?)))
+        .map(|(pid, since)| format!(" (pid {} since {})", pid, since))
+        .unwrap_or_default();
+    napi::Error::from_reason(format!(
+        "[STATE] {} is open for writing in another process{}; open it with \
+         follower: true to read alongside it",
+        path, holder
+    ))
+}
+
+/// The database open at one path, if any. Opens and the last close of a
+/// path hold its slot's lock, so two opens of a new path can't both create
+/// it, while the registry lock is only held to find the slot. The entry is
+/// weak, so a database whose handles were all collected without `close()`
+/// is released.
+type RegistrySlot = Arc<Mutex<Weak<Registered>>>;
+
+/// Databases open in this process, by canonical path.
+fn registry() -> &'static Mutex<HashMap<PathBuf, RegistrySlot>> {
+    static REGISTRY: OnceLock<Mutex<HashMap<PathBuf, RegistrySlot>>> = OnceLock::new();
+    REGISTRY.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// The slot for `key`, added if there is none. Drops slots nobody is
+/// opening or holding a database in.
+fn registry_slot(key: &std::path::Path) -> RegistrySlot {
+    let mut registry = registry().lock().unwrap_or_else(|e| e.into_inner());
+    registry.retain(|_, slot| {
+        Arc::strong_count(slot) > 1 || !matches!(slot.try_lock(), Ok(db) if db.strong_count() == 0)
+    });
+    registry.entry(key.to_path_buf()).or_default().clone()
+}
+
+fn lock_slot(slot: &RegistrySlot) -> std::sync::MutexGuard<'_, Weak<Registered>> {
+    slot.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Databases handed out by `share()`, by token id. Entries are weak: a
+/// token stops working once every handle on its database has closed.
+fn shares() -> &'static Mutex<HashMap<u64, Weak<Registered>>> {
+    static SHARES: OnceLock<Mutex<HashMap<u64, Weak<Registered>>>> = OnceLock::new();
+    SHARES.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+const SHARE_TOKEN_PREFIX: &str = "strata-share:";
+
+/// A new handle on a registered database, starting on the default branch
+/// and space.
+fn new_handle(
+    registered: Arc<Registered>,
+    pool: WorkerPool,
+    json_limits: JsonLimits,
+    value_mode: ValueMode,
+) -> napi::Result<Strata> {
+    let access_mode = if registered.follower {
+        "follower"
+    } else if registered.read_only {
+        "readOnly"
+    } else {
+        "readWrite"
+    };
+    let view =
+        RustStrata::from_database(registered.engine.database().clone()).map_err(to_napi_err)?;
+    let inner = HandleDb {
+        db: RwLock::new(Some(view)),
+        cut: registered.cut.clone(),
+    };
+    Ok(Strata {
+        path: registered
+            .key
+            .as_ref()
+            .map(|key| key.to_string_lossy().into_owned()),
+        access_mode,
+        inner: Arc::new(inner),
+        session: Arc::new(Mutex::new(None)),
+        txn_scope: Arc::new(Mutex::new(TxnScope::default())),
+        pool: Arc::new(pool),
+        last_flush: registered.last_flush.clone(),
+        syncs_seen: registered.syncs_seen.clone(),
+        counter_gate: registered.counter_gate.clone(),
+        cache: registered.cache.clone(),
+        shared_id: registered.id,
+        json_limits,
+        value_mode,
+        registration: Arc::new(Mutex::new(Some(registered))),
+    })
+}
+
+/// Give up a handle's share of its database. Returns the registration if it
+/// was the last share, in which case the caller releases the database before
+/// dropping it (and with it the writer lock).
+fn release_registration(
+    registration: &Mutex<Option<Arc<Registered>>>,
+) -> napi::Result<Option<Arc<Registered>>> {
+    let taken = registration
+        .lock()
+        .map_err(|_| napi::Error::from_reason("Lock poisoned"))?
+        .take();
+    let Some(registered) = taken else {
+        return Ok(None);
+    };
+    // Opens share a database under its slot's lock, so none can take a
+    // share between the count and the reset.
+    let slot = registered.key.as_deref().map(registry_slot);
+    let mut entry = slot.as_ref().map(lock_slot);
+    if Arc::strong_count(&registered) > 1 {
+        return Ok(None);
     }
+    if let Some(entry) = entry.as_mut() {
+        **entry = Weak::new();
+    }
+    Ok(Some(registered))
+}
 
-    /// Get a state cell value. Optionally pass `asOf` for time-travel.
-    #[napi(js_name = "stateGet")]
-    pub async fn state_get(
-        &self,
-        cell: String,
-        as_of: Option<i64>,
-    ) -> napi::Result<serde_json::Value> {
-        let inner = self.inner.clone();
-        let as_of_u64 = as_of.map(|t| t as u64);
-        tokio::task::spawn_blocking(move || {
-            let guard = lock_inner(&inner)?;
-            match guard.state_get_as_of(&cell, as_of_u64).map_err(to_napi_err)? {
-                Some(v) => Ok(value_to_js(v)),
-                None => Ok(serde_json::Value::Null),
-            }
-        })
-        .await
-        .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
+/// A handle collected without `close()` gives up its share like `close()`
+/// would; if it held the last one, the database is flushed and dropped so
+/// its file locks are released.
+impl Drop for Strata {
+    fn drop(&mut self) {
+        // Already closed if the view is gone.
+        let Some(view) = self.inner.db.write().ok().and_then(|mut db| db.take()) else {
+            return;
+        };
+        if let Ok(mut session) = self.session.lock() {
+            *session = None;
+        }
+        drop(view);
+        let Ok(Some(last)) = release_registration(&self.registration) else {
+            return;
+        };
+        let _ = last.engine.flush();
+        drop(last);
     }
+}
 
-    /// Initialize a state cell if it doesn't exist.
-    #[napi(js_name = "stateInit")]
-    pub async fn state_init(&self, cell: String, value: serde_json::Value) -> napi::Result<i64> {
+/// Open the database at `path` and wrap it in a handle. If the path is
+/// already open in this process, the handle shares that database.
+fn open_strata(path: &str, options: Option<&JsOpenOptions>) -> napi::Result<Strata> {
+    try_open_strata(path, options, lock_wait(options))?.ok_or_else(|| writer_lock_busy(path))
+}
+
+/// `lockWaitMs` from open options.
+fn lock_wait(options: Option<&JsOpenOptions>) -> std::time::Duration {
+    let wait = options.and_then(|o| o.lock_wait_ms).unwrap_or(0);
+    std::time::Duration::from_millis(wait as u64)
+}
+
+/// Open a database, waiting up to `wait` for the writer lock if another
+/// process holds it. Returns `None` if it still does.
+fn try_open_strata(
+    path: &str,
+    options: Option<&JsOpenOptions>,
+    wait: std::time::Duration,
+) -> napi::Result<Option<Strata>> {
+    let auto_embed = options.and_then(|o| o.auto_embed).unwrap_or(false);
+    let read_only = options.and_then(|o| o.read_only).unwrap_or(false);
+    let follower = options.and_then(|o| o.follower).unwrap_or(false);
+    check_open_mode(path, options)?;
+    let pool = WorkerPool::new(options)?;
+    let json_limits = open_json_limits(options)?;
+    let value_mode = ValueMode::from_options(options);
+
+    #[cfg(feature = "embed")]
+    if auto_embed {
+        if let Err(e) = strata_intelligence::embed::download::ensure_model() {
+            log(
+                "warn",
+                &format!("failed to download model files: {}", e),
+                serde_json::json!({ "error": e.to_string() }),
+            );
+        }
+    }
+
+    let mut opts = OpenOptions::new();
+    if read_only || follower {
+        opts = opts.access_mode(AccessMode::ReadOnly);
+    }
+    if follower {
+        opts = opts.follower(true);
+    }
+
+    // Writers create the directory up front so the path has the same key
+    // before and after the open.
+    let writer = !(read_only || follower);
+    if writer {
+        std::fs::create_dir_all(path).map_err(io_err)?;
+    }
+    let key = std::fs::canonicalize(path)
+        .or_else(|_| std::path::absolute(path))
+        .map_err(io_err)?;
+    // Held across the open so two opens of a new path can't both create it;
+    // opens of other paths don't wait on it.
+    let slot = registry_slot(&key);
+    let mut entry = lock_slot(&slot);
+    if let Some(registered) = entry.upgrade() {
+        drop(entry);
+        if registered.read_only != read_only || registered.follower != follower {
+            return Err(napi::Error::from_reason(format!(
+                "[STATE] {} is already open in this process with a different access mode",
+                path
+            )));
+        }
+        let handle = new_handle(registered, pool, json_limits, value_mode)?;
+        if auto_embed {
+            lock_inner(&handle.inner)?
+                .set_auto_embed(true)
+                .map_err(to_napi_err)?;
+        }
+        return Ok(Some(handle));
+    }
+
+    // Readers don't take the lock, so they can open alongside a writer.
+    let writer_lock = if writer {
+        match acquire_writer_lock(path, wait)? {
+            Some(lock) => Some(lock),
+            None => return Ok(None),
+        }
+    } else {
+        None
+    };
+    let raw = RustStrata::open_with(path, opts).map_err(to_napi_err)?;
+    // Syncs made while opening don't count as a flush.
+    let syncs_at_open = raw.durability_counters().map_or(0, |c| c.sync_calls);
+    let registered = Arc::new(Registered {
+        id: next_registered_id(),
+        engine: raw,
+        cut: Arc::new(RwLock::new(())),
+        key: Some(key.clone()),
+        read_only,
+        follower,
+        _writer_lock: writer_lock,
+        last_flush: Arc::new(AtomicU64::new(0)),
+        syncs_seen: Arc::new(AtomicU64::new(syncs_at_open)),
+        counter_gate: Arc::new(Mutex::new(())),
+        cache: Arc::new(ReadCache::new(options.and_then(|o| o.cache_mb))),
+    });
+    *entry = Arc::downgrade(&registered);
+    drop(entry);
+    let handle = new_handle(registered, pool, json_limits, value_mode)?;
+    if auto_embed {
+        lock_inner(&handle.inner)?
+            .set_auto_embed(true)
+            .map_err(to_napi_err)?;
+    }
+    log(
+        "info",
+        "opened database",
+        serde_json::json!({ "path": key.to_string_lossy(), "accessMode": handle.access_mode }),
+    );
+    Ok(Some(handle))
+}
+
+/// The handle-wide `jsonLimits` from open options.
+fn open_json_limits(options: Option<&JsOpenOptions>) -> napi::Result<JsonLimits> {
+    JsonLimits::default().with(options.and_then(|o| o.json_limits.as_ref()))
+}
+
+/// Total size of the files under `path`. Symlinks count as themselves and
+/// are not followed; a file or directory removed during the walk (by a
+/// compaction, say) counts as zero.
+fn dir_size(path: &std::path::Path) -> std::io::Result<u64> {
+    let gone = |e: &std::io::Error| e.kind() == std::io::ErrorKind::NotFound;
+    let entries = match std::fs::read_dir(path) {
+        Err(e) if gone(&e) => return Ok(0),
+        entries => entries?,
+    };
+    let mut total = 0;
+    for entry in entries {
+        let entry = match entry {
+            Err(e) if gone(&e) => continue,
+            entry => entry?,
+        };
+        let meta = match std::fs::symlink_metadata(entry.path()) {
+            Err(e) if gone(&e) => continue,
+            meta => meta?,
+        };
+        total += if meta.is_dir() {
+            dir_size(&entry.path())?
+        } else {
+            meta.len()
+        };
+    }
+    Ok(total)
+}
+
+/// Enforce `createIfMissing` and `errorIfExists`. A database exists at
+/// `path` if it is a directory with anything in it besides the writer lock.
+fn check_open_mode(path: &str, options: Option<&JsOpenOptions>) -> napi::Result<()> {
+    let create_if_missing = options.and_then(|o| o.create_if_missing).unwrap_or(true);
+    let error_if_exists = options.and_then(|o| o.error_if_exists).unwrap_or(false);
+    if !create_if_missing && error_if_exists {
+        return Err(napi::Error::from_reason(
+            "[VALIDATION] createIfMissing: false and errorIfExists: true exclude each other",
+        ));
+    }
+    let exists = match std::fs::read_dir(path) {
+        Ok(entries) => entries
+            .filter_map(Result::ok)
+            .any(|entry| entry.file_name() != WRITER_LOCK_FILE),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => false,
+        Err(e) => return Err(io_err(e)),
+    };
+    if exists && error_if_exists {
+        return Err(napi::Error::from_reason(format!(
+            "[STATE] A database already exists at {}",
+            path
+        )));
+    }
+    if !exists && !create_if_missing {
+        return Err(napi::Error::from_reason(format!(
+            "[NOT_FOUND] No database at {}",
+            path
+        )));
+    }
+    Ok(())
+}
+
+/// Size of the pool shared by handles opened without `workerThreads` or
+/// `maxConcurrentOps`: twice the number of cores, and at least 4, since
+/// calls spend much of their time waiting on disk.
+fn shared_pool_size() -> u32 {
+    let cores = std::thread::available_parallelism().map_or(1, |n| n.get() as u32);
+    cores.saturating_mul(2).max(4)
+}
+
+/// The runtime whose blocking pool is shared by handles without one of
+/// their own, created on first use.
+fn shared_runtime() -> napi::Result<&'static tokio::runtime::Runtime> {
+    static SHARED: OnceLock<Option<tokio::runtime::Runtime>> = OnceLock::new();
+    SHARED
+        .get_or_init(|| {
+            tokio::runtime::Builder::new_current_thread()
+                .max_blocking_threads(shared_pool_size() as usize)
+                .thread_name("strata-worker")
+                .build()
+                .ok()
+        })
+        .as_ref()
+        .ok_or_else(|| napi::Error::from_reason("[IO] Failed to start the shared worker pool"))
+}
+
+/// Where a handle's blocking work runs, with counters for `poolStats()`.
+/// Only a runtime's blocking pool is used, so current-thread runtimes are
+/// enough.
+struct WorkerPool {
+    /// A dedicated runtime whose blocking pool serves only this handle, or
+    /// `None` to use `shared_runtime()`.
+    runtime: Option<tokio::runtime::Runtime>,
+    /// Spawns onto `runtime`, or onto the shared runtime without one.
+    spawner: tokio::runtime::Handle,
+    size: u32,
+    /// Calls allowed to wait for a thread before new ones are rejected.
+    max_queued: Option<u32>,
+    queued: AtomicU64,
+    active: AtomicU64,
+    completed: AtomicU64,
+    rejected: AtomicU64,
+}
+
+impl WorkerPool {
+    fn new(options: Option<&JsOpenOptions>) -> napi::Result<Self> {
+        let worker_threads = options.and_then(|o| o.worker_threads);
+        let max_concurrent = options.and_then(|o| o.max_concurrent_ops);
+        let max_queued = options.and_then(|o| o.max_queued_ops);
+        if worker_threads == Some(0) {
+            return Err(napi::Error::from_reason(
+                "[VALIDATION] workerThreads must be greater than 0",
+            ));
+        }
+        if max_concurrent == Some(0) {
+            return Err(napi::Error::from_reason(
+                "[VALIDATION] maxConcurrentOps must be greater than 0",
+            ));
+        }
+        if max_queued.is_some() && max_concurrent.is_none() {
+            return Err(napi::Error::from_reason(
+                "[VALIDATION] maxQueuedOps requires maxConcurrentOps",
+            ));
+        }
+        let threads = match (worker_threads, max_concurrent) {
+            (Some(w), Some(m)) => Some(w.min(m)),
+            (w, m) => w.or(m),
+        };
+        let runtime = match threads {
+            Some(n) => Some(
+                tokio::runtime::Builder::new_current_thread()
+                    .max_blocking_threads(n as usize)
+                    .thread_name("strata-worker")
+                    .build()
+                    .map_err(io_err)?,
+            ),
+            None => None,
+        };
+        let spawner = match &runtime {
+            Some(rt) => rt.handle().clone(),
+            None => shared_runtime()?.handle().clone(),
+        };
+        Ok(Self {
+            runtime,
+            spawner,
+            size: threads.unwrap_or_else(shared_pool_size),
+            max_queued,
+            queued: AtomicU64::new(0),
+            active: AtomicU64::new(0),
+            completed: AtomicU64::new(0),
+            rejected: AtomicU64::new(0),
+        })
+    }
+
+    /// Count a new call as queued, or reject it if the queue is full.
+    fn admit(&self) -> Result<(), SpawnError> {
+        let queued = self.queued.fetch_add(1, Ordering::Relaxed);
+        if let Some(cap) = self.max_queued {
+            let in_flight = queued + self.active.load(Ordering::Relaxed);
+            if in_flight >= self.size as u64 + cap as u64 {
+                self.queued.fetch_sub(1, Ordering::Relaxed);
+                self.rejected.fetch_add(1, Ordering::Relaxed);
+                return Err(SpawnError::Exhausted {
+                    size: self.size,
+                    max_queued: cap,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    fn stats(&self) -> serde_json::Value {
+        let active = self.active.load(Ordering::Relaxed);
+        serde_json::json!({
+            "dedicated": self.runtime.is_some(),
+            "size": self.size,
+            "maxQueued": self.max_queued,
+            "active": active,
+            "queued": self.queued.load(Ordering::Relaxed),
+            "completed": self.completed.load(Ordering::Relaxed),
+            "rejected": self.rejected.load(Ordering::Relaxed),
+            "saturation": active as f64 / self.size as f64,
+        })
+    }
+}
+
+/// Why a call never produced a result: its worker task failed, or the pool
+/// turned it away.
+enum SpawnError {
+    Join(tokio::task::JoinError),
+    Exhausted { size: u32, max_queued: u32 },
+}
+
+impl std::fmt::Display for SpawnError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SpawnError::Join(e) => write!(f, "{}", e),
+            SpawnError::Exhausted { size, max_queued } => write!(
+                f,
+                "[RESOURCE_EXHAUSTED] Too many operations in flight (maxConcurrentOps {}, maxQueuedOps {})",
+                size, max_queued
+            ),
+        }
+    }
+}
+
+/// Run `f` on a blocking thread of `pool`, counting it as queued until it
+/// starts. Fails without running `f` if the pool's queue is full.
+fn spawn_on<F, R>(pool: &Arc<WorkerPool>, f: F) -> impl Future<Output = Result<R, SpawnError>>
+where
+    F: FnOnce() -> R + Send + 'static,
+    R: Send + 'static,
+{
+    spawn_admitted(pool, pool.admit(), f)
+}
+
+/// `spawn_on` past the queue limit, for calls that give resources back
+/// (`close()`), which must not be turned away.
+fn spawn_unlimited_on<F, R>(
+    pool: &Arc<WorkerPool>,
+    f: F,
+) -> impl Future<Output = Result<R, SpawnError>>
+where
+    F: FnOnce() -> R + Send + 'static,
+    R: Send + 'static,
+{
+    pool.queued.fetch_add(1, Ordering::Relaxed);
+    spawn_admitted(pool, Ok(()), f)
+}
+
+fn spawn_admitted<F, R>(
+    pool: &Arc<WorkerPool>,
+    admitted: Result<(), SpawnError>,
+    f: F,
+) -> impl Future<Output = Result<R, SpawnError>>
+where
+    F: FnOnce() -> R + Send + 'static,
+    R: Send + 'static,
+{
+    struct Running(Arc<WorkerPool>);
+    impl Drop for Running {
+        fn drop(&mut self) {
+            self.0.active.fetch_sub(1, Ordering::Relaxed);
+            self.0.completed.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    let counted = Arc::clone(pool);
+    let task = move || {
+        counted.queued.fetch_sub(1, Ordering::Relaxed);
+        counted.active.fetch_add(1, Ordering::Relaxed);
+        let _running = Running(counted);
+        f()
+    };
+    let handle = admitted.map(|()| pool.spawner.spawn_blocking(task));
+    async move { handle?.await.map_err(SpawnError::Join) }
+}
+
+impl Drop for WorkerPool {
+    fn drop(&mut self) {
+        // Dropping a runtime blocks on its threads, which panics inside an
+        // async context; let in-flight work finish on its own instead.
+        if let Some(rt) = self.runtime.take() {
+            rt.shutdown_background();
+        }
+    }
+}
+
+/// Target branch and space of the active transaction, and its writes.
+#[derive(Default)]
+struct TxnScope {
+    active: bool,
+    branch: Option<String>,
+    space: Option<String>,
+    /// `{ command, key, branch, space }` for each write, in order.
+    writes: Vec<serde_json::Value>,
+    /// Savepoint names and the number of writes made before each.
+    savepoints: Vec<(String, usize)>,
+}
+
+/// Write commands recorded by a transaction, with their key argument.
+const TXN_WRITE_COMMANDS: &[(&str, &str)] = &[
+    ("KvPut", "key"),
+    ("KvDelete", "key"),
+    ("StateSet", "cell"),
+    ("StateInit", "cell"),
+    ("StateCas", "cell"),
+    ("StateDelete", "cell"),
+    ("JsonSet", "key"),
+    ("JsonDelete", "key"),
+    ("EventAppend", "event_type"),
+    ("VectorUpsert", "key"),
+    ("VectorDelete", "key"),
+];
+
+#[napi]
+impl Strata {
+    // =========================================================================
+    // Factory methods
+    // =========================================================================
+
+    /// Open a database at the given path.
+    ///
+    /// Runs on the calling thread, including WAL recovery and any model
+    /// download; use `openAsync` to keep the event loop free.
+    #[napi(factory)]
+    pub fn open(path: String, options: Option<JsOpenOptions>) -> napi::Result<Self> {
+        open_strata(&path, options.as_ref())
+    }
+
+    /// Open a database at the given path on the blocking pool, resolving
+    /// to the handle once recovery (and any model download) is done.
+    ///
+    /// While another process holds the writer lock, the retries for
+    /// `lockWaitMs` wait on a timer rather than on a blocking thread.
+    #[napi(js_name = "openAsync")]
+    pub async fn open_async(path: String, options: Option<JsOpenOptions>) -> napi::Result<Strata> {
+        let wait = lock_wait(options.as_ref());
+        let deadline = std::time::Instant::now() + wait;
+        let (path, options) = (Arc::new(path), Arc::new(options));
+        let mut waiting = false;
+        loop {
+            let (p, o) = (path.clone(), options.clone());
+            let opened = tokio::task::spawn_blocking(move || {
+                try_open_strata(&p, o.as_ref().as_ref(), std::time::Duration::ZERO)
+            })
+            .await
+            .map_err(|e| napi::Error::from_reason(format!("{}", e)))??;
+            if let Some(handle) = opened {
+                return Ok(handle);
+            }
+            let now = std::time::Instant::now();
+            if now >= deadline {
+                return Err(writer_lock_busy(&path));
+            }
+            if !waiting {
+                waiting = true;
+                log_lock_wait(&path, wait);
+            }
+            tokio::time::sleep(LOCK_RETRY_INTERVAL.min(deadline - now)).await;
+        }
+    }
+
+    /// Create an in-memory database (no persistence).
+    #[napi(factory)]
+    pub fn cache() -> napi::Result<Self> {
+        let raw = RustStrata::cache().map_err(to_napi_err)?;
+        let registered = Arc::new(Registered {
+            id: next_registered_id(),
+            engine: raw,
+            cut: Arc::new(RwLock::new(())),
+            key: None,
+            read_only: false,
+            follower: false,
+            _writer_lock: None,
+            last_flush: Arc::new(AtomicU64::new(0)),
+            syncs_seen: Arc::new(AtomicU64::new(0)),
+            counter_gate: Arc::new(Mutex::new(())),
+            cache: Arc::new(ReadCache::new(None)),
+        });
+        new_handle(
+            registered,
+            WorkerPool::new(None)?,
+            JsonLimits::default(),
+            ValueMode::default(),
+        )
+    }
+
+    /// Attach to a database shared by `share()`, typically from another
+    /// worker thread. `options` configures this handle's worker pool.
+    #[napi(factory, js_name = "fromShared")]
+    pub fn from_shared(token: String, options: Option<JsOpenOptions>) -> napi::Result<Self> {
+        let id = token
+            .strip_prefix(SHARE_TOKEN_PREFIX)
+            .and_then(|id| id.parse::<u64>().ok())
+            .ok_or_else(|| {
+                napi::Error::from_reason(format!("[VALIDATION] Invalid share token '{}'", token))
+            })?;
+        let registered = shares()
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(&id)
+            .and_then(Weak::upgrade)
+            .ok_or_else(|| {
+                napi::Error::from_reason(format!(
+                    "[NOT_FOUND] Shared database '{}' has been closed",
+                    token
+                ))
+            })?;
+        new_handle(
+            registered,
+            WorkerPool::new(options.as_ref())?,
+            open_json_limits(options.as_ref())?,
+            ValueMode::from_options(options.as_ref()),
+        )
+    }
+
+    /// A token that `Strata.fromShared()` turns into another handle on this
+    /// database. It is a plain string, so it can be sent to worker threads
+    /// with `postMessage`.
+    #[napi]
+    pub fn share(&self) -> napi::Result<String> {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+        ensure_open(&self.inner)?;
+        let registered = self
+            .registration
+            .lock()
+            .map_err(|_| napi::Error::from_reason("Lock poisoned"))?
+            .clone()
+            .ok_or_else(closed_err)?;
+        let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+        let mut shares = shares().lock().unwrap_or_else(|e| e.into_inner());
+        shares.retain(|_, shared| shared.strong_count() > 0);
+        shares.insert(id, Arc::downgrade(&registered));
+        Ok(format!("{}{}", SHARE_TOKEN_PREFIX, id))
+    }
+
+    /// Worker pool usage: `{ dedicated, size, active, queued, completed,
+    /// saturation }`, where `saturation` is `active / size`.
+    ///
+    /// Without `workerThreads` or `maxConcurrentOps` the handle uses the
+    /// pool shared by every such handle in the process, whose `size` is
+    /// twice the number of cores (at least 4); `active` and `queued` count
+    /// only this handle's calls.
+    #[napi(js_name = "poolStats")]
+    pub fn pool_stats(&self) -> napi::Result<serde_json::Value> {
+        ensure_open(&self.inner)?;
+        Ok(self.pool.stats())
+    }
+
+    /// Read cache usage: `{ capacityBytes, residentBytes, entries, hits,
+    /// misses, hitRate }`, shared by every handle on the database. The
+    /// cache is sized by the `cacheMB` open option; without it,
+    /// `capacityBytes` is 0 and nothing is counted.
+    #[napi(js_name = "cacheStats")]
+    pub fn cache_stats(&self) -> napi::Result<serde_json::Value> {
+        ensure_open(&self.inner)?;
+        self.cache.stats()
+    }
+
+    /// Whether this handle is open (not closed, and its database not
+    /// dropped).
+    #[napi(js_name = "isOpen")]
+    pub fn is_open(&self) -> bool {
+        ensure_open(&self.inner).is_ok()
+    }
+
+    /// Id of the database this handle shares with the other handles opened
+    /// on it or made from its `share()` token, unique in the process.
+    #[napi(js_name = "sharedId")]
+    pub fn shared_id(&self) -> i64 {
+        self.shared_id as i64
+    }
+
+    /// Handle health: `{ open, path, accessMode, branch, space,
+    /// inTransaction, inFlight }`. `branch` and `space` are `null` once
+    /// closed, and while an operation holds the database exclusively.
+    #[napi]
+    pub fn status(&self) -> napi::Result<serde_json::Value> {
+        let open = self.is_open();
+        let (branch, space) = match self.inner.db.try_read() {
+            Ok(guard) if open => match guard.as_ref() {
+                Some(db) => (
+                    Some(db.current_branch().to_string()),
+                    Some(db.current_space().to_string()),
+                ),
+                None => (None, None),
+            },
+            _ => (None, None),
+        };
+        let in_transaction = lock_scope(&self.txn_scope)?.active;
+        let in_flight =
+            self.pool.active.load(Ordering::Relaxed) + self.pool.queued.load(Ordering::Relaxed);
+        Ok(serde_json::json!({
+            "open": open,
+            "path": self.path,
+            "accessMode": self.access_mode,
+            "branch": branch,
+            "space": space,
+            "inTransaction": in_transaction,
+            "inFlight": in_flight,
+        }))
+    }
+
+    // =========================================================================
+    // KV Store
+    // =========================================================================
+
+    /// Store a key-value pair.
+    ///
+    /// With `annotation`, the write's author, message, and request id are
+    /// recorded for its version, and returned with it by `kvHistory`,
+    /// `kvTimeline`, and `diffBranches`.
+    #[napi(js_name = "kvPut")]
+    pub async fn kv_put(
+        &self,
+        key: String,
+        #[napi(ts_arg_type = "any")] value: JsValue,
+        json_limits: Option<JsJsonLimits>,
+        annotation: Option<JsWriteAnnotation>,
+    ) -> napi::Result<i64> {
         let inner = self.inner.clone();
-        let v = js_to_value_checked(value, 0)?;
-        tokio::task::spawn_blocking(move || {
-            let guard = lock_inner(&inner)?;
-            guard
-                .state_init(&cell, v)
-                .map(|n| n as i64)
-                .map_err(to_napi_err)
+        let limits = self.json_limits.with(json_limits.as_ref())?;
+        limits.check_exact(&value)?;
+        let v = value.0;
+        limits.check(&v)?;
+        let note = annotation.map(annotation_fields).filter(|n| !n.is_empty());
+        check_user_key(&key)?;
+        spawn_on(&self.pool, move || {
+            let guard = lock_inner(&inner)?;
+            ensure_writable(&guard)?;
+            let version = match note {
+                Some(note) => kv_put_annotated(&guard, &key, v, note)?,
+                None => guard.kv_put(&key, v).map_err(to_napi_err)?,
+            };
+            Ok(version as i64)
         })
         .await
         .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
     }
 
-    /// Compare-and-swap update based on version.
-    #[napi(js_name = "stateCas")]
-    pub async fn state_cas(
+    /// Get a value by key. Optionally pass `asOf` (microseconds since epoch,
+    /// or a tag name) to read as of a past timestamp, or `atVersion` to read
+    /// the key as it was at that write version.
+    #[napi(js_name = "kvGet", ts_return_type = "Promise<any>")]
+    pub async fn kv_get(
         &self,
-        cell: String,
-        new_value: serde_json::Value,
-        expected_version: Option<i64>,
-    ) -> napi::Result<Option<i64>> {
+        key: String,
+        as_of: Option<Either<i64, String>>,
+        at_version: Option<i64>,
+    ) -> napi::Result<JsOutput> {
+        let inner = self.inner.clone();
+        let cache = self.cache.clone();
+        let mode = self.value_mode;
+        let at_version = check_at_version(&as_of, at_version)?;
+        spawn_on(&self.pool, move || {
+            let guard = lock_inner(&inner)?;
+            let as_of_u64 = match at_version {
+                Some(v) => match version_timestamp(guard.kv_getv(&key).map_err(to_napi_err)?, v) {
+                    Some(ts) => Some(ts),
+                    None => return Ok(JsOutput(Value::Null, mode)),
+                },
+                None => resolve_as_of(&guard, as_of)?,
+            };
+            let value = match as_of_u64 {
+                Some(ts) => guard.kv_get_as_of(&key, Some(ts)).map_err(to_napi_err)?,
+                None => cache.kv_get(&guard, &key)?,
+            };
+            Ok(JsOutput(value.unwrap_or(Value::Null), mode))
+        })
+        .await
+        .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
+    }
+
+    /// Store a key from a pre-serialized MessagePack buffer, skipping
+    /// per-field JS object conversion.
+    #[napi(js_name = "kvPutRaw")]
+    pub async fn kv_put_raw(
+        &self,
+        key: String,
+        value: Buffer,
+        json_limits: Option<JsJsonLimits>,
+    ) -> napi::Result<i64> {
         let inner = self.inner.clone();
-        let v = js_to_value_checked(new_value, 0)?;
-        let exp = expected_version.map(|n| n as u64);
-        tokio::task::spawn_blocking(move || {
+        let limits = self.json_limits.with(json_limits.as_ref())?;
+        let bytes: Vec<u8> = value.into();
+        check_user_key(&key)?;
+        spawn_on(&self.pool, move || {
+            let v = msgpack_decode(&bytes)?;
+            limits.check(&v)?;
             let guard = lock_inner(&inner)?;
-            guard
-                .state_cas(&cell, exp, v)
-                .map(|opt| opt.map(|n| n as i64))
-                .map_err(to_napi_err)
+            ensure_writable(&guard)?;
+            guard.kv_put(&key, v).map(|n| n as i64).map_err(to_napi_err)
         })
         .await
         .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
     }
 
-    /// Get version history for a state cell.
-    #[napi(js_name = "stateHistory")]
-    pub async fn state_history(&self, cell: String) -> napi::Result<serde_json::Value> {
+    /// Get a key as a MessagePack buffer, or `null` if it doesn't exist.
+    /// Optionally pass `asOf` to read as of a past timestamp or tag.
+    #[napi(js_name = "kvGetRaw")]
+    pub async fn kv_get_raw(
+        &self,
+        key: String,
+        as_of: Option<Either<i64, String>>,
+    ) -> napi::Result<Option<Buffer>> {
         let inner = self.inner.clone();
-        tokio::task::spawn_blocking(move || {
+        spawn_on(&self.pool, move || {
             let guard = lock_inner(&inner)?;
-            match guard.state_getv(&cell).map_err(to_napi_err)? {
-                Some(versions) => {
-                    let arr: Vec<serde_json::Value> =
-                        versions.into_iter().map(versioned_to_js).collect();
-                    Ok(serde_json::Value::Array(arr))
-                }
-                None => Ok(serde_json::Value::Null),
-            }
+            let as_of_u64 = resolve_as_of(&guard, as_of)?;
+            let value = guard.kv_get_as_of(&key, as_of_u64).map_err(to_napi_err)?;
+            Ok(value.map(|v| {
+                let mut out = Vec::new();
+                msgpack_encode(&v, &mut out);
+                Buffer::from(out)
+            }))
         })
         .await
         .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
     }
 
-    // =========================================================================
-    // Event Log
-    // =========================================================================
-
-    /// Append an event to the log.
-    #[napi(js_name = "eventAppend")]
-    pub async fn event_append(
-        &self,
-        event_type: String,
-        payload: serde_json::Value,
-    ) -> napi::Result<i64> {
+    /// Delete a key.
+    #[napi(js_name = "kvDelete")]
+    pub async fn kv_delete(&self, key: String) -> napi::Result<bool> {
         let inner = self.inner.clone();
-        let v = js_to_value_checked(payload, 0)?;
-        tokio::task::spawn_blocking(move || {
+        check_user_key(&key)?;
+        spawn_on(&self.pool, move || {
             let guard = lock_inner(&inner)?;
-            guard
-                .event_append(&event_type, v)
-                .map(|n| n as i64)
-                .map_err(to_napi_err)
+            ensure_writable(&guard)?;
+            guard.kv_delete(&key).map_err(to_napi_err)
         })
         .await
         .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
     }
 
-    /// Get an event by sequence number. Optionally pass `asOf` for time-travel.
-    #[napi(js_name = "eventGet")]
-    pub async fn event_get(
+    /// List keys with optional prefix filter. Optionally pass `asOf` for time-travel.
+    ///
+    /// With `spaces` (a list of names, or `"*"` for all), keys from every
+    /// selected space are returned as `{ space, key }` objects.
+    #[napi(js_name = "kvList")]
+    pub async fn kv_list(
         &self,
-        sequence: i64,
-        as_of: Option<i64>,
+        prefix: Option<String>,
+        as_of: Option<Either<i64, String>>,
+        spaces: Option<Either<Vec<String>, String>>,
     ) -> napi::Result<serde_json::Value> {
         let inner = self.inner.clone();
-        let as_of_u64 = as_of.map(|t| t as u64);
-        tokio::task::spawn_blocking(move || {
+        spawn_on(&self.pool, move || {
             let guard = lock_inner(&inner)?;
-            match guard
-                .event_get_as_of(sequence as u64, as_of_u64)
-                .map_err(to_napi_err)?
-            {
-                Some(vv) => Ok(versioned_to_js(vv)),
-                None => Ok(serde_json::Value::Null),
-            }
+            let as_of_u64 = resolve_as_of(&guard, as_of)?;
+            let list = |db: &RustStrata| {
+                db.kv_list_as_of(prefix.as_deref(), None, None, as_of_u64)
+                    .map(user_keys)
+                    .map_err(to_napi_err)
+            };
+            let selected = match spaces {
+                Some(sel) => select_spaces(&guard, sel)?,
+                None => return Ok(serde_json::json!(list(&*guard)?)),
+            };
+            let keys: Vec<serde_json::Value> = across_spaces(&guard, &selected, list)?
+                .into_iter()
+                .flat_map(|(space, keys)| {
+                    keys.into_iter()
+                        .map(move |key| serde_json::json!({ "space": space.clone(), "key": key }))
+                })
+                .collect();
+            Ok(serde_json::Value::Array(keys))
         })
         .await
         .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
     }
 
-    /// List events by type. Optionally pass `asOf` for time-travel.
-    #[napi(js_name = "eventList")]
-    pub async fn event_list(
+    /// Get version history for a key.
+    ///
+    /// With `options`, returns one page as `{ versions, cursor }`; pass
+    /// `cursor` back to continue.
+    #[napi(js_name = "kvHistory")]
+    pub async fn kv_history(
         &self,
-        event_type: String,
-        as_of: Option<i64>,
+        key: String,
+        options: Option<JsHistoryOptions>,
     ) -> napi::Result<serde_json::Value> {
         let inner = self.inner.clone();
-        let as_of_u64 = as_of.map(|t| t as u64);
-        tokio::task::spawn_blocking(move || {
+        spawn_on(&self.pool, move || {
             let guard = lock_inner(&inner)?;
-            let events = guard
-                .event_get_by_type_with_options(&event_type, None, None, as_of_u64)
-                .map_err(to_napi_err)?;
-            let arr: Vec<serde_json::Value> =
-                events.into_iter().map(versioned_to_js).collect();
-            Ok(serde_json::Value::Array(arr))
+            let notes = kv_annotations(&guard, &key)?;
+            match (guard.kv_getv(&key).map_err(to_napi_err)?, options) {
+                (Some(versions), Some(options)) => {
+                    let mut page = history_page(&guard, versions, options)?;
+                    if let Some(versions) = page["versions"].as_array_mut() {
+                        annotate_versions(versions, &notes);
+                    }
+                    Ok(page)
+                }
+                (Some(versions), None) => {
+                    let mut arr: Vec<serde_json::Value> =
+                        versions.into_iter().map(versioned_to_js).collect();
+                    annotate_versions(&mut arr, &notes);
+                    Ok(serde_json::Value::Array(arr))
+                }
+                (None, _) => Ok(serde_json::Value::Null),
+            }
         })
         .await
         .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
     }
 
-    /// Get total event count.
-    #[napi(js_name = "eventLen")]
-    pub async fn event_len(&self) -> napi::Result<i64> {
+    /// Get the versions of a key written between `from` and `to`, oldest
+    /// first, each with a `diff` against the version before it.
+    ///
+    /// Diffs are JSON Patch style operations (`add`, `remove`, `replace`)
+    /// addressed by JSON Pointer, so object values only report the fields
+    /// that changed.
+    #[napi(js_name = "kvTimeline")]
+    pub async fn kv_timeline(
+        &self,
+        key: String,
+        options: Option<JsTimelineOptions>,
+    ) -> napi::Result<serde_json::Value> {
         let inner = self.inner.clone();
-        tokio::task::spawn_blocking(move || {
+        let (from, to) = options.map_or((None, None), |o| (o.from, o.to));
+        spawn_on(&self.pool, move || {
             let guard = lock_inner(&inner)?;
-            guard.event_len().map(|n| n as i64).map_err(to_napi_err)
+            let from = resolve_as_of(&guard, from)?.unwrap_or(0);
+            let to = resolve_as_of(&guard, to)?.unwrap_or(u64::MAX);
+            let mut versions = match guard.kv_getv(&key).map_err(to_napi_err)? {
+                Some(versions) => versions,
+                None => return Ok(serde_json::Value::Null),
+            };
+            versions.sort_by_key(|vv| vv.version);
+            let mut previous = serde_json::Value::Null;
+            let mut existed = false;
+            let mut out = Vec::new();
+            for vv in versions {
+                if vv.timestamp > to {
+                    break;
+                }
+                let value = value_to_js(vv.value);
+                if vv.timestamp >= from {
+                    let mut diff = Vec::new();
+                    if existed {
+                        json_diff(&previous, &value, &mut String::new(), &mut diff);
+                    } else {
+                        diff.push(serde_json::json!({ "op": "add", "path": "", "value": value }));
+                    }
+                    out.push(serde_json::json!({
+                        "value": value,
+                        "version": vv.version,
+                        "timestamp": vv.timestamp,
+                        "diff": diff,
+                    }));
+                }
+                previous = value;
+                existed = true;
+            }
+            annotate_versions(&mut out, &kv_annotations(&guard, &key)?);
+            Ok(serde_json::Value::Array(out))
         })
         .await
         .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
     }
 
     // =========================================================================
-    // JSON Store
+    // State Cell
     // =========================================================================
 
-    /// Set a value at a JSONPath.
-    #[napi(js_name = "jsonSet")]
-    pub async fn json_set(
+    /// Set a state cell value.
+    #[napi(js_name = "stateSet")]
+    pub async fn state_set(
         &self,
-        key: String,
-        path: String,
-        value: serde_json::Value,
+        cell: String,
+        #[napi(ts_arg_type = "any")] value: JsValue,
+        json_limits: Option<JsJsonLimits>,
     ) -> napi::Result<i64> {
+        check_user_cell(&cell)?;
         let inner = self.inner.clone();
-        let v = js_to_value_checked(value, 0)?;
-        tokio::task::spawn_blocking(move || {
+        let limits = self.json_limits.with(json_limits.as_ref())?;
+        limits.check_exact(&value)?;
+        let v = value.0;
+        limits.check(&v)?;
+        spawn_on(&self.pool, move || {
             let guard = lock_inner(&inner)?;
+            ensure_writable(&guard)?;
             guard
-                .json_set(&key, &path, v)
+                .state_set(&cell, v)
                 .map(|n| n as i64)
                 .map_err(to_napi_err)
         })
@@ -906,38 +3196,57 @@ impl Strata {
         .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
     }
 
-    /// Get a value at a JSONPath. Optionally pass `asOf` for time-travel.
-    #[napi(js_name = "jsonGet")]
-    pub async fn json_get(
+    /// Get a state cell value. Optionally pass `asOf` or `atVersion` for
+    /// time-travel.
+    #[napi(js_name = "stateGet", ts_return_type = "Promise<any>")]
+    pub async fn state_get(
         &self,
-        key: String,
-        path: String,
-        as_of: Option<i64>,
-    ) -> napi::Result<serde_json::Value> {
-        let inner = self.inner.clone();
-        let as_of_u64 = as_of.map(|t| t as u64);
-        tokio::task::spawn_blocking(move || {
-            let guard = lock_inner(&inner)?;
-            match guard
-                .json_get_as_of(&key, &path, as_of_u64)
-                .map_err(to_napi_err)?
-            {
-                Some(v) => Ok(value_to_js(v)),
-                None => Ok(serde_json::Value::Null),
-            }
+        cell: String,
+        as_of: Option<Either<i64, String>>,
+        at_version: Option<i64>,
+    ) -> napi::Result<JsOutput> {
+        let inner = self.inner.clone();
+        let mode = self.value_mode;
+        let at_version = check_at_version(&as_of, at_version)?;
+        spawn_on(&self.pool, move || {
+            let guard = lock_inner(&inner)?;
+            let as_of_u64 = match at_version {
+                Some(v) => {
+                    match version_timestamp(guard.state_getv(&cell).map_err(to_napi_err)?, v) {
+                        Some(ts) => Some(ts),
+                        None => return Ok(JsOutput(Value::Null, mode)),
+                    }
+                }
+                None => resolve_as_of(&guard, as_of)?,
+            };
+            let value = guard
+                .state_get_as_of(&cell, as_of_u64)
+                .map_err(to_napi_err)?;
+            Ok(JsOutput(value.unwrap_or(Value::Null), mode))
         })
         .await
         .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
     }
 
-    /// Delete a JSON document.
-    #[napi(js_name = "jsonDelete")]
-    pub async fn json_delete(&self, key: String, path: String) -> napi::Result<i64> {
+    /// Initialize a state cell if it doesn't exist.
+    #[napi(js_name = "stateInit")]
+    pub async fn state_init(
+        &self,
+        cell: String,
+        #[napi(ts_arg_type = "any")] value: JsValue,
+        json_limits: Option<JsJsonLimits>,
+    ) -> napi::Result<i64> {
+        check_user_cell(&cell)?;
         let inner = self.inner.clone();
-        tokio::task::spawn_blocking(move || {
+        let limits = self.json_limits.with(json_limits.as_ref())?;
+        limits.check_exact(&value)?;
+        let v = value.0;
+        limits.check(&v)?;
+        spawn_on(&self.pool, move || {
             let guard = lock_inner(&inner)?;
+            ensure_writable(&guard)?;
             guard
-                .json_delete(&key, &path)
+                .state_init(&cell, v)
                 .map(|n| n as i64)
                 .map_err(to_napi_err)
         })
@@ -945,1714 +3254,2343 @@ impl Strata {
         .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
     }
 
-    /// Get version history for a JSON document.
-    #[napi(js_name = "jsonHistory")]
-    pub async fn json_history(&self, key: String) -> napi::Result<serde_json::Value> {
+    /// Compare-and-swap update based on version.
+    #[napi(js_name = "stateCas")]
+    pub async fn state_cas(
+        &self,
+        cell: String,
+        #[napi(ts_arg_type = "any")] new_value: JsValue,
+        expected_version: Option<i64>,
+        json_limits: Option<JsJsonLimits>,
+    ) -> napi::Result<Option<i64>> {
+        check_user_cell(&cell)?;
         let inner = self.inner.clone();
-        tokio::task::spawn_blocking(move || {
+        let limits = self.json_limits.with(json_limits.as_ref())?;
+        limits.check_exact(&new_value)?;
+        let v = new_value.0;
+        limits.check(&v)?;
+        let exp = expected_version.map(|n| n as u64);
+        spawn_on(&self.pool, move || {
             let guard = lock_inner(&inner)?;
-            match guard.json_getv(&key).map_err(to_napi_err)? {
-                Some(versions) => {
-                    let arr: Vec<serde_json::Value> =
-                        versions.into_iter().map(versioned_to_js).collect();
-                    Ok(serde_json::Value::Array(arr))
-                }
-                None => Ok(serde_json::Value::Null),
-            }
+            ensure_writable(&guard)?;
+            guard
+                .state_cas(&cell, exp, v)
+                .map(|opt| opt.map(|n| n as i64))
+                .map_err(to_napi_err)
         })
         .await
         .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
     }
 
-    /// List JSON document keys. Optionally pass `asOf` for time-travel.
-    #[napi(js_name = "jsonList")]
-    pub async fn json_list(
+    /// Get version history for a state cell.
+    ///
+    /// With `options`, returns one page as `{ versions, cursor }`; pass
+    /// `cursor` back to continue.
+    #[napi(js_name = "stateHistory")]
+    pub async fn state_history(
         &self,
-        limit: u32,
-        prefix: Option<String>,
-        cursor: Option<String>,
-        as_of: Option<i64>,
+        cell: String,
+        options: Option<JsHistoryOptions>,
     ) -> napi::Result<serde_json::Value> {
         let inner = self.inner.clone();
-        let as_of_u64 = as_of.map(|t| t as u64);
-        tokio::task::spawn_blocking(move || {
+        spawn_on(&self.pool, move || {
             let guard = lock_inner(&inner)?;
-            let (keys, next_cursor) = guard
-                .json_list_as_of(prefix, cursor, limit as u64, as_of_u64)
-                .map_err(to_napi_err)?;
-            let has_more = next_cursor.is_some();
-            Ok(serde_json::json!({
-                "keys": keys,
-                "cursor": next_cursor,
-                "hasMore": has_more,
-            }))
+            match (guard.state_getv(&cell).map_err(to_napi_err)?, options) {
+                (Some(versions), Some(options)) => history_page(&guard, versions, options),
+                (Some(versions), None) => {
+                    let arr: Vec<serde_json::Value> =
+                        versions.into_iter().map(versioned_to_js).collect();
+                    Ok(serde_json::Value::Array(arr))
+                }
+                (None, _) => Ok(serde_json::Value::Null),
+            }
         })
         .await
         .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
     }
 
     // =========================================================================
-    // Vector Store
+    // Counters
     // =========================================================================
 
-    /// Create a vector collection.
-    #[napi(js_name = "vectorCreateCollection")]
-    pub async fn vector_create_collection(
+    /// Add `delta` (default 1, may be negative) to counter `name` and
+    /// return the new total. A counter starts at 0.
+    #[napi(js_name = "counterIncr")]
+    pub async fn counter_incr(&self, name: String, delta: Option<i64>) -> napi::Result<i64> {
+        let inner = self.inner.clone();
+        let gate = self.counter_gate.clone();
+        let delta = delta.unwrap_or(1);
+        spawn_on(&self.pool, move || {
+            let guard = lock_inner(&inner)?;
+            ensure_writable(&guard)?;
+            let _gate = lock_gate(&gate)?;
+            let (_, total) = update_counter(&guard, &counter_cell(&name), |total| {
+                total.checked_add(delta).ok_or_else(|| {
+                    napi::Error::from_reason(format!(
+                        "[VALIDATION] Counter '{}' would overflow a 64-bit integer",
+                        name
+                    ))
+                })
+            })?;
+            Ok(total)
+        })
+        .await
+        .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
+    }
+
+    /// Counter `name`'s total, 0 if it was never incremented. Optionally
+    /// pass `asOf` to read it as of a past timestamp or tag.
+    #[napi(js_name = "counterGet")]
+    pub async fn counter_get(
         &self,
-        collection: String,
-        dimension: u32,
-        metric: Option<String>,
+        name: String,
+        as_of: Option<Either<i64, String>>,
     ) -> napi::Result<i64> {
         let inner = self.inner.clone();
-        let m = match metric.as_deref().unwrap_or("cosine") {
-            "cosine" => DistanceMetric::Cosine,
-            "euclidean" => DistanceMetric::Euclidean,
-            "dot_product" | "dotproduct" => DistanceMetric::DotProduct,
-            _ => return Err(napi::Error::from_reason("[VALIDATION] Invalid metric")),
-        };
-        tokio::task::spawn_blocking(move || {
+        spawn_on(&self.pool, move || {
             let guard = lock_inner(&inner)?;
-            guard
-                .vector_create_collection(&collection, dimension as u64, m)
-                .map(|n| n as i64)
-                .map_err(to_napi_err)
+            let as_of_u64 = resolve_as_of(&guard, as_of)?;
+            let cell = counter_cell(&name);
+            let value = guard
+                .state_get_as_of(&cell, as_of_u64)
+                .map_err(to_napi_err)?;
+            counter_total(&cell, value.as_ref())
         })
         .await
         .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
     }
 
-    /// Delete a vector collection.
-    #[napi(js_name = "vectorDeleteCollection")]
-    pub async fn vector_delete_collection(&self, collection: String) -> napi::Result<bool> {
+    /// Set counter `name` back to `value` (default 0) and return the total
+    /// it had, e.g. to read and restart a metering window in one call.
+    #[napi(js_name = "counterReset")]
+    pub async fn counter_reset(&self, name: String, value: Option<i64>) -> napi::Result<i64> {
         let inner = self.inner.clone();
-        tokio::task::spawn_blocking(move || {
+        let gate = self.counter_gate.clone();
+        let value = value.unwrap_or(0);
+        spawn_on(&self.pool, move || {
             let guard = lock_inner(&inner)?;
-            guard
-                .vector_delete_collection(&collection)
-                .map_err(to_napi_err)
+            ensure_writable(&guard)?;
+            let _gate = lock_gate(&gate)?;
+            let (previous, _) = update_counter(&guard, &counter_cell(&name), |_| Ok(value))?;
+            Ok(previous)
         })
         .await
         .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
     }
 
-    /// List vector collections.
-    #[napi(js_name = "vectorListCollections")]
-    pub async fn vector_list_collections(&self) -> napi::Result<serde_json::Value> {
+    /// Every total counter `name` has had, newest first, as
+    /// `{ value, version, timestamp }`. With `options`, returns one page as
+    /// `{ versions, cursor }`, as `stateHistory` does.
+    #[napi(js_name = "counterHistory")]
+    pub async fn counter_history(
+        &self,
+        name: String,
+        options: Option<JsHistoryOptions>,
+    ) -> napi::Result<serde_json::Value> {
         let inner = self.inner.clone();
-        tokio::task::spawn_blocking(move || {
+        spawn_on(&self.pool, move || {
             let guard = lock_inner(&inner)?;
-            let collections = guard.vector_list_collections().map_err(to_napi_err)?;
-            let arr: Vec<serde_json::Value> =
-                collections.into_iter().map(collection_info_to_js).collect();
-            Ok(serde_json::Value::Array(arr))
+            let versions = guard
+                .state_getv(&counter_cell(&name))
+                .map_err(to_napi_err)?
+                .unwrap_or_default();
+            match options {
+                Some(options) => history_page(&guard, versions, options),
+                None => Ok(serde_json::Value::Array(
+                    versions.into_iter().map(versioned_to_js).collect(),
+                )),
+            }
         })
         .await
         .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
     }
 
-    /// Insert or update a vector.
-    #[napi(js_name = "vectorUpsert")]
-    pub async fn vector_upsert(
+    // =========================================================================
+    // Queues
+    // =========================================================================
+
+    /// Add `item` to the end of `queue`. Returns its sequence number.
+    #[napi(js_name = "queuePush")]
+    pub async fn queue_push(
         &self,
-        collection: String,
-        key: String,
-        vector: Vec<f64>,
-        metadata: Option<serde_json::Value>,
+        queue: String,
+        #[napi(ts_arg_type = "any")] item: JsValue,
+        json_limits: Option<JsJsonLimits>,
     ) -> napi::Result<i64> {
         let inner = self.inner.clone();
-        let vec = validate_vector(&vector)?;
-        let meta = match metadata {
-            Some(m) => Some(js_to_value_checked(m, 0)?),
-            None => None,
-        };
-        tokio::task::spawn_blocking(move || {
-            let guard = lock_inner(&inner)?;
+        let gate = self.counter_gate.clone();
+        check_queue_name(&queue)?;
+        let limits = self.json_limits.with(json_limits.as_ref())?;
+        limits.check_exact(&item)?;
+        let v = item.0;
+        limits.check(&v)?;
+        spawn_on(&self.pool, move || {
+            let guard = lock_inner(&inner)?;
+            ensure_writable(&guard)?;
+            let seq = {
+                let _gate = lock_gate(&gate)?;
+                update_counter(&guard, &queue_seq_cell(&queue), |seq| Ok(seq + 1))?.1
+            };
+            let now = now_micros() as i64;
+            let message = queue_message(v, 0, now, now);
             guard
-                .vector_upsert(&collection, &key, vec, meta)
-                .map(|n| n as i64)
-                .map_err(to_napi_err)
+                .state_init(&queue_cell(&queue, seq), message)
+                .map_err(to_napi_err)?;
+            Ok(seq)
         })
         .await
         .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
     }
 
-    /// Get a vector by key. Optionally pass `asOf` for time-travel.
-    #[napi(js_name = "vectorGet")]
-    pub async fn vector_get(
+    /// Take the oldest visible message of `queue` as `{ id, item, attempts }`,
+    /// or `null` if there is none. The message is hidden for the visibility
+    /// timeout, then delivered again unless `queueAck(id)` was called.
+    #[napi(js_name = "queuePop", ts_return_type = "Promise<any>")]
+    pub async fn queue_pop(
         &self,
-        collection: String,
-        key: String,
-        as_of: Option<i64>,
-    ) -> napi::Result<serde_json::Value> {
-        let inner = self.inner.clone();
-        let as_of_u64 = as_of.map(|t| t as u64);
-        tokio::task::spawn_blocking(move || {
-            let guard = lock_inner(&inner)?;
-            match guard
-                .vector_get_as_of(&collection, &key, as_of_u64)
-                .map_err(to_napi_err)?
-            {
-                Some(vd) => {
-                    let embedding: Vec<f64> =
-                        vd.data.embedding.iter().map(|&f| f as f64).collect();
-                    Ok(serde_json::json!({
-                        "key": vd.key,
-                        "embedding": embedding,
-                        "metadata": vd.data.metadata.map(value_to_js),
-                        "version": vd.version,
-                        "timestamp": vd.timestamp,
-                    }))
+        queue: String,
+        options: Option<JsQueuePopOptions>,
+    ) -> napi::Result<JsOutput> {
+        let inner = self.inner.clone();
+        let mode = self.value_mode;
+        check_queue_name(&queue)?;
+        let timeout_ms = options
+            .and_then(|o| o.visibility_timeout)
+            .unwrap_or(DEFAULT_VISIBILITY_TIMEOUT_MS);
+        spawn_on(&self.pool, move || {
+            let guard = lock_inner(&inner)?;
+            ensure_writable(&guard)?;
+            let range = queue_range(&guard, &queue)?;
+            let (head, tail, mut live) = (*range.start(), *range.end(), None);
+            for seq in range {
+                let cell = queue_cell(&queue, seq);
+                let Some(latest) = latest_version(&guard, &cell)? else {
+                    continue;
+                };
+                let Some(message) = QueueMessage::read(latest.value) else {
+                    continue;
+                };
+                if live.is_none() {
+                    live = Some(seq);
+                    if seq > head {
+                        advance_queue_head(&guard, &queue, seq)?;
+                    }
                 }
-                None => Ok(serde_json::Value::Null),
+                let now = now_micros() as i64;
+                if message.visible_at > now {
+                    continue;
+                }
+                let attempts = message.attempts + 1;
+                let visible_at = now + i64::from(timeout_ms) * 1000;
+                let claimed = queue_message(
+                    message.item.clone(),
+                    attempts,
+                    visible_at,
+                    message.pushed_at,
+                );
+                let swapped = guard
+                    .state_cas(&cell, Some(latest.version), claimed)
+                    .map_err(to_napi_err)?;
+                if swapped.is_none() {
+                    // Another consumer got there first.
+                    continue;
+                }
+                let mut out = HashMap::new();
+                let id = format!("{}/{}/{}", queue, seq, attempts);
+                out.insert("id".to_string(), Value::String(id));
+                out.insert("item".to_string(), message.item);
+                out.insert("attempts".to_string(), Value::Int(attempts));
+                return Ok(JsOutput(Value::Object(Box::new(out)), mode));
             }
+            if live.is_none() && tail + 1 > head {
+                advance_queue_head(&guard, &queue, tail + 1)?;
+            }
+            Ok(JsOutput(Value::Null, mode))
         })
         .await
         .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
     }
 
-    /// Delete a vector.
-    #[napi(js_name = "vectorDelete")]
-    pub async fn vector_delete(&self, collection: String, key: String) -> napi::Result<bool> {
-        let inner = self.inner.clone();
-        tokio::task::spawn_blocking(move || {
-            let guard = lock_inner(&inner)?;
-            guard.vector_delete(&collection, &key).map_err(to_napi_err)
-        })
-        .await
-        .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
-    }
-
-    /// Search for similar vectors. Optionally pass `asOf` for time-travel.
-    #[napi(js_name = "vectorSearch")]
-    pub async fn vector_search(
-        &self,
-        collection: String,
-        query: Vec<f64>,
-        k: u32,
-        as_of: Option<i64>,
-    ) -> napi::Result<serde_json::Value> {
+    /// Acknowledge a message from `queuePop`, removing it from its queue.
+    /// Fails with a conflict if its visibility timeout ran out and it was
+    /// delivered again, since the later delivery now owns it.
+    #[napi(js_name = "queueAck")]
+    pub async fn queue_ack(&self, id: String) -> napi::Result<()> {
         let inner = self.inner.clone();
-        let vec = validate_vector(&query)?;
-        let as_of_u64 = as_of.map(|t| t as u64);
-        tokio::task::spawn_blocking(move || {
+        let (queue, seq, attempts) = parse_queue_id(&id)?;
+        spawn_on(&self.pool, move || {
             let guard = lock_inner(&inner)?;
-            let matches = guard
-                .vector_search_with_filter(&collection, vec, k as u64, None, None, as_of_u64)
+            ensure_writable(&guard)?;
+            let cell = queue_cell(&queue, seq);
+            let latest = latest_version(&guard, &cell)?;
+            let Some((version, message)) =
+                latest.and_then(|vv| Some((vv.version, QueueMessage::read(vv.value)?)))
+            else {
+                return Err(napi::Error::from_reason(format!(
+                    "[NOT_FOUND] Queue message not found: {}",
+                    id
+                )));
+            };
+            if message.attempts != attempts {
+                return Err(napi::Error::from_reason(format!(
+                    "[CONFLICT] Queue message {} was delivered again after its visibility timeout",
+                    id
+                )));
+            }
+            // Clear the message with a compare-and-swap first, so a
+            // redelivery that races this ack is not lost.
+            let cleared = guard
+                .state_cas(&cell, Some(version), Value::Null)
                 .map_err(to_napi_err)?;
-            let arr: Vec<serde_json::Value> = matches
-                .into_iter()
-                .map(|m| {
-                    serde_json::json!({
-                        "key": m.key,
-                        "score": m.score,
-                        "metadata": m.metadata.map(value_to_js),
-                    })
-                })
-                .collect();
-            Ok(serde_json::Value::Array(arr))
+            if cleared.is_none() {
+                return Err(napi::Error::from_reason(format!(
+                    "[CONFLICT] Queue message {} was delivered again after its visibility timeout",
+                    id
+                )));
+            }
+            guard.state_delete(&cell).map_err(to_napi_err)?;
+            Ok(())
         })
         .await
         .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
     }
 
-    /// Get statistics for a single collection.
-    #[napi(js_name = "vectorCollectionStats")]
-    pub async fn vector_collection_stats(
-        &self,
-        collection: String,
-    ) -> napi::Result<serde_json::Value> {
+    /// Counts for `queue`: `{ ready, inFlight, pushed }`, where `inFlight`
+    /// messages are popped but not yet acknowledged or timed out, and
+    /// `pushed` counts every message ever pushed.
+    #[napi(js_name = "queueStats")]
+    pub async fn queue_stats(&self, queue: String) -> napi::Result<serde_json::Value> {
         let inner = self.inner.clone();
-        tokio::task::spawn_blocking(move || {
+        check_queue_name(&queue)?;
+        spawn_on(&self.pool, move || {
             let guard = lock_inner(&inner)?;
-            let info = guard
-                .vector_collection_stats(&collection)
+            let now = now_micros() as i64;
+            let (mut ready, mut in_flight) = (0u64, 0u64);
+            for seq in queue_range(&guard, &queue)? {
+                let value = guard
+                    .state_get_as_of(&queue_cell(&queue, seq), None)
+                    .map_err(to_napi_err)?;
+                match value.and_then(QueueMessage::read) {
+                    Some(m) if m.visible_at > now => in_flight += 1,
+                    Some(_) => ready += 1,
+                    None => {}
+                }
+            }
+            let pushed = guard
+                .state_get_as_of(&queue_seq_cell(&queue), None)
                 .map_err(to_napi_err)?;
-            Ok(collection_info_to_js(info))
+            Ok(serde_json::json!({
+                "ready": ready,
+                "inFlight": in_flight,
+                "pushed": counter_total(&queue_seq_cell(&queue), pushed.as_ref())?,
+            }))
         })
         .await
         .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
     }
 
-    /// Batch insert/update multiple vectors.
-    #[napi(js_name = "vectorBatchUpsert")]
-    pub async fn vector_batch_upsert(
-        &self,
-        collection: String,
-        vectors: Vec<serde_json::Value>,
-    ) -> napi::Result<Vec<i64>> {
-        let inner = self.inner.clone();
-        // Parse and validate all entries on the JS thread before spawning.
-        let batch: Vec<BatchVectorEntry> = vectors
-            .into_iter()
-            .map(|v| {
-                let obj = v
-                    .as_object()
-                    .ok_or_else(|| napi::Error::from_reason("[VALIDATION] Expected object"))?;
-                let key = obj
-                    .get("key")
-                    .and_then(|k| k.as_str())
-                    .ok_or_else(|| napi::Error::from_reason("[VALIDATION] Missing 'key'"))?
-                    .to_string();
-                let raw_vec: Vec<f64> = obj
-                    .get("vector")
-                    .and_then(|v| v.as_array())
-                    .ok_or_else(|| napi::Error::from_reason("[VALIDATION] Missing 'vector'"))?
-                    .iter()
-                    .map(|n| {
-                        n.as_f64().ok_or_else(|| {
-                            napi::Error::from_reason(
-                                "[VALIDATION] Vector element is not a number",
-                            )
-                        })
-                    })
-                    .collect::<napi::Result<_>>()?;
-                let vec = validate_vector(&raw_vec)?;
-                let meta = match obj.get("metadata") {
-                    Some(m) => Some(js_to_value_checked(m.clone(), 0)?),
-                    None => None,
-                };
-                Ok(BatchVectorEntry {
-                    key,
-                    vector: vec,
-                    metadata: meta,
+    // =========================================================================
+    // Locks
+    // =========================================================================
+
+    /// Take lock `name` for `ttlMs`, retrying for up to `waitMs` while
+    /// another holder has it. Returns a new random lease token, or `null`
+    /// if the lock is still held when the wait runs out. An expired lease
+    /// is free for the taking.
+    #[napi(js_name = "lockAcquire")]
+    pub async fn lock_acquire(
+        &self,
+        name: String,
+        options: Option<JsLockOptions>,
+    ) -> napi::Result<Option<String>> {
+        check_lock_name(&name)?;
+        let ttl_ms = options
+            .as_ref()
+            .and_then(|o| o.ttl_ms)
+            .unwrap_or(DEFAULT_LOCK_TTL_MS);
+        let wait_ms = options.as_ref().and_then(|o| o.wait_ms).unwrap_or(0);
+        let deadline = std::time::Instant::now() + std::time::Duration::from_millis(wait_ms as u64);
+        let token = lease_token();
+        loop {
+            let (inner, cell, lease) = (self.inner.clone(), lock_cell(&name), token.clone());
+            let acquired = spawn_on(&self.pool, move || {
+                let guard = lock_inner(&inner)?;
+                ensure_writable(&guard)?;
+                let view = lock_handle(&guard)?;
+                let now = now_millis();
+                swap_lease(&view, &cell, |current| {
+                    if current.is_some_and(|l| l.expires_at > now) {
+                        return None;
+                    }
+                    let lease = Lease {
+                        token: lease.clone(),
+                        expires_at: now + i64::from(ttl_ms),
+                    };
+                    Some(lease.into_value())
                 })
             })
-            .collect::<napi::Result<_>>()?;
-        tokio::task::spawn_blocking(move || {
-            let guard = lock_inner(&inner)?;
-            guard
-                .vector_batch_upsert(&collection, batch)
-                .map(|versions| versions.into_iter().map(|v| v as i64).collect())
-                .map_err(to_napi_err)
-        })
-        .await
-        .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
+            .await
+            .map_err(|e| napi::Error::from_reason(format!("{}", e)))??;
+            if acquired {
+                return Ok(Some(token));
+            }
+            let now = std::time::Instant::now();
+            if now >= deadline {
+                return Ok(None);
+            }
+            tokio::time::sleep(LOCK_RETRY_INTERVAL.min(deadline - now)).await;
+        }
     }
 
-    // =========================================================================
-    // Branch Management
-    // =========================================================================
-
-    /// Get the current branch name.
-    #[napi(js_name = "currentBranch")]
-    pub async fn current_branch(&self) -> napi::Result<String> {
-        let inner = self.inner.clone();
-        tokio::task::spawn_blocking(move || {
-            let guard = lock_inner(&inner)?;
-            Ok(guard.current_branch().to_string())
+    /// Extend the lease `token` on lock `name` to `ttlMs` (default 30,000)
+    /// from now. Returns `false` if the lease is no longer held. A lease
+    /// that expired but was not taken by anyone else is still `token`'s.
+    #[napi(js_name = "lockRenew")]
+    pub async fn lock_renew(
+        &self,
+        name: String,
+        token: String,
+        ttl_ms: Option<u32>,
+    ) -> napi::Result<bool> {
+        let inner = self.inner.clone();
+        let ttl_ms = ttl_ms.unwrap_or(DEFAULT_LOCK_TTL_MS);
+        spawn_on(&self.pool, move || {
+            let guard = lock_inner(&inner)?;
+            ensure_writable(&guard)?;
+            let view = lock_handle(&guard)?;
+            let now = now_millis();
+            swap_lease(&view, &lock_cell(&name), |current| {
+                let held = current.filter(|l| l.token == token)?;
+                let lease = Lease {
+                    expires_at: now + i64::from(ttl_ms),
+                    ..held
+                };
+                Some(lease.into_value())
+            })
         })
         .await
         .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
     }
 
-    /// Switch to a different branch.
-    #[napi(js_name = "setBranch")]
-    pub async fn set_branch(&self, branch: String) -> napi::Result<()> {
+    /// Give up the lease `token` on lock `name`. Returns `false` if it was
+    /// no longer held.
+    #[napi(js_name = "lockRelease")]
+    pub async fn lock_release(&self, name: String, token: String) -> napi::Result<bool> {
         let inner = self.inner.clone();
-        tokio::task::spawn_blocking(move || {
-            let mut guard = inner
-                .lock()
-                .map_err(|_| napi::Error::from_reason("Lock poisoned"))?;
-            guard.set_branch(&branch).map_err(to_napi_err)
+        spawn_on(&self.pool, move || {
+            let guard = lock_inner(&inner)?;
+            ensure_writable(&guard)?;
+            let view = lock_handle(&guard)?;
+            swap_lease(&view, &lock_cell(&name), |current| {
+                current.filter(|l| l.token == token).map(|_| Value::Null)
+            })
         })
         .await
         .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
     }
 
-    /// Create a new empty branch.
-    #[napi(js_name = "createBranch")]
-    pub async fn create_branch(
+    // =========================================================================
+    // Blobs
+    // =========================================================================
+
+    /// Store chunk `index` of upload `upload`, for `blobPut`. Chunks are
+    /// kept as bytes, so they skip value conversion and JSON limits.
+    #[napi(js_name = "blobWriteChunk")]
+    pub async fn blob_write_chunk(
         &self,
-        branch: String,
-        metadata: Option<serde_json::Value>,
+        upload: String,
+        index: u32,
+        data: Buffer,
     ) -> napi::Result<()> {
         let inner = self.inner.clone();
-        let meta_val = metadata
-            .map(|m| js_to_value_checked(m, 0))
-            .transpose()?;
-        tokio::task::spawn_blocking(move || {
+        check_blob_upload(&upload)?;
+        let bytes: Vec<u8> = data.into();
+        spawn_on(&self.pool, move || {
             let guard = lock_inner(&inner)?;
+            ensure_writable(&guard)?;
             guard
-                .branch_create(Some(branch), meta_val)
-                .map(|_| ())
-                .map_err(to_napi_err)
+                .kv_put(&blob_chunk_key(&upload, index), Value::Bytes(bytes))
+                .map_err(to_napi_err)?;
+            Ok(())
         })
         .await
         .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
     }
 
-    /// Fork the current branch to a new branch, copying all data.
-    #[napi(js_name = "forkBranch")]
-    pub async fn fork_branch(&self, destination: String) -> napi::Result<serde_json::Value> {
+    /// Point blob `key` at the first `chunks` chunks of `upload`, `size`
+    /// bytes in all, and delete the chunks of the content it replaces.
+    /// Returns the blob's `blobStat`.
+    #[napi(js_name = "blobCommit")]
+    pub async fn blob_commit(
+        &self,
+        key: String,
+        upload: String,
+        size: i64,
+        chunks: u32,
+    ) -> napi::Result<serde_json::Value> {
         let inner = self.inner.clone();
-        tokio::task::spawn_blocking(move || {
+        check_blob_key(&key)?;
+        check_blob_upload(&upload)?;
+        spawn_on(&self.pool, move || {
             let guard = lock_inner(&inner)?;
-            let info = guard.fork_branch(&destination).map_err(to_napi_err)?;
-            Ok(serde_json::json!({
-                "source": info.source,
-                "destination": info.destination,
-                "keysCopied": info.keys_copied,
-            }))
+            ensure_writable(&guard)?;
+            let manifest = BlobManifest {
+                upload,
+                size,
+                chunks,
+            };
+            let replaced = replace_state(&guard, &blob_cell(&key), manifest.to_value())?;
+            if let Some(old) = replaced.as_ref().and_then(BlobManifest::read) {
+                delete_blob_chunks(&guard, &old.upload, old.chunks)?;
+            }
+            blob_stat(&guard, &key)
         })
         .await
         .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
     }
 
-    /// List all branches.
-    #[napi(js_name = "listBranches")]
-    pub async fn list_branches(
-        &self,
-        limit: Option<u32>,
-        offset: Option<u32>,
-    ) -> napi::Result<serde_json::Value> {
+    /// Delete the first `chunks` chunks of an upload that `blobPut` gave up
+    /// on.
+    #[napi(js_name = "blobDiscard")]
+    pub async fn blob_discard(&self, upload: String, chunks: u32) -> napi::Result<()> {
         let inner = self.inner.clone();
-        tokio::task::spawn_blocking(move || {
+        check_blob_upload(&upload)?;
+        spawn_on(&self.pool, move || {
             let guard = lock_inner(&inner)?;
-            let branches = guard
-                .branch_list(
-                    None,
-                    limit.map(|l| l as u64),
-                    offset.map(|o| o as u64),
-                )
-                .map_err(to_napi_err)?;
-            let names: Vec<serde_json::Value> = branches
-                .into_iter()
-                .map(|b| serde_json::Value::String(b.info.id.as_str().to_string()))
-                .collect();
-            Ok(serde_json::Value::Array(names))
+            ensure_writable(&guard)?;
+            delete_blob_chunks(&guard, &upload, chunks)
         })
         .await
         .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
     }
 
-    /// Delete a branch.
-    #[napi(js_name = "deleteBranch")]
-    pub async fn delete_branch(&self, branch: String) -> napi::Result<()> {
-        let inner = self.inner.clone();
-        tokio::task::spawn_blocking(move || {
-            let guard = lock_inner(&inner)?;
-            guard.delete_branch(&branch).map_err(to_napi_err)
+    /// Chunk `index` of upload `upload`, or `null` if it was deleted.
+    #[napi(js_name = "blobReadChunk")]
+    pub async fn blob_read_chunk(
+        &self,
+        upload: String,
+        index: u32,
+    ) -> napi::Result<Option<Buffer>> {
+        let inner = self.inner.clone();
+        check_blob_upload(&upload)?;
+        spawn_on(&self.pool, move || {
+            let guard = lock_inner(&inner)?;
+            let key = blob_chunk_key(&upload, index);
+            match guard.kv_get_as_of(&key, None).map_err(to_napi_err)? {
+                Some(Value::Bytes(bytes)) => Ok(Some(Buffer::from(bytes))),
+                Some(_) => Err(napi::Error::from_reason(format!(
+                    "[STATE] Key '{}' does not hold a blob chunk",
+                    key
+                ))),
+                None => Ok(None),
+            }
         })
         .await
         .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
     }
 
-    /// Check if a branch exists.
-    #[napi(js_name = "branchExists")]
-    pub async fn branch_exists(&self, name: String) -> napi::Result<bool> {
+    /// `{ size, chunks, upload, timestamp }` of blob `key`, or `null` if it
+    /// doesn't exist. `timestamp` is when it was last written.
+    #[napi(js_name = "blobStat")]
+    pub async fn blob_stat(&self, key: String) -> napi::Result<serde_json::Value> {
         let inner = self.inner.clone();
-        tokio::task::spawn_blocking(move || {
+        check_blob_key(&key)?;
+        spawn_on(&self.pool, move || {
             let guard = lock_inner(&inner)?;
-            guard.branches().exists(&name).map_err(to_napi_err)
+            blob_stat(&guard, &key)
         })
         .await
         .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
     }
 
-    /// Get branch metadata with version info.
-    #[napi(js_name = "branchGet")]
-    pub async fn branch_get(&self, name: String) -> napi::Result<serde_json::Value> {
+    /// Delete blob `key` and its chunks. Returns `false` if it didn't exist.
+    #[napi(js_name = "blobDelete")]
+    pub async fn blob_delete(&self, key: String) -> napi::Result<bool> {
         let inner = self.inner.clone();
-        tokio::task::spawn_blocking(move || {
+        check_blob_key(&key)?;
+        spawn_on(&self.pool, move || {
             let guard = lock_inner(&inner)?;
-            match guard.branch_get(&name).map_err(to_napi_err)? {
-                Some(info) => Ok(versioned_branch_info_to_js(info)),
-                None => Ok(serde_json::Value::Null),
+            ensure_writable(&guard)?;
+            let cell = blob_cell(&key);
+            if latest_version(&guard, &cell)?.is_none() {
+                return Ok(false);
             }
+            let replaced = replace_state(&guard, &cell, Value::Null)?;
+            guard.state_delete(&cell).map_err(to_napi_err)?;
+            let Some(old) = replaced.as_ref().and_then(BlobManifest::read) else {
+                return Ok(false);
+            };
+            delete_blob_chunks(&guard, &old.upload, old.chunks)?;
+            Ok(true)
         })
         .await
         .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
     }
 
-    /// Compare two branches.
-    #[napi(js_name = "diffBranches")]
-    pub async fn diff_branches(
+    // =========================================================================
+    // Edges
+    // =========================================================================
+
+    /// Add the edge `from -[relation]-> to` with optional `properties`,
+    /// replacing the properties if it exists.
+    #[napi(js_name = "edgeAdd")]
+    pub async fn edge_add(
         &self,
-        branch_a: String,
-        branch_b: String,
-    ) -> napi::Result<serde_json::Value> {
+        from: String,
+        relation: String,
+        to: String,
+        #[napi(ts_arg_type = "any")] properties: Option<JsValue>,
+        json_limits: Option<JsJsonLimits>,
+    ) -> napi::Result<()> {
         let inner = self.inner.clone();
-        tokio::task::spawn_blocking(move || {
+        check_edge(&from, &relation, &to)?;
+        let limits = self.json_limits.with(json_limits.as_ref())?;
+        let v = match properties {
+            Some(properties) => {
+                limits.check_exact(&properties)?;
+                properties.0
+            }
+            None => Value::Null,
+        };
+        limits.check(&v)?;
+        spawn_on(&self.pool, move || {
             let guard = lock_inner(&inner)?;
-            let diff = guard
-                .diff_branches(&branch_a, &branch_b)
+            ensure_writable(&guard)?;
+            guard
+                .state_set(&edge_cell(EDGE_OUT, &from, &relation, &to), v.clone())
                 .map_err(to_napi_err)?;
-            Ok(serde_json::json!({
-                "branchA": diff.branch_a,
-                "branchB": diff.branch_b,
-                "summary": {
-                    "totalAdded": diff.summary.total_added,
-                    "totalRemoved": diff.summary.total_removed,
-                    "totalModified": diff.summary.total_modified,
-                },
-            }))
+            guard
+                .state_set(&edge_cell(EDGE_IN, &to, &relation, &from), v)
+                .map_err(to_napi_err)?;
+            Ok(())
         })
         .await
         .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
     }
 
-    /// Merge a branch into the current branch.
-    #[napi(js_name = "mergeBranches")]
-    pub async fn merge_branches(
+    /// Remove the edge `from -[relation]-> to`. Returns `false` if it
+    /// didn't exist.
+    #[napi(js_name = "edgeRemove")]
+    pub async fn edge_remove(
         &self,
-        source: String,
-        strategy: Option<String>,
-    ) -> napi::Result<serde_json::Value> {
-        let inner = self.inner.clone();
-        let strat = match strategy.as_deref().unwrap_or("last_writer_wins") {
-            "last_writer_wins" => MergeStrategy::LastWriterWins,
-            "strict" => MergeStrategy::Strict,
-            _ => return Err(napi::Error::from_reason("[VALIDATION] Invalid merge strategy")),
-        };
-        tokio::task::spawn_blocking(move || {
-            let guard = lock_inner(&inner)?;
-            let target = guard.current_branch().to_string();
-            let info = guard
-                .merge_branches(&source, &target, strat)
+        from: String,
+        relation: String,
+        to: String,
+    ) -> napi::Result<bool> {
+        let inner = self.inner.clone();
+        check_edge(&from, &relation, &to)?;
+        spawn_on(&self.pool, move || {
+            let guard = lock_inner(&inner)?;
+            ensure_writable(&guard)?;
+            let out = edge_cell(EDGE_OUT, &from, &relation, &to);
+            let existing = guard.state_get_as_of(&out, None).map_err(to_napi_err)?;
+            if existing.is_none() {
+                return Ok(false);
+            }
+            guard.state_delete(&out).map_err(to_napi_err)?;
+            guard
+                .state_delete(&edge_cell(EDGE_IN, &to, &relation, &from))
                 .map_err(to_napi_err)?;
-            let conflicts: Vec<serde_json::Value> = info
-                .conflicts
-                .into_iter()
-                .map(|c| {
-                    serde_json::json!({
-                        "key": c.key,
-                        "space": c.space,
-                    })
-                })
-                .collect();
-            Ok(serde_json::json!({
-                "keysApplied": info.keys_applied,
-                "spacesMerged": info.spaces_merged,
-                "conflicts": conflicts,
-            }))
+            Ok(true)
         })
         .await
         .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
     }
 
-    // =========================================================================
-    // Space Management
-    // =========================================================================
-
-    /// Get the current space name.
-    #[napi(js_name = "currentSpace")]
-    pub async fn current_space(&self) -> napi::Result<String> {
+    /// The edges at `node` as `{ from, relation, to, properties }`, outgoing
+    /// unless `direction` says otherwise.
+    #[napi(js_name = "edgeList", ts_return_type = "Promise<any[]>")]
+    pub async fn edge_list(
+        &self,
+        node: String,
+        options: Option<JsEdgeListOptions>,
+    ) -> napi::Result<JsOutput> {
         let inner = self.inner.clone();
-        tokio::task::spawn_blocking(move || {
+        let mode = self.value_mode;
+        let options = options.unwrap_or(JsEdgeListOptions {
+            direction: None,
+            relation: None,
+            as_of: None,
+        });
+        let dirs = edge_directions(options.direction.as_deref())?;
+        spawn_on(&self.pool, move || {
             let guard = lock_inner(&inner)?;
-            Ok(guard.current_space().to_string())
+            let as_of = resolve_as_of(&guard, options.as_of)?;
+            let edges = node_edges(&guard, &node, dirs, options.relation.as_deref(), as_of)?;
+            let out = edges.into_iter().map(Edge::into_value).collect();
+            Ok(JsOutput(Value::Array(Box::new(out)), mode))
         })
         .await
         .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
     }
 
-    /// Switch to a different space.
-    #[napi(js_name = "setSpace")]
-    pub async fn set_space(&self, space: String) -> napi::Result<()> {
-        let inner = self.inner.clone();
-        tokio::task::spawn_blocking(move || {
-            let mut guard = inner
-                .lock()
-                .map_err(|_| napi::Error::from_reason("Lock poisoned"))?;
-            guard.set_space(&space).map_err(to_napi_err)
+    /// Walk the edges breadth-first from `start`, up to `depth` hops and
+    /// `limit` nodes. Returns `{ visited, depths, edges }`: the nodes in the
+    /// order reached, each node's hop count, and the edges followed.
+    #[napi(js_name = "traverse", ts_return_type = "Promise<any>")]
+    pub async fn traverse(
+        &self,
+        start: String,
+        options: Option<JsTraverseOptions>,
+    ) -> napi::Result<JsOutput> {
+        let inner = self.inner.clone();
+        let mode = self.value_mode;
+        let options = options.unwrap_or(JsTraverseOptions {
+            depth: None,
+            direction: None,
+            relation: None,
+            limit: None,
+            as_of: None,
+        });
+        let dirs = edge_directions(options.direction.as_deref())?;
+        let max_depth = options.depth.unwrap_or(1);
+        let limit = options.limit.unwrap_or(DEFAULT_TRAVERSE_LIMIT) as usize;
+        spawn_on(&self.pool, move || {
+            let guard = lock_inner(&inner)?;
+            let as_of = resolve_as_of(&guard, options.as_of)?;
+            let relation = options.relation.as_deref();
+            let mut depths = HashMap::new();
+            let mut visited = vec![start.clone()];
+            let mut queue = std::collections::VecDeque::from([start.clone()]);
+            let mut seen = HashSet::new();
+            let mut followed = Vec::new();
+            depths.insert(start, 0u32);
+            while let Some(node) = queue.pop_front() {
+                let depth = depths[&node];
+                if depth >= max_depth {
+                    continue;
+                }
+                for edge in node_edges(&guard, &node, dirs, relation, as_of)? {
+                    let next = if edge.from == node {
+                        &edge.to
+                    } else {
+                        &edge.from
+                    };
+                    if !depths.contains_key(next) {
+                        if visited.len() >= limit {
+                            continue;
+                        }
+                        depths.insert(next.clone(), depth + 1);
+                        visited.push(next.clone());
+                        queue.push_back(next.clone());
+                    }
+                    let id = (edge.from.clone(), edge.relation.clone(), edge.to.clone());
+                    if seen.insert(id) {
+                        followed.push(edge.into_value());
+                    }
+                }
+            }
+            let depths: HashMap<String, Value> = depths
+                .into_iter()
+                .map(|(node, depth)| (node, Value::Int(i64::from(depth))))
+                .collect();
+            let mut out = HashMap::new();
+            let visited = visited.into_iter().map(Value::String).collect();
+            out.insert("visited".to_string(), Value::Array(Box::new(visited)));
+            out.insert("depths".to_string(), Value::Object(Box::new(depths)));
+            out.insert("edges".to_string(), Value::Array(Box::new(followed)));
+            Ok(JsOutput(Value::Object(Box::new(out)), mode))
         })
         .await
         .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
     }
 
-    /// List all spaces in the current branch.
-    #[napi(js_name = "listSpaces")]
-    pub async fn list_spaces(&self) -> napi::Result<Vec<String>> {
-        let inner = self.inner.clone();
-        tokio::task::spawn_blocking(move || {
-            let guard = lock_inner(&inner)?;
-            guard.list_spaces().map_err(to_napi_err)
-        })
-        .await
-        .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
-    }
+    // =========================================================================
+    // Time series
+    // =========================================================================
 
-    /// Delete a space and all its data.
-    #[napi(js_name = "deleteSpace")]
-    pub async fn delete_space(&self, space: String) -> napi::Result<()> {
+    /// Record `value` in `series` at `timestamp` (microseconds, default
+    /// now) with optional string `tags`. A point at the same timestamp is
+    /// replaced. Returns the timestamp.
+    #[napi(js_name = "tsAppend")]
+    pub async fn ts_append(
+        &self,
+        series: String,
+        timestamp: Option<i64>,
+        value: f64,
+        tags: Option<HashMap<String, String>>,
+    ) -> napi::Result<i64> {
         let inner = self.inner.clone();
-        tokio::task::spawn_blocking(move || {
+        check_series_name(&series)?;
+        let timestamp = timestamp.unwrap_or_else(|| now_micros() as i64);
+        if timestamp < 0 {
+            return Err(napi::Error::from_reason(
+                "[VALIDATION] Timestamp must not be negative",
+            ));
+        }
+        if !value.is_finite() {
+            return Err(napi::Error::from_reason(format!(
+                "[VALIDATION] Time series values must be finite, got {}",
+                value
+            )));
+        }
+        spawn_on(&self.pool, move || {
             let guard = lock_inner(&inner)?;
-            guard.delete_space(&space).map_err(to_napi_err)
+            ensure_writable(&guard)?;
+            guard
+                .kv_put(&ts_key(&series, timestamp), ts_point(value, tags))
+                .map_err(to_napi_err)?;
+            Ok(timestamp)
         })
         .await
         .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
     }
 
-    /// Force delete a space even if non-empty.
-    #[napi(js_name = "deleteSpaceForce")]
-    pub async fn delete_space_force(&self, space: String) -> napi::Result<()> {
+    /// The points of `series` in `[from, to]`, oldest first, as
+    /// `{ timestamp, value, tags }`. With `aggregate`, returns one
+    /// `{ timestamp, value, count }` per `bucket` instead, stamped with the
+    /// bucket's start.
+    #[napi(js_name = "tsQuery")]
+    pub async fn ts_query(
+        &self,
+        series: String,
+        options: Option<JsTsQueryOptions>,
+    ) -> napi::Result<serde_json::Value> {
         let inner = self.inner.clone();
-        tokio::task::spawn_blocking(move || {
-            let guard = lock_inner(&inner)?;
-            guard.delete_space_force(&space).map_err(to_napi_err)
+        check_series_name(&series)?;
+        let options = options.unwrap_or(JsTsQueryOptions {
+            from: None,
+            to: None,
+            tags: None,
+            aggregate: None,
+            bucket: None,
+        });
+        let aggregate = options
+            .aggregate
+            .as_deref()
+            .map(TsAggregate::parse)
+            .transpose()?;
+        match (aggregate.is_some(), options.bucket) {
+            (false, Some(_)) => {
+                return Err(napi::Error::from_reason(
+                    "[VALIDATION] bucket requires aggregate",
+                ))
+            }
+            (_, Some(width)) if width <= 0 => {
+                return Err(napi::Error::from_reason(
+                    "[VALIDATION] bucket must be greater than 0",
+                ))
+            }
+            _ => {}
+        }
+        spawn_on(&self.pool, move || {
+            let guard = lock_inner(&inner)?;
+            let from = options.from.unwrap_or(0);
+            let to = options.to.unwrap_or(i64::MAX);
+            let mut points = Vec::new();
+            for (timestamp, key) in ts_keys(&guard, &series)? {
+                if timestamp < from || timestamp > to {
+                    continue;
+                }
+                let Some(point) = guard.kv_get_as_of(&key, None).map_err(to_napi_err)? else {
+                    continue;
+                };
+                let Some((value, tags)) = read_ts_point(point) else {
+                    continue;
+                };
+                let wanted = options
+                    .tags
+                    .as_ref()
+                    .is_none_or(|filter| filter.iter().all(|(k, v)| tags.get(k) == Some(v)));
+                if wanted {
+                    points.push((timestamp, value, tags));
+                }
+            }
+            let Some(aggregate) = aggregate else {
+                let out = points
+                    .into_iter()
+                    .map(|(timestamp, value, tags)| {
+                        serde_json::json!({ "timestamp": timestamp, "value": value, "tags": tags })
+                    })
+                    .collect();
+                return Ok(serde_json::Value::Array(out));
+            };
+            let mut buckets: Vec<TsBucket> = Vec::new();
+            for (timestamp, value, _) in points {
+                let start = match options.bucket {
+                    Some(width) => timestamp - timestamp.rem_euclid(width),
+                    None => from,
+                };
+                match buckets.last_mut() {
+                    Some(bucket) if bucket.start == start => bucket.add(value),
+                    _ => buckets.push(TsBucket::new(start, value)),
+                }
+            }
+            let out = buckets
+                .into_iter()
+                .map(|bucket| {
+                    serde_json::json!({
+                        "timestamp": bucket.start,
+                        "value": bucket.value(aggregate),
+                        "count": bucket.count,
+                    })
+                })
+                .collect();
+            Ok(serde_json::Value::Array(out))
         })
         .await
         .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
     }
 
     // =========================================================================
-    // Database Operations
+    // Collections
     // =========================================================================
 
-    /// Check database connectivity.
-    #[napi]
-    pub async fn ping(&self) -> napi::Result<String> {
+    /// Register a JSON Schema for the documents of collection `name` on the
+    /// current branch, replacing any earlier one. `collectionPut` checks
+    /// each document against it.
+    #[napi(js_name = "collectionDefine")]
+    pub async fn collection_define(
+        &self,
+        name: String,
+        schema: serde_json::Value,
+    ) -> napi::Result<()> {
         let inner = self.inner.clone();
-        tokio::task::spawn_blocking(move || {
+        check_collection_name(&name)?;
+        check_schema(&schema, "$")?;
+        let v = js_to_value_checked(schema, 0)?;
+        spawn_on(&self.pool, move || {
             let guard = lock_inner(&inner)?;
-            guard.ping().map_err(to_napi_err)
+            ensure_writable(&guard)?;
+            guard
+                .state_set(&collection_schema_cell(&name), v)
+                .map_err(to_napi_err)?;
+            Ok(())
         })
         .await
         .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
     }
 
-    /// Get database info.
-    #[napi]
-    pub async fn info(&self) -> napi::Result<serde_json::Value> {
+    /// The schema registered for collection `name`, or null.
+    #[napi(js_name = "collectionSchema")]
+    pub async fn collection_schema(&self, name: String) -> napi::Result<serde_json::Value> {
         let inner = self.inner.clone();
-        tokio::task::spawn_blocking(move || {
+        check_collection_name(&name)?;
+        spawn_on(&self.pool, move || {
             let guard = lock_inner(&inner)?;
-            let info = guard.info().map_err(to_napi_err)?;
-            Ok(serde_json::json!({
-                "version": info.version,
-                "uptimeSecs": info.uptime_secs,
-                "branchCount": info.branch_count,
-                "totalKeys": info.total_keys,
-            }))
+            Ok(collection_schema_of(&guard, &name)?.unwrap_or_default())
         })
         .await
         .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
     }
 
-    /// Get a structured snapshot of the database for agent introspection.
-    ///
-    /// Returns version, branch, spaces, follower status, per-primitive
-    /// summaries (counts, collections, graphs), configuration, and
-    /// capability flags — everything an agent needs to plan its actions.
-    #[napi]
-    pub async fn describe(&self) -> napi::Result<serde_json::Value> {
+    /// Write document `id` of collection `name`, the JSON document
+    /// `<name>/<id>`, once it passes the collection's schema, if it has one.
+    /// Returns the version.
+    #[napi(js_name = "collectionPut")]
+    pub async fn collection_put(
+        &self,
+        name: String,
+        id: String,
+        #[napi(ts_arg_type = "any")] value: JsValue,
+        json_limits: Option<JsJsonLimits>,
+    ) -> napi::Result<i64> {
         let inner = self.inner.clone();
-        tokio::task::spawn_blocking(move || {
-            let guard = lock_inner(&inner)?;
-            let result = guard.describe().map_err(to_napi_err)?;
-            serde_json::to_value(result)
-                .map_err(|e| napi::Error::from_reason(format!("Failed to serialize DescribeResult: {}", e)))
+        check_collection_name(&name)?;
+        if id.is_empty() {
+            return Err(napi::Error::from_reason(
+                "[VALIDATION] Document id must not be empty",
+            ));
+        }
+        let limits = self.json_limits.with(json_limits.as_ref())?;
+        limits.check_exact(&value)?;
+        let v = value.0;
+        limits.check(&v)?;
+        spawn_on(&self.pool, move || {
+            let guard = lock_inner(&inner)?;
+            ensure_writable(&guard)?;
+            let key = collection_doc_key(&name, &id);
+            if let Some(schema) = collection_schema_of(&guard, &name)? {
+                if let Some(problem) = schema_violation(&schema, &value_to_js(v.clone()), "$") {
+                    return Err(napi::Error::from_reason(format!(
+                        "[VALIDATION] Document '{}' does not match the schema of '{}': {}",
+                        id, name, problem
+                    )));
+                }
+            }
+            guard
+                .json_set(&key, "$", v)
+                .map(|n| n as i64)
+                .map_err(to_napi_err)
         })
         .await
         .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
     }
 
-    /// Flush writes to disk.
-    #[napi]
-    pub async fn flush(&self) -> napi::Result<()> {
+    // =========================================================================
+    // Conversation Memory
+    // =========================================================================
+
+    /// Append `turn`, an object with a string `content`, to the memory of
+    /// session `sessionId`: an event of type `_memory/<sessionId>`, with
+    /// `content` embedded into the `_memory` vector collection so
+    /// `memoryRecall` can find it. Returns the event's sequence number.
+    #[napi(js_name = "memoryAppend")]
+    pub async fn memory_append(
+        &self,
+        session_id: String,
+        turn: serde_json::Value,
+        json_limits: Option<JsJsonLimits>,
+    ) -> napi::Result<i64> {
         let inner = self.inner.clone();
-        tokio::task::spawn_blocking(move || {
-            let guard = lock_inner(&inner)?;
-            guard.flush().map_err(to_napi_err)
+        check_session_id(&session_id)?;
+        let Some(content) = turn.get("content").and_then(|c| c.as_str()) else {
+            return Err(napi::Error::from_reason(
+                "[VALIDATION] A memory turn must be an object with a string content",
+            ));
+        };
+        let content = content.to_string();
+        let limits = self.json_limits.with(json_limits.as_ref())?;
+        let v = js_to_value_checked(turn, 0)?;
+        limits.check(&v)?;
+        spawn_on(&self.pool, move || {
+            let guard = lock_inner(&inner)?;
+            ensure_writable(&guard)?;
+            let embedding = guard.embed(&content).map_err(to_napi_err)?;
+            let sequence = guard
+                .event_append(&memory_event_type(&session_id), v)
+                .map_err(to_napi_err)?;
+            let exists = guard
+                .vector_list_collections()
+                .map_err(to_napi_err)?
+                .iter()
+                .any(|c| c.name == MEMORY_COLLECTION);
+            if !exists {
+                let dimension = embedding.len() as u64;
+                guard
+                    .vector_create_collection(MEMORY_COLLECTION, dimension, DistanceMetric::Cosine)
+                    .map_err(to_napi_err)?;
+            }
+            let key = format!("{}/{:020}", session_id, sequence);
+            let meta = memory_metadata(&session_id, sequence);
+            guard
+                .vector_upsert(MEMORY_COLLECTION, &key, embedding, Some(meta))
+                .map_err(to_napi_err)?;
+            Ok(sequence as i64)
         })
         .await
         .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
     }
 
-    /// Trigger compaction.
-    #[napi]
-    pub async fn compact(&self) -> napi::Result<()> {
+    /// The `k` turns of session `sessionId` closest in meaning to `query`,
+    /// best first, as `{ sequence, timestamp, score, turn }`. The query is
+    /// embedded, searched among the session's turns, and the matches read
+    /// back from the event log in one call.
+    #[napi(js_name = "memoryRecall")]
+    pub async fn memory_recall(
+        &self,
+        session_id: String,
+        query: String,
+        k: u32,
+    ) -> napi::Result<JsOutput> {
         let inner = self.inner.clone();
-        tokio::task::spawn_blocking(move || {
+        let mode = self.value_mode;
+        check_session_id(&session_id)?;
+        spawn_on(&self.pool, move || {
             let guard = lock_inner(&inner)?;
-            guard.compact().map_err(to_napi_err)
+            let exists = guard
+                .vector_list_collections()
+                .map_err(to_napi_err)?
+                .iter()
+                .any(|c| c.name == MEMORY_COLLECTION);
+            if !exists || k == 0 {
+                return Ok(JsOutput(Value::Array(Box::new(Vec::new())), mode));
+            }
+            let embedding = guard.embed(&query).map_err(to_napi_err)?;
+            let filter = MetadataFilter {
+                field: "sessionId".to_string(),
+                op: FilterOp::Eq,
+                value: Value::String(session_id),
+            };
+            let matches = guard
+                .vector_search_with_filter(
+                    MEMORY_COLLECTION,
+                    embedding,
+                    k as u64,
+                    Some(vec![filter]),
+                    None,
+                    None,
+                )
+                .map_err(to_napi_err)?;
+            let mut out = Vec::new();
+            for m in matches {
+                let Some(sequence) = m.metadata.as_ref().and_then(memory_sequence) else {
+                    continue;
+                };
+                let Some(event) = guard.event_get_as_of(sequence, None).map_err(to_napi_err)?
+                else {
+                    continue;
+                };
+                let mut hit = HashMap::new();
+                hit.insert("sequence".to_string(), Value::Int(sequence as i64));
+                hit.insert("timestamp".to_string(), Value::Int(event.timestamp as i64));
+                hit.insert("score".to_string(), Value::Float(f64::from(m.score)));
+                hit.insert("turn".to_string(), event.value);
+                out.push(Value::Object(Box::new(hit)));
+            }
+            Ok(JsOutput(Value::Array(Box::new(out)), mode))
         })
         .await
         .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
     }
 
     // =========================================================================
-    // Bundle Operations
+    // Traces
     // =========================================================================
 
-    /// Export a branch to a bundle file.
-    #[napi(js_name = "branchExport")]
-    pub async fn branch_export(
+    /// Start the trace of run `runId` on the current branch, with optional
+    /// `metadata`. Fails if the run already has one.
+    #[napi(js_name = "traceStart")]
+    pub async fn trace_start(
         &self,
-        branch: String,
-        path: String,
-    ) -> napi::Result<serde_json::Value> {
+        run_id: String,
+        metadata: Option<serde_json::Value>,
+    ) -> napi::Result<i64> {
         let inner = self.inner.clone();
-        tokio::task::spawn_blocking(move || {
+        check_run_id(&run_id)?;
+        let metadata = match metadata {
+            Some(m) => js_to_value_checked(m, 0)?,
+            None => Value::Null,
+        };
+        spawn_on(&self.pool, move || {
             let guard = lock_inner(&inner)?;
-            let result = guard.branch_export(&branch, &path).map_err(to_napi_err)?;
-            Ok(branch_export_result_to_js(result))
+            ensure_writable(&guard)?;
+            let key = trace_run_key(&run_id);
+            let existing = guard.kv_get_as_of(&key, None).map_err(to_napi_err)?;
+            if existing.is_some() {
+                return Err(napi::Error::from_reason(format!(
+                    "[CONFLICT] Trace '{}' already started",
+                    run_id
+                )));
+            }
+            let mut run = HashMap::new();
+            run.insert("startedAt".to_string(), Value::Int(now_micros() as i64));
+            run.insert("metadata".to_string(), metadata);
+            guard
+                .kv_put(&key, Value::Object(Box::new(run)))
+                .map(|n| n as i64)
+                .map_err(to_napi_err)
         })
         .await
         .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
     }
 
-    /// Import a branch from a bundle file.
-    #[napi(js_name = "branchImport")]
-    pub async fn branch_import(&self, path: String) -> napi::Result<serde_json::Value> {
+    /// Record `span` in the trace of run `runId`: an object with string
+    /// `id` and `name`, and a `parentId` naming a span already recorded.
+    /// Recording an id again replaces that span. Returns the version.
+    #[napi(js_name = "traceRecord")]
+    pub async fn trace_record(
+        &self,
+        run_id: String,
+        span: serde_json::Value,
+        json_limits: Option<JsJsonLimits>,
+    ) -> napi::Result<i64> {
         let inner = self.inner.clone();
-        tokio::task::spawn_blocking(move || {
+        check_run_id(&run_id)?;
+        let (id, parent) = span_ids(&span)?;
+        let limits = self.json_limits.with(json_limits.as_ref())?;
+        let v = js_to_value_checked(span, 0)?;
+        limits.check(&v)?;
+        spawn_on(&self.pool, move || {
             let guard = lock_inner(&inner)?;
-            let result = guard.branch_import(&path).map_err(to_napi_err)?;
-            Ok(branch_import_result_to_js(result))
+            ensure_writable(&guard)?;
+            let run = guard
+                .kv_get_as_of(&trace_run_key(&run_id), None)
+                .map_err(to_napi_err)?;
+            if run.is_none() {
+                return Err(napi::Error::from_reason(format!(
+                    "[NOT_FOUND] Trace '{}' has not been started",
+                    run_id
+                )));
+            }
+            // Walk up from the parent, so a span can't become its own
+            // ancestor.
+            let mut ancestor = parent;
+            while let Some(a) = ancestor {
+                if a == id {
+                    return Err(napi::Error::from_reason(format!(
+                        "[VALIDATION] Span '{}' cannot be its own ancestor",
+                        id
+                    )));
+                }
+                let stored = guard
+                    .kv_get_as_of(&trace_span_key(&run_id, &a), None)
+                    .map_err(to_napi_err)?;
+                let Some(stored) = stored else {
+                    return Err(napi::Error::from_reason(format!(
+                        "[NOT_FOUND] Parent span '{}' is not in trace '{}'",
+                        a, run_id
+                    )));
+                };
+                ancestor = span_ids(&value_to_js(stored))?.1;
+            }
+            guard
+                .kv_put(&trace_span_key(&run_id, &id), v)
+                .map(|n| n as i64)
+                .map_err(to_napi_err)
         })
         .await
         .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
     }
 
-    /// Validate a bundle file without importing.
-    #[napi(js_name = "branchValidateBundle")]
-    pub async fn branch_validate_bundle(&self, path: String) -> napi::Result<serde_json::Value> {
+    /// The trace of run `runId` as `{ runId, startedAt, metadata, spans }`,
+    /// or null if it was never started. `spans` holds the root spans, each
+    /// with its `children`, ordered by `startTime` and then id.
+    #[napi(js_name = "traceGet", ts_return_type = "Promise<any>")]
+    pub async fn trace_get(&self, run_id: String) -> napi::Result<JsOutput> {
         let inner = self.inner.clone();
-        tokio::task::spawn_blocking(move || {
+        let mode = self.value_mode;
+        check_run_id(&run_id)?;
+        spawn_on(&self.pool, move || {
             let guard = lock_inner(&inner)?;
-            let result = guard.branch_validate_bundle(&path).map_err(to_napi_err)?;
-            Ok(bundle_validate_result_to_js(result))
+            let run = guard
+                .kv_get_as_of(&trace_run_key(&run_id), None)
+                .map_err(to_napi_err)?;
+            let Some(Value::Object(mut run)) = run else {
+                return Ok(JsOutput(Value::Null, mode));
+            };
+            let prefix = format!("{}/", trace_run_key(&run_id));
+            let keys = guard
+                .kv_list_as_of(Some(prefix.as_str()), None, None, None)
+                .map_err(to_napi_err)?;
+            let mut spans = Vec::with_capacity(keys.len());
+            for key in keys {
+                if let Some(span) = guard.kv_get_as_of(&key, None).map_err(to_napi_err)? {
+                    spans.push(span);
+                }
+            }
+            run.insert("runId".to_string(), Value::String(run_id));
+            run.insert("spans".to_string(), span_tree(spans));
+            Ok(JsOutput(Value::Object(run), mode))
         })
         .await
         .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
     }
 
     // =========================================================================
-    // Transaction Operations
+    // Event Log
     // =========================================================================
 
-    /// Begin a new transaction.
-    #[napi(js_name = "begin")]
-    pub async fn begin(&self, read_only: Option<bool>) -> napi::Result<()> {
+    /// Append an event to the log.
+    #[napi(js_name = "eventAppend")]
+    pub async fn event_append(
+        &self,
+        event_type: String,
+        #[napi(ts_arg_type = "any")] payload: JsValue,
+        json_limits: Option<JsJsonLimits>,
+    ) -> napi::Result<i64> {
         let inner = self.inner.clone();
-        let session_arc = self.session.clone();
-        tokio::task::spawn_blocking(move || {
-            let mut session_ref = lock_session(&session_arc)?;
-            if session_ref.is_none() {
-                let guard = lock_inner(&inner)?;
-                *session_ref = Some(guard.session());
-            }
-            let session = session_ref.as_mut().unwrap();
-            let cmd = Command::TxnBegin {
-                branch: None,
-                options: Some(TxnOptions {
-                    read_only: read_only.unwrap_or(false),
-                }),
-            };
-            session.execute(cmd).map_err(to_napi_err)?;
-            Ok(())
+        let limits = self.json_limits.with(json_limits.as_ref())?;
+        limits.check_exact(&payload)?;
+        let v = payload.0;
+        limits.check(&v)?;
+        spawn_on(&self.pool, move || {
+            let guard = lock_inner(&inner)?;
+            ensure_writable(&guard)?;
+            guard
+                .event_append(&event_type, v)
+                .map(|n| n as i64)
+                .map_err(to_napi_err)
         })
         .await
         .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
     }
 
-    /// Commit the current transaction.
-    #[napi]
-    pub async fn commit(&self) -> napi::Result<i64> {
-        let session_arc = self.session.clone();
-        tokio::task::spawn_blocking(move || {
-            let mut session_ref = lock_session(&session_arc)?;
-            let session = session_ref
-                .as_mut()
-                .ok_or_else(|| napi::Error::from_reason("[STATE] No transaction active"))?;
-            match session.execute(Command::TxnCommit).map_err(to_napi_err)? {
-                Output::TxnCommitted { version } => Ok(version as i64),
-                _ => Err(napi::Error::from_reason("Unexpected output for TxnCommit")),
+    /// Get an event by sequence number. Optionally pass `asOf` for time-travel.
+    #[napi(js_name = "eventGet")]
+    pub async fn event_get(
+        &self,
+        sequence: i64,
+        as_of: Option<Either<i64, String>>,
+    ) -> napi::Result<serde_json::Value> {
+        let inner = self.inner.clone();
+        spawn_on(&self.pool, move || {
+            let guard = lock_inner(&inner)?;
+            let as_of_u64 = resolve_as_of(&guard, as_of)?;
+            match guard
+                .event_get_as_of(sequence as u64, as_of_u64)
+                .map_err(to_napi_err)?
+            {
+                Some(vv) => Ok(versioned_to_js(vv)),
+                None => Ok(serde_json::Value::Null),
             }
         })
         .await
         .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
     }
 
-    /// Rollback the current transaction.
-    #[napi]
-    pub async fn rollback(&self) -> napi::Result<()> {
-        let session_arc = self.session.clone();
-        tokio::task::spawn_blocking(move || {
-            let mut session_ref = lock_session(&session_arc)?;
-            let session = session_ref
-                .as_mut()
-                .ok_or_else(|| napi::Error::from_reason("[STATE] No transaction active"))?;
-            session.execute(Command::TxnRollback).map_err(to_napi_err)?;
-            Ok(())
+    /// List events by type. Optionally pass `asOf` for time-travel.
+    #[napi(js_name = "eventList")]
+    pub async fn event_list(
+        &self,
+        event_type: String,
+        as_of: Option<Either<i64, String>>,
+    ) -> napi::Result<serde_json::Value> {
+        let inner = self.inner.clone();
+        spawn_on(&self.pool, move || {
+            let guard = lock_inner(&inner)?;
+            let as_of_u64 = resolve_as_of(&guard, as_of)?;
+            let events = guard
+                .event_get_by_type_with_options(&event_type, None, None, as_of_u64)
+                .map_err(to_napi_err)?;
+            let arr: Vec<serde_json::Value> =
+                events.into_iter().map(versioned_to_js).collect();
+            Ok(serde_json::Value::Array(arr))
         })
         .await
         .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
     }
 
-    /// Get current transaction info.
-    #[napi(js_name = "txnInfo")]
-    pub async fn txn_info(&self) -> napi::Result<serde_json::Value> {
-        let session_arc = self.session.clone();
-        tokio::task::spawn_blocking(move || {
-            let mut session_ref = lock_session(&session_arc)?;
-            if session_ref.is_none() {
-                return Ok(serde_json::Value::Null);
-            }
-            let session = session_ref.as_mut().unwrap();
-            match session.execute(Command::TxnInfo).map_err(to_napi_err)? {
-                Output::TxnInfo(Some(info)) => Ok(serde_json::json!({
-                    "id": info.id,
-                    "status": format!("{:?}", info.status).to_lowercase(),
-                    "startedAt": info.started_at,
-                })),
-                Output::TxnInfo(None) => Ok(serde_json::Value::Null),
-                _ => Err(napi::Error::from_reason("Unexpected output for TxnInfo")),
-            }
-        })
-        .await
-        .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
-    }
-
-    /// Check if a transaction is currently active.
-    #[napi(js_name = "txnIsActive")]
-    pub async fn txn_is_active(&self) -> napi::Result<bool> {
-        let session_arc = self.session.clone();
-        tokio::task::spawn_blocking(move || {
-            let mut session_ref = lock_session(&session_arc)?;
-            if session_ref.is_none() {
-                return Ok(false);
-            }
-            let session = session_ref.as_mut().unwrap();
-            match session.execute(Command::TxnIsActive).map_err(to_napi_err)? {
-                Output::Bool(active) => Ok(active),
-                _ => Err(napi::Error::from_reason(
-                    "Unexpected output for TxnIsActive",
-                )),
-            }
+    /// Get total event count.
+    #[napi(js_name = "eventLen")]
+    pub async fn event_len(&self) -> napi::Result<i64> {
+        let inner = self.inner.clone();
+        spawn_on(&self.pool, move || {
+            let guard = lock_inner(&inner)?;
+            guard.event_len().map(|n| n as i64).map_err(to_napi_err)
         })
         .await
         .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
     }
 
     // =========================================================================
-    // State Operations
+    // JSON Store
     // =========================================================================
 
-    /// Delete a state cell.
-    #[napi(js_name = "stateDelete")]
-    pub async fn state_delete(&self, cell: String) -> napi::Result<bool> {
-        let inner = self.inner.clone();
-        tokio::task::spawn_blocking(move || {
-            let guard = lock_inner(&inner)?;
-            guard.state_delete(&cell).map_err(to_napi_err)
-        })
-        .await
-        .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
-    }
-
-    /// List state cell names with optional prefix filter. Optionally pass `asOf` for time-travel.
-    #[napi(js_name = "stateList")]
-    pub async fn state_list(
+    /// Set a value at a JSONPath.
+    #[napi(js_name = "jsonSet")]
+    pub async fn json_set(
         &self,
-        prefix: Option<String>,
-        as_of: Option<i64>,
-    ) -> napi::Result<Vec<String>> {
+        key: String,
+        path: String,
+        #[napi(ts_arg_type = "any")] value: JsValue,
+        json_limits: Option<JsJsonLimits>,
+    ) -> napi::Result<i64> {
         let inner = self.inner.clone();
-        let as_of_u64 = as_of.map(|t| t as u64);
-        tokio::task::spawn_blocking(move || {
+        let limits = self.json_limits.with(json_limits.as_ref())?;
+        limits.check_exact(&value)?;
+        let v = value.0;
+        limits.check(&v)?;
+        spawn_on(&self.pool, move || {
             let guard = lock_inner(&inner)?;
+            ensure_writable(&guard)?;
             guard
-                .state_list_as_of(prefix.as_deref(), as_of_u64)
+                .json_set(&key, &path, v)
+                .map(|n| n as i64)
                 .map_err(to_napi_err)
         })
         .await
         .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
     }
 
-    // =========================================================================
-    // Versioned Getters
-    // =========================================================================
-
-    /// Get a value by key with version info.
-    #[napi(js_name = "kvGetVersioned")]
-    pub async fn kv_get_versioned(&self, key: String) -> napi::Result<serde_json::Value> {
-        let inner = self.inner.clone();
-        tokio::task::spawn_blocking(move || {
-            let guard = lock_inner(&inner)?;
-            match guard.kv_getv(&key).map_err(to_napi_err)? {
-                Some(versions) if !versions.is_empty() => {
-                    Ok(versioned_to_js(versions.into_iter().next().unwrap()))
+    /// Get a value at a JSONPath. Optionally pass `asOf` or `atVersion` for
+    /// time-travel.
+    #[napi(js_name = "jsonGet", ts_return_type = "Promise<any>")]
+    pub async fn json_get(
+        &self,
+        key: String,
+        path: String,
+        as_of: Option<Either<i64, String>>,
+        at_version: Option<i64>,
+    ) -> napi::Result<JsOutput> {
+        let inner = self.inner.clone();
+        let mode = self.value_mode;
+        let at_version = check_at_version(&as_of, at_version)?;
+        spawn_on(&self.pool, move || {
+            let guard = lock_inner(&inner)?;
+            let as_of_u64 = match at_version {
+                Some(v) => {
+                    match version_timestamp(guard.json_getv(&key).map_err(to_napi_err)?, v) {
+                        Some(ts) => Some(ts),
+                        None => return Ok(JsOutput(Value::Null, mode)),
+                    }
                 }
-                _ => Ok(serde_json::Value::Null),
-            }
+                None => resolve_as_of(&guard, as_of)?,
+            };
+            let value = guard
+                .json_get_as_of(&key, &path, as_of_u64)
+                .map_err(to_napi_err)?;
+            Ok(JsOutput(value.unwrap_or(Value::Null), mode))
         })
         .await
         .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
     }
 
-    /// Get a state cell value with version info.
-    #[napi(js_name = "stateGetVersioned")]
-    pub async fn state_get_versioned(&self, cell: String) -> napi::Result<serde_json::Value> {
+    /// Delete a JSON document.
+    #[napi(js_name = "jsonDelete")]
+    pub async fn json_delete(&self, key: String, path: String) -> napi::Result<i64> {
         let inner = self.inner.clone();
-        tokio::task::spawn_blocking(move || {
+        spawn_on(&self.pool, move || {
             let guard = lock_inner(&inner)?;
-            match guard.state_getv(&cell).map_err(to_napi_err)? {
-                Some(versions) if !versions.is_empty() => {
-                    Ok(versioned_to_js(versions.into_iter().next().unwrap()))
-                }
-                _ => Ok(serde_json::Value::Null),
-            }
+            ensure_writable(&guard)?;
+            guard
+                .json_delete(&key, &path)
+                .map(|n| n as i64)
+                .map_err(to_napi_err)
         })
         .await
         .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
     }
 
-    /// Get a JSON document value with version info.
-    #[napi(js_name = "jsonGetVersioned")]
-    pub async fn json_get_versioned(&self, key: String) -> napi::Result<serde_json::Value> {
+    /// Get version history for a JSON document.
+    ///
+    /// With `options`, returns one page as `{ versions, cursor }`; pass
+    /// `cursor` back to continue.
+    #[napi(js_name = "jsonHistory")]
+    pub async fn json_history(
+        &self,
+        key: String,
+        options: Option<JsHistoryOptions>,
+    ) -> napi::Result<serde_json::Value> {
         let inner = self.inner.clone();
-        tokio::task::spawn_blocking(move || {
+        spawn_on(&self.pool, move || {
             let guard = lock_inner(&inner)?;
-            match guard.json_getv(&key).map_err(to_napi_err)? {
-                Some(versions) if !versions.is_empty() => {
-                    Ok(versioned_to_js(versions.into_iter().next().unwrap()))
+            match (guard.json_getv(&key).map_err(to_napi_err)?, options) {
+                (Some(versions), Some(options)) => history_page(&guard, versions, options),
+                (Some(versions), None) => {
+                    let arr: Vec<serde_json::Value> =
+                        versions.into_iter().map(versioned_to_js).collect();
+                    Ok(serde_json::Value::Array(arr))
                 }
-                _ => Ok(serde_json::Value::Null),
+                (None, _) => Ok(serde_json::Value::Null),
             }
         })
         .await
         .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
     }
 
-    // =========================================================================
-    // Pagination
-    // =========================================================================
-
-    /// List keys with pagination support. Optionally pass `asOf` for time-travel.
-    #[napi(js_name = "kvListPaginated")]
-    pub async fn kv_list_paginated(
+    /// List JSON document keys. Optionally pass `asOf` for time-travel.
+    ///
+    /// With `spaces`, up to `limit` keys are listed from each selected space
+    /// and returned as `{ space, key }` objects; `cursor` is not supported.
+    #[napi(js_name = "jsonList")]
+    pub async fn json_list(
         &self,
+        limit: u32,
         prefix: Option<String>,
-        limit: Option<u32>,
-        as_of: Option<i64>,
+        cursor: Option<String>,
+        as_of: Option<Either<i64, String>>,
+        spaces: Option<Either<Vec<String>, String>>,
     ) -> napi::Result<serde_json::Value> {
         let inner = self.inner.clone();
-        let as_of_u64 = as_of.map(|t| t as u64);
-        tokio::task::spawn_blocking(move || {
-            let guard = lock_inner(&inner)?;
-            let keys = guard
-                .kv_list_as_of(prefix.as_deref(), None, limit.map(|l| l as u64), as_of_u64)
+        if spaces.is_some() && cursor.is_some() {
+            return Err(napi::Error::from_reason(
+                "[VALIDATION] cursor cannot be combined with spaces",
+            ));
+        }
+        spawn_on(&self.pool, move || {
+            let guard = lock_inner(&inner)?;
+            let as_of_u64 = resolve_as_of(&guard, as_of)?;
+            if let Some(sel) = spaces {
+                let selected = select_spaces(&guard, sel)?;
+                let pages = across_spaces(&guard, &selected, |db| {
+                    db.json_list_as_of(prefix.clone(), None, limit as u64, as_of_u64)
+                        .map_err(to_napi_err)
+                })?;
+                let has_more = pages.iter().any(|(_, (_, next))| next.is_some());
+                let keys: Vec<serde_json::Value> = pages
+                    .into_iter()
+                    .flat_map(|(space, (keys, _))| {
+                        keys.into_iter().map(
+                            move |key| serde_json::json!({ "space": space.clone(), "key": key }),
+                        )
+                    })
+                    .collect();
+                return Ok(serde_json::json!({
+                    "keys": keys,
+                    "cursor": null,
+                    "hasMore": has_more,
+                }));
+            }
+            let (keys, next_cursor) = guard
+                .json_list_as_of(prefix, cursor, limit as u64, as_of_u64)
                 .map_err(to_napi_err)?;
-            // kv_list_as_of returns a flat Vec<String>; when limit is set,
-            // has_more is inferred from whether we got exactly limit items.
-            let has_more = limit.map_or(false, |l| keys.len() == l as usize);
+            let has_more = next_cursor.is_some();
             Ok(serde_json::json!({
                 "keys": keys,
+                "cursor": next_cursor,
                 "hasMore": has_more,
-                "cursor": serde_json::Value::Null,
             }))
         })
         .await
         .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
     }
 
-    /// List events by type with pagination support. Optionally pass `asOf` for time-travel.
-    #[napi(js_name = "eventListPaginated")]
-    pub async fn event_list_paginated(
+    /// The JSON documents matching every filter, as `{ key, value }` in key
+    /// order. Filters take the `{ field, op, value }` form of
+    /// `vectorSearchFiltered`, with a dotted path into the document as
+    /// `field`; a document without the field doesn't match.
+    #[napi(js_name = "jsonQuery", ts_return_type = "Promise<any[]>")]
+    pub async fn json_query(
         &self,
-        event_type: String,
-        limit: Option<u32>,
-        after: Option<i64>,
-        as_of: Option<i64>,
-    ) -> napi::Result<serde_json::Value> {
-        let inner = self.inner.clone();
-        let as_of_u64 = as_of.map(|t| t as u64);
-        tokio::task::spawn_blocking(move || {
-            let guard = lock_inner(&inner)?;
-            let events = guard
-                .event_get_by_type_with_options(
-                    &event_type,
-                    limit.map(|l| l as u64),
-                    after.map(|a| a as u64),
-                    as_of_u64,
-                )
-                .map_err(to_napi_err)?;
-            let arr: Vec<serde_json::Value> =
-                events.into_iter().map(versioned_to_js).collect();
-            Ok(serde_json::Value::Array(arr))
+        filter: Vec<serde_json::Value>,
+        options: Option<JsJsonQueryOptions>,
+    ) -> napi::Result<JsOutput> {
+        let inner = self.inner.clone();
+        let mode = self.value_mode;
+        let options = options.unwrap_or(JsJsonQueryOptions {
+            prefix: None,
+            limit: None,
+            as_of: None,
+        });
+        let filters = filter
+            .into_iter()
+            .map(|item| {
+                let (field, op, value) = parse_filter(item)?;
+                QueryFilter::new(field, &op, value)
+            })
+            .collect::<napi::Result<Vec<_>>>()?;
+        let limit = options.limit.map_or(usize::MAX, |l| l as usize);
+        spawn_on(&self.pool, move || {
+            let guard = lock_inner(&inner)?;
+            let as_of = resolve_as_of(&guard, options.as_of)?;
+            let mut out = Vec::new();
+            let mut cursor = None;
+            while out.len() < limit {
+                let (keys, next) = guard
+                    .json_list_as_of(options.prefix.clone(), cursor, JSON_LIST_PAGE, as_of)
+                    .map_err(to_napi_err)?;
+                for key in keys {
+                    let Some(doc) = guard
+                        .json_get_as_of(&key, "$", as_of)
+                        .map_err(to_napi_err)?
+                    else {
+                        continue;
+                    };
+                    let plain = value_to_js(doc.clone());
+                    if out.len() < limit && filters.iter().all(|f| f.matches(&plain)) {
+                        let mut hit = HashMap::new();
+                        hit.insert("key".to_string(), Value::String(key));
+                        hit.insert("value".to_string(), doc);
+                        out.push(Value::Object(Box::new(hit)));
+                    }
+                }
+                match next {
+                    Some(c) => cursor = Some(c),
+                    None => break,
+                }
+            }
+            Ok(JsOutput(Value::Array(Box::new(out)), mode))
         })
         .await
         .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
     }
 
     // =========================================================================
-    // Enhanced Vector Search
+    // Vector Store
     // =========================================================================
 
-    /// Search for similar vectors with optional filter and metric override.
-    /// Optionally pass `asOf` for time-travel.
-    #[napi(js_name = "vectorSearchFiltered")]
-    pub async fn vector_search_filtered(
+    /// Create a vector collection.
+    #[napi(js_name = "vectorCreateCollection")]
+    pub async fn vector_create_collection(
         &self,
         collection: String,
-        query: Vec<f64>,
-        k: u32,
-        metric: Option<String>,
-        filter: Option<Vec<serde_json::Value>>,
-        as_of: Option<i64>,
-    ) -> napi::Result<serde_json::Value> {
+        dimension: u32,
+        #[napi(ts_arg_type = "DistanceMetric | `${DistanceMetric}`")] metric: Option<String>,
+    ) -> napi::Result<i64> {
         let inner = self.inner.clone();
-        let vec = validate_vector(&query)?;
-
-        let metric_enum = match metric.as_deref() {
-            Some("cosine") => Some(DistanceMetric::Cosine),
-            Some("euclidean") => Some(DistanceMetric::Euclidean),
-            Some("dot_product") | Some("dotproduct") => Some(DistanceMetric::DotProduct),
-            Some(m) => {
-                return Err(napi::Error::from_reason(format!(
-                    "[VALIDATION] Invalid metric: {}",
-                    m
-                )))
-            }
-            None => None,
-        };
-
-        let as_of_u64 = as_of.map(|t| t as u64);
-
-        let filter_vec = match filter {
-            Some(arr) => {
-                let mut filters = Vec::new();
-                for item in arr {
-                    let obj = item.as_object().ok_or_else(|| {
-                        napi::Error::from_reason("[VALIDATION] Filter must be an object")
-                    })?;
-                    let field = obj
-                        .get("field")
-                        .and_then(|f| f.as_str())
-                        .ok_or_else(|| {
-                            napi::Error::from_reason("[VALIDATION] Filter missing 'field'")
-                        })?
-                        .to_string();
-                    let op_str =
-                        obj.get("op").and_then(|o| o.as_str()).ok_or_else(|| {
-                            napi::Error::from_reason("[VALIDATION] Filter missing 'op'")
-                        })?;
-                    let op = match op_str {
-                        "eq" => FilterOp::Eq,
-                        "ne" => FilterOp::Ne,
-                        "gt" => FilterOp::Gt,
-                        "gte" => FilterOp::Gte,
-                        "lt" => FilterOp::Lt,
-                        "lte" => FilterOp::Lte,
-                        "in" => FilterOp::In,
-                        "contains" => FilterOp::Contains,
-                        _ => {
-                            return Err(napi::Error::from_reason(format!(
-                                "[VALIDATION] Invalid filter op: {}",
-                                op_str
-                            )))
-                        }
-                    };
-                    let value_json = obj.get("value").ok_or_else(|| {
-                        napi::Error::from_reason("[VALIDATION] Filter missing 'value'")
-                    })?.clone();
-                    let value = js_to_value_checked(value_json, 0)?;
-                    filters.push(MetadataFilter { field, op, value });
-                }
-                Some(filters)
-            }
-            None => None,
+        let m = match metric.as_deref().unwrap_or("cosine") {
+            "cosine" => DistanceMetric::Cosine,
+            "euclidean" => DistanceMetric::Euclidean,
+            "dot_product" | "dotproduct" => DistanceMetric::DotProduct,
+            _ => return Err(napi::Error::from_reason("[VALIDATION] Invalid metric")),
         };
-
-        tokio::task::spawn_blocking(move || {
+        spawn_on(&self.pool, move || {
             let guard = lock_inner(&inner)?;
-            let matches = guard
-                .vector_search_with_filter(
-                    &collection,
-                    vec,
-                    k as u64,
-                    filter_vec,
-                    metric_enum,
-                    as_of_u64,
-                )
-                .map_err(to_napi_err)?;
-            let arr: Vec<serde_json::Value> = matches
-                .into_iter()
-                .map(|m| {
-                    serde_json::json!({
-                        "key": m.key,
-                        "score": m.score,
-                        "metadata": m.metadata.map(value_to_js),
-                    })
-                })
-                .collect();
-            Ok(serde_json::Value::Array(arr))
+            ensure_writable(&guard)?;
+            guard
+                .vector_create_collection(&collection, dimension as u64, m)
+                .map(|n| n as i64)
+                .map_err(to_napi_err)
         })
         .await
         .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
     }
 
-    // =========================================================================
-    // Space Operations
-    // =========================================================================
-
-    /// Create a new space explicitly.
-    #[napi(js_name = "spaceCreate")]
-    pub async fn space_create(&self, space: String) -> napi::Result<()> {
+    /// Delete a vector collection.
+    #[napi(js_name = "vectorDeleteCollection")]
+    pub async fn vector_delete_collection(&self, collection: String) -> napi::Result<bool> {
         let inner = self.inner.clone();
-        tokio::task::spawn_blocking(move || {
+        spawn_on(&self.pool, move || {
             let guard = lock_inner(&inner)?;
-            guard.space_create(&space).map_err(to_napi_err)
+            ensure_writable(&guard)?;
+            guard
+                .vector_delete_collection(&collection)
+                .map_err(to_napi_err)
         })
         .await
         .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
     }
 
-    /// Check if a space exists in the current branch.
-    #[napi(js_name = "spaceExists")]
-    pub async fn space_exists(&self, space: String) -> napi::Result<bool> {
+    /// List vector collections.
+    #[napi(js_name = "vectorListCollections")]
+    pub async fn vector_list_collections(&self) -> napi::Result<serde_json::Value> {
         let inner = self.inner.clone();
-        tokio::task::spawn_blocking(move || {
+        spawn_on(&self.pool, move || {
             let guard = lock_inner(&inner)?;
-            guard.space_exists(&space).map_err(to_napi_err)
-        })
+            let collections = guard.vector_list_collections().map_err(to_napi_err)?;
+            let arr: Vec<serde_json::Value> =
+                collections.into_iter().map(collection_info_to_js).collect();
+            Ok(serde_json::Value::Array(arr))
+        })
         .await
         .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
     }
 
-    // =========================================================================
-    // Configuration
-    // =========================================================================
-
-    /// Get the current database configuration.
-    ///
-    /// Returns an object with `durability`, `autoEmbed`, and optional `model`.
-    #[napi]
-    pub async fn config(&self) -> napi::Result<serde_json::Value> {
+    /// Insert or update a vector.
+    #[napi(js_name = "vectorUpsert")]
+    pub async fn vector_upsert(
+        &self,
+        collection: String,
+        key: String,
+        vector: Vec<f64>,
+        metadata: Option<serde_json::Value>,
+    ) -> napi::Result<i64> {
         let inner = self.inner.clone();
-        tokio::task::spawn_blocking(move || {
+        let vec = validate_vector(&vector)?;
+        let meta = match metadata {
+            Some(m) => Some(js_to_value_checked(m, 0)?),
+            None => None,
+        };
+        spawn_on(&self.pool, move || {
             let guard = lock_inner(&inner)?;
-            let cfg = guard.config().map_err(to_napi_err)?;
-            let mut obj = serde_json::Map::new();
-            obj.insert("durability".into(), serde_json::Value::String(cfg.durability));
-            obj.insert("autoEmbed".into(), serde_json::Value::Bool(cfg.auto_embed));
-            if let Some(model) = cfg.model {
-                let mut m = serde_json::Map::new();
-                m.insert("endpoint".into(), serde_json::Value::String(model.endpoint));
-                m.insert("model".into(), serde_json::Value::String(model.model));
-                m.insert(
-                    "apiKey".into(),
-                    model
-                        .api_key
-                        .map(|s| serde_json::Value::String(s.to_string()))
-                        .unwrap_or(serde_json::Value::Null),
-                );
-                m.insert("timeoutMs".into(), serde_json::Value::Number(model.timeout_ms.into()));
-                obj.insert("model".into(), serde_json::Value::Object(m));
-            } else {
-                obj.insert("model".into(), serde_json::Value::Null);
-            }
-            Ok(serde_json::Value::Object(obj))
+            ensure_writable(&guard)?;
+            guard
+                .vector_upsert(&collection, &key, vec, meta)
+                .map(|n| n as i64)
+                .map_err(to_napi_err)
         })
         .await
         .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
     }
 
-    /// Check whether auto-embedding is enabled.
-    #[napi(js_name = "autoEmbedEnabled")]
-    pub async fn auto_embed_enabled(&self) -> napi::Result<bool> {
+    /// Get a vector by key. Optionally pass `asOf` for time-travel.
+    #[napi(js_name = "vectorGet")]
+    pub async fn vector_get(
+        &self,
+        collection: String,
+        key: String,
+        as_of: Option<Either<i64, String>>,
+    ) -> napi::Result<serde_json::Value> {
         let inner = self.inner.clone();
-        tokio::task::spawn_blocking(move || {
+        spawn_on(&self.pool, move || {
             let guard = lock_inner(&inner)?;
-            guard.auto_embed_enabled().map_err(to_napi_err)
+            let as_of_u64 = resolve_as_of(&guard, as_of)?;
+            match guard
+                .vector_get_as_of(&collection, &key, as_of_u64)
+                .map_err(to_napi_err)?
+            {
+                Some(vd) => {
+                    let embedding: Vec<f64> =
+                        vd.data.embedding.iter().map(|&f| f as f64).collect();
+                    Ok(serde_json::json!({
+                        "key": vd.key,
+                        "embedding": embedding,
+                        "metadata": vd.data.metadata.map(value_to_js),
+                        "version": vd.version,
+                        "timestamp": vd.timestamp,
+                    }))
+                }
+                None => Ok(serde_json::Value::Null),
+            }
         })
         .await
         .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
     }
 
-    /// Enable or disable auto-embedding of text values.
-    ///
-    /// Persisted to strata.toml for disk-backed databases.
-    #[napi(js_name = "setAutoEmbed")]
-    pub async fn set_auto_embed(&self, enabled: bool) -> napi::Result<()> {
+    /// Delete a vector.
+    #[napi(js_name = "vectorDelete")]
+    pub async fn vector_delete(&self, collection: String, key: String) -> napi::Result<bool> {
         let inner = self.inner.clone();
-        tokio::task::spawn_blocking(move || {
+        spawn_on(&self.pool, move || {
             let guard = lock_inner(&inner)?;
-            guard.set_auto_embed(enabled).map_err(to_napi_err)
+            ensure_writable(&guard)?;
+            guard.vector_delete(&collection, &key).map_err(to_napi_err)
         })
         .await
         .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
     }
 
-    /// Configure an inference model endpoint for intelligent search.
-    ///
-    /// When a model is configured, `search()` transparently expands queries
-    /// using the model for better recall. Search works identically without a model.
-    /// Persisted to strata.toml.
-    #[napi(js_name = "configureModel")]
-    pub async fn configure_model(
+    /// Search for similar vectors. Optionally pass `asOf` for time-travel.
+    #[napi(js_name = "vectorSearch")]
+    pub async fn vector_search(
         &self,
-        endpoint: String,
-        model: String,
-        api_key: Option<String>,
-        timeout_ms: Option<u32>,
-    ) -> napi::Result<()> {
+        collection: String,
+        query: Vec<f64>,
+        k: u32,
+        as_of: Option<Either<i64, String>>,
+        operation_id: Option<u32>,
+    ) -> napi::Result<serde_json::Value> {
         let inner = self.inner.clone();
-        tokio::task::spawn_blocking(move || {
+        let vec = validate_vector(&query)?;
+        let op = CancellableOp::start(operation_id);
+        spawn_on(&self.pool, move || {
+            op.check()?;
             let guard = lock_inner(&inner)?;
-            guard
-                .configure_model(
-                    &endpoint,
-                    &model,
-                    api_key.as_deref(),
-                    timeout_ms.map(|ms| ms as u64),
-                )
-                .map_err(to_napi_err)
+            let as_of_u64 = resolve_as_of(&guard, as_of)?;
+            let matches = guard
+                .vector_search_with_filter(&collection, vec, k as u64, None, None, as_of_u64)
+                .map_err(to_napi_err)?;
+            let arr: Vec<serde_json::Value> = matches
+                .into_iter()
+                .map(|m| {
+                    serde_json::json!({
+                        "key": m.key,
+                        "score": m.score,
+                        "metadata": m.metadata.map(value_to_js),
+                    })
+                })
+                .collect();
+            Ok(serde_json::Value::Array(arr))
         })
         .await
         .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
     }
 
-    // =========================================================================
-    // Search
-    // =========================================================================
-
-    /// Search across multiple primitives for matching content.
-    #[napi]
-    pub async fn search(
+    /// Get statistics for a single collection.
+    #[napi(js_name = "vectorCollectionStats")]
+    pub async fn vector_collection_stats(
         &self,
-        query: String,
-        options: Option<JsSearchOptions>,
+        collection: String,
     ) -> napi::Result<serde_json::Value> {
         let inner = self.inner.clone();
-        tokio::task::spawn_blocking(move || {
+        spawn_on(&self.pool, move || {
             let guard = lock_inner(&inner)?;
+            let info = guard
+                .vector_collection_stats(&collection)
+                .map_err(to_napi_err)?;
+            Ok(collection_info_to_js(info))
+        })
+        .await
+        .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
+    }
 
-            let (k, primitives, time_range, mode, expand, rerank) = match options {
-                Some(opts) => (
-                    opts.k,
-                    opts.primitives,
-                    opts.time_range.map(|tr| TimeRangeInput {
-                        start: tr.start,
-                        end: tr.end,
-                    }),
-                    opts.mode,
-                    opts.expand,
-                    opts.rerank,
-                ),
-                None => (None, None, None, None, None, None),
-            };
-
-            let sq = SearchQuery {
-                query,
-                k: k.map(|n| n as u64),
-                primitives,
-                time_range,
-                mode,
-                expand,
-                rerank,
-                precomputed_embedding: None,
-            };
-
-            let (hits, _stats) = guard.search(sq).map_err(to_napi_err)?;
-            let arr: Vec<serde_json::Value> = hits
-                .into_iter()
-                .map(|hit| {
-                    serde_json::json!({
-                        "entity": hit.entity,
-                        "primitive": hit.primitive,
-                        "score": hit.score,
-                        "rank": hit.rank,
-                        "snippet": hit.snippet,
+    /// Batch insert/update multiple vectors.
+    #[napi(js_name = "vectorBatchUpsert")]
+    pub async fn vector_batch_upsert(
+        &self,
+        collection: String,
+        vectors: Vec<serde_json::Value>,
+    ) -> napi::Result<Vec<i64>> {
+        let inner = self.inner.clone();
+        // Parse and validate all entries on the JS thread before spawning.
+        let batch: Vec<BatchVectorEntry> = vectors
+            .into_iter()
+            .map(|v| {
+                let obj = v
+                    .as_object()
+                    .ok_or_else(|| napi::Error::from_reason("[VALIDATION] Expected object"))?;
+                let key = obj
+                    .get("key")
+                    .and_then(|k| k.as_str())
+                    .ok_or_else(|| napi::Error::from_reason("[VALIDATION] Missing 'key'"))?
+                    .to_string();
+                let raw_vec: Vec<f64> = obj
+                    .get("vector")
+                    .and_then(|v| v.as_array())
+                    .ok_or_else(|| napi::Error::from_reason("[VALIDATION] Missing 'vector'"))?
+                    .iter()
+                    .map(|n| {
+                        n.as_f64().ok_or_else(|| {
+                            napi::Error::from_reason(
+                                "[VALIDATION] Vector element is not a number",
+                            )
+                        })
                     })
+                    .collect::<napi::Result<_>>()?;
+                let vec = validate_vector(&raw_vec)?;
+                let meta = match obj.get("metadata") {
+                    Some(m) => Some(js_to_value_checked(m.clone(), 0)?),
+                    None => None,
+                };
+                Ok(BatchVectorEntry {
+                    key,
+                    vector: vec,
+                    metadata: meta,
                 })
-                .collect();
-            Ok(serde_json::Value::Array(arr))
+            })
+            .collect::<napi::Result<_>>()?;
+        spawn_on(&self.pool, move || {
+            let guard = lock_inner(&inner)?;
+            ensure_writable(&guard)?;
+            guard
+                .vector_batch_upsert(&collection, batch)
+                .map(|versions| versions.into_iter().map(|v| v as i64).collect())
+                .map_err(to_napi_err)
         })
         .await
         .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
     }
 
     // =========================================================================
-    // Retention
+    // Branch Management
     // =========================================================================
 
-    /// Apply retention policy to trigger garbage collection.
-    #[napi(js_name = "retentionApply")]
-    pub async fn retention_apply(&self) -> napi::Result<()> {
+    /// Get the current branch name.
+    #[napi(js_name = "currentBranch")]
+    pub async fn current_branch(&self) -> napi::Result<String> {
         let inner = self.inner.clone();
-        tokio::task::spawn_blocking(move || {
+        spawn_on(&self.pool, move || {
             let guard = lock_inner(&inner)?;
-            guard.retention_apply().map_err(to_napi_err)
+            Ok(guard.current_branch().to_string())
         })
         .await
         .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
     }
 
-    // =========================================================================
-    // Generic command dispatch
-    // =========================================================================
+    /// Switch to a different branch.
+    #[napi(js_name = "setBranch")]
+    pub async fn set_branch(&self, branch: String) -> napi::Result<()> {
+        let inner = self.inner.clone();
+        spawn_on(&self.pool, move || {
+            let mut guard = lock_inner_mut(&inner)?;
+            guard.set_branch(&branch).map_err(to_napi_err)
+        })
+        .await
+        .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
+    }
 
-    /// Execute any command by name with JSON arguments.
-    ///
-    /// This provides a generic dispatch interface: pass a command name (snake_case
-    /// or dot-notation) and a JSON args object, and get a JSON result back.
-    ///
-    /// ```js
-    /// const version = await db.execute("kv_put", { key: "foo", value: "bar" });
-    /// const val = await db.execute("kv_get", { key: "foo" });
-    /// const keys = await db.execute("kv.list", { prefix: "f" });
-    /// ```
-    ///
-    /// Command names map to executor Command variants: `kv_put` → `KvPut`,
-    /// `graph_add_node` → `GraphAddNode`, etc.  Branch and space default to
-    /// the current context if not specified in args.
-    #[napi]
-    pub async fn execute(
+    /// Create a new empty branch.
+    #[napi(js_name = "createBranch")]
+    pub async fn create_branch(
         &self,
-        command: String,
-        args: Option<serde_json::Value>,
-    ) -> napi::Result<serde_json::Value> {
+        branch: String,
+        metadata: Option<serde_json::Value>,
+    ) -> napi::Result<()> {
         let inner = self.inner.clone();
-        let session_arc = self.session.clone();
-        tokio::task::spawn_blocking(move || {
-            // Normalize command name: kv.put → kv_put → KvPut
-            let pascal = to_pascal_case(&command);
-
-            // Get args as a mutable map (empty if null/absent)
-            let mut args_map = match args.unwrap_or(serde_json::Value::Null) {
-                serde_json::Value::Object(m) => m,
-                serde_json::Value::Null => serde_json::Map::new(),
-                _ => {
-                    return Err(napi::Error::from_reason(
-                        "[VALIDATION] args must be an object or null",
-                    ))
-                }
-            };
-
-            // Convert plain JSON values to tagged Value format for value/payload fields
-            preprocess_value_fields(&mut args_map);
-
-            // Build the Command JSON.
-            // Unit variants (Ping, Info, etc.) serialize as just "Ping",
-            // while struct variants serialize as {"KvPut": {key: ..., value: ...}}.
-            // Try struct form first, fall back to unit variant if args are empty.
-            let cmd: Command = if args_map.is_empty() {
-                // Try unit variant first (e.g., "Ping")
-                serde_json::from_value::<Command>(serde_json::Value::String(pascal.clone()))
-                    .or_else(|_| {
-                        // Fall back to struct variant with empty fields
-                        let mut m = serde_json::Map::new();
-                        m.insert(pascal.clone(), serde_json::Value::Object(args_map.clone()));
-                        serde_json::from_value::<Command>(serde_json::Value::Object(m))
-                    })
-            } else {
-                let mut m = serde_json::Map::new();
-                m.insert(pascal.clone(), serde_json::Value::Object(args_map));
-                serde_json::from_value::<Command>(serde_json::Value::Object(m))
-            }
-            .map_err(|e| {
-                napi::Error::from_reason(format!(
-                    "[VALIDATION] Invalid command '{}': {}",
-                    command, e
-                ))
-            })?;
-
-            // Execute through session (supports transactions) or executor
-            let mut session_guard = lock_session(&session_arc)?;
-            let output = if let Some(session) = session_guard.as_mut() {
-                session.execute(cmd).map_err(to_napi_err)?
-            } else {
-                let guard = lock_inner(&inner)?;
-                guard.executor().execute(cmd).map_err(to_napi_err)?
-            };
-
-            // Convert Output to plain JSON
-            Ok(output_to_json(output))
+        let meta_val = metadata
+            .map(|m| js_to_value_checked(m, 0))
+            .transpose()?;
+        spawn_on(&self.pool, move || {
+            let guard = lock_inner(&inner)?;
+            guard
+                .branch_create(Some(branch), meta_val)
+                .map(|_| ())
+                .map_err(to_napi_err)
         })
         .await
         .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
     }
 
-    // =========================================================================
-    // Follower mode
-    // =========================================================================
-
-    /// Returns `true` if this database was opened in read-only follower mode.
-    #[napi(js_name = "isFollower")]
-    pub fn is_follower(&self) -> napi::Result<bool> {
-        let guard = lock_inner(&self.inner)?;
-        Ok(guard.database().is_follower())
-    }
-
-    /// Replay new WAL records from the primary.
-    ///
-    /// Only meaningful for follower instances (opened with `{ follower: true }`).
-    /// Returns the number of new records applied. Returns 0 for non-follower
-    /// instances or when there are no new records.
-    #[napi]
-    pub async fn refresh(&self) -> napi::Result<i64> {
+    /// Fork the current branch to a new branch, copying all data.
+    #[napi(js_name = "forkBranch")]
+    pub async fn fork_branch(&self, destination: String) -> napi::Result<serde_json::Value> {
         let inner = self.inner.clone();
-        tokio::task::spawn_blocking(move || {
+        spawn_on(&self.pool, move || {
             let guard = lock_inner(&inner)?;
-            let applied = guard
-                .database()
-                .refresh()
-                .map_err(|e| napi::Error::from_reason(format!("{}", e)))?;
-            Ok(applied as i64)
+            let info = guard.fork_branch(&destination).map_err(to_napi_err)?;
+            Ok(serde_json::json!({
+                "source": info.source,
+                "destination": info.destination,
+                "keysCopied": info.keys_copied,
+            }))
         })
         .await
         .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
     }
 
-    // =========================================================================
-    // Lifecycle
-    // =========================================================================
-
-    /// Close the database, releasing all resources.
+    /// Create a new branch from the current branch's state at a past
+    /// timestamp (microseconds since epoch, or a tag name).
     ///
-    /// After calling `close()`, any further method call on this instance will
-    /// fail with a "Lock poisoned" or similar error.  This mirrors the
-    /// `client.close()` pattern used by every major Node.js database driver.
-    #[napi]
-    pub async fn close(&self) -> napi::Result<()> {
+    /// KV, state, and JSON data visible at `asOf` is copied into the new
+    /// branch in every space. Events, vectors, and graphs are not copied.
+    #[napi(js_name = "forkBranchAt")]
+    pub async fn fork_branch_at(
+        &self,
+        destination: String,
+        as_of: Either<i64, String>,
+    ) -> napi::Result<serde_json::Value> {
         let inner = self.inner.clone();
-        let session_arc = self.session.clone();
-        tokio::task::spawn_blocking(move || {
-            // Drop session first (it borrows the inner DB).
-            {
-                let mut s = lock_session(&session_arc)?;
-                *s = None;
-            }
-            // Replace the inner Strata with a freshly-opened cache that will
-            // be immediately dropped, effectively releasing the original DB.
-            let mut guard = inner
-                .lock()
-                .map_err(|_| napi::Error::from_reason("Lock poisoned"))?;
-            let placeholder = RustStrata::cache().map_err(to_napi_err)?;
-            *guard = placeholder;
-            Ok(())
+        spawn_on(&self.pool, move || {
+            let guard = lock_inner(&inner)?;
+            let as_of = resolve_as_of(&guard, Some(as_of))?.unwrap_or(0);
+            let source = guard.current_branch().to_string();
+            guard
+                .branch_create(Some(destination.clone()), None)
+                .map_err(to_napi_err)?;
+            let copied = detached_handle(&guard)
+                .and_then(|mut view| copy_branch_as_of(&mut view, &source, &destination, as_of));
+            let keys_copied = match copied {
+                Ok(n) => n,
+                Err(e) => {
+                    let _ = guard.delete_branch(&destination);
+                    return Err(e);
+                }
+            };
+            Ok(serde_json::json!({
+                "source": source,
+                "destination": destination,
+                "asOf": as_of,
+                "keysCopied": keys_copied,
+            }))
         })
         .await
         .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
     }
 
-    // =========================================================================
-    // Time Travel
-    // =========================================================================
-
-    /// Get the time range (oldest and latest timestamps) for the current branch.
-    #[napi(js_name = "timeRange")]
-    pub async fn time_range(&self) -> napi::Result<serde_json::Value> {
+    /// Rewind a branch's KV, state, and JSON data, in every space, to what
+    /// it held at `asOf` (microseconds since epoch), writing the
+    /// differences as new versions. Events, vectors, and graphs are left
+    /// as they are. Used by `Strata.restore()` for `rewindTo`.
+    #[napi(js_name = "branchRewind")]
+    pub async fn branch_rewind(
+        &self,
+        branch: String,
+        as_of: i64,
+    ) -> napi::Result<serde_json::Value> {
         let inner = self.inner.clone();
-        tokio::task::spawn_blocking(move || {
+        if as_of < 0 {
+            return Err(napi::Error::from_reason(
+                "[VALIDATION] asOf must be a non-negative timestamp",
+            ));
+        }
+        spawn_on(&self.pool, move || {
             let guard = lock_inner(&inner)?;
-            let (oldest_ts, latest_ts) = guard.time_range().map_err(to_napi_err)?;
-            Ok(serde_json::json!({
-                "oldestTs": oldest_ts.map(|t| t as i64),
-                "latestTs": latest_ts.map(|t| t as i64),
-            }))
+            ensure_branch_writable(&guard, &branch, false)?;
+            let mut view = detached_handle(&guard)?;
+            let (written, deleted) = rewind_branch(&mut view, &branch, as_of as u64)?;
+            Ok(serde_json::json!({ "written": written, "deleted": deleted }))
         })
         .await
         .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
     }
 
-    // =========================================================================
-    // Batch Operations
-    // =========================================================================
-
-    /// Batch put multiple KV entries.
-    #[napi(js_name = "kvBatchPut")]
-    pub async fn kv_batch_put(
+    /// List all branches.
+    ///
+    /// Archived branches are left out unless `includeArchived` is `true`.
+    #[napi(js_name = "listBranches")]
+    pub async fn list_branches(
         &self,
-        entries: Vec<serde_json::Value>,
+        limit: Option<u32>,
+        offset: Option<u32>,
+        include_archived: Option<bool>,
     ) -> napi::Result<serde_json::Value> {
         let inner = self.inner.clone();
-        let batch: Vec<BatchKvEntry> = entries
-            .into_iter()
-            .map(|v| {
-                let obj = v
-                    .as_object()
-                    .ok_or_else(|| napi::Error::from_reason("[VALIDATION] Expected object"))?;
-                let key = obj
-                    .get("key")
-                    .and_then(|k| k.as_str())
-                    .ok_or_else(|| napi::Error::from_reason("[VALIDATION] Missing 'key'"))?
-                    .to_string();
-                let value = obj
-                    .get("value")
-                    .ok_or_else(|| napi::Error::from_reason("[VALIDATION] Missing 'value'"))?
-                    .clone();
-                let value = js_to_value_checked(value, 0)?;
-                Ok(BatchKvEntry { key, value })
-            })
-            .collect::<napi::Result<_>>()?;
-        tokio::task::spawn_blocking(move || {
+        spawn_on(&self.pool, move || {
             let guard = lock_inner(&inner)?;
-            let results = guard.kv_batch_put(batch).map_err(to_napi_err)?;
-            Ok(batch_results_to_js(results))
+            let archived = if include_archived.unwrap_or(false) {
+                HashSet::new()
+            } else {
+                archived_branches(&guard)?
+            };
+            // With archived branches to hide, paginate after filtering so
+            // pages stay full.
+            let names: Vec<serde_json::Value> = if archived.is_empty() {
+                guard
+                    .branch_list(
+                        None,
+                        limit.map(|l| l as u64),
+                        offset.map(|o| o as u64),
+                    )
+                    .map_err(to_napi_err)?
+                    .into_iter()
+                    .map(|b| serde_json::Value::String(b.info.id.as_str().to_string()))
+                    .collect()
+            } else {
+                guard
+                    .branch_list(None, None, None)
+                    .map_err(to_napi_err)?
+                    .into_iter()
+                    .map(|b| b.info.id.as_str().to_string())
+                    .filter(|name| !archived.contains(name))
+                    .skip(offset.unwrap_or(0) as usize)
+                    .take(limit.map(|l| l as usize).unwrap_or(usize::MAX))
+                    .map(serde_json::Value::String)
+                    .collect()
+            };
+            Ok(serde_json::Value::Array(names))
         })
         .await
         .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
     }
 
-    /// Batch set multiple state cells.
-    #[napi(js_name = "stateBatchSet")]
-    pub async fn state_batch_set(
-        &self,
-        entries: Vec<serde_json::Value>,
-    ) -> napi::Result<serde_json::Value> {
+    /// Delete a branch.
+    #[napi(js_name = "deleteBranch")]
+    pub async fn delete_branch(&self, branch: String) -> napi::Result<()> {
         let inner = self.inner.clone();
-        let batch: Vec<BatchStateEntry> = entries
-            .into_iter()
-            .map(|v| {
-                let obj = v
-                    .as_object()
-                    .ok_or_else(|| napi::Error::from_reason("[VALIDATION] Expected object"))?;
-                let cell = obj
-                    .get("cell")
-                    .and_then(|k| k.as_str())
-                    .ok_or_else(|| napi::Error::from_reason("[VALIDATION] Missing 'cell'"))?
-                    .to_string();
-                let value = obj
-                    .get("value")
-                    .ok_or_else(|| napi::Error::from_reason("[VALIDATION] Missing 'value'"))?
-                    .clone();
-                let value = js_to_value_checked(value, 0)?;
-                Ok(BatchStateEntry { cell, value })
-            })
-            .collect::<napi::Result<_>>()?;
-        tokio::task::spawn_blocking(move || {
+        spawn_on(&self.pool, move || {
             let guard = lock_inner(&inner)?;
-            let results = guard.state_batch_set(batch).map_err(to_napi_err)?;
-            Ok(batch_results_to_js(results))
+            if branch_flag(&read_branch_meta(&guard, &branch)?, "protected") {
+                return Err(napi::Error::from_reason(format!(
+                    "[ACCESS_DENIED] Branch '{}' is protected",
+                    branch
+                )));
+            }
+            guard.delete_branch(&branch).map_err(to_napi_err)?;
+            guard
+                .system_branch()
+                .kv_delete(&branch_meta_key(&branch))
+                .map(|_| ())
+                .map_err(to_napi_err)
         })
         .await
         .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
     }
 
-    /// Batch append multiple events.
-    #[napi(js_name = "eventBatchAppend")]
-    pub async fn event_batch_append(
-        &self,
-        entries: Vec<serde_json::Value>,
-    ) -> napi::Result<serde_json::Value> {
+    /// Check if a branch exists.
+    #[napi(js_name = "branchExists")]
+    pub async fn branch_exists(&self, name: String) -> napi::Result<bool> {
         let inner = self.inner.clone();
-        let batch: Vec<BatchEventEntry> = entries
-            .into_iter()
-            .map(|v| {
-                let obj = v
-                    .as_object()
-                    .ok_or_else(|| napi::Error::from_reason("[VALIDATION] Expected object"))?;
-                let event_type = obj
-                    .get("event_type")
-                    .or_else(|| obj.get("eventType"))
-                    .and_then(|k| k.as_str())
-                    .ok_or_else(|| {
-                        napi::Error::from_reason("[VALIDATION] Missing 'event_type'")
-                    })?
-                    .to_string();
-                let payload = obj
-                    .get("payload")
-                    .ok_or_else(|| napi::Error::from_reason("[VALIDATION] Missing 'payload'"))?
-                    .clone();
-                let payload = js_to_value_checked(payload, 0)?;
-                Ok(BatchEventEntry {
-                    event_type,
-                    payload,
-                })
-            })
-            .collect::<napi::Result<_>>()?;
-        tokio::task::spawn_blocking(move || {
+        spawn_on(&self.pool, move || {
             let guard = lock_inner(&inner)?;
-            let results = guard.event_batch_append(batch).map_err(to_napi_err)?;
-            Ok(batch_results_to_js(results))
+            guard.branches().exists(&name).map_err(to_napi_err)
         })
         .await
         .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
     }
 
-    /// Batch set multiple JSON documents.
-    #[napi(js_name = "jsonBatchSet")]
-    pub async fn json_batch_set(
-        &self,
-        entries: Vec<serde_json::Value>,
-    ) -> napi::Result<serde_json::Value> {
-        let inner = self.inner.clone();
-        let batch: Vec<BatchJsonEntry> = entries
-            .into_iter()
-            .map(|v| {
-                let obj = v
-                    .as_object()
-                    .ok_or_else(|| napi::Error::from_reason("[VALIDATION] Expected object"))?;
-                let key = obj
-                    .get("key")
-                    .and_then(|k| k.as_str())
-                    .ok_or_else(|| napi::Error::from_reason("[VALIDATION] Missing 'key'"))?
-                    .to_string();
-                let path = obj
-                    .get("path")
-                    .and_then(|p| p.as_str())
-                    .ok_or_else(|| napi::Error::from_reason("[VALIDATION] Missing 'path'"))?
-                    .to_string();
-                let value = obj
-                    .get("value")
-                    .ok_or_else(|| napi::Error::from_reason("[VALIDATION] Missing 'value'"))?
-                    .clone();
-                let value = js_to_value_checked(value, 0)?;
-                Ok(BatchJsonEntry { key, path, value })
-            })
-            .collect::<napi::Result<_>>()?;
-        tokio::task::spawn_blocking(move || {
+    /// Get branch metadata with version info.
+    #[napi(js_name = "branchGet")]
+    pub async fn branch_get(&self, name: String) -> napi::Result<serde_json::Value> {
+        let inner = self.inner.clone();
+        spawn_on(&self.pool, move || {
             let guard = lock_inner(&inner)?;
-            let results = guard.json_batch_set(batch).map_err(to_napi_err)?;
-            Ok(batch_results_to_js(results))
+            match guard.branch_get(&name).map_err(to_napi_err)? {
+                Some(info) => {
+                    let meta = read_branch_meta(&guard, &name)?;
+                    Ok(versioned_branch_info_to_js(info, &meta))
+                }
+                None => Ok(serde_json::Value::Null),
+            }
         })
         .await
         .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
     }
 
-    /// Batch get multiple JSON documents.
-    #[napi(js_name = "jsonBatchGet")]
-    pub async fn json_batch_get(
+    /// Rename a branch.
+    ///
+    /// The branch is forked under the new name and the old name is deleted,
+    /// carrying its description and labels along. If the renamed branch is
+    /// the current branch, the handle follows it to the new name.
+    #[napi(js_name = "renameBranch")]
+    pub async fn rename_branch(&self, old_name: String, new_name: String) -> napi::Result<()> {
+        let inner = self.inner.clone();
+        spawn_on(&self.pool, move || {
+            let mut guard = lock_inner_mut(&inner)?;
+            if old_name == "default" {
+                return Err(napi::Error::from_reason(
+                    "[CONSTRAINT] Cannot rename the default branch",
+                ));
+            }
+            if branch_flag(&read_branch_meta(&guard, &old_name)?, "protected") {
+                return Err(napi::Error::from_reason(format!(
+                    "[ACCESS_DENIED] Branch '{}' is protected",
+                    old_name
+                )));
+            }
+            let current = guard.current_branch().to_string();
+            guard.set_branch(&old_name).map_err(to_napi_err)?;
+            let forked = guard.fork_branch(&new_name).map(|_| ());
+            let restore = if current == old_name && forked.is_ok() {
+                &new_name
+            } else {
+                &current
+            };
+            guard.set_branch(restore).map_err(to_napi_err)?;
+            forked.map_err(to_napi_err)?;
+
+            let meta = read_branch_meta(&guard, &old_name)?;
+            if !meta.is_empty() {
+                write_branch_meta(&guard, &new_name, meta)?;
+            }
+            guard.delete_branch(&old_name).map_err(to_napi_err)?;
+            guard
+                .system_branch()
+                .kv_delete(&branch_meta_key(&old_name))
+                .map(|_| ())
+                .map_err(to_napi_err)
+        })
+        .await
+        .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
+    }
+
+    /// Set a branch's description and/or labels.
+    ///
+    /// Fields left out are unchanged. The values are returned by `branchGet`.
+    #[napi(js_name = "setBranchMetadata")]
+    pub async fn set_branch_metadata(
         &self,
-        entries: Vec<serde_json::Value>,
-    ) -> napi::Result<serde_json::Value> {
+        name: String,
+        metadata: JsBranchMetadata,
+    ) -> napi::Result<()> {
         let inner = self.inner.clone();
-        let batch: Vec<BatchJsonGetEntry> = entries
-            .into_iter()
-            .map(|v| {
-                let obj = v
-                    .as_object()
-                    .ok_or_else(|| napi::Error::from_reason("[VALIDATION] Expected object"))?;
-                let key = obj
-                    .get("key")
-                    .and_then(|k| k.as_str())
-                    .ok_or_else(|| napi::Error::from_reason("[VALIDATION] Missing 'key'"))?
-                    .to_string();
-                let path = obj
-                    .get("path")
-                    .and_then(|p| p.as_str())
-                    .ok_or_else(|| napi::Error::from_reason("[VALIDATION] Missing 'path'"))?
-                    .to_string();
-                Ok(BatchJsonGetEntry { key, path })
-            })
-            .collect::<napi::Result<_>>()?;
-        tokio::task::spawn_blocking(move || {
+        spawn_on(&self.pool, move || {
             let guard = lock_inner(&inner)?;
-            let results = guard.json_batch_get(batch).map_err(to_napi_err)?;
-            Ok(batch_get_results_to_js(results))
+            if !guard.branches().exists(&name).map_err(to_napi_err)? {
+                return Err(napi::Error::from_reason(format!(
+                    "[NOT_FOUND] Branch not found: {}",
+                    name
+                )));
+            }
+            let mut meta = read_branch_meta(&guard, &name)?;
+            match metadata.description {
+                Some(d) if d.is_empty() => {
+                    meta.remove("description");
+                }
+                Some(d) => {
+                    meta.insert("description".into(), serde_json::Value::String(d));
+                }
+                None => {}
+            }
+            if let Some(labels) = metadata.labels {
+                meta.insert("labels".into(), serde_json::json!(labels));
+            }
+            write_branch_meta(&guard, &name, meta)
         })
         .await
         .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
     }
 
-    /// Batch delete multiple JSON documents.
-    #[napi(js_name = "jsonBatchDelete")]
-    pub async fn json_batch_delete(
-        &self,
-        entries: Vec<serde_json::Value>,
-    ) -> napi::Result<serde_json::Value> {
+    /// Protect a branch from deletion and direct writes.
+    ///
+    /// Data can still reach a protected branch by merging into it. Pass
+    /// `false` to lift protection.
+    #[napi(js_name = "protectBranch")]
+    pub async fn protect_branch(&self, name: String, enabled: Option<bool>) -> napi::Result<()> {
         let inner = self.inner.clone();
-        let batch: Vec<BatchJsonDeleteEntry> = entries
-            .into_iter()
-            .map(|v| {
-                let obj = v
-                    .as_object()
-                    .ok_or_else(|| napi::Error::from_reason("[VALIDATION] Expected object"))?;
-                let key = obj
-                    .get("key")
-                    .and_then(|k| k.as_str())
-                    .ok_or_else(|| napi::Error::from_reason("[VALIDATION] Missing 'key'"))?
-                    .to_string();
-                let path = obj
-                    .get("path")
-                    .and_then(|p| p.as_str())
-                    .ok_or_else(|| napi::Error::from_reason("[VALIDATION] Missing 'path'"))?
-                    .to_string();
-                Ok(BatchJsonDeleteEntry { key, path })
-            })
-            .collect::<napi::Result<_>>()?;
-        tokio::task::spawn_blocking(move || {
+        spawn_on(&self.pool, move || {
             let guard = lock_inner(&inner)?;
-            let results = guard.json_batch_delete(batch).map_err(to_napi_err)?;
-            Ok(batch_results_to_js(results))
+            set_branch_flag(&guard, &name, "protected", enabled.unwrap_or(true))
         })
         .await
         .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
     }
 
-    // =========================================================================
-    // Configuration (key-value)
-    // =========================================================================
-
-    /// Set a configuration key-value pair.
-    #[napi(js_name = "configureSet")]
-    pub async fn configure_set(&self, key: String, value: String) -> napi::Result<()> {
+    /// Archive a branch, making it read-only and hiding it from
+    /// `listBranches` by default. Pass `false` to unarchive.
+    #[napi(js_name = "archiveBranch")]
+    pub async fn archive_branch(&self, name: String, enabled: Option<bool>) -> napi::Result<()> {
         let inner = self.inner.clone();
-        tokio::task::spawn_blocking(move || {
+        spawn_on(&self.pool, move || {
             let guard = lock_inner(&inner)?;
-            guard.config_set(&key, &value).map_err(to_napi_err)
+            if name == "default" {
+                return Err(napi::Error::from_reason(
+                    "[CONSTRAINT] Cannot archive the default branch",
+                ));
+            }
+            set_branch_flag(&guard, &name, "archived", enabled.unwrap_or(true))
         })
         .await
         .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
     }
 
-    /// Get a configuration value by key.
-    #[napi(js_name = "configureGet")]
-    pub async fn configure_get(&self, key: String) -> napi::Result<Option<String>> {
+    /// Get statistics for one branch.
+    ///
+    /// Reports entry counts per primitive summed over all spaces, the space
+    /// count, an estimate of the stored bytes, and the last write timestamp.
+    /// Every value on the branch is read, so this is O(branch size).
+    #[napi(js_name = "branchStats")]
+    pub async fn branch_stats(&self, name: String) -> napi::Result<serde_json::Value> {
         let inner = self.inner.clone();
-        tokio::task::spawn_blocking(move || {
+        spawn_on(&self.pool, move || {
             let guard = lock_inner(&inner)?;
-            guard.config_get(&key).map_err(to_napi_err)
+            let mut view = detached_handle(&guard)?;
+            let (tally, spaces, last_write) = tally_branch(&mut view, &name)?;
+            let mut out = tally.to_js();
+            out["branch"] = serde_json::json!(name);
+            out["spaces"] = serde_json::json!(spaces);
+            out["lastWriteAt"] = serde_json::json!(last_write);
+            Ok(out)
         })
         .await
         .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
     }
 
-    // =========================================================================
-    // Embedding
-    // =========================================================================
-
-    /// Embed a single text string.
-    #[napi]
-    pub async fn embed(&self, text: String) -> napi::Result<Vec<f64>> {
+    /// Get the branch fork graph.
+    ///
+    /// Returns one node per branch with its parent, fork timestamp, and
+    /// divergence counts relative to the parent (keys added, removed, and
+    /// modified on the child). Pass `false` to skip the divergence diffs,
+    /// which cost one branch diff per forked branch.
+    #[napi(js_name = "branchLineage")]
+    pub async fn branch_lineage(
+        &self,
+        include_divergence: Option<bool>,
+    ) -> napi::Result<serde_json::Value> {
         let inner = self.inner.clone();
-        tokio::task::spawn_blocking(move || {
+        let include_divergence = include_divergence.unwrap_or(true);
+        spawn_on(&self.pool, move || {
             let guard = lock_inner(&inner)?;
-            let vec = guard.embed(&text).map_err(to_napi_err)?;
-            Ok(vec.into_iter().map(|f| f as f64).collect())
+            let branches = guard.branch_list(None, None, None).map_err(to_napi_err)?;
+            let ids: HashSet<String> = branches
+                .iter()
+                .map(|b| b.info.id.as_str().to_string())
+                .collect();
+            let mut nodes = Vec::with_capacity(branches.len());
+            for b in branches {
+                let id = b.info.id.as_str().to_string();
+                let parent = b.info.parent_id.map(|p| p.as_str().to_string());
+                let divergence = match &parent {
+                    Some(p) if include_divergence && ids.contains(p) => {
+                        let diff = guard.diff_branches(p, &id).map_err(to_napi_err)?;
+                        serde_json::json!({
+                            "added": diff.summary.total_added,
+                            "removed": diff.summary.total_removed,
+                            "modified": diff.summary.total_modified,
+                        })
+                    }
+                    _ => serde_json::Value::Null,
+                };
+                nodes.push(serde_json::json!({
+                    "id": id,
+                    "parentId": parent,
+                    "status": format!("{:?}", b.info.status).to_lowercase(),
+                    "forkedAt": parent.as_ref().map(|_| b.info.created_at),
+                    "createdAt": b.info.created_at,
+                    "divergence": divergence,
+                }));
+            }
+            Ok(serde_json::json!({ "branches": nodes }))
         })
         .await
         .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
     }
 
-    /// Embed multiple texts in a batch.
-    #[napi(js_name = "embedBatch")]
-    pub async fn embed_batch(&self, texts: Vec<String>) -> napi::Result<Vec<Vec<f64>>> {
+    /// Compare two branches.
+    ///
+    /// By default only summary counts are returned. Pass `{ detailed: true }`
+    /// to also get per-space lists of added, removed, and modified keys,
+    /// paginated with `limit`/`cursor`.
+    #[napi(js_name = "diffBranches")]
+    pub async fn diff_branches(
+        &self,
+        branch_a: String,
+        branch_b: String,
+        options: Option<JsDiffOptions>,
+        operation_id: Option<u32>,
+    ) -> napi::Result<serde_json::Value> {
         let inner = self.inner.clone();
-        tokio::task::spawn_blocking(move || {
+        let output = DiffOutput::from_options(options.as_ref())?;
+        let op = CancellableOp::start(operation_id);
+        spawn_on(&self.pool, move || {
+            op.check()?;
             let guard = lock_inner(&inner)?;
-            let refs: Vec<&str> = texts.iter().map(|s| s.as_str()).collect();
-            let vecs = guard.embed_batch(&refs).map_err(to_napi_err)?;
-            Ok(vecs
-                .into_iter()
-                .map(|v| v.into_iter().map(|f| f as f64).collect())
-                .collect())
+            let mut out = diff_to_js(&guard, &branch_a, &branch_b, &output)?;
+            if output.detailed {
+                let mut view = detached_handle(&guard)?;
+                annotate_diff(&mut view, &branch_a, &branch_b, &mut out)?;
+            }
+            Ok(out)
         })
         .await
         .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
     }
 
-    /// Get the embedding pipeline status.
-    #[napi(js_name = "embedStatus")]
-    pub async fn embed_status(&self) -> napi::Result<serde_json::Value> {
+    /// Compare a bundle with another bundle, or with a live branch when the
+    /// second argument is `{ branch }`.
+    ///
+    /// Neither bundle is imported: each is staged in a hidden branch that
+    /// is removed once the diff is taken. Bundle A is reported as `branchA`.
+    /// `options` shape the result as for `diffBranches`; `encryption`
+    /// decrypts both bundles.
+    #[napi(js_name = "bundleDiff")]
+    pub async fn bundle_diff(
+        &self,
+        path_a: String,
+        target: Either<String, JsBundleDiffTarget>,
+        options: Option<JsDiffOptions>,
+        encryption: Option<JsBundleEncryption>,
+    ) -> napi::Result<serde_json::Value> {
         let inner = self.inner.clone();
-        tokio::task::spawn_blocking(move || {
+        let output = DiffOutput::from_options(options.as_ref())?;
+        let decrypt = encryption.map(bundle_encryption).transpose()?;
+        spawn_on(&self.pool, move || {
+            let guard = lock_inner(&inner)?;
+            let mut staged = Vec::new();
+            let diffed = (|| -> napi::Result<serde_json::Value> {
+                let a = stage_bundle(&guard, &path_a, &decrypt, &mut staged)?;
+                let (b, label_b) = match &target {
+                    Either::A(path_b) => (
+                        stage_bundle(&guard, path_b, &decrypt, &mut staged)?,
+                        path_b.clone(),
+                    ),
+                    Either::B(t) => (t.branch.clone(), t.branch.clone()),
+                };
+                let mut out = diff_to_js(&guard, &a, &b, &output)?;
+                out["branchA"] = serde_json::json!(path_a);
+                out["branchB"] = serde_json::json!(label_b);
+                Ok(out)
+            })();
+            for branch in staged {
+                guard.delete_branch(&branch).map_err(to_napi_err)?;
+            }
+            diffed
+        })
+        .await
+        .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
+    }
+
+    /// Merge a branch into the current branch.
+    ///
+    /// The second argument is either a strategy name or an options object.
+    /// With `{ dryRun: true }` nothing is written: the result reports the
+    /// keys that would be applied, the keys changed on both branches since
+    /// they forked as conflicts, and per-space effects.
+    #[napi(js_name = "mergeBranches")]
+    pub async fn merge_branches(
+        &self,
+        source: String,
+        #[napi(ts_arg_type = "MergeStrategy | `${MergeStrategy}` | JsMergeOptions")]
+        options: Option<Either<String, JsMergeOptions>>,
+    ) -> napi::Result<serde_json::Value> {
+        let inner = self.inner.clone();
+        let (strategy, dry_run, include_values) = match options {
+            Some(Either::A(s)) => (Some(s), false, false),
+            Some(Either::B(o)) => (
+                o.strategy,
+                o.dry_run.unwrap_or(false),
+                o.include_values.unwrap_or(false),
+            ),
+            None => (None, false, false),
+        };
+        let strat = match strategy.as_deref().unwrap_or("last_writer_wins") {
+            "last_writer_wins" => MergeStrategy::LastWriterWins,
+            "strict" => MergeStrategy::Strict,
+            _ => return Err(napi::Error::from_reason("[VALIDATION] Invalid merge strategy")),
+        };
+        spawn_on(&self.pool, move || {
             let guard = lock_inner(&inner)?;
-            let info = guard.embed_status().map_err(to_napi_err)?;
+            let target = guard.current_branch().to_string();
+            if !dry_run {
+                ensure_branch_writable(&guard, &target, true)?;
+            }
+            if dry_run {
+                let diff = guard.diff_branches(&target, &source).map_err(to_napi_err)?;
+                return merge_preview_to_js(
+                    &guard,
+                    &target,
+                    &source,
+                    diff.spaces,
+                    strat,
+                    include_values,
+                );
+            }
+            // Capture both sides of every differing key before the merge
+            // overwrites the target, so conflicts can carry their values.
+            let mut before: HashMap<(String, String), BranchDiffEntry> = HashMap::new();
+            if include_values {
+                let diff = guard.diff_branches(&target, &source).map_err(to_napi_err)?;
+                for sd in diff.spaces {
+                    for e in sd.modified {
+                        before.insert((sd.space.clone(), e.key.clone()), e);
+                    }
+                }
+            }
+            let info = guard
+                .merge_branches(&source, &target, strat)
+                .map_err(to_napi_err)?;
+            let conflicts: Vec<serde_json::Value> = info
+                .conflicts
+                .into_iter()
+                .map(|c| {
+                    let entry = before.remove(&(c.space.clone(), c.key.clone()));
+                    merge_conflict_to_js(c.space, c.key, entry, include_values)
+                })
+                .collect();
             Ok(serde_json::json!({
-                "autoEmbed": info.auto_embed,
-                "batchSize": info.batch_size,
-                "pending": info.pending,
-                "totalQueued": info.total_queued,
-                "totalEmbedded": info.total_embedded,
-                "totalFailed": info.total_failed,
-                "schedulerQueueDepth": info.scheduler_queue_depth,
-                "schedulerActiveTasks": info.scheduler_active_tasks,
+                "keysApplied": info.keys_applied,
+                "spacesMerged": info.spaces_merged,
+                "conflicts": conflicts,
             }))
         })
         .await
@@ -2660,1239 +5598,7801 @@ impl Strata {
     }
 
     // =========================================================================
-    // Inference
+    // Tags
     // =========================================================================
 
-    /// Generate text from a model.
-    #[napi]
-    pub async fn generate(
+    /// Create a named tag for a point in time.
+    ///
+    /// Tags default to the current time. Any `asOf` parameter accepts a tag
+    /// name in place of a timestamp.
+    #[napi(js_name = "tagCreate")]
+    pub async fn tag_create(
         &self,
-        model: String,
-        prompt: String,
-        options: Option<serde_json::Value>,
+        name: String,
+        options: Option<JsTagOptions>,
     ) -> napi::Result<serde_json::Value> {
         let inner = self.inner.clone();
-        let (max_tokens, temperature, top_k, top_p, seed, stop_tokens, stop_sequences) =
-            match options {
-                Some(opts) => {
-                    let obj = opts.as_object();
-                    (
-                        obj.and_then(|o| o.get("maxTokens"))
-                            .and_then(|v| v.as_u64())
-                            .map(|n| n as usize),
-                        obj.and_then(|o| o.get("temperature"))
-                            .and_then(|v| v.as_f64())
-                            .map(|f| f as f32),
-                        obj.and_then(|o| o.get("topK"))
-                            .and_then(|v| v.as_u64())
-                            .map(|n| n as usize),
-                        obj.and_then(|o| o.get("topP"))
-                            .and_then(|v| v.as_f64())
-                            .map(|f| f as f32),
-                        obj.and_then(|o| o.get("seed")).and_then(|v| v.as_u64()),
-                        obj.and_then(|o| o.get("stopTokens"))
-                            .and_then(|v| v.as_array())
-                            .map(|arr| {
-                                arr.iter()
-                                    .filter_map(|n| n.as_u64().map(|n| n as u32))
-                                    .collect()
-                            }),
-                        obj.and_then(|o| o.get("stopSequences"))
-                            .and_then(|v| v.as_array())
-                            .map(|arr| {
-                                arr.iter()
-                                    .filter_map(|s| s.as_str().map(|s| s.to_string()))
-                                    .collect()
-                            }),
-                    )
-                }
-                None => (None, None, None, None, None, None, None),
-            };
-        tokio::task::spawn_blocking(move || {
+        if name.is_empty() {
+            return Err(napi::Error::from_reason(
+                "[VALIDATION] Tag name must not be empty",
+            ));
+        }
+        let now = now_micros();
+        let timestamp = match options.and_then(|o| o.timestamp) {
+            Some(ts) if ts < 0 => {
+                return Err(napi::Error::from_reason(
+                    "[VALIDATION] Tag timestamp must be non-negative",
+                ))
+            }
+            Some(ts) => ts as u64,
+            None => now,
+        };
+        spawn_on(&self.pool, move || {
             let guard = lock_inner(&inner)?;
-            let result = guard
-                .generate_with_options(
-                    &model,
-                    &prompt,
-                    max_tokens,
-                    temperature,
-                    top_k,
-                    top_p,
-                    seed,
-                    stop_tokens,
-                    stop_sequences,
-                )
+            if read_tag(&guard, &name)?.is_some() {
+                return Err(napi::Error::from_reason(format!(
+                    "[STATE] Tag already exists: {}",
+                    name
+                )));
+            }
+            let tag = serde_json::json!({
+                "name": name,
+                "timestamp": timestamp,
+                "branch": guard.current_branch().to_string(),
+                "createdAt": now,
+            });
+            let v = js_to_value_checked(tag.clone(), 0)?;
+            guard
+                .system_branch()
+                .kv_put(&tag_key(&name), v)
                 .map_err(to_napi_err)?;
-            Ok(serde_json::json!({
-                "text": result.text,
-                "stopReason": result.stop_reason,
-                "promptTokens": result.prompt_tokens,
-                "completionTokens": result.completion_tokens,
-                "model": result.model,
-            }))
+            Ok(tag)
         })
         .await
         .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
     }
 
-    /// Tokenize text using a model's tokenizer.
-    #[napi]
-    pub async fn tokenize(
-        &self,
-        model: String,
-        text: String,
-        options: Option<serde_json::Value>,
-    ) -> napi::Result<serde_json::Value> {
+    /// List all tags, oldest timestamp first.
+    #[napi(js_name = "tagList")]
+    pub async fn tag_list(&self) -> napi::Result<serde_json::Value> {
         let inner = self.inner.clone();
-        let add_special_tokens = options
-            .and_then(|o| o.as_object().and_then(|obj| obj.get("addSpecialTokens")?.as_bool()));
-        tokio::task::spawn_blocking(move || {
+        spawn_on(&self.pool, move || {
             let guard = lock_inner(&inner)?;
-            let result = guard
-                .tokenize(&model, &text, add_special_tokens)
+            let keys = guard
+                .system_branch()
+                .kv_list(Some(TAG_PREFIX))
                 .map_err(to_napi_err)?;
-            Ok(serde_json::json!({
-                "ids": result.ids,
-                "count": result.count,
-                "model": result.model,
-            }))
+            let mut tags = Vec::with_capacity(keys.len());
+            for key in keys {
+                if let Some(tag) = read_tag(&guard, &key[TAG_PREFIX.len()..])? {
+                    tags.push(tag);
+                }
+            }
+            tags.sort_by_key(|t| t["timestamp"].as_u64().unwrap_or(0));
+            Ok(serde_json::Value::Array(tags))
         })
         .await
         .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
     }
 
-    /// Detokenize token IDs back to text.
-    #[napi]
-    pub async fn detokenize(
-        &self,
-        model: String,
-        ids: Vec<u32>,
-    ) -> napi::Result<String> {
+    /// Delete a tag. Returns `true` if it existed.
+    #[napi(js_name = "tagDelete")]
+    pub async fn tag_delete(&self, name: String) -> napi::Result<bool> {
         let inner = self.inner.clone();
-        tokio::task::spawn_blocking(move || {
+        spawn_on(&self.pool, move || {
             let guard = lock_inner(&inner)?;
-            guard.detokenize(&model, ids).map_err(to_napi_err)
+            guard
+                .system_branch()
+                .kv_delete(&tag_key(&name))
+                .map_err(to_napi_err)
         })
         .await
         .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
     }
 
-    /// Unload a model from memory.
-    #[napi(js_name = "generateUnload")]
-    pub async fn generate_unload(&self, model: String) -> napi::Result<bool> {
+    // =========================================================================
+    // Space Management
+    // =========================================================================
+
+    /// Get the current space name.
+    #[napi(js_name = "currentSpace")]
+    pub async fn current_space(&self) -> napi::Result<String> {
         let inner = self.inner.clone();
-        tokio::task::spawn_blocking(move || {
+        spawn_on(&self.pool, move || {
             let guard = lock_inner(&inner)?;
-            guard.generate_unload(&model).map_err(to_napi_err)
+            Ok(guard.current_space().to_string())
         })
         .await
         .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
     }
 
-    // =========================================================================
-    // Model Management
-    // =========================================================================
+    /// Switch to a different space.
+    #[napi(js_name = "setSpace")]
+    pub async fn set_space(&self, space: String) -> napi::Result<()> {
+        let inner = self.inner.clone();
+        spawn_on(&self.pool, move || {
+            let mut guard = lock_inner_mut(&inner)?;
+            guard.set_space(&space).map_err(to_napi_err)
+        })
+        .await
+        .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
+    }
 
-    /// List all available models.
-    #[napi(js_name = "modelsList")]
-    pub async fn models_list(&self) -> napi::Result<serde_json::Value> {
+    /// List all spaces in the current branch.
+    #[napi(js_name = "listSpaces")]
+    pub async fn list_spaces(&self) -> napi::Result<Vec<String>> {
         let inner = self.inner.clone();
-        tokio::task::spawn_blocking(move || {
+        spawn_on(&self.pool, move || {
             let guard = lock_inner(&inner)?;
-            let models = guard.models_list().map_err(to_napi_err)?;
-            let arr: Vec<serde_json::Value> = models
-                .into_iter()
-                .map(|m| {
-                    serde_json::json!({
-                        "name": m.name,
-                        "task": m.task,
-                        "architecture": m.architecture,
-                        "defaultQuant": m.default_quant,
-                        "embeddingDim": m.embedding_dim,
-                        "isLocal": m.is_local,
-                        "sizeBytes": m.size_bytes,
-                    })
-                })
-                .collect();
-            Ok(serde_json::Value::Array(arr))
+            guard.list_spaces().map_err(to_napi_err)
         })
         .await
         .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
     }
 
-    /// Pull/download a model by name.
-    #[napi(js_name = "modelsPull")]
-    pub async fn models_pull(&self, name: String) -> napi::Result<serde_json::Value> {
+    /// Delete a space and all its data.
+    #[napi(js_name = "deleteSpace")]
+    pub async fn delete_space(&self, space: String) -> napi::Result<()> {
         let inner = self.inner.clone();
-        tokio::task::spawn_blocking(move || {
+        spawn_on(&self.pool, move || {
             let guard = lock_inner(&inner)?;
-            let (name, path) = guard.models_pull(&name).map_err(to_napi_err)?;
-            Ok(serde_json::json!({
-                "name": name,
-                "path": path,
-            }))
+            guard.delete_space(&space).map_err(to_napi_err)
         })
         .await
         .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
     }
 
-    /// List locally downloaded models.
-    #[napi(js_name = "modelsLocal")]
-    pub async fn models_local(&self) -> napi::Result<serde_json::Value> {
+    /// Force delete a space even if non-empty.
+    #[napi(js_name = "deleteSpaceForce")]
+    pub async fn delete_space_force(&self, space: String) -> napi::Result<()> {
         let inner = self.inner.clone();
-        tokio::task::spawn_blocking(move || {
+        spawn_on(&self.pool, move || {
             let guard = lock_inner(&inner)?;
-            let models = guard.models_local().map_err(to_napi_err)?;
-            let arr: Vec<serde_json::Value> = models
-                .into_iter()
-                .map(|m| {
-                    serde_json::json!({
-                        "name": m.name,
-                        "task": m.task,
-                        "architecture": m.architecture,
-                        "defaultQuant": m.default_quant,
-                        "embeddingDim": m.embedding_dim,
-                        "isLocal": m.is_local,
-                        "sizeBytes": m.size_bytes,
-                    })
-                })
-                .collect();
-            Ok(serde_json::Value::Array(arr))
+            guard.delete_space_force(&space).map_err(to_napi_err)
         })
         .await
         .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
     }
 
-    // =========================================================================
-    // Durability
-    // =========================================================================
+    /// Get statistics for one space on the current branch.
+    ///
+    /// Reports KV, state, JSON, event, vector, and graph counts plus an
+    /// estimate of the stored bytes. Every value in the space is read.
+    #[napi(js_name = "spaceStats")]
+    pub async fn space_stats(&self, space: String) -> napi::Result<serde_json::Value> {
+        let inner = self.inner.clone();
+        spawn_on(&self.pool, move || {
+            let guard = lock_inner(&inner)?;
+            if !guard.space_exists(&space).map_err(to_napi_err)? {
+                return Err(napi::Error::from_reason(format!(
+                    "[NOT_FOUND] Space not found: {}",
+                    space
+                )));
+            }
+            let mut view = detached_handle(&guard)?;
+            view.set_space(&space).map_err(to_napi_err)?;
+            let mut out = tally_space(&view)?.to_js();
+            out["space"] = serde_json::json!(space);
+            out["branch"] = serde_json::json!(guard.current_branch().to_string());
+            Ok(out)
+        })
+        .await
+        .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
+    }
 
-    /// Get WAL durability counters.
-    #[napi(js_name = "durabilityCounters")]
-    pub async fn durability_counters(&self) -> napi::Result<serde_json::Value> {
+    /// Copy a space's KV, state, and JSON data into a new space.
+    ///
+    /// Runs on the current branch unless `branch` is given. Events, vectors,
+    /// and graphs are not copied. Returns the number of entries copied.
+    #[napi(js_name = "spaceCopy")]
+    pub async fn space_copy(
+        &self,
+        source: String,
+        destination: String,
+        options: Option<JsSpaceCopyOptions>,
+    ) -> napi::Result<serde_json::Value> {
         let inner = self.inner.clone();
-        tokio::task::spawn_blocking(move || {
+        let target_branch = options.and_then(|o| o.branch);
+        spawn_on(&self.pool, move || {
             let guard = lock_inner(&inner)?;
-            let counters = guard.durability_counters().map_err(to_napi_err)?;
+            let on = target_branch.unwrap_or_else(|| guard.current_branch().to_string());
+            ensure_branch_writable(&guard, &on, false)?;
+            let mut view = detached_handle(&guard)?;
+            let copied = copy_space(&mut view, &on, &source, &destination);
             Ok(serde_json::json!({
-                "walAppends": counters.wal_appends,
-                "syncCalls": counters.sync_calls,
-                "bytesWritten": counters.bytes_written,
-                "syncNanos": counters.sync_nanos,
+                "source": source,
+                "destination": destination,
+                "branch": on,
+                "keysCopied": copied?,
             }))
         })
         .await
         .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
     }
 
-    // =========================================================================
-    // Graph — Lifecycle
-    // =========================================================================
+    /// Rename a space on the current branch.
+    ///
+    /// The space's KV, state, and JSON data is copied to the new name and
+    /// the old space is deleted. Spaces holding events, vectors, or graphs
+    /// are rejected, since those would be lost.
+    #[napi(js_name = "spaceRename")]
+    pub async fn space_rename(&self, old_name: String, new_name: String) -> napi::Result<()> {
+        let inner = self.inner.clone();
+        spawn_on(&self.pool, move || {
+            let mut guard = lock_inner_mut(&inner)?;
+            if old_name == "default" {
+                return Err(napi::Error::from_reason(
+                    "[CONSTRAINT] Cannot rename the default space",
+                ));
+            }
+            ensure_writable(&guard)?;
+            let branch = guard.current_branch().to_string();
+            let space = guard.current_space().to_string();
+            let renamed = rename_space(&mut guard, &branch, &old_name, &new_name);
+            let back = if space == old_name && renamed.is_ok() {
+                &new_name
+            } else {
+                &space
+            };
+            guard.set_space(back).map_err(to_napi_err)?;
+            renamed
+        })
+        .await
+        .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
+    }
 
-    /// Create a new graph.
-    #[napi(js_name = "graphCreate")]
-    pub async fn graph_create(
+    /// Read a page of the KV, state, and JSON writes made after
+    /// `sinceVersion`, on every branch, for `changelogExport()`.
+    ///
+    /// Returns `{ version, records, more }`: records are `{ version, branch,
+    /// space, primitive, key, op, value }`, oldest first, starting after
+    /// version `after` (default `sinceVersion`) and ending on a version
+    /// boundary once at least `limit` (default 1000) are read. `version` is
+    /// the newest version read, to pass as `after` for the next page while
+    /// `more` is true and as `sinceVersion` next time. Deletes carry no
+    /// version and come on the last page. Databases holding events,
+    /// vectors, or graphs are refused, since those can't be replayed.
+    #[napi(js_name = "changelogRead")]
+    pub async fn changelog_read(
         &self,
-        graph: String,
-        cascade_policy: Option<String>,
-    ) -> napi::Result<()> {
+        since_version: i64,
+        after: Option<i64>,
+        limit: Option<u32>,
+    ) -> napi::Result<JsValue> {
+        if since_version < 0 {
+            return Err(napi::Error::from_reason(
+                "[VALIDATION] sinceVersion must be a non-negative integer",
+            ));
+        }
+        let after = after.unwrap_or(since_version);
+        if after < since_version {
+            return Err(napi::Error::from_reason(
+                "[VALIDATION] after must not be less than sinceVersion",
+            ));
+        }
+        let limit = limit.unwrap_or(CHANGELOG_PAGE);
+        if limit == 0 {
+            return Err(napi::Error::from_reason(
+                "[VALIDATION] limit must be a positive integer",
+            ));
+        }
         let inner = self.inner.clone();
-        tokio::task::spawn_blocking(move || {
+        spawn_on(&self.pool, move || {
             let guard = lock_inner(&inner)?;
-            guard
-                .graph_create_with_policy(&graph, cascade_policy.as_deref())
-                .map_err(to_napi_err)
+            let mut view = detached_handle(&guard)?;
+            let (since, after) = (since_version as u64, after as u64);
+            let ChangePage {
+                records,
+                version,
+                more,
+            } = read_changes(&mut view, since, after, limit as usize)?;
+            let records = records.into_iter().map(ChangeRecord::into_value).collect();
+            let mut out = HashMap::new();
+            out.insert("version".to_string(), Value::Int(version as i64));
+            out.insert("records".to_string(), Value::Array(Box::new(records)));
+            out.insert("more".to_string(), Value::Bool(more));
+            Ok(JsValue(Value::Object(Box::new(out)), None))
         })
         .await
         .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
     }
 
-    /// Delete a graph.
-    #[napi(js_name = "graphDelete")]
-    pub async fn graph_delete(&self, graph: String) -> napi::Result<()> {
+    /// Apply a batch of changelog records in order, for `changelogApply()`.
+    /// Branches and spaces that don't exist are created. Returns the number
+    /// of records applied.
+    #[napi(js_name = "changelogApplyBatch")]
+    pub async fn changelog_apply_batch(
+        &self,
+        #[napi(ts_arg_type = "Array<any>")] records: Vec<JsBatchEntry>,
+        json_limits: Option<JsJsonLimits>,
+    ) -> napi::Result<u32> {
         let inner = self.inner.clone();
-        tokio::task::spawn_blocking(move || {
+        let limits = self.json_limits.with(json_limits.as_ref())?;
+        let batch = records
+            .into_iter()
+            .map(|v| {
+                limits.check_exact(&v.0)?;
+                let mut obj = batch_entry(v)?;
+                let primitive = match take_string(&mut obj, "primitive")?.as_str() {
+                    "kv" => "kv",
+                    "state" => "state",
+                    "json" => "json",
+                    other => {
+                        return Err(napi::Error::from_reason(format!(
+                            "[VALIDATION] Unknown primitive '{}'; expected kv, state, or json",
+                            other
+                        )))
+                    }
+                };
+                let value = match take_string(&mut obj, "op")?.as_str() {
+                    "put" => Some(take_field(&mut obj, "value")?),
+                    "delete" => None,
+                    other => {
+                        return Err(napi::Error::from_reason(format!(
+                            "[VALIDATION] Unknown op '{}'; expected put or delete",
+                            other
+                        )))
+                    }
+                };
+                if let Some(v) = &value {
+                    limits.check(v)?;
+                }
+                Ok(ChangeRecord {
+                    version: None,
+                    branch: take_string(&mut obj, "branch")?,
+                    space: take_string(&mut obj, "space")?,
+                    primitive,
+                    key: take_string(&mut obj, "key")?,
+                    value,
+                })
+            })
+            .collect::<napi::Result<Vec<_>>>()?;
+        spawn_on(&self.pool, move || {
             let guard = lock_inner(&inner)?;
-            guard.graph_delete(&graph).map_err(to_napi_err)
+            let mut view = detached_handle(&guard)?;
+            apply_changes(&mut view, batch)
         })
         .await
         .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
     }
 
-    /// List all graph names.
-    #[napi(js_name = "graphList")]
-    pub async fn graph_list(&self) -> napi::Result<Vec<String>> {
+    /// Read every KV, state, and JSON entry of `branch`, in every space,
+    /// for `syncTo()`. Returns `[{ space, primitive, key, value }, ...]`.
+    /// Refuses a branch with a space holding events, vectors, or graphs.
+    #[napi(js_name = "syncRead")]
+    pub async fn sync_read(&self, branch: String) -> napi::Result<JsValue> {
         let inner = self.inner.clone();
-        tokio::task::spawn_blocking(move || {
+        spawn_on(&self.pool, move || {
             let guard = lock_inner(&inner)?;
-            guard.graph_list().map_err(to_napi_err)
+            let mut view = detached_handle(&guard)?;
+            let entries = read_branch_entries(&mut view, &branch)?;
+            Ok(JsValue(Value::Array(Box::new(entries)), None))
         })
         .await
         .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
     }
 
-    /// Get graph metadata.
-    #[napi(js_name = "graphGetMeta")]
-    pub async fn graph_get_meta(&self, graph: String) -> napi::Result<serde_json::Value> {
+    /// Read one page of a space's KV, state, or JSON entries on the current
+    /// branch, for `dump()`. With `primitive` `"event"`, `prefix` is an
+    /// event type and the entries are its events, keyed by sequence.
+    ///
+    /// Returns `{ entries: [[key, value], ...], cursor }`. Pass `cursor`
+    /// back for the next page; it is `null` after the last one.
+    #[napi(js_name = "dumpPage")]
+    pub async fn dump_page(
+        &self,
+        space: String,
+        primitive: String,
+        prefix: Option<String>,
+        cursor: Option<String>,
+        limit: u32,
+    ) -> napi::Result<JsValue> {
         let inner = self.inner.clone();
-        tokio::task::spawn_blocking(move || {
+        spawn_on(&self.pool, move || {
             let guard = lock_inner(&inner)?;
-            match guard.graph_get_meta(&graph).map_err(to_napi_err)? {
-                Some(v) => Ok(value_to_js(v)),
-                None => Ok(serde_json::Value::Null),
+            if !guard.space_exists(&space).map_err(to_napi_err)? {
+                return Err(napi::Error::from_reason(format!(
+                    "[NOT_FOUND] Space not found: {}",
+                    space
+                )));
             }
+            let mut view = detached_handle(&guard)?;
+            view.set_space(&space).map_err(to_napi_err)?;
+            let (entries, next) =
+                read_page(&view, &primitive, prefix, cursor, limit.max(1) as usize)?;
+            let entries = entries
+                .into_iter()
+                .map(|(k, v)| Value::Array(Box::new(vec![Value::String(k), v])))
+                .collect();
+            let mut out = HashMap::new();
+            out.insert("entries".to_string(), Value::Array(Box::new(entries)));
+            let cursor = next.map_or(Value::Null, Value::String);
+            out.insert("cursor".to_string(), cursor);
+            Ok(JsValue(Value::Object(Box::new(out)), None))
         })
         .await
         .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
     }
 
-    // =========================================================================
-    // Graph — Nodes
-    // =========================================================================
-
-    /// Add or update a node.
-    #[napi(js_name = "graphAddNode")]
-    pub async fn graph_add_node(
+    /// Write a batch of `{ space, primitive, key, value }` entries on the
+    /// current branch, for `load()`. Spaces that don't exist are created.
+    /// Returns the number of entries written.
+    #[napi(js_name = "loadBatch")]
+    pub async fn load_batch(
         &self,
-        graph: String,
-        node_id: String,
-        entity_ref: Option<String>,
-        properties: Option<serde_json::Value>,
-        object_type: Option<String>,
-    ) -> napi::Result<()> {
+        #[napi(ts_arg_type = "Array<any>")] entries: Vec<JsBatchEntry>,
+        json_limits: Option<JsJsonLimits>,
+    ) -> napi::Result<u32> {
+        let inner = self.inner.clone();
+        let limits = self.json_limits.with(json_limits.as_ref())?;
+        let mut batch: HashMap<String, SpaceData> = HashMap::new();
+        for v in entries {
+            limits.check_exact(&v.0)?;
+            let mut obj = batch_entry(v)?;
+            let space = take_string(&mut obj, "space")?;
+            let primitive = take_string(&mut obj, "primitive")?;
+            let key = take_string(&mut obj, "key")?;
+            let value = take_field(&mut obj, "value")?;
+            limits.check(&value)?;
+            let data = batch.entry(space).or_default();
+            match primitive.as_str() {
+                "kv" => data.kv.push((key, value)),
+                "state" => data.cells.push((key, value)),
+                "json" => data.docs.push((key, value)),
+                other => {
+                    return Err(napi::Error::from_reason(format!(
+                        "[VALIDATION] Unknown primitive '{}'; expected kv, state, or json",
+                        other
+                    )))
+                }
+            }
+        }
+        spawn_on(&self.pool, move || {
+            let guard = lock_inner(&inner)?;
+            ensure_writable(&guard)?;
+            let mut view = detached_handle(&guard)?;
+            load_spaces(&mut view, batch)
+        })
+        .await
+        .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
+    }
+
+    // =========================================================================
+    // Database Operations
+    // =========================================================================
+
+    /// Check database connectivity.
+    #[napi]
+    pub async fn ping(&self) -> napi::Result<String> {
         let inner = self.inner.clone();
-        let props = properties
-            .map(|p| js_to_value_checked(p, 0))
-            .transpose()?;
-        tokio::task::spawn_blocking(move || {
+        spawn_on(&self.pool, move || {
             let guard = lock_inner(&inner)?;
-            guard
-                .graph_add_node_typed(
-                    &graph,
-                    &node_id,
-                    entity_ref.as_deref(),
-                    props,
-                    object_type.as_deref(),
-                )
-                .map_err(to_napi_err)
+            guard.ping().map_err(to_napi_err)
         })
         .await
         .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
     }
 
-    /// Get a node.
-    #[napi(js_name = "graphGetNode")]
-    pub async fn graph_get_node(
-        &self,
-        graph: String,
-        node_id: String,
-    ) -> napi::Result<serde_json::Value> {
+    /// Get database info.
+    ///
+    /// `diskBytes` is the size of the database directory when requested
+    /// with `{ diskBytes: true }`, and `null` otherwise or for an in-memory
+    /// database.
+    #[napi]
+    pub async fn info(&self, options: Option<JsInfoOptions>) -> napi::Result<serde_json::Value> {
         let inner = self.inner.clone();
-        tokio::task::spawn_blocking(move || {
+        let path = self
+            .path
+            .clone()
+            .filter(|_| options.and_then(|o| o.disk_bytes).unwrap_or(false));
+        spawn_on(&self.pool, move || {
             let guard = lock_inner(&inner)?;
-            match guard.graph_get_node(&graph, &node_id).map_err(to_napi_err)? {
-                Some(v) => Ok(value_to_js(v)),
-                None => Ok(serde_json::Value::Null),
-            }
+            let info = guard.info().map_err(to_napi_err)?;
+            let disk_bytes = match &path {
+                Some(path) => Some(dir_size(std::path::Path::new(path)).map_err(io_err)?),
+                None => None,
+            };
+            Ok(serde_json::json!({
+                "version": info.version,
+                "uptimeSecs": info.uptime_secs,
+                "branchCount": info.branch_count,
+                "totalKeys": info.total_keys,
+                "diskBytes": disk_bytes,
+            }))
         })
         .await
         .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
     }
 
-    /// Remove a node and its incident edges.
-    #[napi(js_name = "graphRemoveNode")]
-    pub async fn graph_remove_node(
-        &self,
-        graph: String,
-        node_id: String,
-    ) -> napi::Result<()> {
+    /// Get a structured snapshot of the database for agent introspection.
+    ///
+    /// Returns version, branch, spaces, follower status, per-primitive
+    /// summaries (counts, collections, graphs), configuration, and
+    /// capability flags — everything an agent needs to plan its actions.
+    #[napi]
+    pub async fn describe(&self) -> napi::Result<serde_json::Value> {
         let inner = self.inner.clone();
-        tokio::task::spawn_blocking(move || {
+        spawn_on(&self.pool, move || {
             let guard = lock_inner(&inner)?;
-            guard.graph_remove_node(&graph, &node_id).map_err(to_napi_err)
+            let result = guard.describe().map_err(to_napi_err)?;
+            serde_json::to_value(result)
+                .map_err(|e| napi::Error::from_reason(format!("Failed to serialize DescribeResult: {}", e)))
         })
         .await
         .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
     }
 
-    /// List all node IDs in a graph.
-    #[napi(js_name = "graphListNodes")]
-    pub async fn graph_list_nodes(&self, graph: String) -> napi::Result<Vec<String>> {
+    /// Flush writes to disk.
+    #[napi]
+    pub async fn flush(&self) -> napi::Result<()> {
         let inner = self.inner.clone();
-        tokio::task::spawn_blocking(move || {
+        let last_flush = self.last_flush.clone();
+        let syncs_seen = self.syncs_seen.clone();
+        spawn_on(&self.pool, move || {
             let guard = lock_inner(&inner)?;
-            guard.graph_list_nodes(&graph).map_err(to_napi_err)
+            guard.flush().map_err(to_napi_err)?;
+            last_flush.fetch_max(now_micros(), Ordering::Relaxed);
+            // The flush's own syncs are accounted for.
+            if let Ok(counters) = guard.durability_counters() {
+                syncs_seen.fetch_max(counters.sync_calls, Ordering::Relaxed);
+            }
+            Ok(())
         })
         .await
         .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
     }
 
-    /// List node IDs with cursor-based pagination.
-    #[napi(js_name = "graphListNodesPaginated")]
-    pub async fn graph_list_nodes_paginated(
+    /// Trigger compaction.
+    ///
+    /// Returns `durationMs` and, for a database on disk, the size of its
+    /// directory before and after (`diskBytesBefore`, `diskBytesAfter`).
+    /// The engine compacts the whole database and cannot stop once started.
+    #[napi]
+    pub async fn compact(&self, operation_id: Option<u32>) -> napi::Result<serde_json::Value> {
+        let inner = self.inner.clone();
+        let path = self.path.clone();
+        let op = CancellableOp::start(operation_id);
+        spawn_on(&self.pool, move || {
+            op.check()?;
+            let guard = lock_inner(&inner)?;
+            let disk_bytes = || match &path {
+                Some(path) => dir_size(std::path::Path::new(path))
+                    .map(Some)
+                    .map_err(io_err),
+                None => Ok(None),
+            };
+            let before = disk_bytes()?;
+            let started = std::time::Instant::now();
+            guard.compact().map_err(to_napi_err)?;
+            let elapsed = started.elapsed();
+            Ok(serde_json::json!({
+                "durationMs": elapsed.as_secs_f64() * 1000.0,
+                "diskBytesBefore": before,
+                "diskBytesAfter": disk_bytes()?,
+            }))
+        })
+        .await
+        .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
+    }
+
+    /// Check the database for damage without changing it.
+    ///
+    /// Walks every branch and space, checking branch metadata and that each
+    /// primitive's listing can be read. With `deep`, also reads every
+    /// latest value, event, and graph edge, so the engine verifies their
+    /// checksums and listed keys are checked against stored values.
+    /// Returns `{ ok, deep, branches, spaces, entriesChecked, problems }`;
+    /// failures are reported as problems rather than thrown.
+    #[napi]
+    pub async fn verify(
         &self,
-        graph: String,
-        limit: u32,
-        cursor: Option<String>,
+        deep: Option<bool>,
+        operation_id: Option<u32>,
     ) -> napi::Result<serde_json::Value> {
         let inner = self.inner.clone();
-        tokio::task::spawn_blocking(move || {
+        let op = CancellableOp::start(operation_id);
+        spawn_on(&self.pool, move || {
             let guard = lock_inner(&inner)?;
-            let (items, next_cursor) = guard
-                .graph_list_nodes_paginated(&graph, limit as usize, cursor.as_deref())
-                .map_err(to_napi_err)?;
-            Ok(serde_json::json!({
-                "items": items,
-                "nextCursor": next_cursor,
-            }))
+            let mut view = detached_handle(&guard)?;
+            let mut verifier = Verifier::new(deep.unwrap_or(false), op);
+            verifier.database(&mut view)?;
+            Ok(verifier.report())
         })
         .await
         .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
     }
 
     // =========================================================================
-    // Graph — Edges
+    // Bundle Operations
     // =========================================================================
 
-    /// Add or update an edge.
-    #[napi(js_name = "graphAddEdge")]
-    pub async fn graph_add_edge(
+    /// Export a branch to a bundle file.
+    ///
+    /// Pass `options` to export only some spaces, key prefixes, or
+    /// primitives, or to leave out version history. `onProgress` receives
+    /// `{ entriesDone, bytesDone }`; `operationId` makes the export
+    /// cancellable through `bundleCancel()`.
+    #[napi(js_name = "branchExport")]
+    pub async fn branch_export(
         &self,
-        graph: String,
-        src: String,
-        dst: String,
-        edge_type: String,
-        weight: Option<f64>,
-        properties: Option<serde_json::Value>,
-    ) -> napi::Result<()> {
+        branch: String,
+        path: String,
+        options: Option<JsBundleExportOptions>,
+        on_progress: Option<ProgressCallback>,
+        operation_id: Option<u32>,
+    ) -> napi::Result<serde_json::Value> {
         let inner = self.inner.clone();
-        let props = properties
-            .map(|p| js_to_value_checked(p, 0))
-            .transpose()?;
-        tokio::task::spawn_blocking(move || {
+        let op = CancellableOp::start(operation_id);
+        let progress = bundle_progress(on_progress, &op);
+        let options = match (options, progress) {
+            (None, None) => None,
+            (options, progress) => {
+                let mut opts = bundle_export_options(options.unwrap_or_default())?;
+                opts.progress = progress;
+                Some(opts)
+            }
+        };
+        spawn_on(&self.pool, move || {
+            let _op = op;
             let guard = lock_inner(&inner)?;
-            guard
-                .graph_add_edge(&graph, &src, &dst, &edge_type, weight, props)
-                .map_err(to_napi_err)
+            let result = export_bundle(&guard, &branch, &path, options)?;
+            Ok(branch_export_result_to_js(result))
         })
         .await
         .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
     }
 
-    /// Remove an edge.
-    #[napi(js_name = "graphRemoveEdge")]
-    pub async fn graph_remove_edge(
+    /// Export only the changes made to a branch after `sinceVersion`.
+    ///
+    /// The delta bundle is applied by `branchImport` on top of a copy of the
+    /// branch that already holds everything up to `sinceVersion`.
+    #[napi(js_name = "branchExportIncremental")]
+    pub async fn branch_export_incremental(
         &self,
-        graph: String,
-        src: String,
-        dst: String,
-        edge_type: String,
-    ) -> napi::Result<()> {
+        branch: String,
+        since_version: i64,
+        path: String,
+        options: Option<JsBundleExportOptions>,
+    ) -> napi::Result<serde_json::Value> {
         let inner = self.inner.clone();
-        tokio::task::spawn_blocking(move || {
+        if since_version < 0 {
+            return Err(napi::Error::from_reason(
+                "[VALIDATION] sinceVersion must be non-negative",
+            ));
+        }
+        let mut opts = bundle_export_options(options.unwrap_or_default())?;
+        opts.since_version = Some(since_version as u64);
+        spawn_on(&self.pool, move || {
             let guard = lock_inner(&inner)?;
-            guard
-                .graph_remove_edge(&graph, &src, &dst, &edge_type)
-                .map_err(to_napi_err)
+            let result = export_bundle(&guard, &branch, &path, Some(opts))?;
+            Ok(branch_export_result_to_js(result))
         })
         .await
         .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
     }
 
-    /// Get neighbors of a node.
-    #[napi(js_name = "graphNeighbors")]
-    pub async fn graph_neighbors(
+    /// Export several branches as of one moment, for `backup()`.
+    ///
+    /// Writes through every handle on the database wait until the last
+    /// export finishes, so the bundles and the `_system_` records under
+    /// `systemPrefixes` form one consistent cut. Returns the cut's
+    /// `version`, where the next incremental backup starts, each export's
+    /// result, and the records as `[key, value]` pairs. Nothing is written,
+    /// so read-only and follower handles can back up too.
+    #[napi(js_name = "backupBranches")]
+    pub async fn backup_branches(
         &self,
-        graph: String,
-        node_id: String,
-        direction: Option<String>,
-        edge_type: Option<String>,
+        branches: Vec<JsBackupBranch>,
+        system_prefixes: Vec<String>,
     ) -> napi::Result<serde_json::Value> {
         let inner = self.inner.clone();
-        tokio::task::spawn_blocking(move || {
+        for b in &branches {
+            if b.since_version.is_some_and(|v| v < 0) {
+                return Err(napi::Error::from_reason(
+                    "[VALIDATION] sinceVersion must be non-negative",
+                ));
+            }
+        }
+        spawn_on(&self.pool, move || {
+            // No write through any handle lands between the first export and
+            // the last.
+            let guard = lock_database(&inner)?;
+            let version = guard.database().current_version();
+            let mut exports = Vec::with_capacity(branches.len());
+            for b in branches {
+                let options = match b.since_version {
+                    Some(since) => {
+                        let mut opts = bundle_export_options(JsBundleExportOptions::default())?;
+                        opts.since_version = Some(since as u64);
+                        Some(opts)
+                    }
+                    None => None,
+                };
+                let result = export_bundle(&guard, &b.branch, &b.path, options)?;
+                exports.push(branch_export_result_to_js(result));
+            }
+            let sys = guard.system_branch();
+            let mut records = Vec::new();
+            for prefix in &system_prefixes {
+                for key in sys.kv_list(Some(prefix)).map_err(to_napi_err)? {
+                    if let Some(v) = sys.kv_get(&key).map_err(to_napi_err)? {
+                        records.push(serde_json::json!([key, value_to_js(v)]));
+                    }
+                }
+            }
+            Ok(serde_json::json!({
+                "version": version,
+                "exports": exports,
+                "system": records,
+            }))
+        })
+        .await
+        .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
+    }
+
+    /// Import a branch from a bundle file.
+    ///
+    /// Delta bundles from `branchExportIncremental` are applied on top of the
+    /// existing branch instead of creating a new one. `onProgress` and
+    /// `operationId` work as for `branchExport`.
+    #[napi(js_name = "branchImport")]
+    pub async fn branch_import(
+        &self,
+        path: String,
+        options: Option<JsBundleImportOptions>,
+        on_progress: Option<ProgressCallback>,
+        operation_id: Option<u32>,
+    ) -> napi::Result<serde_json::Value> {
+        let inner = self.inner.clone();
+        let (mut options, on_conflict) = bundle_import_options(options)?;
+        let op = CancellableOp::start(operation_id);
+        options.progress = bundle_progress(on_progress, &op);
+        spawn_on(&self.pool, move || {
+            let _op = op;
             let guard = lock_inner(&inner)?;
-            let dir = direction.as_deref().unwrap_or("outgoing");
-            let neighbors = guard
-                .graph_neighbors(&graph, &node_id, dir, edge_type.as_deref())
+            let result = import_bundle(&guard, &path, options, on_conflict)?;
+            Ok(branch_import_result_to_js(result))
+        })
+        .await
+        .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
+    }
+
+    /// Validate a bundle file without importing.
+    ///
+    /// Checksums of an encrypted bundle are only verified when its key is
+    /// given; the header (including `keyId`) is always readable. `onProgress`
+    /// and `operationId` work as for `branchExport`.
+    #[napi(js_name = "branchValidateBundle")]
+    pub async fn branch_validate_bundle(
+        &self,
+        path: String,
+        options: Option<JsBundleImportOptions>,
+        on_progress: Option<ProgressCallback>,
+        operation_id: Option<u32>,
+    ) -> napi::Result<serde_json::Value> {
+        let inner = self.inner.clone();
+        let (mut options, _) = bundle_import_options(options)?;
+        let op = CancellableOp::start(operation_id);
+        options.progress = bundle_progress(on_progress, &op);
+        spawn_on(&self.pool, move || {
+            let _op = op;
+            let guard = lock_inner(&inner)?;
+            let result = guard
+                .branch_validate_bundle_with_options(&path, &options)
                 .map_err(to_napi_err)?;
-            let arr: Vec<serde_json::Value> = neighbors
-                .into_iter()
-                .map(|n| {
-                    serde_json::json!({
-                        "nodeId": n.node_id,
-                        "edgeType": n.edge_type,
-                        "weight": n.weight,
-                    })
-                })
-                .collect();
-            Ok(serde_json::Value::Array(arr))
+            Ok(bundle_validate_result_to_js(result))
+        })
+        .await
+        .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
+    }
+
+    /// Export a branch for streaming. The bundle is staged in a temporary
+    /// file and handed out in chunks by the returned reader.
+    #[napi(js_name = "branchExportOpen")]
+    pub async fn branch_export_open(
+        &self,
+        branch: String,
+        options: Option<JsBundleExportOptions>,
+    ) -> napi::Result<BundleReader> {
+        let inner = self.inner.clone();
+        let options = options.map(bundle_export_options).transpose()?;
+        spawn_on(&self.pool, move || {
+            let guard = lock_inner(&inner)?;
+            let path = bundle_temp_path();
+            let exported = export_bundle(&guard, &branch, &path.to_string_lossy(), options)
+                .and_then(|_| std::fs::File::open(&path).map_err(io_err));
+            match exported {
+                Ok(file) => Ok(BundleReader {
+                    file: Arc::new(Mutex::new(Some(file))),
+                    path,
+                }),
+                Err(e) => {
+                    let _ = std::fs::remove_file(&path);
+                    Err(e)
+                }
+            }
         })
         .await
         .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
     }
 
+    /// Start a streamed import. Chunks written to the returned writer are
+    /// staged in a temporary file and imported on `finish()`.
+    #[napi(js_name = "branchImportOpen")]
+    pub fn branch_import_open(
+        &self,
+        options: Option<JsBundleImportOptions>,
+    ) -> napi::Result<BundleWriter> {
+        ensure_open(&self.inner)?;
+        let (options, on_conflict) = bundle_import_options(options)?;
+        let path = bundle_temp_path();
+        let file = std::fs::File::create(&path).map_err(io_err)?;
+        Ok(BundleWriter {
+            inner: self.inner.clone(),
+            file: Arc::new(Mutex::new(Some(file))),
+            path,
+            options,
+            on_conflict,
+        })
+    }
+
+    /// Cancel the bundle export, import, or validation started with this
+    /// `operationId`. Same as `cancelOperation`.
+    #[napi(js_name = "bundleCancel")]
+    pub fn bundle_cancel(&self, operation_id: u32) {
+        self.cancel_operation(operation_id);
+    }
+
+    /// Cancel the operation started with this `operationId`. Bundle
+    /// operations stop at their next progress check; search, vector search,
+    /// diff, and compaction stop before their next step, or never start if
+    /// still queued for a worker thread. Either way the call rejects.
+    #[napi(js_name = "cancelOperation")]
+    pub fn cancel_operation(&self, operation_id: u32) {
+        cancel_flag(operation_id).store(true, Ordering::Relaxed);
+    }
+
     // =========================================================================
-    // Graph — Bulk & Traversal
+    // Transaction Operations
     // =========================================================================
 
-    /// Bulk insert nodes and edges into a graph.
-    #[napi(js_name = "graphBulkInsert")]
-    pub async fn graph_bulk_insert(
-        &self,
-        graph: String,
-        nodes: Vec<serde_json::Value>,
-        edges: Vec<serde_json::Value>,
-        chunk_size: Option<u32>,
-    ) -> napi::Result<serde_json::Value> {
+    /// Begin a new transaction.
+    ///
+    /// Pass `true` or `{ readOnly: true }` for a read-only transaction. A
+    /// read-only transaction with `asOf` is a snapshot: every read in it
+    /// sees the database as of that timestamp or tag.
+    ///
+    /// `branch` and `space` target the transaction without switching the
+    /// handle: `execute()` commands that don't name a branch or space use
+    /// them until the transaction ends.
+    #[napi(js_name = "begin")]
+    pub async fn begin(&self, options: Option<Either<bool, JsBeginOptions>>) -> napi::Result<()> {
         let inner = self.inner.clone();
-        let bulk_nodes: Vec<BulkGraphNode> = nodes
-            .into_iter()
-            .map(|v| {
-                let obj = v
-                    .as_object()
-                    .ok_or_else(|| napi::Error::from_reason("[VALIDATION] Expected object"))?;
-                let node_id = obj
-                    .get("nodeId")
-                    .or_else(|| obj.get("node_id"))
-                    .and_then(|k| k.as_str())
-                    .ok_or_else(|| napi::Error::from_reason("[VALIDATION] Missing 'nodeId'"))?
-                    .to_string();
-                let entity_ref = obj
-                    .get("entityRef")
-                    .or_else(|| obj.get("entity_ref"))
-                    .and_then(|k| k.as_str())
-                    .map(|s| s.to_string());
-                let properties = obj
-                    .get("properties")
-                    .filter(|v| !v.is_null())
-                    .map(|p| js_to_value_checked(p.clone(), 0))
-                    .transpose()?;
-                let object_type = obj
-                    .get("objectType")
-                    .or_else(|| obj.get("object_type"))
-                    .and_then(|k| k.as_str())
-                    .map(|s| s.to_string());
-                Ok(BulkGraphNode {
-                    node_id,
-                    entity_ref,
-                    properties,
-                    object_type,
-                })
-            })
-            .collect::<napi::Result<_>>()?;
-        let bulk_edges: Vec<BulkGraphEdge> = edges
-            .into_iter()
-            .map(|v| {
-                let obj = v
-                    .as_object()
-                    .ok_or_else(|| napi::Error::from_reason("[VALIDATION] Expected object"))?;
-                let src = obj
-                    .get("src")
-                    .and_then(|k| k.as_str())
-                    .ok_or_else(|| napi::Error::from_reason("[VALIDATION] Missing 'src'"))?
-                    .to_string();
-                let dst = obj
-                    .get("dst")
-                    .and_then(|k| k.as_str())
-                    .ok_or_else(|| napi::Error::from_reason("[VALIDATION] Missing 'dst'"))?
-                    .to_string();
-                let edge_type = obj
-                    .get("edgeType")
-                    .or_else(|| obj.get("edge_type"))
-                    .and_then(|k| k.as_str())
-                    .ok_or_else(|| napi::Error::from_reason("[VALIDATION] Missing 'edgeType'"))?
-                    .to_string();
-                let weight = obj.get("weight").and_then(|w| w.as_f64());
-                let properties = obj
-                    .get("properties")
-                    .filter(|v| !v.is_null())
-                    .map(|p| js_to_value_checked(p.clone(), 0))
-                    .transpose()?;
-                Ok(BulkGraphEdge {
-                    src,
-                    dst,
-                    edge_type,
-                    weight,
-                    properties,
-                })
-            })
-            .collect::<napi::Result<_>>()?;
-        tokio::task::spawn_blocking(move || {
+        let session_arc = self.session.clone();
+        let scope_arc = self.txn_scope.clone();
+        let (read_only, as_of, scope) = match options {
+            None => (false, None, TxnScope::default()),
+            Some(Either::A(read_only)) => (read_only, None, TxnScope::default()),
+            Some(Either::B(o)) => (
+                o.read_only.unwrap_or(false),
+                o.as_of,
+                TxnScope {
+                    branch: o.branch,
+                    space: o.space,
+                    ..TxnScope::default()
+                },
+            ),
+        };
+        if as_of.is_some() && !read_only {
+            return Err(napi::Error::from_reason(
+                "[VALIDATION] asOf requires a read-only transaction",
+            ));
+        }
+        spawn_on(&self.pool, move || {
+            let mut session_ref = lock_session(&session_arc)?;
             let guard = lock_inner(&inner)?;
-            let (nodes_inserted, edges_inserted) = guard
-                .graph_bulk_insert_typed(
-                    &graph,
-                    bulk_nodes,
-                    bulk_edges,
-                    chunk_size.map(|c| c as usize),
-                )
-                .map_err(to_napi_err)?;
-            Ok(serde_json::json!({
-                "nodesInserted": nodes_inserted,
-                "edgesInserted": edges_inserted,
-            }))
+            let as_of = resolve_as_of(&guard, as_of)?;
+            if let Some(branch) = &scope.branch {
+                if !guard.branches().exists(branch).map_err(to_napi_err)? {
+                    return Err(napi::Error::from_reason(format!(
+                        "[NOT_FOUND] Branch not found: {}",
+                        branch
+                    )));
+                }
+            }
+            if session_ref.is_none() {
+                *session_ref = Some(guard.session());
+            }
+            drop(guard);
+            let session = session_ref.as_mut().unwrap();
+            let cmd = Command::TxnBegin {
+                branch: scope.branch.clone().map(Into::into),
+                options: Some(TxnOptions { read_only, as_of }),
+            };
+            session.execute(cmd).map_err(to_napi_err)?;
+            *lock_scope(&scope_arc)? = TxnScope {
+                active: true,
+                ..scope
+            };
+            Ok(())
         })
         .await
         .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
     }
 
-    /// BFS traversal from a start node.
-    #[napi(js_name = "graphBfs")]
-    pub async fn graph_bfs(
+    /// Commit the current transaction. Returns the commit version.
+    ///
+    /// With `{ detailed: true }`, returns `{ version, operations }` instead,
+    /// listing each write made through `execute()` in the transaction with
+    /// the version it was stamped with. Every write in a transaction gets
+    /// the commit version.
+    #[napi]
+    pub async fn commit(
         &self,
-        graph: String,
-        start: String,
-        max_depth: u32,
-        max_nodes: Option<u32>,
-        edge_types: Option<Vec<String>>,
-        direction: Option<String>,
-    ) -> napi::Result<serde_json::Value> {
-        let inner = self.inner.clone();
-        tokio::task::spawn_blocking(move || {
-            let guard = lock_inner(&inner)?;
-            let result = guard
-                .graph_bfs(
-                    &graph,
-                    &start,
-                    max_depth as usize,
-                    max_nodes.map(|n| n as usize),
-                    edge_types,
-                    direction.as_deref(),
-                )
-                .map_err(to_napi_err)?;
-            Ok(graph_bfs_result_to_js(result))
+        options: Option<JsCommitOptions>,
+    ) -> napi::Result<Either<i64, serde_json::Value>> {
+        let session_arc = self.session.clone();
+        let scope_arc = self.txn_scope.clone();
+        let detailed = options.and_then(|o| o.detailed).unwrap_or(false);
+        spawn_on(&self.pool, move || {
+            let mut session_ref = lock_session(&session_arc)?;
+            let session = session_ref
+                .as_mut()
+                .ok_or_else(|| napi::Error::from_reason("[STATE] No transaction active"))?;
+            let committed = session.execute(Command::TxnCommit);
+            // A failed commit ends the transaction too.
+            let scope = std::mem::take(&mut *lock_scope(&scope_arc)?);
+            let version = match committed.map_err(to_napi_err)? {
+                Output::TxnCommitted { version } => version,
+                _ => return Err(napi::Error::from_reason("Unexpected output for TxnCommit")),
+            };
+            if !detailed {
+                return Ok(Either::A(version as i64));
+            }
+            let operations: Vec<serde_json::Value> = scope
+                .writes
+                .into_iter()
+                .map(|mut w| {
+                    w["version"] = serde_json::json!(version);
+                    w
+                })
+                .collect();
+            Ok(Either::B(serde_json::json!({
+                "version": version,
+                "operations": operations,
+            })))
         })
         .await
         .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
     }
 
-    // =========================================================================
-    // Graph — Ontology
-    // =========================================================================
+    /// Rollback the current transaction.
+    #[napi]
+    pub async fn rollback(&self) -> napi::Result<()> {
+        let session_arc = self.session.clone();
+        let scope_arc = self.txn_scope.clone();
+        spawn_on(&self.pool, move || {
+            let mut session_ref = lock_session(&session_arc)?;
+            let session = session_ref
+                .as_mut()
+                .ok_or_else(|| napi::Error::from_reason("[STATE] No transaction active"))?;
+            *lock_scope(&scope_arc)? = TxnScope::default();
+            session.execute(Command::TxnRollback).map_err(to_napi_err)?;
+            Ok(())
+        })
+        .await
+        .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
+    }
 
-    /// Define an object type in the graph ontology.
-    #[napi(js_name = "graphDefineObjectType")]
-    pub async fn graph_define_object_type(
-        &self,
-        graph: String,
-        definition: serde_json::Value,
-    ) -> napi::Result<()> {
-        let inner = self.inner.clone();
-        let def = js_to_value_checked(definition, 0)?;
-        tokio::task::spawn_blocking(move || {
-            let guard = lock_inner(&inner)?;
-            guard
-                .graph_define_object_type(&graph, def)
-                .map_err(to_napi_err)
+    /// Mark a savepoint in the active transaction. Reusing a name moves
+    /// the savepoint.
+    #[napi(js_name = "txSavepoint")]
+    pub async fn tx_savepoint(&self, name: String) -> napi::Result<()> {
+        let session_arc = self.session.clone();
+        let scope_arc = self.txn_scope.clone();
+        spawn_on(&self.pool, move || {
+            let mut session_ref = lock_session(&session_arc)?;
+            let session = session_ref
+                .as_mut()
+                .ok_or_else(|| napi::Error::from_reason("[STATE] No transaction active"))?;
+            session
+                .execute(Command::TxnSavepoint { name: name.clone() })
+                .map_err(to_napi_err)?;
+            let mut scope = lock_scope(&scope_arc)?;
+            let written = scope.writes.len();
+            scope.savepoints.retain(|(n, _)| *n != name);
+            scope.savepoints.push((name, written));
+            Ok(())
         })
         .await
         .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
     }
 
-    /// Get an object type definition.
-    #[napi(js_name = "graphGetObjectType")]
-    pub async fn graph_get_object_type(
-        &self,
-        graph: String,
-        name: String,
-    ) -> napi::Result<serde_json::Value> {
-        let inner = self.inner.clone();
-        tokio::task::spawn_blocking(move || {
-            let guard = lock_inner(&inner)?;
-            match guard.graph_get_object_type(&graph, &name).map_err(to_napi_err)? {
-                Some(v) => Ok(value_to_js(v)),
-                None => Ok(serde_json::Value::Null),
+    /// Undo the writes made since a savepoint, keeping the transaction (and
+    /// the savepoint) active. Later savepoints are discarded.
+    #[napi(js_name = "txRollbackTo")]
+    pub async fn tx_rollback_to(&self, name: String) -> napi::Result<()> {
+        let session_arc = self.session.clone();
+        let scope_arc = self.txn_scope.clone();
+        spawn_on(&self.pool, move || {
+            let mut session_ref = lock_session(&session_arc)?;
+            let session = session_ref
+                .as_mut()
+                .ok_or_else(|| napi::Error::from_reason("[STATE] No transaction active"))?;
+            session
+                .execute(Command::TxnRollbackTo { name: name.clone() })
+                .map_err(to_napi_err)?;
+            let mut scope = lock_scope(&scope_arc)?;
+            if let Some(i) = scope.savepoints.iter().position(|(n, _)| *n == name) {
+                let written = scope.savepoints[i].1;
+                scope.writes.truncate(written);
+                scope.savepoints.truncate(i + 1);
             }
+            Ok(())
         })
         .await
         .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
     }
 
-    /// List all object type names.
-    #[napi(js_name = "graphListObjectTypes")]
-    pub async fn graph_list_object_types(
-        &self,
-        graph: String,
-    ) -> napi::Result<Vec<String>> {
-        let inner = self.inner.clone();
-        tokio::task::spawn_blocking(move || {
-            let guard = lock_inner(&inner)?;
-            guard.graph_list_object_types(&graph).map_err(to_napi_err)
+    /// Get current transaction info.
+    #[napi(js_name = "txnInfo")]
+    pub async fn txn_info(&self) -> napi::Result<serde_json::Value> {
+        let session_arc = self.session.clone();
+        spawn_on(&self.pool, move || {
+            let mut session_ref = lock_session(&session_arc)?;
+            if session_ref.is_none() {
+                return Ok(serde_json::Value::Null);
+            }
+            let session = session_ref.as_mut().unwrap();
+            match session.execute(Command::TxnInfo).map_err(to_napi_err)? {
+                Output::TxnInfo(Some(info)) => Ok(serde_json::json!({
+                    "id": info.id,
+                    "status": format!("{:?}", info.status).to_lowercase(),
+                    "startedAt": info.started_at,
+                })),
+                Output::TxnInfo(None) => Ok(serde_json::Value::Null),
+                _ => Err(napi::Error::from_reason("Unexpected output for TxnInfo")),
+            }
         })
         .await
         .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
     }
 
-    /// Delete an object type definition.
-    #[napi(js_name = "graphDeleteObjectType")]
-    pub async fn graph_delete_object_type(
-        &self,
-        graph: String,
-        name: String,
-    ) -> napi::Result<()> {
-        let inner = self.inner.clone();
-        tokio::task::spawn_blocking(move || {
-            let guard = lock_inner(&inner)?;
-            guard.graph_delete_object_type(&graph, &name).map_err(to_napi_err)
+    /// Check if a transaction is currently active.
+    #[napi(js_name = "txnIsActive")]
+    pub async fn txn_is_active(&self) -> napi::Result<bool> {
+        let session_arc = self.session.clone();
+        spawn_on(&self.pool, move || {
+            let mut session_ref = lock_session(&session_arc)?;
+            if session_ref.is_none() {
+                return Ok(false);
+            }
+            let session = session_ref.as_mut().unwrap();
+            match session.execute(Command::TxnIsActive).map_err(to_napi_err)? {
+                Output::Bool(active) => Ok(active),
+                _ => Err(napi::Error::from_reason(
+                    "Unexpected output for TxnIsActive",
+                )),
+            }
         })
         .await
         .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
     }
 
-    /// Define a link type in the graph ontology.
-    #[napi(js_name = "graphDefineLinkType")]
-    pub async fn graph_define_link_type(
-        &self,
-        graph: String,
-        definition: serde_json::Value,
-    ) -> napi::Result<()> {
-        let inner = self.inner.clone();
-        let def = js_to_value_checked(definition, 0)?;
-        tokio::task::spawn_blocking(move || {
-            let guard = lock_inner(&inner)?;
-            guard.graph_define_link_type(&graph, def).map_err(to_napi_err)
-        })
-        .await
-        .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
-    }
+    // =========================================================================
+    // State Operations
+    // =========================================================================
 
-    /// Get a link type definition.
-    #[napi(js_name = "graphGetLinkType")]
-    pub async fn graph_get_link_type(
-        &self,
-        graph: String,
-        name: String,
-    ) -> napi::Result<serde_json::Value> {
+    /// Delete a state cell.
+    #[napi(js_name = "stateDelete")]
+    pub async fn state_delete(&self, cell: String) -> napi::Result<bool> {
+        check_user_cell(&cell)?;
         let inner = self.inner.clone();
-        tokio::task::spawn_blocking(move || {
+        spawn_on(&self.pool, move || {
             let guard = lock_inner(&inner)?;
-            match guard.graph_get_link_type(&graph, &name).map_err(to_napi_err)? {
-                Some(v) => Ok(value_to_js(v)),
-                None => Ok(serde_json::Value::Null),
-            }
+            ensure_writable(&guard)?;
+            guard.state_delete(&cell).map_err(to_napi_err)
         })
         .await
         .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
     }
 
-    /// List all link type names.
-    #[napi(js_name = "graphListLinkTypes")]
-    pub async fn graph_list_link_types(
+    /// List state cell names with optional prefix filter. Optionally pass `asOf` for time-travel.
+    #[napi(js_name = "stateList")]
+    pub async fn state_list(
         &self,
-        graph: String,
+        prefix: Option<String>,
+        as_of: Option<Either<i64, String>>,
     ) -> napi::Result<Vec<String>> {
         let inner = self.inner.clone();
-        tokio::task::spawn_blocking(move || {
+        spawn_on(&self.pool, move || {
             let guard = lock_inner(&inner)?;
-            guard.graph_list_link_types(&graph).map_err(to_napi_err)
+            let as_of_u64 = resolve_as_of(&guard, as_of)?;
+            guard
+                .state_list_as_of(prefix.as_deref(), as_of_u64)
+                .map_err(to_napi_err)
         })
         .await
         .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
     }
 
-    /// Delete a link type definition.
-    #[napi(js_name = "graphDeleteLinkType")]
-    pub async fn graph_delete_link_type(
-        &self,
-        graph: String,
-        name: String,
-    ) -> napi::Result<()> {
+    // =========================================================================
+    // Versioned Getters
+    // =========================================================================
+
+    /// Get a value by key with version info.
+    #[napi(js_name = "kvGetVersioned")]
+    pub async fn kv_get_versioned(&self, key: String) -> napi::Result<serde_json::Value> {
         let inner = self.inner.clone();
-        tokio::task::spawn_blocking(move || {
+        spawn_on(&self.pool, move || {
             let guard = lock_inner(&inner)?;
-            guard.graph_delete_link_type(&graph, &name).map_err(to_napi_err)
+            match guard.kv_getv(&key).map_err(to_napi_err)? {
+                Some(versions) if !versions.is_empty() => {
+                    Ok(versioned_to_js(versions.into_iter().next().unwrap()))
+                }
+                _ => Ok(serde_json::Value::Null),
+            }
         })
         .await
         .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
     }
 
-    /// Freeze the graph ontology (no more type changes).
-    #[napi(js_name = "graphFreezeOntology")]
-    pub async fn graph_freeze_ontology(&self, graph: String) -> napi::Result<()> {
+    /// Get a state cell value with version info.
+    #[napi(js_name = "stateGetVersioned")]
+    pub async fn state_get_versioned(&self, cell: String) -> napi::Result<serde_json::Value> {
         let inner = self.inner.clone();
-        tokio::task::spawn_blocking(move || {
+        spawn_on(&self.pool, move || {
             let guard = lock_inner(&inner)?;
-            guard.graph_freeze_ontology(&graph).map_err(to_napi_err)
+            match guard.state_getv(&cell).map_err(to_napi_err)? {
+                Some(versions) if !versions.is_empty() => {
+                    Ok(versioned_to_js(versions.into_iter().next().unwrap()))
+                }
+                _ => Ok(serde_json::Value::Null),
+            }
         })
         .await
         .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
     }
 
-    /// Get the ontology status of a graph.
-    #[napi(js_name = "graphOntologyStatus")]
-    pub async fn graph_ontology_status(
-        &self,
-        graph: String,
-    ) -> napi::Result<serde_json::Value> {
+    /// Get a JSON document value with version info.
+    #[napi(js_name = "jsonGetVersioned")]
+    pub async fn json_get_versioned(&self, key: String) -> napi::Result<serde_json::Value> {
         let inner = self.inner.clone();
-        tokio::task::spawn_blocking(move || {
+        spawn_on(&self.pool, move || {
             let guard = lock_inner(&inner)?;
-            match guard.graph_ontology_status(&graph).map_err(to_napi_err)? {
-                Some(v) => Ok(value_to_js(v)),
-                None => Ok(serde_json::Value::Null),
+            match guard.json_getv(&key).map_err(to_napi_err)? {
+                Some(versions) if !versions.is_empty() => {
+                    Ok(versioned_to_js(versions.into_iter().next().unwrap()))
+                }
+                _ => Ok(serde_json::Value::Null),
             }
         })
         .await
         .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
     }
 
-    /// Get a complete ontology summary.
-    #[napi(js_name = "graphOntologySummary")]
-    pub async fn graph_ontology_summary(
+    // =========================================================================
+    // Pagination
+    // =========================================================================
+
+    /// List keys with pagination support. Optionally pass `asOf` for time-travel.
+    #[napi(js_name = "kvListPaginated")]
+    pub async fn kv_list_paginated(
         &self,
-        graph: String,
+        prefix: Option<String>,
+        limit: Option<u32>,
+        as_of: Option<Either<i64, String>>,
     ) -> napi::Result<serde_json::Value> {
         let inner = self.inner.clone();
-        tokio::task::spawn_blocking(move || {
+        spawn_on(&self.pool, move || {
             let guard = lock_inner(&inner)?;
-            match guard.graph_ontology_summary(&graph).map_err(to_napi_err)? {
-                Some(v) => Ok(value_to_js(v)),
-                None => Ok(serde_json::Value::Null),
+            let as_of_u64 = resolve_as_of(&guard, as_of)?;
+            let mut keys = guard
+                .kv_list_as_of(prefix.as_deref(), None, None, as_of_u64)
+                .map(user_keys)
+                .map_err(to_napi_err)?;
+            // Annotation keys are dropped before the page is cut, so the
+            // limit applies to the keys that are returned.
+            let has_more = limit.is_some_and(|l| keys.len() > l as usize);
+            if let Some(l) = limit {
+                keys.truncate(l as usize);
             }
+            Ok(serde_json::json!({
+                "keys": keys,
+                "hasMore": has_more,
+                "cursor": serde_json::Value::Null,
+            }))
         })
         .await
         .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
     }
 
-    /// List all ontology types (both object and link types).
-    #[napi(js_name = "graphListOntologyTypes")]
-    pub async fn graph_list_ontology_types(
+    /// List events by type with pagination support. Optionally pass `asOf` for time-travel.
+    #[napi(js_name = "eventListPaginated")]
+    pub async fn event_list_paginated(
         &self,
-        graph: String,
-    ) -> napi::Result<Vec<String>> {
+        event_type: String,
+        limit: Option<u32>,
+        after: Option<i64>,
+        as_of: Option<Either<i64, String>>,
+    ) -> napi::Result<serde_json::Value> {
         let inner = self.inner.clone();
-        tokio::task::spawn_blocking(move || {
+        spawn_on(&self.pool, move || {
             let guard = lock_inner(&inner)?;
-            guard.graph_list_ontology_types(&graph).map_err(to_napi_err)
+            let as_of_u64 = resolve_as_of(&guard, as_of)?;
+            let events = guard
+                .event_get_by_type_with_options(
+                    &event_type,
+                    limit.map(|l| l as u64),
+                    after.map(|a| a as u64),
+                    as_of_u64,
+                )
+                .map_err(to_napi_err)?;
+            let arr: Vec<serde_json::Value> =
+                events.into_iter().map(versioned_to_js).collect();
+            Ok(serde_json::Value::Array(arr))
         })
         .await
         .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
     }
 
-    /// Get all node IDs of a given object type.
-    #[napi(js_name = "graphNodesByType")]
-    pub async fn graph_nodes_by_type(
+    // =========================================================================
+    // Enhanced Vector Search
+    // =========================================================================
+
+    /// Search for similar vectors with optional filter and metric override.
+    /// Optionally pass `asOf` for time-travel.
+    ///
+    /// With `spaces`, the collection is searched in each selected space and
+    /// the best `k` matches overall are returned, each tagged with `space`.
+    ///
+    /// `withinRadius` and `boundingBox` filters match metadata fields holding
+    /// a `{ lat, lon }` point.
+    #[napi(js_name = "vectorSearchFiltered")]
+    pub async fn vector_search_filtered(
         &self,
-        graph: String,
-        object_type: String,
-    ) -> napi::Result<Vec<String>> {
+        collection: String,
+        query: Vec<f64>,
+        k: u32,
+        #[napi(ts_arg_type = "DistanceMetric | `${DistanceMetric}`")] metric: Option<String>,
+        filter: Option<Vec<serde_json::Value>>,
+        as_of: Option<Either<i64, String>>,
+        spaces: Option<Either<Vec<String>, String>>,
+        operation_id: Option<u32>,
+    ) -> napi::Result<serde_json::Value> {
         let inner = self.inner.clone();
-        tokio::task::spawn_blocking(move || {
+        let vec = validate_vector(&query)?;
+        let op = CancellableOp::start(operation_id);
+
+        let metric_enum = match metric.as_deref() {
+            Some("cosine") => Some(DistanceMetric::Cosine),
+            Some("euclidean") => Some(DistanceMetric::Euclidean),
+            Some("dot_product") | Some("dotproduct") => Some(DistanceMetric::DotProduct),
+            Some(m) => {
+                return Err(napi::Error::from_reason(format!(
+                    "[VALIDATION] Invalid metric: {}",
+                    m
+                )))
+            }
+            None => None,
+        };
+
+        let mut geo = Vec::new();
+        let filter_vec = match filter {
+            Some(arr) => {
+                let mut filters = Vec::new();
+                for item in arr {
+                    let (field, op_str, value_json) = parse_filter(item)?;
+                    let op = match op_str.as_str() {
+                        "eq" => FilterOp::Eq,
+                        "ne" => FilterOp::Ne,
+                        "gt" => FilterOp::Gt,
+                        "gte" => FilterOp::Gte,
+                        "lt" => FilterOp::Lt,
+                        "lte" => FilterOp::Lte,
+                        "in" => FilterOp::In,
+                        "contains" => FilterOp::Contains,
+                        _ => {
+                            // Geo ops are checked here, on the engine's matches.
+                            geo.push(QueryFilter::new(field, &op_str, value_json)?);
+                            continue;
+                        }
+                    };
+                    let value = js_to_value_checked(value_json, 0)?;
+                    filters.push(MetadataFilter { field, op, value });
+                }
+                Some(filters)
+            }
+            None => None,
+        };
+
+        spawn_on(&self.pool, move || {
             let guard = lock_inner(&inner)?;
-            guard
-                .graph_nodes_by_type(&graph, &object_type)
-                .map_err(to_napi_err)
+            let as_of_u64 = resolve_as_of(&guard, as_of)?;
+            // With geo filters, ask the engine for more matches than `k` and
+            // keep asking for more until `k` pass or it runs out.
+            let search = |db: &RustStrata| {
+                let mut fetch = if geo.is_empty() {
+                    k as u64
+                } else {
+                    k as u64 * GEO_OVERFETCH
+                };
+                loop {
+                    op.check()?;
+                    let mut matches = db
+                        .vector_search_with_filter(
+                            &collection,
+                            vec.clone(),
+                            fetch,
+                            filter_vec.clone(),
+                            metric_enum.clone(),
+                            as_of_u64,
+                        )
+                        .map_err(to_napi_err)?;
+                    let exhausted = (matches.len() as u64) < fetch;
+                    matches.retain(|m| {
+                        let metadata = m.metadata.clone().map(value_to_js).unwrap_or_default();
+                        geo.iter().all(|g| g.matches(&metadata))
+                    });
+                    if exhausted || matches.len() >= k as usize {
+                        matches.truncate(k as usize);
+                        return Ok(matches);
+                    }
+                    fetch *= 2;
+                }
+            };
+            let per_space = match spaces {
+                Some(sel) => {
+                    let selected = select_spaces(&guard, sel)?;
+                    across_spaces(&guard, &selected, search)?
+                        .into_iter()
+                        .map(|(space, m)| (Some(space), m))
+                        .collect()
+                }
+                None => vec![(None, search(&*guard)?)],
+            };
+            let mut arr: Vec<serde_json::Value> = Vec::new();
+            for (space, matches) in per_space {
+                for m in matches {
+                    let mut hit = serde_json::json!({
+                        "key": m.key,
+                        "score": m.score,
+                        "metadata": m.metadata.map(value_to_js),
+                    });
+                    if let Some(space) = &space {
+                        hit["space"] = serde_json::json!(space);
+                    }
+                    arr.push(hit);
+                }
+            }
+            merge_ranked(&mut arr, k as usize);
+            Ok(serde_json::Value::Array(arr))
         })
         .await
         .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
     }
 
     // =========================================================================
-    // Graph — Analytics
+    // Space Operations
     // =========================================================================
 
-    /// Weakly Connected Components.
-    #[napi(js_name = "graphWcc")]
-    pub async fn graph_wcc(&self, graph: String) -> napi::Result<serde_json::Value> {
+    /// Create a new space explicitly.
+    #[napi(js_name = "spaceCreate")]
+    pub async fn space_create(&self, space: String) -> napi::Result<()> {
         let inner = self.inner.clone();
-        tokio::task::spawn_blocking(move || {
+        spawn_on(&self.pool, move || {
             let guard = lock_inner(&inner)?;
-            let result = guard.graph_wcc(&graph, None, None).map_err(to_napi_err)?;
-            graph_group_summary_to_js(result)
+            guard.space_create(&space).map_err(to_napi_err)
         })
         .await
         .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
     }
 
-    /// Community Detection via Label Propagation.
-    #[napi(js_name = "graphCdlp")]
-    pub async fn graph_cdlp(
-        &self,
-        graph: String,
-        max_iterations: u32,
-        direction: Option<String>,
-    ) -> napi::Result<serde_json::Value> {
+    /// Check if a space exists in the current branch.
+    #[napi(js_name = "spaceExists")]
+    pub async fn space_exists(&self, space: String) -> napi::Result<bool> {
         let inner = self.inner.clone();
-        tokio::task::spawn_blocking(move || {
+        spawn_on(&self.pool, move || {
             let guard = lock_inner(&inner)?;
-            let result = guard
-                .graph_cdlp(
-                    &graph,
-                    max_iterations as usize,
-                    direction.as_deref(),
-                    None,
-                    None,
-                )
-                .map_err(to_napi_err)?;
-            graph_group_summary_to_js(result)
+            guard.space_exists(&space).map_err(to_napi_err)
         })
         .await
         .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
     }
 
-    /// PageRank importance scoring.
-    #[napi(js_name = "graphPagerank")]
-    pub async fn graph_pagerank(
-        &self,
-        graph: String,
-        damping: Option<f64>,
-        max_iterations: Option<u32>,
-        tolerance: Option<f64>,
-    ) -> napi::Result<serde_json::Value> {
+    // =========================================================================
+    // Configuration
+    // =========================================================================
+
+    /// Get the current database configuration.
+    ///
+    /// Returns an object with `durability`, `autoEmbed`, and optional `model`.
+    #[napi]
+    pub async fn config(&self) -> napi::Result<serde_json::Value> {
         let inner = self.inner.clone();
-        tokio::task::spawn_blocking(move || {
+        spawn_on(&self.pool, move || {
             let guard = lock_inner(&inner)?;
-            let result = guard
-                .graph_pagerank(
-                    &graph,
-                    damping,
-                    max_iterations.map(|m| m as usize),
-                    tolerance,
-                    None,
-                    None,
-                )
-                .map_err(to_napi_err)?;
-            graph_score_summary_to_js(result)
+            let cfg = guard.config().map_err(to_napi_err)?;
+            let mut obj = serde_json::Map::new();
+            obj.insert("durability".into(), serde_json::Value::String(cfg.durability));
+            obj.insert("autoEmbed".into(), serde_json::Value::Bool(cfg.auto_embed));
+            if let Some(model) = cfg.model {
+                let mut m = serde_json::Map::new();
+                m.insert("endpoint".into(), serde_json::Value::String(model.endpoint));
+                m.insert("model".into(), serde_json::Value::String(model.model));
+                m.insert(
+                    "apiKey".into(),
+                    model
+                        .api_key
+                        .map(|s| serde_json::Value::String(s.to_string()))
+                        .unwrap_or(serde_json::Value::Null),
+                );
+                m.insert("timeoutMs".into(), serde_json::Value::Number(model.timeout_ms.into()));
+                obj.insert("model".into(), serde_json::Value::Object(m));
+            } else {
+                obj.insert("model".into(), serde_json::Value::Null);
+            }
+            Ok(serde_json::Value::Object(obj))
         })
         .await
         .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
     }
 
-    /// Local Clustering Coefficient.
-    #[napi(js_name = "graphLcc")]
-    pub async fn graph_lcc(&self, graph: String) -> napi::Result<serde_json::Value> {
+    /// Check whether auto-embedding is enabled.
+    #[napi(js_name = "autoEmbedEnabled")]
+    pub async fn auto_embed_enabled(&self) -> napi::Result<bool> {
         let inner = self.inner.clone();
-        tokio::task::spawn_blocking(move || {
+        spawn_on(&self.pool, move || {
             let guard = lock_inner(&inner)?;
-            let result = guard.graph_lcc(&graph, None, None).map_err(to_napi_err)?;
-            graph_score_summary_to_js(result)
+            guard.auto_embed_enabled().map_err(to_napi_err)
         })
         .await
         .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
     }
 
-    /// Single-Source Shortest Path (Dijkstra).
-    #[napi(js_name = "graphSssp")]
-    pub async fn graph_sssp(
+    /// Enable or disable auto-embedding of text values.
+    ///
+    /// Persisted to strata.toml for disk-backed databases.
+    #[napi(js_name = "setAutoEmbed")]
+    pub async fn set_auto_embed(&self, enabled: bool) -> napi::Result<()> {
+        let inner = self.inner.clone();
+        spawn_on(&self.pool, move || {
+            let guard = lock_inner(&inner)?;
+            guard.set_auto_embed(enabled).map_err(to_napi_err)
+        })
+        .await
+        .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
+    }
+
+    /// Configure an inference model endpoint for intelligent search.
+    ///
+    /// When a model is configured, `search()` transparently expands queries
+    /// using the model for better recall. Search works identically without a model.
+    /// Persisted to strata.toml.
+    #[napi(js_name = "configureModel")]
+    pub async fn configure_model(
         &self,
-        graph: String,
-        source: String,
-        direction: Option<String>,
-    ) -> napi::Result<serde_json::Value> {
+        endpoint: String,
+        model: String,
+        api_key: Option<String>,
+        timeout_ms: Option<u32>,
+    ) -> napi::Result<()> {
         let inner = self.inner.clone();
-        tokio::task::spawn_blocking(move || {
+        spawn_on(&self.pool, move || {
             let guard = lock_inner(&inner)?;
-            let result = guard
-                .graph_sssp(&graph, &source, direction.as_deref(), None, None)
-                .map_err(to_napi_err)?;
-            graph_score_summary_to_js(result)
+            guard
+                .configure_model(
+                    &endpoint,
+                    &model,
+                    api_key.as_deref(),
+                    timeout_ms.map(|ms| ms as u64),
+                )
+                .map_err(to_napi_err)
         })
         .await
         .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
     }
 
     // =========================================================================
-    // System Branch
+    // Search
     // =========================================================================
 
-    /// Get a handle to the `_system_` branch.
+    /// Search across multiple primitives for matching content.
     ///
-    /// Returns a `SystemBranch` object with KV, JSON, state, and event
-    /// methods pre-bound to the internal `_system_` branch.
-    #[napi(js_name = "systemBranch")]
-    pub fn system_branch_handle(&self) -> SystemBranch {
-        SystemBranch {
-            inner: self.inner.clone(),
-        }
-    }
-}
-
-// =============================================================================
-// SystemBranch — handle pre-bound to the _system_ branch
-// =============================================================================
+    /// With `spaces`, each selected space is searched and the best `k`
+    /// hits overall are returned, re-ranked and tagged with `space`.
+    #[napi]
+    pub async fn search(
+        &self,
+        query: String,
+        options: Option<JsSearchOptions>,
+        operation_id: Option<u32>,
+    ) -> napi::Result<serde_json::Value> {
+        let inner = self.inner.clone();
+        let op = CancellableOp::start(operation_id);
+        spawn_on(&self.pool, move || {
+            let guard = lock_inner(&inner)?;
 
-/// Handle for operations on the internal `_system_` branch.
-///
-/// Obtained via `db.systemBranch()`. All operations are routed to the
-/// `_system_` branch regardless of the database's current branch context.
-#[napi]
-pub struct SystemBranch {
-    inner: Arc<Mutex<RustStrata>>,
-}
+            let (k, primitives, time_range, mode, expand, rerank, spaces) = match options {
+                Some(opts) => (
+                    opts.k,
+                    opts.primitives,
+                    opts.time_range.map(|tr| TimeRangeInput {
+                        start: tr.start,
+                        end: tr.end,
+                    }),
+                    opts.mode,
+                    opts.expand,
+                    opts.rerank,
+                    opts.spaces,
+                ),
+                None => (None, None, None, None, None, None, None),
+            };
 
-#[napi]
-impl SystemBranch {
-    // -- KV --
+            let search = |db: &RustStrata| {
+                op.check()?;
+                let sq = SearchQuery {
+                    query: query.clone(),
+                    k: k.map(|n| n as u64),
+                    primitives: primitives.clone(),
+                    time_range: time_range.clone(),
+                    mode: mode.clone(),
+                    expand,
+                    rerank,
+                    precomputed_embedding: None,
+                };
+                db.search(sq)
+                    .map(|(hits, _stats)| hits)
+                    .map_err(to_napi_err)
+            };
 
-    #[napi(js_name = "kvPut")]
-    pub async fn kv_put(&self, key: String, value: serde_json::Value) -> napi::Result<i64> {
-        let inner = self.inner.clone();
-        let v = js_to_value_checked(value, 0)?;
-        tokio::task::spawn_blocking(move || {
-            let guard = lock_inner(&inner)?;
-            guard
-                .system_branch()
-                .kv_put(&key, v)
-                .map(|n| n as i64)
-                .map_err(to_napi_err)
+            let per_space = match spaces {
+                Some(sel) => {
+                    let selected = select_spaces(&guard, sel)?;
+                    across_spaces(&guard, &selected, search)?
+                        .into_iter()
+                        .map(|(space, hits)| (Some(space), hits))
+                        .collect()
+                }
+                None => vec![(None, search(&*guard)?)],
+            };
+            let cross_space = per_space.iter().any(|(space, _)| space.is_some());
+            let mut arr: Vec<serde_json::Value> = Vec::new();
+            for (space, hits) in per_space {
+                for hit in hits {
+                    let mut out = serde_json::json!({
+                        "entity": hit.entity,
+                        "primitive": hit.primitive,
+                        "score": hit.score,
+                        "rank": hit.rank,
+                        "snippet": hit.snippet,
+                    });
+                    if let Some(space) = &space {
+                        out["space"] = serde_json::json!(space);
+                    }
+                    arr.push(out);
+                }
+            }
+            if cross_space {
+                merge_ranked(&mut arr, k.unwrap_or(10) as usize);
+                for (i, hit) in arr.iter_mut().enumerate() {
+                    hit["rank"] = serde_json::json!(i + 1);
+                }
+            }
+            Ok(serde_json::Value::Array(arr))
         })
         .await
         .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
     }
 
-    #[napi(js_name = "kvGet")]
-    pub async fn kv_get(&self, key: String) -> napi::Result<serde_json::Value> {
+    // =========================================================================
+    // Retention
+    // =========================================================================
+
+    /// Apply retention policy to trigger garbage collection.
+    ///
+    /// Rules set with `retentionConfigure` are applied first, on every
+    /// branch, then the database-wide retention pass runs. Returns what the
+    /// rules removed: `versionsRemoved` per primitive, `bytesReclaimed`,
+    /// the oldest surviving timestamp of each trimmed space, and
+    /// `durationMs` for the whole call.
+    #[napi(js_name = "retentionApply")]
+    pub async fn retention_apply(&self) -> napi::Result<serde_json::Value> {
         let inner = self.inner.clone();
-        tokio::task::spawn_blocking(move || {
+        spawn_on(&self.pool, move || {
+            let started = std::time::Instant::now();
             let guard = lock_inner(&inner)?;
-            match guard.system_branch().kv_get(&key).map_err(to_napi_err)? {
-                Some(v) => Ok(value_to_js(v)),
-                None => Ok(serde_json::Value::Null),
+            let rules = read_retention_rules(&guard)?;
+            let mut report = RetentionReport::default();
+            if !rules.is_empty() {
+                let mut view = detached_handle(&guard)?;
+                report = apply_retention_rules(&mut view, &rules)?;
             }
+            guard.retention_apply().map_err(to_napi_err)?;
+            Ok(report.to_js(started.elapsed()))
         })
         .await
         .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
     }
 
-    #[napi(js_name = "kvDelete")]
-    pub async fn kv_delete(&self, key: String) -> napi::Result<bool> {
+    /// Set the retention rule for a space and/or primitive.
+    ///
+    /// The most specific rule wins: space + primitive, then space, then
+    /// primitive, then the database-wide rule. A rule with neither
+    /// `maxVersions` nor `maxAge` keeps all history for its scope.
+    #[napi(js_name = "retentionConfigure")]
+    pub async fn retention_configure(&self, options: JsRetentionOptions) -> napi::Result<()> {
         let inner = self.inner.clone();
-        tokio::task::spawn_blocking(move || {
+        let rule = RetentionRule::from_options(options)?;
+        spawn_on(&self.pool, move || {
             let guard = lock_inner(&inner)?;
-            guard.system_branch().kv_delete(&key).map_err(to_napi_err)
+            let v = js_to_value_checked(rule.to_js(), 0)?;
+            guard
+                .system_branch()
+                .kv_put(&rule.key(), v)
+                .map(|_| ())
+                .map_err(to_napi_err)
         })
         .await
         .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
     }
 
-    #[napi(js_name = "kvList")]
-    pub async fn kv_list(&self, prefix: Option<String>) -> napi::Result<Vec<String>> {
-        let inner = self.inner.clone();
-        tokio::task::spawn_blocking(move || {
+    /// Remove the retention rule for a space and/or primitive. Returns
+    /// `true` if a rule existed.
+    #[napi(js_name = "retentionReset")]
+    pub async fn retention_reset(
+        &self,
+        space: Option<String>,
+        primitive: Option<String>,
+    ) -> napi::Result<bool> {
+        let inner = self.inner.clone();
+        let rule = RetentionRule::from_options(JsRetentionOptions {
+            space,
+            primitive,
+            max_versions: None,
+            max_age: None,
+            keep_tags: None,
+        })?;
+        spawn_on(&self.pool, move || {
             let guard = lock_inner(&inner)?;
             guard
                 .system_branch()
-                .kv_list(prefix.as_deref())
+                .kv_delete(&rule.key())
                 .map_err(to_napi_err)
         })
         .await
         .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
     }
 
-    // -- JSON --
+    /// Get all configured retention rules.
+    #[napi(js_name = "retentionGetConfig")]
+    pub async fn retention_get_config(&self) -> napi::Result<serde_json::Value> {
+        let inner = self.inner.clone();
+        spawn_on(&self.pool, move || {
+            let guard = lock_inner(&inner)?;
+            let rules: Vec<serde_json::Value> = read_retention_rules(&guard)?
+                .iter()
+                .map(RetentionRule::to_js)
+                .collect();
+            Ok(serde_json::json!({ "rules": rules }))
+        })
+        .await
+        .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
+    }
 
-    #[napi(js_name = "jsonSet")]
-    pub async fn json_set(
+    /// Trim history on the current branch now, independent of the
+    /// retention rules.
+    ///
+    /// Versions older than `before`, or beyond the newest `keepLast` per
+    /// key, are removed; retention pins are still honoured. Returns a report
+    /// shaped like `retentionApply`'s.
+    #[napi(js_name = "historyTrim")]
+    pub async fn history_trim(
         &self,
-        key: String,
-        path: String,
-        value: serde_json::Value,
-    ) -> napi::Result<i64> {
+        options: JsHistoryTrimOptions,
+    ) -> napi::Result<serde_json::Value> {
         let inner = self.inner.clone();
-        let v = js_to_value_checked(value, 0)?;
-        tokio::task::spawn_blocking(move || {
-            let guard = lock_inner(&inner)?;
+        if options.before.is_none() && options.keep_last.is_none() {
+            return Err(napi::Error::from_reason(
+                "[VALIDATION] historyTrim needs before or keepLast",
+            ));
+        }
+        if options.keep_last == Some(0) {
+            return Err(napi::Error::from_reason(
+                "[VALIDATION] keepLast must be at least 1",
+            ));
+        }
+        let primitives: Vec<&'static str> = match options.primitive.as_deref() {
+            None => RETENTION_PRIMITIVES.to_vec(),
+            Some(p) => match RETENTION_PRIMITIVES.iter().find(|&&r| r == p) {
+                Some(&r) => vec![r],
+                None => {
+                    return Err(napi::Error::from_reason(format!(
+                        "[VALIDATION] Invalid retention primitive: {}",
+                        p
+                    )))
+                }
+            },
+        };
+        spawn_on(&self.pool, move || {
+            let started = std::time::Instant::now();
+            let guard = lock_inner(&inner)?;
+            let branch = guard.current_branch().to_string();
+            ensure_branch_writable(&guard, &branch, false)?;
+            let before = resolve_as_of(&guard, options.before)?;
+            let spaces = match options.space {
+                Some(name) => select_spaces(&guard, Either::A(vec![name]))?,
+                None => guard.list_spaces().map_err(to_napi_err)?,
+            };
+            let pins = read_retention_pins(&guard)?;
+            let mut pinned = PinnedTimestamps::new(&pins);
+            pinned.add_branch(&mut detached_handle(&guard)?, &pins)?;
+            let mut report = RetentionReport::default();
+            for space in &spaces {
+                for &primitive in &primitives {
+                    let trim = guard
+                        .history_trim(HistoryTrimOptions {
+                            branch: Some(branch.clone()),
+                            space: Some(space.clone()),
+                            primitive: Some(primitive.to_string()),
+                            keep_last: options.keep_last.map(u64::from),
+                            before,
+                            keep_timestamps: pinned.keep(space, primitive),
+                        })
+                        .map_err(to_napi_err)?;
+                    report.record(space, primitive, trim);
+                }
+            }
+            Ok(report.to_js(started.elapsed()))
+        })
+        .await
+        .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
+    }
+
+    /// Pin history so `retentionApply` keeps it.
+    ///
+    /// A key pin keeps every version of that key on every branch (pins act
+    /// on write timestamps, so versions of other keys in the same space
+    /// that were current at those writes are kept too). A tag or timestamp
+    /// pin keeps the versions visible at that moment, in every space. A tag pin records the tag's timestamp, so deleting the tag
+    /// later does not release it.
+    #[napi(js_name = "retentionPin")]
+    pub async fn retention_pin(&self, pin: JsRetentionPin) -> napi::Result<serde_json::Value> {
+        let inner = self.inner.clone();
+        spawn_on(&self.pool, move || {
+            let guard = lock_inner(&inner)?;
+            let pin = RetentionPin::from_options(&guard, pin)?;
+            let v = js_to_value_checked(pin.to_js(), 0)?;
             guard
                 .system_branch()
-                .json_set(&key, &path, v)
-                .map(|n| n as i64)
-                .map_err(to_napi_err)
+                .kv_put(&pin.key(), v)
+                .map_err(to_napi_err)?;
+            Ok(pin.to_js())
         })
         .await
         .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
     }
 
-    #[napi(js_name = "jsonGet")]
-    pub async fn json_get(&self, key: String, path: String) -> napi::Result<serde_json::Value> {
+    /// Remove a pin set with `retentionPin`. Returns `true` if it existed.
+    #[napi(js_name = "retentionUnpin")]
+    pub async fn retention_unpin(&self, pin: JsRetentionPin) -> napi::Result<bool> {
         let inner = self.inner.clone();
-        tokio::task::spawn_blocking(move || {
+        spawn_on(&self.pool, move || {
             let guard = lock_inner(&inner)?;
-            match guard
-                .system_branch()
-                .json_get(&key, &path)
-                .map_err(to_napi_err)?
-            {
-                Some(v) => Ok(value_to_js(v)),
-                None => Ok(serde_json::Value::Null),
+            let key = match pin.tag {
+                // The tag may already be gone; its pin is keyed by name.
+                Some(ref name) if pin.key.is_none() && pin.timestamp.is_none() => {
+                    format!("{}tag/{}", RETENTION_PIN_PREFIX, name)
+                }
+                _ => RetentionPin::from_options(&guard, pin)?.key(),
+            };
+            guard.system_branch().kv_delete(&key).map_err(to_napi_err)
+        })
+        .await
+        .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
+    }
+
+    /// List retention pins.
+    #[napi(js_name = "retentionListPins")]
+    pub async fn retention_list_pins(&self) -> napi::Result<serde_json::Value> {
+        let inner = self.inner.clone();
+        spawn_on(&self.pool, move || {
+            let guard = lock_inner(&inner)?;
+            let pins: Vec<serde_json::Value> = read_retention_pins(&guard)?
+                .iter()
+                .map(RetentionPin::to_js)
+                .collect();
+            Ok(serde_json::Value::Array(pins))
+        })
+        .await
+        .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
+    }
+
+    // =========================================================================
+    // Generic command dispatch
+    // =========================================================================
+
+    /// Execute any command by name with JSON arguments.
+    ///
+    /// This provides a generic dispatch interface: pass a command name (snake_case
+    /// or dot-notation) and a JSON args object, and get a JSON result back.
+    ///
+    /// ```js
+    /// const version = await db.execute("kv_put", { key: "foo", value: "bar" });
+    /// const val = await db.execute("kv_get", { key: "foo" });
+    /// const keys = await db.execute("kv.list", { prefix: "f" });
+    /// ```
+    ///
+    /// Command names map to executor Command variants: `kv_put` → `KvPut`,
+    /// `graph_add_node` → `GraphAddNode`, etc.  Branch and space default to
+    /// the current context if not specified in args.
+    #[napi]
+    pub async fn execute(
+        &self,
+        command: String,
+        args: Option<serde_json::Value>,
+    ) -> napi::Result<serde_json::Value> {
+        let inner = self.inner.clone();
+        let session_arc = self.session.clone();
+        let scope_arc = self.txn_scope.clone();
+        spawn_on(&self.pool, move || {
+            // Normalize command name: kv.put → kv_put → KvPut
+            let pascal = to_pascal_case(&command);
+
+            // Get args as a mutable map (empty if null/absent)
+            let mut args_map = match args.unwrap_or(serde_json::Value::Null) {
+                serde_json::Value::Object(m) => m,
+                serde_json::Value::Null => serde_json::Map::new(),
+                _ => {
+                    return Err(napi::Error::from_reason(
+                        "[VALIDATION] args must be an object or null",
+                    ))
+                }
+            };
+
+            // Convert plain JSON values to tagged Value format for value/payload fields
+            preprocess_value_fields(&mut args_map);
+
+            if matches!(
+                pascal.as_str(),
+                "StateSet" | "StateInit" | "StateCas" | "StateDelete"
+            ) {
+                if let Some(serde_json::Value::String(cell)) = args_map.get("cell") {
+                    check_user_cell(cell)?;
+                }
+            }
+            if matches!(pascal.as_str(), "KvPut" | "KvDelete") {
+                if let Some(serde_json::Value::String(key)) = args_map.get("key") {
+                    check_user_key(key)?;
+                }
+            }
+            if let Some(serde_json::Value::Array(entries)) = args_map.get("entries") {
+                for entry in entries {
+                    let (key, cell) = (entry["key"].as_str(), entry["cell"].as_str());
+                    match (pascal.as_str(), key, cell) {
+                        ("KvBatchPut", Some(key), _) => check_user_key(key)?,
+                        ("StateBatchSet", _, Some(cell)) => check_user_cell(cell)?,
+                        _ => {}
+                    }
+                }
+            }
+
+            // Inside a transaction begun with a branch or space, commands
+            // that don't name their own take the transaction's. Commands
+            // that can't take them are parsed from the args as given.
+            let mut scoped_args = args_map.clone();
+            let write = {
+                let scope = lock_scope(&scope_arc)?;
+                for (field, value) in [("branch", &scope.branch), ("space", &scope.space)] {
+                    if let Some(v) = value {
+                        scoped_args
+                            .entry(field)
+                            .or_insert_with(|| serde_json::json!(v));
+                    }
+                }
+                TXN_WRITE_COMMANDS
+                    .iter()
+                    .find(|(name, _)| scope.active && *name == pascal)
+                    .map(|(_, key_field)| {
+                        let arg = |f: &str| scoped_args.get(f).cloned().unwrap_or_default();
+                        serde_json::json!({
+                            "command": command,
+                            "key": arg(key_field),
+                            "branch": arg("branch"),
+                            "space": arg("space"),
+                        })
+                    })
+            };
+            let scoped = if scoped_args.len() > args_map.len() {
+                let mut m = serde_json::Map::new();
+                m.insert(pascal.clone(), serde_json::Value::Object(scoped_args));
+                serde_json::from_value::<Command>(serde_json::Value::Object(m)).ok()
+            } else {
+                None
+            };
+
+            // Build the Command JSON.
+            // Unit variants (Ping, Info, etc.) serialize as just "Ping",
+            // while struct variants serialize as {"KvPut": {key: ..., value: ...}}.
+            // Try struct form first, fall back to unit variant if args are empty.
+            let cmd: Command = if let Some(cmd) = scoped {
+                Ok(cmd)
+            } else if args_map.is_empty() {
+                // Try unit variant first (e.g., "Ping")
+                serde_json::from_value::<Command>(serde_json::Value::String(pascal.clone()))
+                    .or_else(|_| {
+                        // Fall back to struct variant with empty fields
+                        let mut m = serde_json::Map::new();
+                        m.insert(pascal.clone(), serde_json::Value::Object(args_map.clone()));
+                        serde_json::from_value::<Command>(serde_json::Value::Object(m))
+                    })
+            } else {
+                let mut m = serde_json::Map::new();
+                m.insert(pascal.clone(), serde_json::Value::Object(args_map));
+                serde_json::from_value::<Command>(serde_json::Value::Object(m))
+            }
+            .map_err(|e| {
+                napi::Error::from_reason(format!(
+                    "[VALIDATION] Invalid command '{}': {}",
+                    command, e
+                ))
+            })?;
+
+            // Execute through session (supports transactions) or executor
+            let mut session_guard = lock_session(&session_arc)?;
+            let output = if let Some(session) = session_guard.as_mut() {
+                let output = session.execute(cmd).map_err(to_napi_err)?;
+                if let Some(write) = write {
+                    lock_scope(&scope_arc)?.writes.push(write);
+                }
+                output
+            } else {
+                let guard = lock_inner(&inner)?;
+                guard.executor().execute(cmd).map_err(to_napi_err)?
+            };
+
+            // Convert Output to plain JSON
+            Ok(output_to_json(output))
+        })
+        .await
+        .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
+    }
+
+    // =========================================================================
+    // Follower mode
+    // =========================================================================
+
+    /// Returns `true` if this database was opened in read-only follower mode.
+    #[napi(js_name = "isFollower")]
+    pub fn is_follower(&self) -> napi::Result<bool> {
+        let guard = lock_inner(&self.inner)?;
+        Ok(guard.database().is_follower())
+    }
+
+    /// Replay new WAL records from the primary.
+    ///
+    /// Only meaningful for follower instances (opened with `{ follower: true }`).
+    /// Returns the number of new records applied. Returns 0 for non-follower
+    /// instances or when there are no new records.
+    #[napi]
+    pub async fn refresh(&self) -> napi::Result<i64> {
+        let inner = self.inner.clone();
+        spawn_on(&self.pool, move || {
+            let guard = lock_inner(&inner)?;
+            let applied = guard
+                .database()
+                .refresh()
+                .map_err(|e| napi::Error::from_reason(format!("{}", e)))?;
+            Ok(applied as i64)
+        })
+        .await
+        .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
+    }
+
+    // =========================================================================
+    // Lifecycle
+    // =========================================================================
+
+    /// Close the database, releasing all resources.
+    ///
+    /// After calling `close()`, any further method call on this instance
+    /// fails with a `[CLOSED]` error; calls already running finish first.
+    /// This mirrors the `client.close()` pattern used by every major Node.js
+    /// database driver. When other handles share the database, only this
+    /// handle's share is given up; the last one to close drops the database.
+    /// Closing twice is a no-op.
+    #[napi]
+    pub async fn close(&self) -> napi::Result<()> {
+        let inner = self.inner.clone();
+        let session_arc = self.session.clone();
+        let registration = self.registration.clone();
+        spawn_unlimited_on(&self.pool, move || {
+            // Waits out operations still running on this handle, then takes
+            // its view of the database. Whichever close takes it releases
+            // the handle; any other finds it gone.
+            let view = inner
+                .db
+                .write()
+                .map_err(|_| napi::Error::from_reason("Lock poisoned"))?
+                .take();
+            let Some(view) = view else {
+                return Ok(());
+            };
+            // Drop session before the view (it borrows the inner DB).
+            {
+                let mut s = lock_session(&session_arc)?;
+                *s = None;
+            }
+            drop(view);
+            let Some(last) = release_registration(&registration)? else {
+                return Ok(());
+            };
+            // Flushed as `Drop` does; the database is released either way.
+            let flushed = last.engine.flush().map_err(to_napi_err);
+            if let Some(key) = &last.key {
+                log(
+                    "info",
+                    "closed database",
+                    serde_json::json!({ "path": key.to_string_lossy() }),
+                );
+            }
+            // Only now let another process take the writer lock.
+            drop(last);
+            flushed
+        })
+        .await
+        .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
+    }
+
+    // =========================================================================
+    // Time Travel
+    // =========================================================================
+
+    /// Get the time range (oldest and latest timestamps) for the current branch.
+    #[napi(js_name = "timeRange")]
+    pub async fn time_range(&self) -> napi::Result<serde_json::Value> {
+        let inner = self.inner.clone();
+        spawn_on(&self.pool, move || {
+            let guard = lock_inner(&inner)?;
+            let (oldest_ts, latest_ts) = guard.time_range().map_err(to_napi_err)?;
+            Ok(serde_json::json!({
+                "oldestTs": oldest_ts.map(|t| t as i64),
+                "latestTs": latest_ts.map(|t| t as i64),
+            }))
+        })
+        .await
+        .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
+    }
+
+    /// Diff the current branch against itself between two timestamps (or
+    /// tag names).
+    ///
+    /// Reports the KV, state, and JSON keys added, removed, and modified
+    /// between `tsA` and `tsB`, per space. Events, vectors, and graphs are
+    /// not compared.
+    #[napi(js_name = "diffTime")]
+    pub async fn diff_time(
+        &self,
+        ts_a: Either<i64, String>,
+        ts_b: Either<i64, String>,
+        options: Option<JsDiffTimeOptions>,
+    ) -> napi::Result<serde_json::Value> {
+        let inner = self.inner.clone();
+        let (space, prefix) = options.map_or((None, None), |o| (o.space, o.prefix));
+        spawn_on(&self.pool, move || {
+            let guard = lock_inner(&inner)?;
+            let from = resolve_as_of(&guard, Some(ts_a))?.unwrap_or(0);
+            let to = resolve_as_of(&guard, Some(ts_b))?.unwrap_or(0);
+            let spaces = match space {
+                Some(name) => select_spaces(&guard, Either::A(vec![name]))?,
+                None => guard.list_spaces().map_err(to_napi_err)?,
+            };
+            let prefix = prefix.unwrap_or_default();
+            let diffs = across_spaces(&guard, &spaces, |db| {
+                let before = read_space_data(db, Some(from))?;
+                let after = read_space_data(db, Some(to))?;
+                Ok(diff_space_data(before, after, &prefix))
+            })?;
+            let mut totals = [0usize; 3];
+            let mut out = Vec::new();
+            for (space, lists) in diffs {
+                if lists.iter().all(|l| l.is_empty()) {
+                    continue;
+                }
+                for (total, list) in totals.iter_mut().zip(&lists) {
+                    *total += list.len();
+                }
+                let [added, removed, modified] = lists;
+                out.push(serde_json::json!({
+                    "space": space,
+                    "added": added,
+                    "removed": removed,
+                    "modified": modified,
+                }));
+            }
+            Ok(serde_json::json!({
+                "fromTs": from,
+                "toTs": to,
+                "summary": {
+                    "totalAdded": totals[0],
+                    "totalRemoved": totals[1],
+                    "totalModified": totals[2],
+                },
+                "spaces": out,
+            }))
+        })
+        .await
+        .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
+    }
+
+    // =========================================================================
+    // Batch Operations
+    // =========================================================================
+
+    /// Batch put multiple KV entries.
+    #[napi(js_name = "kvBatchPut")]
+    pub async fn kv_batch_put(
+        &self,
+        #[napi(ts_arg_type = "Array<any>")] entries: Vec<JsBatchEntry>,
+        json_limits: Option<JsJsonLimits>,
+    ) -> napi::Result<serde_json::Value> {
+        let inner = self.inner.clone();
+        let limits = self.json_limits.with(json_limits.as_ref())?;
+        let batch: Vec<BatchKvEntry> = entries
+            .into_iter()
+            .map(|v| {
+                limits.check_exact(&v.0)?;
+                let mut obj = batch_entry(v)?;
+                let key = take_string(&mut obj, "key")?;
+                check_user_key(&key)?;
+                let value = take_field(&mut obj, "value")?;
+                limits.check(&value)?;
+                Ok(BatchKvEntry { key, value })
+            })
+            .collect::<napi::Result<_>>()?;
+        spawn_on(&self.pool, move || {
+            let guard = lock_inner(&inner)?;
+            ensure_writable(&guard)?;
+            let results = guard.kv_batch_put(batch).map_err(to_napi_err)?;
+            Ok(batch_results_to_js(results))
+        })
+        .await
+        .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
+    }
+
+    /// Batch set multiple state cells.
+    #[napi(js_name = "stateBatchSet")]
+    pub async fn state_batch_set(
+        &self,
+        #[napi(ts_arg_type = "Array<any>")] entries: Vec<JsBatchEntry>,
+        json_limits: Option<JsJsonLimits>,
+    ) -> napi::Result<serde_json::Value> {
+        let inner = self.inner.clone();
+        let limits = self.json_limits.with(json_limits.as_ref())?;
+        let batch: Vec<BatchStateEntry> = entries
+            .into_iter()
+            .map(|v| {
+                limits.check_exact(&v.0)?;
+                let mut obj = batch_entry(v)?;
+                let cell = take_string(&mut obj, "cell")?;
+                check_user_cell(&cell)?;
+                let value = take_field(&mut obj, "value")?;
+                limits.check(&value)?;
+                Ok(BatchStateEntry { cell, value })
+            })
+            .collect::<napi::Result<_>>()?;
+        spawn_on(&self.pool, move || {
+            let guard = lock_inner(&inner)?;
+            ensure_writable(&guard)?;
+            let results = guard.state_batch_set(batch).map_err(to_napi_err)?;
+            Ok(batch_results_to_js(results))
+        })
+        .await
+        .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
+    }
+
+    /// Batch append multiple events.
+    #[napi(js_name = "eventBatchAppend")]
+    pub async fn event_batch_append(
+        &self,
+        #[napi(ts_arg_type = "Array<any>")] entries: Vec<JsBatchEntry>,
+        json_limits: Option<JsJsonLimits>,
+    ) -> napi::Result<serde_json::Value> {
+        let inner = self.inner.clone();
+        let limits = self.json_limits.with(json_limits.as_ref())?;
+        let batch: Vec<BatchEventEntry> = entries
+            .into_iter()
+            .map(|v| {
+                limits.check_exact(&v.0)?;
+                let mut obj = batch_entry(v)?;
+                let event_type = take_string(&mut obj, "event_type")
+                    .or_else(|e| take_string(&mut obj, "eventType").map_err(|_| e))?;
+                let payload = take_field(&mut obj, "payload")?;
+                limits.check(&payload)?;
+                Ok(BatchEventEntry {
+                    event_type,
+                    payload,
+                })
+            })
+            .collect::<napi::Result<_>>()?;
+        spawn_on(&self.pool, move || {
+            let guard = lock_inner(&inner)?;
+            ensure_writable(&guard)?;
+            let results = guard.event_batch_append(batch).map_err(to_napi_err)?;
+            Ok(batch_results_to_js(results))
+        })
+        .await
+        .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
+    }
+
+    /// Batch set multiple JSON documents.
+    #[napi(js_name = "jsonBatchSet")]
+    pub async fn json_batch_set(
+        &self,
+        #[napi(ts_arg_type = "Array<any>")] entries: Vec<JsBatchEntry>,
+        json_limits: Option<JsJsonLimits>,
+    ) -> napi::Result<serde_json::Value> {
+        let inner = self.inner.clone();
+        let limits = self.json_limits.with(json_limits.as_ref())?;
+        let batch: Vec<BatchJsonEntry> = entries
+            .into_iter()
+            .map(|v| {
+                limits.check_exact(&v.0)?;
+                let mut obj = batch_entry(v)?;
+                let key = take_string(&mut obj, "key")?;
+                let path = take_string(&mut obj, "path")?;
+                let value = take_field(&mut obj, "value")?;
+                limits.check(&value)?;
+                Ok(BatchJsonEntry { key, path, value })
+            })
+            .collect::<napi::Result<_>>()?;
+        spawn_on(&self.pool, move || {
+            let guard = lock_inner(&inner)?;
+            ensure_writable(&guard)?;
+            let results = guard.json_batch_set(batch).map_err(to_napi_err)?;
+            Ok(batch_results_to_js(results))
+        })
+        .await
+        .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
+    }
+
+    /// Batch get multiple JSON documents.
+    #[napi(js_name = "jsonBatchGet")]
+    pub async fn json_batch_get(
+        &self,
+        entries: Vec<serde_json::Value>,
+    ) -> napi::Result<serde_json::Value> {
+        let inner = self.inner.clone();
+        let batch: Vec<BatchJsonGetEntry> = entries
+            .into_iter()
+            .map(|v| {
+                let obj = v
+                    .as_object()
+                    .ok_or_else(|| napi::Error::from_reason("[VALIDATION] Expected object"))?;
+                let key = obj
+                    .get("key")
+                    .and_then(|k| k.as_str())
+                    .ok_or_else(|| napi::Error::from_reason("[VALIDATION] Missing 'key'"))?
+                    .to_string();
+                let path = obj
+                    .get("path")
+                    .and_then(|p| p.as_str())
+                    .ok_or_else(|| napi::Error::from_reason("[VALIDATION] Missing 'path'"))?
+                    .to_string();
+                Ok(BatchJsonGetEntry { key, path })
+            })
+            .collect::<napi::Result<_>>()?;
+        spawn_on(&self.pool, move || {
+            let guard = lock_inner(&inner)?;
+            let results = guard.json_batch_get(batch).map_err(to_napi_err)?;
+            Ok(batch_get_results_to_js(results))
+        })
+        .await
+        .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
+    }
+
+    /// Batch delete multiple JSON documents.
+    #[napi(js_name = "jsonBatchDelete")]
+    pub async fn json_batch_delete(
+        &self,
+        entries: Vec<serde_json::Value>,
+    ) -> napi::Result<serde_json::Value> {
+        let inner = self.inner.clone();
+        let batch: Vec<BatchJsonDeleteEntry> = entries
+            .into_iter()
+            .map(|v| {
+                let obj = v
+                    .as_object()
+                    .ok_or_else(|| napi::Error::from_reason("[VALIDATION] Expected object"))?;
+                let key = obj
+                    .get("key")
+                    .and_then(|k| k.as_str())
+                    .ok_or_else(|| napi::Error::from_reason("[VALIDATION] Missing 'key'"))?
+                    .to_string();
+                let path = obj
+                    .get("path")
+                    .and_then(|p| p.as_str())
+                    .ok_or_else(|| napi::Error::from_reason("[VALIDATION] Missing 'path'"))?
+                    .to_string();
+                Ok(BatchJsonDeleteEntry { key, path })
+            })
+            .collect::<napi::Result<_>>()?;
+        spawn_on(&self.pool, move || {
+            let guard = lock_inner(&inner)?;
+            ensure_writable(&guard)?;
+            let results = guard.json_batch_delete(batch).map_err(to_napi_err)?;
+            Ok(batch_results_to_js(results))
+        })
+        .await
+        .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
+    }
+
+    // =========================================================================
+    // Configuration (key-value)
+    // =========================================================================
+
+    /// Set a configuration key-value pair.
+    #[napi(js_name = "configureSet")]
+    pub async fn configure_set(&self, key: String, value: String) -> napi::Result<()> {
+        let inner = self.inner.clone();
+        spawn_on(&self.pool, move || {
+            let guard = lock_inner(&inner)?;
+            guard.config_set(&key, &value).map_err(to_napi_err)
+        })
+        .await
+        .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
+    }
+
+    /// Get a configuration value by key.
+    #[napi(js_name = "configureGet")]
+    pub async fn configure_get(&self, key: String) -> napi::Result<Option<String>> {
+        let inner = self.inner.clone();
+        spawn_on(&self.pool, move || {
+            let guard = lock_inner(&inner)?;
+            guard.config_get(&key).map_err(to_napi_err)
+        })
+        .await
+        .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
+    }
+
+    // =========================================================================
+    // Embedding
+    // =========================================================================
+
+    /// Embed a single text string.
+    #[napi]
+    pub async fn embed(&self, text: String) -> napi::Result<Vec<f64>> {
+        let inner = self.inner.clone();
+        spawn_on(&self.pool, move || {
+            let guard = lock_inner(&inner)?;
+            let vec = guard.embed(&text).map_err(to_napi_err)?;
+            Ok(vec.into_iter().map(|f| f as f64).collect())
+        })
+        .await
+        .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
+    }
+
+    /// Embed multiple texts in a batch.
+    #[napi(js_name = "embedBatch")]
+    pub async fn embed_batch(&self, texts: Vec<String>) -> napi::Result<Vec<Vec<f64>>> {
+        let inner = self.inner.clone();
+        spawn_on(&self.pool, move || {
+            let guard = lock_inner(&inner)?;
+            let refs: Vec<&str> = texts.iter().map(|s| s.as_str()).collect();
+            let vecs = guard.embed_batch(&refs).map_err(to_napi_err)?;
+            Ok(vecs
+                .into_iter()
+                .map(|v| v.into_iter().map(|f| f as f64).collect())
+                .collect())
+        })
+        .await
+        .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
+    }
+
+    /// Get the embedding pipeline status.
+    #[napi(js_name = "embedStatus")]
+    pub async fn embed_status(&self) -> napi::Result<serde_json::Value> {
+        let inner = self.inner.clone();
+        spawn_on(&self.pool, move || {
+            let guard = lock_inner(&inner)?;
+            let info = guard.embed_status().map_err(to_napi_err)?;
+            Ok(serde_json::json!({
+                "autoEmbed": info.auto_embed,
+                "batchSize": info.batch_size,
+                "pending": info.pending,
+                "totalQueued": info.total_queued,
+                "totalEmbedded": info.total_embedded,
+                "totalFailed": info.total_failed,
+                "schedulerQueueDepth": info.scheduler_queue_depth,
+                "schedulerActiveTasks": info.scheduler_active_tasks,
+            }))
+        })
+        .await
+        .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
+    }
+
+    /// Chunk a document, embed every chunk, and upsert the chunks into a
+    /// vector collection in a single call.
+    ///
+    /// Chunks are stored under `{key}#{index}` with the caller's metadata
+    /// plus `source`, `chunk`, and `text` fields. Re-ingesting a key
+    /// replaces its chunks: those past the new chunk count are deleted in
+    /// the same transaction as the upsert. Returns the chunk keys in order,
+    /// each with the commit version.
+    #[napi(js_name = "ingestDocument")]
+    pub async fn ingest_document(
+        &self,
+        collection: String,
+        key: String,
+        text: String,
+        options: Option<JsIngestOptions>,
+    ) -> napi::Result<serde_json::Value> {
+        let inner = self.inner.clone();
+        let (chunk_size, overlap, metadata) = match options {
+            Some(opts) => (opts.chunk_size, opts.overlap, opts.metadata),
+            None => (None, None, None),
+        };
+        let chunks = chunk_text(
+            &text,
+            chunk_size.unwrap_or(1000) as usize,
+            overlap.unwrap_or(100) as usize,
+        )?;
+        let base_meta = match metadata {
+            Some(serde_json::Value::Object(m)) => m,
+            Some(serde_json::Value::Null) | None => serde_json::Map::new(),
+            Some(_) => {
+                return Err(napi::Error::from_reason(
+                    "[VALIDATION] metadata must be an object",
+                ))
+            }
+        };
+        let chunk_keys: Vec<String> = (0..chunks.len()).map(|i| format!("{}#{}", key, i)).collect();
+        let chunk_metas: Vec<Value> = chunks
+            .iter()
+            .enumerate()
+            .map(|(i, chunk)| {
+                let mut meta = base_meta.clone();
+                meta.insert("source".into(), serde_json::json!(key));
+                meta.insert("chunk".into(), serde_json::json!(i));
+                meta.insert("text".into(), serde_json::json!(chunk));
+                js_to_value_checked(serde_json::Value::Object(meta), 0)
+            })
+            .collect::<napi::Result<_>>()?;
+        spawn_on(&self.pool, move || {
+            let guard = lock_inner(&inner)?;
+            ensure_writable(&guard)?;
+            let refs: Vec<&str> = chunks.iter().map(|s| s.as_str()).collect();
+            let embeddings = guard.embed_batch(&refs).map_err(to_napi_err)?;
+            let batch: Vec<BatchVectorEntry> = chunk_keys
+                .iter()
+                .zip(embeddings)
+                .zip(chunk_metas)
+                .map(|((k, vector), meta)| BatchVectorEntry {
+                    key: k.clone(),
+                    vector,
+                    metadata: Some(meta),
+                })
+                .collect();
+            let version = replace_chunks(&guard, &collection, &key, batch)?;
+            let arr: Vec<serde_json::Value> = chunk_keys
+                .into_iter()
+                .map(|k| serde_json::json!({ "key": k, "version": version }))
+                .collect();
+            Ok(serde_json::Value::Array(arr))
+        })
+        .await
+        .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
+    }
+
+    /// Score candidate texts against a query with a cross-encoder.
+    ///
+    /// Returns one entry per candidate, sorted by descending score, with the
+    /// candidate's original `index` so callers can map back to their hits.
+    #[napi]
+    pub async fn rerank(
+        &self,
+        query: String,
+        candidates: Vec<String>,
+        options: Option<JsRerankOptions>,
+    ) -> napi::Result<serde_json::Value> {
+        #[cfg(feature = "embed")]
+        {
+            let inner = self.inner.clone();
+            let model = options.and_then(|o| o.model);
+            spawn_on(&self.pool, move || {
+                let guard = lock_inner(&inner)?;
+                let refs: Vec<&str> = candidates.iter().map(|s| s.as_str()).collect();
+                let scores = guard
+                    .rerank(&query, &refs, model.as_deref())
+                    .map_err(to_napi_err)?;
+                let mut scored: Vec<(usize, f32)> = scores.into_iter().enumerate().collect();
+                scored.sort_by(|a, b| b.1.total_cmp(&a.1));
+                let arr: Vec<serde_json::Value> = scored
+                    .into_iter()
+                    .map(|(i, score)| {
+                        serde_json::json!({
+                            "index": i,
+                            "text": candidates[i],
+                            "score": score,
+                        })
+                    })
+                    .collect();
+                Ok(serde_json::Value::Array(arr))
+            })
+            .await
+            .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
+        }
+
+        #[cfg(not(feature = "embed"))]
+        {
+            let _ = (query, candidates, options);
+            Err(napi::Error::from_reason(
+                "[IO] The 'embed' feature is not enabled in this build",
+            ))
+        }
+    }
+
+    // =========================================================================
+    // Inference
+    // =========================================================================
+
+    /// Generate text from a model.
+    #[napi]
+    pub async fn generate(
+        &self,
+        model: String,
+        prompt: String,
+        options: Option<serde_json::Value>,
+    ) -> napi::Result<serde_json::Value> {
+        let inner = self.inner.clone();
+        let (max_tokens, temperature, top_k, top_p, seed, stop_tokens, stop_sequences) =
+            match options {
+                Some(opts) => {
+                    let obj = opts.as_object();
+                    (
+                        obj.and_then(|o| o.get("maxTokens"))
+                            .and_then(|v| v.as_u64())
+                            .map(|n| n as usize),
+                        obj.and_then(|o| o.get("temperature"))
+                            .and_then(|v| v.as_f64())
+                            .map(|f| f as f32),
+                        obj.and_then(|o| o.get("topK"))
+                            .and_then(|v| v.as_u64())
+                            .map(|n| n as usize),
+                        obj.and_then(|o| o.get("topP"))
+                            .and_then(|v| v.as_f64())
+                            .map(|f| f as f32),
+                        obj.and_then(|o| o.get("seed")).and_then(|v| v.as_u64()),
+                        obj.and_then(|o| o.get("stopTokens"))
+                            .and_then(|v| v.as_array())
+                            .map(|arr| {
+                                arr.iter()
+                                    .filter_map(|n| n.as_u64().map(|n| n as u32))
+                                    .collect()
+                            }),
+                        obj.and_then(|o| o.get("stopSequences"))
+                            .and_then(|v| v.as_array())
+                            .map(|arr| {
+                                arr.iter()
+                                    .filter_map(|s| s.as_str().map(|s| s.to_string()))
+                                    .collect()
+                            }),
+                    )
+                }
+                None => (None, None, None, None, None, None, None),
+            };
+        spawn_on(&self.pool, move || {
+            let guard = lock_inner(&inner)?;
+            let result = guard
+                .generate_with_options(
+                    &model,
+                    &prompt,
+                    max_tokens,
+                    temperature,
+                    top_k,
+                    top_p,
+                    seed,
+                    stop_tokens,
+                    stop_sequences,
+                )
+                .map_err(to_napi_err)?;
+            Ok(serde_json::json!({
+                "text": result.text,
+                "stopReason": result.stop_reason,
+                "promptTokens": result.prompt_tokens,
+                "completionTokens": result.completion_tokens,
+                "model": result.model,
+            }))
+        })
+        .await
+        .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
+    }
+
+    /// Tokenize text using a model's tokenizer.
+    #[napi]
+    pub async fn tokenize(
+        &self,
+        model: String,
+        text: String,
+        options: Option<serde_json::Value>,
+    ) -> napi::Result<serde_json::Value> {
+        let inner = self.inner.clone();
+        let add_special_tokens = options
+            .and_then(|o| o.as_object().and_then(|obj| obj.get("addSpecialTokens")?.as_bool()));
+        spawn_on(&self.pool, move || {
+            let guard = lock_inner(&inner)?;
+            let result = guard
+                .tokenize(&model, &text, add_special_tokens)
+                .map_err(to_napi_err)?;
+            Ok(serde_json::json!({
+                "ids": result.ids,
+                "count": result.count,
+                "model": result.model,
+            }))
+        })
+        .await
+        .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
+    }
+
+    /// Detokenize token IDs back to text.
+    #[napi]
+    pub async fn detokenize(
+        &self,
+        model: String,
+        ids: Vec<u32>,
+    ) -> napi::Result<String> {
+        let inner = self.inner.clone();
+        spawn_on(&self.pool, move || {
+            let guard = lock_inner(&inner)?;
+            guard.detokenize(&model, ids).map_err(to_napi_err)
+        })
+        .await
+        .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
+    }
+
+    /// Unload a model from memory.
+    #[napi(js_name = "generateUnload")]
+    pub async fn generate_unload(&self, model: String) -> napi::Result<bool> {
+        let inner = self.inner.clone();
+        spawn_on(&self.pool, move || {
+            let guard = lock_inner(&inner)?;
+            guard.generate_unload(&model).map_err(to_napi_err)
+        })
+        .await
+        .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
+    }
+
+    // =========================================================================
+    // Model Management
+    // =========================================================================
+
+    /// List all available models.
+    #[napi(js_name = "modelsList")]
+    pub async fn models_list(&self) -> napi::Result<serde_json::Value> {
+        let inner = self.inner.clone();
+        spawn_on(&self.pool, move || {
+            let guard = lock_inner(&inner)?;
+            let models = guard.models_list().map_err(to_napi_err)?;
+            let arr: Vec<serde_json::Value> = models
+                .into_iter()
+                .map(|m| {
+                    serde_json::json!({
+                        "name": m.name,
+                        "task": m.task,
+                        "architecture": m.architecture,
+                        "defaultQuant": m.default_quant,
+                        "embeddingDim": m.embedding_dim,
+                        "isLocal": m.is_local,
+                        "sizeBytes": m.size_bytes,
+                    })
+                })
+                .collect();
+            Ok(serde_json::Value::Array(arr))
+        })
+        .await
+        .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
+    }
+
+    /// Pull/download a model by name.
+    #[napi(js_name = "modelsPull")]
+    pub async fn models_pull(&self, name: String) -> napi::Result<serde_json::Value> {
+        let inner = self.inner.clone();
+        spawn_on(&self.pool, move || {
+            let guard = lock_inner(&inner)?;
+            let (name, path) = guard.models_pull(&name).map_err(to_napi_err)?;
+            Ok(serde_json::json!({
+                "name": name,
+                "path": path,
+            }))
+        })
+        .await
+        .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
+    }
+
+    /// List locally downloaded models.
+    #[napi(js_name = "modelsLocal")]
+    pub async fn models_local(&self) -> napi::Result<serde_json::Value> {
+        let inner = self.inner.clone();
+        spawn_on(&self.pool, move || {
+            let guard = lock_inner(&inner)?;
+            let models = guard.models_local().map_err(to_napi_err)?;
+            let arr: Vec<serde_json::Value> = models
+                .into_iter()
+                .map(|m| {
+                    serde_json::json!({
+                        "name": m.name,
+                        "task": m.task,
+                        "architecture": m.architecture,
+                        "defaultQuant": m.default_quant,
+                        "embeddingDim": m.embedding_dim,
+                        "isLocal": m.is_local,
+                        "sizeBytes": m.size_bytes,
+                    })
+                })
+                .collect();
+            Ok(serde_json::Value::Array(arr))
+        })
+        .await
+        .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
+    }
+
+    // =========================================================================
+    // Durability
+    // =========================================================================
+
+    /// Get WAL durability counters.
+    #[napi(js_name = "durabilityCounters")]
+    pub async fn durability_counters(&self) -> napi::Result<serde_json::Value> {
+        let inner = self.inner.clone();
+        let last_flush = self.last_flush.clone();
+        let syncs_seen = self.syncs_seen.clone();
+        spawn_on(&self.pool, move || {
+            let guard = lock_inner(&inner)?;
+            let counters = guard.durability_counters().map_err(to_napi_err)?;
+            note_engine_syncs(&last_flush, &syncs_seen, counters.sync_calls);
+            Ok(serde_json::json!({
+                "walAppends": counters.wal_appends,
+                "syncCalls": counters.sync_calls,
+                "bytesWritten": counters.bytes_written,
+                "syncNanos": counters.sync_nanos,
+            }))
+        })
+        .await
+        .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
+    }
+
+    /// WAL activity since open, the size and number of the WAL segments on
+    /// disk (`null` for an in-memory database), and when the WAL was last
+    /// synced.
+    ///
+    /// `lastFlushAt` covers `flush()` on any handle and the engine's own
+    /// syncs. The engine doesn't report when it synced, so one of its syncs
+    /// is dated when `walStats()` or `durabilityCounters()` first sees it.
+    #[napi(js_name = "walStats")]
+    pub async fn wal_stats(&self) -> napi::Result<serde_json::Value> {
+        let inner = self.inner.clone();
+        let path = self.path.clone();
+        let last_flush = self.last_flush.clone();
+        let syncs_seen = self.syncs_seen.clone();
+        spawn_on(&self.pool, move || {
+            let guard = lock_inner(&inner)?;
+            let counters = guard.durability_counters().map_err(to_napi_err)?;
+            note_engine_syncs(&last_flush, &syncs_seen, counters.sync_calls);
+            let (size, segments) = match &path {
+                Some(path) => {
+                    let (bytes, segments) = wal_segments(path).map_err(io_err)?;
+                    (Some(bytes), Some(segments))
+                }
+                None => (None, None),
+            };
+            let last_flush = match last_flush.load(Ordering::Relaxed) {
+                0 => None,
+                micros => Some(micros),
+            };
+            Ok(serde_json::json!({
+                "appends": counters.wal_appends,
+                "bytesWritten": counters.bytes_written,
+                "syncCalls": counters.sync_calls,
+                "sizeBytes": size,
+                "segments": segments,
+                "lastFlushAt": last_flush,
+            }))
+        })
+        .await
+        .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
+    }
+
+    // =========================================================================
+    // Graph — Lifecycle
+    // =========================================================================
+
+    /// Create a new graph.
+    #[napi(js_name = "graphCreate")]
+    pub async fn graph_create(
+        &self,
+        graph: String,
+        cascade_policy: Option<String>,
+    ) -> napi::Result<()> {
+        let inner = self.inner.clone();
+        spawn_on(&self.pool, move || {
+            let guard = lock_inner(&inner)?;
+            ensure_writable(&guard)?;
+            guard
+                .graph_create_with_policy(&graph, cascade_policy.as_deref())
+                .map_err(to_napi_err)
+        })
+        .await
+        .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
+    }
+
+    /// Delete a graph.
+    #[napi(js_name = "graphDelete")]
+    pub async fn graph_delete(&self, graph: String) -> napi::Result<()> {
+        let inner = self.inner.clone();
+        spawn_on(&self.pool, move || {
+            let guard = lock_inner(&inner)?;
+            ensure_writable(&guard)?;
+            guard.graph_delete(&graph).map_err(to_napi_err)
+        })
+        .await
+        .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
+    }
+
+    /// List all graph names.
+    #[napi(js_name = "graphList")]
+    pub async fn graph_list(&self) -> napi::Result<Vec<String>> {
+        let inner = self.inner.clone();
+        spawn_on(&self.pool, move || {
+            let guard = lock_inner(&inner)?;
+            guard.graph_list().map_err(to_napi_err)
+        })
+        .await
+        .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
+    }
+
+    /// Get graph metadata.
+    #[napi(js_name = "graphGetMeta")]
+    pub async fn graph_get_meta(&self, graph: String) -> napi::Result<serde_json::Value> {
+        let inner = self.inner.clone();
+        spawn_on(&self.pool, move || {
+            let guard = lock_inner(&inner)?;
+            match guard.graph_get_meta(&graph).map_err(to_napi_err)? {
+                Some(v) => Ok(value_to_js(v)),
+                None => Ok(serde_json::Value::Null),
+            }
+        })
+        .await
+        .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
+    }
+
+    // =========================================================================
+    // Graph — Nodes
+    // =========================================================================
+
+    /// Add or update a node.
+    #[napi(js_name = "graphAddNode")]
+    pub async fn graph_add_node(
+        &self,
+        graph: String,
+        node_id: String,
+        entity_ref: Option<String>,
+        properties: Option<serde_json::Value>,
+        object_type: Option<String>,
+    ) -> napi::Result<()> {
+        let inner = self.inner.clone();
+        let props = properties
+            .map(|p| js_to_value_checked(p, 0))
+            .transpose()?;
+        spawn_on(&self.pool, move || {
+            let guard = lock_inner(&inner)?;
+            ensure_writable(&guard)?;
+            guard
+                .graph_add_node_typed(
+                    &graph,
+                    &node_id,
+                    entity_ref.as_deref(),
+                    props,
+                    object_type.as_deref(),
+                )
+                .map_err(to_napi_err)
+        })
+        .await
+        .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
+    }
+
+    /// Get a node.
+    #[napi(js_name = "graphGetNode")]
+    pub async fn graph_get_node(
+        &self,
+        graph: String,
+        node_id: String,
+    ) -> napi::Result<serde_json::Value> {
+        let inner = self.inner.clone();
+        spawn_on(&self.pool, move || {
+            let guard = lock_inner(&inner)?;
+            match guard.graph_get_node(&graph, &node_id).map_err(to_napi_err)? {
+                Some(v) => Ok(value_to_js(v)),
+                None => Ok(serde_json::Value::Null),
+            }
+        })
+        .await
+        .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
+    }
+
+    /// Remove a node and its incident edges.
+    #[napi(js_name = "graphRemoveNode")]
+    pub async fn graph_remove_node(
+        &self,
+        graph: String,
+        node_id: String,
+    ) -> napi::Result<()> {
+        let inner = self.inner.clone();
+        spawn_on(&self.pool, move || {
+            let guard = lock_inner(&inner)?;
+            ensure_writable(&guard)?;
+            guard.graph_remove_node(&graph, &node_id).map_err(to_napi_err)
+        })
+        .await
+        .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
+    }
+
+    /// List all node IDs in a graph.
+    #[napi(js_name = "graphListNodes")]
+    pub async fn graph_list_nodes(&self, graph: String) -> napi::Result<Vec<String>> {
+        let inner = self.inner.clone();
+        spawn_on(&self.pool, move || {
+            let guard = lock_inner(&inner)?;
+            guard.graph_list_nodes(&graph).map_err(to_napi_err)
+        })
+        .await
+        .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
+    }
+
+    /// List node IDs with cursor-based pagination.
+    #[napi(js_name = "graphListNodesPaginated")]
+    pub async fn graph_list_nodes_paginated(
+        &self,
+        graph: String,
+        limit: u32,
+        cursor: Option<String>,
+    ) -> napi::Result<serde_json::Value> {
+        let inner = self.inner.clone();
+        spawn_on(&self.pool, move || {
+            let guard = lock_inner(&inner)?;
+            let (items, next_cursor) = guard
+                .graph_list_nodes_paginated(&graph, limit as usize, cursor.as_deref())
+                .map_err(to_napi_err)?;
+            Ok(serde_json::json!({
+                "items": items,
+                "nextCursor": next_cursor,
+            }))
+        })
+        .await
+        .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
+    }
+
+    // =========================================================================
+    // Graph — Edges
+    // =========================================================================
+
+    /// Add or update an edge.
+    #[napi(js_name = "graphAddEdge")]
+    pub async fn graph_add_edge(
+        &self,
+        graph: String,
+        src: String,
+        dst: String,
+        edge_type: String,
+        weight: Option<f64>,
+        properties: Option<serde_json::Value>,
+    ) -> napi::Result<()> {
+        let inner = self.inner.clone();
+        let props = properties
+            .map(|p| js_to_value_checked(p, 0))
+            .transpose()?;
+        spawn_on(&self.pool, move || {
+            let guard = lock_inner(&inner)?;
+            ensure_writable(&guard)?;
+            guard
+                .graph_add_edge(&graph, &src, &dst, &edge_type, weight, props)
+                .map_err(to_napi_err)
+        })
+        .await
+        .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
+    }
+
+    /// Remove an edge.
+    #[napi(js_name = "graphRemoveEdge")]
+    pub async fn graph_remove_edge(
+        &self,
+        graph: String,
+        src: String,
+        dst: String,
+        edge_type: String,
+    ) -> napi::Result<()> {
+        let inner = self.inner.clone();
+        spawn_on(&self.pool, move || {
+            let guard = lock_inner(&inner)?;
+            ensure_writable(&guard)?;
+            guard
+                .graph_remove_edge(&graph, &src, &dst, &edge_type)
+                .map_err(to_napi_err)
+        })
+        .await
+        .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
+    }
+
+    /// Get neighbors of a node.
+    #[napi(js_name = "graphNeighbors")]
+    pub async fn graph_neighbors(
+        &self,
+        graph: String,
+        node_id: String,
+        direction: Option<String>,
+        edge_type: Option<String>,
+    ) -> napi::Result<serde_json::Value> {
+        let inner = self.inner.clone();
+        spawn_on(&self.pool, move || {
+            let guard = lock_inner(&inner)?;
+            let dir = direction.as_deref().unwrap_or("outgoing");
+            let neighbors = guard
+                .graph_neighbors(&graph, &node_id, dir, edge_type.as_deref())
+                .map_err(to_napi_err)?;
+            let arr: Vec<serde_json::Value> = neighbors
+                .into_iter()
+                .map(|n| {
+                    serde_json::json!({
+                        "nodeId": n.node_id,
+                        "edgeType": n.edge_type,
+                        "weight": n.weight,
+                    })
+                })
+                .collect();
+            Ok(serde_json::Value::Array(arr))
+        })
+        .await
+        .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
+    }
+
+    // =========================================================================
+    // Graph — Bulk & Traversal
+    // =========================================================================
+
+    /// Bulk insert nodes and edges into a graph.
+    #[napi(js_name = "graphBulkInsert")]
+    pub async fn graph_bulk_insert(
+        &self,
+        graph: String,
+        nodes: Vec<serde_json::Value>,
+        edges: Vec<serde_json::Value>,
+        chunk_size: Option<u32>,
+    ) -> napi::Result<serde_json::Value> {
+        let inner = self.inner.clone();
+        let bulk_nodes: Vec<BulkGraphNode> = nodes
+            .into_iter()
+            .map(|v| {
+                let obj = v
+                    .as_object()
+                    .ok_or_else(|| napi::Error::from_reason("[VALIDATION] Expected object"))?;
+                let node_id = obj
+                    .get("nodeId")
+                    .or_else(|| obj.get("node_id"))
+                    .and_then(|k| k.as_str())
+                    .ok_or_else(|| napi::Error::from_reason("[VALIDATION] Missing 'nodeId'"))?
+                    .to_string();
+                let entity_ref = obj
+                    .get("entityRef")
+                    .or_else(|| obj.get("entity_ref"))
+                    .and_then(|k| k.as_str())
+                    .map(|s| s.to_string());
+                let properties = obj
+                    .get("properties")
+                    .filter(|v| !v.is_null())
+                    .map(|p| js_to_value_checked(p.clone(), 0))
+                    .transpose()?;
+                let object_type = obj
+                    .get("objectType")
+                    .or_else(|| obj.get("object_type"))
+                    .and_then(|k| k.as_str())
+                    .map(|s| s.to_string());
+                Ok(BulkGraphNode {
+                    node_id,
+                    entity_ref,
+                    properties,
+                    object_type,
+                })
+            })
+            .collect::<napi::Result<_>>()?;
+        let bulk_edges: Vec<BulkGraphEdge> = edges
+            .into_iter()
+            .map(|v| {
+                let obj = v
+                    .as_object()
+                    .ok_or_else(|| napi::Error::from_reason("[VALIDATION] Expected object"))?;
+                let src = obj
+                    .get("src")
+                    .and_then(|k| k.as_str())
+                    .ok_or_else(|| napi::Error::from_reason("[VALIDATION] Missing 'src'"))?
+                    .to_string();
+                let dst = obj
+                    .get("dst")
+                    .and_then(|k| k.as_str())
+                    .ok_or_else(|| napi::Error::from_reason("[VALIDATION] Missing 'dst'"))?
+                    .to_string();
+                let edge_type = obj
+                    .get("edgeType")
+                    .or_else(|| obj.get("edge_type"))
+                    .and_then(|k| k.as_str())
+                    .ok_or_else(|| napi::Error::from_reason("[VALIDATION] Missing 'edgeType'"))?
+                    .to_string();
+                let weight = obj.get("weight").and_then(|w| w.as_f64());
+                let properties = obj
+                    .get("properties")
+                    .filter(|v| !v.is_null())
+                    .map(|p| js_to_value_checked(p.clone(), 0))
+                    .transpose()?;
+                Ok(BulkGraphEdge {
+                    src,
+                    dst,
+                    edge_type,
+                    weight,
+                    properties,
+                })
+            })
+            .collect::<napi::Result<_>>()?;
+        spawn_on(&self.pool, move || {
+            let guard = lock_inner(&inner)?;
+            ensure_writable(&guard)?;
+            let (nodes_inserted, edges_inserted) = guard
+                .graph_bulk_insert_typed(
+                    &graph,
+                    bulk_nodes,
+                    bulk_edges,
+                    chunk_size.map(|c| c as usize),
+                )
+                .map_err(to_napi_err)?;
+            Ok(serde_json::json!({
+                "nodesInserted": nodes_inserted,
+                "edgesInserted": edges_inserted,
+            }))
+        })
+        .await
+        .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
+    }
+
+    /// BFS traversal from a start node.
+    #[napi(js_name = "graphBfs")]
+    pub async fn graph_bfs(
+        &self,
+        graph: String,
+        start: String,
+        max_depth: u32,
+        max_nodes: Option<u32>,
+        edge_types: Option<Vec<String>>,
+        direction: Option<String>,
+    ) -> napi::Result<serde_json::Value> {
+        let inner = self.inner.clone();
+        spawn_on(&self.pool, move || {
+            let guard = lock_inner(&inner)?;
+            let result = guard
+                .graph_bfs(
+                    &graph,
+                    &start,
+                    max_depth as usize,
+                    max_nodes.map(|n| n as usize),
+                    edge_types,
+                    direction.as_deref(),
+                )
+                .map_err(to_napi_err)?;
+            Ok(graph_bfs_result_to_js(result))
+        })
+        .await
+        .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
+    }
+
+    // =========================================================================
+    // Graph — Ontology
+    // =========================================================================
+
+    /// Define an object type in the graph ontology.
+    #[napi(js_name = "graphDefineObjectType")]
+    pub async fn graph_define_object_type(
+        &self,
+        graph: String,
+        definition: serde_json::Value,
+    ) -> napi::Result<()> {
+        let inner = self.inner.clone();
+        let def = js_to_value_checked(definition, 0)?;
+        spawn_on(&self.pool, move || {
+            let guard = lock_inner(&inner)?;
+            ensure_writable(&guard)?;
+            guard
+                .graph_define_object_type(&graph, def)
+                .map_err(to_napi_err)
+        })
+        .await
+        .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
+    }
+
+    /// Get an object type definition.
+    #[napi(js_name = "graphGetObjectType")]
+    pub async fn graph_get_object_type(
+        &self,
+        graph: String,
+        name: String,
+    ) -> napi::Result<serde_json::Value> {
+        let inner = self.inner.clone();
+        spawn_on(&self.pool, move || {
+            let guard = lock_inner(&inner)?;
+            match guard.graph_get_object_type(&graph, &name).map_err(to_napi_err)? {
+                Some(v) => Ok(value_to_js(v)),
+                None => Ok(serde_json::Value::Null),
+            }
+        })
+        .await
+        .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
+    }
+
+    /// List all object type names.
+    #[napi(js_name = "graphListObjectTypes")]
+    pub async fn graph_list_object_types(
+        &self,
+        graph: String,
+    ) -> napi::Result<Vec<String>> {
+        let inner = self.inner.clone();
+        spawn_on(&self.pool, move || {
+            let guard = lock_inner(&inner)?;
+            guard.graph_list_object_types(&graph).map_err(to_napi_err)
+        })
+        .await
+        .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
+    }
+
+    /// Delete an object type definition.
+    #[napi(js_name = "graphDeleteObjectType")]
+    pub async fn graph_delete_object_type(
+        &self,
+        graph: String,
+        name: String,
+    ) -> napi::Result<()> {
+        let inner = self.inner.clone();
+        spawn_on(&self.pool, move || {
+            let guard = lock_inner(&inner)?;
+            ensure_writable(&guard)?;
+            guard.graph_delete_object_type(&graph, &name).map_err(to_napi_err)
+        })
+        .await
+        .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
+    }
+
+    /// Define a link type in the graph ontology.
+    #[napi(js_name = "graphDefineLinkType")]
+    pub async fn graph_define_link_type(
+        &self,
+        graph: String,
+        definition: serde_json::Value,
+    ) -> napi::Result<()> {
+        let inner = self.inner.clone();
+        let def = js_to_value_checked(definition, 0)?;
+        spawn_on(&self.pool, move || {
+            let guard = lock_inner(&inner)?;
+            ensure_writable(&guard)?;
+            guard.graph_define_link_type(&graph, def).map_err(to_napi_err)
+        })
+        .await
+        .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
+    }
+
+    /// Get a link type definition.
+    #[napi(js_name = "graphGetLinkType")]
+    pub async fn graph_get_link_type(
+        &self,
+        graph: String,
+        name: String,
+    ) -> napi::Result<serde_json::Value> {
+        let inner = self.inner.clone();
+        spawn_on(&self.pool, move || {
+            let guard = lock_inner(&inner)?;
+            match guard.graph_get_link_type(&graph, &name).map_err(to_napi_err)? {
+                Some(v) => Ok(value_to_js(v)),
+                None => Ok(serde_json::Value::Null),
+            }
+        })
+        .await
+        .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
+    }
+
+    /// List all link type names.
+    #[napi(js_name = "graphListLinkTypes")]
+    pub async fn graph_list_link_types(
+        &self,
+        graph: String,
+    ) -> napi::Result<Vec<String>> {
+        let inner = self.inner.clone();
+        spawn_on(&self.pool, move || {
+            let guard = lock_inner(&inner)?;
+            guard.graph_list_link_types(&graph).map_err(to_napi_err)
+        })
+        .await
+        .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
+    }
+
+    /// Delete a link type definition.
+    #[napi(js_name = "graphDeleteLinkType")]
+    pub async fn graph_delete_link_type(
+        &self,
+        graph: String,
+        name: String,
+    ) -> napi::Result<()> {
+        let inner = self.inner.clone();
+        spawn_on(&self.pool, move || {
+            let guard = lock_inner(&inner)?;
+            ensure_writable(&guard)?;
+            guard.graph_delete_link_type(&graph, &name).map_err(to_napi_err)
+        })
+        .await
+        .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
+    }
+
+    /// Freeze the graph ontology (no more type changes).
+    #[napi(js_name = "graphFreezeOntology")]
+    pub async fn graph_freeze_ontology(&self, graph: String) -> napi::Result<()> {
+        let inner = self.inner.clone();
+        spawn_on(&self.pool, move || {
+            let guard = lock_inner(&inner)?;
+            ensure_writable(&guard)?;
+            guard.graph_freeze_ontology(&graph).map_err(to_napi_err)
+        })
+        .await
+        .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
+    }
+
+    /// Get the ontology status of a graph.
+    #[napi(js_name = "graphOntologyStatus")]
+    pub async fn graph_ontology_status(
+        &self,
+        graph: String,
+    ) -> napi::Result<serde_json::Value> {
+        let inner = self.inner.clone();
+        spawn_on(&self.pool, move || {
+            let guard = lock_inner(&inner)?;
+            match guard.graph_ontology_status(&graph).map_err(to_napi_err)? {
+                Some(v) => Ok(value_to_js(v)),
+                None => Ok(serde_json::Value::Null),
+            }
+        })
+        .await
+        .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
+    }
+
+    /// Get a complete ontology summary.
+    #[napi(js_name = "graphOntologySummary")]
+    pub async fn graph_ontology_summary(
+        &self,
+        graph: String,
+    ) -> napi::Result<serde_json::Value> {
+        let inner = self.inner.clone();
+        spawn_on(&self.pool, move || {
+            let guard = lock_inner(&inner)?;
+            match guard.graph_ontology_summary(&graph).map_err(to_napi_err)? {
+                Some(v) => Ok(value_to_js(v)),
+                None => Ok(serde_json::Value::Null),
+            }
+        })
+        .await
+        .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
+    }
+
+    /// List all ontology types (both object and link types).
+    #[napi(js_name = "graphListOntologyTypes")]
+    pub async fn graph_list_ontology_types(
+        &self,
+        graph: String,
+    ) -> napi::Result<Vec<String>> {
+        let inner = self.inner.clone();
+        spawn_on(&self.pool, move || {
+            let guard = lock_inner(&inner)?;
+            guard.graph_list_ontology_types(&graph).map_err(to_napi_err)
+        })
+        .await
+        .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
+    }
+
+    /// Get all node IDs of a given object type.
+    #[napi(js_name = "graphNodesByType")]
+    pub async fn graph_nodes_by_type(
+        &self,
+        graph: String,
+        object_type: String,
+    ) -> napi::Result<Vec<String>> {
+        let inner = self.inner.clone();
+        spawn_on(&self.pool, move || {
+            let guard = lock_inner(&inner)?;
+            guard
+                .graph_nodes_by_type(&graph, &object_type)
+                .map_err(to_napi_err)
+        })
+        .await
+        .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
+    }
+
+    // =========================================================================
+    // Graph — Analytics
+    // =========================================================================
+
+    /// Weakly Connected Components.
+    #[napi(js_name = "graphWcc")]
+    pub async fn graph_wcc(&self, graph: String) -> napi::Result<serde_json::Value> {
+        let inner = self.inner.clone();
+        spawn_on(&self.pool, move || {
+            let guard = lock_inner(&inner)?;
+            let result = guard.graph_wcc(&graph, None, None).map_err(to_napi_err)?;
+            graph_group_summary_to_js(result)
+        })
+        .await
+        .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
+    }
+
+    /// Community Detection via Label Propagation.
+    #[napi(js_name = "graphCdlp")]
+    pub async fn graph_cdlp(
+        &self,
+        graph: String,
+        max_iterations: u32,
+        direction: Option<String>,
+    ) -> napi::Result<serde_json::Value> {
+        let inner = self.inner.clone();
+        spawn_on(&self.pool, move || {
+            let guard = lock_inner(&inner)?;
+            let result = guard
+                .graph_cdlp(
+                    &graph,
+                    max_iterations as usize,
+                    direction.as_deref(),
+                    None,
+                    None,
+                )
+                .map_err(to_napi_err)?;
+            graph_group_summary_to_js(result)
+        })
+        .await
+        .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
+    }
+
+    /// PageRank importance scoring.
+    #[napi(js_name = "graphPagerank")]
+    pub async fn graph_pagerank(
+        &self,
+        graph: String,
+        damping: Option<f64>,
+        max_iterations: Option<u32>,
+        tolerance: Option<f64>,
+    ) -> napi::Result<serde_json::Value> {
+        let inner = self.inner.clone();
+        spawn_on(&self.pool, move || {
+            let guard = lock_inner(&inner)?;
+            let result = guard
+                .graph_pagerank(
+                    &graph,
+                    damping,
+                    max_iterations.map(|m| m as usize),
+                    tolerance,
+                    None,
+                    None,
+                )
+                .map_err(to_napi_err)?;
+            graph_score_summary_to_js(result)
+        })
+        .await
+        .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
+    }
+
+    /// Local Clustering Coefficient.
+    #[napi(js_name = "graphLcc")]
+    pub async fn graph_lcc(&self, graph: String) -> napi::Result<serde_json::Value> {
+        let inner = self.inner.clone();
+        spawn_on(&self.pool, move || {
+            let guard = lock_inner(&inner)?;
+            let result = guard.graph_lcc(&graph, None, None).map_err(to_napi_err)?;
+            graph_score_summary_to_js(result)
+        })
+        .await
+        .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
+    }
+
+    /// Single-Source Shortest Path (Dijkstra).
+    #[napi(js_name = "graphSssp")]
+    pub async fn graph_sssp(
+        &self,
+        graph: String,
+        source: String,
+        direction: Option<String>,
+    ) -> napi::Result<serde_json::Value> {
+        let inner = self.inner.clone();
+        spawn_on(&self.pool, move || {
+            let guard = lock_inner(&inner)?;
+            let result = guard
+                .graph_sssp(&graph, &source, direction.as_deref(), None, None)
+                .map_err(to_napi_err)?;
+            graph_score_summary_to_js(result)
+        })
+        .await
+        .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
+    }
+
+    // =========================================================================
+    // System Branch
+    // =========================================================================
+
+    /// This database's id, kept on the `_system_` branch, which stays the
+    /// same wherever the database is opened from. `null` if it has none;
+    /// given `candidate`, a database without one takes it, which needs a
+    /// writable handle.
+    #[napi(js_name = "databaseId")]
+    pub async fn database_id(&self, candidate: Option<String>) -> napi::Result<Option<String>> {
+        if candidate.as_deref() == Some("") {
+            return Err(napi::Error::from_reason(
+                "[VALIDATION] Database id must not be empty",
+            ));
+        }
+        let inner = self.inner.clone();
+        let writable = self.access_mode == "readWrite";
+        spawn_on(&self.pool, move || {
+            // Held off every handle, so two can't both assign an id.
+            let guard = lock_database(&inner)?;
+            let system = guard.system_branch();
+            match system.kv_get(DATABASE_ID_KEY).map_err(to_napi_err)? {
+                Some(Value::String(id)) => return Ok(Some(id)),
+                Some(_) => {
+                    return Err(napi::Error::from_reason(
+                        "[STATE] Database id on the _system_ branch is not a string",
+                    ))
+                }
+                None => {}
+            }
+            let Some(id) = candidate else {
+                return Ok(None);
+            };
+            if !writable {
+                return Err(napi::Error::from_reason(
+                    "[STATE] Database has no id yet; open it for writing to assign one",
+                ));
+            }
+            system
+                .kv_put(DATABASE_ID_KEY, Value::String(id.clone()))
+                .map_err(to_napi_err)?;
+            Ok(Some(id))
+        })
+        .await
+        .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
+    }
+
+    /// Get a handle to the `_system_` branch.
+    ///
+    /// Returns a `SystemBranch` object with KV, JSON, state, and event
+    /// methods pre-bound to the internal `_system_` branch.
+    #[napi(js_name = "systemBranch")]
+    pub fn system_branch_handle(&self) -> napi::Result<SystemBranch> {
+        ensure_open(&self.inner)?;
+        Ok(SystemBranch {
+            inner: self.inner.clone(),
+        })
+    }
+}
+
+// =============================================================================
+// Bundle streams — chunked access to a bundle staged in a temporary file
+// =============================================================================
+
+/// A fresh path in the OS temp directory for staging a streamed bundle.
+fn bundle_temp_path() -> std::path::PathBuf {
+    static NEXT: AtomicU64 = AtomicU64::new(0);
+    let n = NEXT.fetch_add(1, Ordering::Relaxed);
+    std::env::temp_dir().join(format!("strata-bundle-{}-{}.tmp", std::process::id(), n))
+}
+
+fn lock_file(
+    file: &Mutex<Option<std::fs::File>>,
+) -> napi::Result<std::sync::MutexGuard<'_, Option<std::fs::File>>> {
+    file.lock()
+        .map_err(|_| napi::Error::from_reason("Lock poisoned"))
+}
+
+/// Reader over an exported bundle. Obtained via `db.branchExportOpen()`.
+#[napi]
+pub struct BundleReader {
+    file: Arc<Mutex<Option<std::fs::File>>>,
+    path: std::path::PathBuf,
+}
+
+#[napi]
+impl BundleReader {
+    /// Read the next chunk of at most `size` bytes, or `null` at the end.
+    #[napi]
+    pub async fn read(&self, size: u32) -> napi::Result<Option<Buffer>> {
+        let file = self.file.clone();
+        let chunk = tokio::task::spawn_blocking(move || {
+            let mut guard = lock_file(&file)?;
+            let Some(f) = guard.as_mut() else {
+                return Ok(None);
+            };
+            let mut buf = vec![0u8; size.max(1) as usize];
+            let n = f.read(&mut buf).map_err(io_err)?;
+            if n == 0 {
+                *guard = None;
+                return Ok(None);
+            }
+            buf.truncate(n);
+            Ok(Some(buf))
+        })
+        .await
+        .map_err(|e| napi::Error::from_reason(format!("{}", e)))??;
+        Ok(chunk.map(Buffer::from))
+    }
+
+    /// Release the staged bundle. Further reads return `null`.
+    #[napi]
+    pub fn close(&self) -> napi::Result<()> {
+        *lock_file(&self.file)? = None;
+        remove_staged(&self.path)
+    }
+}
+
+impl Drop for BundleReader {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// Writer that stages a streamed bundle and imports it on `finish()`.
+/// Obtained via `db.branchImportOpen()`.
+#[napi]
+pub struct BundleWriter {
+    inner: Arc<HandleDb>,
+    file: Arc<Mutex<Option<std::fs::File>>>,
+    path: std::path::PathBuf,
+    options: BundleImportOptions,
+    on_conflict: ImportConflict,
+}
+
+#[napi]
+impl BundleWriter {
+    /// Append a chunk of bundle bytes.
+    #[napi]
+    pub async fn write(&self, chunk: Buffer) -> napi::Result<()> {
+        ensure_open(&self.inner)?;
+        let file = self.file.clone();
+        let data = chunk.to_vec();
+        tokio::task::spawn_blocking(move || {
+            let mut guard = lock_file(&file)?;
+            let f = guard.as_mut().ok_or_else(|| {
+                napi::Error::from_reason("[STATE] Bundle writer is already finished")
+            })?;
+            f.write_all(&data).map_err(io_err)
+        })
+        .await
+        .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
+    }
+
+    /// Import the staged bundle and release it.
+    #[napi]
+    pub async fn finish(&self) -> napi::Result<serde_json::Value> {
+        let inner = self.inner.clone();
+        let file = self.file.clone();
+        let path = self.path.clone();
+        let options = self.options.clone();
+        let on_conflict = self.on_conflict;
+        tokio::task::spawn_blocking(move || {
+            let f = lock_file(&file)?.take().ok_or_else(|| {
+                napi::Error::from_reason("[STATE] Bundle writer is already finished")
+            })?;
+            let imported = f.sync_all().map_err(io_err).and_then(|_| {
+                drop(f);
+                let guard = lock_inner(&inner)?;
+                import_bundle(&guard, &path.to_string_lossy(), options, on_conflict)
+            });
+            remove_staged(&path)?;
+            Ok(branch_import_result_to_js(imported?))
+        })
+        .await
+        .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
+    }
+
+    /// Discard the staged bundle without importing it.
+    #[napi]
+    pub fn abort(&self) -> napi::Result<()> {
+        *lock_file(&self.file)? = None;
+        remove_staged(&self.path)
+    }
+}
+
+impl Drop for BundleWriter {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// Remove a staged bundle file, ignoring one that is already gone.
+fn remove_staged(path: &std::path::Path) -> napi::Result<()> {
+    match std::fs::remove_file(path) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(io_err(e)),
+        _ => Ok(()),
+    }
+}
+
+// =============================================================================
+// SystemBranch — handle pre-bound to the _system_ branch
+// =============================================================================
+
+/// Handle for operations on the internal `_system_` branch.
+///
+/// Obtained via `db.systemBranch()`. All operations are routed to the
+/// `_system_` branch regardless of the database's current branch context.
+#[napi]
+pub struct SystemBranch {
+    inner: Arc<HandleDb>,
+}
+
+#[napi]
+impl SystemBranch {
+    // -- KV --
+
+    #[napi(js_name = "kvPut")]
+    pub async fn kv_put(&self, key: String, value: serde_json::Value) -> napi::Result<i64> {
+        let inner = self.inner.clone();
+        let v = js_to_value_checked(value, 0)?;
+        tokio::task::spawn_blocking(move || {
+            let guard = lock_inner(&inner)?;
+            guard
+                .system_branch()
+                .kv_put(&key, v)
+                .map(|n| n as i64)
+                .map_err(to_napi_err)
+        })
+        .await
+        .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
+    }
+
+    #[napi(js_name = "kvGet")]
+    pub async fn kv_get(&self, key: String) -> napi::Result<serde_json::Value> {
+        let inner = self.inner.clone();
+        tokio::task::spawn_blocking(move || {
+            let guard = lock_inner(&inner)?;
+            match guard.system_branch().kv_get(&key).map_err(to_napi_err)? {
+                Some(v) => Ok(value_to_js(v)),
+                None => Ok(serde_json::Value::Null),
+            }
+        })
+        .await
+        .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
+    }
+
+    #[napi(js_name = "kvDelete")]
+    pub async fn kv_delete(&self, key: String) -> napi::Result<bool> {
+        let inner = self.inner.clone();
+        tokio::task::spawn_blocking(move || {
+            let guard = lock_inner(&inner)?;
+            guard.system_branch().kv_delete(&key).map_err(to_napi_err)
+        })
+        .await
+        .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
+    }
+
+    #[napi(js_name = "kvList")]
+    pub async fn kv_list(&self, prefix: Option<String>) -> napi::Result<Vec<String>> {
+        let inner = self.inner.clone();
+        tokio::task::spawn_blocking(move || {
+            let guard = lock_inner(&inner)?;
+            guard
+                .system_branch()
+                .kv_list(prefix.as_deref())
+                .map_err(to_napi_err)
+        })
+        .await
+        .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
+    }
+
+    // -- JSON --
+
+    #[napi(js_name = "jsonSet")]
+    pub async fn json_set(
+        &self,
+        key: String,
+        path: String,
+        value: serde_json::Value,
+    ) -> napi::Result<i64> {
+        let inner = self.inner.clone();
+        let v = js_to_value_checked(value, 0)?;
+        tokio::task::spawn_blocking(move || {
+            let guard = lock_inner(&inner)?;
+            guard
+                .system_branch()
+                .json_set(&key, &path, v)
+                .map(|n| n as i64)
+                .map_err(to_napi_err)
+        })
+        .await
+        .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
+    }
+
+    #[napi(js_name = "jsonGet")]
+    pub async fn json_get(&self, key: String, path: String) -> napi::Result<serde_json::Value> {
+        let inner = self.inner.clone();
+        tokio::task::spawn_blocking(move || {
+            let guard = lock_inner(&inner)?;
+            match guard
+                .system_branch()
+                .json_get(&key, &path)
+                .map_err(to_napi_err)?
+            {
+                Some(v) => Ok(value_to_js(v)),
+                None => Ok(serde_json::Value::Null),
+            }
+        })
+        .await
+        .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
+    }
+
+    #[napi(js_name = "jsonDelete")]
+    pub async fn json_delete(&self, key: String, path: String) -> napi::Result<i64> {
+        let inner = self.inner.clone();
+        tokio::task::spawn_blocking(move || {
+            let guard = lock_inner(&inner)?;
+            guard
+                .system_branch()
+                .json_delete(&key, &path)
+                .map(|n| n as i64)
+                .map_err(to_napi_err)
+        })
+        .await
+        .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
+    }
+
+    // -- State --
+
+    #[napi(js_name = "stateSet")]
+    pub async fn state_set(&self, cell: String, value: serde_json::Value) -> napi::Result<i64> {
+        let inner = self.inner.clone();
+        let v = js_to_value_checked(value, 0)?;
+        tokio::task::spawn_blocking(move || {
+            let guard = lock_inner(&inner)?;
+            guard
+                .system_branch()
+                .state_set(&cell, v)
+                .map(|n| n as i64)
+                .map_err(to_napi_err)
+        })
+        .await
+        .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
+    }
+
+    #[napi(js_name = "stateGet")]
+    pub async fn state_get(&self, cell: String) -> napi::Result<serde_json::Value> {
+        let inner = self.inner.clone();
+        tokio::task::spawn_blocking(move || {
+            let guard = lock_inner(&inner)?;
+            match guard
+                .system_branch()
+                .state_get(&cell)
+                .map_err(to_napi_err)?
+            {
+                Some(v) => Ok(value_to_js(v)),
+                None => Ok(serde_json::Value::Null),
+            }
+        })
+        .await
+        .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
+    }
+
+    // -- Events --
+
+    #[napi(js_name = "eventAppend")]
+    pub async fn event_append(
+        &self,
+        event_type: String,
+        payload: serde_json::Value,
+    ) -> napi::Result<i64> {
+        let inner = self.inner.clone();
+        let v = js_to_value_checked(payload, 0)?;
+        tokio::task::spawn_blocking(move || {
+            let guard = lock_inner(&inner)?;
+            guard
+                .system_branch()
+                .event_append(&event_type, v)
+                .map(|n| n as i64)
+                .map_err(to_napi_err)
+        })
+        .await
+        .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
+    }
+
+    #[napi(js_name = "eventGet")]
+    pub async fn event_get(&self, sequence: i64) -> napi::Result<serde_json::Value> {
+        let inner = self.inner.clone();
+        tokio::task::spawn_blocking(move || {
+            let guard = lock_inner(&inner)?;
+            match guard
+                .system_branch()
+                .event_get(sequence as u64)
+                .map_err(to_napi_err)?
+            {
+                Some(vv) => Ok(versioned_to_js(vv)),
+                None => Ok(serde_json::Value::Null),
+            }
+        })
+        .await
+        .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Batch result helpers
+// ---------------------------------------------------------------------------
+
+fn batch_results_to_js(results: Vec<BatchItemResult>) -> serde_json::Value {
+    let arr: Vec<serde_json::Value> = results
+        .into_iter()
+        .map(|r| {
+            serde_json::json!({
+                "version": r.version.map(|v| v as i64),
+                "error": r.error,
+            })
+        })
+        .collect();
+    serde_json::Value::Array(arr)
+}
+
+fn batch_get_results_to_js(results: Vec<BatchGetItemResult>) -> serde_json::Value {
+    let arr: Vec<serde_json::Value> = results
+        .into_iter()
+        .map(|r| {
+            serde_json::json!({
+                "value": r.value.map(value_to_js),
+                "version": r.version.map(|v| v as i64),
+                "timestamp": r.timestamp.map(|t| t as i64),
+                "error": r.error,
+            })
+        })
+        .collect();
+    serde_json::Value::Array(arr)
+}
+
+// ---------------------------------------------------------------------------
+// Top-level functions
+// ---------------------------------------------------------------------------
+
+/// JS callback receiving `{ level, message, fields }` log records.
+type LogCallback = ThreadsafeFunction<serde_json::Value, ErrorStrategy::Fatal>;
+
+/// The sink set by `setLogSink()`.
+fn log_sink() -> &'static Mutex<Option<LogCallback>> {
+    static SINK: OnceLock<Mutex<Option<LogCallback>>> = OnceLock::new();
+    SINK.get_or_init(|| Mutex::new(None))
+}
+
+/// Send a log record to the JS sink. Without one, warnings and errors go
+/// to stderr and the rest are dropped.
+fn log(level: &str, message: &str, fields: serde_json::Value) {
+    let sink = log_sink().lock().unwrap_or_else(|e| e.into_inner());
+    match sink.as_ref() {
+        Some(sink) => {
+            sink.call(
+                serde_json::json!({ "level": level, "message": message, "fields": fields }),
+                ThreadsafeFunctionCallMode::NonBlocking,
+            );
+        }
+        None if level == "warn" => eprintln!("Warning: {}", message),
+        None if level == "error" => eprintln!("Error: {}", message),
+        None => {}
+    }
+}
+
+/// Route the binding's log records to `sink` as `{ level, message, fields }`
+/// instead of stderr. `null` restores stderr.
+#[napi(js_name = "setLogSink")]
+pub fn set_log_sink(env: Env, sink: Option<LogCallback>) -> napi::Result<()> {
+    let mut sink = sink;
+    // A registered logger shouldn't keep the process alive.
+    if let Some(sink) = sink.as_mut() {
+        sink.unref(&env)?;
+    }
+    *log_sink().lock().unwrap_or_else(|e| e.into_inner()) = sink;
+    Ok(())
+}
+
+/// Download model files for auto-embedding.
+#[napi]
+pub fn setup() -> napi::Result<String> {
+    #[cfg(feature = "embed")]
+    {
+        let path = strata_intelligence::embed::download::ensure_model()
+            .map_err(napi::Error::from_reason)?;
+        Ok(path.to_string_lossy().into_owned())
+    }
+
+    #[cfg(not(feature = "embed"))]
+    {
+        Err(napi::Error::from_reason(
+            "The 'embed' feature is not enabled in this build",
+        ))
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Branch annotations (kept on the _system_ branch)
+// ---------------------------------------------------------------------------
+
+/// `_system_` KV key prefix for per-branch annotations.
+const BRANCH_META_PREFIX: &str = "branch-meta/";
+
+fn branch_meta_key(branch: &str) -> String {
+    format!("{}{}", BRANCH_META_PREFIX, branch)
+}
+
+/// Read a branch's annotations; empty if none were ever set.
+fn read_branch_meta(
+    db: &RustStrata,
+    branch: &str,
+) -> napi::Result<serde_json::Map<String, serde_json::Value>> {
+    let stored = db
+        .system_branch()
+        .kv_get(&branch_meta_key(branch))
+        .map_err(to_napi_err)?;
+    match stored.map(value_to_js) {
+        Some(serde_json::Value::Object(m)) => Ok(m),
+        _ => Ok(serde_json::Map::new()),
+    }
+}
+
+fn write_branch_meta(
+    db: &RustStrata,
+    branch: &str,
+    meta: serde_json::Map<String, serde_json::Value>,
+) -> napi::Result<()> {
+    let v = js_to_value_checked(serde_json::Value::Object(meta), 0)?;
+    db.system_branch()
+        .kv_put(&branch_meta_key(branch), v)
+        .map(|_| ())
+        .map_err(to_napi_err)
+}
+
+fn branch_flag(meta: &serde_json::Map<String, serde_json::Value>, flag: &str) -> bool {
+    meta.get(flag).and_then(|v| v.as_bool()).unwrap_or(false)
+}
+
+fn set_branch_flag(db: &RustStrata, branch: &str, flag: &str, enabled: bool) -> napi::Result<()> {
+    if !db.branches().exists(branch).map_err(to_napi_err)? {
+        return Err(napi::Error::from_reason(format!(
+            "[NOT_FOUND] Branch not found: {}",
+            branch
+        )));
+    }
+    let mut meta = read_branch_meta(db, branch)?;
+    if enabled {
+        meta.insert(flag.into(), serde_json::Value::Bool(true));
+    } else {
+        meta.remove(flag);
+    }
+    write_branch_meta(db, branch, meta)
+}
+
+/// Names of all archived branches.
+fn archived_branches(db: &RustStrata) -> napi::Result<HashSet<String>> {
+    let keys = db
+        .system_branch()
+        .kv_list(Some(BRANCH_META_PREFIX))
+        .map_err(to_napi_err)?;
+    let mut out = HashSet::new();
+    for key in keys {
+        let branch = &key[BRANCH_META_PREFIX.len()..];
+        if branch_flag(&read_branch_meta(db, branch)?, "archived") {
+            out.insert(branch.to_string());
+        }
+    }
+    Ok(out)
+}
+
+/// Reject writes to `branch` if it is archived, or protected and the write
+/// is not a merge.
+fn ensure_branch_writable(db: &RustStrata, branch: &str, via_merge: bool) -> napi::Result<()> {
+    let meta = read_branch_meta(db, branch)?;
+    if branch_flag(&meta, "archived") {
+        return Err(napi::Error::from_reason(format!(
+            "[ACCESS_DENIED] Branch '{}' is archived and read-only",
+            branch
+        )));
+    }
+    if !via_merge && branch_flag(&meta, "protected") {
+        return Err(napi::Error::from_reason(format!(
+            "[ACCESS_DENIED] Branch '{}' is protected; merge into it instead",
+            branch
+        )));
+    }
+    Ok(())
+}
+
+/// Reject direct writes to the current branch if it is protected or archived.
+fn ensure_writable(db: &RustStrata) -> napi::Result<()> {
+    let branch = db.current_branch().to_string();
+    ensure_branch_writable(db, &branch, false)
+}
+
+// ---------------------------------------------------------------------------
+// Reserved state cells and KV keys
+// ---------------------------------------------------------------------------
+
+/// State cell prefixes kept by the methods named, which only they may
+/// write.
+const RESERVED_CELL_PREFIXES: &[(&str, &str)] = &[
+    (COUNTER_PREFIX, "counters"),
+    (QUEUE_PREFIX, "queues"),
+    (QUEUE_HEAD_PREFIX, "queues"),
+    (LOCK_PREFIX, "locks"),
+    (BLOB_PREFIX, "blobs"),
+    (EDGE_PREFIX, "edges"),
+    (TS_PREFIX, "time series"),
+    (COLLECTION_PREFIX, "collections"),
+    (TRACE_PREFIX, "traces"),
+];
+
+/// KV key prefixes kept by the methods named, which only they may write.
+const RESERVED_KEY_PREFIXES: &[(&str, &str)] = &[
+    (BLOB_PREFIX, "blobs"),
+    (TS_PREFIX, "time series"),
+    (TRACE_PREFIX, "traces"),
+    (ANNOTATION_PREFIX, "write annotations"),
+];
+
+/// The methods that keep `name`, if it starts with one of `prefixes`.
+fn reserved_by(prefixes: &[(&str, &'static str)], name: &str) -> Option<&'static str> {
+    let (_, owner) = prefixes.iter().find(|(p, _)| name.starts_with(p))?;
+    Some(*owner)
+}
+
+/// Reject user writes to a state cell in `RESERVED_CELL_PREFIXES`.
+fn check_user_cell(cell: &str) -> napi::Result<()> {
+    if let Some(owner) = reserved_by(RESERVED_CELL_PREFIXES, cell) {
+        return Err(napi::Error::from_reason(format!(
+            "[VALIDATION] State cell '{}' is reserved for {}",
+            cell, owner
+        )));
+    }
+    Ok(())
+}
+
+/// Reject user writes to a KV key in `RESERVED_KEY_PREFIXES`.
+fn check_user_key(key: &str) -> napi::Result<()> {
+    if let Some(owner) = reserved_by(RESERVED_KEY_PREFIXES, key) {
+        return Err(napi::Error::from_reason(format!(
+            "[VALIDATION] Key '{}' is reserved for {}",
+            key, owner
+        )));
+    }
+    Ok(())
+}
+
+// ---------------------------------------------------------------------------
+// Counters (kept as state cells)
+// ---------------------------------------------------------------------------
+
+/// State cell prefix for counters, so each total is a version of the cell.
+const COUNTER_PREFIX: &str = "_counter/";
+
+fn counter_cell(name: &str) -> String {
+    format!("{}{}", COUNTER_PREFIX, name)
+}
+
+/// A counter cell's total: 0 if it doesn't exist.
+fn counter_total(cell: &str, value: Option<&Value>) -> napi::Result<i64> {
+    match value {
+        None | Some(Value::Null) => Ok(0),
+        Some(Value::Int(n)) => Ok(*n),
+        Some(_) => Err(napi::Error::from_reason(format!(
+            "[STATE] State cell '{}' holds a value that is not an integer",
+            cell
+        ))),
+    }
+}
+
+/// Compare-and-swap attempts `update_counter` and `replace_state` make
+/// before giving up.
+const COUNTER_RETRIES: usize = 100;
+
+fn lock_gate(gate: &Mutex<()>) -> napi::Result<std::sync::MutexGuard<'_, ()>> {
+    gate.lock()
+        .map_err(|_| napi::Error::from_reason("Lock poisoned"))
+}
+
+/// Replace the total in counter cell `cell` with `next(total)`, retrying
+/// the compare-and-swap until no other write gets in between. Returns the
+/// totals before and after. Callers hold the database's `counter_gate`, so
+/// only writes made outside counters can force a retry.
+fn update_counter(
+    db: &RustStrata,
+    cell: &str,
+    next: impl Fn(i64) -> napi::Result<i64>,
+) -> napi::Result<(i64, i64)> {
+    for _ in 0..COUNTER_RETRIES {
+        let Some(latest) = latest_version(db, cell)? else {
+            db.state_init(cell, Value::Int(0)).map_err(to_napi_err)?;
+            continue;
+        };
+        let total = counter_total(cell, Some(&latest.value))?;
+        let updated = next(total)?;
+        let swapped = db
+            .state_cas(cell, Some(latest.version), Value::Int(updated))
+            .map_err(to_napi_err)?;
+        if swapped.is_some() {
+            return Ok((total, updated));
+        }
+    }
+    Err(napi::Error::from_reason(format!(
+        "[CONFLICT] State cell '{}' kept changing; gave up after {} attempts",
+        cell, COUNTER_RETRIES
+    )))
+}
+
+// ---------------------------------------------------------------------------
+// Queues (kept as state cells)
+// ---------------------------------------------------------------------------
+
+/// State cell prefix for queues: `_queue/<queue>` holds the last sequence
+/// number and `_queue/<queue>/<seq>` each unacknowledged message.
+const QUEUE_PREFIX: &str = "_queue/";
+
+/// State cell prefix for queue head indexes: `_queue_head/<queue>` holds a
+/// sequence number below which every message was acknowledged.
+const QUEUE_HEAD_PREFIX: &str = "_queue_head/";
+
+/// Default `visibilityTimeout` of `queuePop`.
+const DEFAULT_VISIBILITY_TIMEOUT_MS: u32 = 30_000;
+
+fn queue_seq_cell(queue: &str) -> String {
+    format!("{}{}", QUEUE_PREFIX, queue)
+}
+
+fn queue_head_cell(queue: &str) -> String {
+    format!("{}{}", QUEUE_HEAD_PREFIX, queue)
+}
+
+/// Sequence numbers are zero-padded so cells sort in push order.
+fn queue_cell(queue: &str, seq: i64) -> String {
+    format!("{}{}/{:020}", QUEUE_PREFIX, queue, seq)
+}
+
+/// Queue names can't hold `/`, so a queue's sequence cell `_queue/<q>` is
+/// never one of another queue's message cells `_queue/<q>/<seq>`.
+fn check_queue_name(queue: &str) -> napi::Result<()> {
+    if queue.is_empty() {
+        return Err(napi::Error::from_reason(
+            "[VALIDATION] Queue name must not be empty",
+        ));
+    }
+    if queue.contains('/') {
+        return Err(napi::Error::from_reason(format!(
+            "[VALIDATION] Queue name must not contain '/': {}",
+            queue
+        )));
+    }
+    Ok(())
+}
+
+/// Split a `queuePop` id, `<queue>/<seq>/<attempts>`.
+fn parse_queue_id(id: &str) -> napi::Result<(String, i64, i64)> {
+    let invalid =
+        || napi::Error::from_reason(format!("[VALIDATION] Invalid queue message id: {}", id));
+    let mut parts = id.rsplitn(3, '/');
+    let attempts = parts
+        .next()
+        .and_then(|a| a.parse().ok())
+        .ok_or_else(invalid)?;
+    let seq = parts
+        .next()
+        .and_then(|s| s.parse().ok())
+        .ok_or_else(invalid)?;
+    let queue = parts
+        .next()
+        .filter(|q| check_queue_name(q).is_ok())
+        .ok_or_else(invalid)?;
+    Ok((queue.to_string(), seq, attempts))
+}
+
+/// Sequence numbers that may still hold a message of `queue`, oldest
+/// first: from the head index, below which every message was acknowledged,
+/// to the last one pushed.
+fn queue_range(db: &RustStrata, queue: &str) -> napi::Result<std::ops::RangeInclusive<i64>> {
+    let (head_cell, seq_cell) = (queue_head_cell(queue), queue_seq_cell(queue));
+    let head = db.state_get_as_of(&head_cell, None).map_err(to_napi_err)?;
+    let tail = db.state_get_as_of(&seq_cell, None).map_err(to_napi_err)?;
+    let head = counter_total(&head_cell, head.as_ref())?.max(1);
+    Ok(head..=counter_total(&seq_cell, tail.as_ref())?)
+}
+
+/// Move `queue`'s head index up to `seq`. Losing a race to another caller
+/// only leaves the head lower, so a later scan starts earlier.
+fn advance_queue_head(db: &RustStrata, queue: &str, seq: i64) -> napi::Result<()> {
+    let cell = queue_head_cell(queue);
+    match latest_version(db, &cell)? {
+        Some(latest) if counter_total(&cell, Some(&latest.value))? >= seq => {}
+        Some(latest) => {
+            db.state_cas(&cell, Some(latest.version), Value::Int(seq))
+                .map_err(to_napi_err)?;
+        }
+        None => {
+            db.state_init(&cell, Value::Int(seq)).map_err(to_napi_err)?;
+        }
+    }
+    Ok(())
+}
+
+/// The newest version of state cell `cell` in the handle's current branch
+/// and space, read without the rest of its history.
+fn latest_version(db: &RustStrata, cell: &str) -> napi::Result<Option<VersionedValue>> {
+    let cmd = serde_json::from_value::<Command>(serde_json::json!({
+        "StateGet": {
+            "branch": db.current_branch().to_string(),
+            "space": db.current_space().to_string(),
+            "cell": cell,
+        }
+    }))
+    .map_err(|e| napi::Error::from_reason(format!("Invalid StateGet command: {}", e)))?;
+    match db.executor().execute(cmd).map_err(to_napi_err)? {
+        Output::MaybeVersioned(latest) => Ok(latest),
+        _ => Err(napi::Error::from_reason("Unexpected output for StateGet")),
+    }
+}
+
+fn queue_message(item: Value, attempts: i64, visible_at: i64, pushed_at: i64) -> Value {
+    let mut out = HashMap::new();
+    out.insert("item".to_string(), item);
+    out.insert("attempts".to_string(), Value::Int(attempts));
+    out.insert("visibleAt".to_string(), Value::Int(visible_at));
+    out.insert("pushedAt".to_string(), Value::Int(pushed_at));
+    Value::Object(Box::new(out))
+}
+
+/// A message as stored by `queue_message`.
+struct QueueMessage {
+    item: Value,
+    attempts: i64,
+    visible_at: i64,
+    pushed_at: i64,
+}
+
+impl QueueMessage {
+    /// The message in a cell's value, or `None` for an acknowledged one.
+    fn read(value: Value) -> Option<Self> {
+        let Value::Object(mut map) = value else {
+            return None;
+        };
+        let int = |v: Option<&Value>| match v {
+            Some(Value::Int(n)) => Some(*n),
+            _ => None,
+        };
+        Some(QueueMessage {
+            attempts: int(map.get("attempts"))?,
+            visible_at: int(map.get("visibleAt"))?,
+            pushed_at: int(map.get("pushedAt"))?,
+            item: map.remove("item")?,
+        })
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Locks (state cells in the default branch and space)
+// ---------------------------------------------------------------------------
+
+/// State cell prefix for locks: `_lock/<name>` holds the current lease,
+/// `{ token, expiresAt }` with `expiresAt` in milliseconds since the epoch,
+/// or `null` once released. Leases change only by compare-and-swap and live
+/// in the default branch and space whatever branch or space a handle is
+/// on, so every handle and process on a database sees the same ones.
+const LOCK_PREFIX: &str = "_lock/";
+
+/// Default `ttlMs` of `lockAcquire` and `lockRenew`.
+const DEFAULT_LOCK_TTL_MS: u32 = 30_000;
+
+fn lock_cell(name: &str) -> String {
+    format!("{}{}", LOCK_PREFIX, name)
+}
+
+fn now_millis() -> i64 {
+    (now_micros() / 1000) as i64
+}
+
+/// A view of `db`'s database on the default branch and space, where
+/// leases are kept.
+fn lock_handle(db: &RustStrata) -> napi::Result<RustStrata> {
+    let mut view = RustStrata::from_database(db.database().clone()).map_err(to_napi_err)?;
+    view.set_branch("default").map_err(to_napi_err)?;
+    view.set_space("default").map_err(to_napi_err)?;
+    Ok(view)
+}
+
+/// A random lease token, so one holder can't guess another's token to
+/// renew or release its lock. Every `RandomState` hasher is keyed from the
+/// OS's random source.
+fn lease_token() -> String {
+    use std::hash::{BuildHasher, Hasher};
+    let mut words = [0u64; 2];
+    for (i, word) in words.iter_mut().enumerate() {
+        let mut hasher = std::collections::hash_map::RandomState::new().build_hasher();
+        hasher.write_u64(now_micros());
+        hasher.write_usize(i);
+        *word = hasher.finish();
+    }
+    format!("{:016x}{:016x}", words[0], words[1])
+}
+
+/// A lock held by `token` until `expires_at`, as stored in its cell.
+struct Lease {
+    token: String,
+    expires_at: i64,
+}
+
+impl Lease {
+    /// The lease in a cell's value, or `None` for a released lock.
+    fn read(value: &Value) -> Option<Self> {
+        let Value::Object(map) = value else {
+            return None;
+        };
+        match (map.get("token"), map.get("expiresAt")) {
+            (Some(Value::String(token)), Some(Value::Int(expires_at))) => Some(Lease {
+                token: token.clone(),
+                expires_at: *expires_at,
+            }),
+            _ => None,
+        }
+    }
+
+    fn into_value(self) -> Value {
+        let mut out = HashMap::new();
+        out.insert("token".to_string(), Value::String(self.token));
+        out.insert("expiresAt".to_string(), Value::Int(self.expires_at));
+        Value::Object(Box::new(out))
+    }
+}
+
+/// Replace the lease in lock cell `cell` with `update(current)`, by a
+/// compare-and-swap against the version `update` was shown. `update`
+/// returns `None` to leave the cell alone. Returns whether the cell was
+/// replaced; a swap lost to another writer re-reads the cell and asks
+/// again.
+fn swap_lease(
+    db: &RustStrata,
+    cell: &str,
+    update: impl Fn(Option<Lease>) -> Option<Value>,
+) -> napi::Result<bool> {
+    for _ in 0..COUNTER_RETRIES {
+        let Some(latest) = latest_version(db, cell)? else {
+            db.state_init(cell, Value::Null).map_err(to_napi_err)?;
+            continue;
+        };
+        let Some(next) = update(Lease::read(&latest.value)) else {
+            return Ok(false);
+        };
+        let swapped = db
+            .state_cas(cell, Some(latest.version), next)
+            .map_err(to_napi_err)?;
+        if swapped.is_some() {
+            return Ok(true);
+        }
+    }
+    Err(napi::Error::from_reason(format!(
+        "[CONFLICT] State cell '{}' kept changing; gave up after {} attempts",
+        cell, COUNTER_RETRIES
+    )))
+}
+
+fn check_lock_name(name: &str) -> napi::Result<()> {
+    if name.is_empty() {
+        return Err(napi::Error::from_reason(
+            "[VALIDATION] Lock name must not be empty",
+        ));
+    }
+    Ok(())
+}
+
+// ---------------------------------------------------------------------------
+// Blobs (a state cell each, with chunks in KV)
+// ---------------------------------------------------------------------------
+
+/// State cell prefix for blobs, and KV prefix for their chunks. A blob's
+/// cell is `{ upload, size, chunks }` and chunk `i` of an upload is the KV
+/// entry `_blob/<upload>/<i>`, so a write never touches the chunks that
+/// readers of the previous content use until it replaces the cell.
+const BLOB_PREFIX: &str = "_blob/";
+
+fn blob_cell(key: &str) -> String {
+    format!("{}{}", BLOB_PREFIX, key)
+}
+
+/// Chunk indexes are zero-padded so keys sort in order.
+fn blob_chunk_key(upload: &str, index: u32) -> String {
+    format!("{}{}/{:010}", BLOB_PREFIX, upload, index)
+}
+
+fn check_blob_key(key: &str) -> napi::Result<()> {
+    if key.is_empty() {
+        return Err(napi::Error::from_reason(
+            "[VALIDATION] Blob key must not be empty",
+        ));
+    }
+    Ok(())
+}
+
+fn check_blob_upload(upload: &str) -> napi::Result<()> {
+    if upload.is_empty() || upload.contains('/') {
+        return Err(napi::Error::from_reason(format!(
+            "[VALIDATION] Invalid blob upload id: '{}'",
+            upload
+        )));
+    }
+    Ok(())
+}
+
+/// A blob's cell, as written by `blobCommit`.
+struct BlobManifest {
+    upload: String,
+    size: i64,
+    chunks: u32,
+}
+
+impl BlobManifest {
+    fn to_value(&self) -> Value {
+        let mut out = HashMap::new();
+        out.insert("upload".to_string(), Value::String(self.upload.clone()));
+        out.insert("size".to_string(), Value::Int(self.size));
+        out.insert("chunks".to_string(), Value::Int(i64::from(self.chunks)));
+        Value::Object(Box::new(out))
+    }
+
+    /// The manifest in a cell's value, or `None` for a deleted blob.
+    fn read(value: &Value) -> Option<Self> {
+        let Value::Object(map) = value else {
+            return None;
+        };
+        match (map.get("upload"), map.get("size"), map.get("chunks")) {
+            (Some(Value::String(upload)), Some(Value::Int(size)), Some(Value::Int(chunks))) => {
+                Some(BlobManifest {
+                    upload: upload.clone(),
+                    size: *size,
+                    chunks: u32::try_from(*chunks).ok()?,
+                })
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Set state cell `cell` to `value`, retrying the compare-and-swap until
+/// no other write gets in between, and return the value it replaced.
+fn replace_state(db: &RustStrata, cell: &str, value: Value) -> napi::Result<Option<Value>> {
+    for _ in 0..COUNTER_RETRIES {
+        let Some(latest) = latest_version(db, cell)? else {
+            db.state_init(cell, Value::Null).map_err(to_napi_err)?;
+            continue;
+        };
+        let swapped = db
+            .state_cas(cell, Some(latest.version), value.clone())
+            .map_err(to_napi_err)?;
+        if swapped.is_some() {
+            return Ok(Some(latest.value));
+        }
+    }
+    Err(napi::Error::from_reason(format!(
+        "[CONFLICT] State cell '{}' kept changing; gave up after {} attempts",
+        cell, COUNTER_RETRIES
+    )))
+}
+
+fn delete_blob_chunks(db: &RustStrata, upload: &str, chunks: u32) -> napi::Result<()> {
+    for index in 0..chunks {
+        db.kv_delete(&blob_chunk_key(upload, index))
+            .map_err(to_napi_err)?;
+    }
+    Ok(())
+}
+
+fn blob_stat(db: &RustStrata, key: &str) -> napi::Result<serde_json::Value> {
+    let latest = latest_version(db, &blob_cell(key))?;
+    let Some((manifest, timestamp)) =
+        latest.and_then(|vv| Some((BlobManifest::read(&vv.value)?, vv.timestamp)))
+    else {
+        return Ok(serde_json::Value::Null);
+    };
+    Ok(serde_json::json!({
+        "size": manifest.size,
+        "chunks": manifest.chunks,
+        "upload": manifest.upload,
+        "timestamp": timestamp,
+    }))
+}
+
+// ---------------------------------------------------------------------------
+// Edges (kept as state cells)
+// ---------------------------------------------------------------------------
+
+/// State cell prefix for edges. Each edge is two cells holding its
+/// properties, `_edge/out/<from>/<relation>/<to>` and
+/// `_edge/in/<to>/<relation>/<from>`, so both ends list it with one prefix
+/// scan, as of any time.
+const EDGE_PREFIX: &str = "_edge/";
+const EDGE_OUT: &str = "out";
+const EDGE_IN: &str = "in";
+
+/// Default `limit` of `traverse`.
+const DEFAULT_TRAVERSE_LIMIT: u32 = 1000;
+
+/// Escape `/` in a node or relation name, so it can't split a cell name.
+fn escape_edge_part(part: &str) -> String {
+    part.replace('%', "%25").replace('/', "%2F")
+}
+
+fn unescape_edge_part(part: &str) -> String {
+    part.replace("%2F", "/").replace("%25", "%")
+}
+
+fn edge_cell(dir: &str, node: &str, relation: &str, other: &str) -> String {
+    format!(
+        "{}{}/{}/{}/{}",
+        EDGE_PREFIX,
+        dir,
+        escape_edge_part(node),
+        escape_edge_part(relation),
+        escape_edge_part(other)
+    )
+}
+
+fn check_edge(from: &str, relation: &str, to: &str) -> napi::Result<()> {
+    if from.is_empty() || relation.is_empty() || to.is_empty() {
+        return Err(napi::Error::from_reason(
+            "[VALIDATION] Edge nodes and relation must not be empty",
+        ));
+    }
+    Ok(())
+}
+
+/// The cell directions a `direction` option reads.
+fn edge_directions(direction: Option<&str>) -> napi::Result<&'static [&'static str]> {
+    match direction {
+        None | Some("outgoing") => Ok(&[EDGE_OUT]),
+        Some("incoming") => Ok(&[EDGE_IN]),
+        Some("both") => Ok(&[EDGE_OUT, EDGE_IN]),
+        Some(other) => Err(napi::Error::from_reason(format!(
+            "[VALIDATION] direction must be \"outgoing\", \"incoming\", or \"both\", got \"{}\"",
+            other
+        ))),
+    }
+}
+
+struct Edge {
+    from: String,
+    relation: String,
+    to: String,
+    properties: Value,
+}
+
+impl Edge {
+    fn into_value(self) -> Value {
+        let mut out = HashMap::new();
+        out.insert("from".to_string(), Value::String(self.from));
+        out.insert("relation".to_string(), Value::String(self.relation));
+        out.insert("to".to_string(), Value::String(self.to));
+        out.insert("properties".to_string(), self.properties);
+        Value::Object(Box::new(out))
+    }
+}
+
+/// The edges at `node` in the `dirs` cell directions, as of `as_of`.
+fn node_edges(
+    db: &RustStrata,
+    node: &str,
+    dirs: &[&str],
+    relation: Option<&str>,
+    as_of: Option<u64>,
+) -> napi::Result<Vec<Edge>> {
+    let mut edges = Vec::new();
+    for &dir in dirs {
+        let mut prefix = format!("{}{}/{}/", EDGE_PREFIX, dir, escape_edge_part(node));
+        if let Some(relation) = relation {
+            prefix.push_str(&escape_edge_part(relation));
+            prefix.push('/');
+        }
+        let cells = db
+            .state_list_as_of(Some(prefix.as_str()), as_of)
+            .map_err(to_napi_err)?;
+        for cell in cells {
+            let rest = &cell[prefix.len()..];
+            let (relation, other) = match relation {
+                Some(relation) => (relation.to_string(), rest),
+                None => match rest.split_once('/') {
+                    Some((relation, other)) => (unescape_edge_part(relation), other),
+                    None => continue,
+                },
+            };
+            let Some(properties) = db.state_get_as_of(&cell, as_of).map_err(to_napi_err)? else {
+                continue;
+            };
+            let other = unescape_edge_part(other);
+            let (from, to) = if dir == EDGE_OUT {
+                (node.to_string(), other)
+            } else {
+                (other, node.to_string())
+            };
+            edges.push(Edge {
+                from,
+                relation,
+                to,
+                properties,
+            });
+        }
+    }
+    Ok(edges)
+}
+
+// ---------------------------------------------------------------------------
+// Time series (kept as KV entries)
+// ---------------------------------------------------------------------------
+
+/// KV prefix for time series: each point is `_ts/<series>/<timestamp>`,
+/// holding `{ value, tags }`.
+const TS_PREFIX: &str = "_ts/";
+
+/// Timestamps are zero-padded so keys sort in time order.
+fn ts_key(series: &str, timestamp: i64) -> String {
+    format!("{}{}/{:020}", TS_PREFIX, series, timestamp)
+}
+
+fn check_series_name(series: &str) -> napi::Result<()> {
+    if series.is_empty() {
+        return Err(napi::Error::from_reason(
+            "[VALIDATION] Series name must not be empty",
+        ));
+    }
+    Ok(())
+}
+
+fn ts_point(value: f64, tags: Option<HashMap<String, String>>) -> Value {
+    let tags = tags
+        .unwrap_or_default()
+        .into_iter()
+        .map(|(k, v)| (k, Value::String(v)))
+        .collect();
+    let mut out = HashMap::new();
+    out.insert("value".to_string(), Value::Float(value));
+    out.insert("tags".to_string(), Value::Object(Box::new(tags)));
+    Value::Object(Box::new(out))
+}
+
+/// The `(value, tags)` of a point as stored by `ts_point`.
+fn read_ts_point(point: Value) -> Option<(f64, BTreeMap<String, String>)> {
+    let Value::Object(map) = point else {
+        return None;
+    };
+    let value = match map.get("value") {
+        Some(Value::Float(f)) => *f,
+        Some(Value::Int(n)) => *n as f64,
+        _ => return None,
+    };
+    let tags = match map.get("tags") {
+        Some(Value::Object(tags)) => tags
+            .iter()
+            .filter_map(|(k, v)| match v {
+                Value::String(s) => Some((k.clone(), s.clone())),
+                _ => None,
+            })
+            .collect(),
+        _ => BTreeMap::new(),
+    };
+    Some((value, tags))
+}
+
+/// The `(timestamp, key)` of every point in `series`, oldest first.
+fn ts_keys(db: &RustStrata, series: &str) -> napi::Result<Vec<(i64, String)>> {
+    let prefix = format!("{}{}/", TS_PREFIX, series);
+    let keys = db
+        .kv_list_as_of(Some(prefix.as_str()), None, None, None)
+        .map_err(to_napi_err)?;
+    // Points of a series named `<series>/...` share the prefix; theirs
+    // don't end in a bare timestamp.
+    let mut points: Vec<(i64, String)> = keys
+        .into_iter()
+        .filter_map(|key| Some((key[prefix.len()..].parse().ok()?, key)))
+        .collect();
+    points.sort_unstable();
+    Ok(points)
+}
+
+#[derive(Clone, Copy)]
+enum TsAggregate {
+    Avg,
+    Sum,
+    Min,
+    Max,
+    Count,
+    First,
+    Last,
+}
+
+impl TsAggregate {
+    fn parse(name: &str) -> napi::Result<Self> {
+        Ok(match name {
+            "avg" => TsAggregate::Avg,
+            "sum" => TsAggregate::Sum,
+            "min" => TsAggregate::Min,
+            "max" => TsAggregate::Max,
+            "count" => TsAggregate::Count,
+            "first" => TsAggregate::First,
+            "last" => TsAggregate::Last,
+            other => {
+                return Err(napi::Error::from_reason(format!(
+                    "[VALIDATION] Unknown aggregate '{}'; use avg, sum, min, max, count, first, or last",
+                    other
+                )))
+            }
+        })
+    }
+}
+
+/// Running totals for the points of one `tsQuery` bucket.
+struct TsBucket {
+    start: i64,
+    count: u64,
+    sum: f64,
+    min: f64,
+    max: f64,
+    first: f64,
+    last: f64,
+}
+
+impl TsBucket {
+    fn new(start: i64, value: f64) -> Self {
+        TsBucket {
+            start,
+            count: 1,
+            sum: value,
+            min: value,
+            max: value,
+            first: value,
+            last: value,
+        }
+    }
+
+    fn add(&mut self, value: f64) {
+        self.count += 1;
+        self.sum += value;
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+        self.last = value;
+    }
+
+    fn value(&self, aggregate: TsAggregate) -> f64 {
+        match aggregate {
+            TsAggregate::Avg => self.sum / self.count as f64,
+            TsAggregate::Sum => self.sum,
+            TsAggregate::Min => self.min,
+            TsAggregate::Max => self.max,
+            TsAggregate::Count => self.count as f64,
+            TsAggregate::First => self.first,
+            TsAggregate::Last => self.last,
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Collections (JSON documents under `<name>/`, schemas kept as state cells)
+// ---------------------------------------------------------------------------
+
+/// State cell prefix for collection schemas: `_collection/<name>`.
+const COLLECTION_PREFIX: &str = "_collection/";
+
+/// JSON Schema types `collectionDefine` accepts.
+const SCHEMA_TYPES: &[&str] = &[
+    "null", "boolean", "integer", "number", "string", "array", "object",
+];
+
+fn check_collection_name(name: &str) -> napi::Result<()> {
+    if name.is_empty() || name.contains('/') {
+        return Err(napi::Error::from_reason(format!(
+            "[VALIDATION] Invalid collection name '{}': must be non-empty, without '/'",
+            name
+        )));
+    }
+    Ok(())
+}
+
+fn collection_schema_cell(name: &str) -> String {
+    format!("{}{}", COLLECTION_PREFIX, name)
+}
+
+fn collection_doc_key(name: &str, id: &str) -> String {
+    format!("{}/{}", name, id)
+}
+
+fn collection_schema_of(db: &RustStrata, name: &str) -> napi::Result<Option<serde_json::Value>> {
+    let schema = db
+        .state_get_as_of(&collection_schema_cell(name), None)
+        .map_err(to_napi_err)?;
+    Ok(schema.filter(|v| *v != Value::Null).map(value_to_js))
+}
+
+fn schema_error(at: &str, problem: &str) -> napi::Error {
+    napi::Error::from_reason(format!("[VALIDATION] Schema at {}: {}", at, problem))
+}
+
+/// Check that `schema` uses only the JSON Schema subset `schema_violation`
+/// enforces, so a misspelled keyword fails here instead of checking nothing.
+fn check_schema(schema: &serde_json::Value, at: &str) -> napi::Result<()> {
+    let obj = schema
+        .as_object()
+        .ok_or_else(|| schema_error(at, "must be an object"))?;
+    for (keyword, v) in obj {
+        match keyword.as_str() {
+            "type" => {
+                let types: Vec<&serde_json::Value> = match v {
+                    serde_json::Value::Array(types) => types.iter().collect(),
+                    single => vec![single],
+                };
+                for t in types {
+                    if !t.as_str().is_some_and(|t| SCHEMA_TYPES.contains(&t)) {
+                        return Err(schema_error(at, &format!("unknown type {}", t)));
+                    }
+                }
+            }
+            "properties" => {
+                let props = v
+                    .as_object()
+                    .ok_or_else(|| schema_error(at, "properties must be an object"))?;
+                for (name, sub) in props {
+                    check_schema(sub, &format!("{}.{}", at, name))?;
+                }
+            }
+            "items" => check_schema(v, &format!("{}[]", at))?,
+            "additionalProperties" if v.is_boolean() => {}
+            "additionalProperties" => check_schema(v, &format!("{}.*", at))?,
+            "required" => {
+                let names = v.as_array().filter(|a| a.iter().all(|n| n.is_string()));
+                if names.is_none() {
+                    return Err(schema_error(at, "required must be an array of strings"));
+                }
+            }
+            "enum" if v.is_array() => {}
+            "minimum" | "maximum" if v.is_number() => {}
+            "minLength" | "maxLength" if v.is_u64() => {}
+            "title" | "description" => {}
+            "enum" | "minimum" | "maximum" | "minLength" | "maxLength" => {
+                return Err(schema_error(at, &format!("invalid {}", keyword)));
+            }
+            other => {
+                let problem = format!("unsupported keyword '{}'", other);
+                return Err(schema_error(at, &problem));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn json_type_name(v: &serde_json::Value) -> &'static str {
+    match v {
+        serde_json::Value::Null => "null",
+        serde_json::Value::Bool(_) => "boolean",
+        serde_json::Value::Number(n) if n.is_i64() || n.is_u64() => "integer",
+        serde_json::Value::Number(_) => "number",
+        serde_json::Value::String(_) => "string",
+        serde_json::Value::Array(_) => "array",
+        serde_json::Value::Object(_) => "object",
+    }
+}
+
+/// The first way `value` breaks `schema` (checked by `check_schema`), as
+/// `<path>: <problem>`, or None if it conforms.
+fn schema_violation(
+    schema: &serde_json::Value,
+    value: &serde_json::Value,
+    at: &str,
+) -> Option<String> {
+    let actual = json_type_name(value);
+    if let Some(t) = schema.get("type") {
+        let types: Vec<&str> = match t {
+            serde_json::Value::Array(types) => types.iter().filter_map(|t| t.as_str()).collect(),
+            single => single.as_str().into_iter().collect(),
+        };
+        let ok = types
+            .iter()
+            .any(|&t| t == actual || (t == "number" && actual == "integer"));
+        if !ok {
+            let expected = types.join(" or ");
+            return Some(format!("{}: expected {}, got {}", at, expected, actual));
+        }
+    }
+    if let Some(options) = schema.get("enum").and_then(|e| e.as_array()) {
+        if !options.iter().any(|o| json_eq(o, value)) {
+            let options = serde_json::Value::Array(options.clone());
+            return Some(format!("{}: must be one of {}", at, options));
+        }
+    }
+    if let Some(n) = value.as_f64() {
+        if let Some(min) = schema.get("minimum").and_then(|m| m.as_f64()) {
+            if n < min {
+                return Some(format!("{}: must be at least {}", at, min));
+            }
+        }
+        if let Some(max) = schema.get("maximum").and_then(|m| m.as_f64()) {
+            if n > max {
+                return Some(format!("{}: must be at most {}", at, max));
+            }
+        }
+    }
+    if let serde_json::Value::String(s) = value {
+        let len = s.chars().count() as u64;
+        if let Some(min) = schema.get("minLength").and_then(|m| m.as_u64()) {
+            if len < min {
+                return Some(format!("{}: must be at least {} characters", at, min));
+            }
+        }
+        if let Some(max) = schema.get("maxLength").and_then(|m| m.as_u64()) {
+            if len > max {
+                return Some(format!("{}: must be at most {} characters", at, max));
+            }
+        }
+    }
+    if let serde_json::Value::Array(items) = value {
+        if let Some(item_schema) = schema.get("items") {
+            for (i, item) in items.iter().enumerate() {
+                let problem = schema_violation(item_schema, item, &format!("{}[{}]", at, i));
+                if problem.is_some() {
+                    return problem;
+                }
+            }
+        }
+    }
+    if let serde_json::Value::Object(obj) = value {
+        for name in schema
+            .get("required")
+            .and_then(|r| r.as_array())
+            .into_iter()
+            .flatten()
+            .filter_map(|n| n.as_str())
+        {
+            if !obj.contains_key(name) {
+                return Some(format!("{}: missing required property '{}'", at, name));
+            }
+        }
+        let props = schema.get("properties").and_then(|p| p.as_object());
+        let additional = schema.get("additionalProperties");
+        for (name, v) in obj {
+            let path = format!("{}.{}", at, name);
+            let problem = match (props.and_then(|p| p.get(name)), additional) {
+                (Some(sub), _) => schema_violation(sub, v, &path),
+                (None, Some(serde_json::Value::Bool(false))) => {
+                    Some(format!("{}: unexpected property '{}'", at, name))
+                }
+                (None, Some(sub)) if sub.is_object() => schema_violation(sub, v, &path),
+                (None, _) => None,
+            };
+            if problem.is_some() {
+                return problem;
+            }
+        }
+    }
+    None
+}
+
+// ---------------------------------------------------------------------------
+// Conversation memory (turns as events, their embeddings in `_memory`)
+// ---------------------------------------------------------------------------
+
+/// Vector collection holding the embedding of every memory turn, keyed
+/// `<sessionId>/<sequence>`.
+const MEMORY_COLLECTION: &str = "_memory";
+
+fn check_session_id(session_id: &str) -> napi::Result<()> {
+    if session_id.is_empty() {
+        return Err(napi::Error::from_reason(
+            "[VALIDATION] Session id must not be empty",
+        ));
+    }
+    Ok(())
+}
+
+/// Event type of the turns of `session_id`.
+fn memory_event_type(session_id: &str) -> String {
+    format!("_memory/{}", session_id)
+}
+
+fn memory_metadata(session_id: &str, sequence: u64) -> Value {
+    let session = Value::String(session_id.to_string());
+    let mut meta = HashMap::new();
+    meta.insert("sessionId".to_string(), session);
+    meta.insert("sequence".to_string(), Value::Int(sequence as i64));
+    Value::Object(Box::new(meta))
+}
+
+/// The event sequence number in metadata written by `memory_metadata`.
+fn memory_sequence(meta: &Value) -> Option<u64> {
+    match meta {
+        Value::Object(map) => match map.get("sequence") {
+            Some(Value::Int(n)) => u64::try_from(*n).ok(),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Traces (a run under `_trace/<runId>`, its spans under `_trace/<runId>/`)
+// ---------------------------------------------------------------------------
+
+/// KV prefix for traces.
+const TRACE_PREFIX: &str = "_trace/";
+
+fn check_run_id(run_id: &str) -> napi::Result<()> {
+    if run_id.is_empty() || run_id.contains('/') {
+        return Err(napi::Error::from_reason(format!(
+            "[VALIDATION] Invalid run id '{}': must be non-empty, without '/'",
+            run_id
+        )));
+    }
+    Ok(())
+}
+
+fn trace_run_key(run_id: &str) -> String {
+    format!("{}{}", TRACE_PREFIX, run_id)
+}
+
+fn trace_span_key(run_id: &str, span_id: &str) -> String {
+    format!("{}{}/{}", TRACE_PREFIX, run_id, span_id)
+}
+
+/// The `id` and `parentId` of a span, which must be an object with string
+/// `id` and `name`.
+fn span_ids(span: &serde_json::Value) -> napi::Result<(String, Option<String>)> {
+    let id = span.get("id").and_then(|v| v.as_str()).unwrap_or_default();
+    let named = span.get("name").is_some_and(|v| v.is_string());
+    if id.is_empty() || !named {
+        return Err(napi::Error::from_reason(
+            "[VALIDATION] A span must be an object with a non-empty string id and a string name",
+        ));
+    }
+    let parent = match span.get("parentId") {
+        None | Some(serde_json::Value::Null) => None,
+        Some(serde_json::Value::String(p)) => Some(p.clone()),
+        Some(_) => {
+            return Err(napi::Error::from_reason(
+                "[VALIDATION] A span's parentId must be a string",
+            ))
+        }
+    };
+    Ok((id.to_string(), parent))
+}
+
+fn span_field<'a>(span: &'a Value, field: &str) -> Option<&'a Value> {
+    match span {
+        Value::Object(map) => map.get(field),
+        _ => None,
+    }
+}
+
+/// Nest `spans` under their parents, each with a `children` array, and
+/// return the roots. Siblings are ordered by `startTime`, then id.
+fn span_tree(spans: Vec<Value>) -> Value {
+    let id_of = |span: &Value| match span_field(span, "id") {
+        Some(Value::String(id)) => id.clone(),
+        _ => String::new(),
+    };
+    let ids: HashSet<String> = spans.iter().map(id_of).collect();
+    let mut children: HashMap<Option<String>, Vec<Value>> = HashMap::new();
+    for span in spans {
+        let parent = match span_field(&span, "parentId") {
+            Some(Value::String(p)) if ids.contains(p) => Some(p.clone()),
+            _ => None,
+        };
+        children.entry(parent).or_default().push(span);
+    }
+    fn attach(
+        parent: Option<String>,
+        children: &mut HashMap<Option<String>, Vec<Value>>,
+        id_of: &dyn Fn(&Value) -> String,
+    ) -> Value {
+        let mut level = children.remove(&parent).unwrap_or_default();
+        level.sort_by(|a, b| {
+            let start = |s: &Value| match span_field(s, "startTime") {
+                Some(Value::Int(n)) => *n as f64,
+                Some(Value::Float(f)) => *f,
+                _ => f64::INFINITY,
+            };
+            start(a)
+                .total_cmp(&start(b))
+                .then_with(|| id_of(a).cmp(&id_of(b)))
+        });
+        let nested = level
+            .into_iter()
+            .map(|mut span| {
+                let kids = attach(Some(id_of(&span)), children, id_of);
+                if let Value::Object(map) = &mut span {
+                    map.insert("children".to_string(), kids);
+                }
+                span
+            })
+            .collect();
+        Value::Array(Box::new(nested))
+    }
+    attach(None, &mut children, &id_of)
+}
+
+// ---------------------------------------------------------------------------
+// Cross-space queries
+// ---------------------------------------------------------------------------
+
+/// Resolve a `spaces` option — a list of names, or `"*"` for every space —
+/// to the existing spaces it names.
+fn select_spaces(
+    db: &RustStrata,
+    spaces: Either<Vec<String>, String>,
+) -> napi::Result<Vec<String>> {
+    match spaces {
+        Either::B(s) if s == "*" => db.list_spaces().map_err(to_napi_err),
+        Either::B(s) => Err(napi::Error::from_reason(format!(
+            "[VALIDATION] spaces must be an array of names or \"*\", got \"{}\"",
+            s
+        ))),
+        Either::A(names) => {
+            let mut out = Vec::with_capacity(names.len());
+            for name in names {
+                if db.space_exists(&name).map_err(to_napi_err)? {
+                    out.push(name);
+                }
+            }
+            Ok(out)
+        }
+    }
+}
+
+/// A handle on the same database as `db`, starting on its branch and
+/// space, that can switch branch and space without touching `db`'s. Work
+/// that visits other branches or spaces runs on one under `lock_inner`
+/// instead of switching the caller's handle under `lock_inner_mut`.
+fn detached_handle(db: &RustStrata) -> napi::Result<RustStrata> {
+    let mut view = RustStrata::from_database(db.database().clone()).map_err(to_napi_err)?;
+    view.set_branch(db.current_branch()).map_err(to_napi_err)?;
+    view.set_space(db.current_space()).map_err(to_napi_err)?;
+    Ok(view)
+}
+
+/// Run `f` on `db`'s branch in each space in turn, through a detached
+/// handle so `db`'s own space is left alone.
+fn across_spaces<T>(
+    db: &RustStrata,
+    spaces: &[String],
+    mut f: impl FnMut(&RustStrata) -> napi::Result<T>,
+) -> napi::Result<Vec<(String, T)>> {
+    let mut view = detached_handle(db)?;
+    let mut out = Vec::with_capacity(spaces.len());
+    for space in spaces {
+        view.set_space(space).map_err(to_napi_err)?;
+        out.push((space.clone(), f(&view)?));
+    }
+    Ok(out)
+}
+
+/// Sort hits by descending `score` and keep the best `k`.
+fn merge_ranked(hits: &mut Vec<serde_json::Value>, k: usize) {
+    hits.sort_by(|a, b| {
+        let (sa, sb) = (a["score"].as_f64(), b["score"].as_f64());
+        sb.partial_cmp(&sa).unwrap_or(std::cmp::Ordering::Equal)
+    });
+    hits.truncate(k);
+}
+
+// ---------------------------------------------------------------------------
+// Query filters (geo ops for vectorSearchFiltered, every op for jsonQuery)
+// ---------------------------------------------------------------------------
+
+/// Mean radius of the Earth in meters, for `withinRadius`.
+const EARTH_RADIUS_M: f64 = 6_371_008.8;
+
+/// Multiple of `k` that `vectorSearchFiltered` first asks the engine for
+/// when geo filters will drop some of its matches.
+const GEO_OVERFETCH: u64 = 4;
+
+/// The `field`, `op`, and `value` of a filter object.
+fn parse_filter(item: serde_json::Value) -> napi::Result<(String, String, serde_json::Value)> {
+    let serde_json::Value::Object(mut obj) = item else {
+        return Err(napi::Error::from_reason(
+            "[VALIDATION] Filter must be an object",
+        ));
+    };
+    let field = match obj.remove("field") {
+        Some(serde_json::Value::String(f)) => f,
+        _ => return Err(napi::Error::from_reason("[VALIDATION] Filter missing 'field'")),
+    };
+    let op = match obj.remove("op") {
+        Some(serde_json::Value::String(o)) => o,
+        _ => return Err(napi::Error::from_reason("[VALIDATION] Filter missing 'op'")),
+    };
+    let value = obj
+        .remove("value")
+        .ok_or_else(|| napi::Error::from_reason("[VALIDATION] Filter missing 'value'"))?;
+    Ok((field, op, value))
+}
+
+/// A filter the binding checks itself, against plain JSON.
+enum QueryFilter {
+    Compare {
+        field: String,
+        op: String,
+        value: serde_json::Value,
+    },
+    /// Points no more than `radius` meters from `(lat, lon)`.
+    WithinRadius {
+        field: String,
+        lat: f64,
+        lon: f64,
+        radius: f64,
+    },
+    /// Points inside the box, which crosses the antimeridian when
+    /// `min_lon > max_lon`.
+    BoundingBox {
+        field: String,
+        min_lat: f64,
+        min_lon: f64,
+        max_lat: f64,
+        max_lon: f64,
+    },
+}
+
+impl QueryFilter {
+    fn new(field: String, op: &str, value: serde_json::Value) -> napi::Result<Self> {
+        let number = |name: &str| {
+            value.get(name).and_then(|v| v.as_f64()).ok_or_else(|| {
+                napi::Error::from_reason(format!(
+                    "[VALIDATION] {} filter value needs a numeric '{}'",
+                    op, name
+                ))
+            })
+        };
+        let filter = match op {
+            "eq" | "ne" | "gt" | "gte" | "lt" | "lte" | "contains" => QueryFilter::Compare {
+                field,
+                op: op.to_string(),
+                value,
+            },
+            "in" if value.is_array() => QueryFilter::Compare {
+                field,
+                op: op.to_string(),
+                value,
+            },
+            "in" => {
+                return Err(napi::Error::from_reason(
+                    "[VALIDATION] in filter value must be an array",
+                ))
+            }
+            "withinRadius" => {
+                let (lat, lon) = check_geo_point(number("lat")?, number("lon")?)?;
+                let radius = number("radius")?;
+                if radius < 0.0 {
+                    return Err(napi::Error::from_reason(
+                        "[VALIDATION] radius must not be negative",
+                    ));
+                }
+                QueryFilter::WithinRadius {
+                    field,
+                    lat,
+                    lon,
+                    radius,
+                }
+            }
+            "boundingBox" => {
+                let (min_lat, min_lon) = check_geo_point(number("minLat")?, number("minLon")?)?;
+                let (max_lat, max_lon) = check_geo_point(number("maxLat")?, number("maxLon")?)?;
+                if min_lat > max_lat {
+                    return Err(napi::Error::from_reason(
+                        "[VALIDATION] minLat must not be greater than maxLat",
+                    ));
+                }
+                QueryFilter::BoundingBox {
+                    field,
+                    min_lat,
+                    min_lon,
+                    max_lat,
+                    max_lon,
+                }
+            }
+            _ => {
+                return Err(napi::Error::from_reason(format!(
+                    "[VALIDATION] Invalid filter op: {}",
+                    op
+                )))
+            }
+        };
+        Ok(filter)
+    }
+
+    /// Whether `doc` (a document, or a vector's metadata) passes.
+    fn matches(&self, doc: &serde_json::Value) -> bool {
+        match self {
+            QueryFilter::Compare { field, op, value } => {
+                field_at(doc, field).is_some_and(|found| compare_json(found, op, value))
+            }
+            QueryFilter::WithinRadius {
+                field,
+                lat,
+                lon,
+                radius,
+            } => field_at(doc, field)
+                .and_then(geo_point)
+                .is_some_and(|p| haversine_m((*lat, *lon), p) <= *radius),
+            QueryFilter::BoundingBox {
+                field,
+                min_lat,
+                min_lon,
+                max_lat,
+                max_lon,
+            } => field_at(doc, field)
+                .and_then(geo_point)
+                .is_some_and(|(lat, lon)| {
+                    let lon_inside = if min_lon <= max_lon {
+                        lon >= *min_lon && lon <= *max_lon
+                    } else {
+                        lon >= *min_lon || lon <= *max_lon
+                    };
+                    lat >= *min_lat && lat <= *max_lat && lon_inside
+                }),
+        }
+    }
+}
+
+/// The value at a dotted `field` path in `doc`.
+fn field_at<'a>(doc: &'a serde_json::Value, field: &str) -> Option<&'a serde_json::Value> {
+    field.split('.').try_fold(doc, |v, part| v.get(part))
+}
+
+fn check_geo_point(lat: f64, lon: f64) -> napi::Result<(f64, f64)> {
+    if !(-90.0..=90.0).contains(&lat) || !(-180.0..=180.0).contains(&lon) {
+        return Err(napi::Error::from_reason(format!(
+            "[VALIDATION] ({}, {}) is not a valid latitude and longitude",
+            lat, lon
+        )));
+    }
+    Ok((lat, lon))
+}
+
+/// The `(lat, lon)` of a `{ lat, lon }` geo point.
+fn geo_point(v: &serde_json::Value) -> Option<(f64, f64)> {
+    let lat = v.get("lat")?.as_f64()?;
+    let lon = v.get("lon")?.as_f64()?;
+    check_geo_point(lat, lon).ok()
+}
+
+/// Great-circle distance in meters between two `(lat, lon)` points.
+fn haversine_m(a: (f64, f64), b: (f64, f64)) -> f64 {
+    let (lat1, lat2) = (a.0.to_radians(), b.0.to_radians());
+    let dlat = lat2 - lat1;
+    let dlon = (b.1 - a.1).to_radians();
+    let h = (dlat / 2.0).sin().powi(2) + lat1.cos() * lat2.cos() * (dlon / 2.0).sin().powi(2);
+    2.0 * EARTH_RADIUS_M * h.sqrt().min(1.0).asin()
+}
+
+fn json_eq(a: &serde_json::Value, b: &serde_json::Value) -> bool {
+    match (a.as_f64(), b.as_f64()) {
+        (Some(x), Some(y)) => x == y,
+        _ => a == b,
+    }
+}
+
+/// Apply a comparison op; numbers compare with numbers and strings with
+/// strings, and other pairings only match `ne`.
+fn compare_json(found: &serde_json::Value, op: &str, value: &serde_json::Value) -> bool {
+    use std::cmp::Ordering;
+    let order = match (found, value) {
+        (serde_json::Value::String(a), serde_json::Value::String(b)) => Some(a.cmp(b)),
+        _ => found
+            .as_f64()
+            .zip(value.as_f64())
+            .and_then(|(a, b)| a.partial_cmp(&b)),
+    };
+    match op {
+        "eq" => json_eq(found, value),
+        "ne" => !json_eq(found, value),
+        "gt" => order == Some(Ordering::Greater),
+        "gte" => matches!(order, Some(Ordering::Greater | Ordering::Equal)),
+        "lt" => order == Some(Ordering::Less),
+        "lte" => matches!(order, Some(Ordering::Less | Ordering::Equal)),
+        "in" => value
+            .as_array()
+            .is_some_and(|options| options.iter().any(|o| json_eq(found, o))),
+        "contains" => match found {
+            serde_json::Value::String(s) => value.as_str().is_some_and(|sub| s.contains(sub)),
+            serde_json::Value::Array(items) => items.iter().any(|item| json_eq(item, value)),
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Tags (kept on the _system_ branch)
+// ---------------------------------------------------------------------------
+
+/// `_system_` KV key prefix for tags.
+const TAG_PREFIX: &str = "tag/";
+
+fn tag_key(name: &str) -> String {
+    format!("{}{}", TAG_PREFIX, name)
+}
+
+fn now_micros() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_micros() as u64)
+        .unwrap_or(0)
+}
+
+fn read_tag(db: &RustStrata, name: &str) -> napi::Result<Option<serde_json::Value>> {
+    let stored = db
+        .system_branch()
+        .kv_get(&tag_key(name))
+        .map_err(to_napi_err)?;
+    Ok(stored.map(value_to_js))
+}
+
+/// Validate an `atVersion` argument, which replaces `asOf`.
+fn check_at_version(
+    as_of: &Option<Either<i64, String>>,
+    at_version: Option<i64>,
+) -> napi::Result<Option<u64>> {
+    match at_version {
+        None => Ok(None),
+        Some(_) if as_of.is_some() => Err(napi::Error::from_reason(
+            "[VALIDATION] asOf and atVersion cannot be combined",
+        )),
+        Some(v) if v < 0 => Err(napi::Error::from_reason(
+            "[VALIDATION] atVersion must be non-negative",
+        )),
+        Some(v) => Ok(Some(v as u64)),
+    }
+}
+
+/// The write timestamp of the newest entry in `history` at or below
+/// `version`, i.e. the moment the key took the value it had at that
+/// version. `None` if the key did not exist yet.
+fn version_timestamp(history: Option<Vec<VersionedValue>>, version: u64) -> Option<u64> {
+    history?
+        .into_iter()
+        .filter(|vv| vv.version <= version)
+        .max_by_key(|vv| vv.version)
+        .map(|vv| vv.timestamp)
+}
+
+/// Resolve an `asOf` argument (timestamp or tag name) to microseconds.
+fn resolve_as_of(db: &RustStrata, as_of: Option<Either<i64, String>>) -> napi::Result<Option<u64>> {
+    match as_of {
+        None => Ok(None),
+        Some(Either::A(ts)) if ts < 0 => Err(napi::Error::from_reason(
+            "[VALIDATION] asOf must be a non-negative timestamp",
+        )),
+        Some(Either::A(ts)) => Ok(Some(ts as u64)),
+        Some(Either::B(name)) => match read_tag(db, &name)? {
+            Some(tag) => Ok(tag["timestamp"].as_u64()),
+            None => Err(napi::Error::from_reason(format!(
+                "[NOT_FOUND] Tag not found: {}",
+                name
+            ))),
+        },
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Retention rules (kept on the _system_ branch)
+// ---------------------------------------------------------------------------
+
+/// `_system_` KV key prefix for retention rules.
+const RETENTION_PREFIX: &str = "retention/";
+
+/// Primitives with version history that retention rules can target.
+const RETENTION_PRIMITIVES: &[&str] = &["kv", "state", "json", "event", "vector"];
+
+struct RetentionRule {
+    space: Option<String>,
+    primitive: Option<String>,
+    max_versions: Option<u64>,
+    max_age: Option<u64>,
+    keep_tags: bool,
+}
+
+impl RetentionRule {
+    fn from_options(o: JsRetentionOptions) -> napi::Result<Self> {
+        if let Some(p) = o.primitive.as_deref() {
+            if !RETENTION_PRIMITIVES.contains(&p) {
+                return Err(napi::Error::from_reason(format!(
+                    "[VALIDATION] Invalid retention primitive: {}",
+                    p
+                )));
+            }
+        }
+        if o.max_versions == Some(0) {
+            return Err(napi::Error::from_reason(
+                "[VALIDATION] maxVersions must be at least 1",
+            ));
+        }
+        Ok(Self {
+            space: o.space,
+            primitive: o.primitive,
+            max_versions: o.max_versions.map(u64::from),
+            max_age: o.max_age.map(u64::from),
+            keep_tags: o.keep_tags.unwrap_or(false),
+        })
+    }
+
+    fn from_js(v: &serde_json::Value) -> Self {
+        Self {
+            space: v["space"].as_str().map(String::from),
+            primitive: v["primitive"].as_str().map(String::from),
+            max_versions: v["maxVersions"].as_u64(),
+            max_age: v["maxAge"].as_u64(),
+            keep_tags: v["keepTags"].as_bool().unwrap_or(false),
+        }
+    }
+
+    fn to_js(&self) -> serde_json::Value {
+        serde_json::json!({
+            "space": self.space,
+            "primitive": self.primitive,
+            "maxVersions": self.max_versions,
+            "maxAge": self.max_age,
+            "keepTags": self.keep_tags,
+        })
+    }
+
+    /// `_system_` key for this rule's scope; `*` stands for "every".
+    fn key(&self) -> String {
+        format!(
+            "{}{}/{}",
+            RETENTION_PREFIX,
+            self.space.as_deref().unwrap_or("*"),
+            self.primitive.as_deref().unwrap_or("*")
+        )
+    }
+
+    fn matches(&self, space: &str, primitive: &str) -> bool {
+        self.space.as_deref().is_none_or(|s| s == space)
+            && self.primitive.as_deref().is_none_or(|p| p == primitive)
+    }
+
+    fn specificity(&self) -> u8 {
+        (self.space.is_some() as u8) * 2 + self.primitive.is_some() as u8
+    }
+}
+
+fn read_retention_rules(db: &RustStrata) -> napi::Result<Vec<RetentionRule>> {
+    let sys = db.system_branch();
+    let mut rules = Vec::new();
+    for key in sys.kv_list(Some(RETENTION_PREFIX)).map_err(to_napi_err)? {
+        if let Some(v) = sys.kv_get(&key).map_err(to_napi_err)? {
+            rules.push(RetentionRule::from_js(&value_to_js(v)));
+        }
+    }
+    Ok(rules)
+}
+
+/// `_system_` KV key prefix for retention pins.
+const RETENTION_PIN_PREFIX: &str = "retention-pin/";
+
+/// Primitives whose keys can be pinned.
+const PIN_PRIMITIVES: &[&str] = &["kv", "state", "json"];
+
+/// History kept by `retentionApply`: every version of one key, or the
+/// versions visible at one timestamp (given directly or via a tag).
+struct RetentionPin {
+    key: Option<String>,
+    space: Option<String>,
+    primitive: Option<String>,
+    tag: Option<String>,
+    timestamp: Option<u64>,
+}
+
+impl RetentionPin {
+    fn from_options(db: &RustStrata, o: JsRetentionPin) -> napi::Result<Self> {
+        let targets = o.key.is_some() as u8 + o.tag.is_some() as u8 + o.timestamp.is_some() as u8;
+        if targets != 1 {
+            return Err(napi::Error::from_reason(
+                "[VALIDATION] Pin exactly one of key, tag, or timestamp",
+            ));
+        }
+        if o.key.is_none() && (o.space.is_some() || o.primitive.is_some()) {
+            return Err(napi::Error::from_reason(
+                "[VALIDATION] space and primitive only apply to key pins",
+            ));
+        }
+        if let Some(key) = o.key {
+            let primitive = o.primitive.unwrap_or_else(|| "kv".to_string());
+            if !PIN_PRIMITIVES.contains(&primitive.as_str()) {
+                return Err(napi::Error::from_reason(format!(
+                    "[VALIDATION] Invalid pin primitive: {}",
+                    primitive
+                )));
+            }
+            return Ok(Self {
+                key: Some(key),
+                space: Some(o.space.unwrap_or_else(|| db.current_space().to_string())),
+                primitive: Some(primitive),
+                tag: None,
+                timestamp: None,
+            });
+        }
+        let timestamp = match (o.tag.as_deref(), o.timestamp) {
+            (Some(name), _) => read_tag(db, name)?
+                .and_then(|t| t["timestamp"].as_u64())
+                .ok_or_else(|| {
+                    napi::Error::from_reason(format!("[NOT_FOUND] Tag not found: {}", name))
+                })?,
+            (None, Some(ts)) if ts < 0 => {
+                return Err(napi::Error::from_reason(
+                    "[VALIDATION] Pin timestamp must be non-negative",
+                ))
+            }
+            (None, ts) => ts.unwrap_or(0) as u64,
+        };
+        Ok(Self {
+            key: None,
+            space: None,
+            primitive: None,
+            tag: o.tag,
+            timestamp: Some(timestamp),
+        })
+    }
+
+    fn from_js(v: &serde_json::Value) -> Self {
+        Self {
+            key: v["key"].as_str().map(String::from),
+            space: v["space"].as_str().map(String::from),
+            primitive: v["primitive"].as_str().map(String::from),
+            tag: v["tag"].as_str().map(String::from),
+            timestamp: v["timestamp"].as_u64(),
+        }
+    }
+
+    fn to_js(&self) -> serde_json::Value {
+        let mut out = serde_json::Map::new();
+        for (name, value) in [
+            ("key", serde_json::json!(self.key)),
+            ("space", serde_json::json!(self.space)),
+            ("primitive", serde_json::json!(self.primitive)),
+            ("tag", serde_json::json!(self.tag)),
+            ("timestamp", serde_json::json!(self.timestamp)),
+        ] {
+            if !value.is_null() {
+                out.insert(name.to_string(), value);
+            }
+        }
+        serde_json::Value::Object(out)
+    }
+
+    /// `_system_` key for this pin.
+    fn key(&self) -> String {
+        match (&self.key, &self.tag) {
+            (Some(key), _) => format!(
+                "{}key/{}/{}/{}",
+                RETENTION_PIN_PREFIX,
+                self.space.as_deref().unwrap_or_default(),
+                self.primitive.as_deref().unwrap_or_default(),
+                key
+            ),
+            (None, Some(tag)) => format!("{}tag/{}", RETENTION_PIN_PREFIX, tag),
+            (None, None) => format!(
+                "{}ts/{}",
+                RETENTION_PIN_PREFIX,
+                self.timestamp.unwrap_or_default()
+            ),
+        }
+    }
+}
+
+fn read_retention_pins(db: &RustStrata) -> napi::Result<Vec<RetentionPin>> {
+    let sys = db.system_branch();
+    let mut pins = Vec::new();
+    for key in sys
+        .kv_list(Some(RETENTION_PIN_PREFIX))
+        .map_err(to_napi_err)?
+    {
+        if let Some(v) = sys.kv_get(&key).map_err(to_napi_err)? {
+            pins.push(RetentionPin::from_js(&value_to_js(v)));
+        }
+    }
+    Ok(pins)
+}
+
+/// Timestamps that history trims must keep because of retention pins.
+struct PinnedTimestamps {
+    /// Tag and timestamp pins, kept everywhere.
+    all: Vec<u64>,
+    /// Versions of pinned keys, per (space, primitive).
+    keys: HashMap<(String, String), Vec<u64>>,
+}
+
+impl PinnedTimestamps {
+    fn new(pins: &[RetentionPin]) -> Self {
+        Self {
+            all: pins
+                .iter()
+                .filter(|p| p.key.is_none())
+                .filter_map(|p| p.timestamp)
+                .collect(),
+            keys: HashMap::new(),
+        }
+    }
+
+    /// Add the versions of pinned keys on the handle's current branch.
+    /// Leaves the handle's space changed.
+    fn add_branch(&mut self, db: &mut RustStrata, pins: &[RetentionPin]) -> napi::Result<()> {
+        for pin in pins.iter().filter(|p| p.key.is_some()) {
+            let scope = (
+                pin.space.clone().unwrap_or_default(),
+                pin.primitive.clone().unwrap_or_default(),
+            );
+            let timestamps = pinned_key_timestamps(db, pin)?;
+            self.keys.entry(scope).or_default().extend(timestamps);
+        }
+        Ok(())
+    }
+
+    fn keep(&self, space: &str, primitive: &str) -> Vec<u64> {
+        let mut keep = self.all.clone();
+        if let Some(ts) = self.keys.get(&(space.to_string(), primitive.to_string())) {
+            keep.extend(ts);
+        }
+        keep
+    }
+}
+
+/// Timestamps of every version of a pinned key on the handle's
+/// current branch, if its space exists there. Leaves the handle's space
+/// changed.
+fn pinned_key_timestamps(db: &mut RustStrata, pin: &RetentionPin) -> napi::Result<Vec<u64>> {
+    let (Some(key), Some(space)) = (pin.key.as_deref(), pin.space.as_deref()) else {
+        return Ok(Vec::new());
+    };
+    if !db.space_exists(space).map_err(to_napi_err)? {
+        return Ok(Vec::new());
+    }
+    db.set_space(space).map_err(to_napi_err)?;
+    let history = match pin.primitive.as_deref() {
+        Some("state") => db.state_getv(key),
+        Some("json") => db.json_getv(key),
+        _ => db.kv_getv(key),
+    }
+    .map_err(to_napi_err)?;
+    Ok(history
+        .unwrap_or_default()
+        .into_iter()
+        .map(|vv| vv.timestamp)
+        .collect())
+}
+
+/// Timestamps of all tags, for rules that keep tagged versions.
+fn tag_timestamps(db: &RustStrata) -> napi::Result<Vec<u64>> {
+    let sys = db.system_branch();
+    let mut out = Vec::new();
+    for key in sys.kv_list(Some(TAG_PREFIX)).map_err(to_napi_err)? {
+        if let Some(tag) = read_tag(db, &key[TAG_PREFIX.len()..])? {
+            out.extend(tag["timestamp"].as_u64());
+        }
+    }
+    Ok(out)
+}
+
+/// What a retention pass removed.
+#[derive(Default)]
+struct RetentionReport {
+    versions_removed: BTreeMap<&'static str, u64>,
+    bytes_reclaimed: u64,
+    oldest_by_space: BTreeMap<String, Option<u64>>,
+}
+
+impl RetentionReport {
+    fn record(&mut self, space: &str, primitive: &'static str, trim: HistoryTrimResult) {
+        *self.versions_removed.entry(primitive).or_default() += trim.versions_removed;
+        self.bytes_reclaimed += trim.bytes_reclaimed;
+        let oldest = self.oldest_by_space.entry(space.to_string()).or_default();
+        *oldest = match (*oldest, trim.oldest_timestamp) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        };
+    }
+
+    fn to_js(&self, elapsed: std::time::Duration) -> serde_json::Value {
+        let mut removed = serde_json::Map::new();
+        for primitive in RETENTION_PRIMITIVES {
+            let n = self.versions_removed.get(primitive).copied().unwrap_or(0);
+            removed.insert(primitive.to_string(), serde_json::json!(n));
+        }
+        serde_json::json!({
+            "versionsRemoved": removed,
+            "bytesReclaimed": self.bytes_reclaimed,
+            "oldestTimestamps": self.oldest_by_space,
+            "durationMs": elapsed.as_secs_f64() * 1000.0,
+        })
+    }
+}
+
+/// Trim history for every (space, primitive) pair according to its most
+/// specific rule, on all branches. Leaves the handle's branch changed; callers
+/// pass a `detached_handle`.
+fn apply_retention_rules(
+    db: &mut RustStrata,
+    rules: &[RetentionRule],
+) -> napi::Result<RetentionReport> {
+    let pins = read_retention_pins(db)?;
+    let mut pinned = PinnedTimestamps::new(&pins);
+    let mut spaces = BTreeSet::new();
+    for b in db.branch_list(None, None, None).map_err(to_napi_err)? {
+        db.set_branch(b.info.id.as_str()).map_err(to_napi_err)?;
+        spaces.extend(db.list_spaces().map_err(to_napi_err)?);
+        pinned.add_branch(db, &pins)?;
+    }
+    let tags = if rules.iter().any(|r| r.keep_tags) {
+        tag_timestamps(db)?
+    } else {
+        Vec::new()
+    };
+    let now = now_micros();
+    let mut report = RetentionReport::default();
+    for space in &spaces {
+        for primitive in RETENTION_PRIMITIVES {
+            let rule = match rules
+                .iter()
+                .filter(|r| r.matches(space, primitive))
+                .max_by_key(|r| r.specificity())
+            {
+                Some(r) if r.max_versions.is_some() || r.max_age.is_some() => r,
+                _ => continue,
+            };
+            let mut keep = pinned.keep(space, primitive);
+            if rule.keep_tags {
+                keep.extend(&tags);
+            }
+            let trim = db
+                .history_trim(HistoryTrimOptions {
+                    branch: None,
+                    space: Some(space.clone()),
+                    primitive: Some(primitive.to_string()),
+                    keep_last: rule.max_versions,
+                    before: rule
+                        .max_age
+                        .map(|secs| now.saturating_sub(secs.saturating_mul(1_000_000))),
+                    keep_timestamps: keep,
+                })
+                .map_err(to_napi_err)?;
+            report.record(space, *primitive, trim);
+        }
+    }
+    Ok(report)
+}
+
+// ---------------------------------------------------------------------------
+// Space data copy (branch forks at a timestamp, space copy/rename)
+// ---------------------------------------------------------------------------
+
+/// Page size for walking every JSON document in a space.
+const JSON_LIST_PAGE: u64 = 1000;
+
+/// KV, state, and JSON contents of one space.
+#[derive(Default)]
+struct SpaceData {
+    kv: Vec<(String, Value)>,
+    cells: Vec<(String, Value)>,
+    docs: Vec<(String, Value)>,
+}
+
+impl SpaceData {
+    fn len(&self) -> usize {
+        self.kv.len() + self.cells.len() + self.docs.len()
+    }
+}
+
+/// Read the KV, state, and JSON data of the handle's current branch and
+/// space, as of `as_of` (latest if `None`).
+fn read_space_data(db: &RustStrata, as_of: Option<u64>) -> napi::Result<SpaceData> {
+    let mut data = SpaceData::default();
+    for key in db
+        .kv_list_as_of(None, None, None, as_of)
+        .map(user_keys)
+        .map_err(to_napi_err)?
+    {
+        if let Some(v) = db.kv_get_as_of(&key, as_of).map_err(to_napi_err)? {
+            data.kv.push((key, v));
+        }
+    }
+    for cell in db.state_list_as_of(None, as_of).map_err(to_napi_err)? {
+        if let Some(v) = db.state_get_as_of(&cell, as_of).map_err(to_napi_err)? {
+            data.cells.push((cell, v));
+        }
+    }
+    let mut cursor = None;
+    loop {
+        let (keys, next) = db
+            .json_list_as_of(None, cursor, JSON_LIST_PAGE, as_of)
+            .map_err(to_napi_err)?;
+        for key in keys {
+            if let Some(v) = db.json_get_as_of(&key, "$", as_of).map_err(to_napi_err)? {
+                data.docs.push((key, v));
+            }
+        }
+        match next {
+            Some(c) => cursor = Some(c),
+            None => break,
+        }
+    }
+    Ok(data)
+}
+
+/// Compare two readings of the same space, returning the added, removed,
+/// and modified `{ key, primitive }` entries for keys starting with `prefix`.
+fn diff_space_data(
+    before: SpaceData,
+    after: SpaceData,
+    prefix: &str,
+) -> [Vec<serde_json::Value>; 3] {
+    let mut lists: [Vec<serde_json::Value>; 3] = Default::default();
+    for (primitive, a, b) in [
+        ("kv", before.kv, after.kv),
+        ("state", before.cells, after.cells),
+        ("json", before.docs, after.docs),
+    ] {
+        let mut old: HashMap<String, serde_json::Value> = a
+            .into_iter()
+            .filter(|(k, _)| k.starts_with(prefix))
+            .map(|(k, v)| (k, value_to_js(v)))
+            .collect();
+        for (key, v) in b.into_iter().filter(|(k, _)| k.starts_with(prefix)) {
+            let slot = match old.remove(&key) {
+                None => 0,
+                Some(prev) if prev != value_to_js(v) => 2,
+                Some(_) => continue,
+            };
+            lists[slot].push(serde_json::json!({ "key": key, "primitive": primitive }));
+        }
+        let mut removed: Vec<String> = old.into_keys().collect();
+        removed.sort();
+        for key in removed {
+            lists[1].push(serde_json::json!({ "key": key, "primitive": primitive }));
+        }
+    }
+    lists
+}
+
+/// Write `data` into the handle's current branch and space.
+fn write_space_data(db: &RustStrata, data: SpaceData) -> napi::Result<()> {
+    for (key, v) in data.kv {
+        db.kv_put(&key, v).map_err(to_napi_err)?;
+    }
+    for (cell, v) in data.cells {
+        db.state_set(&cell, v).map_err(to_napi_err)?;
+    }
+    for (key, v) in data.docs {
+        db.json_set(&key, "$", v).map_err(to_napi_err)?;
+    }
+    Ok(())
+}
+
+/// Switch to `space`, creating it first if needed.
+fn enter_space(db: &mut RustStrata, space: &str) -> napi::Result<()> {
+    if !db.space_exists(space).map_err(to_napi_err)? {
+        db.space_create(space).map_err(to_napi_err)?;
+    }
+    db.set_space(space).map_err(to_napi_err)
+}
+
+/// Copy the KV, state, and JSON data that `source` held at `as_of` into
+/// `dest`, space by space. Leaves the handle's branch and space changed;
+/// callers pass a `detached_handle`.
+fn copy_branch_as_of(
+    db: &mut RustStrata,
+    source: &str,
+    dest: &str,
+    as_of: u64,
+) -> napi::Result<u64> {
+    db.set_branch(source).map_err(to_napi_err)?;
+    let spaces = db.list_spaces().map_err(to_napi_err)?;
+    let mut copied = 0u64;
+    for space in spaces {
+        db.set_branch(source).map_err(to_napi_err)?;
+        db.set_space(&space).map_err(to_napi_err)?;
+        let data = read_space_data(db, Some(as_of))?;
+        if data.len() == 0 {
+            continue;
+        }
+        db.set_branch(dest).map_err(to_napi_err)?;
+        enter_space(db, &space)?;
+        copied += data.len() as u64;
+        write_space_data(db, data)?;
+    }
+    Ok(copied)
+}
+
+/// Entries of `then` that differ from `now`, and keys of `now` that
+/// `then` does not have.
+fn rewind_plan(
+    then: Vec<(String, Value)>,
+    now: Vec<(String, Value)>,
+) -> (Vec<(String, Value)>, Vec<String>) {
+    let mut current: HashMap<String, serde_json::Value> =
+        now.into_iter().map(|(k, v)| (k, value_to_js(v))).collect();
+    let mut writes = Vec::new();
+    for (key, v) in then {
+        match current.remove(&key) {
+            Some(existing) if existing == value_to_js(v.clone()) => {}
+            _ => writes.push((key, v)),
+        }
+    }
+    let mut deletes: Vec<String> = current.into_keys().collect();
+    deletes.sort();
+    (writes, deletes)
+}
+
+/// Rewrite `branch`'s KV, state, and JSON data, space by space, to what it
+/// held at `as_of`. Returns the number of keys written and deleted. Leaves
+/// the handle's branch and space changed; callers pass a `detached_handle`.
+fn rewind_branch(db: &mut RustStrata, branch: &str, as_of: u64) -> napi::Result<(u64, u64)> {
+    db.set_branch(branch).map_err(to_napi_err)?;
+    let (mut written, mut deleted) = (0u64, 0u64);
+    for space in db.list_spaces().map_err(to_napi_err)? {
+        db.set_space(&space).map_err(to_napi_err)?;
+        let then = read_space_data(db, Some(as_of))?;
+        let now = read_space_data(db, None)?;
+
+        let (kv, kv_gone) = rewind_plan(then.kv, now.kv);
+        let (cells, cells_gone) = rewind_plan(then.cells, now.cells);
+        let (docs, docs_gone) = rewind_plan(then.docs, now.docs);
+        for key in &kv_gone {
+            db.kv_delete(key).map_err(to_napi_err)?;
+        }
+        for cell in &cells_gone {
+            db.state_delete(cell).map_err(to_napi_err)?;
+        }
+        for key in &docs_gone {
+            db.json_delete(key, "$").map_err(to_napi_err)?;
+        }
+        deleted += (kv_gone.len() + cells_gone.len() + docs_gone.len()) as u64;
+        let changed = SpaceData { kv, cells, docs };
+        written += changed.len() as u64;
+        write_space_data(db, changed)?;
+    }
+    Ok((written, deleted))
+}
+
+/// Move a space's KV, state, and JSON data to a new name on `branch`.
+/// Leaves the handle's space changed; the caller restores it.
+fn rename_space(db: &mut RustStrata, branch: &str, old: &str, new: &str) -> napi::Result<()> {
+    if db.space_exists(old).map_err(to_napi_err)? {
+        db.set_space(old).map_err(to_napi_err)?;
+        if holds_uncopied(db)? {
+            return Err(napi::Error::from_reason(format!(
+                "[CONSTRAINT] Space '{}' holds events, vectors, or graphs and cannot be renamed",
+                old
+            )));
+        }
+    }
+    copy_space(db, branch, old, new)?;
+    db.set_space(new).map_err(to_napi_err)?;
+    db.delete_space_force(old).map_err(to_napi_err)
+}
+
+/// Copy the KV, state, and JSON data of `source` into `dest` on `branch`.
+/// Leaves the handle's branch and space changed; callers pass a `detached_handle`.
+fn copy_space(db: &mut RustStrata, branch: &str, source: &str, dest: &str) -> napi::Result<u64> {
+    db.set_branch(branch).map_err(to_napi_err)?;
+    if !db.space_exists(source).map_err(to_napi_err)? {
+        return Err(napi::Error::from_reason(format!(
+            "[NOT_FOUND] Space not found: {}",
+            source
+        )));
+    }
+    if db.space_exists(dest).map_err(to_napi_err)? {
+        return Err(napi::Error::from_reason(format!(
+            "[STATE] Space already exists: {}",
+            dest
+        )));
+    }
+    db.set_space(source).map_err(to_napi_err)?;
+    let data = read_space_data(db, None)?;
+    enter_space(db, dest)?;
+    let copied = data.len() as u64;
+    write_space_data(db, data)?;
+    Ok(copied)
+}
+
+/// One page of the KV, state, or JSON entries in the handle's current
+/// space whose keys start with `prefix`. KV and state pages resume after
+/// the key in `cursor`; JSON pages use the engine's list cursor.
+fn read_page(
+    db: &RustStrata,
+    primitive: &str,
+    prefix: Option<String>,
+    cursor: Option<String>,
+    limit: usize,
+) -> napi::Result<(Vec<(String, Value)>, Option<String>)> {
+    let mut entries = Vec::new();
+    if primitive == "event" {
+        let Some(event_type) = prefix else {
+            return Err(napi::Error::from_reason(
+                "[VALIDATION] Reading events needs an event type",
+            ));
+        };
+        let after = match cursor {
+            Some(c) => Some(c.parse::<u64>().map_err(|_| {
+                napi::Error::from_reason(format!("[VALIDATION] Invalid event cursor '{}'", c))
+            })?),
+            None => None,
+        };
+        let events = db
+            .event_get_by_type_with_options(&event_type, Some(limit as u64), after, None)
+            .map_err(to_napi_err)?;
+        let next = match events.last() {
+            Some(e) if events.len() == limit => Some(e.version.to_string()),
+            _ => None,
+        };
+        for e in events {
+            entries.push((e.version.to_string(), e.value));
+        }
+        return Ok((entries, next));
+    }
+    if primitive == "json" {
+        let (keys, next) = db
+            .json_list_as_of(prefix, cursor, limit as u64, None)
+            .map_err(to_napi_err)?;
+        for key in keys {
+            if let Some(v) = db.json_get_as_of(&key, "$", None).map_err(to_napi_err)? {
+                entries.push((key, v));
+            }
+        }
+        return Ok((entries, next));
+    }
+    let mut keys = match primitive {
+        "kv" => db
+            .kv_list_as_of(prefix.as_deref(), None, None, None)
+            .map(user_keys),
+        "state" => db.state_list_as_of(prefix.as_deref(), None),
+        other => {
+            return Err(napi::Error::from_reason(format!(
+                "[VALIDATION] Unknown primitive '{}'; expected kv, state, or json",
+                other
+            )))
+        }
+    }
+    .map_err(to_napi_err)?;
+    keys.sort();
+    if let Some(after) = cursor {
+        keys.retain(|k| *k > after);
+    }
+    let next = if keys.len() > limit {
+        keys.truncate(limit);
+        keys.last().cloned()
+    } else {
+        None
+    };
+    for key in keys {
+        let value = if primitive == "kv" {
+            db.kv_get_as_of(&key, None)
+        } else {
+            db.state_get_as_of(&key, None)
+        };
+        if let Some(v) = value.map_err(to_napi_err)? {
+            entries.push((key, v));
+        }
+    }
+    Ok((entries, next))
+}
+
+/// Write each space's data, creating spaces that don't exist. Leaves the
+/// handle's space changed; callers pass a `detached_handle`.
+fn load_spaces(db: &mut RustStrata, batch: HashMap<String, SpaceData>) -> napi::Result<u32> {
+    let mut written = 0;
+    for (space, data) in batch {
+        enter_space(db, &space)?;
+        written += data.len() as u32;
+        write_space_data(db, data)?;
+    }
+    Ok(written)
+}
+
+// ---------------------------------------------------------------------------
+// Statistics
+// ---------------------------------------------------------------------------
+
+/// Entry counts and estimated bytes for a branch or space.
+#[derive(Default)]
+struct Tally {
+    kv: u64,
+    state: u64,
+    json: u64,
+    events: u64,
+    vectors: u64,
+    collections: u64,
+    graphs: u64,
+    bytes: u64,
+}
+
+impl Tally {
+    fn add(&mut self, other: &Tally) {
+        self.kv += other.kv;
+        self.state += other.state;
+        self.json += other.json;
+        self.events += other.events;
+        self.vectors += other.vectors;
+        self.collections += other.collections;
+        self.graphs += other.graphs;
+        self.bytes += other.bytes;
+    }
+
+    fn to_js(&self) -> serde_json::Value {
+        serde_json::json!({
+            "kv": self.kv,
+            "state": self.state,
+            "json": self.json,
+            "events": self.events,
+            "vectors": self.vectors,
+            "collections": self.collections,
+            "graphs": self.graphs,
+            "estimatedBytes": self.bytes,
+        })
+    }
+}
+
+/// Encoded size of a stored value, used for byte estimates.
+fn value_size(v: Value) -> u64 {
+    value_to_js(v).to_string().len() as u64
+}
+
+/// Tally the handle's current branch and space.
+///
+/// Byte estimates count keys plus JSON-encoded values for KV, state, and
+/// JSON, and index memory for vectors. Events and graphs add no bytes.
+fn tally_space(db: &RustStrata) -> napi::Result<Tally> {
+    let mut t = Tally::default();
+    for key in db
+        .kv_list_as_of(None, None, None, None)
+        .map(user_keys)
+        .map_err(to_napi_err)?
+    {
+        t.kv += 1;
+        t.bytes += key.len() as u64;
+        if let Some(v) = db.kv_get_as_of(&key, None).map_err(to_napi_err)? {
+            t.bytes += value_size(v);
+        }
+    }
+    for cell in db.state_list_as_of(None, None).map_err(to_napi_err)? {
+        t.state += 1;
+        t.bytes += cell.len() as u64;
+        if let Some(v) = db.state_get_as_of(&cell, None).map_err(to_napi_err)? {
+            t.bytes += value_size(v);
+        }
+    }
+    let mut cursor = None;
+    loop {
+        let (keys, next) = db
+            .json_list_as_of(None, cursor, JSON_LIST_PAGE, None)
+            .map_err(to_napi_err)?;
+        for key in keys {
+            t.json += 1;
+            t.bytes += key.len() as u64;
+            if let Some(v) = db.json_get_as_of(&key, "$", None).map_err(to_napi_err)? {
+                t.bytes += value_size(v);
+            }
+        }
+        match next {
+            Some(c) => cursor = Some(c),
+            None => break,
+        }
+    }
+    t.events = db.event_len().map_err(to_napi_err)? as u64;
+    for c in db.vector_list_collections().map_err(to_napi_err)? {
+        t.collections += 1;
+        t.vectors += c.count as u64;
+        t.bytes += c.memory_bytes as u64;
+    }
+    t.graphs = db.graph_list().map_err(to_napi_err)?.len() as u64;
+    Ok(t)
+}
+
+/// Tally every space of `branch`. Returns the totals, the space count, and
+/// the branch's latest write timestamp. Leaves the handle's branch and
+/// space changed; callers pass a `detached_handle`.
+fn tally_branch(db: &mut RustStrata, branch: &str) -> napi::Result<(Tally, usize, Option<u64>)> {
+    db.set_branch(branch).map_err(to_napi_err)?;
+    let spaces = db.list_spaces().map_err(to_napi_err)?;
+    let mut total = Tally::default();
+    for space in &spaces {
+        db.set_space(space).map_err(to_napi_err)?;
+        total.add(&tally_space(db)?);
+    }
+    let (_, latest) = db.time_range().map_err(to_napi_err)?;
+    Ok((total, spaces.len(), latest))
+}
+
+// ---------------------------------------------------------------------------
+// Changelog (replication)
+// ---------------------------------------------------------------------------
+
+/// `_system_` key holding the id from `databaseId()`.
+const DATABASE_ID_KEY: &str = "db/id";
+
+/// Records per `changelogRead` page when no limit is given.
+const CHANGELOG_PAGE: u32 = 1000;
+
+/// One write in a changelog. A `value` of `None` is a delete.
+struct ChangeRecord {
+    version: Option<u64>,
+    branch: String,
+    space: String,
+    primitive: &'static str,
+    key: String,
+    value: Option<Value>,
+}
+
+impl ChangeRecord {
+    fn into_value(self) -> Value {
+        let mut out = HashMap::new();
+        let version = self.version.map_or(Value::Null, |v| Value::Int(v as i64));
+        let op = match self.value {
+            Some(_) => "put",
+            None => "delete",
+        };
+        out.insert("version".to_string(), version);
+        out.insert("branch".to_string(), Value::String(self.branch));
+        out.insert("space".to_string(), Value::String(self.space));
+        let primitive = Value::String(self.primitive.into());
+        out.insert("primitive".to_string(), primitive);
+        out.insert("key".to_string(), Value::String(self.key));
+        out.insert("op".to_string(), Value::String(op.into()));
+        out.insert("value".to_string(), self.value.unwrap_or(Value::Null));
+        Value::Object(Box::new(out))
+    }
+}
+
+/// Keys of the KV, state, and JSON data in the handle's current space, as
+/// of `as_of` (latest if `None`).
+fn list_space_keys(
+    db: &RustStrata,
+    as_of: Option<u64>,
+) -> napi::Result<Vec<(&'static str, String)>> {
+    let mut keys: Vec<(&'static str, String)> = Vec::new();
+    for key in db
+        .kv_list_as_of(None, None, None, as_of)
+        .map(user_keys)
+        .map_err(to_napi_err)?
+    {
+        keys.push(("kv", key));
+    }
+    for cell in db.state_list_as_of(None, as_of).map_err(to_napi_err)? {
+        keys.push(("state", cell));
+    }
+    let mut cursor = None;
+    loop {
+        let (page, next) = db
+            .json_list_as_of(None, cursor, JSON_LIST_PAGE, as_of)
+            .map_err(to_napi_err)?;
+        keys.extend(page.into_iter().map(|k| ("json", k)));
+        match next {
+            Some(c) => cursor = Some(c),
+            None => break,
+        }
+    }
+    Ok(keys)
+}
+
+/// One page of a changelog, from `read_changes`.
+struct ChangePage {
+    records: Vec<ChangeRecord>,
+    version: u64,
+    more: bool,
+}
+
+/// Whether the handle's current space holds events, vectors, or graphs,
+/// which are not copied or replicated entry by entry.
+fn holds_uncopied(db: &RustStrata) -> napi::Result<bool> {
+    Ok(db.event_len().map_err(to_napi_err)? > 0
+        || !db
+            .vector_list_collections()
+            .map_err(to_napi_err)?
+            .is_empty()
+        || !db.graph_list().map_err(to_napi_err)?.is_empty())
+}
+
+/// The KV, state, and JSON writes made after version `after` (at least
+/// `since`), on every branch, oldest first, ending on a version boundary
+/// once at least `limit` are read. The last page is followed by a delete
+/// for each key that existed at `since` and no longer does. The engine
+/// keeps no record of deletes, so those carry no version, and the time of
+/// `since` is taken from the newest version at or before it that any key
+/// still has. Leaves the handle's branch and space changed; callers pass a
+/// `detached_handle`.
+fn read_changes(
+    db: &mut RustStrata,
+    since: u64,
+    after: u64,
+    limit: usize,
+) -> napi::Result<ChangePage> {
+    let mut records = Vec::new();
+    let mut present: Vec<(String, String, HashSet<(&'static str, String)>)> = Vec::new();
+    let mut since_ts = 0u64;
+    let mut version = after;
+    let branches = db.branch_list(None, None, None).map_err(to_napi_err)?;
+    for b in branches {
+        let branch = b.info.id.as_str().to_string();
+        db.set_branch(&branch).map_err(to_napi_err)?;
+        for space in db.list_spaces().map_err(to_napi_err)? {
+            db.set_space(&space).map_err(to_napi_err)?;
+            if holds_uncopied(db)? {
+                return Err(napi::Error::from_reason(format!(
+                    "[CONSTRAINT] Space '{}' on branch '{}' holds events, vectors, or graphs, which a changelog can't carry",
+                    space, branch
+                )));
+            }
+            let keys = list_space_keys(db, None)?;
+            for (primitive, key) in &keys {
+                let history = match *primitive {
+                    "kv" => db.kv_getv(key),
+                    "state" => db.state_getv(key),
+                    _ => db.json_getv(key),
+                }
+                .map_err(to_napi_err)?
+                .unwrap_or_default();
+                for vv in history {
+                    if vv.version <= since {
+                        since_ts = since_ts.max(vv.timestamp);
+                    }
+                    if vv.version <= after {
+                        continue;
+                    }
+                    records.push(ChangeRecord {
+                        version: Some(vv.version),
+                        branch: branch.clone(),
+                        space: space.clone(),
+                        primitive: *primitive,
+                        key: key.clone(),
+                        value: Some(vv.value),
+                    });
+                }
+            }
+            present.push((branch.clone(), space, keys.into_iter().collect()));
+        }
+    }
+    records.sort_by_key(|r| r.version);
+    // Writes committed together share a version; a page never splits one.
+    let mut end = records.len().min(limit);
+    while end > 0 && end < records.len() && records[end].version == records[end - 1].version {
+        end += 1;
+    }
+    let more = end < records.len();
+    records.truncate(end);
+    if let Some(Some(v)) = records.last().map(|r| r.version) {
+        version = v;
+    }
+    let mut page = ChangePage {
+        records,
+        version,
+        more,
+    };
+    if more || since_ts == 0 {
+        return Ok(page);
+    }
+    for (branch, space, keys) in present {
+        db.set_branch(&branch).map_err(to_napi_err)?;
+        db.set_space(&space).map_err(to_napi_err)?;
+        for (primitive, key) in list_space_keys(db, Some(since_ts))? {
+            if !keys.contains(&(primitive, key.clone())) {
+                page.records.push(ChangeRecord {
+                    version: None,
+                    branch: branch.clone(),
+                    space: space.clone(),
+                    primitive,
+                    key,
+                    value: None,
+                });
+            }
+        }
+    }
+    Ok(page)
+}
+
+/// Every KV, state, and JSON entry on `branch`, as `{ space, primitive,
+/// key, value }` objects. Refuses branches with a space holding events,
+/// vectors, or graphs. Leaves the handle's branch and space changed;
+/// callers pass a `detached_handle`.
+fn read_branch_entries(db: &mut RustStrata, branch: &str) -> napi::Result<Vec<Value>> {
+    if !db.branches().exists(branch).map_err(to_napi_err)? {
+        return Err(napi::Error::from_reason(format!(
+            "[NOT_FOUND] Branch not found: {}",
+            branch
+        )));
+    }
+    db.set_branch(branch).map_err(to_napi_err)?;
+    let mut out = Vec::new();
+    for space in db.list_spaces().map_err(to_napi_err)? {
+        db.set_space(&space).map_err(to_napi_err)?;
+        if holds_uncopied(db)? {
+            return Err(napi::Error::from_reason(format!(
+                "[CONSTRAINT] Space '{}' on branch '{}' holds events, vectors, or graphs, which sync can't copy",
+                space, branch
+            )));
+        }
+        let data = read_space_data(db, None)?;
+        for (primitive, entries) in [("kv", data.kv), ("state", data.cells), ("json", data.docs)] {
+            for (key, value) in entries {
+                let mut entry = HashMap::new();
+                entry.insert("space".to_string(), Value::String(space.clone()));
+                entry.insert("primitive".to_string(), Value::String(primitive.into()));
+                entry.insert("key".to_string(), Value::String(key));
+                entry.insert("value".to_string(), value);
+                out.push(Value::Object(Box::new(entry)));
+            }
+        }
+    }
+    Ok(out)
+}
+
+/// Apply changelog records in order, creating branches and spaces that
+/// don't exist. Leaves the handle's branch and space changed; callers pass a
+/// `detached_handle`.
+fn apply_changes(db: &mut RustStrata, records: Vec<ChangeRecord>) -> napi::Result<u32> {
+    let mut applied = 0;
+    for r in records {
+        if db.current_branch().to_string() != r.branch {
+            if !db.branches().exists(&r.branch).map_err(to_napi_err)? {
+                db.branch_create(Some(r.branch.clone()), None)
+                    .map_err(to_napi_err)?;
+            }
+            db.set_branch(&r.branch).map_err(to_napi_err)?;
+        }
+        if db.current_space().to_string() != r.space {
+            enter_space(db, &r.space)?;
+        }
+        match (r.primitive, r.value) {
+            ("kv", Some(v)) => db.kv_put(&r.key, v).map(|_| ()),
+            ("kv", None) => db.kv_delete(&r.key).map(|_| ()),
+            ("state", Some(v)) => db.state_set(&r.key, v).map(|_| ()),
+            ("state", None) => db.state_delete(&r.key).map(|_| ()),
+            (_, Some(v)) => db.json_set(&r.key, "$", v).map(|_| ()),
+            (_, None) => db.json_delete(&r.key, "$").map(|_| ()),
+        }
+        .map_err(to_napi_err)?;
+        applied += 1;
+    }
+    Ok(applied)
+}
+
+// ---------------------------------------------------------------------------
+// Verification
+// ---------------------------------------------------------------------------
+
+/// State of a `verify()` walk: where it is and what it has found.
+struct Verifier {
+    deep: bool,
+    op: CancellableOp,
+    branch: String,
+    space: Option<String>,
+    branches: u64,
+    spaces: u64,
+    checked: u64,
+    problems: Vec<serde_json::Value>,
+}
+
+impl Verifier {
+    fn new(deep: bool, op: CancellableOp) -> Self {
+        Verifier {
+            deep,
+            op,
+            branch: String::new(),
+            space: None,
+            branches: 0,
+            spaces: 0,
+            checked: 0,
+            problems: Vec::new(),
+        }
+    }
+
+    /// Record a problem at the current branch and space. `kind` is
+    /// `metadata`, `index`, or `read`.
+    fn problem(
+        &mut self,
+        kind: &str,
+        primitive: Option<&str>,
+        key: Option<String>,
+        message: String,
+    ) {
+        self.problems.push(serde_json::json!({
+            "kind": kind,
+            "branch": self.branch,
+            "space": self.space,
+            "primitive": primitive,
+            "key": key,
+            "message": message,
+        }));
+    }
+
+    /// Run `read`, recording its failure as a `read` problem.
+    fn read<T>(
+        &mut self,
+        primitive: &str,
+        key: Option<&str>,
+        read: impl FnOnce() -> Result<T, StrataError>,
+    ) -> Option<T> {
+        match read() {
+            Ok(v) => Some(v),
+            Err(e) => {
+                self.problem(
+                    "read",
+                    Some(primitive),
+                    key.map(String::from),
+                    e.to_string(),
+                );
+                None
+            }
+        }
+    }
+
+    /// Count a latest-value read of a listed key; a key that is listed but
+    /// has no value is an index problem.
+    fn value<T>(
+        &mut self,
+        primitive: &str,
+        key: &str,
+        read: impl FnOnce() -> Result<Option<T>, StrataError>,
+    ) {
+        self.checked += 1;
+        if let Some(None) = self.read(primitive, Some(key), read) {
+            self.problem(
+                "index",
+                Some(primitive),
+                Some(key.to_string()),
+                "Listed but has no value".to_string(),
+            );
+        }
+    }
+
+    fn database(&mut self, db: &mut RustStrata) -> napi::Result<()> {
+        let branches = db.branch_list(None, None, None).map_err(to_napi_err)?;
+        let ids: HashSet<String> = branches
+            .iter()
+            .map(|b| b.info.id.as_str().to_string())
+            .collect();
+        // Annotations left behind by a branch that no longer exists.
+        self.branch = "_system_".to_string();
+        for key in db
+            .system_branch()
+            .kv_list(Some(BRANCH_META_PREFIX))
+            .map_err(to_napi_err)?
+        {
+            let owner = &key[BRANCH_META_PREFIX.len()..];
+            if !ids.contains(owner) {
+                self.problem(
+                    "metadata",
+                    None,
+                    Some(key.clone()),
+                    format!("Metadata for missing branch {}", owner),
+                );
+            }
+        }
+        for b in branches {
+            self.op.check()?;
+            self.branch = b.info.id.as_str().to_string();
+            self.space = None;
+            self.branches += 1;
+            if let Some(parent) = b.info.parent_id.map(|p| p.as_str().to_string()) {
+                if !ids.contains(&parent) {
+                    self.problem(
+                        "metadata",
+                        None,
+                        None,
+                        format!("Parent branch {} does not exist", parent),
+                    );
+                }
+            }
+            if let Err(e) = db.set_branch(&self.branch) {
+                self.problem("metadata", None, None, e.to_string());
+                continue;
+            }
+            let Some(spaces) = self.read("space", None, || db.list_spaces()) else {
+                continue;
+            };
+            for space in spaces {
+                self.op.check()?;
+                self.space = Some(space.clone());
+                self.spaces += 1;
+                match db.set_space(&space) {
+                    Ok(()) => self.space_contents(db)?,
+                    Err(e) => self.problem("metadata", None, None, e.to_string()),
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Check the handle's current branch and space.
+    fn space_contents(&mut self, db: &RustStrata) -> napi::Result<()> {
+        let kv = self.read("kv", None, || db.kv_list_as_of(None, None, None, None));
+        let cells = self.read("state", None, || db.state_list_as_of(None, None));
+        let mut docs = Vec::new();
+        let mut cursor = None;
+        while let Some((keys, next)) = self.read("json", None, || {
+            db.json_list_as_of(None, cursor.take(), JSON_LIST_PAGE, None)
+        }) {
+            docs.extend(keys);
+            match next {
+                Some(c) => cursor = Some(c),
+                None => break,
+            }
+        }
+        let events = self.read("event", None, || db.event_len());
+        self.read("vector", None, || db.vector_list_collections());
+        let graphs = self.read("graph", None, || db.graph_list());
+        if !self.deep {
+            return Ok(());
+        }
+        for key in kv.unwrap_or_default() {
+            self.op.check()?;
+            self.value("kv", &key, || db.kv_get_as_of(&key, None));
+        }
+        for cell in cells.unwrap_or_default() {
+            self.op.check()?;
+            self.value("state", &cell, || db.state_get_as_of(&cell, None));
+        }
+        for key in docs {
+            self.op.check()?;
+            self.value("json", &key, || db.json_get_as_of(&key, "$", None));
+        }
+        for sequence in 0..events.unwrap_or(0) as u64 {
+            self.op.check()?;
+            self.checked += 1;
+            let key = sequence.to_string();
+            self.read("event", Some(&key), || db.event_get_as_of(sequence, None));
+        }
+        for graph in graphs.unwrap_or_default() {
+            self.graph(db, &graph)?;
+        }
+        Ok(())
+    }
+
+    /// Check that every edge of `graph` points at a node that exists.
+    fn graph(&mut self, db: &RustStrata, graph: &str) -> napi::Result<()> {
+        let Some(nodes) = self.read("graph", Some(graph), || db.graph_list_nodes(graph)) else {
+            return Ok(());
+        };
+        let known: HashSet<&String> = nodes.iter().collect();
+        for node in &nodes {
+            self.op.check()?;
+            let key = format!("{}/{}", graph, node);
+            let neighbors = self.read("graph", Some(&key), || {
+                db.graph_neighbors(graph, node, "outgoing", None)
+            });
+            for n in neighbors.into_iter().flatten() {
+                self.checked += 1;
+                if !known.contains(&n.node_id) {
+                    self.problem(
+                        "index",
+                        Some("graph"),
+                        Some(key.clone()),
+                        format!("Edge to missing node {}", n.node_id),
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn report(self) -> serde_json::Value {
+        serde_json::json!({
+            "ok": self.problems.is_empty(),
+            "deep": self.deep,
+            "branches": self.branches,
+            "spaces": self.spaces,
+            "entriesChecked": self.checked,
+            "problems": self.problems,
+        })
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Conversion helpers (free functions)
+// ---------------------------------------------------------------------------
+
+fn collection_info_to_js(c: CollectionInfo) -> serde_json::Value {
+    serde_json::json!({
+        "name": c.name,
+        "dimension": c.dimension,
+        "metric": format!("{:?}", c.metric).to_lowercase(),
+        "count": c.count,
+        "indexType": c.index_type,
+        "memoryBytes": c.memory_bytes,
+    })
+}
+
+fn versioned_branch_info_to_js(
+    info: VersionedBranchInfo,
+    meta: &serde_json::Map<String, serde_json::Value>,
+) -> serde_json::Value {
+    serde_json::json!({
+        "id": info.info.id.as_str(),
+        "status": format!("{:?}", info.info.status).to_lowercase(),
+        "createdAt": info.info.created_at,
+        "updatedAt": info.info.updated_at,
+        "parentId": info.info.parent_id.map(|p| p.as_str().to_string()),
+        "version": info.version,
+        "timestamp": info.timestamp,
+        "description": meta.get("description").cloned().unwrap_or(serde_json::Value::Null),
+        "labels": meta.get("labels").cloned().unwrap_or_else(|| serde_json::json!([])),
+        "protected": branch_flag(meta, "protected"),
+        "archived": branch_flag(meta, "archived"),
+    })
+}
+
+/// How values of diffed keys are reported.
+#[derive(Clone, Copy, PartialEq)]
+enum DiffValues {
+    None,
+    Values,
+    Hashes,
+}
+
+/// How much of a branch diff to return, parsed from `JsDiffOptions`.
+struct DiffOutput {
+    detailed: bool,
+    values: DiffValues,
+    limit: Option<usize>,
+    /// Where the previous page ended, from its cursor.
+    after: Option<DiffPosition>,
+}
+
+impl DiffOutput {
+    fn from_options(options: Option<&JsDiffOptions>) -> napi::Result<Self> {
+        let values = match options.and_then(|o| o.values.as_deref()) {
+            None | Some("none") => DiffValues::None,
+            Some("values") => DiffValues::Values,
+            Some("hashes") => DiffValues::Hashes,
+            Some(other) => {
+                return Err(napi::Error::from_reason(format!(
+                    "[VALIDATION] Invalid diff values mode: {}",
+                    other
+                )))
+            }
+        };
+        let after = options
+            .and_then(|o| o.cursor.as_deref())
+            .map(DiffPosition::from_cursor)
+            .transpose()?;
+        let limit = options.and_then(|o| o.limit);
+        if limit == Some(0) {
+            return Err(napi::Error::from_reason(
+                "[VALIDATION] Diff limit must be at least 1",
+            ));
+        }
+        Ok(DiffOutput {
+            detailed: options.and_then(|o| o.detailed).unwrap_or(false),
+            values,
+            limit: limit.map(|l| l as usize),
+            after,
+        })
+    }
+}
+
+/// Diff two branches and render the result as `diffBranches` returns it.
+fn diff_to_js(
+    db: &RustStrata,
+    branch_a: &str,
+    branch_b: &str,
+    output: &DiffOutput,
+) -> napi::Result<serde_json::Value> {
+    let diff = db.diff_branches(branch_a, branch_b).map_err(to_napi_err)?;
+    let mut out = serde_json::json!({
+        "branchA": diff.branch_a,
+        "branchB": diff.branch_b,
+        "summary": {
+            "totalAdded": diff.summary.total_added,
+            "totalRemoved": diff.summary.total_removed,
+            "totalModified": diff.summary.total_modified,
+        },
+    });
+    if output.detailed {
+        let (spaces, next_cursor) = diff_spaces_to_js(
+            diff.spaces,
+            output.values,
+            output.after.as_ref(),
+            output.limit,
+        );
+        out["spaces"] = spaces;
+        out["hasMore"] = serde_json::json!(next_cursor.is_some());
+        out["cursor"] = serde_json::json!(next_cursor);
+    }
+    Ok(out)
+}
+
+/// KV prefix for write annotations: the annotations of key `k`'s writes
+/// are the versions of `_annotation/<k>`, each `{ author?, message?,
+/// requestId? }` written in the same transaction as the version of `k` it
+/// describes, so the two share a version. Listings leave these keys out
+/// and only an annotated `kvPut` may write them.
+const ANNOTATION_PREFIX: &str = "_annotation/";
+
+fn annotation_key(key: &str) -> String {
+    format!("{}{}", ANNOTATION_PREFIX, key)
+}
+
+/// `keys` without the annotation keys.
+fn user_keys(mut keys: Vec<String>) -> Vec<String> {
+    keys.retain(|k| !k.starts_with(ANNOTATION_PREFIX));
+    keys
+}
+
+/// Write `value` to `key` and `note` to its annotation in one transaction
+/// in the handle's current branch and space. Returns the commit version,
+/// which both writes get.
+fn kv_put_annotated(
+    db: &RustStrata,
+    key: &str,
+    value: Value,
+    note: HashMap<String, Value>,
+) -> napi::Result<u64> {
+    let branch = db.current_branch().to_string();
+    let space = db.current_space().to_string();
+    let put = |key: &str, value: Value| {
+        serde_json::from_value::<Command>(serde_json::json!({
+            "KvPut": { "branch": branch, "space": space, "key": key, "value": value }
+        }))
+        .map_err(|e| napi::Error::from_reason(format!("Invalid KvPut command: {}", e)))
+    };
+    let puts = [
+        put(key, value)?,
+        put(&annotation_key(key), Value::Object(Box::new(note)))?,
+    ];
+    commit_in_txn(db, |session| {
+        for cmd in puts {
+            session.execute(cmd).map_err(to_napi_err)?;
+        }
+        Ok(())
+    })
+}
+
+/// Run `writes` in a transaction of its own on `db`'s current branch and
+/// commit it, or roll it back if `writes` fails. Returns the commit
+/// version.
+fn commit_in_txn(
+    db: &RustStrata,
+    writes: impl FnOnce(&mut Session) -> napi::Result<()>,
+) -> napi::Result<u64> {
+    let mut session = db.session();
+    let begin = Command::TxnBegin {
+        branch: Some(db.current_branch().to_string().into()),
+        options: None,
+    };
+    session.execute(begin).map_err(to_napi_err)?;
+    if let Err(e) = writes(&mut session) {
+        let _ = session.execute(Command::TxnRollback);
+        return Err(e);
+    }
+    match session.execute(Command::TxnCommit).map_err(to_napi_err)? {
+        Output::TxnCommitted { version } => Ok(version),
+        _ => Err(napi::Error::from_reason("Unexpected output for TxnCommit")),
+    }
+}
+
+/// Replace the chunks of document `key` in `collection` with `chunks`, in
+/// order, and delete the chunks of a longer earlier version, all in one
+/// transaction. Returns the commit version.
+fn replace_chunks(
+    db: &RustStrata,
+    collection: &str,
+    key: &str,
+    chunks: Vec<BatchVectorEntry>,
+) -> napi::Result<u64> {
+    let branch = db.current_branch().to_string();
+    let space = db.current_space().to_string();
+    let command = |json: serde_json::Value| {
+        serde_json::from_value::<Command>(json)
+            .map_err(|e| napi::Error::from_reason(format!("Invalid vector command: {}", e)))
+    };
+    let count = chunks.len();
+    commit_in_txn(db, |session| {
+        for chunk in chunks {
+            let upsert = command(serde_json::json!({
+                "VectorUpsert": {
+                    "branch": branch,
+                    "space": space,
+                    "collection": collection,
+                    "key": chunk.key,
+                    "vector": chunk.vector,
+                    "metadata": chunk.metadata,
+                }
+            }))?;
+            session.execute(upsert).map_err(to_napi_err)?;
+        }
+        // Chunks are numbered from 0 without gaps, so the stale ones of a
+        // longer earlier version end at the first missing index.
+        for i in count.. {
+            let delete = command(serde_json::json!({
+                "VectorDelete": {
+                    "branch": branch,
+                    "space": space,
+                    "collection": collection,
+                    "key": format!("{}#{}", key, i),
+                }
+            }))?;
+            match session.execute(delete).map_err(to_napi_err)? {
+                Output::VectorDeleteResult { deleted: true, .. } => {}
+                Output::VectorDeleteResult { .. } => break,
+                _ => {
+                    return Err(napi::Error::from_reason(
+                        "Unexpected output for VectorDelete",
+                    ))
+                }
             }
-        })
-        .await
-        .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
+        }
+        Ok(())
+    })
+}
+
+/// The fields of `annotation` that are set, as stored.
+fn annotation_fields(annotation: JsWriteAnnotation) -> HashMap<String, Value> {
+    [
+        ("author", annotation.author),
+        ("message", annotation.message),
+        ("requestId", annotation.request_id),
+    ]
+    .into_iter()
+    .filter_map(|(field, v)| Some((field.to_string(), Value::String(v?))))
+    .collect()
+}
+
+/// The annotations of `key`'s writes in the current branch and space, by
+/// the version they describe.
+fn kv_annotations(db: &RustStrata, key: &str) -> napi::Result<HashMap<u64, serde_json::Value>> {
+    let mut notes = HashMap::new();
+    let versions = db.kv_getv(&annotation_key(key)).map_err(to_napi_err)?;
+    for vv in versions.unwrap_or_default() {
+        notes.insert(vv.version, value_to_js(vv.value));
     }
+    Ok(notes)
+}
 
-    #[napi(js_name = "jsonDelete")]
-    pub async fn json_delete(&self, key: String, path: String) -> napi::Result<i64> {
-        let inner = self.inner.clone();
-        tokio::task::spawn_blocking(move || {
-            let guard = lock_inner(&inner)?;
-            guard
-                .system_branch()
-                .json_delete(&key, &path)
-                .map(|n| n as i64)
-                .map_err(to_napi_err)
-        })
-        .await
-        .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
+/// Add its `annotation` to each `{ version }` entry of `versions` that has
+/// one.
+fn annotate_versions(versions: &mut [serde_json::Value], notes: &HashMap<u64, serde_json::Value>) {
+    for entry in versions {
+        if let Some(note) = entry["version"].as_u64().and_then(|v| notes.get(&v)) {
+            entry["annotation"] = note.clone();
+        }
     }
+}
 
-    // -- State --
+/// The annotation of the latest write of `key` in the current branch and
+/// space, if it has one.
+fn latest_annotation(db: &RustStrata, key: &str) -> napi::Result<Option<serde_json::Value>> {
+    let versions = db.kv_getv(key).map_err(to_napi_err)?.unwrap_or_default();
+    let Some(latest) = versions.iter().map(|vv| vv.version).max() else {
+        return Ok(None);
+    };
+    Ok(kv_annotations(db, key)?.remove(&latest))
+}
 
-    #[napi(js_name = "stateSet")]
-    pub async fn state_set(&self, cell: String, value: serde_json::Value) -> napi::Result<i64> {
-        let inner = self.inner.clone();
-        let v = js_to_value_checked(value, 0)?;
-        tokio::task::spawn_blocking(move || {
-            let guard = lock_inner(&inner)?;
-            guard
-                .system_branch()
-                .state_set(&cell, v)
-                .map(|n| n as i64)
-                .map_err(to_napi_err)
-        })
-        .await
-        .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
+/// Add `annotationA` and `annotationB` to the KV entries of a detailed
+/// diff: the annotations of the key's latest write on each side that has
+/// it. Leaves the handle's branch and space changed; callers pass a `detached_handle`.
+fn annotate_diff(
+    db: &mut RustStrata,
+    branch_a: &str,
+    branch_b: &str,
+    out: &mut serde_json::Value,
+) -> napi::Result<()> {
+    let Some(spaces) = out["spaces"].as_array_mut() else {
+        return Ok(());
+    };
+    let both = [("annotationA", branch_a), ("annotationB", branch_b)];
+    for sd in spaces {
+        let space = sd["space"].as_str().unwrap_or("default").to_string();
+        for (kind, sides) in [
+            ("added", &both[1..]),
+            ("removed", &both[..1]),
+            ("modified", &both[..]),
+        ] {
+            let Some(entries) = sd[kind].as_array_mut() else {
+                continue;
+            };
+            for entry in entries.iter_mut().filter(|e| e["primitive"] == "kv") {
+                let key = entry["key"].as_str().unwrap_or_default().to_string();
+                for (field, branch) in sides {
+                    db.set_branch(branch).map_err(to_napi_err)?;
+                    db.set_space(&space).map_err(to_napi_err)?;
+                    if let Some(note) = latest_annotation(db, &key)? {
+                        entry[*field] = note;
+                    }
+                }
+            }
+        }
     }
+    Ok(())
+}
 
-    #[napi(js_name = "stateGet")]
-    pub async fn state_get(&self, cell: String) -> napi::Result<serde_json::Value> {
-        let inner = self.inner.clone();
-        tokio::task::spawn_blocking(move || {
-            let guard = lock_inner(&inner)?;
-            match guard
-                .system_branch()
-                .state_get(&cell)
-                .map_err(to_napi_err)?
-            {
-                Some(v) => Ok(value_to_js(v)),
-                None => Ok(serde_json::Value::Null),
+/// Stable 64-bit FNV-1a hash of a value's JSON encoding, as hex.
+fn value_hash(val: Value) -> String {
+    let encoded = value_to_js(val).to_string();
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for b in encoded.bytes() {
+        hash ^= b as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    format!("{:016x}", hash)
+}
+
+fn diff_entry_to_js(e: BranchDiffEntry, kind: &str, values: DiffValues) -> serde_json::Value {
+    let mut obj = serde_json::json!({
+        "key": e.key,
+        "primitive": format!("{:?}", e.primitive).to_lowercase(),
+    });
+    if kind == "modified" {
+        match values {
+            DiffValues::None => {}
+            DiffValues::Values => {
+                obj["valueA"] = e.value_a.map(value_to_js).unwrap_or_default();
+                obj["valueB"] = e.value_b.map(value_to_js).unwrap_or_default();
             }
-        })
-        .await
-        .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
+            DiffValues::Hashes => {
+                obj["hashA"] = serde_json::json!(e.value_a.map(value_hash));
+                obj["hashB"] = serde_json::json!(e.value_b.map(value_hash));
+            }
+        }
     }
+    obj
+}
 
-    // -- Events --
+/// Kinds of diff entry, in page order.
+const DIFF_KINDS: [&str; 3] = ["added", "removed", "modified"];
+
+/// Where a diff entry falls in page order: by space, then kind, then
+/// primitive, then key. A cursor names the last entry of its page, so the
+/// next page resumes after that key even if the diff has changed since.
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct DiffPosition {
+    space: String,
+    kind: usize,
+    primitive: String,
+    key: String,
+}
 
-    #[napi(js_name = "eventAppend")]
-    pub async fn event_append(
-        &self,
-        event_type: String,
-        payload: serde_json::Value,
-    ) -> napi::Result<i64> {
-        let inner = self.inner.clone();
-        let v = js_to_value_checked(payload, 0)?;
-        tokio::task::spawn_blocking(move || {
-            let guard = lock_inner(&inner)?;
-            guard
-                .system_branch()
-                .event_append(&event_type, v)
-                .map(|n| n as i64)
-                .map_err(to_napi_err)
+impl DiffPosition {
+    /// Whether the entry is a write annotation rather than user data.
+    fn is_annotation(&self) -> bool {
+        self.primitive == "kv" && self.key.starts_with(ANNOTATION_PREFIX)
+    }
+
+    fn to_cursor(&self) -> String {
+        serde_json::json!([self.space, DIFF_KINDS[self.kind], self.primitive, self.key]).to_string()
+    }
+
+    fn from_cursor(cursor: &str) -> napi::Result<Self> {
+        let invalid = || napi::Error::from_reason("[VALIDATION] Invalid diff cursor");
+        let parts: Vec<String> = serde_json::from_str(cursor).map_err(|_| invalid())?;
+        let [space, kind, primitive, key] =
+            <[String; 4]>::try_from(parts).map_err(|_| invalid())?;
+        let kind = DIFF_KINDS
+            .iter()
+            .position(|k| *k == kind)
+            .ok_or_else(invalid)?;
+        Ok(DiffPosition {
+            space,
+            kind,
+            primitive,
+            key,
         })
-        .await
-        .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
     }
+}
 
-    #[napi(js_name = "eventGet")]
-    pub async fn event_get(&self, sequence: i64) -> napi::Result<serde_json::Value> {
-        let inner = self.inner.clone();
-        tokio::task::spawn_blocking(move || {
-            let guard = lock_inner(&inner)?;
-            match guard
-                .system_branch()
-                .event_get(sequence as u64)
-                .map_err(to_napi_err)?
-            {
-                Some(vv) => Ok(versioned_to_js(vv)),
-                None => Ok(serde_json::Value::Null),
+/// One page of per-space diff entries, in `DiffPosition` order, starting
+/// after `after`.
+///
+/// Returns the per-space JSON and the cursor for the next page, if any.
+fn diff_spaces_to_js(
+    mut spaces: Vec<SpaceDiff>,
+    values: DiffValues,
+    after: Option<&DiffPosition>,
+    limit: Option<usize>,
+) -> (serde_json::Value, Option<String>) {
+    spaces.sort_by(|a, b| a.space.cmp(&b.space));
+    let mut emitted = 0usize;
+    let mut last = None;
+    let mut has_more = false;
+    let mut out = Vec::new();
+    for sd in spaces {
+        let mut lists = serde_json::Map::new();
+        for (kind, entries) in [sd.added, sd.removed, sd.modified].into_iter().enumerate() {
+            let mut entries: Vec<(DiffPosition, BranchDiffEntry)> = entries
+                .into_iter()
+                .map(|e| {
+                    let position = DiffPosition {
+                        space: sd.space.clone(),
+                        kind,
+                        primitive: format!("{:?}", e.primitive).to_lowercase(),
+                        key: e.key.clone(),
+                    };
+                    (position, e)
+                })
+                .filter(|(position, _)| !position.is_annotation())
+                .filter(|(position, _)| after.is_none_or(|after| position > after))
+                .collect();
+            entries.sort_by(|a, b| a.0.cmp(&b.0));
+            let mut page = Vec::new();
+            for (position, e) in entries {
+                if limit.is_some_and(|l| emitted >= l) {
+                    has_more = true;
+                    break;
+                }
+                page.push(diff_entry_to_js(e, DIFF_KINDS[kind], values));
+                last = Some(position);
+                emitted += 1;
             }
-        })
-        .await
-        .map_err(|e| napi::Error::from_reason(format!("{}", e)))?
+            lists.insert(DIFF_KINDS[kind].to_string(), serde_json::Value::Array(page));
+        }
+        let non_empty = lists
+            .values()
+            .any(|v| v.as_array().is_some_and(|a| !a.is_empty()));
+        if non_empty {
+            lists.insert("space".to_string(), serde_json::json!(sd.space));
+            out.push(serde_json::Value::Object(lists));
+        }
+        if has_more {
+            break;
+        }
     }
+    let next = if has_more {
+        last.map(|last| last.to_cursor())
+    } else {
+        None
+    };
+    (serde_json::Value::Array(out), next)
 }
 
-// ---------------------------------------------------------------------------
-// Batch result helpers
-// ---------------------------------------------------------------------------
+/// Convert a merge conflict to JSON, optionally with the target (pre-merge)
+/// and source values and versions taken from the branch diff.
+fn merge_conflict_to_js(
+    space: String,
+    key: String,
+    entry: Option<BranchDiffEntry>,
+    include_values: bool,
+) -> serde_json::Value {
+    let mut obj = serde_json::json!({ "key": key, "space": space });
+    if include_values {
+        if let Some(e) = entry {
+            obj["primitive"] = serde_json::json!(format!("{:?}", e.primitive).to_lowercase());
+            obj["targetValue"] = e.value_a.map(value_to_js).unwrap_or_default();
+            obj["targetVersion"] = serde_json::json!(e.version_a);
+            obj["sourceValue"] = e.value_b.map(value_to_js).unwrap_or_default();
+            obj["sourceVersion"] = serde_json::json!(e.version_b);
+        }
+    }
+    obj
+}
 
-fn batch_results_to_js(results: Vec<BatchItemResult>) -> serde_json::Value {
-    let arr: Vec<serde_json::Value> = results
+/// The fork point of `target` and `source`: their nearest common ancestor
+/// and the time the earlier of the two lines left it, or `None` if the
+/// branches share no history.
+fn merge_base(db: &RustStrata, target: &str, source: &str) -> napi::Result<Option<(String, u64)>> {
+    let branches = db.branch_list(None, None, None).map_err(to_napi_err)?;
+    let parents: HashMap<String, (Option<String>, u64)> = branches
         .into_iter()
-        .map(|r| {
-            serde_json::json!({
-                "version": r.version.map(|v| v as i64),
-                "error": r.error,
-            })
+        .map(|b| {
+            let parent = b.info.parent_id.map(|p| p.as_str().to_string());
+            (b.info.id.as_str().to_string(), (parent, b.info.created_at))
         })
         .collect();
-    serde_json::Value::Array(arr)
+    // Each ancestor of `branch` (itself included) with the time the line
+    // to `branch` left it; `branch` itself is followed to the present.
+    let ancestry = |branch: &str| {
+        let mut out = vec![(branch.to_string(), u64::MAX)];
+        let mut current = branch.to_string();
+        while let Some((Some(parent), forked_at)) = parents.get(&current) {
+            if out.iter().any(|(b, _)| b == parent) {
+                break;
+            }
+            out.push((parent.clone(), *forked_at));
+            current = parent.clone();
+        }
+        out
+    };
+    let source_line = ancestry(source);
+    for (ancestor, t) in ancestry(target) {
+        if let Some((_, s)) = source_line.iter().find(|(b, _)| *b == ancestor) {
+            let at = t.min(*s);
+            return Ok((at != u64::MAX).then_some((ancestor, at)));
+        }
+    }
+    Ok(None)
 }
 
-fn batch_get_results_to_js(results: Vec<BatchGetItemResult>) -> serde_json::Value {
-    let arr: Vec<serde_json::Value> = results
-        .into_iter()
-        .map(|r| {
-            serde_json::json!({
-                "value": r.value.map(value_to_js),
-                "version": r.version.map(|v| v as i64),
-                "timestamp": r.timestamp.map(|t| t as i64),
-                "error": r.error,
-            })
-        })
-        .collect();
-    serde_json::Value::Array(arr)
+/// The value of `key` in the handle's current branch and space as of
+/// `as_of`, for the primitive named as in a diff entry.
+fn read_entry_as_of(
+    db: &RustStrata,
+    primitive: &str,
+    key: &str,
+    as_of: u64,
+) -> napi::Result<Option<serde_json::Value>> {
+    let value = match primitive {
+        "kv" => db.kv_get_as_of(key, Some(as_of)),
+        "state" => db.state_get_as_of(key, Some(as_of)),
+        "json" => db.json_get_as_of(key, "$", Some(as_of)),
+        _ => return Ok(None),
+    }
+    .map_err(to_napi_err)?;
+    Ok(value.map(value_to_js))
 }
 
-// ---------------------------------------------------------------------------
-// Top-level functions
-// ---------------------------------------------------------------------------
+/// Build a merge result from the target→source diff without applying it.
+///
+/// Keys that differ are compared with their value at the fork point, as
+/// the real merge does: a key changed only on the source would be applied,
+/// one changed only on the target is left alone, and one changed on both
+/// sides is a conflict. Branches with no common history treat every
+/// differing key as a conflict. Under the strict strategy any conflict
+/// would abort the real merge, which `wouldFail` reflects.
+fn merge_preview_to_js(
+    db: &RustStrata,
+    target: &str,
+    source: &str,
+    spaces: Vec<SpaceDiff>,
+    strategy: MergeStrategy,
+    include_values: bool,
+) -> napi::Result<serde_json::Value> {
+    let base = merge_base(db, target, source)?;
+    let mut view = detached_handle(db)?;
+    if let Some((branch, _)) = &base {
+        view.set_branch(branch).map_err(to_napi_err)?;
+    }
+    let mut keys_applied = 0usize;
+    let mut conflicts = Vec::new();
+    let mut effects = Vec::new();
+    for sd in spaces {
+        let added = sd.added.len();
+        let mut modified = 0usize;
+        let in_base = match &base {
+            Some(_) => view.space_exists(&sd.space).map_err(to_napi_err)?,
+            None => false,
+        };
+        if in_base {
+            view.set_space(&sd.space).map_err(to_napi_err)?;
+        }
+        let base_at = base.as_ref().filter(|_| in_base).map(|(_, at)| *at);
+        for e in sd.modified {
+            if let Some(at) = base_at {
+                let primitive = format!("{:?}", e.primitive).to_lowercase();
+                let was = read_entry_as_of(&view, &primitive, &e.key, at)?;
+                let ours = e.value_a.clone().map(value_to_js);
+                let theirs = e.value_b.clone().map(value_to_js);
+                if was == theirs {
+                    continue;
+                }
+                if was == ours {
+                    modified += 1;
+                    continue;
+                }
+            }
+            modified += 1;
+            let key = e.key.clone();
+            conflicts.push(merge_conflict_to_js(
+                sd.space.clone(),
+                key,
+                Some(e),
+                include_values,
+            ));
+        }
+        if added + modified == 0 {
+            continue;
+        }
+        keys_applied += added + modified;
+        effects.push(serde_json::json!({
+            "space": sd.space,
+            "keysAdded": added,
+            "keysModified": modified,
+        }));
+    }
+    let would_fail = matches!(strategy, MergeStrategy::Strict) && !conflicts.is_empty();
+    Ok(serde_json::json!({
+        "keysApplied": keys_applied,
+        "spacesMerged": effects.len(),
+        "conflicts": conflicts,
+        "dryRun": true,
+        "wouldFail": would_fail,
+        "spaces": effects,
+    }))
+}
 
-/// Download model files for auto-embedding.
-#[napi]
-pub fn setup() -> napi::Result<String> {
-    #[cfg(feature = "embed")]
-    {
-        let path = strata_intelligence::embed::download::ensure_model()
-            .map_err(napi::Error::from_reason)?;
-        Ok(path.to_string_lossy().into_owned())
+const BUNDLE_PRIMITIVES: &[&str] = &["kv", "state", "json", "event", "vector", "graph"];
+
+fn bundle_export_options(o: JsBundleExportOptions) -> napi::Result<BundleExportOptions> {
+    for p in o.primitives.iter().flatten() {
+        if !BUNDLE_PRIMITIVES.contains(&p.as_str()) {
+            return Err(napi::Error::from_reason(format!(
+                "[VALIDATION] Invalid bundle primitive: {}",
+                p
+            )));
+        }
     }
+    Ok(BundleExportOptions {
+        spaces: o.spaces,
+        key_prefixes: o.prefixes,
+        primitives: o.primitives,
+        include_history: o.include_history.unwrap_or(true),
+        since_version: None,
+        encryption: o.encryption.map(bundle_encryption).transpose()?,
+        progress: None,
+    })
+}
 
-    #[cfg(not(feature = "embed"))]
-    {
-        Err(napi::Error::from_reason(
-            "The 'embed' feature is not enabled in this build",
-        ))
+/// Import a full bundle into a fresh staging branch, recording its name in
+/// `staged` so the caller can remove it.
+fn stage_bundle(
+    db: &RustStrata,
+    path: &str,
+    encryption: &Option<BundleEncryption>,
+    staged: &mut Vec<String>,
+) -> napi::Result<String> {
+    let mut options = BundleImportOptions {
+        encryption: encryption.clone(),
+        destination_branch: None,
+        progress: None,
+    };
+    let header = db
+        .branch_validate_bundle_with_options(path, &options)
+        .map_err(to_napi_err)?;
+    if header.since_version.is_some() {
+        return Err(napi::Error::from_reason(format!(
+            "[VALIDATION] '{}' is a delta bundle and cannot be diffed on its own",
+            path
+        )));
+    }
+    let staging = bundle_staging_branch();
+    options.destination_branch = Some(staging.clone());
+    db.branch_import_with_options(path, options)
+        .map_err(to_napi_err)?;
+    staged.push(staging.clone());
+    Ok(staging)
+}
+
+/// JS callback receiving `{ entriesDone, bytesDone }` during bundle operations.
+type ProgressCallback = ThreadsafeFunction<serde_json::Value, ErrorStrategy::Fatal>;
+
+/// Cancellation flags of in-flight cancellable operations, by operation id.
+fn operations() -> &'static Mutex<HashMap<u32, Arc<AtomicBool>>> {
+    static OPS: OnceLock<Mutex<HashMap<u32, Arc<AtomicBool>>>> = OnceLock::new();
+    OPS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// The cancellation flag for `id`. A cancel that arrives before the
+/// operation starts creates the flag already set.
+fn cancel_flag(id: u32) -> Arc<AtomicBool> {
+    let mut ops = operations().lock().unwrap_or_else(|e| e.into_inner());
+    ops.entry(id).or_default().clone()
+}
+
+/// Registration of a cancellable operation; dropping it forgets the
+/// operation's flag.
+struct CancellableOp {
+    id: Option<u32>,
+    cancelled: Option<Arc<AtomicBool>>,
+}
+
+impl CancellableOp {
+    fn start(id: Option<u32>) -> Self {
+        CancellableOp {
+            id,
+            cancelled: id.map(cancel_flag),
+        }
+    }
+
+    /// Fail if the operation has been cancelled. Called between steps the
+    /// binding controls, so work still queued for a thread never starts.
+    fn check(&self) -> napi::Result<()> {
+        match &self.cancelled {
+            Some(c) if c.load(Ordering::Relaxed) => {
+                Err(napi::Error::from_reason("[STATE] Operation cancelled"))
+            }
+            _ => Ok(()),
+        }
     }
 }
 
-// ---------------------------------------------------------------------------
-// Conversion helpers (free functions)
-// ---------------------------------------------------------------------------
+impl Drop for CancellableOp {
+    fn drop(&mut self) {
+        if let Some(id) = self.id {
+            let mut ops = operations().lock().unwrap_or_else(|e| e.into_inner());
+            ops.remove(&id);
+        }
+    }
+}
 
-fn collection_info_to_js(c: CollectionInfo) -> serde_json::Value {
-    serde_json::json!({
-        "name": c.name,
-        "dimension": c.dimension,
-        "metric": format!("{:?}", c.metric).to_lowercase(),
-        "count": c.count,
-        "indexType": c.index_type,
-        "memoryBytes": c.memory_bytes,
-    })
+/// Engine progress hook for a bundle operation: reports to `on_progress`
+/// and stops the operation once it is cancelled.
+fn bundle_progress(
+    on_progress: Option<ProgressCallback>,
+    op: &CancellableOp,
+) -> Option<BundleProgressFn> {
+    if on_progress.is_none() && op.cancelled.is_none() {
+        return None;
+    }
+    let cancelled = op.cancelled.clone();
+    Some(Arc::new(move |p: BundleProgress| {
+        if let Some(cb) = &on_progress {
+            cb.call(
+                serde_json::json!({
+                    "entriesDone": p.entries_done,
+                    "bytesDone": p.bytes_done,
+                }),
+                ThreadsafeFunctionCallMode::NonBlocking,
+            );
+        }
+        !cancelled
+            .as_ref()
+            .is_some_and(|c| c.load(Ordering::Relaxed))
+    }))
 }
 
-fn versioned_branch_info_to_js(info: VersionedBranchInfo) -> serde_json::Value {
-    serde_json::json!({
-        "id": info.info.id.as_str(),
-        "status": format!("{:?}", info.info.status).to_lowercase(),
-        "createdAt": info.info.created_at,
-        "updatedAt": info.info.updated_at,
-        "parentId": info.info.parent_id.map(|p| p.as_str().to_string()),
-        "version": info.version,
-        "timestamp": info.timestamp,
+/// How `branchImport` treats a destination branch that already exists.
+#[derive(Clone, Copy)]
+enum ImportConflict {
+    Fail,
+    Replace,
+    Merge,
+}
+
+fn bundle_import_options(
+    o: Option<JsBundleImportOptions>,
+) -> napi::Result<(BundleImportOptions, ImportConflict)> {
+    let Some(o) = o else {
+        let options = BundleImportOptions {
+            encryption: None,
+            destination_branch: None,
+            progress: None,
+        };
+        return Ok((options, ImportConflict::Fail));
+    };
+    let on_conflict = match o.on_conflict.as_deref().unwrap_or("fail") {
+        "fail" => ImportConflict::Fail,
+        "replace" => ImportConflict::Replace,
+        "merge" => ImportConflict::Merge,
+        other => {
+            return Err(napi::Error::from_reason(format!(
+                "[VALIDATION] Invalid onConflict: {}",
+                other
+            )))
+        }
+    };
+    if o.destination_branch.as_deref() == Some("") {
+        return Err(napi::Error::from_reason(
+            "[VALIDATION] destinationBranch must not be empty",
+        ));
+    }
+    let options = BundleImportOptions {
+        encryption: o.encryption.map(bundle_encryption).transpose()?,
+        destination_branch: o.destination_branch,
+        progress: None,
+    };
+    Ok((options, on_conflict))
+}
+
+fn bundle_encryption(e: JsBundleEncryption) -> napi::Result<BundleEncryption> {
+    let secret = match (e.key, e.passphrase) {
+        (Some(key), None) => {
+            if key.len() != 32 {
+                return Err(napi::Error::from_reason(format!(
+                    "[VALIDATION] Encryption key must be 32 bytes, got {}",
+                    key.len()
+                )));
+            }
+            BundleSecret::Key(key.to_vec())
+        }
+        (None, Some(passphrase)) if !passphrase.is_empty() => BundleSecret::Passphrase(passphrase),
+        _ => {
+            return Err(napi::Error::from_reason(
+                "[VALIDATION] Give exactly one of encryption.key or encryption.passphrase",
+            ))
+        }
+    };
+    Ok(BundleEncryption {
+        secret,
+        key_id: e.key_id,
     })
 }
 
+/// Export a whole branch, or the filtered subset described by `options`.
+fn export_bundle(
+    db: &RustStrata,
+    branch: &str,
+    path: &str,
+    options: Option<BundleExportOptions>,
+) -> napi::Result<BranchExportResult> {
+    match options {
+        Some(opts) => db.branch_export_with_options(branch, path, opts),
+        None => db.branch_export(branch, path),
+    }
+    .map_err(to_napi_err)
+}
+
+/// Import a full bundle as a new branch, or apply a delta bundle to the
+/// existing branch it was exported from.
+///
+/// The destination is `options.destination_branch` if set, otherwise the
+/// branch recorded in the bundle. An existing destination is handled per
+/// `on_conflict`; merging goes through a staging branch that is removed
+/// afterwards.
+fn import_bundle(
+    db: &RustStrata,
+    path: &str,
+    mut options: BundleImportOptions,
+    on_conflict: ImportConflict,
+) -> napi::Result<BranchImportResult> {
+    let header = db
+        .branch_validate_bundle_with_options(path, &options)
+        .map_err(to_napi_err)?;
+    let dest = options
+        .destination_branch
+        .clone()
+        .unwrap_or_else(|| header.branch_id.clone());
+    let exists = db.branches().exists(&dest).map_err(to_napi_err)?;
+
+    if header.since_version.is_some() {
+        if !exists {
+            return Err(napi::Error::from_reason(format!(
+                "[NOT_FOUND] Delta bundle targets branch '{}', which does not exist",
+                dest
+            )));
+        }
+        ensure_branch_writable(db, &dest, false)?;
+        return db.branch_import_delta(path, options).map_err(to_napi_err);
+    }
+    if !exists {
+        return db
+            .branch_import_with_options(path, options)
+            .map_err(to_napi_err);
+    }
+
+    match on_conflict {
+        ImportConflict::Fail => Err(napi::Error::from_reason(format!(
+            "[STATE] Branch '{}' already exists; pass onConflict 'replace' or 'merge'",
+            dest
+        ))),
+        ImportConflict::Replace => {
+            ensure_branch_writable(db, &dest, false)?;
+            db.delete_branch(&dest).map_err(to_napi_err)?;
+            db.branch_import_with_options(path, options)
+                .map_err(to_napi_err)
+        }
+        ImportConflict::Merge => {
+            ensure_branch_writable(db, &dest, true)?;
+            let staging = bundle_staging_branch();
+            options.destination_branch = Some(staging.clone());
+            let imported = db
+                .branch_import_with_options(path, options)
+                .map_err(to_napi_err);
+            let merged = imported.and_then(|mut result| {
+                db.merge_branches(&staging, &dest, MergeStrategy::LastWriterWins)
+                    .map_err(to_napi_err)?;
+                result.branch_id = dest.clone();
+                Ok(result)
+            });
+            if db.branches().exists(&staging).unwrap_or(false) {
+                db.delete_branch(&staging).map_err(to_napi_err)?;
+            }
+            merged
+        }
+    }
+}
+
+/// A fresh name for a hidden branch a bundle is staged in.
+fn bundle_staging_branch() -> String {
+    static NEXT: AtomicU64 = AtomicU64::new(0);
+    let n = NEXT.fetch_add(1, Ordering::Relaxed);
+    format!("_import-{}-{}", std::process::id(), n)
+}
+
 fn branch_export_result_to_js(r: BranchExportResult) -> serde_json::Value {
     serde_json::json!({
         "branchId": r.branch_id,
@@ -3916,6 +13416,9 @@ fn bundle_validate_result_to_js(r: BundleValidateResult) -> serde_json::Value {
         "formatVersion": r.format_version,
         "entryCount": r.entry_count,
         "checksumsValid": r.checksums_valid,
+        "sinceVersion": r.since_version,
+        "encrypted": r.encrypted,
+        "keyId": r.key_id,
     })
 }
 
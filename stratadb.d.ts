@@ -2,9 +2,13 @@
  * TypeScript definitions for StrataDB Node.js SDK
  *
  * All data methods are async and return Promises. Factory methods
- * (Strata.open, Strata.cache) remain synchronous.
+ * (Strata.open, Strata.cache) remain synchronous; Strata.openAsync opens
+ * off the event loop.
  */
 
+import type { Readable, Writable } from 'stream';
+import type { ConnectionOptions, TlsOptions } from 'tls';
+
 // =========================================================================
 // Error classes
 // =========================================================================
@@ -17,10 +21,25 @@ export class StrataError extends Error {
 export class NotFoundError extends StrataError {}
 export class ValidationError extends StrataError {}
 export class ConflictError extends StrataError {}
-export class StateError extends StrataError {}
+export class StateError extends StrataError {
+  /** For an open refused by another process's writer lock, the holder. */
+  lockHolder?: LockHolder;
+}
+/** The process holding a database's writer lock. */
+export interface LockHolder {
+  pid: number;
+  /** When it took the lock, in microseconds since the epoch. */
+  since: number;
+}
 export class ConstraintError extends StrataError {}
 export class AccessDeniedError extends StrataError {}
 export class IoError extends StrataError {}
+/** The handle's `maxConcurrentOps` queue is full. */
+export class ResourceExhaustedError extends StrataError {}
+/** A call ran past its `timeoutMs`. */
+export class TimeoutError extends StrataError {}
+/** The handle (or remote connection) was closed. */
+export class StrataClosedError extends StrataError {}
 
 // =========================================================================
 // Value types
@@ -35,11 +54,63 @@ export type JsonValue =
   | JsonValue[]
   | { [key: string]: JsonValue };
 
+/** Who made a KV write and why; see `KvWriteOptions` */
+export interface WriteAnnotation {
+  author?: string;
+  message?: string;
+  requestId?: string;
+}
+
 /** Versioned value returned by history operations */
 export interface VersionedValue {
   value: JsonValue;
   version: number;
   timestamp: number;
+  /** Set on KV versions written with an author, message, or request id. */
+  annotation?: WriteAnnotation;
+}
+
+/** Filters and paging for `history` */
+export interface HistoryOptions {
+  limit?: number;
+  /** Only versions older than this version. */
+  beforeVersion?: number;
+  /** Only versions written at or after this timestamp or tag. */
+  fromTs?: AsOf;
+  /** Only versions written at or before this timestamp or tag. */
+  toTs?: AsOf;
+  /** Newest first (default) or oldest first. */
+  order?: 'asc' | 'desc';
+  /** Cursor returned by a previous page. */
+  cursor?: string;
+}
+
+/** One page of a version history */
+export interface HistoryPage {
+  versions: VersionedValue[];
+  /** Pass back as `cursor` for the next page; `null` on the last page. */
+  cursor: string | null;
+}
+
+/** A JSON Patch style change between two versions of a value */
+export interface PatchOperation {
+  op: 'add' | 'remove' | 'replace';
+  /** JSON Pointer to the changed location (`""` for the whole value). */
+  path: string;
+  /** New value, for `add` and `replace`. */
+  value?: JsonValue;
+}
+
+/** One version in a key timeline */
+export interface TimelineEntry extends VersionedValue {
+  /** Changes from the previous version (an `add` at `""` for the first). */
+  diff: PatchOperation[];
+}
+
+/** Time bounds for `kv.timeline`; each bound may be a timestamp or tag */
+export interface TimelineOptions {
+  from?: AsOf;
+  to?: AsOf;
 }
 
 /** JSON list result with pagination cursor */
@@ -49,6 +120,22 @@ export interface JsonListResult {
   hasMore: boolean;
 }
 
+/** Spaces to query: a list of names, or `'*'` for every space */
+export type SpaceSelector = string[] | '*';
+
+/** A key tagged with the space it was found in */
+export interface SpaceKey {
+  space: string;
+  key: string;
+}
+
+/** JSON list result across spaces */
+export interface JsonSpaceListResult {
+  keys: SpaceKey[];
+  cursor: null;
+  hasMore: boolean;
+}
+
 /** KV list result with pagination metadata (returned by kvListPaginated) */
 export interface KvListResult {
   keys: string[];
@@ -80,6 +167,8 @@ export interface SearchMatch {
   key: string;
   score: number;
   metadata?: JsonValue;
+  /** Set when searching with `spaces`. */
+  space?: string;
 }
 
 /** Fork operation result */
@@ -87,6 +176,8 @@ export interface ForkResult {
   source: string;
   destination: string;
   keysCopied: number;
+  /** Set when forked with `asOf`. */
+  asOf?: number;
 }
 
 /** Branch diff summary */
@@ -96,17 +187,120 @@ export interface DiffSummary {
   totalModified: number;
 }
 
+/** A single key in a detailed branch diff */
+export interface DiffEntry {
+  key: string;
+  primitive: string;
+  /** Value in branch A (modified keys, `values: "values"` only). */
+  valueA?: JsonValue;
+  /** Value in branch B (modified keys, `values: "values"` only). */
+  valueB?: JsonValue;
+  /** Hash of the value in branch A (modified keys, `values: "hashes"` only). */
+  hashA?: string | null;
+  /** Hash of the value in branch B (modified keys, `values: "hashes"` only). */
+  hashB?: string | null;
+  /** Annotation of the key's latest write in branch A (KV keys only). */
+  annotationA?: WriteAnnotation;
+  /** Annotation of the key's latest write in branch B (KV keys only). */
+  annotationB?: WriteAnnotation;
+}
+
+/** Per-space key lists in a detailed branch diff */
+export interface SpaceDiff {
+  space: string;
+  added: DiffEntry[];
+  removed: DiffEntry[];
+  modified: DiffEntry[];
+}
+
 /** Branch diff result */
 export interface DiffResult {
   branchA: string;
   branchB: string;
   summary: DiffSummary;
+  /** Present when `detailed: true`. */
+  spaces?: SpaceDiff[];
+  /** Present when `detailed: true`. */
+  hasMore?: boolean;
+  /** Cursor for the next page, when `detailed: true`. */
+  cursor?: string | null;
+}
+
+/** Options for branch diff */
+export interface DiffOptions {
+  /** Include per-space key lists, not just summary counts. */
+  detailed?: boolean;
+  /** For modified keys: include both values, value hashes, or neither. */
+  values?: 'none' | 'values' | 'hashes';
+  /** Maximum number of key entries per page; at least 1. */
+  limit?: number;
+  /**
+   * Cursor returned by a previous page. Entries are ordered by space, kind,
+   * primitive, and key, and the cursor names the last one of its page, so
+   * the next page resumes after that key even if the branches have changed.
+   */
+  cursor?: string;
+  /** Abort the diff; the call then rejects with `signal.reason`. */
+  signal?: AbortSignal;
+  /** Cancel the diff and reject with `TimeoutError` after this many ms. */
+  timeoutMs?: number;
+}
+
+/** Options for `diffTime` */
+export interface DiffTimeOptions {
+  /** Only diff this space (default: every space on the branch). */
+  space?: string;
+  /** Only report keys starting with this prefix. */
+  prefix?: string;
+}
+
+/** Changes to the current branch between two timestamps */
+export interface DiffTimeResult {
+  fromTs: number;
+  toTs: number;
+  summary: DiffSummary;
+  /** Spaces with at least one change. */
+  spaces: SpaceDiff[];
+}
+
+/** Options for comparing bundles */
+export interface BundleDiffOptions extends DiffOptions {
+  /** Key material for encrypted bundles. */
+  encryption?: BundleEncryption;
 }
 
 /** Merge conflict */
 export interface MergeConflict {
   key: string;
   space: string;
+  /** Present when `includeValues` or `resolve` is used. */
+  primitive?: string;
+  /** Target branch value before the merge. */
+  targetValue?: JsonValue;
+  targetVersion?: number | null;
+  /** Source branch value. */
+  sourceValue?: JsonValue;
+  sourceVersion?: number | null;
+  /** Value written by the `resolve` callback. */
+  resolvedValue?: JsonValue;
+  /** Staged conflicts only: the merge's target and source branches. */
+  branch?: string;
+  source?: string;
+}
+
+/**
+ * Conflict resolver for merges. Return the value to keep on the target
+ * branch, or `undefined` to keep the target's pre-merge value.
+ */
+export type MergeConflictResolver = (
+  conflict: MergeConflict,
+) => JsonValue | undefined | Promise<JsonValue | undefined>;
+
+/** Per-space effect of a dry-run merge */
+export interface MergeSpaceEffect {
+  space: string;
+  keysAdded: number;
+  keysModified: number;
 }
 
 /** Merge operation result */
@@ -114,6 +308,12 @@ export interface MergeResult {
   keysApplied: number;
   spacesMerged: number;
   conflicts: MergeConflict[];
+  /** Present (true) for dry-run merges. */
+  dryRun?: boolean;
+  /** Dry-run only: whether a strict merge would abort on conflicts. */
+  wouldFail?: boolean;
+  /** Dry-run only: per-space effects. */
+  spaces?: MergeSpaceEffect[];
 }
 
 /** Database information */
@@ -122,6 +322,17 @@ export interface DatabaseInfo {
   uptimeSecs: number;
   branchCount: number;
   totalKeys: number;
+  /**
+   * Bytes the database occupies on disk, when requested with
+   * `{ diskBytes: true }`; null otherwise and for in-memory databases.
+   */
+  diskBytes: number | null;
+}
+
+/** Options for `info()` */
+export interface InfoOptions {
+  /** Also report `diskBytes`. Walks the whole database directory. */
+  diskBytes?: boolean;
 }
 
 /** Structured database snapshot for agent introspection. */
@@ -190,6 +401,297 @@ export interface BranchInfo {
   parentId?: string;
   version: number;
   timestamp: number;
+  description: string | null;
+  labels: string[];
+  /** Rejects deletion and direct writes; merges are still allowed. */
+  protected: boolean;
+  /** Read-only and hidden from `list()` by default. */
+  archived: boolean;
+}
+
+/** Entry counts and estimated size */
+export interface PrimitiveCounts {
+  kv: number;
+  state: number;
+  json: number;
+  events: number;
+  vectors: number;
+  collections: number;
+  graphs: number;
+  /** Keys plus encoded values for KV/state/JSON, plus vector index memory. */
+  estimatedBytes: number;
+}
+
+/** Per-branch statistics */
+export interface BranchStats extends PrimitiveCounts {
+  branch: string;
+  spaces: number;
+  /** Latest write timestamp (microseconds), or null if empty. */
+  lastWriteAt: number | null;
+}
+
+/** One branch in the fork graph */
+export interface BranchLineageNode {
+  id: string;
+  parentId: string | null;
+  status: string;
+  /** Fork timestamp (microseconds), or null for root branches. */
+  forkedAt: number | null;
+  createdAt: number;
+  /** Changes on this branch relative to its parent, or null for roots. */
+  divergence: { added: number; removed: number; modified: number } | null;
+}
+
+/** Branch fork graph */
+export interface BranchLineage {
+  branches: BranchLineageNode[];
+}
+
+/** Options for `branch.lineage()` */
+export interface BranchLineageOptions {
+  /** Compute divergence counts (default: true). */
+  divergence?: boolean;
+}
+
+export type RetentionPrimitive = 'kv' | 'state' | 'json' | 'event' | 'vector';
+
+/** Retention rule; omitted `space`/`primitive` means "every". */
+export interface RetentionRule {
+  space?: string | null;
+  primitive?: RetentionPrimitive | null;
+  /** Keep at most this many versions per key. */
+  maxVersions?: number | null;
+  /** Drop versions older than this many seconds. */
+  maxAge?: number | null;
+  /** Keep versions visible at any tagged timestamp. */
+  keepTags?: boolean;
+}
+
+/**
+ * History kept by `retentionApply`: every version of a key, or the versions
+ * visible at a tag or timestamp. Set exactly one of `key`, `tag`, `timestamp`.
+ */
+export interface RetentionPin {
+  key?: string;
+  /** Space of `key` (default: the current space). */
+  space?: string;
+  /** Primitive of `key` (default: `'kv'`). */
+  primitive?: 'kv' | 'state' | 'json';
+  tag?: string;
+  timestamp?: number;
+}
+
+/** What `retentionApply` removed */
+export interface RetentionReport {
+  versionsRemoved: Record<RetentionPrimitive, number>;
+  bytesReclaimed: number;
+  /** Oldest surviving timestamp of each trimmed space (`null` if emptied). */
+  oldestTimestamps: Record<string, number | null>;
+  durationMs: number;
+}
+
+/** Options for `historyTrim`; set `before`, `keepLast`, or both */
+export interface HistoryTrimOptions {
+  /** Drop versions written before this timestamp or tag. */
+  before?: AsOf;
+  /** Keep at most this many versions per key. */
+  keepLast?: number;
+  space?: string;
+  primitive?: RetentionPrimitive;
+}
+
+/** Named point in time */
+export interface Tag {
+  name: string;
+  /** Tagged timestamp (microseconds since epoch). */
+  timestamp: number;
+  /** Branch that was current when the tag was created. */
+  branch: string;
+  createdAt: number;
+}
+
+/** A message delivered by `queuePop()` */
+export interface QueueMessage {
+  /** Pass to `queueAck()`. Each delivery of a message has its own id. */
+  id: string;
+  item: JsonValue;
+  /** Deliveries so far, including this one. */
+  attempts: number;
+}
+
+/** Message counts from `queueStats()` */
+export interface QueueStats {
+  /** Waiting to be popped, including timed-out deliveries. */
+  ready: number;
+  /** Popped, and neither acknowledged nor timed out. */
+  inFlight: number;
+  /** Every message ever pushed. */
+  pushed: number;
+}
+
+/** A blob, from `blobStat()` and `blobPut()` */
+export interface BlobStat {
+  /** Length in bytes. */
+  size: number;
+  /** Number of chunks it is stored in. */
+  chunks: number;
+  /** Id of the upload that wrote the current content. */
+  upload: string;
+  /** When it was last written (microseconds since epoch). */
+  timestamp: number;
+}
+
+/** An edge from `edgeList()` and `traverse()` */
+export interface Edge {
+  from: string;
+  relation: string;
+  to: string;
+  /** What `edgeAdd()` stored, or null. */
+  properties: JsonValue;
+}
+
+/** Options for `edgeList()` */
+export interface EdgeListOptions {
+  /** Which edges of the node (default: 'outgoing'). */
+  direction?: 'outgoing' | 'incoming' | 'both';
+  /** Only edges with this relation. */
+  relation?: string;
+  /** Read the edges as of this timestamp (microseconds), Date, or tag. */
+  asOf?: number | Date | string;
+}
+
+/** Options for `traverse()` */
+export interface TraverseOptions extends EdgeListOptions {
+  /** Hops to follow from the start node (default: 1). */
+  depth?: number;
+  /** Most nodes to visit, counting the start node (default: 1,000). */
+  limit?: number;
+}
+
+/** Result of `traverse()` */
+export interface TraverseResult {
+  /** Nodes in the order reached, starting with the start node. */
+  visited: string[];
+  /** Hops from the start node to each visited node. */
+  depths: Record<string, number>;
+  /** The edges followed, each once. */
+  edges: Edge[];
+}
+
+/** A point from `tsQuery()` */
+export interface TimeSeriesPoint {
+  /** Microseconds since epoch. */
+  timestamp: number;
+  value: number;
+  tags: Record<string, string>;
+}
+
+/** A bucket from `tsQuery()` with `aggregate` */
+export interface TimeSeriesBucket {
+  /** Start of the bucket, or of the range without `bucket`. */
+  timestamp: number;
+  /** The aggregate of the bucket's points. */
+  value: number;
+  /** Number of points in the bucket. */
+  count: number;
+}
+
+/** Options for `tsQuery()` */
+export interface TimeSeriesQueryOptions {
+  /** Earliest timestamp to include (microseconds or Date, inclusive). */
+  from?: number | Date;
+  /** Latest timestamp to include (microseconds or Date, inclusive). */
+  to?: number | Date;
+  /** Only points carrying all of these tags. */
+  tags?: Record<string, string>;
+  /** Combine the points in each bucket. */
+  aggregate?: 'avg' | 'sum' | 'min' | 'max' | 'count' | 'first' | 'last';
+  /**
+   * Bucket width in microseconds, aligned to the epoch. Without it,
+   * `aggregate` combines the whole range.
+   */
+  bucket?: number;
+}
+
+/** One step of `migrate()` */
+export interface Migration {
+  /** Unique, stable name, recorded once the migration is applied. */
+  id: string;
+  up(tx: Strata): Promise<void>;
+  /** Undo `up()`. Required to revert the migration. */
+  down?(tx: Strata): Promise<void>;
+}
+
+/** Options for `migrate()` */
+export interface MigrateOptions {
+  /**
+   * Stop after this migration, reverting applied ones after it; `null`
+   * reverts them all. Defaults to the last one.
+   */
+  to?: string | null;
+  /** Report what would run without running it. */
+  dryRun?: boolean;
+}
+
+/** The migration ids `migrate()` ran, in the order it ran them */
+export interface MigrateResult {
+  applied: string[];
+  reverted: string[];
+}
+
+/** A conversation turn for `memoryAppend()`; `content` is embedded */
+export interface MemoryTurn {
+  content: string;
+  role?: string;
+  [field: string]: JsonValue | undefined;
+}
+
+/** A turn found by `memoryRecall()` */
+export interface MemoryMatch<T extends MemoryTurn = MemoryTurn> {
+  /** Sequence number of the turn's event. */
+  sequence: number;
+  timestamp: number;
+  score: number;
+  turn: T;
+}
+
+/** A span for `traceRecord()`; other fields are stored as given */
+export interface TraceSpan {
+  id: string;
+  name: string;
+  /** Id of a span already recorded in the same run. */
+  parentId?: string | null;
+  /** Orders siblings in `traceGet()`; spans without one come last. */
+  startTime?: number;
+  endTime?: number;
+  [field: string]: JsonValue | undefined;
+}
+
+/** A span in `traceGet()`, with the spans recorded under it */
+export type TraceSpanNode<T extends TraceSpan = TraceSpan> = T & { children: TraceSpanNode<T>[] };
+
+/** A run's trace from `traceGet()` */
+export interface Trace<T extends TraceSpan = TraceSpan> {
+  runId: string;
+  /** When `traceStart()` was called, in microseconds. */
+  startedAt: number;
+  metadata: JsonValue;
+  /** Spans without a parent, each with its descendants. */
+  spans: TraceSpanNode<T>[];
+}
+
+/** Options for `tagCreate()` */
+export interface TagCreateOptions {
+  /** Timestamp to tag (microseconds). Defaults to now. */
+  timestamp?: number;
+}
+
+/** Editable branch annotations */
+export interface BranchMetadata {
+  /** Free-form description. An empty string clears it. */
+  description?: string;
+  /** Labels, replacing any existing ones. */
+  labels?: string[];
 }
 
 /** Branch export result */
@@ -207,12 +709,249 @@ export interface BranchImportResult {
   keysWritten: number;
 }
 
+/** Progress reported during bundle export, import, and validation */
+export interface BundleProgress {
+  entriesDone: number;
+  bytesDone: number;
+}
+
+/** Progress and cancellation for long-running bundle operations */
+export interface BundleControlOptions {
+  onProgress?: (progress: BundleProgress) => void;
+  /** Abort the operation; the call then rejects with `signal.reason`. */
+  signal?: AbortSignal;
+  /** Cancel the operation and reject with `TimeoutError` after this many ms. */
+  timeoutMs?: number;
+}
+
+/** Options for exporting part of a branch */
+export interface BundleExportOptions extends BundleControlOptions {
+  /** Only export these spaces (default: every space). */
+  spaces?: string[];
+  /** Only export keys starting with one of these prefixes. */
+  prefixes?: string[];
+  /** Only export these primitives (default: all). */
+  primitives?: Array<'kv' | 'state' | 'json' | 'event' | 'vector' | 'graph'>;
+  /** Include version history (default: true). `false` exports latest versions only. */
+  includeHistory?: boolean;
+  /** Encrypt the bundle (AES-256-GCM). */
+  encryption?: BundleEncryption;
+}
+
+/** Key material for encrypted bundles. Give exactly one of `key` or `passphrase`. */
+export interface BundleEncryption {
+  /** Raw 32-byte key. */
+  key?: Buffer;
+  /** Passphrase; the key is derived from it with a per-bundle salt. */
+  passphrase?: string;
+  /** Recorded in the bundle header to identify the key (export only). */
+  keyId?: string;
+}
+
+/** Options for importing or validating a bundle */
+export interface BundleImportOptions extends BundleControlOptions {
+  /** Key material for an encrypted bundle. */
+  encryption?: BundleEncryption;
+  /** Import under this name instead of the branch recorded in the bundle. */
+  destinationBranch?: string;
+  /** If the destination exists: fail (default), replace it, or merge into it. */
+  onConflict?: 'fail' | 'replace' | 'merge';
+}
+
+/** Options for streaming a bundle export */
+export interface BundleExportStreamOptions
+  extends Omit<BundleExportOptions, keyof BundleControlOptions> {
+  /** Bytes per chunk (default: 65536). */
+  chunkSize?: number;
+}
+
 /** Bundle validation result */
 export interface BundleValidateResult {
   branchId: string;
   formatVersion: number;
   entryCount: number;
   checksumsValid: boolean;
+  /** Set for delta bundles: the version the delta starts after. */
+  sinceVersion: number | null;
+  encrypted: boolean;
+  /** Key id recorded at export, if any. */
+  keyId: string | null;
+}
+
+export interface BackupOptions {
+  /**
+   * Directory of an earlier backup; only the changes since it are saved,
+   * and restoring needs it too.
+   */
+  incrementalFrom?: string;
+}
+
+/** Result of `backup()` */
+export interface BackupResult {
+  path: string;
+  /** Database version the backup starts from; later writes may be included too. */
+  version: number;
+  incremental: boolean;
+  branches: number;
+  entryCount: number;
+}
+
+export interface RestoreOptions {
+  /**
+   * Rewind KV, state, and JSON data to this time (microseconds since the
+   * epoch) inside the backup. Nothing after the backup is replayed, so it
+   * must be before the newest backup in the chain was taken. Branches
+   * created later are left out; events, vectors, and graphs are restored
+   * as of the backup.
+   */
+  rewindTo?: number;
+}
+
+/** Result of `Strata.restore()` */
+export interface RestoreResult {
+  path: string;
+  /** `version` of the restored backup. */
+  version: number;
+  /** `rewindTo`, or `null` when the backup was restored as taken. */
+  rewoundTo: number | null;
+  /** Backups applied: 1, plus one per incremental step. */
+  backups: number;
+  branches: number;
+}
+
+/** Buffers writes for `bulkLoad()`; each call resolves once its entry is buffered or written. */
+export interface BulkLoader {
+  kvPut(key: string, value: JsonValue): Promise<void>;
+  stateSet(cell: string, value: JsonValue): Promise<void>;
+  /** Set a whole document. */
+  jsonSet(key: string, value: JsonValue): Promise<void>;
+  eventAppend(eventType: string, payload: JsonValue): Promise<void>;
+  vectorUpsert(collection: string, key: string, vector: number[], metadata?: JsonValue): Promise<void>;
+}
+
+export interface BulkLoadOptions {
+  /** Entries per batch, per primitive (and per collection, for vectors). Default: 5000. */
+  batchSize?: number;
+  /** Called after each batch is written. */
+  onProgress?: (progress: { entries: number; batches: number }) => void;
+}
+
+/** Result of `bulkLoad()` */
+export interface BulkLoadResult {
+  entries: number;
+  /** Entries the engine rejected. */
+  failed: number;
+  batches: number;
+  durationMs: number;
+}
+
+/** One write in a changelog. */
+export interface ChangeRecord {
+  /** Version the write got; `null` for deletes, which the engine keeps no version of. */
+  version: number | null;
+  branch: string;
+  space: string;
+  primitive: 'kv' | 'state' | 'json';
+  key: string;
+  op: 'put' | 'delete';
+  /** The written value; `null` for deletes. */
+  value: JsonValue;
+}
+
+/** Result of `changelogExport()` */
+export interface ChangelogExportResult {
+  /** Newest version exported; pass it as `sinceVersion` next time. */
+  version: number;
+  records: number;
+}
+
+/** Result of `changelogApply()` */
+export interface ChangelogApplyResult {
+  applied: number;
+  /** Newest version among the applied records, or `null` if none had one. */
+  version: number | null;
+}
+
+export interface SyncOptions {
+  /** Branches to sync. Default: every branch of the side(s) being copied from. */
+  branches?: string[];
+  /**
+   * `'push'` copies local changes to the other database, `'pull'` the
+   * other way, and `'two-way'` (the default) both.
+   */
+  mode?: 'push' | 'pull' | 'two-way';
+}
+
+/** Result of `syncTo()` */
+export interface SyncResult {
+  branches: number;
+  /** Entries written or deleted on the other database. */
+  pushed: number;
+  /** Entries written or deleted locally. */
+  pulled: number;
+  /** Entries changed on both sides since the last sync; neither is changed. */
+  conflicts: Array<{ branch: string; space: string; primitive: 'kv' | 'state' | 'json'; key: string }>;
+}
+
+export interface DumpOptions {
+  /** Only this space. Default: every space on the current branch. */
+  space?: string;
+  /** Only keys starting with this prefix. */
+  prefix?: string;
+  /** Only this primitive. Default: all three. */
+  primitive?: 'kv' | 'state' | 'json';
+  /** Called after each page of entries is written. */
+  onProgress?: (progress: { entries: number; space: string; primitive: string }) => void;
+}
+
+/** Result of `dump()` */
+export interface DumpResult {
+  entries: number;
+  spaces: number;
+}
+
+export interface LoadOptions {
+  /** Entries written per batch. Default: 1000. */
+  batchSize?: number;
+  /** Called after each batch is written. */
+  onProgress?: (progress: { entries: number; line: number }) => void;
+}
+
+/** Result of `load()` */
+export interface LoadResult {
+  entries: number;
+  /** Lines read, blank ones included. */
+  lines: number;
+}
+
+/**
+ * Data for `Strata.fromFixture()`, and what `toFixture()` captures. Buffers
+ * are written as `{ $base64: '...' }`.
+ */
+export interface Fixture {
+  kv?: Record<string, JsonValue>;
+  state?: Record<string, JsonValue>;
+  json?: Record<string, JsonValue>;
+  /** Appended in order. */
+  events?: Array<{ type: string; payload: JsonValue }>;
+  /** Collections by name, created before their entries are written. */
+  vectors?: Record<string, {
+    dimension: number;
+    metric?: DistanceMetric | `${DistanceMetric}`;
+    entries?: BatchVectorEntry[];
+  }>;
+}
+
+/** Options for `Strata.temp()` */
+export interface TempOptions extends OpenOptions {
+  /** Keep the directory after the handle closes, and warn with its path. */
+  persistToDiskForDebug?: boolean;
+}
+
+/** Options for `toFixture()` */
+export interface ToFixtureOptions {
+  /** Event types to capture; the engine keeps no list of them. */
+  eventTypes?: string[];
 }
 
 /** Vector entry for batch upsert */
@@ -229,11 +968,65 @@ export interface TransactionInfo {
   startedAt: number;
 }
 
-/** Metadata filter for vector search */
+/** Distance metrics for vector collections; methods also take the strings. */
+export enum DistanceMetric {
+  Cosine = 'cosine',
+  Euclidean = 'euclidean',
+  DotProduct = 'dot_product',
+}
+
+/** Branch merge strategies */
+export enum MergeStrategy {
+  LastWriterWins = 'last_writer_wins',
+  Strict = 'strict',
+  /** Merge the rest, keep the target's side of conflicts, and stage them. */
+  Manual = 'manual',
+}
+
+/** Filter operators for vector search metadata and JSON queries */
+export enum FilterOp {
+  Eq = 'eq',
+  Ne = 'ne',
+  Gt = 'gt',
+  Gte = 'gte',
+  Lt = 'lt',
+  Lte = 'lte',
+  In = 'in',
+  Contains = 'contains',
+  /** The field holds a `GeoPoint` within `radius` meters of the value's point. */
+  WithinRadius = 'withinRadius',
+  /** The field holds a `GeoPoint` inside the value's box. */
+  BoundingBox = 'boundingBox',
+}
+
+/** A location stored in vector metadata or a JSON document */
+export interface GeoPoint {
+  lat: number;
+  lon: number;
+}
+
+/** Value of a `withinRadius` filter; `radius` is in meters */
+export interface GeoRadius extends GeoPoint {
+  radius: number;
+}
+
+/**
+ * Value of a `boundingBox` filter. A box with `minLon` greater than
+ * `maxLon` crosses the antimeridian.
+ */
+export interface GeoBoundingBox {
+  minLat: number;
+  minLon: number;
+  maxLat: number;
+  maxLon: number;
+}
+
+/** Filter for vector search metadata and JSON queries */
 export interface MetadataFilter {
   field: string;
-  op: 'eq' | 'ne' | 'gt' | 'gte' | 'lt' | 'lte' | 'in' | 'contains';
-  value: JsonValue;
+  op: FilterOp | `${FilterOp}`;
+  /** A `GeoRadius` for `withinRadius`, a `GeoBoundingBox` for `boundingBox`. */
+  value: JsonValue | GeoRadius | GeoBoundingBox;
 }
 
 /** KV list result with pagination */
@@ -248,6 +1041,8 @@ export interface SearchHit {
   score: number;
   rank: number;
   snippet?: string;
+  /** Set when searching with `spaces`. */
+  space?: string;
 }
 
 /** Time range filter for search (ISO 8601 datetime strings) */
@@ -272,6 +1067,12 @@ export interface SearchOptions {
   expand?: boolean;
   /** Enable/disable reranking. Absent = auto (use if model configured). */
   rerank?: boolean;
+  /** Search these spaces instead of the current one; hits are merged by score. */
+  spaces?: SpaceSelector;
+  /** Abort the search; the call then rejects with `signal.reason`. */
+  signal?: AbortSignal;
+  /** Cancel the search and reject with `TimeoutError` after this many ms. */
+  timeoutMs?: number;
 }
 
 /** Time range for a branch */
@@ -280,19 +1081,430 @@ export interface TimeRange {
   latestTs: number | null;
 }
 
-/** Options for opening a database */
-export interface OpenOptions {
-  /** Enable automatic text embedding for semantic search. */
-  autoEmbed?: boolean;
-  /** Open in read-only mode. */
-  readOnly?: boolean;
+/** Options for opening a database */
+export interface OpenOptions {
+  /** Enable automatic text embedding for semantic search. */
+  autoEmbed?: boolean;
+  /** Open in read-only mode. */
+  readOnly?: boolean;
+  /**
+   * Open as a read-only follower of an existing primary instance.
+   * Followers do not acquire any file lock and can open a database
+   * that is already exclusively locked by another process.
+   * Call `refresh()` to see new commits from the primary.
+   */
+  follower?: boolean;
+  /**
+   * Run this handle's operations on a dedicated pool of this many blocking
+   * threads instead of the process-wide pool.
+   */
+  workerThreads?: number;
+  /** Run at most this many operations at once; further calls wait for a slot. */
+  maxConcurrentOps?: number;
+  /**
+   * With `maxConcurrentOps`, how many calls may wait before new ones fail
+   * with `ResourceExhaustedError`. Absent = unbounded; 0 = fail as soon as
+   * every slot is busy.
+   */
+  maxQueuedOps?: number;
+  /**
+   * Create the database if there is none at the path (default: true). Pass
+   * `false` to attach only to an existing database; a missing one fails with
+   * `NotFoundError`.
+   */
+  createIfMissing?: boolean;
+  /**
+   * Fail with `StateError` if a database already exists at the path
+   * (default: false), so initialization never reuses an existing one.
+   */
+  errorIfExists?: boolean;
+  /**
+   * Reject any call on this handle that takes longer than this many
+   * milliseconds with a `TimeoutError`. Cancellable calls are also stopped;
+   * other work is abandoned and finishes in the background.
+   */
+  timeoutMs?: number;
+  /**
+   * Flush and close this handle when the event loop drains (`beforeExit`)
+   * or the process gets SIGTERM, so short-lived scripts keep their last
+   * writes without calling `close()`.
+   */
+  flushOnExit?: boolean;
+  /**
+   * When the WAL is synced to disk: `'always'` after every write, before it
+   * resolves; `'interval'` every `syncIntervalMs`; `'async'` (default) when
+   * the engine decides.
+   */
+  syncMode?: 'always' | 'interval' | 'async';
+  /** Sync period for `syncMode: 'interval'` (default: 1000). */
+  syncIntervalMs?: number;
+  /**
+   * Background compaction: `'interval'` every `compactionIntervalMs`;
+   * `'writeAmplification'` once the bytes written to the WAL since the
+   * last compaction reach `compactionTarget` times the database's size,
+   * checked every `compactionIntervalMs`; `'off'` (default) leaves it to
+   * the engine and `compact()`. Failures are emitted as `error` events.
+   */
+  compactionMode?: 'off' | 'interval' | 'writeAmplification';
+  /** Default: an hour for `'interval'`, a minute for `'writeAmplification'`. */
+  compactionIntervalMs?: number;
+  /** Default: 4. */
+  compactionTarget?: number;
+  /** Limits on the values written through this handle. */
+  jsonLimits?: JsonLimits;
+  /** Shorthand for `jsonLimits.strictValues`. */
+  strictValues?: boolean;
+  /**
+   * Return stored integers beyond `Number.MAX_SAFE_INTEGER` from `get()`
+   * on KV, state, and JSON as BigInt instead of a rounded number.
+   */
+  bigints?: boolean;
+  /**
+   * Return whole-number floats from `get()` on KV, state, and JSON as boxed
+   * `Number` objects, so writing them back keeps them floats.
+   */
+  boxedFloats?: boolean;
+  /**
+   * Return the `timestamp` fields of results (versions, history, tags) as
+   * `Date`s rather than microseconds since the epoch. Dates have
+   * millisecond precision. The result types still declare `number`.
+   */
+  dateTimestamps?: boolean;
+  /**
+   * If another process has the path open for writing, keep retrying for up
+   * to this many milliseconds before failing (default: 0).
+   */
+  lockWaitMs?: number;
+  /**
+   * Megabytes for a read cache of the latest values `kvGet()` reads, shared
+   * by every handle on the database and emptied by any write (default: 0,
+   * no cache). Only the first open of a path sizes it.
+   */
+  cacheMB?: number;
+  /**
+   * Report every call made through this handle: `true` logs each at debug
+   * level (to `Strata.setLogger()`'s sink, or stderr), a function gets the
+   * records.
+   */
+  trace?: boolean | ((record: TraceRecord) => void);
+}
+
+/** A call reported by the `trace` open option */
+export interface TraceRecord extends SlowOp {
+  /** The typed error the call failed with, if it failed. */
+  error?: Error;
+}
+
+/** Limits on the values a write accepts. Unset fields keep their default. */
+export interface JsonLimits {
+  /** Deepest nesting of arrays and objects (default: 64, at most 1024). */
+  maxDepth?: number;
+  /**
+   * Largest value, counting string and byte lengths, object keys, and 8
+   * bytes per other scalar (default: 64 MiB).
+   */
+  maxValueBytes?: number;
+  /** Most elements in one array or object (default: 1,000,000). */
+  maxElements?: number;
+  /**
+   * Throw `ValidationError` for a value that would lose data in
+   * conversion (default: false): a dropped `undefined`, function, or
+   * symbol property, `NaN` or `Infinity` becoming `null`, or an integer
+   * beyond 2^53 stored as a rounded float.
+   */
+  strictValues?: boolean;
+}
+
+/** Options accepted by single writes and batches */
+export interface WriteOptions {
+  /** Sync the WAL before the write resolves, whatever the `syncMode`. */
+  durable?: boolean;
+}
+
+/** Options accepted by writes that store a value */
+export interface ValueWriteOptions extends WriteOptions {
+  /** Overrides the handle's `jsonLimits` for this call. */
+  jsonLimits?: JsonLimits;
+  /** Overrides the handle's `strictValues` for this call. */
+  strictValues?: boolean;
+}
+
+/** Options for `kv.set()`: the value write options and an annotation */
+export interface KvWriteOptions extends ValueWriteOptions, WriteAnnotation {}
+
+/** Options for `Strata.connect()` */
+export interface ConnectOptions {
+  /** Connect over TLS; pass `tls.connect()` options to configure it. */
+  tls?: boolean | ConnectionOptions;
+  /** Token the server checks before serving any call. */
+  token?: string;
+  /** Reject any call that takes longer than this many milliseconds. */
+  timeoutMs?: number;
+}
+
+/** Options for `db.listen()`; pass exactly one of `port` and `socketPath`. */
+export interface ListenOptions {
+  /** TCP port to listen on (0 picks a free one). */
+  port?: number;
+  /** Interface to bind with `port` (default: `127.0.0.1`). */
+  host?: string;
+  /** Unix socket path to listen on. */
+  socketPath?: string;
+  /** Serve over TLS with these `tls.createServer()` options. */
+  tls?: TlsOptions;
+  /**
+   * Who may connect. With `{ token, readOnlyToken }`, clients present one of
+   * the two; a function maps a client's token to its access, or `null` to
+   * refuse it. Absent = anyone may connect read-write.
+   */
+  auth?:
+    | { token?: string; readOnlyToken?: string }
+    | ((token: string | null) => Access | null | Promise<Access | null>);
+  /** Make every connection read-only. */
+  readOnly?: boolean;
+  /**
+   * Refuse connections past this many with `ResourceExhaustedError`. Sockets
+   * that have not authenticated yet count against the limit.
+   */
+  maxConnections?: number;
+  /** Drop connections that have not authenticated within this long (default: 10000). */
+  handshakeTimeoutMs?: number;
+  /**
+   * Directory that clients' bundle paths (`branch.export()`, `branch.import()`,
+   * `branch.validateBundle()`, `branch.diffBundle()`) resolve inside.
+   * Absent = those calls are refused with `AccessDeniedError`.
+   */
+  bundleDir?: string;
+}
+
+/** A connection's access from `ListenOptions.auth`. */
+export type Access = 'readWrite' | 'readOnly';
+
+/** A running server from `db.listen()` */
+export interface StrataServer {
+  /** The bound address. */
+  readonly address: { port: number; host: string } | { socketPath: string };
+  /** Number of open client connections. */
+  readonly connections: number;
+  /** Stop accepting connections and drop the open ones. */
+  close(): Promise<void>;
+}
+
+/** Handle health from `status()` */
+export interface HandleStatus {
+  /** `false` once the handle is closed. */
+  open: boolean;
+  /** Canonical database path, or `null` for in-memory databases. */
+  path: string | null;
+  accessMode: 'readWrite' | 'readOnly' | 'follower';
+  /** Current branch; `null` once closed or while an exclusive operation runs. */
+  branch: string | null;
+  /** Current space; `null` once closed or while an exclusive operation runs. */
+  space: string | null;
+  /** Whether a transaction is active on this handle. */
+  inTransaction: boolean;
+  /** Operations running or queued on this handle. */
+  inFlight: number;
+}
+
+/**
+ * Runs synchronously as a call starts, in the caller's async context, so a
+ * span it starts has the caller's active span as parent.
+ */
+export type Tracer = (operation: string, attributes: SpanAttributes) => ((outcome: SpanOutcome) => void) | void;
+
+export interface SpanAttributes {
+  'strata.operation': string;
+  'strata.branch': string | null;
+  'strata.space': string | null;
+  /** Keys touched, for data calls: the entry count of a batch, otherwise 1. */
+  'strata.key_count'?: number;
+}
+
+export interface SpanOutcome {
+  durationMs: number;
+  /** The typed error the call failed with. */
+  error?: StrataError;
+}
+
+export type LogLevel = 'debug' | 'info' | 'warn' | 'error';
+
+/** Options for `Strata.setLogger()` */
+export interface LoggerOptions {
+  /** Lowest level passed to the sink (default: `'info'`). */
+  level?: LogLevel;
+  sink: (level: LogLevel, message: string, fields: Record<string, unknown>) => void;
+}
+
+/** Lifecycle events and their payloads, for `db.on()` */
+export interface StrataEvents {
+  flush: {};
+  compactionStart: {};
+  compactionEnd: { durationMs: number; error?: StrataError };
+  /** The report `retentionApply()` resolved with. */
+  retentionApplied: unknown;
+  branchCreated: { branch: string };
+  branchDeleted: { branch: string };
+  /** Failures no caller sees, such as an interval sync or flush on exit. */
+  error: StrataError;
+}
+
+/** A call reported by `onSlowOp()` */
+export interface SlowOp {
+  /** Method name, e.g. `kvPut`. */
+  op: string;
+  /** Arguments without their values: strings truncated, arrays and buffers as lengths, objects as keys. */
+  args: unknown[];
+  durationMs: number;
+  /** Branch and space of the handle when the call started. */
+  branch: string | null;
+  space: string | null;
+}
+
+/** One entry a write call changes, as seen by write hooks */
+export interface WriteEvent {
+  /** Method called, e.g. `kvPut`, or `execute` for commands. */
+  method: string;
+  primitive: 'kv' | 'state' | 'event' | 'json' | 'vector';
+  op: 'put' | 'delete' | 'append';
+  /** Key, state cell, or event type. */
+  key: string;
+  /** Value being written; vector metadata for vectors. A before hook may replace it. */
+  value: unknown;
+  /** JSON path, for JSON writes. */
+  path?: string;
+  /** Collection, for vector writes. */
+  collection?: string;
+  branch: string;
+  space: string;
+}
+
+/** What a trigger watches: writes under a key prefix, in a space, or both */
+export interface TriggerTarget {
+  prefix?: string;
+  space?: string;
+  primitive?: 'kv' | 'state' | 'json' | 'event';
+}
+
+/** A committed write, as passed to a trigger */
+export interface TriggerChange {
+  primitive: 'kv' | 'state' | 'json' | 'event';
+  op: 'put' | 'delete' | 'append';
+  key: string;
+  /** JSON path, for JSON writes. */
+  path?: string;
+  branch: string;
+  space: string;
+  /** The entry before the write; null for appends and new entries. */
+  oldValue: unknown;
+  /** The entry written; null for deletes. */
+  newValue: unknown;
+}
+
+/** The entries a view reads: a key prefix or an event type */
+export interface ViewSource {
+  prefix?: string;
+  eventType?: string;
+  /** For prefix sources. Defaults to `'kv'`. */
+  primitive?: 'kv' | 'state' | 'json';
+  /** Defaults to the current space. */
+  space?: string;
+}
+
+/** A materialized view, for `viewCreate()` */
+export interface ViewDefinition {
+  source: ViewSource;
+  /** `[outputKey, value]` pairs for one source entry (the event type and payload, for events). */
+  map: (entry: { key: string; value: any }) => Array<[string, any]> | null | undefined;
+  /** Combine the values mapped to an output key. Without it, the key holds the values. */
+  reduce?: (values: any[], key: string) => any;
+  /** Space the output is written to, as KV entries. Must differ from the source's. */
+  space: string;
+}
+
+/** Where a view stands, from `viewStatus()` */
+export interface ViewStatus {
+  name: string;
+  branch: string;
+  source: ViewSource;
+  space: string;
+  /** Source entries that map to at least one output. */
+  sources: number;
+  /** Output keys. */
+  keys: number;
+  /** When the view was last built from its whole source (ms since epoch). */
+  refreshedAt: number | null;
+  /** When the view last changed (ms since epoch). */
+  updatedAt: number | null;
+  /** The last error from `map` or `reduce`, if the latest update failed. */
+  lastError: Error | null;
+}
+
+/** Calls made through one handle, from `metrics()` */
+export interface Metrics {
+  /** When counting started (open or the last `metricsReset()`), in ms since the epoch. */
+  since: number;
+  calls: number;
+  errors: number;
+  /** Failed calls by error code, e.g. `{ NOT_FOUND: 2, TIMEOUT: 1 }`. */
+  errorsByCode: Record<string, number>;
+  /** Per method family: `kv`, `state`, `event`, `json`, `vector`, `graph`, `txn`, `other`. */
+  families: Record<string, FamilyMetrics>;
+  /** Completed `compact()` calls. */
+  compactions: number;
+}
+
+/**
+ * Calls in one method family. Percentiles are the upper bound of the
+ * latency bucket they fall in, capped at `maxMs`.
+ */
+export interface FamilyMetrics {
+  calls: number;
+  errors: number;
+  meanMs: number;
+  p50Ms: number;
+  p90Ms: number;
+  p99Ms: number;
+  maxMs: number;
+}
+
+/** Read cache usage from `cacheStats()` */
+export interface CacheStats {
+  /** `cacheMB` in bytes; 0 without a cache. */
+  capacityBytes: number;
+  /** Bytes of keys and JSON-encoded values held. */
+  residentBytes: number;
+  /** Values held. */
+  entries: number;
+  /** Reads answered from the cache. */
+  hits: number;
+  /** Reads that went to the engine. */
+  misses: number;
+  /** `hits / (hits + misses)`, or 0 before any read. */
+  hitRate: number;
+}
+
+/** Worker pool usage from `poolStats()` */
+export interface PoolStats {
+  /** Whether the handle has its own pool (`workerThreads` was set). */
+  dedicated: boolean;
   /**
-   * Open as a read-only follower of an existing primary instance.
-   * Followers do not acquire any file lock and can open a database
-   * that is already exclusively locked by another process.
-   * Call `refresh()` to see new commits from the primary.
+   * Maximum number of worker threads. The shared pool has twice the number
+   * of cores, at least 4.
    */
-  follower?: boolean;
+  size: number;
+  /** `maxQueuedOps`, or `null` if the queue is unbounded. */
+  maxQueued: number | null;
+  /** Operations currently running. */
+  active: number;
+  /** Operations waiting for a thread. */
+  queued: number;
+  /** Operations finished since open. */
+  completed: number;
+  /** Calls turned away because the queue was full. */
+  rejected: number;
+  /** `active / size`. */
+  saturation: number;
 }
 
 /** Database configuration snapshot */
@@ -314,49 +1526,73 @@ export interface ModelConfig {
 // Options types for the new namespace API
 // =========================================================================
 
+/**
+ * Point in time for reads: microseconds since epoch, a `Date`, or a tag
+ * name created with `tagCreate()`.
+ */
+export type AsOf = number | string | Date;
+
+/**
+ * Read from another branch without switching. Cannot be combined with
+ * `asOf`.
+ */
+export interface BranchReadOptions {
+  branch?: string;
+  /** Space on that branch (default: the current space). */
+  space?: string;
+}
+
 /** Options for KV get */
-export interface KvGetOptions {
-  asOf?: number;
+export interface KvGetOptions extends BranchReadOptions {
+  asOf?: AsOf;
+  /** Read the value as it was at this write version (exclusive with `asOf`). */
+  atVersion?: number;
 }
 
 /** Options for KV keys listing */
 export interface KvKeysOptions {
   prefix?: string;
   limit?: number;
-  asOf?: number;
+  asOf?: AsOf;
+  /** List keys from these spaces; results become `{ space, key }` objects. */
+  spaces?: SpaceSelector;
 }
 
 /** Options for state get */
-export interface StateGetOptions {
-  asOf?: number;
+export interface StateGetOptions extends BranchReadOptions {
+  asOf?: AsOf;
+  /** Read the value as it was at this write version (exclusive with `asOf`). */
+  atVersion?: number;
 }
 
 /** Options for state CAS */
-export interface StateCasOptions {
+export interface StateCasOptions extends ValueWriteOptions {
   expectedVersion?: number;
 }
 
 /** Options for state keys listing */
 export interface StateKeysOptions {
   prefix?: string;
-  asOf?: number;
+  asOf?: AsOf;
 }
 
 /** Options for event get */
-export interface EventGetOptions {
-  asOf?: number;
+export interface EventGetOptions extends BranchReadOptions {
+  asOf?: AsOf;
 }
 
 /** Options for event listing */
 export interface EventListOptions {
   limit?: number;
   after?: number;
-  asOf?: number;
+  asOf?: AsOf;
 }
 
 /** Options for JSON get */
-export interface JsonGetOptions {
-  asOf?: number;
+export interface JsonGetOptions extends BranchReadOptions {
+  asOf?: AsOf;
+  /** Read the value as it was at this write version (exclusive with `asOf`). */
+  atVersion?: number;
 }
 
 /** Options for JSON keys listing */
@@ -364,36 +1600,110 @@ export interface JsonKeysOptions {
   limit?: number;
   prefix?: string;
   cursor?: string;
-  asOf?: number;
+  asOf?: AsOf;
+  /** List keys from these spaces (`limit` applies per space; no `cursor`). */
+  spaces?: SpaceSelector;
+}
+
+/** Options for `json.query()` */
+export interface JsonQueryOptions {
+  /** Only documents whose key starts with this. */
+  prefix?: string;
+  /** Most documents to return (default: all that match). */
+  limit?: number;
+  asOf?: AsOf;
+}
+
+/** A document matched by `json.query()` */
+export interface JsonQueryMatch {
+  key: string;
+  value: JsonValue;
+}
+
+/**
+ * The JSON Schema subset collections enforce: `type`, `properties`,
+ * `required`, `additionalProperties`, `items`, `enum`, `minimum`,
+ * `maximum`, `minLength`, `maxLength`, and the `title` and `description`
+ * annotations. Other keywords are rejected.
+ */
+export type JsonSchema = { [keyword: string]: JsonValue };
+
+/** Options for `collection()` */
+export interface CollectionOptions<T> {
+  /** Checked on every `put()`, and registered on the branch by the first one. */
+  schema?: JsonSchema;
+  /** The id of a document (default: its `id` property). */
+  keyFn?: (doc: T) => string | number;
+}
+
+/** A document matched by `Collection.query()` */
+export interface CollectionMatch<T> {
+  id: string;
+  value: T;
+}
+
+/** JSON documents of one kind, stored as `<name>/<id>`; see `collection()` */
+export interface Collection<T = JsonValue> {
+  readonly name: string;
+  /** The schema registered on the branch, or null. */
+  schema(): Promise<JsonSchema | null>;
+  /**
+   * Write `doc` under the id `keyFn` gives it. Rejects with a
+   * `ValidationError` if it doesn't match the schema. Returns the version.
+   */
+  put(doc: T, opts?: { jsonLimits?: JsonLimits }): Promise<number>;
+  get(id: string, opts?: { asOf?: AsOf }): Promise<T | null>;
+  delete(id: string): Promise<number>;
+  history(id: string, opts: HistoryOptions): Promise<HistoryPage | null>;
+  history(id: string): Promise<VersionedValue[] | null>;
+  /** Documents matching every filter, in id order; `prefix` is an id prefix. */
+  query(filter?: MetadataFilter[], opts?: JsonQueryOptions): Promise<CollectionMatch<T>[]>;
 }
 
 /** Options for vector collection creation */
 export interface VectorCreateCollectionOptions {
   dimension: number;
-  metric?: string;
+  metric?: DistanceMetric | `${DistanceMetric}`;
 }
 
 /** Options for vector upsert */
-export interface VectorUpsertOptions {
+export interface VectorUpsertOptions extends WriteOptions {
   metadata?: JsonValue;
 }
 
 /** Options for vector get */
 export interface VectorGetOptions {
-  asOf?: number;
+  asOf?: AsOf;
 }
 
 /** Options for vector search */
 export interface VectorSearchOptions {
   limit?: number;
-  metric?: string;
+  metric?: DistanceMetric | `${DistanceMetric}`;
   filter?: MetadataFilter[];
-  asOf?: number;
+  asOf?: AsOf;
+  /** Search the collection in these spaces; the best `limit` matches overall are returned. */
+  spaces?: SpaceSelector;
+  /** Abort the search; the call then rejects with `signal.reason`. */
+  signal?: AbortSignal;
+  /** Cancel the search and reject with `TimeoutError` after this many ms. */
+  timeoutMs?: number;
 }
 
 /** Options for branch merge */
 export interface BranchMergeOptions {
-  strategy?: string;
+  strategy?: MergeStrategy | `${MergeStrategy}`;
+  /** Preview the merge without writing anything. */
+  dryRun?: boolean;
+  /** Include both sides' values and versions in reported conflicts. */
+  includeValues?: boolean;
+  /**
+   * Resolve each conflict in JS. Only valid with the `last_writer_wins`
+   * strategy; supports kv, state, and json conflicts. Every conflict is
+   * resolved before anything is written, and the answers are written in
+   * one transaction.
+   */
+  resolve?: MergeConflictResolver;
 }
 
 /** Options for space delete */
@@ -401,9 +1711,68 @@ export interface SpaceDeleteOptions {
   force?: boolean;
 }
 
+export interface SpaceCopyOptions {
+  /** Branch to copy within (default: the current branch). */
+  branch?: string;
+}
+
+/** Per-space statistics on the current branch */
+export interface SpaceStats extends PrimitiveCounts {
+  space: string;
+  branch: string;
+}
+
+/** Space copy result */
+export interface SpaceCopyResult {
+  source: string;
+  destination: string;
+  branch: string;
+  keysCopied: number;
+}
+
 /** Options for transaction callback */
 export interface TransactionOptions {
   readOnly?: boolean;
+  /** Snapshot every read at this timestamp or tag (read-only transactions only). */
+  asOf?: AsOf;
+  /** Target this branch without switching the handle's current branch. */
+  branch?: string;
+  /** Default space for the transaction's commands. */
+  space?: string;
+}
+
+/** A write made in a transaction, as reported by `commit({ detailed: true })` */
+export interface CommittedOperation {
+  /** The `execute()` command name, as called. */
+  command: string;
+  key: string | null;
+  branch: string | null;
+  space: string | null;
+  version: number;
+}
+
+/** Result of `commit({ detailed: true })` */
+export interface CommitResult {
+  version: number;
+  operations: CommittedOperation[];
+}
+
+/** Exponential backoff with full jitter between transaction retries */
+export interface RetryBackoff {
+  /** Delay cap for the first retry (default: 10). */
+  initialMs?: number;
+  /** Upper bound on any delay (default: 1000). */
+  maxMs?: number;
+  /** Growth per retry (default: 2). */
+  factor?: number;
+}
+
+/** Options for `transactionWithRetry` */
+export interface RetryTransactionOptions extends TransactionOptions {
+  /** Retries after the first attempt (default: 5). */
+  maxRetries?: number;
+  /** Backoff settings, or a function returning the delay in ms for a retry. */
+  backoff?: RetryBackoff | ((attempt: number) => number);
 }
 
 // =========================================================================
@@ -497,6 +1866,47 @@ export interface EmbedStatus {
   schedulerActiveTasks: number;
 }
 
+// =========================================================================
+// Document ingestion
+// =========================================================================
+
+/** Options for document ingestion */
+export interface IngestOptions {
+  /** Maximum chunk length in characters (default: 1000). */
+  chunkSize?: number;
+  /** Characters shared between consecutive chunks (default: 100). */
+  overlap?: number;
+  /** Metadata merged into every chunk's vector metadata. */
+  metadata?: { [key: string]: JsonValue };
+}
+
+/** A single ingested chunk */
+export interface IngestedChunk {
+  key: string;
+  version: number;
+}
+
+/** Options for cross-encoder reranking */
+export interface RerankOptions {
+  /** Reranker model name. Absent = the default cross-encoder. */
+  model?: string;
+  /**
+   * Score each candidate with this function instead of the cross-encoder,
+   * e.g. another model or service, or a deterministic stub in tests. Cannot
+   * be combined with `model`; works without the `embed` feature. A score
+   * that isn't a finite number rejects with a `ValidationError`.
+   */
+  scorer?: (query: string, candidate: string) => number | Promise<number>;
+}
+
+/** A reranked candidate */
+export interface RerankResult {
+  /** Position of the candidate in the input array. */
+  index: number;
+  text: string;
+  score: number;
+}
+
 // =========================================================================
 // Durability types
 // =========================================================================
@@ -509,6 +1919,59 @@ export interface DurabilityCounters {
   syncNanos: number;
 }
 
+export interface WalStats {
+  /** Records appended to the WAL since open. */
+  appends: number;
+  bytesWritten: number;
+  syncCalls: number;
+  /** Total size of the WAL segment files on disk; null for in-memory databases. */
+  sizeBytes: number | null;
+  /** Number of WAL segment files on disk; null for in-memory databases. */
+  segments: number | null;
+  /**
+   * When the WAL was last synced, by `flush()` on any handle to the database
+   * or by the engine, in microseconds since the epoch; null if it hasn't been.
+   * An engine sync is dated when `walStats()` or `durabilityCounters()` first
+   * sees it.
+   */
+  lastFlushAt: number | null;
+}
+
+/** Result of `compact()` */
+export interface CompactResult {
+  durationMs: number;
+  /** Size of the database directory before compacting; null in memory. */
+  diskBytesBefore: number | null;
+  diskBytesAfter: number | null;
+}
+
+/** A problem found by `verify()` */
+export interface VerifyProblem {
+  /**
+   * `metadata` for branch and space records, `index` for listings that
+   * disagree with what is stored, `read` for data that fails to read
+   * (including checksum failures).
+   */
+  kind: 'metadata' | 'index' | 'read';
+  branch: string;
+  space: string | null;
+  primitive: 'kv' | 'state' | 'json' | 'event' | 'vector' | 'graph' | 'space' | null;
+  key: string | null;
+  message: string;
+}
+
+/** Result of `verify()` */
+export interface VerifyReport {
+  /** True if no problems were found. */
+  ok: boolean;
+  deep: boolean;
+  branches: number;
+  spaces: number;
+  /** Values, events, and graph edges read; 0 unless `deep`. */
+  entriesChecked: number;
+  problems: VerifyProblem[];
+}
+
 // =========================================================================
 // Batch entry types
 // =========================================================================
@@ -682,9 +2145,19 @@ export interface BranchCreateOptions {
 }
 
 /** Options for branch listing */
+export interface BranchForkOptions {
+  /**
+   * Fork from the current branch's state at this timestamp (microseconds).
+   * Copies KV, state, and JSON data only.
+   */
+  asOf?: AsOf;
+}
+
 export interface BranchListOptions {
   limit?: number;
   offset?: number;
+  /** Include archived branches (default: false). */
+  includeArchived?: boolean;
 }
 
 // =========================================================================
@@ -693,48 +2166,72 @@ export interface BranchListOptions {
 
 /** KV Store namespace — accessed via `db.kv` */
 export interface KvNamespace {
-  set(key: string, value: JsonValue): Promise<number>;
+  /**
+   * With `author`, `message`, or `requestId`, they are kept with the
+   * version written and returned by `history()`, `timeline()`, and
+   * detailed branch diffs.
+   */
+  set(key: string, value: JsonValue, opts?: KvWriteOptions): Promise<number>;
   get(key: string, opts?: KvGetOptions): Promise<JsonValue>;
-  delete(key: string): Promise<boolean>;
+  /** Store a MessagePack-encoded value without converting it to JS objects. */
+  putRaw(key: string, value: Buffer, opts?: ValueWriteOptions): Promise<number>;
+  /** Read a value as MessagePack, or `null` if the key doesn't exist. */
+  getRaw(key: string, opts?: { asOf?: AsOf }): Promise<Buffer | null>;
+  delete(key: string, opts?: WriteOptions): Promise<boolean>;
+  keys(opts: KvKeysOptions & { spaces: SpaceSelector }): Promise<SpaceKey[]>;
   keys(opts?: KvKeysOptions): Promise<string[]>;
+  history(key: string, opts: HistoryOptions): Promise<HistoryPage | null>;
   history(key: string): Promise<VersionedValue[] | null>;
+  /** Versions written in a time range, each with a diff against the one before. */
+  timeline(key: string, opts?: TimelineOptions): Promise<TimelineEntry[] | null>;
   getVersioned(key: string): Promise<VersionedValue | null>;
-  batchPut(entries: BatchKvEntry[]): Promise<BatchResult[]>;
+  batchPut(entries: BatchKvEntry[], opts?: ValueWriteOptions): Promise<BatchResult[]>;
 }
 
-/** State Cell namespace — accessed via `db.state` */
+/**
+ * State Cell namespace — accessed via `db.state`. Writes to cells under
+ * `_queue/` or `_queue_head/`, which queues keep, throw `ValidationError`.
+ */
 export interface StateNamespace {
-  set(cell: string, value: JsonValue): Promise<number>;
+  set(cell: string, value: JsonValue, opts?: ValueWriteOptions): Promise<number>;
   get(cell: string, opts?: StateGetOptions): Promise<JsonValue>;
-  init(cell: string, value: JsonValue): Promise<number>;
+  init(cell: string, value: JsonValue, opts?: ValueWriteOptions): Promise<number>;
   cas(cell: string, newValue: JsonValue, opts?: StateCasOptions): Promise<number | null>;
-  delete(cell: string): Promise<boolean>;
+  delete(cell: string, opts?: WriteOptions): Promise<boolean>;
   keys(opts?: StateKeysOptions): Promise<string[]>;
+  history(cell: string, opts: HistoryOptions): Promise<HistoryPage | null>;
   history(cell: string): Promise<VersionedValue[] | null>;
   getVersioned(cell: string): Promise<VersionedValue | null>;
-  batchSet(entries: BatchStateEntry[]): Promise<BatchResult[]>;
+  batchSet(entries: BatchStateEntry[], opts?: ValueWriteOptions): Promise<BatchResult[]>;
 }
 
 /** Event Log namespace — accessed via `db.events` */
 export interface EventsNamespace {
-  append(eventType: string, payload: JsonValue): Promise<number>;
+  append(eventType: string, payload: JsonValue, opts?: ValueWriteOptions): Promise<number>;
   get(sequence: number, opts?: EventGetOptions): Promise<VersionedValue | null>;
   list(eventType: string, opts?: EventListOptions): Promise<VersionedValue[]>;
   count(): Promise<number>;
-  batchAppend(entries: BatchEventEntry[]): Promise<BatchResult[]>;
+  batchAppend(entries: BatchEventEntry[], opts?: ValueWriteOptions): Promise<BatchResult[]>;
 }
 
 /** JSON Document namespace — accessed via `db.json` */
 export interface JsonNamespace {
-  set(key: string, path: string, value: JsonValue): Promise<number>;
+  set(key: string, path: string, value: JsonValue, opts?: ValueWriteOptions): Promise<number>;
   get(key: string, path: string, opts?: JsonGetOptions): Promise<JsonValue>;
-  delete(key: string, path: string): Promise<number>;
+  delete(key: string, path: string, opts?: WriteOptions): Promise<number>;
+  keys(opts: JsonKeysOptions & { spaces: SpaceSelector }): Promise<JsonSpaceListResult>;
   keys(opts?: JsonKeysOptions): Promise<JsonListResult>;
+  /**
+   * Documents matching every filter, in key order. `field` is a dotted
+   * path into the document.
+   */
+  query(filter: MetadataFilter[], opts?: JsonQueryOptions): Promise<JsonQueryMatch[]>;
+  history(key: string, opts: HistoryOptions): Promise<HistoryPage | null>;
   history(key: string): Promise<VersionedValue[] | null>;
   getVersioned(key: string): Promise<VersionedValue | null>;
-  batchSet(entries: BatchJsonEntry[]): Promise<BatchResult[]>;
+  batchSet(entries: BatchJsonEntry[], opts?: ValueWriteOptions): Promise<BatchResult[]>;
   batchGet(entries: BatchJsonGetEntry[]): Promise<BatchGetResult[]>;
-  batchDelete(entries: BatchJsonDeleteEntry[]): Promise<BatchResult[]>;
+  batchDelete(entries: BatchJsonDeleteEntry[], opts?: WriteOptions): Promise<BatchResult[]>;
 }
 
 /** Vector Store namespace — accessed via `db.vector` */
@@ -745,8 +2242,8 @@ export interface VectorNamespace {
   stats(collection: string): Promise<CollectionInfo>;
   upsert(collection: string, key: string, vector: number[], opts?: VectorUpsertOptions): Promise<number>;
   get(collection: string, key: string, opts?: VectorGetOptions): Promise<VectorData | null>;
-  delete(collection: string, key: string): Promise<boolean>;
-  batchUpsert(collection: string, entries: BatchVectorEntry[]): Promise<number[]>;
+  delete(collection: string, key: string, opts?: WriteOptions): Promise<boolean>;
+  batchUpsert(collection: string, entries: BatchVectorEntry[], opts?: WriteOptions): Promise<number[]>;
   search(collection: string, query: number[], opts?: VectorSearchOptions): Promise<SearchMatch[]>;
 }
 
@@ -755,16 +2252,51 @@ export interface BranchNamespace {
   current(): Promise<string>;
   switch(name: string): Promise<void>;
   create(name: string, opts?: BranchCreateOptions): Promise<void>;
-  fork(destination: string): Promise<ForkResult>;
+  fork(destination: string, opts?: BranchForkOptions): Promise<ForkResult>;
   list(opts?: BranchListOptions): Promise<string[]>;
   delete(name: string): Promise<void>;
   exists(name: string): Promise<boolean>;
   get(name: string): Promise<BranchInfo | null>;
-  diff(branchA: string, branchB: string): Promise<DiffResult>;
+  rename(oldName: string, newName: string): Promise<void>;
+  setMetadata(name: string, metadata: BranchMetadata): Promise<void>;
+  /** Protect from deletion and direct writes. Pass `false` to lift. */
+  protect(name: string, enabled?: boolean): Promise<void>;
+  /** Make read-only and hide from `list()`. Pass `false` to unarchive. */
+  archive(name: string, enabled?: boolean): Promise<void>;
+  stats(name: string): Promise<BranchStats>;
+  lineage(opts?: BranchLineageOptions): Promise<BranchLineage>;
+  diff(branchA: string, branchB: string, opts?: DiffOptions): Promise<DiffResult>;
   merge(source: string, opts?: BranchMergeOptions): Promise<MergeResult>;
-  export(branch: string, path: string): Promise<BranchExportResult>;
-  import(path: string): Promise<BranchImportResult>;
-  validateBundle(path: string): Promise<BundleValidateResult>;
+  /** Conflicts a `manual` merge staged on `branch` (default: the current branch). */
+  conflicts(branch?: string): Promise<MergeConflict[]>;
+  /** Write `value` for a staged conflict and drop it from `conflicts()`. */
+  resolveConflict(conflict: MergeConflict, value: JsonValue): Promise<void>;
+  export(branch: string, path: string, opts?: BundleExportOptions): Promise<BranchExportResult>;
+  /** Export only changes after `sinceVersion`; `import()` applies the delta to the existing branch. */
+  exportIncremental(
+    branch: string,
+    sinceVersion: number,
+    path: string,
+    opts?: BundleExportOptions,
+  ): Promise<BranchExportResult>;
+  import(path: string, opts?: BundleImportOptions): Promise<BranchImportResult>;
+  validateBundle(path: string, opts?: BundleImportOptions): Promise<BundleValidateResult>;
+  /** Export a branch as a stream of bundle bytes. */
+  /**
+   * Compare bundle `pathA` with another bundle or with a live branch
+   * (`{ branch }`) without importing either.
+   */
+  diffBundle(
+    pathA: string,
+    target: string | { branch: string },
+    opts?: BundleDiffOptions,
+  ): Promise<DiffResult>;
+  exportStream(branch: string, opts?: BundleExportStreamOptions): Readable;
+  /** Import a bundle from any readable stream or async iterable of bytes. */
+  importStream(
+    source: AsyncIterable<Uint8Array>,
+    opts?: Omit<BundleImportOptions, keyof BundleControlOptions>,
+  ): Promise<BranchImportResult>;
 }
 
 /** Space Management namespace — accessed via `db.space` */
@@ -775,6 +2307,11 @@ export interface SpaceNamespace {
   list(): Promise<string[]>;
   delete(name: string, opts?: SpaceDeleteOptions): Promise<void>;
   exists(name: string): Promise<boolean>;
+  stats(name: string): Promise<SpaceStats>;
+  /** Copy KV, state, and JSON data into a new space. */
+  copy(source: string, destination: string, opts?: SpaceCopyOptions): Promise<SpaceCopyResult>;
+  /** Rename a space holding only KV, state, and JSON data. */
+  rename(oldName: string, newName: string): Promise<void>;
 }
 
 /** Graph namespace — accessed via `db.graph` */
@@ -830,7 +2367,7 @@ export interface GraphNamespace {
 /** Read-only KV namespace for snapshots */
 export interface KvSnapshotNamespace {
   get(key: string): Promise<JsonValue>;
-  keys(opts?: Omit<KvKeysOptions, 'asOf'>): Promise<string[]>;
+  keys(opts?: Omit<KvKeysOptions, 'asOf' | 'spaces'>): Promise<string[]>;
   history(key: string): Promise<VersionedValue[] | null>;
   getVersioned(key: string): Promise<VersionedValue | null>;
 }
@@ -853,7 +2390,8 @@ export interface EventsSnapshotNamespace {
 /** Read-only JSON namespace for snapshots */
 export interface JsonSnapshotNamespace {
   get(key: string, path: string): Promise<JsonValue>;
-  keys(opts?: Omit<JsonKeysOptions, 'asOf'>): Promise<JsonListResult>;
+  keys(opts?: Omit<JsonKeysOptions, 'asOf' | 'spaces'>): Promise<JsonListResult>;
+  query(filter: MetadataFilter[], opts?: Omit<JsonQueryOptions, 'asOf'>): Promise<JsonQueryMatch[]>;
   history(key: string): Promise<VersionedValue[] | null>;
   getVersioned(key: string): Promise<VersionedValue | null>;
 }
@@ -863,7 +2401,7 @@ export interface VectorSnapshotNamespace {
   listCollections(): Promise<CollectionInfo[]>;
   stats(collection: string): Promise<CollectionInfo>;
   get(collection: string, key: string): Promise<VectorData | null>;
-  search(collection: string, query: number[], opts?: Omit<VectorSearchOptions, 'asOf'>): Promise<SearchMatch[]>;
+  search(collection: string, query: number[], opts?: Omit<VectorSearchOptions, 'asOf' | 'spaces'>): Promise<SearchMatch[]>;
 }
 
 /** Read-only Graph namespace for snapshots */
@@ -890,10 +2428,16 @@ export interface GraphSnapshotNamespace {
 }
 
 /**
- * Immutable time-travel snapshot returned by `db.at(timestamp)`.
+ * Immutable time-travel snapshot returned by `db.at(timestamp | tag)`.
  * Only read operations are available; writes throw StateError.
+ * `history()` and `getVersioned()` only see versions that existed at the
+ * snapshot time.
  */
 export class StrataSnapshot {
+  /** The timestamp or tag name this snapshot was taken at. */
+  readonly asOf: AsOf;
+  /** Resolve the snapshot time to microseconds since epoch. */
+  timestamp(): Promise<number>;
   readonly kv: KvSnapshotNamespace;
   readonly state: StateSnapshotNamespace;
   readonly events: EventsSnapshotNamespace;
@@ -902,6 +2446,32 @@ export class StrataSnapshot {
   readonly graph: GraphSnapshotNamespace;
 }
 
+/**
+ * Read-only view of another branch returned by `db.readBranch(name)`.
+ * Reads go straight to that branch; the handle's current branch is
+ * untouched. Writes throw StateError.
+ */
+export class BranchView {
+  readonly branch: string;
+  readonly kv: {
+    get(key: string): Promise<JsonValue>;
+    getVersioned(key: string): Promise<VersionedValue | null>;
+    keys(opts?: { prefix?: string; limit?: number }): Promise<string[]>;
+  };
+  readonly state: {
+    get(cell: string): Promise<JsonValue>;
+    getVersioned(cell: string): Promise<VersionedValue | null>;
+    keys(opts?: { prefix?: string }): Promise<string[]>;
+  };
+  readonly events: {
+    get(sequence: number): Promise<VersionedValue | null>;
+  };
+  readonly json: {
+    get(key: string, path?: string): Promise<JsonValue>;
+    getVersioned(key: string): Promise<VersionedValue | null>;
+  };
+}
+
 // =========================================================================
 // Main Strata class
 // =========================================================================
@@ -914,8 +2484,48 @@ export class StrataSnapshot {
  */
 export class Strata {
   // Factory methods (synchronous)
+  /**
+   * Open the database at `path`, which may also be a `strata:///path?option=value`
+   * URL carrying open options; `options` take precedence over the URL's.
+   */
   static open(path: string, options?: OpenOptions): Strata;
   static cache(): Strata;
+  /** The option values methods take, e.g. `Strata.DistanceMetric.Cosine`. */
+  static readonly DistanceMetric: typeof DistanceMetric;
+  static readonly MergeStrategy: typeof MergeStrategy;
+  static readonly FilterOp: typeof FilterOp;
+  /**
+   * Send the binding's log records (opens, closes, lock waits, warnings) to
+   * `sink` from `level` up, instead of stderr and process warnings. Pass
+   * `null` to go back.
+   */
+  static setLogger(options: LoggerOptions | null): void;
+  /** Open without blocking the event loop on recovery or model download. */
+  static openAsync(path: string, options?: OpenOptions): Promise<Strata>;
+  /** Attach to a database shared by `share()`, e.g. from a worker thread. */
+  static fromShared(token: string, options?: OpenOptions): Strata;
+  /**
+   * Connect to a server at `strata://host:port` or `strata+unix:///path`.
+   * The handle has the same API as an embedded one; `close()` closes the
+   * connection only.
+   */
+  static connect(url: string, options?: ConnectOptions): Promise<Strata>;
+  /**
+   * Restore a `backup()`, and the backups an incremental one builds on,
+   * into a new database at `targetPath`; `rewindTo` rewinds it to a moment
+   * inside the backup.
+   */
+  static restore(backupDir: string, targetPath: string, opts?: RestoreOptions): Promise<RestoreResult>;
+  /**
+   * A new in-memory database holding the data of a fixture: the object
+   * itself, its JSON text, or the path of a JSON file.
+   */
+  static fromFixture(fixture: Fixture | string): Promise<Strata>;
+  /**
+   * Open a database in a new directory under the OS temp dir, removed when
+   * the handle closes, is garbage collected, or is open as the process exits.
+   */
+  static temp(options?: TempOptions): Strata;
 
   // -----------------------------------------------------------------------
   // Namespace accessors (NEW — preferred API)
@@ -942,8 +2552,161 @@ export class Strata {
   // Time travel
   // -----------------------------------------------------------------------
 
-  /** Create an immutable snapshot at the given timestamp. */
-  at(timestamp: number): StrataSnapshot;
+  /** Create an immutable snapshot at the given timestamp or tag. */
+  at(timestamp: AsOf): StrataSnapshot;
+
+  /** Read another branch without switching the current one. */
+  readBranch(name: string, opts?: { space?: string }): BranchView;
+
+  /** Tag a point in time (default: now) so it can be used as an `asOf`. */
+  tagCreate(name: string, opts?: TagCreateOptions): Promise<Tag>;
+  /** List all tags, oldest timestamp first. */
+  tagList(): Promise<Tag[]>;
+  /** Delete a tag. Returns `true` if it existed. */
+  tagDelete(name: string): Promise<boolean>;
+
+  // -----------------------------------------------------------------------
+  // Counters
+  // -----------------------------------------------------------------------
+
+  /** Add `delta` (default: 1, may be negative) to a counter; returns the new total. */
+  counterIncr(name: string, delta?: number): Promise<number>;
+  /** A counter's total, 0 if never incremented, optionally as of a past time. */
+  counterGet(name: string, asOf?: AsOf): Promise<number>;
+  /** Set a counter to `value` (default: 0); returns the total it had. */
+  counterReset(name: string, value?: number): Promise<number>;
+  /** Every total a counter has had, newest first. */
+  counterHistory(name: string): Promise<VersionedValue[]>;
+  counterHistory(name: string, opts: HistoryOptions): Promise<HistoryPage>;
+
+  // -----------------------------------------------------------------------
+  // Queues
+  // -----------------------------------------------------------------------
+
+  /**
+   * Add an item to the end of a queue; returns its sequence number. Queue
+   * names can't contain `/`.
+   */
+  queuePush(queue: string, item: JsonValue, jsonLimits?: JsonLimits): Promise<number>;
+  /**
+   * Take the oldest visible message, or null. It is hidden for
+   * `visibilityTimeout` ms (default: 30,000), then delivered again unless
+   * acknowledged.
+   */
+  queuePop(queue: string, opts?: { visibilityTimeout?: number }): Promise<QueueMessage | null>;
+  /**
+   * Acknowledge a popped message, removing it. Throws `ConflictError` if it
+   * timed out and was delivered again.
+   */
+  queueAck(id: string): Promise<void>;
+  /** Message counts for a queue. */
+  queueStats(queue: string): Promise<QueueStats>;
+
+  // -----------------------------------------------------------------------
+  // Locks
+  // -----------------------------------------------------------------------
+
+  /**
+   * Take a lock for `ttlMs` (default: 30,000), retrying for up to `waitMs`
+   * (default: 0) while someone else holds it. Returns a random lease
+   * token, or null if the lock is still held. Leases are stored in the
+   * database, one set per database across branches, spaces, and processes.
+   */
+  lockAcquire(name: string, opts?: { ttlMs?: number; waitMs?: number }): Promise<string | null>;
+  /** Extend a lease to `ttlMs` from now; `false` if it was lost. */
+  lockRenew(name: string, token: string, ttlMs?: number): Promise<boolean>;
+  /** Release a lease; `false` if it was already lost. */
+  lockRelease(name: string, token: string): Promise<boolean>;
+
+  // -----------------------------------------------------------------------
+  // Time series
+  // -----------------------------------------------------------------------
+
+  /**
+   * Record `value` in `series` at `timestamp` (default: now), replacing a
+   * point at the same timestamp. Resolves with the timestamp.
+   */
+  tsAppend(
+    series: string,
+    timestamp: number | Date | null | undefined,
+    value: number,
+    tags?: Record<string, string>,
+  ): Promise<number>;
+  /** The points of a series in a range, oldest first. */
+  tsQuery(
+    series: string,
+    opts?: TimeSeriesQueryOptions & { aggregate?: undefined },
+  ): Promise<TimeSeriesPoint[]>;
+  /** One aggregate per bucket of a series, oldest first. */
+  tsQuery(
+    series: string,
+    opts: TimeSeriesQueryOptions & { aggregate: NonNullable<TimeSeriesQueryOptions['aggregate']> },
+  ): Promise<TimeSeriesBucket[]>;
+
+  // -----------------------------------------------------------------------
+  // Blobs
+  // -----------------------------------------------------------------------
+
+  /**
+   * Store the bytes of `source` as blob `key`, replacing its content. A
+   * stream is written `chunkSize` bytes (default: 1 MiB) at a time, so it
+   * is never held in memory whole.
+   */
+  blobPut(
+    key: string,
+    source: Buffer | Uint8Array | Readable | AsyncIterable<Buffer | Uint8Array | string>,
+    opts?: { chunkSize?: number },
+  ): Promise<BlobStat>;
+  /** A blob's content, or null if it doesn't exist. */
+  blobGet(key: string, opts?: { stream?: false }): Promise<Buffer | null>;
+  /** A blob's content as a stream read a chunk at a time. */
+  blobGet(key: string, opts: { stream: true }): Promise<Readable | null>;
+  /** Size and chunk count of a blob, or null if it doesn't exist. */
+  blobStat(key: string): Promise<BlobStat | null>;
+  /** Delete a blob; `false` if it didn't exist. */
+  blobDelete(key: string): Promise<boolean>;
+
+  // -----------------------------------------------------------------------
+  // Edges
+  // -----------------------------------------------------------------------
+
+  /**
+   * Add the edge `from -[relation]-> to` on the current branch, replacing
+   * its properties if it exists.
+   */
+  edgeAdd(
+    from: string,
+    relation: string,
+    to: string,
+    properties?: JsonValue,
+    jsonLimits?: JsonLimits,
+  ): Promise<void>;
+  /** Remove an edge; `false` if it didn't exist. */
+  edgeRemove(from: string, relation: string, to: string): Promise<boolean>;
+  /** The edges at a node, outgoing unless `direction` says otherwise. */
+  edgeList(node: string, opts?: EdgeListOptions): Promise<Edge[]>;
+  /** Walk the edges breadth-first from `start`, in one call. */
+  traverse(start: string, opts?: TraverseOptions): Promise<TraverseResult>;
+
+  // -----------------------------------------------------------------------
+  // Pub/Sub
+  // -----------------------------------------------------------------------
+
+  /**
+   * Send `message` to the channel's current subscribers: handles on the
+   * same database file in this process, and clients of its server. Nothing
+   * is stored. Resolves with the number of subscribers it went to.
+   */
+  publish(channel: string, message: unknown): Promise<number>;
+  /**
+   * Call `callback` with a copy of each message published to `channel`
+   * until the returned function is called or the handle closes. Errors it
+   * throws are reported as warnings.
+   */
+  subscribe(
+    channel: string,
+    callback: (message: any, channel: string) => void | Promise<void>,
+  ): Promise<() => void>;
 
   // -----------------------------------------------------------------------
   // Transaction callback
@@ -955,6 +2718,37 @@ export class Strata {
    */
   transaction<T>(fn: (tx: Strata) => Promise<T>, opts?: TransactionOptions): Promise<T>;
 
+  /**
+   * Like `transaction()`, but re-runs `fn` in a fresh transaction when the
+   * commit fails with a `ConflictError`, waiting a jittered backoff between
+   * attempts. `fn` must be safe to run more than once.
+   */
+  transactionWithRetry<T>(
+    fn: (tx: Strata, attempt: number) => Promise<T>,
+    opts?: RetryTransactionOptions,
+  ): Promise<T>;
+
+  // -----------------------------------------------------------------------
+  // Collections
+  // -----------------------------------------------------------------------
+
+  /**
+   * A typed wrapper over the JSON documents `<name>/<id>`, checking each
+   * `put()` against `opts.schema` on the worker thread.
+   */
+  collection<T = JsonValue>(name: string, opts?: CollectionOptions<T>): Collection<T>;
+
+  // -----------------------------------------------------------------------
+  // Migrations
+  // -----------------------------------------------------------------------
+
+  /**
+   * Apply the pending migrations in order, each in its own transaction
+   * with the record of it in the `_migrations` space, or move to the state
+   * after `opts.to`, reverting later ones with `down()`.
+   */
+  migrate(migrations: Migration[], opts?: MigrateOptions): Promise<MigrateResult>;
+
   // -----------------------------------------------------------------------
   // Configuration
   // -----------------------------------------------------------------------
@@ -976,18 +2770,102 @@ export class Strata {
   // -----------------------------------------------------------------------
 
   ping(): Promise<string>;
-  info(): Promise<DatabaseInfo>;
+  info(options?: InfoOptions): Promise<DatabaseInfo>;
   /** Get a structured snapshot of the database for agent introspection. */
   describe(): Promise<DescribeResult>;
   flush(): Promise<void>;
-  compact(): Promise<void>;
+  /**
+   * Compact the whole database; aborting `signal` cancels it if it hasn't
+   * started.
+   */
+  compact(opts?: { signal?: AbortSignal; timeoutMs?: number }): Promise<CompactResult>;
+  /**
+   * Check the database for damage without changing it. `deep` also reads
+   * every stored value, event, and graph edge.
+   */
+  verify(opts?: { deep?: boolean; signal?: AbortSignal; timeoutMs?: number }): Promise<VerifyReport>;
+  /**
+   * Back up every branch, with its history, annotations, tags, and
+   * retention rules, into `destDir` (empty or missing) while writes
+   * continue.
+   */
+  backup(destDir: string, opts?: BackupOptions): Promise<BackupResult>;
+  /**
+   * Write the current branch's KV, state, and JSON entries as NDJSON, one
+   * `{ space, primitive, key, value }` object per line, to a file path or
+   * a writable stream (left open). Buffers are written as
+   * `{ "$base64": "..." }`. Events, vectors, and graphs are not included.
+   */
+  dump(target: string | Writable, opts?: DumpOptions): Promise<DumpResult>;
+  /**
+   * Send every KV, state, and JSON write made after `sinceVersion`, on
+   * every branch, to `sink` in order: a function called with each record,
+   * or a writable stream that gets one NDJSON line per record. Records
+   * are read in pages and sent as each page arrives; deletes come last.
+   * Rejects with a `ConstraintError` if any space holds events, vectors,
+   * or graphs, which a changelog can't carry.
+   */
+  changelogExport(
+    sinceVersion: number,
+    sink: ((record: ChangeRecord) => unknown) | Writable,
+  ): Promise<ChangelogExportResult>;
+  /**
+   * Apply changelog records in order, from an iterable or the NDJSON
+   * lines of a readable stream, creating branches and spaces as needed.
+   */
+  changelogApply(
+    source: Iterable<ChangeRecord> | AsyncIterable<ChangeRecord> | Readable,
+    opts?: { batchSize?: number },
+  ): Promise<ChangelogApplyResult>;
+  /**
+   * Sync KV, state, and JSON data with another handle, or with the
+   * database at a path or `strata://` URL. Entries changed on one side
+   * since the last sync are copied to the other; entries changed on both
+   * are reported as conflicts. Rejects with a `ConstraintError` if a
+   * synced branch has a space holding events, vectors, or graphs.
+   */
+  syncTo(other: Strata | string, opts?: SyncOptions): Promise<SyncResult>;
+  /**
+   * Batched writes: run `callback` with a loader whose writes are sent in
+   * large batches, skipping `syncMode` flushes until the end, then flush
+   * once. Faster than looping over single writes for initial imports.
+   * Index and embedding maintenance is not deferred; the engine does it
+   * batch by batch as usual.
+   */
+  bulkLoad(callback: (loader: BulkLoader) => Promise<void>, opts?: BulkLoadOptions): Promise<BulkLoadResult>;
+  /**
+   * Write the entries of a `dump()` into the current branch, in batches.
+   * Lines without a `space` go to the current space; missing spaces are
+   * created.
+   */
+  load(source: string | Readable, opts?: LoadOptions): Promise<LoadResult>;
+  /**
+   * The current branch and space as a fixture for `Strata.fromFixture()`:
+   * its KV, state, JSON, and vectors, and the events of `eventTypes`.
+   */
+  toFixture(opts?: ToFixtureOptions): Promise<Fixture>;
+  /**
+   * Close this handle; later calls reject with `StrataClosedError`. The
+   * database is released when its last handle closes.
+   */
   close(): Promise<void>;
 
   // Search
   search(query: string, opts?: SearchOptions): Promise<SearchHit[]>;
 
   // Retention
-  retentionApply(): Promise<void>;
+  retentionApply(): Promise<RetentionReport>;
+  /** Set the retention rule for a scope; the most specific rule wins. */
+  retentionConfigure(rule: RetentionRule): Promise<void>;
+  /** Remove the rule for a scope. Returns `true` if one existed. */
+  retentionReset(space?: string | null, primitive?: RetentionPrimitive | null): Promise<boolean>;
+  retentionGetConfig(): Promise<{ rules: RetentionRule[] }>;
+  /** Trim history on the current branch now, outside the retention rules (pins still apply). */
+  historyTrim(opts: HistoryTrimOptions): Promise<RetentionReport>;
+  /** Keep a key's versions, or the versions visible at a tag or timestamp, through `retentionApply`. */
+  retentionPin(pin: RetentionPin): Promise<RetentionPin>;
+  retentionUnpin(pin: RetentionPin): Promise<boolean>;
+  retentionListPins(): Promise<RetentionPin[]>;
 
   // Generic command dispatch
   /**
@@ -1001,6 +2879,95 @@ export class Strata {
    */
   execute(command: string, args?: Record<string, unknown>): Promise<unknown>;
 
+  /** Worker pool usage: running and queued operations and saturation. */
+  poolStats(): Promise<PoolStats>;
+  /** Read cache size, usage, and hit rate; see the `cacheMB` open option. */
+  cacheStats(): Promise<CacheStats>;
+  /** A token for `Strata.fromShared()`, transferable with `postMessage`. */
+  share(): Promise<string>;
+  /** Whether the handle is still open. */
+  isOpen(): Promise<boolean>;
+  /** Health-check the handle: open state, path, access mode, context, load. */
+  status(): Promise<HandleStatus>;
+  /**
+   * This database's id, the same wherever it is opened from; `syncTo()`
+   * keys its peers on it. `null` if it has none yet; given `candidate`, a
+   * database without one takes it, which needs a writable handle.
+   */
+  databaseId(candidate?: string): Promise<string | null>;
+  /** Serve this database to `Strata.connect()` clients over TCP or a Unix socket. */
+  listen(options: ListenOptions): Promise<StrataServer>;
+  /** Call counts, errors, and latency of this handle since open or `metricsReset()`. */
+  metrics(): Promise<Metrics>;
+  /** Start `metrics()` over from zero. */
+  metricsReset(): Promise<void>;
+  /**
+   * Call `tracer` as each call on this handle starts, and the function it
+   * returns when the call settles. Pass `null` to stop tracing.
+   */
+  setTracer(tracer: Tracer | null): void;
+  /**
+   * Call `callback` after any call on this handle that takes `thresholdMs`
+   * or longer. Returns a function that unsubscribes it.
+   */
+  onSlowOp(thresholdMs: number, callback: (op: SlowOp) => void): () => void;
+  /**
+   * Call `hook` with each KV, state, event, JSON, or vector entry a write
+   * through this handle is about to change. It may set `write.value`, or
+   * throw to reject the write (errors other than `StrataError` become
+   * `AccessDeniedError`). Returns a function that unregisters it.
+   */
+  onBeforeWrite(hook: (write: WriteEvent) => void | Promise<void>): () => void;
+  /**
+   * Call `hook` with each entry once a write succeeds, with the call's
+   * `result`. Errors it throws are reported as warnings. Returns a function
+   * that unregisters it.
+   */
+  onAfterWrite(hook: (write: WriteEvent & { result: unknown }) => void | Promise<void>): () => void;
+  /**
+   * Call `callback` after each committed KV, state, JSON, or event write
+   * through any handle on this database under a key prefix (a string
+   * target) or matching a `TriggerTarget`. Inside a transaction it runs at
+   * commit. `oldValue` is read just before the write and can be stale if
+   * another call writes the entry concurrently. It doesn't hold up the
+   * write; its errors are reported as warnings. Returns a function that
+   * drops the trigger.
+   */
+  createTrigger(
+    target: string | TriggerTarget,
+    callback: (change: TriggerChange) => void | Promise<void>,
+  ): () => void;
+  /**
+   * Run `fn` with every call on this handle from its async context
+   * targeting `branch` and `space`. Omitted fields come from the enclosing
+   * context or the handle. `setBranch()` and `setSpace()` throw inside.
+   */
+  runWithContext<T>(
+    context: { branch?: string; space?: string },
+    fn: (db: this) => T | Promise<T>,
+  ): Promise<T>;
+  /** The `{ branch, space }` of the enclosing `runWithContext()`, or null. */
+  currentContext(): { branch: string; space: string } | null;
+  /**
+   * Define a materialized view on the current branch and build it. It is
+   * then kept up to date by a trigger on its source, through this handle.
+   */
+  viewCreate(name: string, def: ViewDefinition): Promise<ViewStatus>;
+  /** Rebuild a view from its whole source. */
+  viewRefresh(name: string): Promise<ViewStatus>;
+  /** Where a view stands. */
+  viewStatus(name: string): Promise<ViewStatus>;
+  /** Stop maintaining a view. Its output is left in place. */
+  viewDrop(name: string): Promise<void>;
+  /** Names of this handle's views. */
+  viewList(): Promise<string[]>;
+  /** Listen for lifecycle events of calls made through this handle. */
+  on<E extends keyof StrataEvents>(event: E, listener: (payload: StrataEvents[E]) => void): this;
+  once<E extends keyof StrataEvents>(event: E, listener: (payload: StrataEvents[E]) => void): this;
+  off<E extends keyof StrataEvents>(event: E, listener: (payload: StrataEvents[E]) => void): this;
+  /** `metrics()` in the Prometheus text exposition format. */
+  metricsPrometheus(opts?: { labels?: Record<string, string> }): Promise<string>;
+
   // Follower mode
   /** Returns `true` if this database was opened in read-only follower mode. */
   isFollower(): Promise<boolean>;
@@ -1013,11 +2980,18 @@ export class Strata {
 
   // Time Travel
   timeRange(): Promise<TimeRange>;
+  /** KV, state, and JSON keys that changed on the current branch between two timestamps or tags. */
+  diffTime(tsA: AsOf, tsB: AsOf, opts?: DiffTimeOptions): Promise<DiffTimeResult>;
 
   // Transaction Operations (manual — prefer `transaction()` callback)
-  begin(readOnly?: boolean): Promise<void>;
-  commit(): Promise<number>;
+  begin(opts?: boolean | TransactionOptions): Promise<void>;
+  commit(opts: { detailed: true }): Promise<CommitResult>;
+  commit(opts?: { detailed?: false }): Promise<number>;
   rollback(): Promise<void>;
+  /** Mark a savepoint in the active transaction. */
+  txSavepoint(name: string): Promise<void>;
+  /** Undo writes made since a savepoint; the transaction stays active. */
+  txRollbackTo(name: string): Promise<void>;
   txnInfo(): Promise<TransactionInfo | null>;
   txnIsActive(): Promise<boolean>;
 
@@ -1040,6 +3014,48 @@ export class Strata {
   embedBatch(texts: string[]): Promise<number[][]>;
   /** Get the embedding pipeline status. */
   embedStatus(): Promise<EmbedStatus>;
+  /**
+   * Chunk a document, embed each chunk, and upsert the chunks into a vector
+   * collection. Chunks are keyed `{key}#{index}`; re-ingesting a shorter
+   * document deletes the chunks it no longer has. The chunks are written in
+   * one transaction, so every chunk gets the same version.
+   */
+  ingestDocument(collection: string, key: string, text: string, opts?: IngestOptions): Promise<IngestedChunk[]>;
+  /**
+   * Score candidates against a query with a cross-encoder, highest first.
+   * Requires the `embed` feature.
+   */
+  rerank(query: string, candidates: string[], opts?: RerankOptions): Promise<RerankResult[]>;
+
+  // -----------------------------------------------------------------------
+  // Conversation Memory
+  // -----------------------------------------------------------------------
+
+  /**
+   * Record a turn of session `sessionId` as an event of type
+   * `_memory/<sessionId>` and embed its `content` for `memoryRecall()`.
+   * Returns the event's sequence number. Requires the `embed` feature.
+   */
+  memoryAppend(sessionId: string, turn: MemoryTurn, jsonLimits?: JsonLimits): Promise<number>;
+  /** The `k` turns of the session closest in meaning to `query`, best first. */
+  memoryRecall<T extends MemoryTurn = MemoryTurn>(sessionId: string, query: string, k: number): Promise<MemoryMatch<T>[]>;
+
+  // -----------------------------------------------------------------------
+  // Traces
+  // -----------------------------------------------------------------------
+
+  /**
+   * Start the trace of an agent run on the current branch. Rejects with a
+   * `ConflictError` if the run already has one.
+   */
+  traceStart(runId: string, metadata?: JsonValue): Promise<number>;
+  /**
+   * Record a span of the run, or replace the span with the same id. Its
+   * `parentId` must name a span already recorded.
+   */
+  traceRecord(runId: string, span: TraceSpan, jsonLimits?: JsonLimits): Promise<number>;
+  /** The run's spans as a tree, or null if it was never started. */
+  traceGet<T extends TraceSpan = TraceSpan>(runId: string): Promise<Trace<T> | null>;
 
   // -----------------------------------------------------------------------
   // Inference
@@ -1072,6 +3088,9 @@ export class Strata {
   /** Get WAL durability counters. */
   durabilityCounters(): Promise<DurabilityCounters>;
 
+  /** WAL activity since open and when `flush()` last completed. */
+  walStats(): Promise<WalStats>;
+
 }
 
 /**
@@ -1,5 +1,9 @@
 'use strict';
 
+const { AsyncLocalStorage } = require('async_hooks');
+const crypto = require('crypto');
+const { EventEmitter } = require('events');
+const { Readable } = require('stream');
 const native = require('./index.js');
 const {
   StrataError,
@@ -10,29 +14,664 @@ const {
   ConstraintError,
   AccessDeniedError,
   IoError,
+  ResourceExhaustedError,
+  TimeoutError,
+  StrataClosedError,
   toTypedError,
 } = require('./lib/errors.js');
+const { RemoteStrata, connect } = require('./lib/remote.js');
+const { serve } = require('./lib/server.js');
+const { Metrics, familyOf } = require('./lib/metrics.js');
+const { backup: backupDatabase, restore } = require('./lib/backup.js');
+const { dump, load } = require('./lib/dump.js');
+const { bulkLoad } = require('./lib/bulk.js');
+const { changelogApply, changelogExport } = require('./lib/changelog.js');
+const { sync } = require('./lib/sync.js');
+const views = require('./lib/views.js');
+const pubsub = require('./lib/pubsub.js');
+const { blobGet, blobPut } = require('./lib/blobs.js');
+const { migrate } = require('./lib/migrate.js');
+const { loadFixture, readFixture, toFixture } = require('./lib/fixture.js');
+const temp = require('./lib/temp.js');
+const { Collection } = require('./lib/collection.js');
+const {
+  describeWrites,
+  inWriteHook,
+  runAfterHooks,
+  runBeforeHooks,
+  runTriggers,
+  triggerMatches,
+  triggerTarget,
+} = require('./lib/hooks.js');
+
+// ---------------------------------------------------------------------------
+// Logging. `Strata.setLogger()` routes the binding's log records, native and
+// JS, to one sink; without it, warnings go to stderr or `process` warnings.
+// ---------------------------------------------------------------------------
+
+const LOG_LEVELS = ['debug', 'info', 'warn', 'error'];
+
+// `(level, message, fields) => void`, already filtered by level.
+let logger = null;
+
+function setLogger(options) {
+  if (options == null) {
+    logger = null;
+    native.setLogSink(null);
+    return;
+  }
+  const { level = 'info', sink } = options;
+  if (!LOG_LEVELS.includes(level)) {
+    throw new ValidationError(`Invalid log level '${level}'; use ${LOG_LEVELS.join(', ')}`);
+  }
+  if (typeof sink !== 'function') {
+    throw new ValidationError('setLogger() needs a sink function');
+  }
+  const threshold = LOG_LEVELS.indexOf(level);
+  logger = (recordLevel, message, fields) => {
+    if (LOG_LEVELS.indexOf(recordLevel) < threshold) {
+      return;
+    }
+    try {
+      sink(recordLevel, message, fields);
+    } catch (err) {
+      process.emitWarning(err);
+    }
+  };
+  native.setLogSink((record) => logger?.(record.level, record.message, record.fields));
+}
+
+// Report a failure that has no caller to reject it to.
+function warn(err, code) {
+  if (logger) {
+    logger('warn', err.message ?? String(err), code ? { code } : { error: err });
+  } else if (code) {
+    process.emitWarning(err, { type: 'StrataWarning', code });
+  } else {
+    process.emitWarning(err);
+  }
+}
 
 // ---------------------------------------------------------------------------
 // Wrap every async prototype method so native errors are re-thrown as typed
-// StrataError subclasses.
+// StrataError subclasses, and calls that outlive their timeout reject with a
+// TimeoutError. The engine work itself is abandoned, not stopped, unless the
+// call is cancellable (see withSignal below).
 // ---------------------------------------------------------------------------
 
 const NativeStrata = native.Strata;
 
+// Default `timeoutMs` of each handle, from `Strata.open(path, { timeoutMs })`.
+const defaultTimeouts = new WeakMap();
+
+// Per-call `timeoutMs`, set while a call that takes one dispatches to the
+// native method (which reads it synchronously).
+let scopedTimeoutMs;
+
+function withScopedTimeout(timeoutMs, fn) {
+  const previous = scopedTimeoutMs;
+  scopedTimeoutMs = timeoutMs;
+  try {
+    return fn();
+  } finally {
+    scopedTimeoutMs = previous;
+  }
+}
+
+function withTimeout(promise, timeoutMs) {
+  if (timeoutMs == null) {
+    return promise;
+  }
+  let timer;
+  const timedOut = new Promise((_, reject) => {
+    timer = setTimeout(
+      () => reject(new TimeoutError(`Operation timed out after ${timeoutMs} ms`)),
+      timeoutMs,
+    );
+  });
+  return Promise.race([promise, timedOut]).finally(() => clearTimeout(timer));
+}
+
+// Handles opened with `syncMode: 'always'`, which flush after every write.
+const syncEveryWrite = new WeakSet();
+
+// Handles running a `bulkLoad()`, which skip `syncMode` flushes until it
+// finishes.
+const bulkLoading = new WeakSet();
+
+// Methods that write, and so flush under `syncMode: 'always'`.
+const WRITE_METHODS = new Set([
+  'kvPut', 'kvPutRaw', 'kvDelete', 'kvBatchPut', 'stateSet', 'stateInit', 'stateCas',
+  'stateDelete', 'stateBatchSet', 'eventAppend', 'eventBatchAppend', 'jsonSet', 'jsonDelete',
+  'jsonBatchSet', 'jsonBatchDelete', 'vectorUpsert', 'vectorDelete', 'vectorBatchUpsert',
+  'vectorCreateCollection', 'vectorDeleteCollection', 'graphCreate', 'graphDelete',
+  'graphAddNode', 'graphRemoveNode', 'graphAddEdge', 'graphRemoveEdge', 'graphBulkInsert',
+  'graphDefineObjectType', 'graphDeleteObjectType', 'graphDefineLinkType',
+  'graphDeleteLinkType', 'graphFreezeOntology', 'createBranch', 'deleteBranch', 'forkBranch',
+  'forkBranchAt', 'renameBranch', 'mergeBranches', 'archiveBranch', 'protectBranch',
+  'setBranchMetadata', 'tagCreate', 'tagDelete', 'spaceCreate', 'deleteSpace',
+  'deleteSpaceForce', 'spaceCopy', 'spaceRename', 'branchImport', 'ingestDocument',
+  'retentionApply', 'historyTrim', 'counterIncr', 'counterReset', 'queuePush', 'queuePop',
+  'queueAck', 'blobCommit', 'blobDelete', 'edgeAdd', 'edgeRemove', 'tsAppend', 'collectionDefine',
+  'collectionPut', 'memoryAppend', 'traceStart', 'traceRecord', 'commit', 'execute',
+]);
+
 // Collect all own method names (excluding constructor) from the prototype.
 const methodNames = Object.getOwnPropertyNames(NativeStrata.prototype).filter(
   (name) => name !== 'constructor' && typeof NativeStrata.prototype[name] === 'function',
 );
 
+const nativeFlush = NativeStrata.prototype.flush;
+const nativeStatus = NativeStrata.prototype.status;
+const nativeSharedId = NativeStrata.prototype.sharedId;
+const nativeCurrentBranch = NativeStrata.prototype.currentBranch;
+const nativeCurrentSpace = NativeStrata.prototype.currentSpace;
+const nativeSetBranch = NativeStrata.prototype.setBranch;
+const nativeSetSpace = NativeStrata.prototype.setSpace;
+
+// `onBeforeWrite` and `onAfterWrite` callbacks of each handle; see
+// lib/hooks.js.
+const writeHooks = new WeakMap();
+
+function hooksOf(db) {
+  let hooks = writeHooks.get(db);
+  if (!hooks) {
+    hooks = { before: new Set(), after: new Set() };
+    writeHooks.set(db, hooks);
+  }
+  return hooks;
+}
+
+// Triggers of each database, by `sharedId()`, so a write through any of
+// its handles, server connections included, runs them.
+const sharedTriggers = new Map();
+
+function triggersOf(db) {
+  return sharedTriggers.get(nativeSharedId.call(db)) ?? new Set();
+}
+
+// The entry a write is about to replace, read where the write goes.
+async function readEntry(db, { primitive, key, path, branch, space }) {
+  const scope = { branch, space };
+  let r;
+  if (primitive === 'kv') {
+    r = await db.execute('kv_get', { ...scope, key });
+  } else if (primitive === 'state') {
+    r = await db.execute('state_get', { ...scope, cell: key });
+  } else if (primitive === 'json') {
+    r = await db.execute('json_get', { ...scope, key, path });
+  }
+  return r == null ? null : r.value;
+}
+
+// The entries a write call changes, after this handle's before hooks have
+// seen them, or null if no hooks or triggers apply. Entries a trigger
+// watches get their old value.
+async function beforeWrite(db, name, args) {
+  const hooks = hooksOf(db);
+  const triggers = triggersOf(db);
+  const idle = hooks.before.size === 0 && hooks.after.size === 0 && triggers.size === 0;
+  if (idle || inWriteHook()) {
+    return null;
+  }
+  const current = contextOf(db) ?? {
+    branch: await nativeCurrentBranch.call(db),
+    space: await nativeCurrentSpace.call(db),
+  };
+  const writes = describeWrites(name, args, current);
+  if (writes && hooks.before.size > 0) {
+    await runBeforeHooks(hooks.before, writes);
+  }
+  for (const entry of writes ?? []) {
+    if ([...triggers].some(({ target }) => triggerMatches(target, entry.write))) {
+      entry.triggered = true;
+      entry.oldValue = await readEntry(db, entry.write);
+    }
+  }
+  return writes;
+}
+
+// Trigger changes made inside each handle's open transaction, run once it
+// commits.
+const pendingTriggers = new WeakMap();
+
+// The tail of each handle's trigger runs, so they run in write order.
+const triggerChains = new WeakMap();
+
+function runTriggersLater(db, changes) {
+  const previous = triggerChains.get(db) ?? Promise.resolve();
+  triggerChains.set(db, previous.then(() => runTriggers(triggersOf(db), changes, warn)));
+}
+
+// Queue the triggered entries of a successful write call: run them after
+// this call returns, or after commit inside a transaction. Batch entries
+// that failed are skipped.
+function queueTriggers(db, writes, result) {
+  const changes = [];
+  writes.forEach(({ write, triggered, oldValue }, i) => {
+    if (!triggered || (Array.isArray(result) && result[i]?.error)) {
+      return;
+    }
+    const { method, value, ...change } = write;
+    change.oldValue = oldValue ?? null;
+    change.newValue = write.op === 'delete' ? null : value;
+    changes.push(change);
+  });
+  if (changes.length === 0) {
+    return;
+  }
+  if (nativeStatus.call(db).inTransaction) {
+    pendingTriggers.set(db, [...(pendingTriggers.get(db) ?? []), ...changes]);
+  } else {
+    runTriggersLater(db, changes);
+  }
+}
+
+// Call metrics of each handle, created on its first call.
+const handleMetrics = new WeakMap();
+
+function metricsOf(db) {
+  let metrics = handleMetrics.get(db);
+  if (!metrics) {
+    metrics = new Metrics();
+    handleMetrics.set(db, metrics);
+  }
+  return metrics;
+}
+
+// Lifecycle event emitters, created by a handle's first `on()` or `once()`.
+const emitters = new WeakMap();
+
+// Events emitted when these calls succeed, with their payloads.
+const LIFECYCLE_EVENTS = {
+  flush: ['flush', () => ({})],
+  retentionApply: ['retentionApplied', (args, result) => result],
+  createBranch: ['branchCreated', (args) => ({ branch: args[0] })],
+  forkBranch: ['branchCreated', (args) => ({ branch: args[0] })],
+  forkBranchAt: ['branchCreated', (args) => ({ branch: args[0] })],
+  deleteBranch: ['branchDeleted', (args) => ({ branch: args[0] })],
+};
+
+// Emit on the handle's emitter, if it has one. `error` is only emitted to
+// listeners, and a throwing listener is reported as a warning rather than
+// failing the call that triggered it.
+function emit(db, event, payload) {
+  const emitter = emitters.get(db);
+  if (!emitter || (event === 'error' && emitter.listenerCount('error') === 0)) {
+    return;
+  }
+  try {
+    emitter.emit(event, payload);
+  } catch (err) {
+    warn(err);
+  }
+}
+
+// Tracers set with `setTracer()`, by handle.
+const tracers = new WeakMap();
+
+// `{ thresholdMs, callback }` subscriptions from `onSlowOp()`, by handle.
+const slowOpWatchers = new WeakMap();
+
+// Where each handle opened with `trace` sends its call records.
+const traceSinks = new WeakMap();
+
+// `trace: true` logs each call at debug level, to the logger if there is
+// one and to stderr otherwise.
+function logTrace(record) {
+  const message = `${record.op} ${record.durationMs.toFixed(2)} ms`;
+  if (logger) {
+    logger('debug', message, record);
+  } else {
+    console.error(`[strata] ${message}`, record.args, record.error ? record.error.message : '');
+  }
+}
+
+const KEYED_FAMILIES = new Set(['kv', 'state', 'event', 'json', 'vector', 'graph']);
+
+function spanAttributes(name, args, branch, space) {
+  const attributes = {
+    'strata.operation': name,
+    'strata.branch': branch,
+    'strata.space': space,
+  };
+  if (KEYED_FAMILIES.has(familyOf(name))) {
+    // Batches take their entries as an array; everything else one key.
+    attributes['strata.key_count'] = args.find(Array.isArray)?.length ?? 1;
+  }
+  return attributes;
+}
+
+// A short, value-free description of call arguments for the slow-op log and
+// traces: strings are truncated, arrays and buffers become their length,
+// and objects their keys.
+function summarizeArgs(args) {
+  return args.map((arg) => {
+    if (typeof arg === 'string') {
+      return arg.length > 64 ? `${arg.slice(0, 64)}…` : arg;
+    }
+    if (Array.isArray(arg)) {
+      return `[${arg.length} items]`;
+    }
+    if (ArrayBuffer.isView(arg)) {
+      return `<${arg.byteLength} bytes>`;
+    }
+    if (arg !== null && typeof arg === 'object') {
+      return `{${Object.keys(arg).join(', ')}}`;
+    }
+    return arg;
+  });
+}
+
+// Hooks run around a call if the handle has a tracer, slow-op watchers, or
+// a trace sink; returns what to run when the call settles. Throwing hooks
+// are reported as warnings rather than failing the call.
+function observeCall(db, name, args) {
+  const tracer = tracers.get(db);
+  const watchers = slowOpWatchers.get(db);
+  const traceSink = traceSinks.get(db);
+  if (!tracer && !watchers?.size && !traceSink) {
+    return undefined;
+  }
+  const { branch, space } = contextOf(db) ?? nativeStatus.call(db);
+  let endSpan;
+  try {
+    endSpan = tracer?.(name, spanAttributes(name, args, branch, space));
+  } catch (err) {
+    warn(err);
+  }
+  const hooks = [];
+  if (typeof endSpan === 'function') {
+    hooks.push(endSpan);
+  }
+  for (const { thresholdMs, callback } of watchers ?? []) {
+    hooks.push(({ durationMs }) => {
+      if (durationMs >= thresholdMs) {
+        callback({ op: name, args: summarizeArgs(args), durationMs, branch, space });
+      }
+    });
+  }
+  if (traceSink) {
+    // execute() calls are traced under their command's name.
+    const op = name === 'execute' ? `execute:${args[0]}` : name;
+    const summary = summarizeArgs(name === 'execute' ? args.slice(1) : args);
+    hooks.push(({ durationMs, error }) => {
+      traceSink({ op, args: summary, durationMs, branch, space, error });
+    });
+  }
+  return (outcome) => {
+    for (const hook of hooks) {
+      try {
+        hook(outcome);
+      } catch (err) {
+        warn(err);
+      }
+    }
+  };
+}
+
+// ---------------------------------------------------------------------------
+// Dates. A Date can stand in for a timestamp wherever a call takes one, and
+// handles opened with `dateTimestamps: true` return the `timestamp` fields
+// of results as Dates rather than microseconds since the epoch.
+// ---------------------------------------------------------------------------
+
+// Microseconds since the epoch of a timestamp or Date.
+function microsOf(ts) {
+  if (!(ts instanceof Date)) {
+    return ts;
+  }
+  const ms = ts.getTime();
+  if (Number.isNaN(ms)) {
+    throw new ValidationError('Invalid Date');
+  }
+  return ms * 1000;
+}
+
+// Arguments that take a timestamp, by position, or as `[position,
+// ...fields]` for the fields of an options argument.
+const TIME_ARGS = {
+  kvGet: [1],
+  kvGetRaw: [1],
+  kvList: [1],
+  kvListPaginated: [2],
+  kvHistory: [[1, 'fromTs', 'toTs']],
+  kvTimeline: [[1, 'from', 'to']],
+  stateGet: [1],
+  stateList: [1],
+  stateHistory: [[1, 'fromTs', 'toTs']],
+  counterGet: [1],
+  counterHistory: [[1, 'fromTs', 'toTs']],
+  edgeList: [[1, 'asOf']],
+  traverse: [[1, 'asOf']],
+  tsAppend: [1],
+  tsQuery: [[1, 'from', 'to']],
+  eventGet: [1],
+  eventList: [1],
+  eventListPaginated: [3],
+  jsonGet: [2],
+  jsonList: [3],
+  jsonQuery: [[1, 'asOf']],
+  jsonHistory: [[1, 'fromTs', 'toTs']],
+  vectorGet: [2],
+  vectorSearch: [3],
+  vectorSearchFiltered: [5],
+  forkBranchAt: [1],
+  branchRewind: [1],
+  diffTime: [0, 1],
+  begin: [[0, 'asOf']],
+  historyTrim: [[0, 'before']],
+};
+
+function withTimeArgs(name, args) {
+  for (const at of TIME_ARGS[name] ?? []) {
+    if (typeof at === 'number') {
+      args[at] = microsOf(args[at]);
+      continue;
+    }
+    const [i, ...fields] = at;
+    const opts = args[i];
+    if (opts !== null && typeof opts === 'object' && fields.some((f) => opts[f] instanceof Date)) {
+      args[i] = { ...opts };
+      for (const field of fields) {
+        args[i][field] = microsOf(opts[field]);
+      }
+    }
+  }
+}
+
+// Handles opened with `dateTimestamps: true`.
+const dateTimestamps = new WeakSet();
+
+// Calls that return stored values as they are, where a `timestamp` field
+// is the caller's data.
+const VALUE_RESULTS = new Set(['kvGet', 'kvGetRaw', 'stateGet', 'jsonGet', 'dumpPage', 'syncRead']);
+
+// Fields holding stored values, left as they are.
+const VALUE_FIELDS = new Set(['value', 'payload', 'metadata', 'properties']);
+
+function withDates(result) {
+  if (Array.isArray(result)) {
+    return result.map(withDates);
+  }
+  if (result === null || typeof result !== 'object' || ArrayBuffer.isView(result)) {
+    return result;
+  }
+  const out = {};
+  for (const [key, value] of Object.entries(result)) {
+    if (key === 'timestamp' && typeof value === 'number') {
+      out[key] = new Date(value / 1000);
+    } else {
+      out[key] = VALUE_FIELDS.has(key) ? value : withDates(value);
+    }
+  }
+  return out;
+}
+
+// ---------------------------------------------------------------------------
+// Async contexts. Inside `db.runWithContext({ branch, space }, fn)`, calls on
+// the handle target that branch and space, however they interleave with
+// calls from other contexts. The handle has one current branch and space,
+// so once a context has been used its calls pass through a gate: calls for
+// the same branch and space run together, and a call for another waits for
+// them to finish, then switches the handle over. Calls outside any context
+// use the handle's own branch and space, restored when they come back.
+// ---------------------------------------------------------------------------
+
+// Map of handle → `{ branch, space }` for the current async context.
+const asyncContexts = new AsyncLocalStorage();
+
+function contextOf(db) {
+  return asyncContexts.getStore()?.get(db);
+}
+
+class ScopeGate {
+  constructor(db) {
+    this.db = db;
+    // `branch\0space` the handle is switched to, null for its own, or
+    // undefined after a failed switch.
+    this.key = null;
+    this.home = null;
+    this.active = 0;
+    this.switching = false;
+    this.held = false;
+    this.waiting = [];
+  }
+
+  async run(scope, fn) {
+    const key = scope ? `${scope.branch}\0${scope.space}` : null;
+    while (this.switching || (this.active > 0 && this.key !== key)) {
+      await new Promise((resolve) => this.waiting.push(resolve));
+    }
+    if (this.key !== key) {
+      this.switching = true;
+      try {
+        await this.switchTo(scope);
+        this.key = key;
+      } catch (err) {
+        this.key = undefined;
+        throw err;
+      } finally {
+        this.switching = false;
+        this.wake();
+      }
+    }
+    this.active += 1;
+    try {
+      return await fn();
+    } finally {
+      this.active -= 1;
+      if (this.active === 0) {
+        this.wake();
+      }
+    }
+  }
+
+  async switchTo(scope) {
+    if (this.key === null) {
+      this.home = {
+        branch: await nativeCurrentBranch.call(this.db),
+        space: await nativeCurrentSpace.call(this.db),
+      };
+    }
+    const target = scope ?? this.home;
+    await nativeSetBranch.call(this.db, target.branch);
+    await nativeSetSpace.call(this.db, target.space);
+  }
+
+  // An open transaction keeps the handle on its branch and space until it
+  // commits or rolls back.
+  hold() {
+    if (!this.held) {
+      this.held = true;
+      this.active += 1;
+    }
+  }
+
+  release() {
+    if (this.held) {
+      this.held = false;
+      this.active -= 1;
+      if (this.active === 0) {
+        this.wake();
+      }
+    }
+  }
+
+  wake() {
+    const waiting = this.waiting;
+    this.waiting = [];
+    waiting.forEach((resolve) => resolve());
+  }
+}
+
+// Gates of handles whose contexts have been used.
+const scopeGates = new WeakMap();
+
 for (const name of methodNames) {
   const original = NativeStrata.prototype[name];
   Object.defineProperty(NativeStrata.prototype, name, {
     value: async function (...args) {
+      const timeoutMs = scopedTimeoutMs !== undefined ? scopedTimeoutMs : defaultTimeouts.get(this);
+      const settled = observeCall(this, name, args);
+      const started = performance.now();
+      let failure;
+      if (name === 'compact') {
+        emit(this, 'compactionStart', {});
+      }
       try {
-        return await original.apply(this, args);
+        const context = contextOf(this);
+        if (context && (name === 'setBranch' || name === 'setSpace')) {
+          throw new StateError(
+            `${name}() can't be used inside runWithContext(); nest another context`,
+          );
+        }
+        withTimeArgs(name, args);
+        const gate = scopeGates.get(this);
+        // Hooks and trigger reads run inside the gate, so they see the
+        // branch and space the write goes to.
+        let writes = null;
+        const call = async () => {
+          writes = WRITE_METHODS.has(name) ? await beforeWrite(this, name, args) : null;
+          return withTimeout(original.apply(this, args), timeoutMs);
+        };
+        const result = await (gate ? gate.run(context, call) : call());
+        if (gate && name === 'begin') {
+          gate.hold();
+        } else if (gate && (name === 'commit' || name === 'rollback')) {
+          gate.release();
+        }
+        if (WRITE_METHODS.has(name) && syncEveryWrite.has(this) && !bulkLoading.has(this)) {
+          await nativeFlush.call(this);
+        }
+        if (writes) {
+          await runAfterHooks(writeHooks.get(this).after, writes, result, warn);
+          queueTriggers(this, writes, result);
+        }
+        if (name === 'commit' && pendingTriggers.has(this)) {
+          runTriggersLater(this, pendingTriggers.get(this));
+          pendingTriggers.delete(this);
+        } else if (name === 'rollback') {
+          pendingTriggers.delete(this);
+        }
+        if (name in LIFECYCLE_EVENTS) {
+          const [event, payload] = LIFECYCLE_EVENTS[name];
+          emit(this, event, payload(args, result));
+        }
+        if (dateTimestamps.has(this) && !VALUE_RESULTS.has(name)) {
+          return withDates(result);
+        }
+        return result;
       } catch (err) {
-        throw toTypedError(err);
+        failure = toTypedError(err);
+        throw failure;
+      } finally {
+        const durationMs = performance.now() - started;
+        metricsOf(this).record(name, durationMs, failure);
+        settled?.({ durationMs, error: failure });
+        if (name === 'compact') {
+          emit(this, 'compactionEnd', { durationMs, error: failure });
+        }
       }
     },
     writable: true,
@@ -40,6 +679,33 @@ for (const name of methodNames) {
   });
 }
 
+// A value write's `jsonLimits`, with its `strictValues` option folded in.
+function limitsOf(opts) {
+  if (opts?.strictValues == null) {
+    return opts?.jsonLimits;
+  }
+  return { ...opts.jsonLimits, strictValues: opts.strictValues };
+}
+
+// A KV write's `author`, `message`, and `requestId`, if it has any.
+function annotationOf(opts) {
+  if (opts?.author == null && opts?.message == null && opts?.requestId == null) {
+    return undefined;
+  }
+  return { author: opts.author, message: opts.message, requestId: opts.requestId };
+}
+
+// A write's `{ durable: true }` flushes the WAL before the write resolves.
+function durable(db, opts, write) {
+  if (!opts?.durable) {
+    return write;
+  }
+  return write.then(async (result) => {
+    await db.flush();
+    return result;
+  });
+}
+
 // ---------------------------------------------------------------------------
 // Namespace classes — thin wrappers that translate options-object APIs into
 // positional calls on the native (flat) methods.
@@ -50,38 +716,58 @@ class KvNamespace {
     this._db = db;
   }
 
-  set(key, value) {
-    return this._db.kvPut(key, value);
+  set(key, value, opts) {
+    return durable(this._db, opts, this._db.kvPut(key, value, limitsOf(opts), annotationOf(opts)));
   }
 
   get(key, opts) {
-    return this._db.kvGet(key, opts?.asOf);
+    if (opts?.branch != null) {
+      return readOtherBranch(this._db, opts).kv.get(key);
+    }
+    return this._db.kvGet(key, opts?.asOf, opts?.atVersion);
   }
 
-  delete(key) {
-    return this._db.kvDelete(key);
+  putRaw(key, value, opts) {
+    return durable(this._db, opts, this._db.kvPutRaw(key, value, limitsOf(opts)));
+  }
+
+  getRaw(key, opts) {
+    return this._db.kvGetRaw(key, opts?.asOf);
+  }
+
+  delete(key, opts) {
+    return durable(this._db, opts, this._db.kvDelete(key));
   }
 
   keys(opts) {
     const prefix = opts?.prefix;
     const limit = opts?.limit;
     const asOf = opts?.asOf;
+    if (opts?.spaces != null) {
+      return this._db
+        .kvList(prefix, asOf, opts.spaces)
+        .then((keys) => (limit != null ? keys.slice(0, limit) : keys));
+    }
     if (limit != null) {
       return this._db.kvListPaginated(prefix, limit, asOf).then((r) => r.keys);
     }
     return this._db.kvList(prefix, asOf);
   }
 
-  history(key) {
-    return this._db.kvHistory(key);
+  history(key, opts) {
+    return this._db.kvHistory(key, opts);
+  }
+
+  timeline(key, opts) {
+    return this._db.kvTimeline(key, opts);
   }
 
   getVersioned(key) {
     return this._db.kvGetVersioned(key);
   }
 
-  batchPut(entries) {
-    return this._db.kvBatchPut(entries);
+  batchPut(entries, opts) {
+    return durable(this._db, opts, this._db.kvBatchPut(entries, limitsOf(opts)));
   }
 }
 
@@ -90,40 +776,43 @@ class StateNamespace {
     this._db = db;
   }
 
-  set(cell, value) {
-    return this._db.stateSet(cell, value);
+  set(cell, value, opts) {
+    return durable(this._db, opts, this._db.stateSet(cell, value, limitsOf(opts)));
   }
 
   get(cell, opts) {
-    return this._db.stateGet(cell, opts?.asOf);
+    if (opts?.branch != null) {
+      return readOtherBranch(this._db, opts).state.get(cell);
+    }
+    return this._db.stateGet(cell, opts?.asOf, opts?.atVersion);
   }
 
-  init(cell, value) {
-    return this._db.stateInit(cell, value);
+  init(cell, value, opts) {
+    return durable(this._db, opts, this._db.stateInit(cell, value, limitsOf(opts)));
   }
 
   cas(cell, newValue, opts) {
-    return this._db.stateCas(cell, newValue, opts?.expectedVersion);
+    return durable(this._db, opts, this._db.stateCas(cell, newValue, opts?.expectedVersion, limitsOf(opts)));
   }
 
-  delete(cell) {
-    return this._db.stateDelete(cell);
+  delete(cell, opts) {
+    return durable(this._db, opts, this._db.stateDelete(cell));
   }
 
   keys(opts) {
     return this._db.stateList(opts?.prefix, opts?.asOf);
   }
 
-  history(cell) {
-    return this._db.stateHistory(cell);
+  history(cell, opts) {
+    return this._db.stateHistory(cell, opts);
   }
 
   getVersioned(cell) {
     return this._db.stateGetVersioned(cell);
   }
 
-  batchSet(entries) {
-    return this._db.stateBatchSet(entries);
+  batchSet(entries, opts) {
+    return durable(this._db, opts, this._db.stateBatchSet(entries, limitsOf(opts)));
   }
 }
 
@@ -132,11 +821,14 @@ class EventsNamespace {
     this._db = db;
   }
 
-  append(eventType, payload) {
-    return this._db.eventAppend(eventType, payload);
+  append(eventType, payload, opts) {
+    return durable(this._db, opts, this._db.eventAppend(eventType, payload, limitsOf(opts)));
   }
 
   get(sequence, opts) {
+    if (opts?.branch != null) {
+      return readOtherBranch(this._db, opts).events.get(sequence);
+    }
     return this._db.eventGet(sequence, opts?.asOf);
   }
 
@@ -154,8 +846,8 @@ class EventsNamespace {
     return this._db.eventLen();
   }
 
-  batchAppend(entries) {
-    return this._db.eventBatchAppend(entries);
+  batchAppend(entries, opts) {
+    return durable(this._db, opts, this._db.eventBatchAppend(entries, limitsOf(opts)));
   }
 }
 
@@ -164,41 +856,48 @@ class JsonNamespace {
     this._db = db;
   }
 
-  set(key, path, value) {
-    return this._db.jsonSet(key, path, value);
+  set(key, path, value, opts) {
+    return durable(this._db, opts, this._db.jsonSet(key, path, value, limitsOf(opts)));
   }
 
   get(key, path, opts) {
-    return this._db.jsonGet(key, path, opts?.asOf);
+    if (opts?.branch != null) {
+      return readOtherBranch(this._db, opts).json.get(key, path);
+    }
+    return this._db.jsonGet(key, path, opts?.asOf, opts?.atVersion);
   }
 
-  delete(key, path) {
-    return this._db.jsonDelete(key, path);
+  delete(key, path, opts) {
+    return durable(this._db, opts, this._db.jsonDelete(key, path));
   }
 
   keys(opts) {
     const limit = opts?.limit ?? 100;
-    return this._db.jsonList(limit, opts?.prefix, opts?.cursor, opts?.asOf);
+    return this._db.jsonList(limit, opts?.prefix, opts?.cursor, opts?.asOf, opts?.spaces);
   }
 
-  history(key) {
-    return this._db.jsonHistory(key);
+  query(filter, opts) {
+    return this._db.jsonQuery(filter, opts);
+  }
+
+  history(key, opts) {
+    return this._db.jsonHistory(key, opts);
   }
 
   getVersioned(key) {
     return this._db.jsonGetVersioned(key);
   }
 
-  batchSet(entries) {
-    return this._db.jsonBatchSet(entries);
+  batchSet(entries, opts) {
+    return durable(this._db, opts, this._db.jsonBatchSet(entries, limitsOf(opts)));
   }
 
   batchGet(entries) {
     return this._db.jsonBatchGet(entries);
   }
 
-  batchDelete(entries) {
-    return this._db.jsonBatchDelete(entries);
+  batchDelete(entries, opts) {
+    return durable(this._db, opts, this._db.jsonBatchDelete(entries));
   }
 }
 
@@ -224,19 +923,19 @@ class VectorNamespace {
   }
 
   upsert(collection, key, vector, opts) {
-    return this._db.vectorUpsert(collection, key, vector, opts?.metadata);
+    return durable(this._db, opts, this._db.vectorUpsert(collection, key, vector, opts?.metadata));
   }
 
   get(collection, key, opts) {
     return this._db.vectorGet(collection, key, opts?.asOf);
   }
 
-  delete(collection, key) {
-    return this._db.vectorDelete(collection, key);
+  delete(collection, key, opts) {
+    return durable(this._db, opts, this._db.vectorDelete(collection, key));
   }
 
-  batchUpsert(collection, entries) {
-    return this._db.vectorBatchUpsert(collection, entries);
+  batchUpsert(collection, entries, opts) {
+    return durable(this._db, opts, this._db.vectorBatchUpsert(collection, entries));
   }
 
   search(collection, query, opts) {
@@ -244,10 +943,14 @@ class VectorNamespace {
     const metric = opts?.metric;
     const filter = opts?.filter;
     const asOf = opts?.asOf;
-    if (metric != null || filter != null) {
-      return this._db.vectorSearchFiltered(collection, query, k, metric, filter, asOf);
-    }
-    return this._db.vectorSearch(collection, query, k, asOf);
+    const control = { signal: opts?.signal, timeoutMs: opts?.timeoutMs };
+    return withSignal(this._db, control, (id) => {
+      if (metric != null || filter != null || opts?.spaces != null) {
+        return this._db.vectorSearchFiltered(
+          collection, query, k, metric, filter, asOf, opts?.spaces, id);
+      }
+      return this._db.vectorSearch(collection, query, k, asOf, id);
+    });
   }
 }
 
@@ -268,12 +971,15 @@ class BranchNamespace {
     return this._db.createBranch(name, opts?.metadata);
   }
 
-  fork(destination) {
+  fork(destination, opts) {
+    if (opts?.asOf != null) {
+      return this._db.forkBranchAt(destination, opts.asOf);
+    }
     return this._db.forkBranch(destination);
   }
 
   list(opts) {
-    return this._db.listBranches(opts?.limit, opts?.offset);
+    return this._db.listBranches(opts?.limit, opts?.offset, opts?.includeArchived);
   }
 
   delete(name) {
@@ -288,24 +994,72 @@ class BranchNamespace {
     return this._db.branchGet(name);
   }
 
-  diff(branchA, branchB) {
-    return this._db.diffBranches(branchA, branchB);
+  rename(oldName, newName) {
+    return this._db.renameBranch(oldName, newName);
+  }
+
+  setMetadata(name, metadata) {
+    return this._db.setBranchMetadata(name, metadata);
+  }
+
+  protect(name, enabled) {
+    return this._db.protectBranch(name, enabled);
+  }
+
+  archive(name, enabled) {
+    return this._db.archiveBranch(name, enabled);
+  }
+
+  stats(name) {
+    return this._db.branchStats(name);
+  }
+
+  lineage(opts) {
+    return this._db.branchLineage(opts?.divergence);
+  }
+
+  diff(branchA, branchB, opts) {
+    return this._db.diffBranches(branchA, branchB, opts);
   }
 
   merge(source, opts) {
-    return this._db.mergeBranches(source, opts?.strategy);
+    return this._db.mergeBranches(source, opts);
+  }
+
+  conflicts(branch) {
+    return this._db.branchConflicts(branch);
+  }
+
+  resolveConflict(conflict, value) {
+    return this._db.branchResolveConflict(conflict, value);
+  }
+
+  export(branch, path, opts) {
+    return this._db.branchExport(branch, path, opts);
+  }
+
+  exportIncremental(branch, sinceVersion, path, opts) {
+    return this._db.branchExportIncremental(branch, sinceVersion, path, opts);
+  }
+
+  import(path, opts) {
+    return this._db.branchImport(path, opts);
+  }
+
+  validateBundle(path, opts) {
+    return this._db.branchValidateBundle(path, opts);
   }
 
-  export(branch, path) {
-    return this._db.branchExport(branch, path);
+  diffBundle(pathA, target, opts) {
+    return this._db.bundleDiff(pathA, target, opts);
   }
 
-  import(path) {
-    return this._db.branchImport(path);
+  exportStream(branch, opts) {
+    return this._db.branchExportStream(branch, opts);
   }
 
-  validateBundle(path) {
-    return this._db.branchValidateBundle(path);
+  importStream(source, opts) {
+    return this._db.branchImportStream(source, opts);
   }
 }
 
@@ -340,6 +1094,18 @@ class SpaceNamespace {
   exists(name) {
     return this._db.spaceExists(name);
   }
+
+  stats(name) {
+    return this._db.spaceStats(name);
+  }
+
+  copy(source, destination, opts) {
+    return this._db.spaceCopy(source, destination, opts);
+  }
+
+  rename(oldName, newName) {
+    return this._db.spaceRename(oldName, newName);
+  }
 }
 
 class GraphNamespace {
@@ -502,6 +1268,33 @@ function readOnlyWrite() {
   throw new StateError('Snapshots are read-only');
 }
 
+// Resolve a snapshot's asOf (timestamp, Date, or tag name) to microseconds.
+async function snapshotTime(db, asOf) {
+  if (typeof asOf !== 'string') {
+    return microsOf(asOf);
+  }
+  const tag = (await db.tagList()).find((t) => t.name === asOf);
+  if (tag == null) {
+    throw new NotFoundError(`Tag not found: ${asOf}`);
+  }
+  return microsOf(tag.timestamp);
+}
+
+// Versions from a history call that existed at the snapshot time.
+async function historyAsOf(db, asOf, history) {
+  const [ts, versions] = await Promise.all([snapshotTime(db, asOf), history]);
+  return versions == null ? null : versions.filter((v) => microsOf(v.timestamp) <= ts);
+}
+
+// The version visible at the snapshot time, or null.
+async function versionedAsOf(db, asOf, history) {
+  const versions = await historyAsOf(db, asOf, history);
+  if (versions == null || versions.length === 0) {
+    return null;
+  }
+  return versions.reduce((a, b) => (b.timestamp > a.timestamp ? b : a));
+}
+
 class KvSnapshot {
   constructor(db, asOf) {
     this._db = db;
@@ -527,11 +1320,11 @@ class KvSnapshot {
   }
 
   history(key) {
-    return this._db.kvHistory(key);
+    return historyAsOf(this._db, this._asOf, this._db.kvHistory(key));
   }
 
   getVersioned(key) {
-    return this._db.kvGetVersioned(key);
+    return versionedAsOf(this._db, this._asOf, this._db.kvHistory(key));
   }
 }
 
@@ -557,11 +1350,11 @@ class StateSnapshot {
   }
 
   history(cell) {
-    return this._db.stateHistory(cell);
+    return historyAsOf(this._db, this._asOf, this._db.stateHistory(cell));
   }
 
   getVersioned(cell) {
-    return this._db.stateGetVersioned(cell);
+    return versionedAsOf(this._db, this._asOf, this._db.stateHistory(cell));
   }
 }
 
@@ -613,12 +1406,16 @@ class JsonSnapshot {
     return this._db.jsonList(limit, opts?.prefix, opts?.cursor, this._asOf);
   }
 
+  query(filter, opts) {
+    return this._db.jsonQuery(filter, { ...opts, asOf: this._asOf });
+  }
+
   history(key) {
-    return this._db.jsonHistory(key);
+    return historyAsOf(this._db, this._asOf, this._db.jsonHistory(key));
   }
 
   getVersioned(key) {
-    return this._db.jsonGetVersioned(key);
+    return versionedAsOf(this._db, this._asOf, this._db.jsonHistory(key));
   }
 }
 
@@ -710,41 +1507,190 @@ class GraphSnapshot {
 }
 
 // ---------------------------------------------------------------------------
-// StrataSnapshot — immutable time-travel view returned by db.at(timestamp)
+// BranchView — read another branch without switching, via db.readBranch(name)
 // ---------------------------------------------------------------------------
 
-class StrataSnapshot {
-  constructor(db, asOf) {
-    this._db = db;
-    this._asOf = asOf;
+function branchViewWrite() {
+  throw new StateError('Branch views are read-only');
+}
+
+function branchArgs(view, args) {
+  const out = { branch: view._branch, ...args };
+  if (view._space != null) {
+    out.space = view._space;
   }
+  return out;
+}
 
-  get kv() {
-    return (this._kv ??= new KvSnapshot(this._db, this._asOf));
+class KvBranchView {
+  constructor(view) {
+    this._view = view;
   }
 
-  get state() {
-    return (this._state ??= new StateSnapshot(this._db, this._asOf));
+  set() { branchViewWrite(); }
+  batchPut() { branchViewWrite(); }
+  delete() { branchViewWrite(); }
+
+  async get(key) {
+    const r = await this._view._db.execute('kv_get', branchArgs(this._view, { key }));
+    return r == null ? null : r.value;
   }
 
-  get events() {
-    return (this._events ??= new EventsSnapshot(this._db, this._asOf));
+  getVersioned(key) {
+    return this._view._db.execute('kv_get', branchArgs(this._view, { key }));
   }
 
-  get json() {
-    return (this._json ??= new JsonSnapshot(this._db, this._asOf));
+  async keys(opts) {
+    const args = { prefix: opts?.prefix };
+    if (opts?.limit != null) {
+      args.limit = opts.limit;
+    }
+    const r = await this._view._db.execute('kv_list', branchArgs(this._view, args));
+    return Array.isArray(r) ? r : r.keys;
   }
+}
 
-  get vector() {
-    return (this._vector ??= new VectorSnapshot(this._db, this._asOf));
+class StateBranchView {
+  constructor(view) {
+    this._view = view;
   }
 
-  get graph() {
-    return (this._graph ??= new GraphSnapshot(this._db));
+  set() { branchViewWrite(); }
+  init() { branchViewWrite(); }
+  cas() { branchViewWrite(); }
+  delete() { branchViewWrite(); }
+
+  async get(cell) {
+    const r = await this._view._db.execute('state_get', branchArgs(this._view, { cell }));
+    return r == null ? null : r.value;
+  }
+
+  getVersioned(cell) {
+    return this._view._db.execute('state_get', branchArgs(this._view, { cell }));
+  }
+
+  keys(opts) {
+    return this._view._db.execute('state_list', branchArgs(this._view, { prefix: opts?.prefix }));
   }
 }
 
-// ---------------------------------------------------------------------------
+class EventsBranchView {
+  constructor(view) {
+    this._view = view;
+  }
+
+  append() { branchViewWrite(); }
+  batchAppend() { branchViewWrite(); }
+
+  get(sequence) {
+    return this._view._db.execute('event_get', branchArgs(this._view, { sequence }));
+  }
+}
+
+class JsonBranchView {
+  constructor(view) {
+    this._view = view;
+  }
+
+  set() { branchViewWrite(); }
+  delete() { branchViewWrite(); }
+
+  async get(key, path) {
+    const r = await this._view._db.execute(
+      'json_get', branchArgs(this._view, { key, path: path ?? '$' }),
+    );
+    return r == null ? null : r.value;
+  }
+
+  getVersioned(key) {
+    return this._view._db.execute('json_get', branchArgs(this._view, { key, path: '$' }));
+  }
+}
+
+class BranchView {
+  constructor(db, branch, space) {
+    this._db = db;
+    this._branch = branch;
+    this._space = space;
+  }
+
+  get branch() {
+    return this._branch;
+  }
+
+  get kv() {
+    return (this._kv ??= new KvBranchView(this));
+  }
+
+  get state() {
+    return (this._state ??= new StateBranchView(this));
+  }
+
+  get events() {
+    return (this._events ??= new EventsBranchView(this));
+  }
+
+  get json() {
+    return (this._json ??= new JsonBranchView(this));
+  }
+}
+
+function readOtherBranch(db, opts) {
+  if (opts.asOf != null) {
+    throw new ValidationError('branch and asOf cannot be combined');
+  }
+  if (opts.atVersion != null) {
+    throw new ValidationError('branch and atVersion cannot be combined');
+  }
+  return db.readBranch(opts.branch, { space: opts.space });
+}
+
+// ---------------------------------------------------------------------------
+// StrataSnapshot — immutable time-travel view returned by db.at(timestamp)
+// ---------------------------------------------------------------------------
+
+class StrataSnapshot {
+  constructor(db, asOf) {
+    this._db = db;
+    this._asOf = asOf;
+  }
+
+  /** The timestamp or tag name this snapshot was taken at. */
+  get asOf() {
+    return this._asOf;
+  }
+
+  /** Resolve the snapshot time to microseconds since epoch. */
+  timestamp() {
+    return snapshotTime(this._db, this._asOf);
+  }
+
+  get kv() {
+    return (this._kv ??= new KvSnapshot(this._db, this._asOf));
+  }
+
+  get state() {
+    return (this._state ??= new StateSnapshot(this._db, this._asOf));
+  }
+
+  get events() {
+    return (this._events ??= new EventsSnapshot(this._db, this._asOf));
+  }
+
+  get json() {
+    return (this._json ??= new JsonSnapshot(this._db, this._asOf));
+  }
+
+  get vector() {
+    return (this._vector ??= new VectorSnapshot(this._db, this._asOf));
+  }
+
+  get graph() {
+    return (this._graph ??= new GraphSnapshot(this._db));
+  }
+}
+
+// ---------------------------------------------------------------------------
 // Install namespace getters, at(), and transaction() directly on the
 // NativeStrata prototype so they work regardless of which factory method
 // created the instance (NativeStrata.open/cache return NativeStrata, not a
@@ -790,8 +1736,22 @@ NativeStrata.prototype.at = function at(timestamp) {
   return new StrataSnapshot(this, timestamp);
 };
 
+NativeStrata.prototype.readBranch = function readBranch(name, opts) {
+  return new BranchView(this, name, opts?.space);
+};
+
+// The begin() options carried by transaction()/transactionWithRetry() opts.
+function beginOptions(opts) {
+  return {
+    readOnly: opts?.readOnly,
+    asOf: opts?.asOf,
+    branch: opts?.branch,
+    space: opts?.space,
+  };
+}
+
 NativeStrata.prototype.transaction = async function transaction(fn, opts) {
-  await this.begin(opts?.readOnly);
+  await this.begin(beginOptions(opts));
   try {
     const result = await fn(this);
     if (opts?.readOnly) {
@@ -806,27 +1766,1137 @@ NativeStrata.prototype.transaction = async function transaction(fn, opts) {
   }
 };
 
+// Delay before retry `attempt` (0-based): exponential backoff with full
+// jitter, or whatever a `backoff(attempt)` function returns.
+function retryDelay(backoff, attempt) {
+  if (typeof backoff === 'function') {
+    return backoff(attempt);
+  }
+  const initialMs = backoff?.initialMs ?? 10;
+  const maxMs = backoff?.maxMs ?? 1000;
+  const factor = backoff?.factor ?? 2;
+  return Math.random() * Math.min(maxMs, initialMs * factor ** attempt);
+}
+
+NativeStrata.prototype.transactionWithRetry = async function transactionWithRetry(fn, opts) {
+  const maxRetries = opts?.maxRetries ?? 5;
+  for (let attempt = 0; ; attempt++) {
+    await this.begin(beginOptions(opts));
+    let result;
+    try {
+      result = await fn(this, attempt);
+      if (opts?.readOnly) {
+        await this.rollback();
+        return result;
+      }
+    } catch (err) {
+      try { await this.rollback(); } catch (_) { /* ignore rollback errors */ }
+      throw err;
+    }
+    try {
+      await this.commit();
+      return result;
+    } catch (err) {
+      try { await this.rollback(); } catch (_) { /* ignore rollback errors */ }
+      if (!(err instanceof ConflictError) || attempt >= maxRetries) {
+        throw err;
+      }
+    }
+    await new Promise((resolve) => setTimeout(resolve, retryDelay(opts?.backoff, attempt)));
+  }
+};
+
+// ---------------------------------------------------------------------------
+// mergeBranches() with a JS conflict resolver or the `manual` strategy. The
+// native merge applies the source side (last-writer-wins) and reports
+// conflicts with both values. A resolver is asked about every conflict of a
+// dry run first, so one that throws leaves nothing written; its answers are
+// then written back in one transaction once the merge lands. `manual` puts
+// back the target's values instead and stages the conflicts on the
+// `_system_` branch until resolveConflict() settles them.
+// ---------------------------------------------------------------------------
+
+const nativeMergeBranches = NativeStrata.prototype.mergeBranches;
+
+function resolvedWriteCommand(conflict, branch, value) {
+  const base = { branch, space: conflict.space, value };
+  switch (conflict.primitive) {
+    case 'kv':
+      return ['kv_put', { ...base, key: conflict.key }];
+    case 'state':
+      return ['state_set', { ...base, cell: conflict.key }];
+    case 'json':
+      return ['json_set', { ...base, key: conflict.key, path: '$' }];
+    default:
+      throw new ValidationError(
+        `Cannot resolve conflicts for primitive '${conflict.primitive}' (key '${conflict.key}')`,
+      );
+  }
+}
+
+// Write `values` (conflict → value) to `branch` in one transaction.
+async function writeResolutions(db, branch, values) {
+  const writes = [...values].map(([conflict, value]) =>
+    resolvedWriteCommand(conflict, branch, value));
+  if (writes.length === 0) {
+    return;
+  }
+  await db.transaction(async (tx) => {
+    for (const [command, args] of writes) {
+      await tx.execute(command, args);
+    }
+  }, { branch });
+}
+
+// The `_system_` key a conflict staged by a `manual` merge waits under.
+const STAGED_CONFLICT_PREFIX = 'merge/conflict/';
+
+function stagedConflictKey(branch, space, key) {
+  return STAGED_CONFLICT_PREFIX + [branch, space, key].map(encodeURIComponent).join('/');
+}
+
+NativeStrata.prototype.mergeBranches = async function mergeBranches(source, opts) {
+  const options = typeof opts === 'string' ? { strategy: opts } : opts;
+  const manual = options?.strategy === 'manual';
+  if (typeof options?.resolve !== 'function' && !manual) {
+    return nativeMergeBranches.call(this, source, opts);
+  }
+  const { resolve, ...rest } = options;
+  if (manual && resolve !== undefined) {
+    throw new ValidationError('resolve cannot be combined with the manual strategy');
+  }
+  if (!manual && rest.strategy != null && rest.strategy !== 'last_writer_wins') {
+    throw new ValidationError('resolve can only be combined with the last_writer_wins strategy');
+  }
+  const nativeOpts = { ...rest, strategy: 'last_writer_wins', includeValues: true };
+  if (rest.dryRun) {
+    return nativeMergeBranches.call(this, source, nativeOpts);
+  }
+  const target = await this.currentBranch();
+  const preview = await nativeMergeBranches.call(this, source, { ...nativeOpts, dryRun: true });
+  const answer = async (conflict) => {
+    // Refuse primitives that can't be written back before anything is.
+    resolvedWriteCommand(conflict, target, null);
+    const value = manual ? conflict.targetValue : await resolve(conflict);
+    return value === undefined ? conflict.targetValue : value;
+  };
+  const answers = new Map();
+  for (const conflict of preview.conflicts) {
+    answers.set(JSON.stringify([conflict.space, conflict.key]), await answer(conflict));
+  }
+  const result = await nativeMergeBranches.call(this, source, nativeOpts);
+  // A write racing the merge can add a conflict the dry run didn't see.
+  const values = new Map();
+  for (const conflict of result.conflicts) {
+    const id = JSON.stringify([conflict.space, conflict.key]);
+    values.set(conflict, answers.has(id) ? answers.get(id) : await answer(conflict));
+  }
+  await writeResolutions(this, target, values);
+  if (manual) {
+    const system = await this.systemBranch();
+    for (const conflict of result.conflicts) {
+      await system.kvPut(
+        stagedConflictKey(target, conflict.space, conflict.key),
+        { ...conflict, branch: target, source },
+      );
+    }
+  } else {
+    for (const [conflict, value] of values) {
+      conflict.resolvedValue = value;
+    }
+  }
+  return result;
+};
+
+// Conflicts a `manual` merge staged for `branch` (default: the current one).
+NativeStrata.prototype.branchConflicts = async function branchConflicts(branch) {
+  const name = branch ?? await this.currentBranch();
+  const system = await this.systemBranch();
+  const prefix = STAGED_CONFLICT_PREFIX + encodeURIComponent(name) + '/';
+  const conflicts = [];
+  for (const key of await system.kvList(prefix)) {
+    const conflict = await system.kvGet(key);
+    if (conflict != null) {
+      conflicts.push(conflict);
+    }
+  }
+  return conflicts;
+};
+
+// Settle a staged conflict by writing `value` to its branch.
+NativeStrata.prototype.branchResolveConflict = async function branchResolveConflict(conflict, value) {
+  const key = stagedConflictKey(conflict?.branch, conflict?.space, conflict?.key);
+  const system = await this.systemBranch();
+  const staged = await system.kvGet(key);
+  if (staged == null) {
+    throw new NotFoundError(
+      `No staged conflict for key '${conflict?.key}' in space '${conflict?.space}'`,
+    );
+  }
+  if (value === undefined) {
+    throw new ValidationError('resolveConflict() needs the value to keep');
+  }
+  await writeResolutions(this, staged.branch, new Map([[staged, value]]));
+  await system.kvDelete(key);
+};
+
+// ---------------------------------------------------------------------------
+// Cancellation. Each cancellable call gets an operation id that
+// cancelOperation() can stop. Aborting its AbortSignal, or running past its
+// timeout, cancels the operation; an aborted call rejects with the signal's
+// reason right away, without waiting for the engine to wind down.
+// ---------------------------------------------------------------------------
+
+let nextOperationId = 1;
+
+async function withSignal(db, { signal, timeoutMs }, run) {
+  signal?.throwIfAborted();
+  const operationId = nextOperationId++;
+  let onAbort;
+  const aborted = new Promise((_, reject) => {
+    onAbort = () => {
+      db.cancelOperation(operationId);
+      reject(signal.reason);
+    };
+    signal?.addEventListener('abort', onAbort, { once: true });
+  });
+  try {
+    const running = withScopedTimeout(timeoutMs, () => run(operationId));
+    return await Promise.race([running, aborted]);
+  } catch (err) {
+    if (signal?.aborted) {
+      throw signal.reason;
+    }
+    if (err instanceof TimeoutError) {
+      db.cancelOperation(operationId);
+    }
+    throw err;
+  } finally {
+    signal?.removeEventListener('abort', onAbort);
+  }
+}
+
+// Split `signal` and `timeoutMs` off an options object, leaving `undefined`
+// if nothing else was set.
+function takeControl(opts) {
+  const { signal, timeoutMs, ...rest } = opts ?? {};
+  return [{ signal, timeoutMs }, Object.keys(rest).length > 0 ? rest : undefined];
+}
+
+const nativeSearch = NativeStrata.prototype.search;
+const nativeDiffBranches = NativeStrata.prototype.diffBranches;
+const nativeCompact = NativeStrata.prototype.compact;
+const nativeVerify = NativeStrata.prototype.verify;
+const nativeRerank = NativeStrata.prototype.rerank;
+
+NativeStrata.prototype.search = function search(query, opts) {
+  const [control, options] = takeControl(opts);
+  return withSignal(this, control, (id) => nativeSearch.call(this, query, options, id));
+};
+
+NativeStrata.prototype.diffBranches = function diffBranches(branchA, branchB, opts) {
+  const [control, options] = takeControl(opts);
+  return withSignal(this, control, (id) =>
+    nativeDiffBranches.call(this, branchA, branchB, options, id));
+};
+
+NativeStrata.prototype.compact = function compact(opts) {
+  const [control, options] = takeControl(opts);
+  if (options?.space != null) {
+    return Promise.reject(new ValidationError(
+      'compact() cannot be scoped to a space; the engine compacts the whole database',
+    ));
+  }
+  return withSignal(this, control, async (id) => {
+    const result = await nativeCompact.call(this, id);
+    if (compactionBaselines.has(this)) {
+      await compactionBaseline(this, result.diskBytesAfter);
+    }
+    return result;
+  });
+};
+
+NativeStrata.prototype.verify = function verify(opts) {
+  const [control, options] = takeControl(opts);
+  return withSignal(this, control, (id) => nativeVerify.call(this, options?.deep, id));
+};
+
+// `scorer(query, text)` stands in for the cross-encoder: to rerank with
+// another model or service, or deterministically in tests. Results have
+// the same shape and order (best first, ties in input order).
+NativeStrata.prototype.rerank = async function rerank(query, candidates, opts) {
+  const { scorer, ...options } = opts ?? {};
+  if (scorer === undefined) {
+    return nativeRerank.call(this, query, candidates, options);
+  }
+  if (typeof scorer !== 'function') {
+    throw new ValidationError('scorer must be a function');
+  }
+  if (options.model !== undefined) {
+    throw new ValidationError('rerank() takes a model or a scorer, not both');
+  }
+  if (!Array.isArray(candidates) || candidates.some((text) => typeof text !== 'string')) {
+    throw new ValidationError('candidates must be an array of strings');
+  }
+  const scores = await Promise.all(candidates.map((text) => scorer(query, text)));
+  const bad = scores.findIndex((score) => typeof score !== 'number' || !Number.isFinite(score));
+  if (bad !== -1) {
+    throw new ValidationError(
+      `scorer returned ${String(scores[bad])} for candidate ${bad}; scores must be finite numbers`,
+    );
+  }
+  return candidates
+    .map((text, index) => ({ index, text, score: scores[index] }))
+    .sort((a, b) => b.score - a.score);
+};
+
+// Bundle operations also take `onProgress`, handed to the native call.
+function withBundleControl(db, opts, run) {
+  const { onProgress, ...rest } = opts ?? {};
+  const [control, options] = takeControl(rest);
+  return withSignal(db, control, (id) => run(options, onProgress, id));
+}
+
+const nativeBranchExport = NativeStrata.prototype.branchExport;
+const nativeBranchImport = NativeStrata.prototype.branchImport;
+const nativeBranchValidateBundle = NativeStrata.prototype.branchValidateBundle;
+
+NativeStrata.prototype.branchExport = function branchExport(branch, path, opts) {
+  return withBundleControl(this, opts, (options, onProgress, id) =>
+    nativeBranchExport.call(this, branch, path, options, onProgress, id));
+};
+
+NativeStrata.prototype.branchImport = function branchImport(path, opts) {
+  return withBundleControl(this, opts, (options, onProgress, id) =>
+    nativeBranchImport.call(this, path, options, onProgress, id));
+};
+
+NativeStrata.prototype.branchValidateBundle = function branchValidateBundle(path, opts) {
+  return withBundleControl(this, opts, (options, onProgress, id) =>
+    nativeBranchValidateBundle.call(this, path, options, onProgress, id));
+};
+
+// bundleDiff() takes decryption keys alongside the diff options.
+const nativeBundleDiff = NativeStrata.prototype.bundleDiff;
+
+NativeStrata.prototype.bundleDiff = function bundleDiff(pathA, target, opts) {
+  const { encryption, ...diffOpts } = opts ?? {};
+  return nativeBundleDiff.call(this, pathA, target, diffOpts, encryption);
+};
+
+// ---------------------------------------------------------------------------
+// Streaming bundles. The native side stages the bundle in a temporary file
+// and moves it across in chunks, so memory stays bounded by the chunk size.
+// ---------------------------------------------------------------------------
+
+const BUNDLE_CHUNK_SIZE = 64 * 1024;
+
+// BundleReader/BundleWriter methods are not covered by the prototype wrapper.
+function bundleCall(fn) {
+  try {
+    return Promise.resolve(fn()).catch((err) => {
+      throw toTypedError(err);
+    });
+  } catch (err) {
+    return Promise.reject(toTypedError(err));
+  }
+}
+
+NativeStrata.prototype.branchExportStream = function branchExportStream(branch, opts) {
+  const db = this;
+  const chunkSize = opts?.chunkSize ?? BUNDLE_CHUNK_SIZE;
+  let reader = null;
+  return new Readable({
+    async read() {
+      try {
+        if (reader === null) {
+          const { chunkSize: _, ...exportOpts } = opts ?? {};
+          reader = await db.branchExportOpen(branch, exportOpts);
+        }
+        const chunk = await bundleCall(() => reader.read(chunkSize));
+        if (chunk === null) {
+          await bundleCall(() => reader.close());
+        }
+        this.push(chunk);
+      } catch (err) {
+        this.destroy(err);
+      }
+    },
+    destroy(err, callback) {
+      try { reader?.close(); } catch (_) { /* already released */ }
+      callback(err);
+    },
+  });
+};
+
+NativeStrata.prototype.branchImportStream = async function branchImportStream(source, opts) {
+  const writer = await this.branchImportOpen(opts);
+  try {
+    for await (const chunk of source) {
+      const buf = Buffer.isBuffer(chunk) ? chunk : Buffer.from(chunk);
+      await bundleCall(() => writer.write(buf));
+    }
+    return await bundleCall(() => writer.finish());
+  } catch (err) {
+    try { writer.abort(); } catch (_) { /* already released */ }
+    throw err;
+  }
+};
+
+// Whole-database backups, made of branch bundles; see lib/backup.js.
+NativeStrata.prototype.backup = function backup(destDir, opts) {
+  return backupDatabase(this, destDir, opts);
+};
+
+// NDJSON dumps of KV, state, and JSON data; see lib/dump.js.
+NativeStrata.prototype.dump = function dumpEntries(target, opts) {
+  return dump(this, target, opts);
+};
+
+NativeStrata.prototype.load = function loadEntries(source, opts) {
+  return load(this, source, opts);
+};
+
+// Replication from a primary's committed writes; see lib/changelog.js.
+NativeStrata.prototype.changelogExport = function exportChangelog(sinceVersion, sink) {
+  return changelogExport(this, sinceVersion, sink);
+};
+
+NativeStrata.prototype.changelogApply = function applyChangelog(source, opts) {
+  return changelogApply(this, source, opts);
+};
+
+// Sync with another handle, or with the database at a path or URL, which
+// is opened (or connected to) for the sync and closed after; see lib/sync.js.
+NativeStrata.prototype.syncTo = async function syncTo(other, opts) {
+  if (typeof other !== 'string') {
+    return sync(this, other, opts);
+  }
+  const remote = other.startsWith('strata+unix:') || /^strata:\/\/[^/]/.test(other)
+    ? await Strata.connect(other)
+    : await Strata.openAsync(other);
+  try {
+    return await sync(this, remote, opts);
+  } finally {
+    await remote.close();
+  }
+};
+
+// Batched writes with flushes deferred to the end; see lib/bulk.js.
+NativeStrata.prototype.bulkLoad = async function bulkLoadEntries(callback, opts) {
+  if (bulkLoading.has(this)) {
+    throw new StateError('A bulk load is already running on this handle');
+  }
+  bulkLoading.add(this);
+  try {
+    return await bulkLoad(this, callback, opts);
+  } finally {
+    bulkLoading.delete(this);
+  }
+};
+
 // ---------------------------------------------------------------------------
 // Create a JS wrapper class that delegates to the native class, wrapping
 // the static factory methods with error handling.
 // ---------------------------------------------------------------------------
 
+// ---------------------------------------------------------------------------
+// Leaked handles. A handle collected without close() is flushed and released
+// natively; this warns about it so the leak can be found, the way Node warns
+// about unhandled rejections.
+// ---------------------------------------------------------------------------
+
+const unclosedHandles = new FinalizationRegistry((label) => {
+  warn(
+    `A Strata handle on ${label} was garbage collected without close(); ` +
+      'it was flushed and released. Call close() when done with a handle.',
+    'STRATA_UNCLOSED_HANDLE',
+  );
+});
+
+function tracked(db, label) {
+  unclosedHandles.register(db, label, db);
+  return db;
+}
+
+// ---------------------------------------------------------------------------
+// flushOnExit. Handles opened with it are flushed and closed when the event
+// loop drains (beforeExit) or on SIGTERM, which is then re-raised so the
+// process still exits unless something else handles it.
+// ---------------------------------------------------------------------------
+
+const flushOnExitHandles = new Set();
+let exitHooksInstalled = false;
+
+function closeForExit() {
+  const handles = [...flushOnExitHandles];
+  flushOnExitHandles.clear();
+  return Promise.allSettled(
+    handles.map((db) => db.flush().catch((err) => emit(db, 'error', err)).then(() => db.close())),
+  );
+}
+
+function onSigterm() {
+  process.removeListener('SIGTERM', onSigterm);
+  process.removeListener('beforeExit', closeForExit);
+  exitHooksInstalled = false;
+  closeForExit().then(() => {
+    if (process.listenerCount('SIGTERM') === 0) {
+      process.kill(process.pid, 'SIGTERM');
+    }
+  });
+}
+
+function flushOnExit(db) {
+  flushOnExitHandles.add(db);
+  if (!exitHooksInstalled) {
+    exitHooksInstalled = true;
+    process.on('beforeExit', closeForExit);
+    process.on('SIGTERM', onSigterm);
+  }
+  return db;
+}
+
+const nativeClose = NativeStrata.prototype.close;
+
+NativeStrata.prototype.close = function close() {
+  unclosedHandles.unregister(this);
+  flushOnExitHandles.delete(this);
+  clearInterval(syncTimers.get(this));
+  clearInterval(compactionTimers.get(this));
+  pubsub.unsubscribeAll(this);
+  return nativeClose.call(this).then(() => temp.release(this));
+};
+
+// Lifecycle events of this handle: `flush`, `compactionStart`,
+// `compactionEnd`, `retentionApplied`, `branchCreated`, `branchDeleted`, and
+// `error` for failures no caller sees, such as background syncs.
+for (const method of ['on', 'once', 'off']) {
+  NativeStrata.prototype[method] = function (event, listener) {
+    let emitter = emitters.get(this);
+    if (!emitter) {
+      emitter = new EventEmitter();
+      emitters.set(this, emitter);
+    }
+    emitter[method](event, listener);
+    return this;
+  };
+}
+
+// Metrics count calls made through this handle since it opened or since the
+// last `metricsReset()`.
+NativeStrata.prototype.metrics = async function metrics() {
+  return metricsOf(this).snapshot();
+};
+
+NativeStrata.prototype.metricsReset = async function metricsReset() {
+  metricsOf(this).reset();
+};
+
+// `tracer(operation, attributes)` runs synchronously as each call starts, so
+// a span it opens is parented to the caller's active context; the function it
+// returns, if any, runs with `{ durationMs, error }` when the call settles.
+NativeStrata.prototype.setTracer = function setTracer(tracer) {
+  if (tracer == null) {
+    tracers.delete(this);
+  } else if (typeof tracer === 'function') {
+    tracers.set(this, tracer);
+  } else {
+    throw new ValidationError('setTracer() takes a function or null');
+  }
+};
+
+// `callback` runs after any call on this handle that took `thresholdMs` or
+// longer. Returns a function that unsubscribes it.
+NativeStrata.prototype.onSlowOp = function onSlowOp(thresholdMs, callback) {
+  if (!(thresholdMs >= 0) || typeof callback !== 'function') {
+    throw new ValidationError('onSlowOp() takes a threshold in ms and a callback');
+  }
+  let watchers = slowOpWatchers.get(this);
+  if (!watchers) {
+    watchers = new Set();
+    slowOpWatchers.set(this, watchers);
+  }
+  const watcher = { thresholdMs, callback };
+  watchers.add(watcher);
+  return () => {
+    watchers.delete(watcher);
+  };
+};
+
+// Write hooks. A before hook gets each entry a write is about to change as
+// `{ method, primitive, op, key, value, branch, space }` and may set its
+// `value` or throw to veto the write; an after hook gets the same plus the
+// call's `result`. Both return a function that unregisters the hook.
+function addWriteHook(db, kind, hook, method) {
+  if (typeof hook !== 'function') {
+    throw new ValidationError(`${method}() takes a function`);
+  }
+  const hooks = hooksOf(db);
+  // Wrapped, so the same function can be registered twice.
+  const entry = (write) => hook(write);
+  hooks[kind].add(entry);
+  return () => {
+    hooks[kind].delete(entry);
+  };
+}
+
+NativeStrata.prototype.onBeforeWrite = function onBeforeWrite(hook) {
+  return addWriteHook(this, 'before', hook, 'onBeforeWrite');
+};
+
+NativeStrata.prototype.onAfterWrite = function onAfterWrite(hook) {
+  return addWriteHook(this, 'after', hook, 'onAfterWrite');
+};
+
+// `callback` runs after each committed KV, state, JSON, or event write
+// under a key prefix or in a space, through any handle on the database,
+// with `{ primitive, op, key, branch, space, oldValue, newValue }`. It
+// doesn't hold up the write; its errors are reported as warnings. Returns a
+// function that drops the trigger.
+NativeStrata.prototype.createTrigger = function createTrigger(target, callback) {
+  if (typeof callback !== 'function') {
+    throw new ValidationError('createTrigger() takes a target and a callback');
+  }
+  const trigger = { target: triggerTarget(target), callback };
+  const id = nativeSharedId.call(this);
+  if (!sharedTriggers.has(id)) {
+    sharedTriggers.set(id, new Set());
+  }
+  const triggers = sharedTriggers.get(id);
+  triggers.add(trigger);
+  return () => {
+    triggers.delete(trigger);
+    if (triggers.size === 0 && sharedTriggers.get(id) === triggers) {
+      sharedTriggers.delete(id);
+    }
+  };
+};
+
+// Run `fn` with this handle's calls targeting `branch` and `space` (each
+// defaulting to the enclosing context's, or the handle's own) until every
+// async operation it starts has finished.
+NativeStrata.prototype.runWithContext = async function runWithContext(context, fn) {
+  const { branch, space } = context ?? {};
+  if (typeof fn !== 'function' || [branch, space].some((v) => v != null && typeof v !== 'string')) {
+    throw new ValidationError('runWithContext() takes { branch, space } and a function');
+  }
+  const outer = contextOf(this) ?? {};
+  const scope = {
+    branch: branch ?? outer.branch ?? (await this.currentBranch()),
+    space: space ?? outer.space ?? (await this.currentSpace()),
+  };
+  if (!(await this.branchExists(scope.branch))) {
+    throw new NotFoundError(`Branch not found: ${scope.branch}`);
+  }
+  if (!scopeGates.has(this)) {
+    scopeGates.set(this, new ScopeGate(this));
+  }
+  const contexts = new Map(asyncContexts.getStore() ?? []);
+  contexts.set(this, scope);
+  return asyncContexts.run(contexts, () => fn(this));
+};
+
+// The `{ branch, space }` of the enclosing runWithContext(), or null.
+NativeStrata.prototype.currentContext = function currentContext() {
+  const context = contextOf(this);
+  return context ? { ...context } : null;
+};
+
+// Pub/sub channels; see lib/pubsub.js. Handles on the same database file
+// share channels, and a server passes them on to its clients.
+function channelKey(db) {
+  const { open, path } = nativeStatus.call(db);
+  if (!open) {
+    throw new StrataClosedError('Database is closed');
+  }
+  return path ?? db;
+}
+
+NativeStrata.prototype.publish = async function publish(channel, message) {
+  return pubsub.publish(channelKey(this), channel, message);
+};
+
+NativeStrata.prototype.subscribe = async function subscribe(channel, callback) {
+  return pubsub.subscribe(this, channelKey(this), channel, callback, warn);
+};
+
+// Blobs, written and read a chunk at a time; see lib/blobs.js.
+NativeStrata.prototype.blobPut = function putBlob(key, source, opts) {
+  return blobPut(this, key, source, opts);
+};
+
+NativeStrata.prototype.blobGet = function getBlob(key, opts) {
+  return blobGet(this, key, opts);
+};
+
+// Migrations, each applied in its own transaction; see lib/migrate.js.
+NativeStrata.prototype.migrate = function runMigrations(migrations, opts) {
+  return migrate(this, migrations, opts);
+};
+
+// Typed wrappers over the JSON documents of a collection; see
+// lib/collection.js.
+NativeStrata.prototype.collection = function collection(name, opts) {
+  return new Collection(this, name, opts);
+};
+
+// Test fixtures; see lib/fixture.js. `Strata.fromFixture()` loads one.
+NativeStrata.prototype.toFixture = function captureFixture(opts) {
+  return toFixture(this, opts);
+};
+
+// Materialized views, kept up to date by triggers on their sources; see
+// lib/views.js.
+NativeStrata.prototype.viewCreate = function viewCreate(name, def) {
+  return views.viewCreate(this, name, def);
+};
+
+NativeStrata.prototype.viewRefresh = function viewRefresh(name) {
+  return views.viewRefresh(this, name);
+};
+
+NativeStrata.prototype.viewStatus = async function viewStatus(name) {
+  return views.viewStatus(this, name);
+};
+
+NativeStrata.prototype.viewDrop = async function viewDrop(name) {
+  views.viewDrop(this, name);
+};
+
+NativeStrata.prototype.viewList = async function viewList() {
+  return views.viewList(this);
+};
+
+// The same metrics in Prometheus text format, for a `/metrics` endpoint.
+NativeStrata.prototype.metricsPrometheus = async function metricsPrometheus(opts) {
+  return metricsOf(this).prometheus(opts?.labels);
+};
+
+// ---------------------------------------------------------------------------
+// Remote handles. Namespaces and the helpers built on other methods run on
+// the client as-is; every other method is sent to the server, which runs it
+// on its own handle. Calls that hand back native objects or streams can't
+// cross the wire. RemoteStrata implements close() and subscribe() itself.
+// ---------------------------------------------------------------------------
+
+const CLIENT_SIDE = new Set([
+  'at', 'readBranch', 'transaction', 'transactionWithRetry', 'dump', 'load', 'bulkLoad',
+  'changelogExport', 'changelogApply', 'blobPut', 'blobGet', 'migrate', 'toFixture',
+  'collection',
+]);
+const LOCAL_ONLY = new Set([
+  'branchExportOpen',
+  'branchImportOpen',
+  'branchExportStream',
+  'branchImportStream',
+  'share',
+  'listen',
+  'setTracer',
+  'onSlowOp',
+  'on',
+  'once',
+  'off',
+  'backup',
+  'backupBranches',
+  'syncTo',
+  'onBeforeWrite',
+  'onAfterWrite',
+  'createTrigger',
+  'viewCreate',
+  'viewRefresh',
+  'viewStatus',
+  'viewDrop',
+  'viewList',
+  'runWithContext',
+  'currentContext',
+]);
+// The methods a server runs for its clients.
+const REMOTE_METHODS = new Set();
+
+// Each connection gets its own handle, so its transactions are its own.
+NativeStrata.prototype.listen = function listen(options) {
+  return serve(this, options, REMOTE_METHODS, async (db) => Strata.fromShared(await db.share()));
+};
+
+for (const name of Object.getOwnPropertyNames(NativeStrata.prototype)) {
+  if (name === 'constructor' || Object.prototype.hasOwnProperty.call(RemoteStrata.prototype, name)) {
+    continue;
+  }
+  const descriptor = Object.getOwnPropertyDescriptor(NativeStrata.prototype, name);
+  if (typeof descriptor.value !== 'function' || CLIENT_SIDE.has(name)) {
+    Object.defineProperty(RemoteStrata.prototype, name, descriptor);
+  } else if (LOCAL_ONLY.has(name)) {
+    Object.defineProperty(RemoteStrata.prototype, name, {
+      value: async function () {
+        throw new StateError(`${name}() is not available on a remote handle`);
+      },
+      writable: true,
+      configurable: true,
+    });
+  } else {
+    REMOTE_METHODS.add(name);
+    Object.defineProperty(RemoteStrata.prototype, name, {
+      value: function (...args) {
+        const timeoutMs = scopedTimeoutMs !== undefined ? scopedTimeoutMs : defaultTimeouts.get(this);
+        return withTimeout(this._call(name, args), timeoutMs);
+      },
+      writable: true,
+      configurable: true,
+    });
+  }
+}
+
+// ---------------------------------------------------------------------------
+// Sync mode. 'always' flushes the WAL after every write before it resolves,
+// 'interval' flushes every `syncIntervalMs`, and 'async' (the default) leaves
+// syncing to the engine.
+// ---------------------------------------------------------------------------
+
+const DEFAULT_SYNC_INTERVAL_MS = 1000;
+const syncTimers = new WeakMap();
+
+function applySyncMode(db, syncMode, syncIntervalMs) {
+  if (syncMode === 'always') {
+    syncEveryWrite.add(db);
+  } else if (syncMode === 'interval') {
+    // Weak, so the timer doesn't keep an unclosed handle alive.
+    const ref = new WeakRef(db);
+    const timer = setInterval(() => {
+      const target = ref.deref();
+      if (target === undefined) {
+        clearInterval(timer);
+      } else if (!bulkLoading.has(target)) {
+        target.flush().catch((err) => emit(target, 'error', err));
+      }
+    }, syncIntervalMs ?? DEFAULT_SYNC_INTERVAL_MS);
+    timer.unref();
+    syncTimers.set(db, timer);
+  }
+}
+
+// ---------------------------------------------------------------------------
+// Background compaction. 'interval' compacts every `compactionIntervalMs`;
+// 'writeAmplification' checks every `compactionIntervalMs` and compacts once
+// the bytes written to the WAL since the last compaction reach
+// `compactionTarget` times the database's size after it. The engine reports
+// no write amplification itself, so this ratio stands in for it. 'off' (the
+// default) leaves compaction to the engine and to `compact()`.
+// ---------------------------------------------------------------------------
+
+const DEFAULT_COMPACTION_INTERVAL_MS = { interval: 60 * 60 * 1000, writeAmplification: 60 * 1000 };
+const DEFAULT_COMPACTION_TARGET = 4;
+const compactionTimers = new WeakMap();
+// `{ bytesWritten, diskBytes }` as of the last compaction, for handles in
+// 'writeAmplification' mode.
+const compactionBaselines = new WeakMap();
+
+async function compactionBaseline(db, diskBytes) {
+  const { bytesWritten } = await db.walStats();
+  const size = diskBytes !== undefined ? diskBytes : (await db.info({ diskBytes: true })).diskBytes;
+  compactionBaselines.set(db, { bytesWritten, diskBytes: size ?? 0 });
+}
+
+async function compactIfDue(db, mode, target) {
+  if (mode === 'writeAmplification') {
+    const baseline = compactionBaselines.get(db);
+    const { bytesWritten } = await db.walStats();
+    if (bytesWritten - baseline.bytesWritten < target * Math.max(baseline.diskBytes, 1)) {
+      return;
+    }
+  }
+  // compact() moves the baseline on.
+  await db.compact();
+}
+
+function applyCompactionMode(db, mode, intervalMs, target) {
+  if (mode == null || mode === 'off') {
+    return;
+  }
+  if (mode === 'writeAmplification') {
+    compactionBaselines.set(db, { bytesWritten: 0, diskBytes: 0 });
+    compactionBaseline(db).catch((err) => emit(db, 'error', err));
+  }
+  // Weak, so the timer doesn't keep an unclosed handle alive.
+  const ref = new WeakRef(db);
+  let running = false;
+  const timer = setInterval(() => {
+    const handle = ref.deref();
+    if (handle === undefined) {
+      clearInterval(timer);
+    } else if (!running) {
+      running = true;
+      compactIfDue(handle, mode, target ?? DEFAULT_COMPACTION_TARGET)
+        .catch((err) => emit(handle, 'error', err))
+        .finally(() => {
+          running = false;
+        });
+    }
+  }, intervalMs ?? DEFAULT_COMPACTION_INTERVAL_MS[mode]);
+  timer.unref();
+  compactionTimers.set(db, timer);
+}
+
+function checkCompactionMode(mode, intervalMs, target) {
+  if (mode != null && !['off', 'interval', 'writeAmplification'].includes(mode)) {
+    throw new ValidationError(
+      `Invalid compactionMode '${mode}'; use 'off', 'interval', or 'writeAmplification'`,
+    );
+  }
+  if (intervalMs != null && (mode == null || mode === 'off')) {
+    throw new ValidationError(
+      "compactionIntervalMs requires compactionMode 'interval' or 'writeAmplification'",
+    );
+  }
+  if (intervalMs != null && !(intervalMs > 0)) {
+    throw new ValidationError('compactionIntervalMs must be greater than 0');
+  }
+  if (target != null && mode !== 'writeAmplification') {
+    throw new ValidationError("compactionTarget requires compactionMode: 'writeAmplification'");
+  }
+  if (target != null && !(target > 0)) {
+    throw new ValidationError('compactionTarget must be greater than 0');
+  }
+}
+
+function checkSyncMode(syncMode, syncIntervalMs) {
+  if (syncMode != null && !['always', 'interval', 'async'].includes(syncMode)) {
+    throw new ValidationError(`Invalid syncMode '${syncMode}'; use 'always', 'interval', or 'async'`);
+  }
+  if (syncIntervalMs != null && syncMode !== 'interval') {
+    throw new ValidationError("syncIntervalMs requires syncMode: 'interval'");
+  }
+  if (syncIntervalMs != null && !(syncIntervalMs > 0)) {
+    throw new ValidationError('syncIntervalMs must be greater than 0');
+  }
+}
+
+// Open options the engine has no setting for. They are rejected rather
+// than ignored, so a tuning meant for another engine doesn't go unnoticed.
+const UNSUPPORTED_OPEN_OPTIONS = {
+  compression: 'the engine chooses the codec',
+};
+
+// `timeoutMs`, `flushOnExit`, and the sync and compaction modes are applied
+// here rather than natively; the rest of the open options go to the native
+// factory.
+function splitOpenOptions(options) {
+  const {
+    timeoutMs, flushOnExit: closeOnExit, syncMode, syncIntervalMs, trace,
+    compactionMode, compactionIntervalMs, compactionTarget, dateTimestamps: dates, strictValues,
+    ...rest
+  } = options ?? {};
+  if (strictValues != null) {
+    rest.jsonLimits = { ...rest.jsonLimits, strictValues };
+  }
+  for (const [name, reason] of Object.entries(UNSUPPORTED_OPEN_OPTIONS)) {
+    if (rest[name] !== undefined) {
+      throw new ValidationError(`${name} is not supported; ${reason}`);
+    }
+  }
+  checkSyncMode(syncMode, syncIntervalMs);
+  checkCompactionMode(compactionMode, compactionIntervalMs, compactionTarget);
+  if (trace != null && typeof trace !== 'boolean' && typeof trace !== 'function') {
+    throw new ValidationError('trace must be a boolean or a function');
+  }
+  const withDefaults = (db) => {
+    if (trace) {
+      traceSinks.set(db, trace === true ? logTrace : trace);
+    }
+    if (timeoutMs != null) {
+      defaultTimeouts.set(db, timeoutMs);
+    }
+    if (closeOnExit) {
+      flushOnExit(db);
+    }
+    if (dates) {
+      dateTimestamps.add(db);
+    }
+    applySyncMode(db, syncMode, syncIntervalMs);
+    applyCompactionMode(db, compactionMode, compactionIntervalMs, compactionTarget);
+    return db;
+  };
+  return [options == null ? options : rest, withDefaults];
+}
+
+// Open options a `strata:///path?...` URL may set, by how their values parse.
+const URL_OPTIONS = {
+  readOnly: 'boolean',
+  follower: 'boolean',
+  autoEmbed: 'boolean',
+  createIfMissing: 'boolean',
+  errorIfExists: 'boolean',
+  flushOnExit: 'boolean',
+  bigints: 'boolean',
+  boxedFloats: 'boolean',
+  dateTimestamps: 'boolean',
+  strictValues: 'boolean',
+  workerThreads: 'number',
+  maxConcurrentOps: 'number',
+  maxQueuedOps: 'number',
+  lockWaitMs: 'number',
+  cacheMB: 'number',
+  timeoutMs: 'number',
+  syncIntervalMs: 'number',
+  syncMode: 'string',
+  compactionMode: 'string',
+  compactionIntervalMs: 'number',
+  compactionTarget: 'number',
+};
+
+// Split `strata:///path?option=value&...` into the path and its options, so
+// configuration can live in one environment variable. Options passed to
+// `open()` itself take precedence. Other paths pass through unchanged.
+function parseOpenUrl(path, options) {
+  if (typeof path !== 'string' || !path.startsWith('strata:')) {
+    return [path, options];
+  }
+  let url;
+  try {
+    url = new URL(path);
+  } catch (err) {
+    throw new ValidationError(`Invalid database URL '${path}'`);
+  }
+  if (url.protocol !== 'strata:' || url.host !== '') {
+    throw new ValidationError(
+      `Database URL '${path}' must be strata:///path; use Strata.connect() for a server`,
+    );
+  }
+  const fromUrl = {};
+  for (const [name, raw] of url.searchParams) {
+    if (UNSUPPORTED_OPEN_OPTIONS[name] !== undefined) {
+      throw new ValidationError(`${name} is not supported; ${UNSUPPORTED_OPEN_OPTIONS[name]}`);
+    }
+    const type = URL_OPTIONS[name];
+    if (type === undefined) {
+      throw new ValidationError(`Unknown option '${name}' in database URL`);
+    }
+    if (type === 'boolean') {
+      if (raw !== 'true' && raw !== 'false') {
+        throw new ValidationError(`Option '${name}' in database URL must be true or false`);
+      }
+      fromUrl[name] = raw === 'true';
+    } else if (type === 'number') {
+      if (!/^\d+$/.test(raw)) {
+        throw new ValidationError(`Option '${name}' in database URL must be a whole number`);
+      }
+      fromUrl[name] = Number(raw);
+    } else {
+      fromUrl[name] = raw;
+    }
+  }
+  let pathname;
+  try {
+    pathname = decodeURIComponent(url.pathname);
+  } catch (err) {
+    throw new ValidationError(`Invalid escape in the path of database URL '${path}'`);
+  }
+  // strata:///C:/data on Windows has the pathname /C:/data.
+  const dbPath = pathname.replace(/^\/([A-Za-z]:)/, '$1');
+  return [dbPath, { ...fromUrl, ...options }];
+}
+
+// A writer-lock error names the holder as "(pid N since MICROS)"; expose it
+// as `err.lockHolder`.
+function withLockHolder(err) {
+  const match = err.message.match(/ \(pid (\d+) since (\d+)\);/);
+  if (match) {
+    err.lockHolder = { pid: Number(match[1]), since: Number(match[2]) };
+  }
+  return err;
+}
+
 class Strata extends NativeStrata {
-  static open(...args) {
+  // The option values methods take, e.g. `Strata.DistanceMetric.Cosine`.
+  static DistanceMetric = native.DistanceMetric;
+
+  static MergeStrategy = native.MergeStrategy;
+
+  static FilterOp = native.FilterOp;
+
+  static open(pathOrUrl, options) {
+    const [path, openOptions] = parseOpenUrl(pathOrUrl, options);
+    const [nativeOptions, withDefaults] = splitOpenOptions(openOptions);
     try {
-      return NativeStrata.open(...args);
+      return tracked(withDefaults(NativeStrata.open(path, nativeOptions)), path);
+    } catch (err) {
+      throw withLockHolder(toTypedError(err));
+    }
+  }
+
+  static async openAsync(pathOrUrl, options) {
+    const [path, openOptions] = parseOpenUrl(pathOrUrl, options);
+    const [nativeOptions, withDefaults] = splitOpenOptions(openOptions);
+    try {
+      return tracked(withDefaults(await NativeStrata.openAsync(path, nativeOptions)), path);
+    } catch (err) {
+      throw withLockHolder(toTypedError(err));
+    }
+  }
+
+  static fromShared(token, options) {
+    const [nativeOptions, withDefaults] = splitOpenOptions(options);
+    try {
+      return tracked(withDefaults(NativeStrata.fromShared(token, nativeOptions)), token);
     } catch (err) {
       throw toTypedError(err);
     }
   }
 
+  static async connect(url, options) {
+    const { timeoutMs, ...rest } = options ?? {};
+    const db = await connect(url, rest);
+    if (timeoutMs != null) {
+      defaultTimeouts.set(db, timeoutMs);
+    }
+    return db;
+  }
+
+  static setLogger(options) {
+    setLogger(options);
+  }
+
+  static restore(backupDir, targetPath, opts) {
+    return restore(backupDir, targetPath, (path, options) => Strata.openAsync(path, options), opts);
+  }
+
   static cache(...args) {
     try {
-      return NativeStrata.cache(...args);
+      return tracked(NativeStrata.cache(...args), 'an in-memory database');
     } catch (err) {
       throw toTypedError(err);
     }
   }
+
+  static temp(options) {
+    const { persistToDiskForDebug, ...openOptions } = options ?? {};
+    const dir = temp.makeTempDir();
+    let db;
+    try {
+      db = Strata.open(dir, openOptions);
+    } catch (err) {
+      temp.remove(dir);
+      throw err;
+    }
+    if (persistToDiskForDebug) {
+      warn(`Keeping temporary database ${dir} after close`, 'STRATA_TEMP_KEPT');
+    } else {
+      temp.removeWith(db, dir);
+    }
+    return db;
+  }
+
+  static async fromFixture(fixture) {
+    const data = await readFixture(fixture);
+    const db = Strata.cache();
+    try {
+      await loadFixture(db, data);
+    } catch (err) {
+      await db.close();
+      throw err;
+    }
+    return db;
+  }
 }
 
 // Wrap top-level setup() function.
@@ -846,7 +2916,12 @@ function setup(...args) {
 module.exports = {
   Strata,
   StrataSnapshot,
+  BranchView,
+  RemoteStrata,
   setup,
+  DistanceMetric: native.DistanceMetric,
+  MergeStrategy: native.MergeStrategy,
+  FilterOp: native.FilterOp,
   // Error classes
   StrataError,
   NotFoundError,
@@ -856,4 +2931,7 @@ module.exports = {
   ConstraintError,
   AccessDeniedError,
   IoError,
+  ResourceExhaustedError,
+  TimeoutError,
+  StrataClosedError,
 };